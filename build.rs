@@ -0,0 +1,24 @@
+//! Embeds the git description of the sources in the binary, when built from a checkout.
+
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    // Describe again after commits and checkouts, without rerunning on every build
+    for path in [".git/HEAD", ".git/index"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let output = Command::new("git")
+        .args(["describe", "--tags", "--always", "--dirty"])
+        .output();
+    if let Ok(output) = output {
+        let describe = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if output.status.success() && !describe.is_empty() {
+            println!("cargo:rustc-env=BMREGRESSION_GIT_DESCRIBE={}", describe);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Allowlist of the commands the regressions may run.
+//!
+//! The regressions of a fork of the data repository, submitted in a pull
+//! request, are untrusted: with `--command-allowlist` only the commands matching
+//! one of the patterns of the file are executed, the other regressions are
+//! blocked. The file has one pattern per line, with the syntax of
+//! [`crate::pattern`], matched against the whole command; empty lines and lines
+//! starting with `#` are ignored:
+//!
+//! ```text
+//! # Build and simulate only
+//! make (hdl|sim|clean)
+//! bondmachine .*
+//! ```
+//!
+//! The commands given as a list are matched in their quoted command line form.
+//!
+//! As the other commands run through `sh -c`, a command holding one of the
+//! [`SHELL_METACHARACTERS`], even quoted, is never allowed: `bondmachine .*` would
+//! otherwise allow `bondmachine x; curl ... | sh` too.
+
+use std::fs;
+use std::io;
+
+use crate::pattern::Pattern;
+
+/// Characters with which a shell command runs other commands, or writes other files.
+pub const SHELL_METACHARACTERS: [char; 8] = [';', '&', '|', '`', '$', '<', '>', '\n'];
+
+/// Patterns of the permitted commands.
+#[derive(Debug, Clone)]
+pub struct Allowlist {
+    patterns: Vec<Pattern>,
+}
+
+impl Allowlist {
+    /// Loads an allowlist file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or naming the line of an invalid pattern.
+    pub fn load(path: &str) -> io::Result<Allowlist> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| io::Error::other(format!("reading {} failed: {}", path, err)))?;
+        let mut patterns = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let pattern = Pattern::new(line).map_err(|err| {
+                io::Error::other(format!(
+                    "{}:{}: invalid pattern {:?}: {}",
+                    path,
+                    index + 1,
+                    line,
+                    err
+                ))
+            })?;
+            patterns.push(pattern);
+        }
+        Ok(Allowlist { patterns })
+    }
+
+    /// Returns true if a command matches one of the patterns, and it has no
+    /// [`SHELL_METACHARACTERS`] when it runs through the shell.
+    pub fn allows(&self, command: &str, shell: bool) -> bool {
+        if shell && command.contains(SHELL_METACHARACTERS) {
+            return false;
+        }
+        self.patterns.iter().any(|pattern| pattern.matches(command))
+    }
+}
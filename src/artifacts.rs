@@ -0,0 +1,127 @@
+//! Collection of additional files produced by the regression commands.
+//!
+//! Regressions can list glob patterns under the `artifacts` key of their
+//! configuration, relative to the example directory. The matching files are
+//! copied to `<artifacts-dir>/<run-id>/<regression>/`, preserving their
+//! relative paths, so that they survive the removal of the working directory.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// When the artifacts of a regression are collected.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Collect {
+    /// Only when the regression fails or errors
+    Failure,
+    /// Whenever the regression command has been executed
+    Always,
+}
+
+/// Outcome of the collection of the artifacts of a regression.
+pub struct Collected {
+    /// Directory the artifacts have been copied to
+    pub dir: PathBuf,
+    /// Number of copied files
+    pub copied: usize,
+    /// Patterns that did not match any file
+    pub unmatched: Vec<String>,
+}
+
+/// Copies the files matching the patterns from `base` to `dest`.
+///
+/// # Errors
+///
+/// Returns an error if a pattern is invalid or a file cannot be copied.
+pub fn collect(patterns: &[String], base: &Path, dest: &Path) -> io::Result<Collected> {
+    let mut collected = Collected {
+        dir: dest.to_path_buf(),
+        copied: 0,
+        unmatched: Vec::new(),
+    };
+    let escaped_base = glob::Pattern::escape(&base.to_string_lossy());
+    for pattern in patterns {
+        let full = format!("{}/{}", escaped_base, pattern);
+        let paths = glob::glob(&full).map_err(|err| {
+            io::Error::other(format!("invalid artifact pattern {}: {}", pattern, err))
+        })?;
+        let mut matched = false;
+        for path in paths.flatten() {
+            if !path.is_file() {
+                continue;
+            }
+            let relative = match path.strip_prefix(base) {
+                Ok(relative) => relative,
+                Err(_) => continue,
+            };
+            let target = dest.join(relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(&path, &target)?;
+            collected.copied += 1;
+            matched = true;
+        }
+        if !matched {
+            collected.unmatched.push(pattern.clone());
+        }
+    }
+    Ok(collected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn copies_the_matching_files_with_their_relative_paths() {
+        let dir = TempDir::new("artifacts").unwrap();
+        // A base whose name is a glob pattern itself
+        let base = dir.path().join("example [v2]");
+        fs::create_dir_all(base.join("build/logs")).unwrap();
+        fs::write(base.join("build/synth.log"), "synth").unwrap();
+        fs::write(base.join("build/logs/place.log"), "place").unwrap();
+        fs::write(base.join("build/out.bit"), "bit").unwrap();
+        let dest = dir.path().join("artifacts/run/blink");
+
+        let patterns = [
+            "build/*.log".to_string(),
+            "build/**/place.log".to_string(),
+            "build/logs".to_string(),
+            "timing.rpt".to_string(),
+        ];
+        let collected = collect(&patterns, &base, &dest).unwrap();
+        assert_eq!(collected.dir, dest);
+        assert_eq!(collected.copied, 2);
+        // Directories are not copied
+        assert_eq!(collected.unmatched, ["build/logs", "timing.rpt"]);
+        assert_eq!(
+            fs::read_to_string(dest.join("build/synth.log")).unwrap(),
+            "synth"
+        );
+        assert_eq!(
+            fs::read_to_string(dest.join("build/logs/place.log")).unwrap(),
+            "place"
+        );
+        assert!(!dest.join("build/out.bit").exists());
+    }
+
+    #[test]
+    fn rejects_the_invalid_patterns() {
+        let dir = TempDir::new("artifacts").unwrap();
+        let err = collect(
+            &["build/[".to_string()],
+            dir.path(),
+            &dir.path().join("dest"),
+        )
+        .err()
+        .unwrap();
+        assert!(
+            err.to_string()
+                .starts_with("invalid artifact pattern build/[: "),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Atomic and durable replacement of files.
+//!
+//! The expected outputs, the configurations and the state files are never
+//! written in place: the new content goes to a temporary file in the same
+//! directory, which is flushed to the disk and renamed over the file, then the
+//! directory is flushed so that the rename itself survives a crash. A crash, a
+//! SIGKILL or an error while writing leaves the previous file as it was, never
+//! half written.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Returns the temporary file written before replacing `path`, hidden next to it.
+fn temporary(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
+
+/// Replaces the file at `path` with the content written by `fill` to a new file.
+///
+/// # Errors
+///
+/// Returns the error of `fill`, or an error if the file cannot be written, flushed or
+/// renamed; the previous file is then left as it was and the temporary file is removed.
+pub fn write_with(path: &Path, fill: impl FnOnce(&mut File) -> io::Result<()>) -> io::Result<()> {
+    let tmp = temporary(path);
+    let written = File::create(&tmp).and_then(|mut file| {
+        fill(&mut file)?;
+        file.sync_all()
+    });
+    if let Err(err) = written.and_then(|()| fs::rename(&tmp, path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(err);
+    }
+    // Some file systems cannot flush a directory, the file is in place all the same
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// Replaces the file at `path` with `content`, see [`write_with`].
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written, the previous one is then left as it was.
+pub fn write(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> io::Result<()> {
+    write_with(path.as_ref(), |file| file.write_all(content.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn leaves_the_file_intact_when_the_write_is_interrupted() {
+        let dir = TempDir::new("atomic").unwrap();
+        let path = dir.path().join("out.sv");
+        fs::write(&path, "module blink;\n").unwrap();
+
+        let interrupted = write_with(&path, |file| {
+            file.write_all(b"module bl")?;
+            Err(io::Error::other("interrupted"))
+        });
+        assert_eq!(interrupted.unwrap_err().to_string(), "interrupted");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "module blink;\n");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        write(&path, "module counter;\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "module counter;\n");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,149 @@
+//! Result badges, in the style of shields.io.
+//!
+//! The SVG is rendered locally, with the text widths estimated from the number
+//! of characters: good enough to be readable, without any font metrics.
+
+use std::io;
+use std::path::Path;
+
+use crate::atomic;
+
+/// Text on the left side of the badge.
+const LABEL: &str = "regressions";
+
+/// Fraction of passing regressions from which the badge is orange instead of red.
+const WARNING_THRESHOLD: f64 = 0.9;
+
+/// Approximate width of a character of 11px Verdana, in pixels.
+const CHAR_WIDTH: usize = 7;
+
+/// Horizontal padding around each text, in pixels.
+const PADDING: usize = 10;
+
+/// Returns the color of the badge for `passed` out of `total` regressions.
+fn color(passed: usize, total: usize) -> &'static str {
+    if total == 0 {
+        "#9f9f9f"
+    } else if passed == total {
+        "#4c1"
+    } else if passed as f64 / total as f64 >= WARNING_THRESHOLD {
+        "#fe7d37"
+    } else {
+        "#e05d44"
+    }
+}
+
+/// Renders the badge for `passed` out of `total` regressions.
+pub fn render(passed: usize, total: usize) -> String {
+    let value = if total == 0 {
+        "no results".to_string()
+    } else {
+        format!("{}/{} passing", passed, total)
+    };
+    let label_width = LABEL.len() * CHAR_WIDTH + PADDING;
+    let value_width = value.len() * CHAR_WIDTH + PADDING;
+    let width = label_width + value_width;
+    let label_x = label_width as f64 / 2.0;
+    let value_x = label_width as f64 + value_width as f64 / 2.0;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"20\" role=\"img\" aria-label=\"{l}: {v}\">\n",
+        w = width,
+        l = LABEL,
+        v = value
+    );
+    svg.push_str(&format!("<title>{}: {}</title>\n", LABEL, value));
+    svg.push_str("<linearGradient id=\"s\" x2=\"0\" y2=\"100%\"><stop offset=\"0\" stop-color=\"#bbb\" stop-opacity=\".1\"/><stop offset=\"1\" stop-opacity=\".1\"/></linearGradient>\n");
+    svg.push_str(&format!(
+        "<clipPath id=\"r\"><rect width=\"{}\" height=\"20\" rx=\"3\" fill=\"#fff\"/></clipPath>\n",
+        width
+    ));
+    svg.push_str(&format!(
+        "<g clip-path=\"url(#r)\"><rect width=\"{lw}\" height=\"20\" fill=\"#555\"/><rect x=\"{lw}\" width=\"{vw}\" height=\"20\" fill=\"{c}\"/><rect width=\"{w}\" height=\"20\" fill=\"url(#s)\"/></g>\n",
+        lw = label_width,
+        vw = value_width,
+        w = width,
+        c = color(passed, total)
+    ));
+    svg.push_str("<g fill=\"#fff\" text-anchor=\"middle\" font-family=\"Verdana,Geneva,DejaVu Sans,sans-serif\" font-size=\"11\">\n");
+    for (x, text) in [(label_x, LABEL), (value_x, value.as_str())] {
+        // A shadow below each text, as shields.io does
+        svg.push_str(&format!(
+            "<text x=\"{x}\" y=\"15\" fill=\"#010101\" fill-opacity=\".3\">{t}</text><text x=\"{x}\" y=\"14\">{t}</text>\n",
+            x = x,
+            t = text
+        ));
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// Writes the badge for `passed` out of `total` regressions.
+///
+/// The file is replaced atomically, so that a web server never serves a partial badge.
+pub fn write(passed: usize, total: usize, path: &Path) -> io::Result<()> {
+    atomic::write(path, render(passed, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_the_badge_by_pass_rate() {
+        assert_eq!(color(57, 57), "#4c1");
+        assert_eq!(color(54, 60), "#fe7d37");
+        assert_eq!(color(53, 60), "#e05d44");
+        assert_eq!(color(0, 3), "#e05d44");
+        assert_eq!(color(0, 0), "#9f9f9f");
+    }
+
+    #[test]
+    fn sizes_the_badge_to_its_texts() {
+        let svg = render(54, 57);
+        // 11 and 13 characters
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"188\" height=\"20\" role=\"img\" aria-label=\"regressions: 54/57 passing\">\n"),
+            "{}",
+            svg
+        );
+        assert!(
+            svg.contains("<title>regressions: 54/57 passing</title>\n"),
+            "{}",
+            svg
+        );
+        assert!(
+            svg.contains("<rect x=\"87\" width=\"101\" height=\"20\" fill=\"#fe7d37\"/>"),
+            "{}",
+            svg
+        );
+        assert!(
+            svg.contains("<text x=\"43.5\" y=\"14\">regressions</text>"),
+            "{}",
+            svg
+        );
+        assert!(
+            svg.contains("<text x=\"137.5\" y=\"14\">54/57 passing</text>"),
+            "{}",
+            svg
+        );
+        assert!(svg.ends_with("</g>\n</svg>\n"), "{}", svg);
+
+        let svg = render(0, 0);
+        assert!(
+            svg.contains("aria-label=\"regressions: no results\""),
+            "{}",
+            svg
+        );
+        assert!(svg.contains("fill=\"#9f9f9f\""), "{}", svg);
+    }
+
+    #[test]
+    fn replaces_the_badge_file() {
+        let dir = tempdir::TempDir::new("badge").unwrap();
+        let path = dir.path().join("badge.svg");
+        write(1, 2, &path).unwrap();
+        write(2, 2, &path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), render(2, 2));
+    }
+}
@@ -0,0 +1,220 @@
+//! Git history of the expected outputs, to tell when and why a baseline changed.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::config::RegressionConfig;
+use crate::timestamp::DateTime;
+use crate::toolchain;
+
+/// Format of the commit lines: hash, date, author and subject.
+const LOG_FORMAT: &str = "--format=%h  %ad  %an  %s";
+
+/// Runs a git command in a directory, returning its stdout.
+///
+/// # Errors
+///
+/// Returns an error with the stderr of git if the command fails.
+fn git(dir: &Path, args: &[&str], debug: bool) -> io::Result<String> {
+    let output = Command::new("git").current_dir(dir).args(args).output()?;
+    if debug {
+        println!("git {}: {:?}", args.join(" "), output.status);
+    }
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Prints the last commits that changed the expected output of a regression
+/// for a baseline, and the last one that changed its configuration.
+///
+/// With `patch` the changes of the last commit of the expected output are printed too.
+///
+/// # Errors
+///
+/// Returns an error if the regression has no such baseline, the data directory
+/// is not a git checkout or git fails.
+pub fn show(
+    target: &str,
+    config: &RegressionConfig,
+    baseline: &str,
+    count: usize,
+    patch: bool,
+    debug: bool,
+) -> io::Result<()> {
+    let candidates = config.targetdata_for(baseline)?;
+    if toolchain::repo_commit(Path::new(target)).is_none() {
+        return Err(io::Error::other(format!(
+            "the data directory {} is not a git checkout, its history is not available",
+            target
+        )));
+    }
+    let dir = Path::new(target).join(&config.name);
+    let count_arg = format!("-n{}", count);
+
+    // Each candidate expected output has its own history
+    let mut committed = Vec::new();
+    for targetdata in candidates {
+        let baseline = git(
+            &dir,
+            &[
+                "log",
+                "--follow",
+                &count_arg,
+                "--date=short",
+                LOG_FORMAT,
+                "--",
+                targetdata,
+            ],
+            debug,
+        )?;
+        println!("Expected output {}/{}:", config.name, targetdata);
+        if baseline.trim().is_empty() {
+            println!("  never committed");
+        } else {
+            for line in baseline.lines() {
+                println!("  {}", line);
+            }
+            committed.push(targetdata);
+        }
+    }
+
+    let configuration = git(
+        &dir,
+        &[
+            "log",
+            "-n1",
+            "--date=short",
+            LOG_FORMAT,
+            "--",
+            "config.yaml",
+        ],
+        debug,
+    )?;
+    println!("Configuration last changed:");
+    match configuration.lines().next() {
+        Some(line) => println!("  {}", line),
+        None => println!("  never committed"),
+    }
+
+    if patch {
+        for targetdata in committed {
+            let diff = git(
+                &dir,
+                &[
+                    "log",
+                    "--follow",
+                    "-n1",
+                    "-p",
+                    "--date=short",
+                    LOG_FORMAT,
+                    "--",
+                    targetdata,
+                ],
+                debug,
+            )?;
+            println!("Last change of the expected output {}:", targetdata);
+            print!("{}", diff);
+        }
+    }
+    Ok(())
+}
+
+/// Last commit that changed a file of the data repository.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastChange {
+    /// Abbreviated hash of the commit
+    pub hash: String,
+    /// Author date of the commit
+    pub date: DateTime,
+    pub author: String,
+    pub subject: String,
+}
+
+impl LastChange {
+    /// Describes the change of an expected output, e.g.
+    /// `baseline last changed 2d ago by Ada (1a2b3c4): 'reset after v0.9.1'`.
+    pub fn describe(&self, now: DateTime) -> String {
+        format!(
+            "baseline last changed {} by {} ({}): '{}'",
+            self.date.age(now),
+            self.author,
+            self.hash,
+            self.subject
+        )
+    }
+}
+
+/// Last changes of the files of the data repository, for the failure output.
+///
+/// The whole history is read by a single git log, on the first lookup, so that a
+/// run with many failures does not spawn a git process for each of them.
+#[derive(Debug, Default)]
+pub struct LastChanges {
+    /// Last change of each file, by path relative to the data directory, None if the
+    /// history is not available
+    changes: OnceLock<Option<HashMap<String, LastChange>>>,
+}
+
+impl LastChanges {
+    /// Returns the last change of a file, given relative to the data directory `target`.
+    ///
+    /// None if the file was never committed, or the data directory is not a git checkout.
+    pub fn of(&self, target: &str, path: &str) -> Option<&LastChange> {
+        self.changes
+            .get_or_init(|| {
+                // Paths relative to the data directory, even if it is a subdirectory of the checkout
+                let log = git(
+                    Path::new(target),
+                    &[
+                        "-c",
+                        "core.quotePath=false",
+                        "log",
+                        "--relative",
+                        "--name-only",
+                        "--format=%x1e%h%x1f%at%x1f%an%x1f%s",
+                    ],
+                    false,
+                )
+                .ok()?;
+                Some(parse_last_changes(&log))
+            })
+            .as_ref()?
+            .get(path)
+    }
+}
+
+/// Parses a git log listing the changed files of each commit, most recent first.
+fn parse_last_changes(log: &str) -> HashMap<String, LastChange> {
+    let mut changes = HashMap::new();
+    for commit in log.split('\x1e') {
+        let mut lines = commit.lines();
+        let Some(header) = lines.next() else {
+            continue;
+        };
+        let fields: Vec<&str> = header.splitn(4, '\x1f').collect();
+        let [hash, at, author, subject] = fields[..] else {
+            continue;
+        };
+        let change = LastChange {
+            hash: hash.to_string(),
+            date: DateTime::from_unix(at.parse().unwrap_or_default()),
+            author: author.to_string(),
+            subject: subject.to_string(),
+        };
+        for path in lines.filter(|line| !line.is_empty()) {
+            changes
+                .entry(path.to_string())
+                .or_insert_with(|| change.clone());
+        }
+    }
+    changes
+}
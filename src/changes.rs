@@ -0,0 +1,163 @@
+//! Selection of the regressions affected by the changes since a git reference.
+//!
+//! With `run --since <ref>` only the regressions whose example changed between
+//! the reference and `HEAD` of its examples checkout are run: a changed path
+//! affects the regressions whose `regbase` contains it. With `--since-data
+//! <ref>` the changes of the data repository select the regressions too: the
+//! files in the directory of a regression, the files it includes and, for the
+//! regressions taking keys from it, the tag defaults file. The paths are
+//! those listed by `git diff --name-only`, relative to the checkout.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::{RegressionConfig, TAGS_FILE};
+
+/// Number of changed paths named in the reason of a selection, the others are counted.
+const NAMED_PATHS: usize = 3;
+
+/// References the changes are computed from, for the examples and the data.
+#[derive(Debug, Clone, Default)]
+pub struct Since {
+    pub examples: Option<String>,
+    pub data: Option<String>,
+}
+
+impl Since {
+    /// Returns true if the regressions are selected by their changes.
+    pub fn is_set(&self) -> bool {
+        self.examples.is_some() || self.data.is_some()
+    }
+}
+
+/// Returns the paths changed between a reference and `HEAD`, relative to `dir`.
+///
+/// # Errors
+///
+/// Returns an error with the stderr of git if `dir` is not a git checkout or the
+/// reference is unknown, as in a shallow clone lacking it.
+pub fn changed_paths(dir: &Path, since: &str) -> io::Result<Vec<String>> {
+    let range = format!("{}..HEAD", since);
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["diff", "--name-only", "--relative", &range, "--"])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git diff {} in {} failed: {}",
+            range,
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Returns true if a path is a directory or lies under it.
+fn under(path: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches('/');
+    dir.is_empty() || dir == "." || path == dir || path.starts_with(&format!("{}/", dir))
+}
+
+/// Describes the changed paths matching a predicate, `None` if there are none.
+fn describe(what: &str, changed: &[String], matches: impl Fn(&str) -> bool) -> Option<String> {
+    let matching: Vec<&str> = changed
+        .iter()
+        .map(String::as_str)
+        .filter(|path| matches(path))
+        .collect();
+    if matching.is_empty() {
+        return None;
+    }
+    let mut named = matching[..matching.len().min(NAMED_PATHS)].join(", ");
+    if matching.len() > NAMED_PATHS {
+        named.push_str(&format!(" and {} more", matching.len() - NAMED_PATHS));
+    }
+    Some(format!("{} changed: {}", what, named))
+}
+
+/// Returns why the changes of the examples affect a regression, `None` if they do not.
+pub fn example_reason(config: &RegressionConfig, changed: &[String]) -> Option<String> {
+    describe(&format!("example {}", config.regbase), changed, |path| {
+        under(path, &config.regbase)
+    })
+}
+
+/// Returns why the changes of the data repository affect a regression, `None` if they do not.
+pub fn data_reason(config: &RegressionConfig, changed: &[String]) -> Option<String> {
+    let own = describe("data", changed, |path| under(path, &config.name));
+    let included = describe("included files", changed, |path| {
+        config
+            .sources
+            .iter()
+            .any(|(_, source)| source == path || (path == TAGS_FILE && source.starts_with(path)))
+    });
+    match (own, included) {
+        (None, None) => None,
+        (own, included) => Some(
+            own.into_iter()
+                .chain(included)
+                .collect::<Vec<_>>()
+                .join("; "),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use std::fs;
+    use tempdir::TempDir;
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[test]
+    fn maps_the_changed_paths_to_regressions() {
+        let data = TempDir::new("changes").unwrap();
+        for (path, content) in [
+            (
+                "basys3/blink/config.yaml",
+                "include: ../../_common/make.yaml\nregbase: blink\nsourcedata: out.sv\ntargetdata: out.sv\n",
+            ),
+            ("_common/make.yaml", "regcommand: make hdl\n"),
+        ] {
+            let path = data.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let config = config::load(data.path().to_str().unwrap(), "basys3/blink").unwrap();
+
+        let changed = paths(&[
+            "blinker/Makefile",
+            "blink/a.basm",
+            "blink/b",
+            "blink/c",
+            "blink/d",
+        ]);
+        assert_eq!(
+            example_reason(&config, &changed).as_deref(),
+            Some("example blink changed: blink/a.basm, blink/b, blink/c and 1 more")
+        );
+        assert_eq!(example_reason(&config, &changed[..1]), None);
+
+        let changed = paths(&[
+            "basys3/blink/out.sv",
+            "basys3/blinky/config.yaml",
+            "_common/make.yaml",
+        ]);
+        assert_eq!(
+            data_reason(&config, &changed).as_deref(),
+            Some("data changed: basys3/blink/out.sv; included files changed: _common/make.yaml")
+        );
+        assert_eq!(data_reason(&config, &changed[1..2]), None);
+        assert_eq!(data_reason(&config, &paths(&[TAGS_FILE])), None);
+    }
+}
@@ -0,0 +1,133 @@
+//! Detection of the changes to the examples checkout while a command runs.
+//!
+//! A persistent `--examples-dir` may be edited while a regression command
+//! builds from it, and half-written sources give failures that mean nothing.
+//! Before the command runs, the checkout is snapshotted: its HEAD and the
+//! `git status` of its tracked files when it is a git checkout, and the size
+//! and modification time of the files of the example directory, only the
+//! tracked ones in a git checkout. The snapshot taken after the command tells
+//! what moved underneath it.
+//!
+//! The files the command creates are not changes: only the files present
+//! before are compared. The generated output and the artifacts of the
+//! regression are written by the command, they are left out.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::toolchain;
+use crate::watch;
+
+/// Number of changes named in the description, the others are counted.
+const NAMED_CHANGES: usize = 3;
+
+/// State of the examples checkout of a regression.
+#[derive(Debug)]
+pub struct Snapshot {
+    /// Commit checked out, None if the checkout is not a git one
+    head: Option<String>,
+    /// Lines of `git status --porcelain` of the tracked files, the ignored ones left out
+    status: BTreeSet<String>,
+    /// Files of the example directory
+    files: watch::Snapshot,
+    /// Directory of the example, the changed files are named relative to it
+    example: PathBuf,
+}
+
+/// Runs git in a directory, returning its stdout if it succeeds.
+fn git(dir: &Path, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+/// Returns true if a path relative to the example directory is ignored.
+fn ignored(relative: &Path, ignore: &[glob::Pattern]) -> bool {
+    ignore.iter().any(|pattern| pattern.matches_path(relative))
+}
+
+impl Snapshot {
+    /// Snapshots the checkout `checkout` and its example directory `example`, leaving out
+    /// the files matching the `ignore` patterns, relative to the example directory.
+    pub fn take(checkout: &Path, example: &Path, ignore: &[glob::Pattern]) -> Snapshot {
+        let mut files = watch::snapshot(&[example.to_path_buf()], &[]);
+        files.retain(|path, _| !ignored(path.strip_prefix(example).unwrap_or(path), ignore));
+        let head = toolchain::repo_commit(checkout);
+        let mut status = BTreeSet::new();
+        if head.is_some() {
+            let tracked: BTreeSet<PathBuf> = git(example, &["ls-files", "-z"])
+                .unwrap_or_default()
+                .split(|byte| *byte == 0)
+                .filter(|path| !path.is_empty())
+                .map(|path| example.join(String::from_utf8_lossy(path).as_ref()))
+                .collect();
+            files.retain(|path, _| tracked.contains(path));
+            let porcelain = git(checkout, &["status", "--porcelain", "--untracked-files=no"])
+                .unwrap_or_default();
+            status = String::from_utf8_lossy(&porcelain)
+                .lines()
+                .filter(|line| {
+                    let path = checkout.join(line.get(3..).unwrap_or_default());
+                    !path
+                        .strip_prefix(example)
+                        .is_ok_and(|relative| ignored(relative, ignore))
+                })
+                .map(str::to_string)
+                .collect();
+        }
+        Snapshot {
+            head,
+            status,
+            files,
+            example: example.to_path_buf(),
+        }
+    }
+
+    /// Describes what changed from this snapshot to `after`, e.g. `HEAD moved from 0123456
+    /// to 89abcde, src/main.go modified`, None if nothing did.
+    pub fn changes(&self, after: &Snapshot) -> Option<String> {
+        let mut changes = Vec::new();
+        if let (Some(before), Some(now)) = (&self.head, &after.head) {
+            if before != now {
+                let short = |commit: &str| commit.chars().take(7).collect::<String>();
+                changes.push(format!(
+                    "HEAD moved from {} to {}",
+                    short(before),
+                    short(now)
+                ));
+            }
+        }
+        for (path, state) in &self.files {
+            let relative = path.strip_prefix(&self.example).unwrap_or(path).display();
+            match after.files.get(path) {
+                None => changes.push(format!("{} removed", relative)),
+                Some(now) if now != state => changes.push(format!("{} modified", relative)),
+                Some(_) => {}
+            }
+        }
+        // A tracked file outside the example directory
+        if changes.is_empty() && self.status != after.status {
+            let line = after
+                .status
+                .symmetric_difference(&self.status)
+                .next()
+                .cloned()
+                .unwrap_or_default();
+            changes.push(format!("git status changed ({})", line.trim()));
+        }
+        if changes.is_empty() {
+            return None;
+        }
+        if changes.len() > NAMED_CHANGES {
+            let more = changes.len() - NAMED_CHANGES;
+            changes.truncate(NAMED_CHANGES);
+            changes.push(format!("{} more", more));
+        }
+        Some(changes.join(", "))
+    }
+}
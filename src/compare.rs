@@ -0,0 +1,350 @@
+//! Comparison of two recorded runs.
+
+use std::time::Duration;
+
+use crate::history::{Entry, RecordedRun};
+use crate::report::json_string;
+use crate::results::is_pass;
+
+/// How the status of a regression changed between two runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    /// Passed in the first run, failed in the second
+    NewlyFailing,
+    /// Failed in the first run, passed in the second
+    NewlyPassing,
+    /// Failed in both runs
+    StillFailing,
+    /// Passed in both runs
+    Unchanged,
+    /// Only executed in the first run
+    Removed,
+    /// Only executed in the second run
+    Added,
+}
+
+impl Transition {
+    /// All the transitions, in the order they are reported.
+    pub const ALL: [Transition; 6] = [
+        Transition::NewlyFailing,
+        Transition::NewlyPassing,
+        Transition::StillFailing,
+        Transition::Unchanged,
+        Transition::Removed,
+        Transition::Added,
+    ];
+
+    /// Returns the name of the group of regressions with this transition.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Transition::NewlyFailing => "newly failing",
+            Transition::NewlyPassing => "newly passing",
+            Transition::StillFailing => "still failing",
+            Transition::Unchanged => "unchanged",
+            Transition::Removed => "only in the first run",
+            Transition::Added => "only in the second run",
+        }
+    }
+
+    /// Returns the key of the group in the JSON output.
+    fn key(&self) -> &'static str {
+        match self {
+            Transition::NewlyFailing => "newly_failing",
+            Transition::NewlyPassing => "newly_passing",
+            Transition::StillFailing => "still_failing",
+            Transition::Unchanged => "unchanged",
+            Transition::Removed => "removed",
+            Transition::Added => "added",
+        }
+    }
+}
+
+/// A regression with its status and durations in the two runs.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub name: String,
+    pub transition: Transition,
+    pub before: Option<Duration>,
+    pub after: Option<Duration>,
+    /// Both durations are the medians of the iterations of the command, run with `--bench`
+    pub benched: bool,
+}
+
+impl Change {
+    /// Relative change of the duration in percent, if the regression ran in both runs.
+    pub fn duration_delta(&self) -> Option<f64> {
+        let before = self.before?.as_secs_f64();
+        let after = self.after?.as_secs_f64();
+        if before == 0.0 {
+            return None;
+        }
+        Some((after - before) * 100.0 / before)
+    }
+}
+
+/// Compares two runs, returning the changes sorted by regression name.
+///
+/// The durations of a regression benchmarked in both runs are the medians of the iterations
+/// of its command, which leave out the comparison of the outputs and are steadier.
+pub fn compare<F: Fn(&str) -> bool>(
+    first: &RecordedRun,
+    second: &RecordedRun,
+    filter: F,
+) -> Vec<Change> {
+    let mut names: Vec<&str> = first
+        .entries
+        .iter()
+        .chain(second.entries.iter())
+        .map(|e| e.name.as_str())
+        .filter(|name| filter(name))
+        .collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let before = first.entry(name);
+            let after = second.entry(name);
+            let transition = match (before, after) {
+                (Some(b), Some(a)) => match (is_pass(&b.status), is_pass(&a.status)) {
+                    (true, false) => Transition::NewlyFailing,
+                    (false, true) => Transition::NewlyPassing,
+                    (false, false) => Transition::StillFailing,
+                    (true, true) => Transition::Unchanged,
+                },
+                (Some(_), None) => Transition::Removed,
+                _ => Transition::Added,
+            };
+            let benched = before
+                .zip(after)
+                .is_some_and(|(before, after)| !before.bench.is_empty() && !after.bench.is_empty());
+            let duration = |entry: &Entry| {
+                if benched {
+                    entry.compared_duration()
+                } else {
+                    entry.duration
+                }
+            };
+            Change {
+                name: name.to_string(),
+                transition,
+                before: before.map(duration),
+                after: after.map(duration),
+                benched,
+            }
+        })
+        .collect()
+}
+
+/// Formats an optional duration in seconds.
+fn seconds(duration: Option<Duration>) -> String {
+    duration
+        .map(|d| format!("{:.1}s", d.as_secs_f64()))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Prints the changes as text, grouped by transition.
+///
+/// Duration changes larger than `threshold` percent are highlighted.
+pub fn print_text(changes: &[Change], threshold: f64) {
+    for transition in Transition::ALL {
+        let group: Vec<&Change> = changes
+            .iter()
+            .filter(|c| c.transition == transition)
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+        let color = match transition {
+            Transition::NewlyFailing | Transition::StillFailing => "\x1b[0;31m",
+            Transition::NewlyPassing | Transition::Unchanged => "\x1b[0;32m",
+            Transition::Removed | Transition::Added => "\x1b[0;33m",
+        };
+        println!("{}{}\x1b[0m ({}):", color, transition.label(), group.len());
+        for change in group {
+            let delta = match change.duration_delta() {
+                Some(delta) if delta.abs() > threshold => {
+                    format!(" \x1b[0;33m{:+.0}%\x1b[0m", delta)
+                }
+                _ => String::new(),
+            };
+            let benched = if change.benched {
+                ", bench medians"
+            } else {
+                ""
+            };
+            println!(
+                "  {} ({} -> {}{}){}",
+                change.name,
+                seconds(change.before),
+                seconds(change.after),
+                benched,
+                delta
+            );
+        }
+    }
+}
+
+/// Renders the changes as JSON, one array per transition.
+pub fn render_json(changes: &[Change], threshold: f64) -> String {
+    let groups: Vec<String> = Transition::ALL
+        .iter()
+        .map(|transition| {
+            let entries: Vec<String> = changes
+                .iter()
+                .filter(|c| c.transition == *transition)
+                .map(|c| {
+                    let delta = c.duration_delta();
+                    format!(
+                        "{{\"name\": {}, \"duration_before_seconds\": {}, \"duration_after_seconds\": {}, \"duration_delta_percent\": {}, \"duration_changed\": {}, \"benched\": {}}}",
+                        json_string(&c.name),
+                        c.before
+                            .map(|d| format!("{:.3}", d.as_secs_f64()))
+                            .unwrap_or_else(|| "null".to_string()),
+                        c.after
+                            .map(|d| format!("{:.3}", d.as_secs_f64()))
+                            .unwrap_or_else(|| "null".to_string()),
+                        delta
+                            .map(|d| format!("{:.1}", d))
+                            .unwrap_or_else(|| "null".to_string()),
+                        delta.map(|d| d.abs() > threshold).unwrap_or(false),
+                        c.benched
+                    )
+                })
+                .collect();
+            format!("{}: [{}]", json_string(transition.key()), entries.join(", "))
+        })
+        .collect();
+    format!("{{{}}}", groups.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(id: &str, entries: &[(&str, &str, u64)]) -> RecordedRun {
+        RecordedRun {
+            id: id.to_string(),
+            entries: entries
+                .iter()
+                .map(|(name, status, millis)| Entry {
+                    name: name.to_string(),
+                    status: status.to_string(),
+                    duration: Duration::from_millis(*millis),
+                    tags: Vec::new(),
+                    similarity: None,
+                    bench: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn runs() -> (RecordedRun, RecordedRun) {
+        (
+            run(
+                "before",
+                &[
+                    ("uart", "passed", 1000),
+                    ("blink", "passed", 1000),
+                    ("counter", "failed", 1000),
+                    ("spi", "error", 1000),
+                    ("i2c", "passed", 0),
+                    ("old", "passed", 1000),
+                ],
+            ),
+            run(
+                "after",
+                &[
+                    ("blink", "failed", 1100),
+                    ("counter", "passed", 500),
+                    ("spi", "failed", 1000),
+                    ("uart", "captured", 2500),
+                    ("i2c", "passed", 10),
+                    ("new", "passed", 1000),
+                ],
+            ),
+        )
+    }
+
+    #[test]
+    fn classifies_the_status_transitions() {
+        let (before, after) = runs();
+        let changes = compare(&before, &after, |_| true);
+        let transitions: Vec<(&str, Transition)> = changes
+            .iter()
+            .map(|c| (c.name.as_str(), c.transition))
+            .collect();
+        assert_eq!(
+            transitions,
+            [
+                ("blink", Transition::NewlyFailing),
+                ("counter", Transition::NewlyPassing),
+                ("i2c", Transition::Unchanged),
+                ("new", Transition::Added),
+                ("old", Transition::Removed),
+                ("spi", Transition::StillFailing),
+                ("uart", Transition::Unchanged),
+            ]
+        );
+        let names: Vec<String> = compare(&before, &after, |name| name.contains('i'))
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, ["blink", "i2c", "spi"]);
+    }
+
+    #[test]
+    fn measures_the_duration_changes() {
+        let (before, after) = runs();
+        let changes = compare(&before, &after, |_| true);
+        let delta = |name: &str| {
+            changes
+                .iter()
+                .find(|c| c.name == name)
+                .unwrap()
+                .duration_delta()
+        };
+        assert_eq!(delta("uart"), Some(150.0));
+        assert_eq!(delta("counter"), Some(-50.0));
+        // Not measurable
+        assert_eq!(delta("i2c"), None);
+        assert_eq!(delta("new"), None);
+        assert_eq!(delta("old"), None);
+    }
+
+    #[test]
+    fn compares_the_bench_medians_when_both_runs_have_them() {
+        let (mut before, mut after) = runs();
+        let millis = |values: &[u64]| values.iter().map(|v| Duration::from_millis(*v)).collect();
+        before.entries[1].bench = millis(&[100, 300, 200]);
+        after.entries[0].bench = millis(&[400, 400]);
+        // Only in the second run
+        after.entries[1].bench = millis(&[100]);
+        let changes = compare(&before, &after, |_| true);
+        let blink = &changes[0];
+        assert!(blink.benched);
+        assert_eq!(blink.before, Some(Duration::from_millis(200)));
+        assert_eq!(blink.after, Some(Duration::from_millis(400)));
+        let counter = &changes[1];
+        assert!(!counter.benched);
+        assert_eq!(counter.after, Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn renders_the_groups_as_json() {
+        let (before, after) = runs();
+        let changes = compare(&before, &after, |name| name == "blink" || name == "old");
+        assert_eq!(
+            render_json(&changes, 20.0),
+            "{\"newly_failing\": [{\"name\": \"blink\", \"duration_before_seconds\": 1.000, \
+             \"duration_after_seconds\": 1.100, \"duration_delta_percent\": 10.0, \
+             \"duration_changed\": false, \"benched\": false}], \"newly_passing\": [], \
+             \"still_failing\": [], \"unchanged\": [], \"removed\": [{\"name\": \"old\", \
+             \"duration_before_seconds\": 1.000, \"duration_after_seconds\": null, \
+             \"duration_delta_percent\": null, \"duration_changed\": false, \"benched\": false}], \
+             \"added\": []}"
+        );
+        assert!(render_json(&changes, 5.0).contains("\"duration_changed\": true"));
+    }
+}
@@ -0,0 +1,2763 @@
+//! Loading of the regression configurations.
+//!
+//! Each regression is described by the `config.yaml` file in its directory of
+//! the data repository. The file is parsed once into a [`RegressionConfig`],
+//! which is then used both to select the regression and to execute it.
+//!
+//! A configuration may name, with the `include` key, a YAML file of the data
+//! repository holding values shared by several regressions (by convention in a
+//! directory starting with `_`, such as `_common`). The included values are
+//! merged beneath the configuration at the top level: a key defined by the
+//! configuration replaces the included value entirely. Included files may
+//! themselves include one more file.
+//!
+//! The optional `tags.yaml` file at the root of the data repository maps tags to
+//! default execution options (`timeout`, `retries`, `priority` and `env`), which
+//! are merged beneath the configurations of the regressions carrying the tag,
+//! includes included. When several tags of a regression define the same option,
+//! the one of the tag listed last wins.
+//!
+//! The optional `actions` key overrides some keys for the actions executing the
+//! regression, e.g. a longer timeout for run than for diff:
+//!
+//! ```yaml
+//! actions:
+//!   run: {timeout: 3600}
+//!   diff: {compare: similarity, min_similarity: 95}
+//! ```
+//!
+//! The keys of the block of an action replace those of the configuration, includes
+//! and tag defaults merged, when that action executes the regression.
+//!
+//! A command generating several outputs checks them all with the `files` key,
+//! which replaces `sourcedata` and `targetdata`, each file with the regular
+//! expressions of the lines left out of its comparison, as `ignore` does for
+//! the single output of the other regressions:
+//!
+//! ```yaml
+//! files:
+//!   - {sourcedata: bondmachine.sv, targetdata: bondmachine.sv}
+//!   - {sourcedata: bondmachine.json, targetdata: bondmachine.json, ignore: ['"version":']}
+//! ```
+//!
+//! The optional `tag-groups.yaml` file, next to it, defines tag groups: names
+//! standing for a list of tags, so that a requested group selects the
+//! regressions carrying any of its members. Groups are expanded once, a member
+//! naming another group is matched as a plain tag, and cycles are rejected.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use yaml_rust::yaml::Hash;
+use yaml_rust::{Yaml, YamlEmitter, YamlLoader};
+
+use crate::atomic;
+use crate::paths;
+use crate::pattern::Pattern;
+use crate::provenance;
+use crate::report;
+use crate::schedule::Frequency;
+use crate::summary;
+
+/// Tag of the regressions that do not define any.
+pub const DEFAULT_TAG: &str = "default";
+
+/// Baseline of a `targetdata` given as a single file.
+pub const DEFAULT_BASELINE: &str = "default";
+
+/// Key naming the file whose values a configuration inherits.
+pub const INCLUDE_KEY: &str = "include";
+
+/// Maximum depth of the includes: a config may include a file, which may include one more.
+const MAX_INCLUDE_DEPTH: usize = 2;
+
+/// Name of the file holding the default options of the tags, at the root of the data repository.
+pub const TAGS_FILE: &str = "tags.yaml";
+
+/// Name of the file defining the tag groups, at the root of the data repository.
+pub const TAG_GROUPS_FILE: &str = "tag-groups.yaml";
+
+/// Options that the tags can give defaults for.
+const TAG_OPTIONS: [&str; 4] = ["timeout", "retries", "priority", "env"];
+
+/// Type of the value of a key of `config.yaml`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyType {
+    String,
+    /// A list of strings
+    StringList,
+    /// A command line, or the list of the arguments of a command run without a shell
+    Command,
+    /// A file, a list of candidate files, or a mapping of baseline names to either
+    Targetdata,
+    Boolean,
+    /// An integer of at least this value
+    Integer(i64),
+    /// A number between these bounds
+    Number(f64, f64),
+    /// One of these words
+    Word(&'static [&'static str]),
+    /// A frequency keyword or a cron expression
+    Frequency,
+    /// A mapping of the start and the end markers
+    Section,
+    /// A list of mappings of a pattern and its replacement
+    Masks,
+    /// A list of regular expressions
+    Patterns,
+    /// A list of mappings of a sourcedata, a targetdata and the patterns of the ignored lines
+    Files,
+    /// A mapping of the limited resources to their limits
+    Limits,
+    /// A mapping of environment variables to scalars
+    Env,
+    /// A mapping of actions to the keys they override
+    Actions,
+}
+
+/// A key of `config.yaml`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Key {
+    pub name: &'static str,
+    pub value: KeyType,
+    /// Required unless the configuration includes a file, which may give it
+    pub required: bool,
+    pub description: &'static str,
+}
+
+impl Key {
+    const fn new(name: &'static str, value: KeyType, description: &'static str) -> Key {
+        Key {
+            name,
+            value,
+            required: false,
+            description,
+        }
+    }
+
+    const fn required(name: &'static str, value: KeyType, description: &'static str) -> Key {
+        Key {
+            name,
+            value,
+            required: true,
+            description,
+        }
+    }
+}
+
+/// Keys renamed since they were introduced, as (old, new) pairs, rewritten by `bmregression migrate`.
+pub const RENAMED_KEYS: [(&str, &str); 0] = [];
+
+/// Names of the comparison modes of the `compare` key.
+pub const COMPARE_MODES: [&str; 4] = ["exact", "similarity", "regex_lines", "contains"];
+
+/// Keys of `config.yaml`, those parsed by [`load`] and described by `bmregression schema`, in
+/// the order `bmregression migrate` sorts them in.
+pub const KEYS: [Key; 36] = [
+    Key::new(INCLUDE_KEY, KeyType::String, "File whose keys the configuration inherits, relative to it"),
+    Key::required("regbase", KeyType::String, "Directory of the example in the examples repository"),
+    Key::required("sourcedata", KeyType::String, "Generated output file, relative to the example directory"),
+    Key::required("targetdata", KeyType::Targetdata, "Expected output file, relative to the regression directory: a file, a list of acceptable candidates, or a mapping of baseline names to either"),
+    Key::new("files", KeyType::Files, "Output files checked instead of sourcedata and targetdata, for a command generating several, each a mapping of its sourcedata, targetdata and ignore"),
+    Key::required("regcommand", KeyType::Command, "Command generating the output, run through the shell, or a list of arguments run without a shell"),
+    Key::new("smokecommand", KeyType::Command, "Quick command run instead of regcommand by run --smoke, such as make check"),
+    Key::new("smoke_sourcedata", KeyType::String, "Output file of smokecommand, relative to the example directory, sourcedata if not given"),
+    Key::new("smoke_targetdata", KeyType::Targetdata, "Expected output of smokecommand, relative to the regression directory, targetdata if not given"),
+    Key::new("tags", KeyType::StringList, "Tags selecting the regression, [default] if none is given"),
+    Key::new("artifacts", KeyType::StringList, "Glob patterns of the files collected on failure"),
+    Key::new("on_fail", KeyType::String, "Command run through the shell in the example directory when the regression fails or errors"),
+    Key::new("difftool", KeyType::String, "Diff tool used by the diff command"),
+    Key::new("workdir", KeyType::String, "Directory the command runs in, relative to the regbase"),
+    Key::new("examples", KeyType::String, "Name of the examples repository holding the regbase"),
+    Key::new("timeout", KeyType::Integer(1), "Seconds after which the command is terminated"),
+    Key::new("retries", KeyType::Integer(0), "Number of times a failing run is retried"),
+    Key::new("max_diff_lines", KeyType::Integer(0), "Number of differing lines tolerated by run"),
+    Key::new("max_output_size", KeyType::Integer(1), "Size in bytes above which the generated output is not read, failing the regression"),
+    Key::new("compare", KeyType::Word(&COMPARE_MODES), "How run compares the outputs"),
+    Key::new("min_similarity", KeyType::Number(0.0, 100.0), "Percentage of identical lines required by compare: similarity"),
+    Key::new("compare_between", KeyType::Section, "Markers delimiting the compared part of the outputs"),
+    Key::new("mask", KeyType::Masks, "Substitutions applied to each line of both outputs before comparing them"),
+    Key::new("ignore", KeyType::Patterns, "Regular expressions of the lines left out of both outputs before comparing them"),
+    Key::new("normalize_numbers", KeyType::Boolean, "Rewrite the floating point numbers of both outputs in a canonical form before comparing them"),
+    Key::new("number_precision", KeyType::Integer(0), "Decimals of the canonical numbers, 6 by default"),
+    Key::new("store_compressed", KeyType::Boolean, "Store the expected output gzip-compressed"),
+    Key::new("protected", KeyType::Boolean, "Refuse to overwrite the expected output with reset and update, unless --override-protected is given"),
+    Key::new("protected_reason", KeyType::String, "Who protected the expected output and why, printed when it is not overwritten"),
+    Key::new("priority", KeyType::Integer(i64::MIN), "Regressions with a higher priority are executed first"),
+    Key::new("env", KeyType::Env, "Environment variables set for the command"),
+    Key::new("clean_env", KeyType::Boolean, "Run the command with a minimal environment"),
+    Key::new("env_passthrough", KeyType::StringList, "Variables kept in the minimal environment"),
+    Key::new("limits", KeyType::Limits, "Limits of the resources of the command"),
+    Key::new("frequency", KeyType::Frequency, "How often the regression is due: always, hourly, daily, weekly or a cron expression"),
+    Key::new("actions", KeyType::Actions, "Keys overriding the others when an action, such as run or diff, executes the regression, by action"),
+];
+
+/// Required keys given by each entry of the `files` key instead, with `ignore`.
+pub const OUTPUT_KEYS: [&str; 2] = ["sourcedata", "targetdata"];
+
+/// Keys of an entry of the `files` key.
+pub const FILE_KEYS: [&str; 3] = ["sourcedata", "targetdata", "ignore"];
+
+/// Actions executing the regressions, whose keys the `actions` key overrides.
+pub const ACTIONS: [&str; 6] = ["run", "bootstrap", "reset", "preview", "update", "diff"];
+
+/// Keys the `actions` key may override, those telling how a regression is executed and
+/// compared rather than what it is.
+pub const ACTION_KEYS: [&str; 14] = [
+    "timeout",
+    "retries",
+    "max_diff_lines",
+    "max_output_size",
+    "compare",
+    "min_similarity",
+    "compare_between",
+    "mask",
+    "normalize_numbers",
+    "number_precision",
+    "store_compressed",
+    "difftool",
+    "env",
+    "limits",
+];
+
+/// How the generated output of a regression is compared with the expected one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compare {
+    /// The outputs must be identical, up to `max_diff_lines` differing lines
+    Exact,
+    /// At least this percentage of the lines must be identical
+    Similarity(f64),
+    /// Each line of the expected output is a pattern matching the same line of the generated one
+    RegexLines,
+    /// The lines of the expected output appear in the generated one in the same order, among others
+    Contains,
+}
+
+impl Compare {
+    /// Returns true if the expected output is written by hand rather than captured from a run.
+    pub fn is_hand_written(self) -> bool {
+        matches!(self, Compare::RegexLines | Compare::Contains)
+    }
+}
+
+/// Markers delimiting the part of the outputs that is compared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// Text of the line starting the section, which is not part of it
+    pub start: String,
+    /// Text of the line ending the section, which is not part of it
+    pub end: String,
+}
+
+impl Section {
+    /// Returns the lines between the first line containing the start marker and the next one
+    /// containing the end marker.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the marker that is not found.
+    pub fn extract(&self, text: &str) -> Result<String, String> {
+        let mut lines = text.lines();
+        if !lines.any(|line| line.contains(&self.start)) {
+            return Err(format!("start marker {:?} not found", self.start));
+        }
+        let mut section = String::new();
+        for line in lines {
+            if line.contains(&self.end) {
+                return Ok(section);
+            }
+            section.push_str(line);
+            section.push('\n');
+        }
+        Err(format!(
+            "end marker {:?} not found after the start marker",
+            self.end
+        ))
+    }
+}
+
+/// Substitution applied to both outputs before they are compared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mask {
+    /// Regular expression of the masked text, see [`crate::pattern`]
+    pub pattern: String,
+    /// Text replacing each match
+    pub replace: String,
+    compiled: Pattern,
+}
+
+/// Lines left out of both outputs before they are compared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ignore {
+    /// Regular expression of the ignored lines, see [`crate::pattern`]
+    pub pattern: String,
+    compiled: Pattern,
+}
+
+/// An output file of a regression generating several, from the `files` key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputFile {
+    /// Generated output file, relative to the example directory
+    pub sourcedata: String,
+    /// Expected output files, relative to the regression directory, by baseline name
+    pub targetdata: Vec<(String, String)>,
+    /// Lines left out of both outputs before comparing them
+    pub ignore: Vec<Ignore>,
+}
+
+/// Limits of the resources of the regression command, applied to it and its children.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// Maximum virtual memory of each process, in MiB
+    pub memory_mb: Option<u64>,
+    /// Maximum CPU time of each process, in seconds
+    pub cpu_seconds: Option<u64>,
+    /// Niceness added to the priority of the command, from 0 to 19
+    pub nice: Option<i32>,
+}
+
+impl Limits {
+    /// Returns true if no limit is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Limits::default()
+    }
+}
+
+/// Configuration of a single regression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegressionConfig {
+    /// Name of the regression, the name of its directory
+    pub name: String,
+    /// Directory name in the examples repository
+    pub regbase: String,
+    /// Generated output file, relative to the example directory
+    pub sourcedata: String,
+    /// Expected output files, relative to the regression directory, by baseline name, several
+    /// for a baseline whose candidates are all acceptable
+    pub targetdata: Vec<(String, String)>,
+    /// Command generating the output, run through the shell, or the arguments of `argv`
+    /// joined and quoted for the shell
+    pub regcommand: String,
+    /// Arguments of the command executed without a shell, when regcommand is given as a list
+    pub argv: Option<Vec<String>>,
+    /// Quick command run instead of regcommand in smoke mode, None if the regression has none
+    pub smokecommand: Option<String>,
+    /// Arguments of the smoke command executed without a shell, when it is given as a list
+    pub smoke_argv: Option<Vec<String>>,
+    /// Generated output file of the smoke command, sourcedata if None
+    pub smoke_sourcedata: Option<String>,
+    /// Expected output files of the smoke command by baseline name, targetdata if empty
+    pub smoke_targetdata: Vec<(String, String)>,
+    /// Tags used to select the regression, `["default"]` if none is given
+    pub tags: Vec<String>,
+    /// Glob patterns of the files collected on failure
+    pub artifacts: Vec<String>,
+    /// Command run in the example directory when the regression fails or errors
+    pub on_fail: Option<String>,
+    /// Diff tool used by the diff command for this regression
+    pub difftool: Option<String>,
+    /// Directory the command runs in, relative to the regbase, the regbase itself if None
+    pub workdir: Option<String>,
+    /// Name of the examples repository holding the regbase, the primary one if None
+    pub examples: Option<String>,
+    /// Seconds after which the command is terminated, unlimited if None
+    pub timeout: Option<u64>,
+    /// Number of times a failing or erroring run is retried
+    pub retries: u32,
+    /// Number of differing lines (insertions and deletions) tolerated by run, 0 for an exact comparison
+    pub max_diff_lines: usize,
+    /// Size in bytes above which the generated output is not read, `--max-output-size` if None
+    pub max_output_size: Option<u64>,
+    /// How run compares the outputs
+    pub compare: Compare,
+    /// Markers delimiting the compared part of the outputs, the whole outputs if None
+    pub compare_between: Option<Section>,
+    /// Substitutions applied in order to each line of both outputs before comparing them
+    pub mask: Vec<Mask>,
+    /// Lines left out of both outputs before comparing them, before the masks apply
+    pub ignore: Vec<Ignore>,
+    /// Output files of a command generating several, empty for a single one; sourcedata,
+    /// targetdata and ignore are those of the first
+    pub files: Vec<OutputFile>,
+    /// Decimals of the canonical form the floating point numbers of both outputs are
+    /// rewritten in before comparing them, None to compare them as printed
+    pub normalize_numbers: Option<usize>,
+    /// Store the expected output gzip-compressed when it is written
+    pub store_compressed: bool,
+    /// The expected output is signed off, reset and update only overwrite it on request
+    pub protected: bool,
+    /// Who protected the expected output and why
+    pub protected_reason: Option<String>,
+    /// Regressions with a higher priority are executed first
+    pub priority: i64,
+    /// Environment variables set for the command
+    pub env: Vec<(String, String)>,
+    /// Run the command with a minimal environment instead of the one of bmregression
+    pub clean_env: bool,
+    /// Variables of the environment of bmregression kept in the clean environment
+    pub env_passthrough: Vec<String>,
+    /// Limits of the resources of the command
+    pub limits: Limits,
+    /// When the regression is due, for the scheduler and `run --due`
+    pub frequency: Frequency,
+    /// Keys whose value comes from an included file or a tag, with where it comes from
+    pub sources: Vec<(String, String)>,
+    /// Actions overriding keys, each with the values of the keys it overrides, rendered
+    /// inline, and the configuration it executes the regression with
+    pub actions: Vec<ActionOverride>,
+}
+
+/// The keys an action overrides, from the `actions` key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionOverride {
+    /// Name of the action, one of [`ACTIONS`]
+    pub action: String,
+    /// Overridden keys with their values, rendered inline, in the order of the block
+    pub keys: Vec<(String, String)>,
+    /// Configuration the action executes the regression with
+    pub config: RegressionConfig,
+}
+
+impl RegressionConfig {
+    /// Returns the requested groups through which the regression is selected.
+    pub fn matching_groups<'a>(
+        &self,
+        requested: &[String],
+        groups: &'a [TagGroup],
+        implicit: bool,
+    ) -> Vec<&'a str> {
+        groups
+            .iter()
+            .filter(|group| requested.contains(&group.name))
+            .filter(|group| self.matches_tags(&group.members, implicit))
+            .map(|group| group.name.as_str())
+            .collect()
+    }
+
+    /// Leaves out the ignored lines of a text, applies the masks to each of the others, in
+    /// order, then rewrites its floating point numbers in their canonical form if
+    /// `normalize_numbers` is set.
+    pub fn normalize(&self, text: &str) -> String {
+        if self.mask.is_empty() && self.ignore.is_empty() && self.normalize_numbers.is_none() {
+            return text.to_string();
+        }
+        text.split_inclusive('\n')
+            .filter(|line| {
+                let content = line.strip_suffix('\n').unwrap_or(line);
+                !self
+                    .ignore
+                    .iter()
+                    .any(|ignore| ignore.compiled.is_found_in(content))
+            })
+            .map(|line| {
+                let (content, newline) = match line.strip_suffix('\n') {
+                    Some(content) => (content, "\n"),
+                    None => (line, ""),
+                };
+                let masked = self.mask.iter().fold(content.to_string(), |text, mask| {
+                    mask.compiled.replace_all(&text, &mask.replace)
+                });
+                match self.normalize_numbers {
+                    Some(precision) => canonical_numbers(&masked, precision) + newline,
+                    None => masked + newline,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the configuration of the smoke mode: the smoke command, with its output and
+    /// expected output files, in place of the full ones. None if the regression has no
+    /// smokecommand.
+    pub fn smoke(&self) -> Option<RegressionConfig> {
+        let mut smoke = self.clone();
+        smoke.regcommand = self.smokecommand.clone()?;
+        smoke.argv = self.smoke_argv.clone();
+        if let Some(sourcedata) = &self.smoke_sourcedata {
+            smoke.sourcedata = sourcedata.clone();
+        }
+        if !self.smoke_targetdata.is_empty() {
+            smoke.targetdata = self.smoke_targetdata.clone();
+        }
+        // The smoke command checks its single output, with the ignored lines of the first file
+        if self.smoke_sourcedata.is_some() || !self.smoke_targetdata.is_empty() {
+            smoke.files.clear();
+        }
+        Some(smoke)
+    }
+
+    /// Returns the tags derived from the path of the regression directory, one per component.
+    pub fn implicit_tags(&self) -> Vec<String> {
+        self.name
+            .split(['/', '\\'])
+            .filter(|component| !component.is_empty())
+            .map(|component| component.to_string())
+            .collect()
+    }
+
+    /// Returns true if the regression has at least one of the requested tags.
+    ///
+    /// With `implicit` the tags derived from its path count as well as the explicit ones.
+    pub fn matches_tags(&self, requested: &[String], implicit: bool) -> bool {
+        requested
+            .iter()
+            .any(|tag| self.tags.contains(tag) || (implicit && self.implicit_tags().contains(tag)))
+    }
+
+    /// Returns the expected output files of a baseline, several if any of them is acceptable.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the available baselines if the regression has none with that name.
+    pub fn targetdata_for(&self, baseline: &str) -> io::Result<Vec<&str>> {
+        let candidates: Vec<&str> = self
+            .targetdata
+            .iter()
+            .filter(|(name, _)| name == baseline)
+            .map(|(_, file)| file.as_str())
+            .collect();
+        if !candidates.is_empty() {
+            return Ok(candidates);
+        }
+        let mut available: Vec<&str> = self
+            .targetdata
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        available.dedup();
+        Err(io::Error::other(format!(
+            "no targetdata for the baseline {}, the available ones are {}",
+            baseline,
+            available.join(", ")
+        )))
+    }
+
+    /// Returns the configuration checking one of the output files, as if it were the only one.
+    pub fn for_file(&self, file: &OutputFile) -> RegressionConfig {
+        let mut config = self.clone();
+        config.sourcedata = file.sourcedata.clone();
+        config.targetdata = file.targetdata.clone();
+        config.ignore = file.ignore.clone();
+        config.files.clear();
+        config
+    }
+
+    /// Returns the configuration an action executes the regression with, its overrides
+    /// merged, the configuration itself if the action overrides nothing.
+    pub fn for_action(&self, action: &str) -> &RegressionConfig {
+        self.actions
+            .iter()
+            .find(|overrides| overrides.action == action)
+            .map_or(self, |overrides| &overrides.config)
+    }
+
+    /// Returns the included file or tag a key comes from, `None` if the config defines it.
+    pub fn source_of(&self, key: &str) -> Option<&str> {
+        self.sources
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, file)| file.as_str())
+    }
+}
+
+/// Returns a list of strings, ignoring the items that are not strings.
+fn string_list(value: &Yaml) -> Option<Vec<String>> {
+    value.as_vec().map(|items| {
+        items
+            .iter()
+            .filter_map(|item| item.as_str().map(|s| s.to_string()))
+            .collect()
+    })
+}
+
+/// Reads the targetdata key: a file, or a list of candidate files, of the default
+/// baseline, or a mapping of baseline names to files or lists of candidate files.
+fn baselines(value: &Yaml) -> Option<Vec<(String, String)>> {
+    let candidates = |name: &str, files: &Yaml| match files {
+        Yaml::String(file) => Some(vec![(name.to_string(), file.clone())]),
+        Yaml::Array(files) if !files.is_empty() => files
+            .iter()
+            .map(|file| Some((name.to_string(), file.as_str()?.to_string())))
+            .collect(),
+        _ => None,
+    };
+    match value {
+        Yaml::Hash(variants) if !variants.is_empty() => {
+            let mut baselines = Vec::new();
+            for (name, files) in variants {
+                // Names such as 1.0 are numbers for YAML
+                let name = match name {
+                    Yaml::String(name) | Yaml::Real(name) => name.clone(),
+                    Yaml::Integer(number) => number.to_string(),
+                    _ => return None,
+                };
+                baselines.extend(candidates(&name, files)?);
+            }
+            Some(baselines)
+        }
+        files => candidates(DEFAULT_BASELINE, files),
+    }
+}
+
+/// Parses the first document of a YAML file, `label` names the file in the errors.
+fn parse_document(content: &str, label: &str) -> io::Result<Yaml> {
+    let docs = YamlLoader::load_from_str(content)
+        .map_err(|err| io::Error::other(format!("parsing {} failed: {}", label, err)))?;
+    docs.into_iter()
+        .next()
+        .ok_or_else(|| io::Error::other(format!("{} is empty", label)))
+}
+
+/// Builds a configuration from its YAML document, includes already merged.
+///
+/// # Errors
+///
+/// Returns an error if the document lacks any of the required keys; all the
+/// missing keys are named in the message.
+fn from_document(
+    name: &str,
+    doc: &Yaml,
+    sources: Vec<(String, String)>,
+) -> io::Result<RegressionConfig> {
+    let files = output_files(&doc["files"])?;
+    if !files.is_empty() {
+        if let Some(key) = FILE_KEYS.iter().find(|key| !doc[**key].is_badvalue()) {
+            return Err(io::Error::other(format!(
+                "{} is given with files, which replaces sourcedata, targetdata and ignore, in config.yaml",
+                key
+            )));
+        }
+    }
+    let missing: Vec<&str> = KEYS
+        .iter()
+        .filter(|key| key.required)
+        .map(|key| key.name)
+        .filter(|key| files.is_empty() || !OUTPUT_KEYS.contains(key))
+        .filter(|key| match *key {
+            "targetdata" => baselines(&doc[*key]).is_none(),
+            "regcommand" => doc[*key].as_str().is_none() && doc[*key].as_vec().is_none(),
+            _ => doc[*key].as_str().is_none(),
+        })
+        .collect();
+    if !missing.is_empty() {
+        return Err(io::Error::other(format!(
+            "{} missing or not a string in config.yaml",
+            missing.join(", ")
+        )));
+    }
+    let string = |key: &str| doc[key].as_str().unwrap_or_default().to_string();
+    let timeout = integer(&doc["timeout"], "timeout", "config.yaml", 1)?;
+    let retries = integer(&doc["retries"], "retries", "config.yaml", 0)?;
+    let argv = command_arguments(&doc["regcommand"], "regcommand")?;
+    let smoke_argv = command_arguments(&doc["smokecommand"], "smokecommand")?;
+    let smokecommand = match (&smoke_argv, &doc["smokecommand"]) {
+        (Some(argv), _) => Some(shell_line(argv)),
+        (None, Yaml::String(command)) => Some(command.clone()),
+        (None, Yaml::BadValue | Yaml::Null) => None,
+        _ => {
+            return Err(io::Error::other(
+                "smokecommand is not a string or a list in config.yaml",
+            ))
+        }
+    };
+    let smoke_targetdata = match &doc["smoke_targetdata"] {
+        Yaml::BadValue | Yaml::Null => Vec::new(),
+        value => baselines(value).ok_or_else(|| {
+            io::Error::other(
+                "smoke_targetdata is not a file, a list of files or a mapping of baselines in config.yaml",
+            )
+        })?,
+    };
+    let smoke_sourcedata = doc["smoke_sourcedata"].as_str().map(|s| s.to_string());
+    if smokecommand.is_none() && (smoke_sourcedata.is_some() || !smoke_targetdata.is_empty()) {
+        return Err(io::Error::other(
+            "smoke_sourcedata and smoke_targetdata require smokecommand in config.yaml",
+        ));
+    }
+    let actions = action_overrides(name, doc, &sources)?;
+    // The sidecars hold the provenance of the expected outputs, they are never compared
+    let (sourcedata, targetdata, ignore) = match files.first() {
+        Some(file) => (
+            file.sourcedata.clone(),
+            file.targetdata.clone(),
+            file.ignore.clone(),
+        ),
+        None => (
+            string("sourcedata"),
+            baselines(&doc["targetdata"]).unwrap_or_default(),
+            ignore_patterns(&doc["ignore"], "ignore")?,
+        ),
+    };
+    if let Some((_, file)) = targetdata
+        .iter()
+        .chain(files.iter().flat_map(|file| &file.targetdata))
+        .chain(&smoke_targetdata)
+        .find(|(_, file)| provenance::is_sidecar(file))
+    {
+        return Err(io::Error::other(format!(
+            "targetdata {} ends in {}, which names the provenance sidecars, in config.yaml",
+            file,
+            provenance::SUFFIX
+        )));
+    }
+
+    Ok(RegressionConfig {
+        name: name.to_string(),
+        regbase: string("regbase"),
+        sourcedata,
+        targetdata,
+        regcommand: match &argv {
+            Some(argv) => shell_line(argv),
+            None => string("regcommand"),
+        },
+        argv,
+        smokecommand,
+        smoke_argv,
+        smoke_sourcedata,
+        smoke_targetdata,
+        tags: string_list(&doc["tags"]).unwrap_or_else(|| vec![DEFAULT_TAG.to_string()]),
+        artifacts: string_list(&doc["artifacts"]).unwrap_or_default(),
+        on_fail: doc["on_fail"].as_str().map(|s| s.to_string()),
+        difftool: doc["difftool"].as_str().map(|s| s.to_string()),
+        workdir: doc["workdir"].as_str().map(|s| s.to_string()),
+        examples: doc["examples"].as_str().map(|s| s.to_string()),
+        timeout: timeout.map(|t| t as u64),
+        retries: retries.unwrap_or(0) as u32,
+        max_diff_lines: integer(&doc["max_diff_lines"], "max_diff_lines", "config.yaml", 0)?
+            .unwrap_or(0) as usize,
+        max_output_size: integer(&doc["max_output_size"], "max_output_size", "config.yaml", 1)?
+            .map(|size| size as u64),
+        compare: compare_mode(doc)?,
+        compare_between: section(&doc["compare_between"])?,
+        mask: masks(&doc["mask"])?,
+        ignore,
+        files,
+        normalize_numbers: number_precision(doc)?,
+        store_compressed: match &doc["store_compressed"] {
+            Yaml::BadValue | Yaml::Null => false,
+            Yaml::Boolean(store) => *store,
+            _ => {
+                return Err(io::Error::other(
+                    "store_compressed is not a boolean in config.yaml",
+                ))
+            }
+        },
+        protected: match &doc["protected"] {
+            Yaml::BadValue | Yaml::Null => false,
+            Yaml::Boolean(protected) => *protected,
+            _ => {
+                return Err(io::Error::other(
+                    "protected is not a boolean in config.yaml",
+                ))
+            }
+        },
+        protected_reason: doc["protected_reason"].as_str().map(|s| s.to_string()),
+        priority: integer(&doc["priority"], "priority", "config.yaml", i64::MIN)?.unwrap_or(0),
+        env: environment(&doc["env"], "config.yaml")?,
+        clean_env: match &doc["clean_env"] {
+            Yaml::BadValue | Yaml::Null => false,
+            Yaml::Boolean(clean) => *clean,
+            _ => {
+                return Err(io::Error::other(
+                    "clean_env is not a boolean in config.yaml",
+                ))
+            }
+        },
+        env_passthrough: string_list(&doc["env_passthrough"]).unwrap_or_default(),
+        limits: limits(&doc["limits"])?,
+        frequency: match &doc["frequency"] {
+            Yaml::BadValue | Yaml::Null => Frequency::Always,
+            Yaml::String(text) => Frequency::parse(text)
+                .map_err(|err| io::Error::other(format!("{} in config.yaml", err)))?,
+            _ => return Err(io::Error::other("frequency is not a string in config.yaml")),
+        },
+        sources,
+        actions,
+    })
+}
+
+/// Renders a YAML value on a line, the mappings and lists in flow style.
+fn inline_yaml(value: &Yaml) -> String {
+    match value {
+        Yaml::String(s) => s.clone(),
+        Yaml::Integer(n) => n.to_string(),
+        Yaml::Real(r) => r.clone(),
+        Yaml::Boolean(b) => b.to_string(),
+        Yaml::Array(items) => format!(
+            "[{}]",
+            items.iter().map(inline_yaml).collect::<Vec<_>>().join(", ")
+        ),
+        Yaml::Hash(hash) => format!(
+            "{{{}}}",
+            hash.iter()
+                .map(|(key, value)| format!("{}: {}", inline_yaml(key), inline_yaml(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        _ => "null".to_string(),
+    }
+}
+
+/// Reads the `actions` key, merging the keys each action overrides over the document.
+///
+/// # Errors
+///
+/// Returns an error naming the action if it is unknown, overrides a key that is not in
+/// [`ACTION_KEYS`], or if the merged configuration is invalid.
+fn action_overrides(
+    name: &str,
+    doc: &Yaml,
+    sources: &[(String, String)],
+) -> io::Result<Vec<ActionOverride>> {
+    let actions = match &doc["actions"] {
+        Yaml::BadValue | Yaml::Null => return Ok(Vec::new()),
+        Yaml::Hash(actions) => actions,
+        _ => {
+            return Err(io::Error::other(
+                "actions is not a mapping of actions to keys in config.yaml",
+            ))
+        }
+    };
+    let mut overrides = Vec::new();
+    for (action, keys) in actions {
+        let action = action
+            .as_str()
+            .filter(|action| ACTIONS.contains(action))
+            .ok_or_else(|| {
+                io::Error::other(format!(
+                    "unknown action {:?} in actions in config.yaml, expected one of {}",
+                    action.as_str().unwrap_or("?"),
+                    ACTIONS.join(", ")
+                ))
+            })?;
+        let Yaml::Hash(keys) = keys else {
+            return Err(io::Error::other(format!(
+                "actions.{} is not a mapping of keys in config.yaml",
+                action
+            )));
+        };
+        let mut merged = doc.as_hash().cloned().unwrap_or_default();
+        merged.remove(&Yaml::String("actions".to_string()));
+        let mut action_sources: Vec<(String, String)> = sources.to_vec();
+        let mut rendered = Vec::new();
+        for (key, value) in keys {
+            let key = key
+                .as_str()
+                .filter(|key| ACTION_KEYS.contains(key))
+                .ok_or_else(|| {
+                    io::Error::other(format!(
+                        "actions.{} cannot override {:?} in config.yaml, only {}",
+                        action,
+                        key.as_str().unwrap_or("?"),
+                        ACTION_KEYS.join(", ")
+                    ))
+                })?;
+            merged.insert(Yaml::String(key.to_string()), value.clone());
+            action_sources.retain(|(source, _)| source != key);
+            action_sources.push((key.to_string(), format!("actions.{}", action)));
+            rendered.push((key.to_string(), inline_yaml(value)));
+        }
+        let config = from_document(name, &Yaml::Hash(merged), action_sources)
+            .map_err(|err| io::Error::other(format!("actions.{}: {}", action, err)))?;
+        overrides.push(ActionOverride {
+            action: action.to_string(),
+            keys: rendered,
+            config,
+        });
+    }
+    Ok(overrides)
+}
+
+/// Joins the arguments of a command executed without a shell, quoted for the shell.
+fn shell_line(argv: &[String]) -> String {
+    argv.iter()
+        .map(|arg| summary::shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Reads a command key, regcommand or smokecommand, when it is a list, the arguments of a
+/// command executed without a shell.
+fn command_arguments(value: &Yaml, key: &str) -> io::Result<Option<Vec<String>>> {
+    let Some(items) = value.as_vec() else {
+        return Ok(None);
+    };
+    if items.is_empty() {
+        return Err(io::Error::other(format!(
+            "{} is an empty list in config.yaml",
+            key
+        )));
+    }
+    items
+        .iter()
+        .map(|item| match item {
+            Yaml::String(s) => Ok(s.clone()),
+            Yaml::Integer(n) => Ok(n.to_string()),
+            Yaml::Real(r) => Ok(r.clone()),
+            Yaml::Boolean(b) => Ok(b.to_string()),
+            _ => Err(io::Error::other(format!(
+                "{} arguments must be scalars in config.yaml",
+                key
+            ))),
+        })
+        .collect::<io::Result<Vec<String>>>()
+        .map(Some)
+}
+
+/// Reads the comparison mode, from the `compare` and `min_similarity` keys.
+fn compare_mode(doc: &Yaml) -> io::Result<Compare> {
+    let min_similarity = match &doc["min_similarity"] {
+        Yaml::BadValue | Yaml::Null => None,
+        Yaml::Integer(n) => Some(*n as f64),
+        Yaml::Real(r) => r.parse::<f64>().ok(),
+        _ => {
+            return Err(io::Error::other(
+                "min_similarity is not a number in config.yaml",
+            ))
+        }
+    };
+    match (doc["compare"].as_str(), min_similarity) {
+        (None | Some("exact"), None) => Ok(Compare::Exact),
+        (Some("regex_lines"), None) => Ok(Compare::RegexLines),
+        (Some("contains"), None) => Ok(Compare::Contains),
+        (None | Some("exact" | "regex_lines" | "contains"), Some(_)) => Err(io::Error::other(
+            "min_similarity requires compare: similarity in config.yaml",
+        )),
+        (Some("similarity"), Some(min)) if (0.0..=100.0).contains(&min) => {
+            Ok(Compare::Similarity(min))
+        }
+        (Some("similarity"), Some(_)) => Err(io::Error::other(
+            "min_similarity is not a percentage between 0 and 100 in config.yaml",
+        )),
+        (Some("similarity"), None) => Err(io::Error::other(
+            "compare: similarity requires min_similarity in config.yaml",
+        )),
+        (Some(mode), _) => Err(io::Error::other(format!(
+            "unknown compare mode {:?} in config.yaml, expected {} or {}",
+            mode,
+            COMPARE_MODES[..COMPARE_MODES.len() - 1].join(", "),
+            COMPARE_MODES[COMPARE_MODES.len() - 1]
+        ))),
+    }
+}
+
+/// Reads the compare_between key, a mapping of the start and the end markers.
+fn section(value: &Yaml) -> io::Result<Option<Section>> {
+    match value {
+        Yaml::BadValue | Yaml::Null => Ok(None),
+        _ => match (value["start"].as_str(), value["end"].as_str()) {
+            (Some(start), Some(end)) if !start.is_empty() && !end.is_empty() => {
+                Ok(Some(Section {
+                    start: start.to_string(),
+                    end: end.to_string(),
+                }))
+            }
+            _ => Err(io::Error::other(
+                "compare_between is not a mapping of non-empty start and end markers in config.yaml",
+            )),
+        },
+    }
+}
+
+/// Decimals of the canonical numbers when `number_precision` is not given.
+const DEFAULT_NUMBER_PRECISION: usize = 6;
+
+/// Reads the `normalize_numbers` and `number_precision` keys.
+fn number_precision(doc: &Yaml) -> io::Result<Option<usize>> {
+    let precision = integer(
+        &doc["number_precision"],
+        "number_precision",
+        "config.yaml",
+        0,
+    )?;
+    match (&doc["normalize_numbers"], precision) {
+        (Yaml::Boolean(true), precision) => Ok(Some(
+            precision.map_or(DEFAULT_NUMBER_PRECISION, |p| p as usize),
+        )),
+        (Yaml::Boolean(false) | Yaml::BadValue | Yaml::Null, None) => Ok(None),
+        (Yaml::Boolean(false) | Yaml::BadValue | Yaml::Null, Some(_)) => Err(io::Error::other(
+            "number_precision requires normalize_numbers: true in config.yaml",
+        )),
+        _ => Err(io::Error::other(
+            "normalize_numbers is not a boolean in config.yaml",
+        )),
+    }
+}
+
+/// Rewrites the floating point numbers of a line with a fixed number of decimals.
+///
+/// The tokens are the runs of letters, digits and `.`, `+`, `-` and `_`, and a token is
+/// rewritten if it is a decimal or scientific notation number: integers, versions such as
+/// `1.2.3` and words such as `inf` are left as they are.
+fn canonical_numbers(line: &str, precision: usize) -> String {
+    let is_token = |c: char| c.is_alphanumeric() || matches!(c, '.' | '+' | '-' | '_');
+    let mut canonical = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find(is_token) {
+        canonical.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c: char| !is_token(c)).unwrap_or(rest.len());
+        let token = &rest[..end];
+        let numeric = token.contains(|c: char| c.is_ascii_digit())
+            && token.contains(['.', 'e', 'E'])
+            && token
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'));
+        match token.parse::<f64>() {
+            Ok(value) if numeric => {
+                let formatted = format!("{:.*}", precision, value);
+                // Values rounding to zero lose their sign, -0.000 and 0.000 are the same
+                match formatted.strip_prefix('-') {
+                    Some(positive) if positive.parse::<f64>() == Ok(0.0) => {
+                        canonical.push_str(positive)
+                    }
+                    _ => canonical.push_str(&formatted),
+                }
+            }
+            _ => canonical.push_str(token),
+        }
+        rest = &rest[end..];
+    }
+    canonical.push_str(rest);
+    canonical
+}
+
+/// Reads the mask key, a list of mappings of a pattern and its replacement.
+fn masks(value: &Yaml) -> io::Result<Vec<Mask>> {
+    let items = match value {
+        Yaml::BadValue | Yaml::Null => return Ok(Vec::new()),
+        Yaml::Array(items) => items,
+        _ => return Err(io::Error::other("mask is not a list in config.yaml")),
+    };
+    let mut masks = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let (Some(pattern), Some(replace)) = (item["pattern"].as_str(), item["replace"].as_str())
+        else {
+            return Err(io::Error::other(format!(
+                "mask {} is not a mapping of a pattern and a replace string in config.yaml",
+                index + 1
+            )));
+        };
+        let compiled = Pattern::new(pattern).map_err(|err| {
+            io::Error::other(format!(
+                "mask {}: invalid pattern {:?}: {} in config.yaml",
+                index + 1,
+                pattern,
+                err
+            ))
+        })?;
+        masks.push(Mask {
+            pattern: pattern.to_string(),
+            replace: replace.to_string(),
+            compiled,
+        });
+    }
+    Ok(masks)
+}
+
+/// Reads a list of the regular expressions of ignored lines, `label` names it in the errors.
+fn ignore_patterns(value: &Yaml, label: &str) -> io::Result<Vec<Ignore>> {
+    let items = match value {
+        Yaml::BadValue | Yaml::Null => return Ok(Vec::new()),
+        Yaml::Array(items) => items,
+        _ => {
+            return Err(io::Error::other(format!(
+                "{} is not a list in config.yaml",
+                label
+            )))
+        }
+    };
+    let mut ignore = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let Some(pattern) = item.as_str() else {
+            return Err(io::Error::other(format!(
+                "{} {} is not a string in config.yaml",
+                label,
+                index + 1
+            )));
+        };
+        let compiled = Pattern::new(pattern).map_err(|err| {
+            io::Error::other(format!(
+                "{} {}: invalid pattern {:?}: {} in config.yaml",
+                label,
+                index + 1,
+                pattern,
+                err
+            ))
+        })?;
+        ignore.push(Ignore {
+            pattern: pattern.to_string(),
+            compiled,
+        });
+    }
+    Ok(ignore)
+}
+
+/// Reads the files key, a list of the output files checked instead of sourcedata and
+/// targetdata, each a mapping of its sourcedata, targetdata and ignore.
+fn output_files(value: &Yaml) -> io::Result<Vec<OutputFile>> {
+    let items = match value {
+        Yaml::BadValue | Yaml::Null => return Ok(Vec::new()),
+        Yaml::Array(items) if !items.is_empty() => items,
+        _ => {
+            return Err(io::Error::other(
+                "files is not a list of output files in config.yaml",
+            ))
+        }
+    };
+    let mut files: Vec<OutputFile> = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let number = index + 1;
+        if let Some(key) = item
+            .as_hash()
+            .into_iter()
+            .flat_map(|hash| hash.keys())
+            .find(|key| !key.as_str().is_some_and(|key| FILE_KEYS.contains(&key)))
+        {
+            return Err(io::Error::other(format!(
+                "files {}: unknown key {:?} in config.yaml, expected {}",
+                number,
+                key.as_str().unwrap_or("?"),
+                FILE_KEYS.join(", ")
+            )));
+        }
+        let (Some(sourcedata), Some(targetdata)) =
+            (item["sourcedata"].as_str(), baselines(&item["targetdata"]))
+        else {
+            return Err(io::Error::other(format!(
+                "files {} is not a mapping of a sourcedata and a targetdata in config.yaml",
+                number
+            )));
+        };
+        // Reset would write the outputs of two files to the same expected output
+        if let Some((_, file)) = targetdata.iter().find(|(_, file)| {
+            files
+                .iter()
+                .any(|other| other.targetdata.iter().any(|(_, taken)| taken == file))
+        }) {
+            return Err(io::Error::other(format!(
+                "files {}: targetdata {} is already the one of another file in config.yaml",
+                number, file
+            )));
+        }
+        files.push(OutputFile {
+            sourcedata: sourcedata.to_string(),
+            targetdata,
+            ignore: ignore_patterns(&item["ignore"], &format!("files {}: ignore", number))?,
+        });
+    }
+    Ok(files)
+}
+
+/// Keys of the limits mapping.
+pub const LIMIT_KEYS: [&str; 3] = ["memory_mb", "cpu_seconds", "nice"];
+
+/// Reads the limits key, a mapping of the limited resources to their limits.
+fn limits(value: &Yaml) -> io::Result<Limits> {
+    let hash = match value {
+        Yaml::BadValue | Yaml::Null => return Ok(Limits::default()),
+        Yaml::Hash(hash) => hash,
+        _ => return Err(io::Error::other("limits is not a mapping in config.yaml")),
+    };
+    for key in hash.keys() {
+        let key = key.as_str().unwrap_or_default();
+        if !LIMIT_KEYS.contains(&key) {
+            return Err(io::Error::other(format!(
+                "unknown limit {:?} in config.yaml, expected {}",
+                key,
+                LIMIT_KEYS.join(", ")
+            )));
+        }
+    }
+    let nice = integer(&value["nice"], "nice", "config.yaml", 0)?;
+    if nice.is_some_and(|nice| nice > 19) {
+        return Err(io::Error::other(
+            "nice is not an integer between 0 and 19 in config.yaml",
+        ));
+    }
+    Ok(Limits {
+        memory_mb: integer(&value["memory_mb"], "memory_mb", "config.yaml", 1)?.map(|mb| mb as u64),
+        cpu_seconds: integer(&value["cpu_seconds"], "cpu_seconds", "config.yaml", 1)?
+            .map(|seconds| seconds as u64),
+        nice: nice.map(|nice| nice as i32),
+    })
+}
+
+/// Reads an optional integer option, which must be at least `min`.
+fn integer(value: &Yaml, key: &str, label: &str, min: i64) -> io::Result<Option<i64>> {
+    match value {
+        Yaml::BadValue | Yaml::Null => Ok(None),
+        Yaml::Integer(n) if *n >= min => Ok(Some(*n)),
+        _ if min == i64::MIN => Err(io::Error::other(format!(
+            "{} is not an integer in {}",
+            key, label
+        ))),
+        _ => Err(io::Error::other(format!(
+            "{} is not an integer of at least {} in {}",
+            key, min, label
+        ))),
+    }
+}
+
+/// Reads an optional mapping of environment variables, whose values may be any scalar.
+fn environment(value: &Yaml, label: &str) -> io::Result<Vec<(String, String)>> {
+    let hash = match value {
+        Yaml::BadValue | Yaml::Null => return Ok(Vec::new()),
+        Yaml::Hash(hash) => hash,
+        _ => {
+            return Err(io::Error::other(format!(
+                "env is not a mapping in {}",
+                label
+            )))
+        }
+    };
+    let mut env = Vec::new();
+    for (key, value) in hash {
+        let text = match value {
+            Yaml::String(s) => s.clone(),
+            Yaml::Integer(n) => n.to_string(),
+            Yaml::Real(r) => r.clone(),
+            Yaml::Boolean(b) => b.to_string(),
+            _ => {
+                return Err(io::Error::other(format!(
+                    "env values must be scalars in {}",
+                    label
+                )))
+            }
+        };
+        let key = key
+            .as_str()
+            .ok_or_else(|| io::Error::other(format!("env names must be strings in {}", label)))?;
+        env.push((key.to_string(), text));
+    }
+    Ok(env)
+}
+
+/// Loads the default options of the tags, as (tag, options) pairs in file order.
+///
+/// # Errors
+///
+/// Returns an error if the tags file is not a mapping of tags to mappings of
+/// known options with values of the right type.
+pub fn load_tag_defaults(target: &str) -> io::Result<Vec<(String, Hash)>> {
+    let path = Path::new(target).join(TAGS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)?;
+    // An empty file defines no defaults
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let tags = match parse_document(&content, TAGS_FILE)? {
+        Yaml::Hash(tags) => tags,
+        Yaml::Null => return Ok(Vec::new()),
+        _ => {
+            return Err(io::Error::other(format!(
+                "{} must map tags to options",
+                TAGS_FILE
+            )))
+        }
+    };
+    let mut defaults = Vec::new();
+    for (tag, options) in tags {
+        let tag = tag
+            .as_str()
+            .ok_or_else(|| io::Error::other(format!("{}: tags must be strings", TAGS_FILE)))?
+            .to_string();
+        let label = format!("{} (tag {})", TAGS_FILE, tag);
+        let options = match options {
+            Yaml::Hash(options) => options,
+            _ => {
+                return Err(io::Error::other(format!(
+                    "{}: the options must be a mapping",
+                    label
+                )))
+            }
+        };
+        for key in options.keys() {
+            let known = key.as_str().filter(|key| TAG_OPTIONS.contains(key));
+            if known.is_none() {
+                return Err(io::Error::other(format!(
+                    "{}: unknown option {:?}, expected one of {}",
+                    label,
+                    key.as_str().unwrap_or("?"),
+                    TAG_OPTIONS.join(", ")
+                )));
+            }
+        }
+        let option = |key: &str| {
+            options
+                .get(&Yaml::String(key.to_string()))
+                .cloned()
+                .unwrap_or(Yaml::BadValue)
+        };
+        integer(&option("timeout"), "timeout", &label, 1)?;
+        integer(&option("retries"), "retries", &label, 0)?;
+        integer(&option("priority"), "priority", &label, i64::MIN)?;
+        environment(&option("env"), &label)?;
+        defaults.push((tag, options));
+    }
+    Ok(defaults)
+}
+
+/// Merges the default options of the tags of a configuration beneath it.
+///
+/// # Returns
+///
+/// The merged document and the options whose value comes from a tag.
+fn merge_tag_defaults(doc: Yaml, defaults: &[(String, Hash)]) -> (Yaml, Vec<(String, String)>) {
+    let mut own = match doc {
+        Yaml::Hash(hash) => hash,
+        doc => return (doc, Vec::new()),
+    };
+    let tags = own
+        .get(&Yaml::String("tags".into()))
+        .and_then(string_list)
+        .unwrap_or_else(|| vec![DEFAULT_TAG.to_string()]);
+
+    // Later tags override earlier ones, the configuration overrides them all
+    let mut inherited: Vec<(String, Yaml, String)> = Vec::new();
+    for tag in &tags {
+        let Some((_, options)) = defaults.iter().find(|(name, _)| name == tag) else {
+            continue;
+        };
+        for (key, value) in options {
+            let key = key.as_str().unwrap_or_default().to_string();
+            let source = format!("{}, tag {}", TAGS_FILE, tag);
+            inherited.retain(|(k, _, _)| *k != key);
+            inherited.push((key, value.clone(), source));
+        }
+    }
+
+    let mut sources = Vec::new();
+    for (key, value, source) in inherited {
+        let yaml_key = Yaml::String(key.clone());
+        if !own.contains_key(&yaml_key) {
+            own.insert(yaml_key, value);
+            sources.push((key, source));
+        }
+    }
+    (Yaml::Hash(own), sources)
+}
+
+/// A name standing for several tags, defined in the tag groups file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagGroup {
+    /// Name of the group, requested like a tag
+    pub name: String,
+    /// Tags the group selects
+    pub members: Vec<String>,
+}
+
+/// Loads the tag groups, in file order.
+///
+/// # Errors
+///
+/// Returns an error if the tag groups file is not a mapping of names to lists
+/// of tags, or if groups name each other in a cycle.
+pub fn load_tag_groups(target: &str) -> io::Result<Vec<TagGroup>> {
+    let path = Path::new(target).join(TAG_GROUPS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    parse_tag_groups(&fs::read_to_string(&path)?)
+}
+
+/// Parses the content of the tag groups file.
+fn parse_tag_groups(content: &str) -> io::Result<Vec<TagGroup>> {
+    // An empty file defines no groups
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let entries = match parse_document(content, TAG_GROUPS_FILE)? {
+        Yaml::Hash(entries) => entries,
+        Yaml::Null => return Ok(Vec::new()),
+        _ => {
+            return Err(io::Error::other(format!(
+                "{} must map group names to lists of tags",
+                TAG_GROUPS_FILE
+            )))
+        }
+    };
+    let mut groups = Vec::new();
+    for (name, members) in &entries {
+        let name = name.as_str().ok_or_else(|| {
+            io::Error::other(format!("{}: group names must be strings", TAG_GROUPS_FILE))
+        })?;
+        let members = string_list(members).filter(|members| !members.is_empty());
+        let Some(members) = members else {
+            return Err(io::Error::other(format!(
+                "{}: the group {} must be a non-empty list of tags",
+                TAG_GROUPS_FILE, name
+            )));
+        };
+        groups.push(TagGroup {
+            name: name.to_string(),
+            members,
+        });
+    }
+    for group in &groups {
+        let mut chain = vec![group.name.as_str()];
+        if group_cycle(&groups, &mut chain) {
+            return Err(io::Error::other(format!(
+                "{}: the groups form a cycle: {}",
+                TAG_GROUPS_FILE,
+                chain.join(" -> ")
+            )));
+        }
+    }
+    Ok(groups)
+}
+
+/// Returns true if following the members naming groups from the last group of the chain leads back into it.
+///
+/// On a cycle the chain is left holding its path, ending with the repeated group.
+fn group_cycle<'a>(groups: &'a [TagGroup], chain: &mut Vec<&'a str>) -> bool {
+    let last = chain[chain.len() - 1];
+    let Some(group) = groups.iter().find(|group| group.name == last) else {
+        return false;
+    };
+    for member in &group.members {
+        if !groups.iter().any(|group| group.name == *member) {
+            continue;
+        }
+        let seen = chain.contains(&member.as_str());
+        chain.push(member);
+        if seen || group_cycle(groups, chain) {
+            return true;
+        }
+        chain.pop();
+    }
+    false
+}
+
+/// Expands the requested tags naming a group with the members of the group.
+///
+/// The group name itself is kept, so that a tag shadowed by a group still
+/// selects the regressions carrying it. Members are not expanded further.
+pub fn expand_tags(requested: &[String], groups: &[TagGroup]) -> Vec<String> {
+    let mut expanded: Vec<String> = Vec::new();
+    for tag in requested {
+        let members = groups
+            .iter()
+            .filter(|group| group.name == *tag)
+            .flat_map(|group| group.members.iter());
+        for tag in std::iter::once(tag).chain(members) {
+            if !expanded.contains(tag) {
+                expanded.push(tag.clone());
+            }
+        }
+    }
+    expanded
+}
+
+/// Loads the configuration of a regression of the data directory.
+///
+/// # Errors
+///
+/// Returns an error if the configuration file is missing, unreadable or invalid,
+/// if one of its includes is, escapes the data directory, nests too deeply or
+/// forms a cycle, or if the tags file is invalid.
+pub fn load(target: &str, name: &str) -> io::Result<RegressionConfig> {
+    load_with(target, name, &load_tag_defaults(target)?)
+}
+
+/// Loads the configuration of a regression, with the tag defaults already read.
+fn load_with(
+    target: &str,
+    name: &str,
+    tag_defaults: &[(String, Hash)],
+) -> io::Result<RegressionConfig> {
+    let (doc, sources) = merged_document(target, name, tag_defaults)?;
+    from_document(name, &doc, sources)
+}
+
+/// Reads the document of the configuration of a regression, with its includes and tag
+/// defaults merged, and the file each merged key comes from.
+fn merged_document(
+    target: &str,
+    name: &str,
+    tag_defaults: &[(String, Hash)],
+) -> io::Result<(Yaml, Vec<(String, String)>)> {
+    let path = Path::new(target).join(name).join("config.yaml");
+    if !path.exists() {
+        return Err(io::Error::other("config.yaml not found"));
+    }
+    let doc = parse_document(&fs::read_to_string(&path)?, "config.yaml")?;
+    let root = paths::resolve(Path::new(target))?;
+    let mut chain = vec![paths::resolve(&path)?];
+    let (doc, mut sources) = merge_includes(&root, doc, &mut chain)?;
+    let (doc, tag_sources) = merge_tag_defaults(doc, tag_defaults);
+    sources.extend(tag_sources);
+    Ok((doc, sources))
+}
+
+/// Renders the configuration of a regression as YAML, with its includes and tag defaults
+/// merged, as the regression sees it.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or rendered.
+pub fn merged_yaml(target: &str, name: &str) -> io::Result<String> {
+    let (doc, _) = merged_document(target, name, &load_tag_defaults(target)?)?;
+    let mut rendered = String::new();
+    YamlEmitter::new(&mut rendered)
+        .dump(&doc)
+        .map_err(|err| io::Error::other(format!("rendering config.yaml failed: {:?}", err)))?;
+    Ok(rendered.trim_start_matches("---").trim_start().to_string())
+}
+
+/// Loads the configurations of several regressions in parallel, returning them in the
+/// order of `names`, each with its own error.
+///
+/// The tag defaults are read once for all of them, the error reading them is the error
+/// of every regression.
+pub fn load_many(target: &str, names: &[String]) -> Vec<io::Result<RegressionConfig>> {
+    let tag_defaults = match load_tag_defaults(target) {
+        Ok(tag_defaults) => tag_defaults,
+        Err(err) => {
+            return names
+                .iter()
+                .map(|_| Err(io::Error::other(err.to_string())))
+                .collect()
+        }
+    };
+    let workers = thread::available_parallelism()
+        .map_or(1, |workers| workers.get())
+        .min(names.len())
+        .max(1);
+    // Contiguous chunks keep the order when the results are concatenated
+    let chunks: Vec<&[String]> = names.chunks(names.len().div_ceil(workers).max(1)).collect();
+    thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .iter()
+            .map(|chunk| {
+                let tag_defaults = &tag_defaults;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|name| load_with(target, name, tag_defaults))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .zip(&chunks)
+            .flat_map(|(handle, chunk)| {
+                handle.join().unwrap_or_else(|_| {
+                    chunk
+                        .iter()
+                        .map(|_| Err(io::Error::other("loading thread panicked")))
+                        .collect()
+                })
+            })
+            .collect()
+    })
+}
+
+/// A top-level key of a `config.yaml` with its value, as written.
+struct Block {
+    key: String,
+    /// Comments and blank lines above the key, which move with it
+    leading: String,
+    /// Line of the key and the following lines of its value
+    body: String,
+}
+
+/// The text of a `config.yaml` split by top-level key, to edit it keeping its comments.
+struct Layout {
+    /// Comments before a blank line at the top of the file, and the document start
+    preamble: String,
+    blocks: Vec<Block>,
+    /// Comments and blank lines after the last key
+    trailing: String,
+}
+
+impl Layout {
+    /// Splits the content of a `config.yaml`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the content is not a mapping in block style of plain keys.
+    fn parse(content: &str) -> io::Result<Layout> {
+        let mut preamble = String::new();
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut pending = String::new();
+        for line in content.split_inclusive('\n') {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                pending.push_str(line);
+                // The comments separated from the first key by a blank line are the header
+                if trimmed.is_empty() && blocks.is_empty() {
+                    preamble.push_str(&pending);
+                    pending.clear();
+                }
+            } else if line.starts_with(|c: char| c.is_whitespace() || c == '-') {
+                let Some(block) = blocks.last_mut() else {
+                    return Err(io::Error::other("config.yaml does not start with a key"));
+                };
+                block.body.push_str(&pending);
+                block.body.push_str(line);
+                pending.clear();
+            } else if trimmed == "---" && blocks.is_empty() {
+                preamble.push_str(&pending);
+                preamble.push_str(line);
+                pending.clear();
+            } else if trimmed.starts_with(['{', '[', '?', '"', '\'']) || !trimmed.contains(':') {
+                return Err(io::Error::other(format!(
+                    "config.yaml is not a mapping in block style of plain keys at {:?}",
+                    trimmed
+                )));
+            } else {
+                let key = line
+                    .split(':')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string();
+                blocks.push(Block {
+                    key,
+                    leading: std::mem::take(&mut pending),
+                    body: line.to_string(),
+                });
+            }
+        }
+        Ok(Layout {
+            preamble,
+            blocks,
+            trailing: pending,
+        })
+    }
+
+    /// Joins the parts of the text again.
+    fn render(&self) -> String {
+        let mut content = self.preamble.clone();
+        for block in &self.blocks {
+            content.push_str(&block.leading);
+            content.push_str(&block.body);
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+        }
+        content.push_str(&self.trailing);
+        content
+    }
+}
+
+/// Returns true if a rewritten `config.yaml` holds exactly the expected keys and values.
+fn holds(content: &str, expected: &Hash) -> io::Result<bool> {
+    Ok(match parse_document(content, "config.yaml")? {
+        Yaml::Hash(rewritten) => {
+            rewritten.len() == expected.len()
+                && expected
+                    .iter()
+                    .all(|(key, value)| rewritten.get(key) == Some(value))
+        }
+        _ => false,
+    })
+}
+
+/// Rewrites the content of a `config.yaml` in the canonical form: the keys in the order of
+/// [`KEYS`], then the unknown ones, the `renamed` keys with their current name and, with
+/// `add_tags`, an explicit `tags: [default]` if the configuration has no tags.
+///
+/// Only the text is moved around: the values are kept as written, and the comments and blank
+/// lines go with the key below them, those before a blank line at the top of the file and
+/// after the last key staying in place.
+///
+/// # Errors
+///
+/// Returns an error if the content is not a mapping in block style, or if the rewritten
+/// content does not hold the same keys and values.
+fn canonical(content: &str, add_tags: bool, renamed: &[(&str, &str)]) -> io::Result<String> {
+    let Yaml::Hash(original) = parse_document(content, "config.yaml")? else {
+        return Err(io::Error::other("config.yaml is not a mapping"));
+    };
+    let mut layout = Layout::parse(content)?;
+
+    let mut expected = Hash::new();
+    for (key, value) in original {
+        let name = key.as_str().unwrap_or_default();
+        match renamed.iter().find(|(old, _)| *old == name) {
+            Some((_, new)) => expected.insert(Yaml::String(new.to_string()), value),
+            None => expected.insert(key, value),
+        };
+    }
+    for block in &mut layout.blocks {
+        let Some((_, new)) = renamed.iter().find(|(old, _)| *old == block.key) else {
+            continue;
+        };
+        block.body = format!("{}{}", new, &block.body[block.key.len()..]);
+        block.key = new.to_string();
+    }
+    let tags = Yaml::String("tags".to_string());
+    if add_tags && !expected.contains_key(&tags) {
+        expected.insert(
+            tags,
+            Yaml::Array(vec![Yaml::String(DEFAULT_TAG.to_string())]),
+        );
+        layout.blocks.push(Block {
+            key: "tags".to_string(),
+            leading: String::new(),
+            body: format!("tags: [{}]\n", DEFAULT_TAG),
+        });
+    }
+    layout.blocks.sort_by_key(|block| {
+        KEYS.iter()
+            .position(|key| key.name == block.key)
+            .unwrap_or(KEYS.len())
+    });
+
+    // Whatever the layout, the keys and the values must be the same
+    let canonical = layout.render();
+    if !holds(&canonical, &expected)? {
+        return Err(io::Error::other(
+            "could not rewrite config.yaml in the canonical form, left as is",
+        ));
+    }
+    Ok(canonical)
+}
+
+/// Returns a string as a YAML scalar, plain if it reads back as the same string.
+pub fn yaml_scalar(value: &str) -> String {
+    let plain = YamlLoader::load_from_str(&format!("key: {}", value))
+        .ok()
+        .and_then(|docs| docs.into_iter().next())
+        .is_some_and(|parsed| parsed["key"].as_str() == Some(value));
+    if plain {
+        value.to_string()
+    } else {
+        report::json_string(value)
+    }
+}
+
+/// Sets string keys in the `config.yaml` of a regression, replacing their values or adding
+/// them at the end.
+///
+/// The text of the file is edited rather than rewritten, so that its comments and layout are
+/// kept, and the result is checked by parsing it again.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be read or written, or if the edit does not
+/// set exactly those keys, in which case the configuration is left unchanged.
+pub fn set_keys(target: &str, name: &str, values: &[(&str, &str)]) -> io::Result<()> {
+    let path = Path::new(target).join(name).join("config.yaml");
+    let content = fs::read_to_string(&path)?;
+    let Yaml::Hash(mut expected) = parse_document(&content, "config.yaml")? else {
+        return Err(io::Error::other("config.yaml is not a mapping"));
+    };
+    let mut layout = Layout::parse(&content)?;
+    for (key, value) in values {
+        expected.insert(
+            Yaml::String(key.to_string()),
+            Yaml::String(value.to_string()),
+        );
+        let body = format!("{}: {}\n", key, yaml_scalar(value));
+        match layout.blocks.iter_mut().find(|block| block.key == *key) {
+            Some(block) => block.body = body,
+            None => layout.blocks.push(Block {
+                key: key.to_string(),
+                leading: String::new(),
+                body,
+            }),
+        }
+    }
+    let edited = layout.render();
+    if !holds(&edited, &expected)? {
+        return Err(io::Error::other(format!(
+            "could not set {} in config.yaml, edit it by hand",
+            values
+                .iter()
+                .map(|(key, _)| *key)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+    atomic::write(&path, edited)
+}
+
+/// Returns the content of the `config.yaml` of a regression and its canonical form, see
+/// [`canonical`].
+///
+/// `tags: [default]` is only added if no included file gives the tags.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or rewritten.
+pub fn canonical_config(target: &str, name: &str) -> io::Result<(String, String)> {
+    let config = load(target, name)?;
+    let path = Path::new(target).join(name).join("config.yaml");
+    let content = fs::read_to_string(&path)?;
+    let canonical = canonical(&content, config.source_of("tags").is_none(), &RENAMED_KEYS)?;
+    Ok((content, canonical))
+}
+
+/// Renames an expected output in the `config.yaml` of a regression.
+///
+/// The text of the file is edited rather than rewritten, so that its comments and
+/// layout are kept, and the result is checked by loading it again.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or written, if its
+/// targetdata comes from an included file, or if the edit does not rename exactly
+/// that file, in which case the configuration is left unchanged.
+pub fn rename_targetdata(target: &str, name: &str, old: &str, new: &str) -> io::Result<()> {
+    let config = load(target, name)?;
+    if let Some(origin) = config.source_of("targetdata") {
+        return Err(io::Error::other(format!(
+            "targetdata comes from {}, rename {} to {} there",
+            origin, old, new
+        )));
+    }
+    let mut expected = config;
+    for (_, file) in &mut expected.targetdata {
+        if file == old {
+            *file = new.to_string();
+        }
+    }
+    let path = Path::new(target).join(name).join("config.yaml");
+    let content = fs::read_to_string(&path)?;
+    atomic::write(&path, rename_in_targetdata(&content, old, new))?;
+    if load(target, name).ok().as_ref() != Some(&expected) {
+        atomic::write(&path, content)?;
+        return Err(io::Error::other(format!(
+            "could not rename {} to {} in config.yaml, change its targetdata by hand",
+            old, new
+        )));
+    }
+    Ok(())
+}
+
+/// Replaces the file name `old` by `new` in the value of the targetdata key of a
+/// configuration, where it is a whole file name.
+fn rename_in_targetdata(content: &str, old: &str, new: &str) -> String {
+    let in_name = |c: char| c.is_alphanumeric() || matches!(c, '.' | '_' | '-' | '/' | '\\');
+    let mut in_targetdata = false;
+    let mut renamed = String::new();
+    for line in content.split_inclusive('\n') {
+        // The value goes on until the next top-level key
+        if line.starts_with("targetdata:") {
+            in_targetdata = true;
+        } else if line.starts_with(|c: char| !c.is_whitespace() && c != '-' && c != '#') {
+            in_targetdata = false;
+        }
+        if !in_targetdata {
+            renamed.push_str(line);
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find(old) {
+            let end = start + old.len();
+            let whole = !rest[..start].ends_with(in_name) && !rest[end..].starts_with(in_name);
+            renamed.push_str(&rest[..start]);
+            renamed.push_str(if whole { new } else { old });
+            rest = &rest[end..];
+        }
+        renamed.push_str(rest);
+    }
+    renamed
+}
+
+/// Returns the name of a file of the data directory, relative to it.
+fn label(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// Merges the file included by a document beneath it, recursively.
+///
+/// `chain` holds the resolved files being merged, the document's own last.
+///
+/// # Returns
+///
+/// The merged document and the keys whose value comes from an included file.
+fn merge_includes(
+    root: &Path,
+    doc: Yaml,
+    chain: &mut Vec<PathBuf>,
+) -> io::Result<(Yaml, Vec<(String, String)>)> {
+    let file = chain.last().cloned().unwrap_or_default();
+    let mut own = match doc {
+        Yaml::Hash(hash) => hash,
+        // Not a mapping: nothing to merge, the missing keys are reported later
+        doc => return Ok((doc, Vec::new())),
+    };
+    let include = match own.remove(&Yaml::String(INCLUDE_KEY.to_string())) {
+        None => return Ok((Yaml::Hash(own), Vec::new())),
+        Some(Yaml::String(include)) => include,
+        Some(_) => {
+            return Err(io::Error::other(format!(
+                "include is not a string in {}",
+                label(root, &file)
+            )))
+        }
+    };
+
+    let dir = file.parent().unwrap_or(root);
+    let included = paths::resolve(&dir.join(&include))?;
+    if !included.starts_with(root) {
+        return Err(io::Error::other(format!(
+            "include {} in {} resolves to {}, outside the data directory {}",
+            include,
+            label(root, &file),
+            included.display(),
+            root.display()
+        )));
+    }
+    let included_label = label(root, &included);
+    if chain.contains(&included) {
+        let cycle: Vec<String> = chain
+            .iter()
+            .map(|path| label(root, path))
+            .chain([included_label])
+            .collect();
+        return Err(io::Error::other(format!(
+            "include cycle: {}",
+            cycle.join(" -> ")
+        )));
+    }
+    if chain.len() > MAX_INCLUDE_DEPTH {
+        return Err(io::Error::other(format!(
+            "{} includes {}, but includes may nest only one level",
+            label(root, &file),
+            included_label
+        )));
+    }
+    if !included.is_file() {
+        return Err(io::Error::other(format!(
+            "included file {} not found",
+            included_label
+        )));
+    }
+
+    let base = parse_document(&fs::read_to_string(&included)?, &included_label)?;
+    chain.push(included);
+    let (base, base_sources) = merge_includes(root, base, chain)?;
+    chain.pop();
+    let base: Hash = match base {
+        Yaml::Hash(hash) => hash,
+        _ => {
+            return Err(io::Error::other(format!(
+                "{} is not a mapping",
+                included_label
+            )))
+        }
+    };
+
+    // The document's own keys take precedence over the included ones
+    let mut sources = Vec::new();
+    let mut merged = Hash::new();
+    for (key, value) in base {
+        if own.contains_key(&key) {
+            continue;
+        }
+        if let Some(key_name) = key.as_str() {
+            let source = base_sources
+                .iter()
+                .find(|(k, _)| k == key_name)
+                .map(|(_, file)| file.clone())
+                .unwrap_or_else(|| included_label.clone());
+            sources.push((key_name.to_string(), source));
+        }
+        merged.insert(key, value);
+    }
+    merged.extend(own);
+    Ok((Yaml::Hash(merged), sources))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::DateTime;
+    use tempdir::TempDir;
+
+    /// Parses the content of a `config.yaml` without includes.
+    fn parse(name: &str, content: &str) -> io::Result<RegressionConfig> {
+        from_document(name, &parse_document(content, "config.yaml")?, Vec::new())
+    }
+
+    const MINIMAL: &str =
+        "regbase: blink\nsourcedata: out/bm.sv\ntargetdata: bm.sv\nregcommand: make hdl\n";
+
+    #[test]
+    fn parses_all_the_keys() {
+        let content = format!(
+            "{}tags: [quick, nightly]\nartifacts: [vivado.log, \"out/*.rpt\"]\ndifftool: delta\nworkdir: proj/fpga\nexamples: bmexamples-ml\nclean_env: true\nenv_passthrough: [LM_LICENSE_FILE]\nprotected: true\nprotected_reason: signed off by the hardware team\n",
+            MINIMAL
+        );
+        let config = parse("blink", &content).unwrap();
+        assert_eq!(config.name, "blink");
+        assert_eq!(config.regbase, "blink");
+        assert_eq!(config.sourcedata, "out/bm.sv");
+        assert_eq!(
+            config.targetdata,
+            vec![(DEFAULT_BASELINE.to_string(), "bm.sv".to_string())]
+        );
+        assert_eq!(config.regcommand, "make hdl");
+        assert_eq!(config.tags, vec!["quick", "nightly"]);
+        assert_eq!(config.artifacts, vec!["vivado.log", "out/*.rpt"]);
+        assert_eq!(config.difftool.as_deref(), Some("delta"));
+        assert_eq!(config.workdir.as_deref(), Some("proj/fpga"));
+        assert_eq!(config.examples.as_deref(), Some("bmexamples-ml"));
+        assert!(config.clean_env);
+        assert_eq!(config.env_passthrough, vec!["LM_LICENSE_FILE"]);
+        assert!(config.protected);
+        assert_eq!(
+            config.protected_reason.as_deref(),
+            Some("signed off by the hardware team")
+        );
+    }
+
+    #[test]
+    fn loads_many_configurations_in_order() {
+        let data = TempDir::new("config").unwrap();
+        let names: Vec<String> = (0..50).map(|i| format!("r{}", i)).collect();
+        for name in &names {
+            fs::create_dir(data.path().join(name)).unwrap();
+            let content = if name == "r17" { "regbase: [" } else { MINIMAL };
+            fs::write(data.path().join(name).join("config.yaml"), content).unwrap();
+        }
+        fs::write(data.path().join(TAGS_FILE), "default:\n  timeout: 60\n").unwrap();
+        let loaded = load_many(data.path().to_str().unwrap(), &names);
+        assert_eq!(loaded.len(), names.len());
+        for (name, config) in names.iter().zip(&loaded) {
+            match config {
+                Ok(config) => {
+                    assert_eq!(&config.name, name);
+                    assert_eq!(config.timeout, Some(60));
+                }
+                Err(err) => {
+                    assert_eq!(name, "r17");
+                    assert!(err.to_string().starts_with("parsing config.yaml failed"));
+                }
+            }
+        }
+        assert!(load_many(data.path().to_str().unwrap(), &[]).is_empty());
+    }
+
+    #[test]
+    fn lists_the_parsed_keys() {
+        // The schema is rendered from KEYS, every key read from the documents must be there
+        let source = include_str!("config.rs");
+        let mut parsed: Vec<&str> = ["doc[\"", "string(\""]
+            .iter()
+            .flat_map(|prefix| source.split(prefix).skip(1))
+            .filter_map(|rest| rest.split_once('"').map(|(key, _)| key))
+            .collect();
+        parsed.push(INCLUDE_KEY);
+        for key in &parsed {
+            assert!(
+                KEYS.iter().any(|k| k.name == *key),
+                "{} is not in KEYS",
+                key
+            );
+        }
+        for key in &KEYS {
+            assert!(parsed.contains(&key.name), "{} is not parsed", key.name);
+        }
+    }
+
+    #[test]
+    fn rewrites_the_canonical_form() {
+        let content = "# yaml-language-server: $schema=../config.schema.json\n\nregcommand: make hdl # the HDL only\n# Generated by the Makefile\nsourcedata: out/bm.sv\nregbase: blink\ntargetdata:\n  default: bm.sv\n  # Before the new placer\n  v1: bm-v1.sv\nmaxdiff: 3\n";
+        assert_eq!(
+            canonical(content, true, &[("maxdiff", "max_diff_lines")]).unwrap(),
+            "# yaml-language-server: $schema=../config.schema.json\n\nregbase: blink\n# Generated by the Makefile\nsourcedata: out/bm.sv\ntargetdata:\n  default: bm.sv\n  # Before the new placer\n  v1: bm-v1.sv\nregcommand: make hdl # the HDL only\ntags: [default]\nmax_diff_lines: 3\n"
+        );
+        let canonical_content = canonical(MINIMAL, true, &[]).unwrap();
+        assert_eq!(
+            canonical(&canonical_content, true, &[]).unwrap(),
+            canonical_content
+        );
+        assert_eq!(canonical(MINIMAL, false, &[]).unwrap(), MINIMAL);
+        assert!(canonical("{regbase: blink}\n", true, &[])
+            .unwrap_err()
+            .to_string()
+            .contains("not a mapping in block style"));
+
+        // The tags given by an included file are not overridden
+        let dir = data_dir(&[
+            ("base.yaml", "tags: [nightly]\n"),
+            (
+                "blink/config.yaml",
+                &format!("include: ../base.yaml\n{}", MINIMAL),
+            ),
+        ]);
+        let (content, canonical) = canonical_config(dir.path().to_str().unwrap(), "blink").unwrap();
+        assert_eq!(content, canonical);
+    }
+
+    #[test]
+    fn sets_keys_keeping_the_comments() {
+        let dir = data_dir(&[(
+            "blink/config.yaml",
+            &format!("# Blink on the basys3\n{}tags: [quick]\n", MINIMAL),
+        )]);
+        let target = dir.path().to_str().unwrap();
+        set_keys(
+            target,
+            "blink",
+            &[("regbase", "counter"), ("workdir", "proj: fpga")],
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("blink/config.yaml")).unwrap(),
+            "# Blink on the basys3\nregbase: counter\nsourcedata: out/bm.sv\ntargetdata: bm.sv\nregcommand: make hdl\ntags: [quick]\nworkdir: \"proj: fpga\"\n"
+        );
+        assert_eq!(
+            load(target, "blink").unwrap().workdir.as_deref(),
+            Some("proj: fpga")
+        );
+    }
+
+    #[test]
+    fn defaults_optional_keys() {
+        let config = parse("blink", MINIMAL).unwrap();
+        assert_eq!(config.tags, vec![DEFAULT_TAG]);
+        assert!(config.artifacts.is_empty());
+        assert_eq!(config.difftool, None);
+        assert_eq!(config.workdir, None);
+        assert_eq!(config.examples, None);
+        assert_eq!(config.max_diff_lines, 0);
+        assert!(!config.clean_env);
+        assert!(config.env_passthrough.is_empty());
+        assert!(!config.protected);
+    }
+
+    #[test]
+    fn parses_the_compare_mode() {
+        let similarity = format!("{}compare: similarity\nmin_similarity: 99.5\n", MINIMAL);
+        assert_eq!(
+            parse("blink", &similarity).unwrap().compare,
+            Compare::Similarity(99.5)
+        );
+        assert_eq!(parse("blink", MINIMAL).unwrap().compare, Compare::Exact);
+        let regex_lines = format!("{}compare: regex_lines\n", MINIMAL);
+        assert_eq!(
+            parse("blink", &regex_lines).unwrap().compare,
+            Compare::RegexLines
+        );
+        let contains = format!("{}compare: contains\n", MINIMAL);
+        assert!(parse("blink", &contains).unwrap().compare.is_hand_written());
+        for (content, message) in [
+            (
+                "compare: similarity\n",
+                "compare: similarity requires min_similarity in config.yaml",
+            ),
+            (
+                "min_similarity: 90\n",
+                "min_similarity requires compare: similarity in config.yaml",
+            ),
+            (
+                "compare: similarity\nmin_similarity: 101\n",
+                "min_similarity is not a percentage between 0 and 100 in config.yaml",
+            ),
+            (
+                "compare: fuzzy\n",
+                "unknown compare mode \"fuzzy\" in config.yaml, expected exact, similarity, regex_lines or contains",
+            ),
+        ] {
+            let content = format!("{}{}", MINIMAL, content);
+            assert_eq!(parse("blink", &content).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn extracts_the_compared_section() {
+        let content = format!(
+            "{}compare_between: {{start: \"// BEGIN BM\", end: \"// END BM\"}}\n",
+            MINIMAL
+        );
+        let section = parse("blink", &content).unwrap().compare_between.unwrap();
+        let output =
+            "// vendor preamble\n// BEGIN BM\nmodule bm;\nendmodule\n// END BM\n// BEGIN BM\n";
+        assert_eq!(section.extract(output).unwrap(), "module bm;\nendmodule\n");
+        assert_eq!(
+            section.extract("module bm;\n").unwrap_err(),
+            "start marker \"// BEGIN BM\" not found"
+        );
+        assert_eq!(
+            section.extract("// BEGIN BM\nmodule bm;\n").unwrap_err(),
+            "end marker \"// END BM\" not found after the start marker"
+        );
+        let content = format!("{}compare_between: {{start: \"// BEGIN BM\"}}\n", MINIMAL);
+        assert!(parse("blink", &content).is_err());
+    }
+
+    #[test]
+    fn applies_the_masks_in_order() {
+        let content = format!(
+            "{}mask: [{{pattern: '0x[0-9a-f]{{8}}', replace: ADDR}}, {{pattern: '\\d{{4}}-\\d{{2}}-\\d{{2}}', replace: DATE}}, {{pattern: 'ADDR+', replace: PTR}}]\n",
+            MINIMAL
+        );
+        let config = parse("blink", &content).unwrap();
+        assert_eq!(config.mask.len(), 3);
+        assert_eq!(
+            config.normalize("at 0xdeadbeef on 2024-03-01\nat 0x1234 and 0x0000cafe\n"),
+            "at PTR on DATE\nat 0x1234 and PTR\n"
+        );
+        assert_eq!(config.normalize("no newline"), "no newline");
+        let content = format!(
+            "{}mask: [{{pattern: 'a', replace: b}}, {{pattern: '(x'}}]\n",
+            MINIMAL
+        );
+        assert_eq!(
+            parse("blink", &content).unwrap_err().to_string(),
+            "mask 2 is not a mapping of a pattern and a replace string in config.yaml"
+        );
+        let content = format!("{}mask: [{{pattern: '(x', replace: y}}]\n", MINIMAL);
+        assert!(parse("blink", &content)
+            .unwrap_err()
+            .to_string()
+            .starts_with("mask 1: invalid pattern \"(x\": "));
+    }
+
+    #[test]
+    fn parses_the_output_files() {
+        let content = "regbase: blink\nregcommand: make hdl\nfiles:\n  - {sourcedata: bm.sv, targetdata: bm.sv}\n  - {sourcedata: out/bm.json, targetdata: bm.json, ignore: ['\"version\":', '^# generated']}\n";
+        let config = parse("blink", content).unwrap();
+        assert_eq!(config.files.len(), 2);
+        assert_eq!(config.sourcedata, "bm.sv");
+        assert!(config.ignore.is_empty());
+        let json = config.for_file(&config.files[1]);
+        assert_eq!(json.sourcedata, "out/bm.json");
+        assert_eq!(
+            json.targetdata,
+            vec![(DEFAULT_BASELINE.to_string(), "bm.json".to_string())]
+        );
+        assert_eq!(
+            json.normalize("{\n  \"version\": \"1.2\",\n# generated on monday\n}\n"),
+            "{\n}\n"
+        );
+        assert!(json.files.is_empty());
+
+        let content = format!("{}files:\n  - {{sourcedata: a, targetdata: a}}\n", MINIMAL);
+        assert_eq!(
+            parse("blink", &content).unwrap_err().to_string(),
+            "sourcedata is given with files, which replaces sourcedata, targetdata and ignore, in config.yaml"
+        );
+        let content = "regbase: blink\nregcommand: make\nfiles:\n  - {sourcedata: a, targetdata: x}\n  - {sourcedata: b, targetdata: x}\n";
+        assert!(parse("blink", content).is_err());
+        let content = "regbase: blink\nregcommand: make\nfiles:\n  - {sourcedata: a}\n";
+        assert_eq!(
+            parse("blink", content).unwrap_err().to_string(),
+            "files 1 is not a mapping of a sourcedata and a targetdata in config.yaml"
+        );
+        let content = format!("{}ignore: ['(x']\n", MINIMAL);
+        assert!(parse("blink", &content)
+            .unwrap_err()
+            .to_string()
+            .starts_with("ignore 1: invalid pattern \"(x\": "));
+    }
+
+    #[test]
+    fn normalizes_the_numbers() {
+        let config = parse("blink", &format!("{}normalize_numbers: true\n", MINIMAL)).unwrap();
+        assert_eq!(config.normalize_numbers, Some(6));
+        // Scientific notation
+        assert_eq!(
+            config.normalize("0.5,5.0e-01,-1.25E+2,1e3\n"),
+            "0.500000,0.500000,-125.000000,1000.000000\n"
+        );
+        assert_eq!(config.normalize("x=5.0E-01;"), config.normalize("x=0.5;"));
+        // Integers, versions, words and locale-style separators are left as they are
+        assert_eq!(
+            config.normalize("42,-7,v1.2.3,inf,NaN,1.000.000,0x1f,build_1.5a\n"),
+            "42,-7,v1.2.3,inf,NaN,1.000.000,0x1f,build_1.5a\n"
+        );
+        assert_eq!(config.normalize("1,5;2,25\n"), "1,5;2,25\n");
+        assert_eq!(config.normalize("-0.0000001 -0.0"), "0.000000 0.000000");
+
+        let config = parse(
+            "blink",
+            &format!("{}normalize_numbers: true\nnumber_precision: 2\n", MINIMAL),
+        )
+        .unwrap();
+        assert_eq!(config.normalize("1.005e1 3.14159"), "10.05 3.14");
+        for (content, message) in [
+            (
+                "number_precision: 2\n",
+                "number_precision requires normalize_numbers: true in config.yaml",
+            ),
+            (
+                "normalize_numbers: yes please\n",
+                "normalize_numbers is not a boolean in config.yaml",
+            ),
+        ] {
+            let content = format!("{}{}", MINIMAL, content);
+            assert_eq!(parse("blink", &content).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn parses_argv_commands() {
+        let content = "regbase: blink\nsourcedata: out/bm.sv\ntargetdata: bm.sv\nregcommand: [bondmachine, -build, -o, out file.sv, 42]\n";
+        let config = parse("blink", content).unwrap();
+        assert_eq!(
+            config.argv.unwrap(),
+            ["bondmachine", "-build", "-o", "out file.sv", "42"]
+        );
+        assert_eq!(config.regcommand, "bondmachine -build -o 'out file.sv' 42");
+        assert_eq!(parse("blink", MINIMAL).unwrap().argv, None);
+        let content = "regbase: blink\nsourcedata: out/bm.sv\ntargetdata: bm.sv\nregcommand: []\n";
+        assert_eq!(
+            parse("blink", content).unwrap_err().to_string(),
+            "regcommand is an empty list in config.yaml"
+        );
+    }
+
+    #[test]
+    fn swaps_in_the_smoke_command() {
+        let content = format!(
+            "{}smokecommand: [make, check]\nsmoke_targetdata: {{default: check.txt, v0.9: check-v0.9.txt}}\n",
+            MINIMAL
+        );
+        let smoke = parse("blink", &content).unwrap().smoke().unwrap();
+        assert_eq!(smoke.regcommand, "make check");
+        assert_eq!(smoke.argv.as_ref().unwrap(), &["make", "check"]);
+        assert_eq!(smoke.sourcedata, "out/bm.sv");
+        assert_eq!(smoke.targetdata_for("v0.9").unwrap(), ["check-v0.9.txt"]);
+        assert!(parse("blink", MINIMAL).unwrap().smoke().is_none());
+        let content = format!("{}smoke_sourcedata: check.txt\n", MINIMAL);
+        assert_eq!(
+            parse("blink", &content).unwrap_err().to_string(),
+            "smoke_sourcedata and smoke_targetdata require smokecommand in config.yaml"
+        );
+    }
+
+    #[test]
+    fn merges_the_action_overrides() {
+        let content = format!(
+            "{}timeout: 600\nactions:\n  run: {{timeout: 3600, retries: 2}}\n  diff:\n    compare: similarity\n    min_similarity: 95\n",
+            MINIMAL
+        );
+        let config = parse("blink", &content).unwrap();
+        assert_eq!(config.timeout, Some(600));
+        assert_eq!(config.for_action("run").timeout, Some(3600));
+        assert_eq!(config.for_action("run").retries, 2);
+        assert_eq!(
+            config.for_action("run").source_of("timeout"),
+            Some("actions.run")
+        );
+        assert_eq!(config.for_action("diff").timeout, Some(600));
+        assert_eq!(config.for_action("diff").compare, Compare::Similarity(95.0));
+        assert_eq!(config.for_action("reset"), &config);
+        assert_eq!(
+            config.actions[1].keys,
+            [
+                ("compare".to_string(), "similarity".to_string()),
+                ("min_similarity".to_string(), "95".to_string())
+            ]
+        );
+        for (actions, message) in [
+            (
+                "actions: {check: {timeout: 1}}\n",
+                "unknown action \"check\" in actions in config.yaml, expected one of run, bootstrap, reset, preview, update, diff",
+            ),
+            (
+                "actions: {run: {regcommand: make}}\n",
+                "actions.run cannot override \"regcommand\" in config.yaml, only timeout, retries, max_diff_lines, max_output_size, compare, min_similarity, compare_between, mask, normalize_numbers, number_precision, store_compressed, difftool, env, limits",
+            ),
+            (
+                "actions: {run: {timeout: 0}}\n",
+                "actions.run: timeout is not an integer of at least 1 in config.yaml",
+            ),
+            ("actions: [run]\n", "actions is not a mapping of actions to keys in config.yaml"),
+        ] {
+            let content = format!("{}{}", MINIMAL, actions);
+            assert_eq!(parse("blink", &content).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn parses_the_limits() {
+        let config = parse(
+            "blink",
+            &format!(
+                "{}limits: {{memory_mb: 8192, cpu_seconds: 3600, nice: 10}}\n",
+                MINIMAL
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            config.limits,
+            Limits {
+                memory_mb: Some(8192),
+                cpu_seconds: Some(3600),
+                nice: Some(10),
+            }
+        );
+        assert!(parse("blink", MINIMAL).unwrap().limits.is_empty());
+        for (content, message) in [
+            ("limits: 8192\n", "limits is not a mapping in config.yaml"),
+            (
+                "limits: {memory: 8192}\n",
+                "unknown limit \"memory\" in config.yaml, expected memory_mb, cpu_seconds, nice",
+            ),
+            (
+                "limits: {memory_mb: 0}\n",
+                "memory_mb is not an integer of at least 1 in config.yaml",
+            ),
+            (
+                "limits: {nice: 20}\n",
+                "nice is not an integer between 0 and 19 in config.yaml",
+            ),
+        ] {
+            let content = format!("{}{}", MINIMAL, content);
+            assert_eq!(parse("blink", &content).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn parses_baseline_variants() {
+        let content = "regbase: blink\nsourcedata: out/bm.sv\nregcommand: make hdl\ntargetdata: {v0.9: bm_v09.sv, 1.0: bm_v10.sv}\n";
+        let config = parse("blink", content).unwrap();
+        assert_eq!(config.targetdata_for("v0.9").unwrap(), ["bm_v09.sv"]);
+        assert_eq!(config.targetdata_for("1.0").unwrap(), ["bm_v10.sv"]);
+        assert_eq!(
+            config
+                .targetdata_for(DEFAULT_BASELINE)
+                .unwrap_err()
+                .to_string(),
+            "no targetdata for the baseline default, the available ones are v0.9, 1.0"
+        );
+    }
+
+    #[test]
+    fn parses_candidate_baselines() {
+        let content = "regbase: blink\nsourcedata: out/bm.sv\nregcommand: make hdl\ntargetdata: [bm_a.sv, bm_b.sv]\n";
+        let config = parse("blink", content).unwrap();
+        assert_eq!(
+            config.targetdata_for(DEFAULT_BASELINE).unwrap(),
+            ["bm_a.sv", "bm_b.sv"]
+        );
+        let content = "regbase: blink\nsourcedata: out/bm.sv\nregcommand: make hdl\ntargetdata: {v0.9: bm_v09.sv, 1.0: [bm_a.sv, bm_b.sv]}\n";
+        let config = parse("blink", content).unwrap();
+        assert_eq!(config.targetdata_for("v0.9").unwrap(), ["bm_v09.sv"]);
+        assert_eq!(
+            config.targetdata_for("1.0").unwrap(),
+            ["bm_a.sv", "bm_b.sv"]
+        );
+        let content =
+            "regbase: blink\nsourcedata: out/bm.sv\nregcommand: make hdl\ntargetdata: []\n";
+        assert!(parse("blink", content).is_err());
+    }
+
+    #[test]
+    fn names_all_missing_keys() {
+        let err = parse("blink", "regbase: blink\nregcommand: true\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "sourcedata, targetdata, regcommand missing or not a string in config.yaml"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_and_empty_documents() {
+        assert!(parse("blink", "regbase: [unclosed\n").is_err());
+        assert_eq!(
+            parse("blink", "").unwrap_err().to_string(),
+            "config.yaml is empty"
+        );
+    }
+
+    #[test]
+    fn matches_any_requested_tag() {
+        let config = parse("blink", &format!("{}tags: [quick]\n", MINIMAL)).unwrap();
+        assert!(config.matches_tags(&["default".to_string(), "quick".to_string()], true));
+        assert!(!config.matches_tags(&["default".to_string()], true));
+    }
+
+    #[test]
+    fn expands_tag_groups_once() {
+        let groups = parse_tag_groups("smoke: [quick, basic]\nnightly: [smoke, slow]\n").unwrap();
+        assert_eq!(
+            expand_tags(&["nightly".to_string()], &groups),
+            ["nightly", "smoke", "slow"]
+        );
+        assert_eq!(
+            expand_tags(&["smoke".to_string(), "quick".to_string()], &groups),
+            ["smoke", "quick", "basic"]
+        );
+        let config = parse("blink", &format!("{}tags: [basic]\n", MINIMAL)).unwrap();
+        assert_eq!(
+            config.matching_groups(&["smoke".to_string()], &groups, true),
+            ["smoke"]
+        );
+        assert_eq!(
+            parse_tag_groups("a: [b]\nb: [quick, a]\n")
+                .unwrap_err()
+                .to_string(),
+            "tag-groups.yaml: the groups form a cycle: a -> b -> a"
+        );
+        assert!(parse_tag_groups("smoke: quick\n").is_err());
+    }
+
+    #[test]
+    fn matches_implicit_tags_from_the_path() {
+        let config = parse("fpga/basys3/blink", MINIMAL).unwrap();
+        assert_eq!(config.implicit_tags(), ["fpga", "basys3", "blink"]);
+        assert!(config.matches_tags(&["basys3".to_string()], true));
+        assert!(!config.matches_tags(&["basys3".to_string()], false));
+    }
+
+    #[test]
+    fn loads_from_the_data_directory() {
+        let dir = TempDir::new("bmregression-config").unwrap();
+        let target = dir.path().to_str().unwrap();
+        fs::create_dir(dir.path().join("blink")).unwrap();
+        fs::write(dir.path().join("blink").join("config.yaml"), MINIMAL).unwrap();
+
+        assert_eq!(
+            load(target, "blink").unwrap(),
+            parse("blink", MINIMAL).unwrap()
+        );
+        assert!(load(target, "missing").is_err());
+    }
+
+    /// Creates a data directory with the given files, paths relative to it.
+    fn data_dir(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new("bmregression-config").unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn merges_includes_beneath_the_config() {
+        let dir = data_dir(&[
+            (
+                "blink/config.yaml",
+                "include: ../_common/fpga.yaml\nregbase: blink\nsourcedata: out/bm.sv\ntargetdata: bm.sv\n",
+            ),
+            (
+                "_common/fpga.yaml",
+                "include: board.yaml\nregbase: other\nregcommand: make hdl\n",
+            ),
+            ("_common/board.yaml", "tags: [fpga]\nregcommand: make all\n"),
+        ]);
+        let config = load(dir.path().to_str().unwrap(), "blink").unwrap();
+        assert_eq!(config.regbase, "blink");
+        assert_eq!(config.regcommand, "make hdl");
+        assert_eq!(config.tags, vec!["fpga"]);
+        assert_eq!(config.source_of("regbase"), None);
+        assert_eq!(config.source_of("regcommand"), Some("_common/fpga.yaml"));
+        assert_eq!(config.source_of("tags"), Some("_common/board.yaml"));
+    }
+
+    #[test]
+    fn renames_the_targetdata_in_place() {
+        let content = "# Blink\nregbase: blink\nsourcedata: out.sv\ntargetdata: [out.sv, \"old/out.sv\"]  # both fine\nregcommand: cp out.sv out.sv.bak\n";
+        assert_eq!(
+            rename_in_targetdata(content, "out.sv", "out.sv.gz"),
+            "# Blink\nregbase: blink\nsourcedata: out.sv\ntargetdata: [out.sv.gz, \"old/out.sv\"]  # both fine\nregcommand: cp out.sv out.sv.bak\n"
+        );
+        let content = "targetdata:\n  v1.0: out.sv\n  v0.9:\n    - out.sv\n    - other.sv\nregcommand: true\n";
+        assert_eq!(
+            rename_in_targetdata(content, "out.sv", "out.sv.gz"),
+            "targetdata:\n  v1.0: out.sv.gz\n  v0.9:\n    - out.sv.gz\n    - other.sv\nregcommand: true\n"
+        );
+
+        let dir = data_dir(&[
+            ("blink/config.yaml", MINIMAL),
+            ("shared/config.yaml", "include: ../_common/a.yaml\n"),
+            ("_common/a.yaml", MINIMAL),
+        ]);
+        let target = dir.path().to_str().unwrap();
+        rename_targetdata(target, "blink", "bm.sv", "bm.sv.gz").unwrap();
+        assert_eq!(
+            load(target, "blink")
+                .unwrap()
+                .targetdata_for(DEFAULT_BASELINE)
+                .unwrap(),
+            ["bm.sv.gz"]
+        );
+        assert_eq!(
+            rename_targetdata(target, "shared", "bm.sv", "bm.sv.gz")
+                .unwrap_err()
+                .to_string(),
+            "targetdata comes from _common/a.yaml, rename bm.sv to bm.sv.gz there"
+        );
+    }
+
+    #[test]
+    fn rejects_include_cycles() {
+        let dir = data_dir(&[
+            ("blink/config.yaml", "include: ../_common/a.yaml\n"),
+            ("_common/a.yaml", "include: ../blink/config.yaml\n"),
+        ]);
+        assert_eq!(
+            load(dir.path().to_str().unwrap(), "blink")
+                .unwrap_err()
+                .to_string(),
+            "include cycle: blink/config.yaml -> _common/a.yaml -> blink/config.yaml"
+        );
+    }
+
+    #[test]
+    fn rejects_deep_and_external_includes() {
+        let dir = data_dir(&[
+            ("deep/config.yaml", "include: ../_common/a.yaml\n"),
+            ("_common/a.yaml", "include: b.yaml\n"),
+            ("_common/b.yaml", "include: c.yaml\n"),
+            ("_common/c.yaml", MINIMAL),
+            ("outside/config.yaml", "include: ../../common.yaml\n"),
+        ]);
+        let target = dir.path().to_str().unwrap();
+        assert!(load(target, "deep")
+            .unwrap_err()
+            .to_string()
+            .contains("includes may nest only one level"));
+        assert!(load(target, "outside")
+            .unwrap_err()
+            .to_string()
+            .contains("outside the data directory"));
+    }
+
+    #[test]
+    fn merges_tag_defaults_beneath_the_config() {
+        let dir = data_dir(&[
+            (
+                "blink/config.yaml",
+                &format!("{}tags: [slow, fpga]\nretries: 3\n", MINIMAL),
+            ),
+            (
+                "tags.yaml",
+                "slow:\n  timeout: 7200\n  retries: 1\n  priority: -1\nfpga:\n  priority: 5\n  env:\n    BOARD: zedboard\n",
+            ),
+        ]);
+        let config = load(dir.path().to_str().unwrap(), "blink").unwrap();
+        assert_eq!(config.timeout, Some(7200));
+        assert_eq!(config.retries, 3);
+        assert_eq!(config.priority, 5);
+        assert_eq!(
+            config.env,
+            vec![("BOARD".to_string(), "zedboard".to_string())]
+        );
+        assert_eq!(config.source_of("timeout"), Some("tags.yaml, tag slow"));
+        assert_eq!(config.source_of("priority"), Some("tags.yaml, tag fpga"));
+        assert_eq!(config.source_of("retries"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_tag_defaults() {
+        let unknown = data_dir(&[("tags.yaml", "slow:\n  serial: true\n")]);
+        assert!(load_tag_defaults(unknown.path().to_str().unwrap())
+            .unwrap_err()
+            .to_string()
+            .contains("unknown option \"serial\""));
+        let negative = data_dir(&[("tags.yaml", "slow:\n  retries: -1\n")]);
+        assert_eq!(
+            load_tag_defaults(negative.path().to_str().unwrap())
+                .unwrap_err()
+                .to_string(),
+            "retries is not an integer of at least 0 in tags.yaml (tag slow)"
+        );
+    }
+
+    #[test]
+    fn parses_frequencies() {
+        let dir = data_dir(&[
+            (
+                "daily/config.yaml",
+                &format!(
+                    "{}frequency: daily
+",
+                    MINIMAL
+                ),
+            ),
+            ("always/config.yaml", MINIMAL),
+            (
+                "monthly/config.yaml",
+                &format!(
+                    "{}frequency: monthly
+",
+                    MINIMAL
+                ),
+            ),
+        ]);
+        let target = dir.path().to_str().unwrap();
+        assert_eq!(
+            load(target, "daily").unwrap().frequency,
+            Frequency::Every(std::time::Duration::from_secs(86400))
+        );
+        assert_eq!(load(target, "always").unwrap().frequency, Frequency::Always);
+        assert_eq!(
+            load(target, "monthly").unwrap_err().to_string(),
+            "unknown frequency \"monthly\", expected one of always, hourly, daily, weekly or a cron expression in config.yaml"
+        );
+    }
+
+    #[test]
+    fn evaluates_cron_frequencies() {
+        let dir = data_dir(&[
+            (
+                "nightly/config.yaml",
+                &format!(
+                    "{}frequency: 0 2 * * mon-fri
+",
+                    MINIMAL
+                ),
+            ),
+            (
+                "reversed/config.yaml",
+                &format!(
+                    "{}frequency: 0 2 * * 5-1
+",
+                    MINIMAL
+                ),
+            ),
+            (
+                "never/config.yaml",
+                &format!(
+                    "{}frequency: 0 0 30 feb *
+",
+                    MINIMAL
+                ),
+            ),
+        ]);
+        let target = dir.path().to_str().unwrap();
+        let frequency = load(target, "nightly").unwrap().frequency;
+        let at = |text: &str| DateTime::from_compact(text).unwrap();
+        // Friday 2024-04-05, the next weekday is Monday
+        assert_eq!(
+            frequency.next_fire(at("20240405T020000Z")),
+            Some(at("20240408T020000Z"))
+        );
+        assert!(frequency.is_due(Some(at("20240404T020010Z")), at("20240405T020000Z")));
+        assert!(!frequency.is_due(Some(at("20240405T020010Z")), at("20240407T230000Z")));
+        assert!(load(target, "reversed")
+            .unwrap_err()
+            .to_string()
+            .contains("day of week field \"5-1\" has a reversed range"));
+        assert!(load(target, "never")
+            .unwrap_err()
+            .to_string()
+            .contains("it never matches"));
+    }
+
+    #[test]
+    fn describes_the_age_of_a_run() {
+        let at = |text: &str| DateTime::from_compact(text).unwrap();
+        let now = at("20240405T020000Z");
+        assert_eq!(at("20240405T015930Z").age(now), "just now");
+        assert_eq!(at("20240405T013000Z").age(now), "30m ago");
+        assert_eq!(at("20240404T030000Z").age(now), "23h ago");
+        assert_eq!(at("20240401T020000Z").age(now), "4d ago");
+        let rendered = crate::style::status(crate::style::StatusStyle::Symbols, "never run");
+        assert_eq!(
+            crate::style::width(&rendered),
+            "· never run".chars().count()
+        );
+    }
+}
@@ -0,0 +1,497 @@
+//! Line-based differences between generated and expected outputs.
+//!
+//! Implements the linear space variant of Myers' O(ND) difference algorithm,
+//! so that even large outputs with many differences can be compared without
+//! allocating quadratic tables.
+
+/// A single line of a diff between an old and a new sequence of lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffLine {
+    /// The line is present in both sequences (old index, new index)
+    Equal(usize, usize),
+    /// The line is only present in the old sequence (old index)
+    Removed(usize),
+    /// The line is only present in the new sequence (new index)
+    Added(usize),
+}
+
+/// Summary of the differences between two texts.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DiffStat {
+    /// Lines present only in the new text
+    pub added: usize,
+    /// Lines present only in the old text
+    pub removed: usize,
+}
+
+impl DiffStat {
+    /// Returns true if the two texts are identical.
+    pub fn is_identical(&self) -> bool {
+        self.added == 0 && self.removed == 0
+    }
+
+    /// Returns the percentage of the lines of the two texts they have in common.
+    ///
+    /// Each common line counts once in each text, so that added and removed
+    /// lines weigh the same. Two empty texts are identical, an empty text has
+    /// nothing in common with a non-empty one.
+    pub fn similarity(&self, old_lines: usize, new_lines: usize) -> f64 {
+        let total = old_lines + new_lines;
+        if total == 0 {
+            return 100.0;
+        }
+        let common = total.saturating_sub(self.added + self.removed);
+        100.0 * common as f64 / total as f64
+    }
+}
+
+/// Splits a text into lines, keeping the line terminators.
+///
+/// Keeping the terminators makes a missing final newline a visible difference.
+pub fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Computes the summary of the differences going from `old` to `new`.
+pub fn diff_stat(old: &str, new: &str) -> DiffStat {
+    if old == new {
+        return DiffStat::default();
+    }
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    stat_of(&diff_lines(&old_lines, &new_lines))
+}
+
+/// Counts the added and removed lines of a diff.
+pub fn stat_of(diff: &[DiffLine]) -> DiffStat {
+    let mut stat = DiffStat::default();
+    for line in diff {
+        match line {
+            DiffLine::Added(_) => stat.added += 1,
+            DiffLine::Removed(_) => stat.removed += 1,
+            DiffLine::Equal(_, _) => {}
+        }
+    }
+    stat
+}
+
+/// Computes the line by line diff going from `old` to `new`.
+pub fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffLine> {
+    let mut out = Vec::with_capacity(old.len().max(new.len()));
+    diff_range(old, new, 0, old.len(), 0, new.len(), &mut out);
+    out
+}
+
+/// Recursively diffs `old[a_lo..a_hi]` against `new[b_lo..b_hi]`.
+fn diff_range(
+    old: &[&str],
+    new: &[&str],
+    mut a_lo: usize,
+    mut a_hi: usize,
+    mut b_lo: usize,
+    mut b_hi: usize,
+    out: &mut Vec<DiffLine>,
+) {
+    // Common prefix
+    while a_lo < a_hi && b_lo < b_hi && old[a_lo] == new[b_lo] {
+        out.push(DiffLine::Equal(a_lo, b_lo));
+        a_lo += 1;
+        b_lo += 1;
+    }
+    // Common suffix, emitted after the middle part
+    let mut suffix = 0;
+    while a_lo < a_hi && b_lo < b_hi && old[a_hi - 1] == new[b_hi - 1] {
+        a_hi -= 1;
+        b_hi -= 1;
+        suffix += 1;
+    }
+
+    if a_lo == a_hi {
+        out.extend((b_lo..b_hi).map(DiffLine::Added));
+    } else if b_lo == b_hi {
+        out.extend((a_lo..a_hi).map(DiffLine::Removed));
+    } else {
+        let (x0, y0, x1, y1) = middle_snake(&old[a_lo..a_hi], &new[b_lo..b_hi]);
+        let (x0, y0, x1, y1) = (a_lo + x0, b_lo + y0, a_lo + x1, b_lo + y1);
+        if (x0, y0) == (a_lo, b_lo) && (x1, y1) == (a_lo, b_lo) {
+            // No progress possible, which only happens on a degenerate snake
+            out.extend((a_lo..a_hi).map(DiffLine::Removed));
+            out.extend((b_lo..b_hi).map(DiffLine::Added));
+        } else {
+            diff_range(old, new, a_lo, x0, b_lo, y0, out);
+            out.extend((0..x1 - x0).map(|i| DiffLine::Equal(x0 + i, y0 + i)));
+            diff_range(old, new, x1, a_hi, y1, b_hi, out);
+        }
+    }
+
+    out.extend((0..suffix).map(|i| DiffLine::Equal(a_hi + i, b_hi + i)));
+}
+
+/// Finds the middle snake of the shortest edit script between `a` and `b`.
+///
+/// Returns the start and end points `(x0, y0, x1, y1)` of the snake.
+fn middle_snake(a: &[&str], b: &[&str]) -> (usize, usize, usize, usize) {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2 + 1;
+    let offset = max + 1;
+    let size = (2 * offset + 1) as usize;
+    // Furthest x reached on each diagonal, forward from the start and
+    // backward (measured from the end) towards the start
+    let mut forward = vec![0isize; size];
+    let mut backward = vec![0isize; size];
+
+    for d in 0..=max {
+        let mut k = -d;
+        while k <= d {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && forward[i - 1] < forward[i + 1]) {
+                forward[i + 1]
+            } else {
+                forward[i - 1] + 1
+            };
+            let mut y = x - k;
+            let (sx, sy) = (x, y);
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[i] = x;
+            let kr = delta - k;
+            if odd && kr > -d && kr < d {
+                let xr = backward[(kr + offset) as usize];
+                if x + xr >= n {
+                    return (sx as usize, sy as usize, x as usize, y as usize);
+                }
+            }
+            k += 2;
+        }
+
+        let mut k = -d;
+        while k <= d {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && backward[i - 1] < backward[i + 1]) {
+                backward[i + 1]
+            } else {
+                backward[i - 1] + 1
+            };
+            let mut y = x - k;
+            let (sx, sy) = (x, y);
+            while x < n && y < m && a[(n - x - 1) as usize] == b[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[i] = x;
+            let kf = delta - k;
+            if !odd && kf >= -d && kf <= d {
+                let xf = forward[(kf + offset) as usize];
+                if xf + x >= n {
+                    return (
+                        (n - x) as usize,
+                        (m - y) as usize,
+                        (n - sx) as usize,
+                        (m - sy) as usize,
+                    );
+                }
+            }
+            k += 2;
+        }
+    }
+
+    (0, 0, 0, 0)
+}
+
+/// Number of context lines around each hunk of a unified diff.
+const CONTEXT_LINES: usize = 3;
+
+/// Renders the differences going from `old` to `new` as a unified diff.
+///
+/// Returns an empty string if the texts are identical.
+pub fn unified(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let diff = diff_lines(&old_lines, &new_lines);
+
+    // Position in both texts before each diff line
+    let mut positions = Vec::with_capacity(diff.len() + 1);
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for line in &diff {
+        positions.push((old_pos, new_pos));
+        match line {
+            DiffLine::Equal(_, _) => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            DiffLine::Removed(_) => old_pos += 1,
+            DiffLine::Added(_) => new_pos += 1,
+        }
+    }
+    positions.push((old_pos, new_pos));
+
+    let changes: Vec<usize> = diff
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Equal(_, _)))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    let mut i = 0;
+    while i < changes.len() {
+        // Extend the hunk while the next change is close enough to share context
+        let start = changes[i].saturating_sub(CONTEXT_LINES);
+        let mut last = changes[i];
+        while i + 1 < changes.len() && changes[i + 1] <= last + 2 * CONTEXT_LINES + 1 {
+            i += 1;
+            last = changes[i];
+        }
+        let end = (last + CONTEXT_LINES + 1).min(diff.len());
+        i += 1;
+
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_end - old_start),
+            hunk_range(new_start, new_end - new_start)
+        ));
+        for line in &diff[start..end] {
+            let (marker, text) = match *line {
+                DiffLine::Equal(a, _) => (' ', old_lines[a]),
+                DiffLine::Removed(a) => ('-', old_lines[a]),
+                DiffLine::Added(b) => ('+', new_lines[b]),
+            };
+            out.push(marker);
+            out.push_str(text);
+            if !text.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// Formats the range of a hunk header, following the GNU diff conventions.
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// Where the lines of an expected output were found, in order, in a generated one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Containment {
+    /// For each expected line, the number (from 1) of the output line it was found on, None if missing
+    pub found: Vec<Option<usize>>,
+}
+
+impl Containment {
+    /// Returns true if every expected line was found.
+    pub fn passes(&self) -> bool {
+        self.found.iter().all(Option::is_some)
+    }
+
+    /// Returns the number of the first missing expected line and of the output line
+    /// matching stopped after, 0 if no line was found before it.
+    pub fn first_missing(&self) -> Option<(usize, usize)> {
+        let index = self.found.iter().position(Option::is_none)?;
+        let stopped = self.found[..index].iter().flatten().last().copied();
+        Some((index + 1, stopped.unwrap_or(0)))
+    }
+
+    /// Renders the expected lines annotated with where they were found, the missing ones removed.
+    pub fn annotate(&self, expected: &str) -> String {
+        let mut out = String::new();
+        for (line, found) in expected.lines().zip(&self.found) {
+            match found {
+                Some(number) => out.push_str(&format!("  found at line {}: {}\n", number, line)),
+                None => out.push_str(&format!("- missing: {}\n", line)),
+            }
+        }
+        out
+    }
+}
+
+/// Looks for the lines of `expected` in `output`, in the same relative order, other lines ignored.
+///
+/// Each expected line is searched after the last one found, so that a missing
+/// line does not prevent the following ones from being found.
+pub fn containment(expected: &str, output: &str) -> Containment {
+    let lines: Vec<&str> = output.lines().collect();
+    let mut next = 0;
+    let mut found = Vec::new();
+    for wanted in expected.lines() {
+        match lines[next..].iter().position(|line| *line == wanted) {
+            Some(offset) => {
+                next += offset + 1;
+                found.push(Some(next));
+            }
+            None => found.push(None),
+        }
+    }
+    Containment { found }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the length of the longest common subsequence, by dynamic programming.
+    fn lcs(a: &[&str], b: &[&str]) -> usize {
+        let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in 0..a.len() {
+            for j in 0..b.len() {
+                table[i + 1][j + 1] = if a[i] == b[j] {
+                    table[i][j] + 1
+                } else {
+                    table[i][j + 1].max(table[i + 1][j])
+                };
+            }
+        }
+        table[a.len()][b.len()]
+    }
+
+    /// Checks that a diff goes from `old` to `new` through every line once, in order.
+    fn check(old: &[&str], new: &[&str], diff: &[DiffLine]) {
+        let (mut x, mut y) = (0, 0);
+        for line in diff {
+            match *line {
+                DiffLine::Equal(a, b) => {
+                    assert_eq!((a, b), (x, y));
+                    assert_eq!(old[a], new[b]);
+                    x += 1;
+                    y += 1;
+                }
+                DiffLine::Removed(a) => {
+                    assert_eq!(a, x);
+                    x += 1;
+                }
+                DiffLine::Added(b) => {
+                    assert_eq!(b, y);
+                    y += 1;
+                }
+            }
+        }
+        assert_eq!((x, y), (old.len(), new.len()));
+    }
+
+    #[test]
+    fn diffs_the_lines_with_a_shortest_edit_script() {
+        let diff = diff_lines(&["a", "b", "c"], &["a", "x", "c", "d"]);
+        assert_eq!(
+            diff,
+            [
+                DiffLine::Equal(0, 0),
+                DiffLine::Removed(1),
+                DiffLine::Added(1),
+                DiffLine::Equal(2, 2),
+                DiffLine::Added(3),
+            ]
+        );
+        assert_eq!(diff_lines(&[], &[]), []);
+        assert_eq!(diff_lines(&["a"], &[]), [DiffLine::Removed(0)]);
+        assert_eq!(diff_lines(&[], &["a"]), [DiffLine::Added(0)]);
+
+        // Pseudo-random texts over a small alphabet, with many repeated lines
+        let mut seed: u32 = 12345;
+        let mut next = |bound: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) % bound
+        };
+        let alphabet = ["a", "b", "c", "d"];
+        for _ in 0..300 {
+            let old: Vec<&str> = (0..next(12)).map(|_| alphabet[next(4) as usize]).collect();
+            let new: Vec<&str> = (0..next(12)).map(|_| alphabet[next(4) as usize]).collect();
+            let diff = diff_lines(&old, &new);
+            check(&old, &new, &diff);
+            let stat = stat_of(&diff);
+            let common = lcs(&old, &new);
+            assert_eq!(
+                (stat.added, stat.removed),
+                (new.len() - common, old.len() - common),
+                "{:?} -> {:?}",
+                old,
+                new
+            );
+        }
+    }
+
+    #[test]
+    fn finds_a_middle_snake_on_the_edit_path() {
+        for (a, b) in [
+            (
+                vec!["a", "b", "c", "a", "b", "b", "a"],
+                vec!["c", "b", "a", "b", "a", "c"],
+            ),
+            (vec!["x", "a", "b"], vec!["a", "b", "y"]),
+            (vec!["a"], vec!["b"]),
+            (vec!["a", "b"], vec!["b", "a"]),
+        ] {
+            let (x0, y0, x1, y1) = middle_snake(&a, &b);
+            assert!(
+                x0 <= x1 && x1 <= a.len() && y1 <= b.len(),
+                "{:?} {:?}",
+                a,
+                b
+            );
+            assert_eq!(x1 - x0, y1 - y0);
+            assert_eq!(a[x0..x1], b[y0..y1]);
+            // On a shortest path: the lines around it can still be matched optimally
+            let common = lcs(&a[..x0], &b[..y0]) + (x1 - x0) + lcs(&a[x1..], &b[y1..]);
+            assert_eq!(common, lcs(&a, &b), "{:?} {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn renders_a_unified_diff() {
+        assert_eq!(unified("a\n", "a\n", "old", "new"), "");
+        assert_eq!(
+            unified("a\nb\nc\n", "a\nx\nc\n", "expected", "generated"),
+            "--- expected\n+++ generated\n@@ -1,3 +1,3 @@\n a\n-b\n+x\n c\n"
+        );
+        // Distant changes make separate hunks, with three lines of context
+        let old: String = (1..=20).map(|i| format!("{}\n", i)).collect();
+        let new = old
+            .replace("2\n3\n", "2\nthree\n")
+            .replace("\n18\n", "\neighteen\n");
+        assert_eq!(
+            unified(&old, &new, "a", "b"),
+            "--- a\n+++ b\n@@ -1,6 +1,6 @@\n 1\n 2\n-3\n+three\n 4\n 5\n 6\n@@ -15,6 +15,6 @@\n 15\n 16\n 17\n-18\n+eighteen\n 19\n 20\n"
+        );
+        assert_eq!(
+            unified("a\n", "a\nb", "a", "b"),
+            "--- a\n+++ b\n@@ -1 +1,2 @@\n a\n+b\n\\ No newline at end of file\n"
+        );
+        assert_eq!(
+            unified("", "a\n", "a", "b"),
+            "--- a\n+++ b\n@@ -0,0 +1 @@\n+a\n"
+        );
+    }
+
+    #[test]
+    fn finds_the_expected_lines_in_order() {
+        let output = "start\nstep 1\nnoise\nstep 2\nend\n";
+        let contained = containment("step 1\nstep 2\nend\n", output);
+        assert_eq!(contained.found, [Some(2), Some(4), Some(5)]);
+        assert!(contained.passes());
+        assert_eq!(contained.first_missing(), None);
+
+        // Out of order and missing lines, the following ones are still found
+        let contained = containment("step 2\nstep 1\nmissing\nend\n", output);
+        assert_eq!(contained.found, [Some(4), None, None, Some(5)]);
+        assert!(!contained.passes());
+        assert_eq!(contained.first_missing(), Some((2, 4)));
+        assert_eq!(
+            contained.annotate("step 2\nstep 1\nmissing\nend\n"),
+            "  found at line 4: step 2\n- missing: step 1\n- missing: missing\n  found at line 5: end\n"
+        );
+        assert_eq!(containment("none\n", output).first_missing(), Some((1, 0)));
+    }
+}
@@ -0,0 +1,25 @@
+//! Free space of the filesystem holding the working directory.
+//!
+//! The clones of the repositories and the builds of the examples fill the
+//! working directory, and a run running out of space midway fails with errors
+//! far from their cause, so the space is checked before anything is cloned.
+
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Returns the space in bytes available to an unprivileged user on the filesystem holding `path`.
+///
+/// # Errors
+///
+/// Returns an error if the filesystem cannot be queried, e.g. when the path does not exist.
+pub fn available(path: &Path) -> io::Result<u64> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte"))?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((stats.f_bavail as u64).saturating_mul(stats.f_frsize as u64))
+}
@@ -0,0 +1,378 @@
+//! Canonical description of the effective configurations of the regressions.
+//!
+//! A change to an included file or to a tag default can silently alter many
+//! regressions. `describe --format yaml --canonical` renders what each one
+//! actually runs with, its includes and tag defaults merged and every default
+//! spelled out, as a single document that only depends on the configurations:
+//! the regressions sorted by name, their keys sorted, no timestamps and no
+//! paths outside the data repository. Rendered on the two sides of a change,
+//! the diff of the documents is its real effect.
+
+use crate::config::{self, Compare, RegressionConfig};
+use crate::redact;
+use crate::report;
+
+/// Output format of describe.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    /// Human readable text, with the checks of the checkouts
+    Text,
+    /// A YAML document of the configurations, by regression name
+    Yaml,
+}
+
+/// A value of the rendered document.
+enum Value {
+    Null,
+    Scalar(String),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn string(text: &str) -> Value {
+        Value::Scalar(report::json_string(text))
+    }
+
+    fn optional(text: Option<&str>) -> Value {
+        text.map_or(Value::Null, Value::string)
+    }
+
+    fn number(number: impl ToString) -> Value {
+        Value::Scalar(number.to_string())
+    }
+
+    fn strings<'a>(strings: impl IntoIterator<Item = &'a str>) -> Value {
+        Value::List(strings.into_iter().map(Value::string).collect())
+    }
+
+    /// Returns true if the value is written on the line of its key.
+    fn is_inline(&self) -> bool {
+        match self {
+            Value::Null | Value::Scalar(_) => true,
+            Value::List(items) => items.iter().all(|item| matches!(item, Value::Scalar(_))),
+            Value::Map(entries) => entries.is_empty(),
+        }
+    }
+
+    /// Renders an inline value.
+    fn inline(&self) -> String {
+        match self {
+            Value::Null => "null".to_string(),
+            Value::Scalar(scalar) => scalar.clone(),
+            Value::List(items) => format!(
+                "[{}]",
+                items
+                    .iter()
+                    .map(Value::inline)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Value::Map(_) => "{}".to_string(),
+        }
+    }
+
+    /// Renders the entries of a mapping, indented by `indent` spaces.
+    fn render_map(entries: &[(String, Value)], indent: usize, out: &mut String) {
+        for (key, value) in entries {
+            out.push_str(&format!("{}{}:", " ".repeat(indent), key));
+            value.render_nested(indent, out);
+        }
+    }
+
+    /// Renders the value of a key or of a list item, on its line if inline.
+    fn render_nested(&self, indent: usize, out: &mut String) {
+        if self.is_inline() {
+            out.push_str(&format!(" {}\n", self.inline()));
+            return;
+        }
+        out.push('\n');
+        match self {
+            Value::Map(entries) => Value::render_map(entries, indent + 2, out),
+            Value::List(items) => {
+                for item in items {
+                    out.push_str(&format!("{}-", " ".repeat(indent + 2)));
+                    match item {
+                        // The first entry of a mapping item follows the dash
+                        Value::Map(entries) if !entries.is_empty() => {
+                            let mut rendered = String::new();
+                            Value::render_map(entries, indent + 4, &mut rendered);
+                            out.push(' ');
+                            out.push_str(&rendered[indent + 4..]);
+                        }
+                        item => item.render_nested(indent + 2, out),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the expected output files by baseline, the baselines sorted and the candidates
+/// in their order, which tells the one a reset creates.
+fn baselines(targetdata: &[(String, String)]) -> Value {
+    let mut names: Vec<&str> = targetdata.iter().map(|(name, _)| name.as_str()).collect();
+    names.sort();
+    names.dedup();
+    Value::Map(
+        names
+            .into_iter()
+            .map(|name| {
+                let files = targetdata
+                    .iter()
+                    .filter(|(baseline, _)| baseline == name)
+                    .map(|(_, file)| file.as_str());
+                (report::json_string(name), Value::strings(files))
+            })
+            .collect(),
+    )
+}
+
+/// Returns the patterns of the ignored lines.
+fn patterns(ignore: &[config::Ignore]) -> Value {
+    Value::strings(ignore.iter().map(|ignore| ignore.pattern.as_str()))
+}
+
+/// Returns a command, as the list of its arguments when it runs without a shell.
+fn command(line: Option<&str>, argv: Option<&Vec<String>>) -> Value {
+    match argv {
+        Some(argv) => Value::strings(argv.iter().map(String::as_str)),
+        None => Value::optional(line),
+    }
+}
+
+/// Returns the effective configuration of a regression, every key with its value or its
+/// default, sorted by key.
+fn effective(config: &RegressionConfig) -> Vec<(String, Value)> {
+    let mut tags: Vec<&str> = config.tags.iter().map(String::as_str).collect();
+    tags.sort();
+    tags.dedup();
+    let mut env: Vec<&(String, String)> = config.env.iter().collect();
+    env.sort();
+    let (compare, min_similarity) = match config.compare {
+        Compare::Exact => ("exact", Value::Null),
+        Compare::Similarity(percentage) => ("similarity", Value::number(percentage)),
+        Compare::RegexLines => ("regex_lines", Value::Null),
+        Compare::Contains => ("contains", Value::Null),
+    };
+    let limits = &config.limits;
+    let mut entries = vec![
+        (
+            "artifacts",
+            Value::strings(config.artifacts.iter().map(String::as_str)),
+        ),
+        ("clean_env", Value::number(config.clean_env)),
+        ("compare", Value::string(compare)),
+        (
+            "compare_between",
+            config
+                .compare_between
+                .as_ref()
+                .map_or(Value::Null, |section| {
+                    Value::Map(vec![
+                        ("end".to_string(), Value::string(&section.end)),
+                        ("start".to_string(), Value::string(&section.start)),
+                    ])
+                }),
+        ),
+        ("difftool", Value::optional(config.difftool.as_deref())),
+        (
+            "env",
+            Value::Map(
+                env.into_iter()
+                    .map(|(name, value)| {
+                        (
+                            report::json_string(name),
+                            Value::string(redact::value(name, value)),
+                        )
+                    })
+                    .collect(),
+            ),
+        ),
+        (
+            "env_passthrough",
+            Value::strings(config.env_passthrough.iter().map(String::as_str)),
+        ),
+        ("examples", Value::optional(config.examples.as_deref())),
+        (
+            "files",
+            Value::List(
+                config
+                    .files
+                    .iter()
+                    .map(|file| {
+                        Value::Map(vec![
+                            ("ignore".to_string(), patterns(&file.ignore)),
+                            ("sourcedata".to_string(), Value::string(&file.sourcedata)),
+                            ("targetdata".to_string(), baselines(&file.targetdata)),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        ("frequency", Value::string(&config.frequency.to_string())),
+        ("ignore", patterns(&config.ignore)),
+        (
+            "limits",
+            Value::Map(vec![
+                (
+                    "cpu_seconds".to_string(),
+                    limits.cpu_seconds.map_or(Value::Null, Value::number),
+                ),
+                (
+                    "memory_mb".to_string(),
+                    limits.memory_mb.map_or(Value::Null, Value::number),
+                ),
+                (
+                    "nice".to_string(),
+                    limits.nice.map_or(Value::Null, Value::number),
+                ),
+            ]),
+        ),
+        (
+            "mask",
+            Value::List(
+                config
+                    .mask
+                    .iter()
+                    .map(|mask| {
+                        Value::Map(vec![
+                            ("pattern".to_string(), Value::string(&mask.pattern)),
+                            ("replace".to_string(), Value::string(&mask.replace)),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+        ("max_diff_lines", Value::number(config.max_diff_lines)),
+        (
+            "max_output_size",
+            config.max_output_size.map_or(Value::Null, Value::number),
+        ),
+        ("min_similarity", min_similarity),
+        (
+            "normalize_numbers",
+            Value::number(config.normalize_numbers.is_some()),
+        ),
+        (
+            "number_precision",
+            config.normalize_numbers.map_or(Value::Null, Value::number),
+        ),
+        ("on_fail", Value::optional(config.on_fail.as_deref())),
+        ("priority", Value::number(config.priority)),
+        ("protected", Value::number(config.protected)),
+        (
+            "protected_reason",
+            Value::optional(config.protected_reason.as_deref()),
+        ),
+        ("regbase", Value::string(&config.regbase)),
+        (
+            "regcommand",
+            command(Some(&config.regcommand), config.argv.as_ref()),
+        ),
+        ("retries", Value::number(config.retries)),
+        (
+            "smoke_sourcedata",
+            Value::optional(config.smoke_sourcedata.as_deref()),
+        ),
+        (
+            "smoke_targetdata",
+            if config.smoke_targetdata.is_empty() {
+                Value::Null
+            } else {
+                baselines(&config.smoke_targetdata)
+            },
+        ),
+        (
+            "smokecommand",
+            command(config.smokecommand.as_deref(), config.smoke_argv.as_ref()),
+        ),
+        ("sourcedata", Value::string(&config.sourcedata)),
+        ("store_compressed", Value::number(config.store_compressed)),
+        ("tags", Value::strings(tags)),
+        ("targetdata", baselines(&config.targetdata)),
+        ("timeout", config.timeout.map_or(Value::Null, Value::number)),
+        ("workdir", Value::optional(config.workdir.as_deref())),
+    ];
+    entries.sort_by_key(|(key, _)| *key);
+    let mut entries: Vec<(String, Value)> = entries
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+    // The overridden keys of each action, with their effective value for that action
+    let mut actions: Vec<&config::ActionOverride> = config.actions.iter().collect();
+    actions.sort_by(|a, b| a.action.cmp(&b.action));
+    let actions = actions
+        .into_iter()
+        .map(|overrides| {
+            let overridden = effective(&overrides.config)
+                .into_iter()
+                .filter(|(key, _)| {
+                    overrides
+                        .keys
+                        .iter()
+                        .any(|(overridden, _)| overridden == key)
+                })
+                .collect();
+            (overrides.action.clone(), Value::Map(overridden))
+        })
+        .collect();
+    entries.insert(0, ("actions".to_string(), Value::Map(actions)));
+    entries
+}
+
+/// Renders the canonical document of the effective configurations, the broken ones with
+/// their error, sorted by regression name.
+pub fn canonical(configs: &[RegressionConfig], broken: &[(String, String)]) -> String {
+    let mut regressions: Vec<(String, Value)> = configs
+        .iter()
+        .map(|config| (config.name.clone(), Value::Map(effective(config))))
+        .chain(broken.iter().map(|(name, err)| {
+            (
+                name.clone(),
+                Value::Map(vec![("error".to_string(), Value::string(err))]),
+            )
+        }))
+        .collect();
+    regressions.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let regressions = regressions
+        .into_iter()
+        .map(|(name, value)| (report::json_string(&name), value))
+        .collect::<Vec<_>>();
+    let mut out = String::new();
+    Value::render_map(
+        &[("regressions".to_string(), Value::Map(regressions))],
+        0,
+        &mut out,
+    );
+    out
+}
+
+/// Renders the configurations as loaded, their includes and tag defaults merged, by
+/// regression name in the order given, the broken ones with their error.
+///
+/// # Errors
+///
+/// Returns an error if a configuration cannot be rendered.
+pub fn merged(
+    target: &str,
+    configs: &[RegressionConfig],
+    broken: &[(String, String)],
+) -> std::io::Result<String> {
+    let mut out = String::from("regressions:\n");
+    for config in configs {
+        out.push_str(&format!("  {}:\n", report::json_string(&config.name)));
+        for line in config::merged_yaml(target, &config.name)?.lines() {
+            out.push_str(&format!("    {}\n", line));
+        }
+    }
+    for (name, err) in broken {
+        out.push_str(&format!(
+            "  {}:\n    error: {}\n",
+            report::json_string(name),
+            report::json_string(err)
+        ));
+    }
+    Ok(out)
+}
@@ -0,0 +1,564 @@
+//! Portable archive of the results of a run.
+//!
+//! `bmregression export` packages what a run left in the data directory in a
+//! gzipped tarball, to attach to a bug report or to archive from CI, so that
+//! the failures can be investigated without access to the machine. The archive
+//! holds a single directory, `bmregression-<run-id>/`:
+//!
+//! ```text
+//! index.json              the run, its counts and its regressions, with their files
+//! summary.json            the JSON summary of the run, if it was the last one
+//! results.tsv             the results of the run, as stored in the history
+//! environment.tsv         the environment the run was started in
+//! recorded.json           the results written by the record command, if any
+//! logs/<name>.{out,err}   the captured output of each regression command
+//! diffs/<name>.diff       the differences of each failing regression
+//! outputs/<name>          the generated output of each failing regression
+//! artifacts/<name>/       the collected artifacts, unless left out
+//! ```
+//!
+//! The tarball is created, and read back by `import-baselines`, with the `tar`
+//! command.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tempdir::TempDir;
+
+use crate::history::{self, Entry};
+use crate::json;
+use crate::logs;
+use crate::recorded;
+use crate::report::json_string;
+use crate::summary;
+use crate::timestamp;
+use crate::toolchain;
+
+/// Version of the layout of the archives, in `index.json`.
+const FORMAT: u32 = 1;
+
+/// What has been exported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exported {
+    pub regressions: usize,
+    pub files: usize,
+}
+
+/// Returns the identifier of the most recent run, from the history or else the logs.
+///
+/// # Errors
+///
+/// Returns an error if no run is found.
+pub fn latest_run(target: &str) -> io::Result<String> {
+    let runs = history::load_all(target)?;
+    if let Some(run) = runs.last() {
+        return Ok(run.id.clone());
+    }
+    logs::runs(target)?
+        .pop()
+        .ok_or_else(|| io::Error::other(format!("no run found in {}", target)))
+}
+
+/// Returns the names of the regressions whose logs are in a directory, sorted.
+fn logged_regressions(dir: &Path) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    if dir.is_dir() {
+        list_files(dir, dir, &mut files)?;
+    }
+    Ok(files
+        .iter()
+        .filter_map(|file| file.strip_suffix(".out").map(str::to_string))
+        .collect())
+}
+
+/// Copies a file into the archive if it exists.
+///
+/// # Returns
+///
+/// The path of the file in the archive, if copied.
+fn copy_file(from: &Path, root: &Path, name: &str) -> io::Result<Option<String>> {
+    if !from.is_file() {
+        return Ok(None);
+    }
+    let to = root.join(name);
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(from, &to)
+        .map_err(|err| io::Error::other(format!("copying {}: {}", from.display(), err)))?;
+    Ok(Some(name.to_string()))
+}
+
+/// Returns the paths of the files under a directory, relative to `root`, sorted.
+fn list_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() && !path.is_symlink() {
+            list_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.display().to_string());
+        }
+    }
+    files.sort();
+    Ok(())
+}
+
+/// Renders an optional path of the archive.
+fn json_path(path: &Option<String>) -> String {
+    path.as_deref()
+        .map(json_string)
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// Renders the entry of a regression in `index.json`.
+fn regression_json(
+    name: &str,
+    entry: Option<&Entry>,
+    logs: &[Option<String>; 2],
+    diff: &Option<String>,
+    output: &Option<String>,
+    artifacts: &Option<String>,
+) -> String {
+    let (status, duration, tags, similarity) = match entry {
+        Some(entry) => (
+            json_string(&entry.status),
+            entry.duration.as_millis().to_string(),
+            entry
+                .tags
+                .iter()
+                .map(|tag| json_string(tag))
+                .collect::<Vec<_>>()
+                .join(", "),
+            entry
+                .similarity
+                .map(|similarity| format!("{:.2}", similarity))
+                .unwrap_or_else(|| "null".to_string()),
+        ),
+        // Logged but not recorded, as in an interrupted run
+        None => (
+            "null".to_string(),
+            "null".to_string(),
+            String::new(),
+            "null".to_string(),
+        ),
+    };
+    format!(
+        "    {{\"name\": {}, \"status\": {}, \"duration_ms\": {}, \"similarity\": {}, \"tags\": [{}], \"stdout\": {}, \"stderr\": {}, \"diff\": {}, \"output\": {}, \"artifacts\": {}}}",
+        json_string(name),
+        status,
+        duration,
+        similarity,
+        tags,
+        json_path(&logs[0]),
+        json_path(&logs[1]),
+        json_path(diff),
+        json_path(output),
+        json_path(artifacts)
+    )
+}
+
+/// Lays out the files of a run in `root`, with its `index.json`.
+///
+/// `artifacts_root` is the directory holding the artifacts of all the runs, `None` to
+/// leave them out.
+///
+/// # Errors
+///
+/// Returns an error if the run is neither in the history nor in the logs, or a file
+/// cannot be copied.
+fn stage(
+    target: &str,
+    run_id: &str,
+    artifacts_root: Option<&Path>,
+    root: &Path,
+) -> io::Result<Exported> {
+    let history_dir = history::history_root(target);
+    let log_dir = logs::logs_root(target).join(run_id);
+    let run = match history::load(target, run_id) {
+        Ok(run) => Some(run),
+        Err(_) if log_dir.is_dir() => None,
+        Err(_) => {
+            return Err(io::Error::other(format!(
+                "run {} not found in the history nor in the logs",
+                run_id
+            )))
+        }
+    };
+    fs::create_dir_all(root)?;
+
+    // The summary is only about the last run
+    let summary_path = summary::path(target);
+    let summary_run = fs::read_to_string(&summary_path)
+        .ok()
+        .and_then(|content| json::parse(&content).ok())
+        .and_then(|summary| summary.get("run_id")?.as_str().map(str::to_string));
+    if summary_run.as_deref() == Some(run_id) {
+        copy_file(&summary_path, root, "summary.json")?;
+    }
+    copy_file(
+        &history_dir.join(format!("{}.tsv", run_id)),
+        root,
+        "results.tsv",
+    )?;
+    copy_file(
+        &history_dir.join(format!("{}.env", run_id)),
+        root,
+        "environment.tsv",
+    )?;
+    copy_file(
+        &recorded::results_root(target).join(format!("{}.json", run_id)),
+        root,
+        "recorded.json",
+    )?;
+
+    // The recorded regressions first, in their order, then those only logged
+    let entries = run
+        .as_ref()
+        .map(|run| run.entries.as_slice())
+        .unwrap_or(&[]);
+    let mut names: Vec<String> = entries.iter().map(|entry| entry.name.clone()).collect();
+    for name in logged_regressions(&log_dir)? {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    let mut regressions = Vec::new();
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for name in &names {
+        let entry = entries.iter().find(|entry| &entry.name == name);
+        if let Some(entry) = entry {
+            match counts
+                .iter_mut()
+                .find(|(status, _)| status == &entry.status)
+            {
+                Some((_, count)) => *count += 1,
+                None => counts.push((entry.status.clone(), 1)),
+            }
+        }
+        let logs = [
+            copy_file(
+                &logs::log_file(&log_dir, name, "out"),
+                root,
+                &format!("logs/{}.out", name),
+            )?,
+            copy_file(
+                &logs::log_file(&log_dir, name, "err"),
+                root,
+                &format!("logs/{}.err", name),
+            )?,
+        ];
+        let diff = copy_file(
+            &logs::log_file(&log_dir, name, "diff"),
+            root,
+            &format!("diffs/{}.diff", name),
+        )?;
+        let output = copy_file(
+            &logs::log_file(&log_dir, name, "generated"),
+            root,
+            &format!("outputs/{}", name),
+        )?;
+        let artifacts = match artifacts_root.map(|dir| dir.join(run_id).join(name)) {
+            Some(dir) if dir.is_dir() => {
+                let archived = format!("artifacts/{}", name);
+                crate::repository::copy_dir(&dir, &root.join(&archived))?;
+                Some(archived)
+            }
+            _ => None,
+        };
+        regressions.push(regression_json(
+            name, entry, &logs, &diff, &output, &artifacts,
+        ));
+    }
+
+    let mut files = vec!["index.json".to_string()];
+    list_files(root, root, &mut files)?;
+    files.sort();
+    let counts: Vec<String> = counts
+        .iter()
+        .map(|(status, count)| format!("{}: {}", json_string(status), count))
+        .collect();
+    let listed: Vec<String> = files.iter().map(|file| json_string(file)).collect();
+    let index = format!(
+        "{{\n  \"format\": {},\n  \"run_id\": {},\n  \"exported\": {},\n  \"bmregression\": {},\n  \"artifacts_included\": {},\n  \"counts\": {{{}}},\n  \"regressions\": [\n{}\n  ],\n  \"files\": [{}]\n}}\n",
+        FORMAT,
+        json_string(run_id),
+        json_string(&timestamp::DateTime::now().compact()),
+        json_string(env!("CARGO_PKG_VERSION")),
+        artifacts_root.is_some(),
+        counts.join(", "),
+        regressions.join(",\n"),
+        listed.join(", ")
+    );
+    fs::write(root.join("index.json"), index)?;
+    Ok(Exported {
+        regressions: names.len(),
+        files: files.len(),
+    })
+}
+
+/// Writes the archive of a run to `out`, a gzipped tarball.
+///
+/// `artifacts_root` is the directory holding the artifacts of all the runs, `None` to
+/// leave them out.
+///
+/// # Errors
+///
+/// Returns an error if the run is not found, tar is missing or fails.
+pub fn write(
+    target: &str,
+    run_id: &str,
+    artifacts_root: Option<&Path>,
+    out: &Path,
+) -> io::Result<Exported> {
+    if toolchain::find_on_path("tar").is_none() {
+        return Err(io::Error::other("tar not found, needed for the archive"));
+    }
+    let staging = TempDir::new("bmregression-export")?;
+    let top = format!("bmregression-{}", run_id);
+    let exported = stage(target, run_id, artifacts_root, &staging.path().join(&top))?;
+    let run = Command::new("tar")
+        .arg("-czf")
+        .arg(out)
+        .arg("-C")
+        .arg(staging.path())
+        .arg(&top)
+        .output()?;
+    if !run.status.success() {
+        return Err(io::Error::other(format!(
+            "{}: tar failed: {}",
+            out.display(),
+            String::from_utf8_lossy(&run.stderr).trim()
+        )));
+    }
+    Ok(exported)
+}
+
+/// An archive written by [`write`], or a directory laid out like one, opened to read it.
+#[derive(Debug)]
+pub struct Archive {
+    /// Directory holding `index.json` and the files of the run
+    root: PathBuf,
+    /// Where a tarball is extracted, removed with the archive
+    _extracted: Option<TempDir>,
+}
+
+impl Archive {
+    /// Returns the identifier of the archived run, from `index.json`.
+    pub fn run_id(&self) -> Option<String> {
+        let index = fs::read_to_string(self.root.join("index.json")).ok()?;
+        let index = json::parse(&index).ok()?;
+        index.get("run_id")?.as_str().map(str::to_string)
+    }
+
+    /// Returns the status of each archived regression, from `index.json`, None for those
+    /// logged but not recorded.
+    pub fn statuses(&self) -> Vec<(String, Option<String>)> {
+        let Some(index) = fs::read_to_string(self.root.join("index.json"))
+            .ok()
+            .and_then(|index| json::parse(&index).ok())
+        else {
+            return Vec::new();
+        };
+        let Some(regressions) = index.get("regressions").and_then(|r| r.as_array()) else {
+            return Vec::new();
+        };
+        regressions
+            .iter()
+            .filter_map(|regression| {
+                let name = regression.get("name")?.as_str()?.to_string();
+                let status = regression
+                    .get("status")
+                    .and_then(|status| status.as_str())
+                    .map(str::to_string);
+                Some((name, status))
+            })
+            .collect()
+    }
+
+    /// Returns the environment the run was started in, as (key, value) pairs, empty if the
+    /// archive does not hold it.
+    pub fn environment(&self) -> Vec<(String, String)> {
+        fs::read_to_string(self.root.join("environment.tsv"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// Returns the generated outputs of the archive, by regression name, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the outputs directory cannot be read.
+    pub fn outputs(&self) -> io::Result<Vec<(String, PathBuf)>> {
+        let dir = self.root.join("outputs");
+        let mut files = Vec::new();
+        if dir.is_dir() {
+            list_files(&dir, &dir, &mut files)?;
+        }
+        Ok(files
+            .into_iter()
+            .map(|name| {
+                let path = dir.join(&name);
+                (name, path)
+            })
+            .collect())
+    }
+}
+
+/// Returns the directory of a run in `dir`: `dir` itself if it holds `index.json` or the
+/// outputs, or else its single `bmregression-<run-id>` directory.
+fn run_dir(dir: &Path) -> io::Result<PathBuf> {
+    if dir.join("index.json").is_file() || dir.join("outputs").is_dir() {
+        return Ok(dir.to_path_buf());
+    }
+    let runs: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with("bmregression-"))
+        })
+        .collect();
+    match runs.as_slice() {
+        [run] => Ok(run.clone()),
+        [] => Err(io::Error::other(format!(
+            "{} is not laid out like an archive of bmregression export, it has no index.json nor outputs directory",
+            dir.display()
+        ))),
+        _ => Err(io::Error::other(format!(
+            "{} holds the archives of {} runs, give the directory of one of them",
+            dir.display(),
+            runs.len()
+        ))),
+    }
+}
+
+/// Opens an archive written by [`write`], extracting it, or a directory laid out like one.
+///
+/// # Errors
+///
+/// Returns an error if the archive cannot be extracted, or does not hold a run.
+pub fn open(path: &Path) -> io::Result<Archive> {
+    if path.is_dir() {
+        return Ok(Archive {
+            root: run_dir(path)?,
+            _extracted: None,
+        });
+    }
+    if !path.is_file() {
+        return Err(io::Error::other(format!("{} not found", path.display())));
+    }
+    if toolchain::find_on_path("tar").is_none() {
+        return Err(io::Error::other(
+            "tar not found, needed to extract the archive",
+        ));
+    }
+    let extracted = TempDir::new("bmregression-import")?;
+    let run = Command::new("tar")
+        .arg("-xzf")
+        .arg(path)
+        .arg("-C")
+        .arg(extracted.path())
+        .output()?;
+    if !run.status.success() {
+        return Err(io::Error::other(format!(
+            "{}: tar failed: {}",
+            path.display(),
+            String::from_utf8_lossy(&run.stderr).trim()
+        )));
+    }
+    Ok(Archive {
+        root: run_dir(extracted.path())?,
+        _extracted: Some(extracted),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::Value;
+
+    #[test]
+    fn lays_out_the_files_of_a_run() {
+        let data = TempDir::new("export").unwrap();
+        let target = data.path().to_str().unwrap();
+        let run_id = "20240401T020000Z-42";
+        let history_dir = history::history_root(target);
+        fs::create_dir_all(&history_dir).unwrap();
+        fs::write(
+            history_dir.join(format!("{}.tsv", run_id)),
+            "blink\tpassed\t1200\tdefault\t\ncounter\tfailed\t800\tdefault,quick\t\n",
+        )
+        .unwrap();
+        let log_dir = logs::logs_root(target).join(run_id);
+        for name in ["blink", "counter", "interrupted"] {
+            logs::write(&log_dir, name, b"out\n", b"").unwrap();
+        }
+        logs::write_diff(&log_dir, "counter", "-a\n+b\n").unwrap();
+        let generated = data.path().join("out.txt");
+        fs::write(&generated, "b\n").unwrap();
+        logs::write_generated(&log_dir, "counter", &generated).unwrap();
+        let artifacts = data.path().join("artifacts");
+        fs::create_dir_all(artifacts.join(run_id).join("counter")).unwrap();
+        fs::write(artifacts.join(run_id).join("counter/sim.vcd"), "").unwrap();
+
+        let root = data.path().join("staged");
+        let exported = stage(target, run_id, Some(&artifacts), &root).unwrap();
+        assert_eq!(exported.regressions, 3);
+        for file in [
+            "results.tsv",
+            "logs/counter.err",
+            "diffs/counter.diff",
+            "outputs/counter",
+            "artifacts/counter/sim.vcd",
+        ] {
+            assert!(root.join(file).is_file(), "{} not staged", file);
+        }
+        let index = json::parse(&fs::read_to_string(root.join("index.json")).unwrap()).unwrap();
+        assert_eq!(
+            index.get("counts"),
+            Some(&Value::Object(vec![
+                ("passed".to_string(), Value::Number(1.0)),
+                ("failed".to_string(), Value::Number(1.0))
+            ]))
+        );
+        let regressions = index.get("regressions").unwrap().as_array().unwrap();
+        assert_eq!(
+            regressions[1].get("diff").unwrap().as_str(),
+            Some("diffs/counter.diff")
+        );
+        assert_eq!(regressions[2].get("status"), Some(&Value::Null));
+        assert_eq!(regressions[0].get("output"), Some(&Value::Null));
+        assert_eq!(
+            index.get("files").unwrap().as_array().unwrap().len(),
+            exported.files
+        );
+
+        // Read back, as import-baselines does
+        let archive = open(data.path()).unwrap_err();
+        assert!(archive.to_string().contains("not laid out like an archive"));
+        let archive = open(&root).unwrap();
+        assert_eq!(archive.run_id().as_deref(), Some(run_id));
+        let outputs = archive.outputs().unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].0, "counter");
+        assert_eq!(fs::read_to_string(&outputs[0].1).unwrap(), "b\n");
+        assert_eq!(
+            archive.statuses()[1],
+            ("counter".to_string(), Some("failed".to_string()))
+        );
+
+        // Without the artifacts
+        let root = data.path().join("without");
+        stage(target, run_id, None, &root).unwrap();
+        assert!(!root.join("artifacts").exists());
+        assert!(stage(target, "20240402T020000Z-43", None, &root).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Byte comparison of two files, without loading them.
+//!
+//! Most regressions pass, and their outputs can be hundreds of megabytes: reading
+//! both as strings and splitting them in lines only to find them identical costs
+//! more than the rest of the comparison. The files are compared first by size,
+//! then in fixed-size chunks stopping at the first difference, and only the
+//! outputs that differ are read as text and diffed.
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Size of the chunks read from each file.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Fills a buffer from a reader, returning how much was read, less than the buffer at the end.
+fn fill(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+/// Returns true if two files have the same content.
+///
+/// # Errors
+///
+/// Returns an error if either file cannot be read.
+pub fn identical(left: &Path, right: &Path) -> io::Result<bool> {
+    if fs::metadata(left)?.len() != fs::metadata(right)?.len() {
+        return Ok(false);
+    }
+    let (mut left, mut right) = (File::open(left)?, File::open(right)?);
+    let mut left_chunk = vec![0; CHUNK_SIZE];
+    let mut right_chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let read = fill(&mut left, &mut left_chunk)?;
+        // The files can still change size while they are read
+        if fill(&mut right, &mut right_chunk)? != read || left_chunk[..read] != right_chunk[..read]
+        {
+            return Ok(false);
+        }
+        if read < CHUNK_SIZE {
+            return Ok(true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use tempdir::TempDir;
+
+    #[test]
+    fn finds_the_first_difference() {
+        let dir = TempDir::new("filecmp").unwrap();
+        let path = |name: &str| dir.path().join(name);
+        let content: Vec<u8> = (0..CHUNK_SIZE * 2 + 7).map(|i| (i % 251) as u8).collect();
+        fs::write(path("a"), &content).unwrap();
+        fs::write(path("b"), &content).unwrap();
+        assert!(identical(&path("a"), &path("b")).unwrap());
+
+        let mut changed = content.clone();
+        changed[CHUNK_SIZE + 3] ^= 1;
+        fs::write(path("b"), &changed).unwrap();
+        assert!(!identical(&path("a"), &path("b")).unwrap());
+        fs::write(path("b"), &content[..content.len() - 1]).unwrap();
+        assert!(!identical(&path("a"), &path("b")).unwrap());
+
+        fs::write(path("a"), "").unwrap();
+        fs::write(path("b"), "").unwrap();
+        assert!(identical(&path("a"), &path("b")).unwrap());
+        assert!(identical(&path("a"), &path("missing")).is_err());
+    }
+
+    /// Times the comparison of two identical 512 MiB outputs against reading them as strings,
+    /// as the comparison did before, run with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn compares_large_outputs_faster_than_reading_them() {
+        let dir = TempDir::new("filecmp").unwrap();
+        let line = "assign out[17] = in[3] & enable; // generated by bondmachine\n";
+        let content = line.repeat((512 << 20) / line.len());
+        for name in ["expected.sv", "generated.sv"] {
+            fs::write(dir.path().join(name), &content).unwrap();
+        }
+        drop(content);
+        let (expected, generated) = (
+            dir.path().join("expected.sv"),
+            dir.path().join("generated.sv"),
+        );
+
+        let start = Instant::now();
+        let old = fs::read_to_string(&expected).unwrap();
+        let new = fs::read_to_string(&generated).unwrap();
+        assert!(crate::diff::diff_stat(&old, &new).is_identical());
+        let as_strings = start.elapsed();
+        drop((old, new));
+
+        let start = Instant::now();
+        assert!(identical(&expected, &generated).unwrap());
+        let chunked = start.elapsed();
+
+        println!(
+            "512 MiB: {:?} read as strings, {:?} compared in chunks",
+            as_strings, chunked
+        );
+        assert!(chunked < as_strings);
+    }
+}
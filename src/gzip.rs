@@ -0,0 +1,129 @@
+//! Gzip-compressed expected and generated outputs.
+//!
+//! Large simulation dumps are stored compressed in the data repository. An
+//! output whose file name ends in `.gz` is decompressed when it is read and
+//! compressed when it is written, through the `gzip` command, in the same way
+//! as git is run for the history, rather than with a compression library.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::atomic;
+use crate::toolchain;
+
+/// Extension of the compressed files.
+pub const EXTENSION: &str = ".gz";
+
+/// Returns true if the file is gzip-compressed, judging by its extension.
+pub fn is_compressed(path: impl AsRef<Path>) -> bool {
+    path.as_ref().to_string_lossy().ends_with(EXTENSION)
+}
+
+/// Runs gzip with the given arguments, its output written to `output`.
+///
+/// # Returns
+///
+/// The output of gzip, when `output` is piped.
+fn gzip(args: &[&str], path: &Path, output: Stdio) -> io::Result<Vec<u8>> {
+    if toolchain::find_on_path("gzip").is_none() {
+        return Err(io::Error::other(format!(
+            "gzip not found, needed for {}",
+            path.display()
+        )));
+    }
+    let run = Command::new("gzip")
+        .args(args)
+        .arg(path)
+        .stdout(output)
+        .stderr(Stdio::piped())
+        .output()?;
+    if !run.status.success() {
+        // gzip names the file itself, as in "gzip: out.gz: unexpected end of file"
+        let stderr = String::from_utf8_lossy(&run.stderr);
+        let prefix = format!("gzip: {}: ", path.display());
+        return Err(io::Error::other(format!(
+            "{}: gzip failed: {}",
+            path.display(),
+            stderr.trim().trim_start_matches(&prefix)
+        )));
+    }
+    Ok(run.stdout)
+}
+
+/// Reads a file, decompressing it if it is compressed.
+///
+/// # Errors
+///
+/// Returns an error naming the file if it cannot be read, is a corrupt archive or is not
+/// valid UTF-8 once decompressed.
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    let path = path.as_ref();
+    if !is_compressed(path) {
+        return fs::read_to_string(path);
+    }
+    let content = gzip(&["-dc"], path, Stdio::piped())?;
+    String::from_utf8(content).map_err(|err| {
+        io::Error::other(format!(
+            "{}: the decompressed content is not text: {}",
+            path.display(),
+            err
+        ))
+    })
+}
+
+/// Returns the size of a file once compressed.
+///
+/// # Errors
+///
+/// Returns an error naming the file if it cannot be compressed.
+pub fn compressed_size(path: impl AsRef<Path>) -> io::Result<u64> {
+    gzip(&["-cn"], path.as_ref(), Stdio::piped()).map(|content| content.len() as u64)
+}
+
+/// Copies a file, compressing or decompressing it when only one of the two is compressed.
+///
+/// The copy replaces `dest` atomically, so that an interrupted or failed copy leaves `dest`
+/// as it was, never half written.
+///
+/// # Errors
+///
+/// Returns an error naming the file if it cannot be read, written, compressed or decompressed.
+pub fn copy(source: impl AsRef<Path>, dest: impl AsRef<Path>) -> io::Result<()> {
+    let (source, dest) = (source.as_ref(), dest.as_ref());
+    atomic::write_with(dest, |file| {
+        match (is_compressed(source), is_compressed(dest)) {
+            (false, true) => gzip(&["-cn"], source, Stdio::from(file.try_clone()?)).map(|_| ()),
+            (true, false) => gzip(&["-dc"], source, Stdio::from(file.try_clone()?)).map(|_| ()),
+            _ => io::copy(&mut File::open(source)?, file).map(|_| ()),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn replaces_the_destination_only_once_copied() {
+        let dir = TempDir::new("gzip").unwrap();
+        let dest = dir.path().join("out.txt");
+        fs::write(&dest, "old\n").unwrap();
+        fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+        copy(dir.path().join("new.txt"), &dest).unwrap();
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "new\n");
+
+        // A corrupt archive fails to decompress, the previous content stays
+        fs::write(dir.path().join("corrupt.txt.gz"), "not gzip").unwrap();
+        assert!(copy(dir.path().join("corrupt.txt.gz"), &dest).is_err());
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "new\n");
+        let mut files: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["corrupt.txt.gz", "new.txt", "out.txt"]);
+    }
+}
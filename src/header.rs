@@ -0,0 +1,153 @@
+//! Header printed by a run before it executes anything.
+//!
+//! A run selecting the wrong regressions, through a tag default or a name
+//! matching more than meant, is otherwise only noticed once it is done. The
+//! header states what was selected and why: the name, tag and regbase filters
+//! with the matching mode, the count of the selected regressions, the checkouts
+//! they run against and the settings in effect. The same facts are recorded with the
+//! options of the run, in the metadata of its reports.
+
+/// What a run selected, and what it runs against.
+#[derive(Debug, Clone, Default)]
+pub struct RunHeader {
+    /// Identifier of the run, naming its logs, artifacts and reports
+    pub run_id: String,
+    /// Names requested on the command line, all the regressions if empty
+    pub names: Vec<String>,
+    /// The names select the regression with exactly that name, not those containing it
+    pub exact: bool,
+    /// Requested tags, before expanding the tag groups
+    pub tags: Vec<String>,
+    /// Expression over the tags selecting the regressions instead of the tags
+    pub tag_expr: Option<String>,
+    /// Pattern the regbase of the selected regressions matches
+    pub regbase: Option<String>,
+    /// Names of the selected regressions, in execution order
+    pub selected: Vec<String>,
+    /// Checkouts, as (repository, directory, commit) triples, the commit None outside git
+    pub checkouts: Vec<(String, String, Option<String>)>,
+    /// Settings in effect, as (setting, value) pairs
+    pub settings: Vec<(String, String)>,
+}
+
+impl RunHeader {
+    /// Describes the name filters and how they match.
+    fn name_filter(&self) -> String {
+        if self.names.is_empty() {
+            return "any name".to_string();
+        }
+        let mode = if self.exact { "exact" } else { "substring" };
+        format!("names {} ({} match)", self.names.join(", "), mode)
+    }
+
+    /// Renders the header, with the names of the selected regressions in debug mode.
+    pub fn render(&self, debug: bool) -> String {
+        let mut tags = match &self.tag_expr {
+            Some(expr) => format!("tag expression {}", expr),
+            None => format!("tags {}", self.tags.join(",")),
+        };
+        if let Some(regbase) = &self.regbase {
+            tags.push_str(&format!(", regbase {}", regbase));
+        }
+        let mut header = format!(
+            "Selected {} regressions: {}, {}\n",
+            self.selected.len(),
+            self.name_filter(),
+            tags
+        );
+        header.push_str(&format!("  run id: {}\n", self.run_id));
+        for (repository, dir, commit) in &self.checkouts {
+            let commit = match commit {
+                Some(commit) => format!("at {}", &commit[..commit.len().min(12)]),
+                None => "not a git checkout".to_string(),
+            };
+            header.push_str(&format!("  {}: {} {}\n", repository, dir, commit));
+        }
+        let settings: Vec<String> = self
+            .settings
+            .iter()
+            .map(|(setting, value)| format!("{}: {}", setting, value))
+            .collect();
+        header.push_str(&format!("  {}\n", settings.join(", ")));
+        if debug {
+            header.push_str(&format!("  regressions: {}\n", self.selected.join(", ")));
+        }
+        header
+    }
+
+    /// Returns the facts of the header recorded with the options of the run.
+    pub fn options(&self) -> Vec<(String, String)> {
+        let mut options = vec![
+            (
+                "name matching".to_string(),
+                if self.exact { "exact" } else { "substring" }.to_string(),
+            ),
+            ("selected".to_string(), self.selected.len().to_string()),
+        ];
+        if let Some(expr) = &self.tag_expr {
+            options.push(("tag expression".to_string(), expr.clone()));
+        }
+        if let Some(regbase) = &self.regbase {
+            options.push(("regbase".to_string(), regbase.clone()));
+        }
+        options.extend(self.settings.iter().cloned());
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn states_what_was_selected_and_why() {
+        let mut header = RunHeader {
+            run_id: "20240401T020000Z-3fa9c1".to_string(),
+            names: vec!["blink".to_string(), "count".to_string()],
+            exact: false,
+            tags: vec!["default".to_string()],
+            tag_expr: None,
+            regbase: None,
+            selected: vec!["basys3_blink".to_string(), "basys3_counter".to_string()],
+            checkouts: vec![
+                (
+                    "examples".to_string(),
+                    "/tmp/examples".to_string(),
+                    Some("0123456789abcdef0123".to_string()),
+                ),
+                ("regression data".to_string(), "/srv/data".to_string(), None),
+            ],
+            settings: vec![
+                ("jobs".to_string(), "1".to_string()),
+                ("timeout".to_string(), "none".to_string()),
+            ],
+        };
+        assert_eq!(
+            header.render(false),
+            "Selected 2 regressions: names blink, count (substring match), tags default\n  run id: 20240401T020000Z-3fa9c1\n  examples: /tmp/examples at 0123456789ab\n  regression data: /srv/data not a git checkout\n  jobs: 1, timeout: none\n"
+        );
+        assert!(header
+            .render(true)
+            .ends_with("\n  regressions: basys3_blink, basys3_counter\n"));
+        assert_eq!(
+            header.options()[..2],
+            [
+                ("name matching".to_string(), "substring".to_string()),
+                ("selected".to_string(), "2".to_string())
+            ]
+        );
+
+        header.names.clear();
+        assert!(header
+            .render(false)
+            .starts_with("Selected 2 regressions: any name, tags default\n"));
+
+        header.regbase = Some("blink".to_string());
+        assert!(header
+            .render(false)
+            .starts_with("Selected 2 regressions: any name, tags default, regbase blink\n"));
+        assert!(header
+            .options()
+            .contains(&("regbase".to_string(), "blink".to_string())));
+    }
+}
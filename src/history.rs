@@ -0,0 +1,530 @@
+//! History of the results of the past runs.
+//!
+//! The results of every `run` are stored in
+//! `<data-dir>/.bmregression/history/<run-id>.tsv`, one regression per line with
+//! its name, status, duration in milliseconds, comma separated tags, for the
+//! regressions compared by similarity the measured percentage and, for those
+//! run with `--bench`, the comma separated milliseconds of the timed
+//! iterations of their command. Unlike
+//! the command logs the history is never pruned, it is small and the statistics
+//! are more meaningful the longer it is. The environment each run was started
+//! in is stored next to its results, in `<run-id>.env`, with the time it started.
+//!
+//! A run identifier is the compact timestamp of its start followed by a short
+//! random suffix, e.g. `20240401T020000Z-3fa9c1`, unless given with `--run-id`.
+//! The same identifier names the command logs and the artifacts of the run and
+//! is embedded in its reports, to correlate them. The runs are ordered by the
+//! time they started, whatever their identifier.
+
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::atomic;
+use crate::logs::STATE_DIR;
+use crate::recorded;
+use crate::results::{Environment, RegressionResult, Spread};
+use crate::timestamp::DateTime;
+
+/// Extension of the history files.
+const EXTENSION: &str = "tsv";
+
+/// A regression result as stored in the history.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    pub status: String,
+    pub duration: Duration,
+    pub tags: Vec<String>,
+    /// Percentage of identical lines, for the regressions compared by similarity
+    pub similarity: Option<f64>,
+    /// Durations of the command in the timed iterations of `run --bench`, empty otherwise
+    pub bench: Vec<Duration>,
+}
+
+impl Entry {
+    /// Returns the duration to compare across runs: the median of the benchmarked
+    /// iterations of the command, or else the duration of the regression.
+    pub fn compared_duration(&self) -> Duration {
+        Spread::of(&self.bench).map_or(self.duration, |spread| spread.median)
+    }
+}
+
+/// The stored results of a run.
+#[derive(Debug, Clone)]
+pub struct RecordedRun {
+    /// Identifier of the run, which starts with its timestamp
+    pub id: String,
+    pub entries: Vec<Entry>,
+}
+
+impl RecordedRun {
+    /// Returns the entry of a regression, if it was executed in this run.
+    pub fn entry(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+}
+
+/// Where the commands analysing the past runs read them from.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Source {
+    /// The local history, in .bmregression/history
+    History,
+    /// The results committed by the record command, in results/
+    Recorded,
+}
+
+impl Source {
+    /// Loads the run with the given identifier.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the run cannot be found or read.
+    pub fn load(self, target: &str, run_id: &str) -> io::Result<RecordedRun> {
+        match self {
+            Source::History => load(target, run_id),
+            Source::Recorded => recorded::load(target, run_id),
+        }
+    }
+
+    /// Loads all the runs, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runs cannot be read.
+    pub fn load_all(self, target: &str) -> io::Result<Vec<RecordedRun>> {
+        match self {
+            Source::History => load_all(target),
+            Source::Recorded => recorded::load_all(target),
+        }
+    }
+}
+
+/// Returns the directory holding the history.
+pub fn history_root(target: &str) -> PathBuf {
+    Path::new(target).join(STATE_DIR).join("history")
+}
+
+/// Returns a new run identifier: the compact timestamp of the start of the run and six random
+/// hexadecimal digits, so that runs started in the same second on several hosts do not collide.
+pub fn new_run_id(started: DateTime) -> String {
+    // The keys of a new RandomState are random, seeded from the operating system
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    format!("{}-{:06x}", started.compact(), hasher.finish() & 0xff_ffff)
+}
+
+/// Checks a run identifier given with `--run-id`: it names files and directories, and must
+/// not be in the history already.
+///
+/// # Errors
+///
+/// Returns an error naming the problem if the identifier cannot be used.
+pub fn check_run_id(target: &str, run_id: &str) -> Result<(), String> {
+    if run_id.is_empty()
+        || !run_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        || run_id.starts_with('.')
+    {
+        return Err(format!(
+            "the run id \"{}\" must be made of letters, digits, '-', '_' and '.', and not start with '.'",
+            run_id
+        ));
+    }
+    if history_root(target)
+        .join(format!("{}.{}", run_id, EXTENSION))
+        .exists()
+    {
+        return Err(format!(
+            "the run id {} is already in the history, choose another one",
+            run_id
+        ));
+    }
+    Ok(())
+}
+
+/// Stores the results of a run.
+pub fn save(target: &str, run_id: &str, results: &[RegressionResult]) -> io::Result<()> {
+    let root = history_root(target);
+    fs::create_dir_all(&root)?;
+    let mut content = String::new();
+    for result in results {
+        content.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}{}\n",
+            result.name,
+            result.status,
+            result.duration.as_millis(),
+            result.tags.join(","),
+            result
+                .similarity
+                .map(|similarity| format!("{:.2}", similarity))
+                .unwrap_or_default(),
+            bench_field(&result.bench)
+        ));
+    }
+    atomic::write(root.join(format!("{}.{}", run_id, EXTENSION)), content)
+}
+
+/// Renders the durations of the benchmarked iterations as the last field of a line, nothing
+/// if the regression was not benchmarked, so that the lines of the other runs are unchanged.
+fn bench_field(bench: &[Duration]) -> String {
+    if bench.is_empty() {
+        return String::new();
+    }
+    let millis: Vec<String> = bench.iter().map(|d| d.as_millis().to_string()).collect();
+    format!("\t{}", millis.join(","))
+}
+
+/// Parses a stored run, skipping malformed lines.
+fn parse(id: &str, content: &str) -> RecordedRun {
+    let entries = content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 3 {
+                return None;
+            }
+            Some(Entry {
+                name: fields[0].to_string(),
+                status: fields[1].to_string(),
+                duration: Duration::from_millis(fields[2].parse().ok()?),
+                tags: fields
+                    .get(3)
+                    .map(|tags| {
+                        tags.split(',')
+                            .filter(|t| !t.is_empty())
+                            .map(|t| t.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                similarity: fields.get(4).and_then(|similarity| similarity.parse().ok()),
+                bench: fields
+                    .get(5)
+                    .map(|bench| {
+                        bench
+                            .split(',')
+                            .filter_map(|millis| millis.parse().ok())
+                            .map(Duration::from_millis)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+        })
+        .collect();
+    RecordedRun {
+        id: id.to_string(),
+        entries,
+    }
+}
+
+/// Stores the environment a run was started in, next to its results.
+///
+/// The file `<run-id>.env` holds one `key\tvalue` line per field, starting with the
+/// `run_id` and the time the run `started`, with the commits, tools and options prefixed
+/// by `commit.`, `tool.` and `option.`.
+pub fn save_environment(
+    target: &str,
+    run_id: &str,
+    started: DateTime,
+    environment: &Environment,
+) -> io::Result<()> {
+    let root = history_root(target);
+    fs::create_dir_all(&root)?;
+    let mut content = format!("run_id\t{}\nstarted\t{}\n", run_id, started.compact());
+    let fields = environment
+        .host()
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .chain(prefixed("commit", &environment.commits))
+        .chain(prefixed("tool", &environment.toolchain))
+        .chain(prefixed("option", &environment.options));
+    for (key, value) in fields {
+        // Keep one field per line whatever the values contain
+        content.push_str(&format!("{}\t{}\n", key, value.replace(['\t', '\n'], " ")));
+    }
+    atomic::write(root.join(format!("{}.env", run_id)), content)
+}
+
+/// Prefixes the keys of (key, value) pairs, e.g. `tool.git`.
+fn prefixed<'a>(
+    prefix: &'a str,
+    pairs: &'a [(String, String)],
+) -> impl Iterator<Item = (String, String)> + 'a {
+    pairs
+        .iter()
+        .map(move |(key, value)| (format!("{}.{}", prefix, key), value.clone()))
+}
+
+/// Loads the stored run with the given identifier.
+///
+/// # Errors
+///
+/// Returns an error if the run is not in the history.
+pub fn load(target: &str, run_id: &str) -> io::Result<RecordedRun> {
+    let path = history_root(target).join(format!("{}.{}", run_id, EXTENSION));
+    if !path.exists() {
+        return Err(io::Error::other(format!(
+            "run {} not found in the history",
+            run_id
+        )));
+    }
+    Ok(parse(run_id, &fs::read_to_string(path)?))
+}
+
+/// Removes the entries of a regression from all the stored runs, for a removed regression.
+///
+/// # Returns
+///
+/// The number of entries removed.
+pub fn remove(target: &str, name: &str) -> io::Result<usize> {
+    let root = history_root(target);
+    if !root.exists() {
+        return Ok(0);
+    }
+    let mut removed = 0;
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        let kept: String = content
+            .split_inclusive('\n')
+            .filter(|line| line.split('\t').next() != Some(name))
+            .collect();
+        if kept.len() != content.len() {
+            removed += content.lines().count() - kept.lines().count();
+            atomic::write(&path, kept)?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Returns the most recent entry of each regression in the history, with the run it is from.
+pub fn latest_entries(target: &str) -> io::Result<HashMap<String, (String, Entry)>> {
+    let mut latest = HashMap::new();
+    // Oldest first, so that the most recent run wins
+    for run in load_all(target)? {
+        for entry in run.entries {
+            latest.insert(entry.name.clone(), (run.id.clone(), entry));
+        }
+    }
+    Ok(latest)
+}
+
+/// Loads all the stored runs, oldest first.
+pub fn load_all(target: &str) -> io::Result<Vec<RecordedRun>> {
+    let root = history_root(target);
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+    let mut ids = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some(EXTENSION) {
+            if let Some(id) = path.file_stem().and_then(|s| s.to_str()) {
+                ids.push(id.to_string());
+            }
+        }
+    }
+    chronological(target, &mut ids);
+    ids.iter().map(|id| load(target, id)).collect()
+}
+
+/// Returns the time a run started, as stored with its environment, or else as its
+/// identifier starts with, None if neither tells.
+pub fn started(target: &str, run_id: &str) -> Option<DateTime> {
+    let environment = fs::read_to_string(history_root(target).join(format!("{}.env", run_id))).ok();
+    environment
+        .as_deref()
+        .unwrap_or_default()
+        .lines()
+        .find_map(|line| line.strip_prefix("started\t"))
+        .and_then(DateTime::from_compact)
+        .or_else(|| DateTime::from_compact(run_id))
+}
+
+/// Sorts run identifiers oldest first, by the time the runs started, those whose start is
+/// unknown first.
+pub fn chronological(target: &str, ids: &mut [String]) {
+    ids.sort_by_cached_key(|id| (started(target, id), id.clone()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn result(name: &str, status: &'static str, millis: u64) -> RegressionResult {
+        RegressionResult {
+            name: name.to_string(),
+            tags: vec!["default".to_string(), "fpga".to_string()],
+            status,
+            duration: Duration::from_millis(millis),
+            ..Default::default()
+        }
+    }
+
+    fn history() -> (TempDir, String) {
+        let dir = TempDir::new("history").unwrap();
+        let target = dir.path().to_str().unwrap().to_string();
+        (dir, target)
+    }
+
+    #[test]
+    fn stores_and_loads_the_results_of_a_run() {
+        let (_dir, target) = history();
+        let mut counter = result("counter", "failed", 250);
+        counter.similarity = Some(97.126);
+        counter.bench = vec![Duration::from_millis(120), Duration::from_millis(100)];
+        save(
+            &target,
+            "nightly-1",
+            &[result("blink", "passed", 1500), counter],
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(history_root(&target).join("nightly-1.tsv")).unwrap(),
+            "blink\tpassed\t1500\tdefault,fpga\t\ncounter\tfailed\t250\tdefault,fpga\t97.13\t120,100\n"
+        );
+
+        let run = load(&target, "nightly-1").unwrap();
+        assert_eq!(run.id, "nightly-1");
+        let blink = run.entry("blink").unwrap();
+        assert_eq!(blink.status, "passed");
+        assert_eq!(blink.duration, Duration::from_millis(1500));
+        assert_eq!(blink.tags, ["default", "fpga"]);
+        assert_eq!(blink.similarity, None);
+        assert_eq!(blink.compared_duration(), Duration::from_millis(1500));
+        let counter = run.entry("counter").unwrap();
+        assert_eq!(counter.similarity, Some(97.13));
+        assert_eq!(counter.bench.len(), 2);
+        assert_eq!(counter.compared_duration(), Duration::from_millis(110));
+        assert!(run.entry("uart").is_none());
+
+        let err = load(&target, "nightly-2").unwrap_err();
+        assert_eq!(err.to_string(), "run nightly-2 not found in the history");
+    }
+
+    #[test]
+    fn skips_the_malformed_lines() {
+        let run = parse(
+            "nightly-1",
+            "blink\tpassed\t10\n\ncounter\tfailed\nuart\terror\tlong\tdefault\nspi\tpassed\t5\t\t\n",
+        );
+        let names: Vec<&str> = run.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["blink", "spi"]);
+        assert!(run.entries[0].tags.is_empty());
+        assert_eq!(run.entries[1].similarity, None);
+    }
+
+    #[test]
+    fn orders_the_runs_by_the_time_they_started() {
+        let (_dir, target) = history();
+        for id in [
+            "nightly-1",
+            "20240402T020000Z-000000",
+            "20240401T020000Z-ffffff",
+        ] {
+            save(&target, id, &[result("blink", "passed", 10)]).unwrap();
+        }
+        // Started before the others, whatever its identifier
+        let march = DateTime::from_compact("20240301T000000Z").unwrap();
+        save_environment(&target, "zz-first", march, &Environment::default()).unwrap();
+        save(&target, "zz-first", &[result("blink", "failed", 10)]).unwrap();
+
+        let ids: Vec<String> = load_all(&target)
+            .unwrap()
+            .into_iter()
+            .map(|run| run.id)
+            .collect();
+        // Those whose start is unknown first
+        assert_eq!(
+            ids,
+            [
+                "nightly-1",
+                "zz-first",
+                "20240401T020000Z-ffffff",
+                "20240402T020000Z-000000"
+            ]
+        );
+        assert_eq!(started(&target, "zz-first"), Some(march));
+        let latest = latest_entries(&target).unwrap();
+        assert_eq!(latest["blink"].0, "20240402T020000Z-000000");
+    }
+
+    #[test]
+    fn stores_the_environment_one_field_per_line() {
+        let (_dir, target) = history();
+        let environment = Environment {
+            hostname: Some("builder".to_string()),
+            os_release: "Linux 6.1".to_string(),
+            cpus: 8,
+            commits: vec![("examples".to_string(), "3fa9c1".to_string())],
+            toolchain: vec![("yosys".to_string(), "0.38\tgit".to_string())],
+            options: vec![("jobs".to_string(), "4".to_string())],
+        };
+        let started = DateTime::from_compact("20240401T020000Z").unwrap();
+        save_environment(&target, "nightly-1", started, &environment).unwrap();
+        assert_eq!(
+            fs::read_to_string(history_root(&target).join("nightly-1.env")).unwrap(),
+            "run_id\tnightly-1\nstarted\t20240401T020000Z\nhostname\tbuilder\nos\tLinux 6.1\n\
+             cpus\t8\ncommit.examples\t3fa9c1\ntool.yosys\t0.38 git\noption.jobs\t4\n"
+        );
+    }
+
+    #[test]
+    fn removes_the_entries_of_a_regression() {
+        let (_dir, target) = history();
+        assert_eq!(remove(&target, "blink").unwrap(), 0);
+        save(
+            &target,
+            "nightly-1",
+            &[
+                result("blink", "passed", 10),
+                result("counter", "passed", 10),
+            ],
+        )
+        .unwrap();
+        save(&target, "nightly-2", &[result("blink", "failed", 10)]).unwrap();
+        assert_eq!(remove(&target, "blink").unwrap(), 2);
+        assert_eq!(remove(&target, "blink").unwrap(), 0);
+        let names: Vec<String> = load_all(&target)
+            .unwrap()
+            .into_iter()
+            .flat_map(|run| run.entries)
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["counter"]);
+    }
+
+    #[test]
+    fn checks_the_given_run_ids() {
+        let (_dir, target) = history();
+        for run_id in ["nightly-42", "release_1.2", "3fa9c1"] {
+            assert!(check_run_id(&target, run_id).is_ok(), "{}", run_id);
+        }
+        for run_id in ["", ".hidden", "a/b", "a b", "../up", "tab\t"] {
+            assert!(check_run_id(&target, run_id).is_err(), "{:?}", run_id);
+        }
+        save(&target, "nightly-42", &[]).unwrap();
+        assert_eq!(
+            check_run_id(&target, "nightly-42").unwrap_err(),
+            "the run id nightly-42 is already in the history, choose another one"
+        );
+    }
+
+    #[test]
+    fn names_the_new_runs_after_their_start() {
+        let april = DateTime::from_compact("20240401T020000Z").unwrap();
+        let run_id = new_run_id(april);
+        assert_eq!(run_id.len(), 23, "{}", run_id);
+        assert!(run_id.starts_with("20240401T020000Z-"), "{}", run_id);
+        assert!(run_id[17..].chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(started("/nonexistent", &run_id), Some(april));
+    }
+}
@@ -0,0 +1,173 @@
+//! Inspection of an example, to propose the configuration of a regression for it.
+//!
+//! The examples following the bmexamples conventions build their HDL with the
+//! `hdl` target of their Makefile, in the working directory named by the
+//! `WORKING_DIR` variable of their `local.mk`, `working_dir` by default. The
+//! command is `make hdl` if the target exists, otherwise each target of the
+//! Makefile is a candidate. The outputs are the HDL files already generated in
+//! the working directory, or the conventional `bondmachine.sv` when the example
+//! has a `local.mk` but has not been built yet.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Names of the Makefile, in the order make looks for them.
+const MAKEFILES: [&str; 3] = ["GNUmakefile", "makefile", "Makefile"];
+
+/// Make target building the HDL of the examples.
+const HDL_TARGET: &str = "hdl";
+
+/// File of the variables of the bmexamples examples.
+const CONVENTION_FILE: &str = "local.mk";
+
+/// Working directory of the examples whose `local.mk` does not name one.
+const DEFAULT_WORKING_DIR: &str = "working_dir";
+
+/// Output of the HDL target of the examples.
+const CONVENTION_OUTPUT: &str = "bondmachine.sv";
+
+/// Extensions of the HDL files taken as outputs.
+const OUTPUT_EXTENSIONS: [&str; 3] = ["sv", "v", "vhd"];
+
+/// Returns the targets defined by the rules of a Makefile, in order, without the special
+/// and pattern ones.
+fn make_targets(content: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    for line in content.lines() {
+        if line.starts_with(char::is_whitespace) || line.starts_with('#') {
+            continue;
+        }
+        // Rules, not the := and ::= assignments
+        let Some((names, rest)) = line.split_once(':') else {
+            continue;
+        };
+        if rest.starts_with(['=', ':']) || names.contains('=') {
+            continue;
+        }
+        for name in names.split_whitespace() {
+            let special = name.starts_with('.') || name.contains(['%', '$']);
+            if !special && !targets.iter().any(|target| target == name) {
+                targets.push(name.to_string());
+            }
+        }
+    }
+    targets
+}
+
+/// Returns the candidate commands of an example, `make hdl` alone if it has that target.
+///
+/// # Errors
+///
+/// Returns an error if the example has no Makefile or its Makefile has no target.
+pub fn commands(example: &Path) -> io::Result<Vec<String>> {
+    let Some(makefile) = MAKEFILES
+        .iter()
+        .map(|name| example.join(name))
+        .find(|path| path.is_file())
+    else {
+        return Err(io::Error::other(
+            "the example has no Makefile, give the command with --regcommand",
+        ));
+    };
+    let targets = make_targets(&fs::read_to_string(&makefile)?);
+    if targets.iter().any(|target| target == HDL_TARGET) {
+        return Ok(vec![format!("make {}", HDL_TARGET)]);
+    }
+    if targets.is_empty() {
+        return Err(io::Error::other(
+            "the Makefile of the example has no target, give the command with --regcommand",
+        ));
+    }
+    Ok(targets
+        .iter()
+        .map(|target| format!("make {}", target))
+        .collect())
+}
+
+/// Returns the working directory of an example, from its `local.mk`.
+fn working_dir(example: &Path) -> String {
+    let content = fs::read_to_string(example.join(CONVENTION_FILE)).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once('=')?;
+            let name = name.trim_end_matches([':', '?', '+']).trim();
+            (name == "WORKING_DIR").then(|| value.trim().to_string())
+        })
+        .find(|value| !value.is_empty() && !value.contains('$'))
+        .unwrap_or_else(|| DEFAULT_WORKING_DIR.to_string())
+}
+
+/// Returns the candidate outputs of an example, relative to it and sorted.
+///
+/// # Errors
+///
+/// Returns an error if none is found.
+pub fn outputs(example: &Path) -> io::Result<Vec<String>> {
+    let working_dir = working_dir(example);
+    let mut outputs = Vec::new();
+    if let Ok(entries) = fs::read_dir(example.join(&working_dir)) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let hdl = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| OUTPUT_EXTENSIONS.contains(&extension));
+            if hdl && path.is_file() {
+                outputs.push(format!(
+                    "{}/{}",
+                    working_dir,
+                    entry.file_name().to_string_lossy()
+                ));
+            }
+        }
+    }
+    outputs.sort();
+    if outputs.is_empty() && example.join(CONVENTION_FILE).is_file() {
+        outputs.push(format!("{}/{}", working_dir, CONVENTION_OUTPUT));
+    }
+    if outputs.is_empty() {
+        return Err(io::Error::other(format!(
+            "no HDL output found in {} of the example, give it with --sourcedata",
+            working_dir
+        )));
+    }
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn proposes_the_conventional_command_and_outputs() {
+        let makefile = "include bmapi.mk\nWORKING_DIR := build\n.PHONY: hdl clean\nall: hdl\nhdl: $(WORKING_DIR)\n\tbondmachine -o $@\n%.o: %.c\nclean:\n";
+        assert_eq!(make_targets(makefile), vec!["all", "hdl", "clean"]);
+
+        let example = TempDir::new("import").unwrap();
+        fs::write(example.path().join("Makefile"), makefile).unwrap();
+        assert_eq!(commands(example.path()).unwrap(), vec!["make hdl"]);
+        fs::write(example.path().join("Makefile"), "sim:\nclean:\n").unwrap();
+        assert_eq!(
+            commands(example.path()).unwrap(),
+            vec!["make sim", "make clean"]
+        );
+
+        assert!(outputs(example.path()).is_err());
+        fs::write(example.path().join(CONVENTION_FILE), "WORKING_DIR=build\n").unwrap();
+        assert_eq!(
+            outputs(example.path()).unwrap(),
+            vec!["build/bondmachine.sv"]
+        );
+        fs::create_dir(example.path().join("build")).unwrap();
+        for file in ["top.v", "bm.sv", "bm.json"] {
+            fs::write(example.path().join("build").join(file), "").unwrap();
+        }
+        assert_eq!(
+            outputs(example.path()).unwrap(),
+            vec!["build/bm.sv", "build/top.v"]
+        );
+    }
+}
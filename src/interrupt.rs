@@ -0,0 +1,178 @@
+//! Handling of SIGINT and SIGTERM.
+//!
+//! Regression commands are spawned in their own process group, so that an
+//! interrupt can terminate the whole tree of processes (make, vivado, ...)
+//! started by the command. The signal handler only records the signal and
+//! forwards a SIGTERM to the process groups of the running commands, the rest
+//! of the cleanup happens in the normal flow once the commands have returned.
+//! A few commands can run at the same time, such as the clones of the two
+//! repositories or the regressions of `run --jobs`.
+//!
+//! Resident modes can ask for a graceful SIGTERM: the running commands are then
+//! left to finish, and only the following ones are not started.
+//!
+//! The handler is installed without `SA_RESTART`, so that a signal interrupts a
+//! blocking read of the terminal: the questions read their answer with
+//! [`read_answer`], which returns on an interrupt.
+
+use std::io::{self, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+/// Signal received, 0 if none
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+/// Leave the running commands alone on SIGTERM
+static GRACEFUL: AtomicBool = AtomicBool::new(false);
+
+/// Maximum number of commands running at the same time.
+pub const MAX_CHILDREN: usize = 64;
+
+/// Process groups of the running commands, 0 for the free slots
+static CHILD_GROUPS: [AtomicI32; MAX_CHILDREN] = [const { AtomicI32::new(0) }; MAX_CHILDREN];
+
+extern "C" fn handle_signal(signal: libc::c_int) {
+    SIGNAL.store(signal, Ordering::SeqCst);
+    if !aborted() {
+        return;
+    }
+    for slot in &CHILD_GROUPS {
+        let group = slot.load(Ordering::SeqCst);
+        if group > 0 {
+            // kill is async-signal-safe, a negative pid targets the whole group
+            unsafe {
+                libc::kill(-group, libc::SIGTERM);
+            }
+        }
+    }
+}
+
+/// Sets the disposition of a signal without `SA_RESTART`, returning the previous one.
+fn set_action(signal: libc::c_int, handler: libc::sighandler_t) -> libc::sigaction {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler;
+        libc::sigemptyset(&mut action.sa_mask);
+        let mut previous: libc::sigaction = std::mem::zeroed();
+        libc::sigaction(signal, &action, &mut previous);
+        previous
+    }
+}
+
+/// Installs the handler for SIGINT and SIGTERM.
+pub fn install() {
+    let handler = handle_signal as extern "C" fn(libc::c_int);
+    set_action(libc::SIGINT, handler as libc::sighandler_t);
+    set_action(libc::SIGTERM, handler as libc::sighandler_t);
+}
+
+/// Runs an interactive command sharing the terminal, such as a shell, until it exits.
+///
+/// Ctrl-C at the terminal then interrupts what runs in the command, not bmregression, which
+/// ignores SIGINT meanwhile; the command itself gets the default disposition back.
+pub fn run_interactive(command: &mut Command) -> io::Result<ExitStatus> {
+    let previous = set_action(libc::SIGINT, libc::SIG_IGN);
+    // Only async-signal-safe calls between fork and exec
+    unsafe {
+        command.pre_exec(|| {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+            Ok(())
+        });
+    }
+    let status = command.status();
+    unsafe {
+        libc::sigaction(libc::SIGINT, &previous, std::ptr::null_mut());
+    }
+    status
+}
+
+/// Reads the answer to an interactive question, up to the end of its line.
+///
+/// Returns None at the end of the input, or once a signal has been received, even
+/// while waiting for the answer.
+pub fn read_answer(input: &mut impl Read) -> io::Result<Option<String>> {
+    let mut answer = Vec::new();
+    let mut byte = [0];
+    loop {
+        if interrupted().is_some() {
+            return Ok(None);
+        }
+        match input.read(&mut byte) {
+            Ok(0) if answer.is_empty() => return Ok(None),
+            Ok(0) => break,
+            Ok(_) => {
+                answer.push(byte[0]);
+                if byte[0] == b'\n' {
+                    break;
+                }
+            }
+            // The signal interrupted the read, checked above
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(Some(String::from_utf8_lossy(&answer).into_owned()))
+}
+
+/// Returns the received signal, if any.
+pub fn interrupted() -> Option<i32> {
+    match SIGNAL.load(Ordering::SeqCst) {
+        0 => None,
+        signal => Some(signal),
+    }
+}
+
+/// Lets the running commands finish on SIGTERM, instead of terminating them.
+pub fn set_graceful() {
+    GRACEFUL.store(true, Ordering::SeqCst);
+}
+
+/// Returns true if the received signal terminates the running commands.
+///
+/// Unlike [`interrupted`], false after a graceful SIGTERM: the command that was
+/// running has completed normally and its result stands.
+pub fn aborted() -> bool {
+    match interrupted() {
+        None => false,
+        Some(signal) => !(signal == libc::SIGTERM && GRACEFUL.load(Ordering::SeqCst)),
+    }
+}
+
+/// Returns the name of the received signal.
+pub fn signal_name() -> &'static str {
+    match SIGNAL.load(Ordering::SeqCst) {
+        libc::SIGINT => "SIGINT",
+        libc::SIGTERM => "SIGTERM",
+        _ => "signal",
+    }
+}
+
+/// Returns the exit code for the received signal, following the shell convention 128 + signal.
+pub fn exit_code() -> i32 {
+    128 + SIGNAL.load(Ordering::SeqCst)
+}
+
+/// Records the process group of a running command.
+///
+/// If a signal already arrived in the meantime the group is terminated right away.
+/// Beyond [`MAX_CHILDREN`] running commands the group is not recorded.
+pub fn set_child_group(group: u32) {
+    // Takes the first free slot
+    let _ = CHILD_GROUPS.iter().any(|slot| {
+        slot.compare_exchange(0, group as i32, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    });
+    if aborted() {
+        unsafe {
+            libc::kill(-(group as i32), libc::SIGTERM);
+        }
+    }
+}
+
+/// Clears the process group of a command once it has terminated.
+pub fn clear_child_group(group: u32) {
+    for slot in &CHILD_GROUPS {
+        let _ = slot.compare_exchange(group as i32, 0, Ordering::SeqCst, Ordering::SeqCst);
+    }
+}
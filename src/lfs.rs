@@ -0,0 +1,79 @@
+//! Detection of the files of the data repository stored with git LFS.
+//!
+//! Git routes a file to LFS when a `.gitattributes` file, at the root of the
+//! repository or in a directory above the file, gives it the `filter=lfs`
+//! attribute. Only this attribute is looked at, and the pattern syntax of git is
+//! approximated with glob patterns: a pattern without a slash matches the file
+//! name at any depth, one with a slash matches the path relative to the
+//! directory of the `.gitattributes` file. The last matching line wins.
+
+use std::fs;
+use std::path::Path;
+
+use glob::{MatchOptions, Pattern};
+
+/// Name of the files giving the git attributes.
+const ATTRIBUTES_FILE: &str = ".gitattributes";
+
+/// Returns true if the pattern of a `.gitattributes` line matches a relative path.
+fn matches(pattern: &str, relative: &Path) -> bool {
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+    let anchored = pattern.trim_start_matches('/');
+    let subject = if anchored.contains('/') {
+        relative.to_string_lossy().to_string()
+    } else {
+        match relative.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => return false,
+        }
+    };
+    Pattern::new(anchored).is_ok_and(|pattern| pattern.matches_with(&subject, options))
+}
+
+/// Returns true if the file, inside the repository at `root`, is routed to LFS.
+pub fn is_tracked(root: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    let mut tracked = false;
+    let mut dir = root.to_path_buf();
+    let mut rest = relative.to_path_buf();
+    loop {
+        if let Ok(content) = fs::read_to_string(dir.join(ATTRIBUTES_FILE)) {
+            for line in content.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let mut fields = line.split_whitespace();
+                let Some(pattern) = fields.next() else {
+                    continue;
+                };
+                if !matches(pattern, &rest) {
+                    continue;
+                }
+                for attribute in fields {
+                    match attribute {
+                        "filter=lfs" => tracked = true,
+                        "-filter" | "!filter" => tracked = false,
+                        other if other.starts_with("filter=") => tracked = false,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        // Down to the directory of the file
+        let mut components = rest.components();
+        let Some(first) = components.next() else {
+            break;
+        };
+        if components.as_path().as_os_str().is_empty() {
+            break;
+        }
+        dir.push(first);
+        rest = components.as_path().to_path_buf();
+    }
+    tracked
+}
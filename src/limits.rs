@@ -0,0 +1,137 @@
+//! Limits of the resources of the regression commands.
+//!
+//! The `limits` of a configuration are applied to its command between fork and
+//! exec, with setrlimit and nice, so that every process the command starts
+//! inherits them. The limits are per process: a build running four compilers
+//! in parallel can use four times the memory limit. A limit the system does not
+//! support is ignored, with a warning printed once.
+//!
+//! Exceeding the CPU time limit kills the command with SIGXCPU. Exceeding the
+//! memory limit makes the allocations fail, which most programs report with a
+//! message or an abort, so it is recognized from the status and the stderr.
+
+use std::io;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Command, ExitStatus};
+use std::sync::Once;
+
+use crate::config::Limits;
+use crate::output;
+use crate::runner;
+
+/// Set when the warning about the unsupported limits has been printed
+static UNSUPPORTED_WARNING: Once = Once::new();
+
+/// Messages with which programs report a failed allocation.
+const OUT_OF_MEMORY_MESSAGES: [&str; 4] = [
+    "cannot allocate memory",
+    "out of memory",
+    "bad_alloc",
+    "memoryerror",
+];
+
+/// Applies the limits to a command, when it is spawned.
+pub fn apply(command: &mut Command, limits: &Limits) {
+    if limits.is_empty() {
+        return;
+    }
+    let mut limits = *limits;
+    // A resource unknown to the system cannot be read either
+    let supported = |resource| {
+        let mut current = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        unsafe { libc::getrlimit(resource, &mut current) == 0 }
+    };
+    let mut unsupported = Vec::new();
+    if limits.memory_mb.is_some() && !supported(libc::RLIMIT_AS) {
+        limits.memory_mb = None;
+        unsupported.push("memory_mb");
+    }
+    if limits.cpu_seconds.is_some() && !supported(libc::RLIMIT_CPU) {
+        limits.cpu_seconds = None;
+        unsupported.push("cpu_seconds");
+    }
+    if !unsupported.is_empty() {
+        UNSUPPORTED_WARNING.call_once(|| {
+            output::line(&format!(
+                "\x1b[0;33mWarning: the {} limits are not supported on this system, the regressions run without them\x1b[0m",
+                unsupported.join(" and ")
+            ))
+        });
+    }
+    // Only async-signal-safe calls between fork and exec
+    unsafe {
+        command.pre_exec(move || {
+            let limit = |resource, value: u64| {
+                let mut current = libc::rlimit {
+                    rlim_cur: 0,
+                    rlim_max: 0,
+                };
+                if libc::getrlimit(resource, &mut current) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                // The hard limit cannot be raised, only the soft one is lowered
+                current.rlim_cur = (value as libc::rlim_t).min(current.rlim_max);
+                if libc::setrlimit(resource, &current) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            };
+            if let Some(mb) = limits.memory_mb {
+                limit(libc::RLIMIT_AS, mb.saturating_mul(1024 * 1024))?;
+            }
+            if let Some(seconds) = limits.cpu_seconds {
+                limit(libc::RLIMIT_CPU, seconds)?;
+            }
+            // Lowering the priority is always allowed, -1 is also a valid niceness
+            if let Some(nice) = limits.nice {
+                libc::nice(nice);
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Tells which limit a failed command exceeded, judging by its status and its stderr.
+///
+/// # Returns
+///
+/// The description of the exceeded limit, None if the failure is not due to a limit.
+pub fn exceeded(limits: &Limits, status: &ExitStatus, stderr: &[u8]) -> Option<String> {
+    // The shell reports a command killed by a signal with the exit code 128 + signal
+    let signal = status.signal().or_else(|| {
+        status
+            .code()
+            .filter(|code| *code > 128)
+            .map(|code| code - 128)
+    });
+    if let (Some(seconds), Some(libc::SIGXCPU)) = (limits.cpu_seconds, signal) {
+        return Some(format!(
+            "the cpu_seconds limit of {}s was exceeded",
+            seconds
+        ));
+    }
+    let mb = limits.memory_mb?;
+    let stderr = String::from_utf8_lossy(stderr).to_lowercase();
+    if OUT_OF_MEMORY_MESSAGES
+        .iter()
+        .any(|message| stderr.contains(message))
+    {
+        return Some(format!(
+            "the memory_mb limit of {} MiB was exceeded, an allocation failed",
+            mb
+        ));
+    }
+    match signal {
+        Some(signal @ (libc::SIGKILL | libc::SIGABRT | libc::SIGSEGV | libc::SIGBUS)) => {
+            Some(format!(
+                "killed by {} under the memory_mb limit of {} MiB, the memory limit was probably exceeded",
+                runner::signal_name(signal),
+                mb
+            ))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,252 @@
+//! Per-regression command logs.
+//!
+//! The captured stdout and stderr of every regression command are written to
+//! `<data-dir>/.bmregression/logs/<run-id>/<regression>.{out,err}`, one
+//! directory per run, with the differences of the failing regressions in
+//! `<regression>.diff` and a copy of their generated output in
+//! `<regression>.generated`. Run identifiers start with a timestamp, so sorting
+//! them by name sorts them by age.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::history;
+use crate::redact;
+
+/// Directory, inside the data directory, holding the state of the tool.
+pub const STATE_DIR: &str = ".bmregression";
+
+/// Returns the directory holding the logs of all the runs.
+pub fn logs_root(target: &str) -> PathBuf {
+    Path::new(target).join(STATE_DIR).join("logs")
+}
+
+/// Returns the path of a log file of a regression, `extension` is `out`, `err`, `diff` or
+/// `generated`.
+pub fn log_file(dir: &Path, regression: &str, extension: &str) -> PathBuf {
+    dir.join(format!("{}.{}", regression, extension))
+}
+
+/// Writes the captured output of a regression command, its secrets redacted.
+///
+/// # Returns
+///
+/// The path of the logs without extension, `<dir>/<regression>`.
+pub fn write(dir: &Path, regression: &str, stdout: &[u8], stderr: &[u8]) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    fs::write(log_file(dir, regression, "out"), redact::bytes(stdout))?;
+    fs::write(log_file(dir, regression, "err"), redact::bytes(stderr))?;
+    Ok(dir.join(regression))
+}
+
+/// Writes the differences of a failing regression next to its logs, its secrets redacted.
+pub fn write_diff(dir: &Path, regression: &str, diff: &str) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(log_file(dir, regression, "diff"), redact::text(diff))
+}
+
+/// Keeps a copy of the generated output of a failing regression next to its logs, as it was
+/// generated, for `import-baselines` to take it as the expected output.
+pub fn write_generated(dir: &Path, regression: &str, generated: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::copy(generated, log_file(dir, regression, "generated"))?;
+    Ok(())
+}
+
+/// Returns the identifiers of the recorded runs, oldest first by the time they started.
+pub fn runs(target: &str) -> io::Result<Vec<String>> {
+    let root = logs_root(target);
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+    let mut runs = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            runs.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    history::chronological(target, &mut runs);
+    Ok(runs)
+}
+
+/// Removes the logs of a regression from all the runs, for a removed regression.
+///
+/// # Returns
+///
+/// The number of runs whose logs of the regression were removed.
+pub fn remove(target: &str, regression: &str) -> io::Result<usize> {
+    let mut removed = 0;
+    for run in runs(target)? {
+        let dir = logs_root(target).join(run);
+        let mut found = false;
+        for extension in ["out", "err", "diff", "generated"] {
+            let path = log_file(&dir, regression, extension);
+            if path.exists() {
+                fs::remove_file(path)?;
+                found = true;
+            }
+        }
+        removed += usize::from(found);
+    }
+    Ok(removed)
+}
+
+/// Removes the logs of the oldest runs, keeping the `keep` most recent ones.
+pub fn prune(target: &str, keep: usize, debug: bool) -> io::Result<()> {
+    let runs = runs(target)?;
+    if runs.len() <= keep {
+        return Ok(());
+    }
+    for run in &runs[..runs.len() - keep] {
+        if debug {
+            println!("Pruning logs of run {}", run);
+        }
+        fs::remove_dir_all(logs_root(target).join(run))?;
+    }
+    Ok(())
+}
+
+/// Prints the logs of a regression.
+///
+/// Without a run identifier the most recent run that executed the regression is used.
+///
+/// # Errors
+///
+/// Returns an error if no logs exist for the regression (in the given run).
+pub fn show(target: &str, regression: &str, run: Option<&str>) -> io::Result<()> {
+    let root = logs_root(target);
+    let run = match run {
+        Some(run) => run.to_string(),
+        None => runs(target)?
+            .into_iter()
+            .rev()
+            .find(|run| log_file(&root.join(run), regression, "out").exists())
+            .ok_or_else(|| {
+                io::Error::other(format!("no logs found for regression {}", regression))
+            })?,
+    };
+
+    let dir = root.join(&run);
+    if !log_file(&dir, regression, "out").exists() {
+        return Err(io::Error::other(format!(
+            "no logs found for regression {} in run {}",
+            regression, run
+        )));
+    }
+
+    println!("Run: {}", run);
+    for (title, extension) in [("stdout", "out"), ("stderr", "err")] {
+        let path = log_file(&dir, regression, extension);
+        println!("\x1b[0;32m==> {} ({})\x1b[0m", title, path.display());
+        print!("{}", String::from_utf8_lossy(&fs::read(&path)?));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    /// Writes the logs of the regression `blink` in each of the runs.
+    fn logged(runs: &[&str]) -> (TempDir, String) {
+        let dir = TempDir::new("logs").unwrap();
+        let target = dir.path().to_str().unwrap().to_string();
+        for run in runs {
+            write(&logs_root(&target).join(run), "blink", b"out\n", b"err\n").unwrap();
+        }
+        (dir, target)
+    }
+
+    #[test]
+    fn writes_the_logs_of_a_regression() {
+        let (_dir, target) = logged(&[]);
+        let dir = logs_root(&target).join("20240401T020000Z-3fa9c1");
+        let base = write(&dir, "blink", b"built\n", b"warning\n").unwrap();
+        assert_eq!(base, dir.join("blink"));
+        assert_eq!(fs::read(dir.join("blink.out")).unwrap(), b"built\n");
+        assert_eq!(fs::read(dir.join("blink.err")).unwrap(), b"warning\n");
+        write_diff(&dir, "blink", "-1\n+2\n").unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("blink.diff")).unwrap(),
+            "-1\n+2\n"
+        );
+        let generated = Path::new(&target).join("out.txt");
+        fs::write(&generated, "2\n").unwrap();
+        write_generated(&dir, "blink", &generated).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("blink.generated")).unwrap(),
+            "2\n"
+        );
+    }
+
+    #[test]
+    fn lists_the_runs_oldest_first() {
+        let (_dir, target) = logged(&[
+            "20240402T020000Z-000000",
+            "20240401T020000Z-ffffff",
+            "20240403T020000Z-aaaaaa",
+        ]);
+        // Not a run
+        fs::write(logs_root(&target).join("notes.txt"), "").unwrap();
+        assert_eq!(
+            runs(&target).unwrap(),
+            [
+                "20240401T020000Z-ffffff",
+                "20240402T020000Z-000000",
+                "20240403T020000Z-aaaaaa"
+            ]
+        );
+        let (_dir, empty) = logged(&[]);
+        assert!(runs(&empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn prunes_all_but_the_most_recent_runs() {
+        let ids = [
+            "20240403T020000Z-000000",
+            "20240401T020000Z-000000",
+            "20240404T020000Z-000000",
+            "20240402T020000Z-000000",
+        ];
+        let (_dir, target) = logged(&ids);
+        prune(&target, 5, false).unwrap();
+        assert_eq!(runs(&target).unwrap().len(), 4);
+        prune(&target, 2, false).unwrap();
+        assert_eq!(
+            runs(&target).unwrap(),
+            ["20240403T020000Z-000000", "20240404T020000Z-000000"]
+        );
+        prune(&target, 0, false).unwrap();
+        assert!(runs(&target).unwrap().is_empty());
+    }
+
+    #[test]
+    fn removes_the_logs_of_a_regression_from_every_run() {
+        let ids = ["20240401T020000Z-000000", "20240402T020000Z-000000"];
+        let (_dir, target) = logged(&ids);
+        let first = logs_root(&target).join(ids[0]);
+        write(&first, "counter", b"", b"").unwrap();
+        write_diff(&first, "blink", "").unwrap();
+        assert_eq!(remove(&target, "blink").unwrap(), 2);
+        assert_eq!(remove(&target, "blink").unwrap(), 0);
+        assert!(!first.join("blink.diff").exists());
+        assert!(first.join("counter.out").exists());
+    }
+
+    #[test]
+    fn shows_only_the_logged_regressions() {
+        let (_dir, target) = logged(&["20240401T020000Z-000000"]);
+        assert!(show(&target, "blink", None).is_ok());
+        assert!(show(&target, "blink", Some("20240401T020000Z-000000")).is_ok());
+        let err = show(&target, "counter", None).unwrap_err();
+        assert_eq!(err.to_string(), "no logs found for regression counter");
+        let err = show(&target, "blink", Some("20240402T020000Z-000000")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no logs found for regression blink in run 20240402T020000Z-000000"
+        );
+    }
+}
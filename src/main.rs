@@ -22,12 +22,70 @@
 //! 5. Reports test status (passed/failed/differences)
 
 extern crate tempdir;
+
+mod allowlist;
+mod artifacts;
+mod atomic;
+mod badge;
+mod blame;
+mod changes;
+mod checkout;
+mod compare;
+mod config;
+mod diff;
+mod diskspace;
+mod effective;
+mod export;
+mod filecmp;
+mod gzip;
+mod header;
+mod history;
+mod import;
+mod interrupt;
+mod json;
+mod lfs;
+mod limits;
+mod logs;
+mod metrics;
+mod notes;
+mod notify;
+#[macro_use]
+mod output;
+mod paths;
+mod pattern;
+mod plan;
+mod present;
+mod provenance;
+mod quarantine;
+mod recorded;
+mod redact;
+mod report;
+mod repository;
+mod results;
+mod runner;
+mod schedule;
+mod schema;
+mod selftest;
+mod serve;
+mod setup;
+mod stats;
+mod style;
+mod summary;
+mod tagexpr;
+mod threshold;
+mod timestamp;
+mod toolchain;
+mod userconfig;
+mod watch;
+
 use clap::{Parser, Subcommand};
-use yaml_rust::YamlLoader;
+use present::{Presenter, RunResult, Status};
 
 use std::fs;
-use std::io::{self};
+use std::io::{self, IsTerminal, Write};
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
 use tempdir::TempDir;
 
 /// Command-line interface for the bmregression tool.
@@ -39,51 +97,897 @@ use tempdir::TempDir;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
-    /// The regression to run. If not specified, all regressions are considered. If the command is 'run', the regressions will be run according to the configured frequency
-    #[clap(short, long, default_value = "")]
-    reg_name: String,
+    /// The regression to select when the command is given no name, an error if it is given another one
+    #[clap(short, long)]
+    reg_name: Option<String>,
     /// Debug flag
     #[clap(short, long, default_value = "false")]
     debug: bool,
+    /// Debug output without truncation: the whole output of the commands and the raw configurations, implies --debug
+    #[clap(long, default_value = "false")]
+    debug_full: bool,
     /// The directory where the regression data is stored, if not specified, the data will be cloned from the data repository and discarded after the run
     #[clap(long, default_value = "")]
     data_dir: String,
     /// The directory where the examples repository is stored, if not specified, the data will be cloned from the data repository and discarded after the run
     #[clap(long, default_value = "")]
     examples_dir: String,
-    /// Example repository URL
+    /// Examples repository: a git URL, the path or file:// URL of a local git repository, or a plain directory to copy
     #[clap(
         long,
         default_value = "https://github.com/BondMachineHQ/bmexamples.git"
     )]
     examples_url: String,
-    /// Data repository URL
+    /// Data repository: a git URL, the path or file:// URL of a local git repository, or a plain directory to copy
     #[clap(
         long,
         default_value = "https://github.com/BondMachineHQ/bmregressiondata.git"
     )]
     data_url: String,
+    /// Additional examples repository, as name=location, used by the regressions with that examples key (repeatable)
+    #[clap(long = "examples", value_name = "NAME=LOCATION")]
+    named_examples: Vec<String>,
     /// Use the tools in the system instead of the ones installed from the official sources
     #[clap(short, long, default_value = "false")]
     system_tools: bool,
     /// Filter tests by tag(s). Multiple tags can be specified comma-separated. If not specified, only tests with 'default' tag are selected
     #[clap(short, long, default_value = "default")]
     tag: String,
+    /// Select the regressions whose tags satisfy this expression instead, e.g. "fpga and quick and not vivado", with and, or, not and parentheses
+    #[clap(long, value_name = "EXPR", conflicts_with = "tag")]
+    tag_expr: Option<String>,
+    /// Select only the regressions whose regbase, the example they run, contains this pattern (is this pattern with --exact), e.g. basys3_blink
+    #[clap(long, value_name = "PATTERN")]
+    regbase: Option<String>,
+    /// Keep the temporary working directory at the end of the run (it is always kept when a regression errors)
+    #[clap(long, default_value = "false")]
+    keep_temp: bool,
+    /// Use this directory as working directory instead of a temporary one, existing clones in it are reused
+    #[clap(long, default_value = "")]
+    workdir: String,
+    /// Create the temporary working directory under this directory, created if missing (default: TMPDIR or the system temporary directory)
+    #[clap(long, value_name = "PATH", conflicts_with = "workdir")]
+    tmp_dir: Option<String>,
+    /// Space in bytes the filesystem of the working directory must have free for the command to start, 0 to skip the check
+    #[clap(long, default_value = "1073741824")]
+    min_free_space: u64,
+    /// Show the output of the regression commands live, prefixed with the regression name
+    #[clap(long, default_value = "false")]
+    stream: bool,
+    /// Print a heartbeat line every this many seconds while a regression command runs, 0 to disable
+    #[clap(long, default_value = "60")]
+    heartbeat: u64,
+    /// Number of runs whose command logs are kept in the data directory
+    #[clap(long, default_value = "20")]
+    keep_logs: usize,
+    /// When to collect the artifacts listed in the regression configurations
+    #[clap(long, value_enum, default_value = "failure")]
+    collect_artifacts: artifacts::Collect,
+    /// Directory where the artifacts are collected, one subdirectory per run (default: in the data directory)
+    #[clap(long, default_value = "")]
+    artifacts_dir: String,
+    /// Identifier of the run, naming its logs, artifacts and history entry, instead of a generated one; refused if already in the history
+    #[clap(long, value_name = "ID")]
+    run_id: Option<String>,
+    /// Maximum number of diff lines printed for a regression
+    #[clap(long, default_value = "200")]
+    max_printed_diff_lines: usize,
+    /// Print the whole diff of each regression, however long
+    #[clap(long, default_value = "false")]
+    full_diff: bool,
+    /// Write the full unified diff of each regression with differences to <DIR>/<regression>.patch
+    #[clap(long, default_value = "")]
+    output_dir: String,
+    /// Allow sourcedata and targetdata to point outside the example and the regression directories
+    #[clap(long, default_value = "false")]
+    allow_external_paths: bool,
+    /// Exit with 3 on infrastructure errors: regressions that cannot be executed or are skipped because of configuration errors
+    #[clap(long, default_value = "false")]
+    strict: bool,
+    /// Exit with 4 from run and apply, instead of 1 on any failure, only when more regressions failed than this count, or percentage with a trailing %, e.g. 10%
+    #[clap(long)]
+    fail_threshold: Option<String>,
+    /// Count the regressions that could not be executed as failures of --fail-threshold, instead of leaving them out
+    #[clap(long, default_value = "false")]
+    threshold_counts_errors: bool,
+    /// Stop starting regressions in run and apply once the invocation has lasted this long, e.g. 1h45m, and exit with 5 listing those not run
+    #[clap(long, value_name = "DURATION")]
+    max_total_time: Option<String>,
+    /// Terminate the running regression command at the --max-total-time deadline instead of letting it finish
+    #[clap(long, default_value = "false")]
+    hard_deadline: bool,
+    /// Command run through the shell in the example directory of each regression that fails or errors, after its on_fail
+    #[clap(long, value_name = "CMD")]
+    on_fail: Option<String>,
+    /// Seconds after which the command of a regression without a timeout of its own is terminated, and the regression reported as an error
+    #[clap(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+    /// Seconds after which a clone of a repository is terminated, 0 to disable
+    #[clap(long, default_value = "600")]
+    clone_timeout: u64,
+    /// Number of times a failed clone of a repository is retried, with an increasing delay
+    #[clap(long, default_value = "2")]
+    clone_retries: u32,
+    /// Show the progress of git while cloning the repositories (not in quiet and JSON outputs)
+    #[clap(long, default_value = "false")]
+    verbose_clone: bool,
+    /// How the status of the regressions is printed (default: the status_style of the user configuration, or words)
+    #[clap(long, value_enum)]
+    status_style: Option<style::StatusStyle>,
+    /// Baseline of the regressions whose targetdata has variants, e.g. a release line of the toolchain
+    #[clap(long, default_value = config::DEFAULT_BASELINE)]
+    baseline: String,
+    /// Do not match --tag against the components of the regression paths, only against the tags of the configurations
+    #[clap(long, default_value = "false")]
+    no_implicit_tags: bool,
+    /// Size in bytes above which reset, update and lint warn about an expected output not stored with git LFS
+    #[clap(long, default_value = "5242880")]
+    baseline_warn_size: u64,
+    /// Size in bytes above which reset and update refuse to write an expected output not stored with git LFS, without --force
+    #[clap(long, default_value = "52428800")]
+    baseline_max_size: u64,
+    /// Size in bytes above which a generated output is not read and its regression fails, unless its max_output_size says otherwise
+    #[clap(long, default_value = "536870912")]
+    max_output_size: u64,
+    /// Select only the regression whose name is exactly the given one, instead of all those containing it
+    #[clap(long, global = true, default_value = "false")]
+    exact: bool,
+    /// Print each regression command, with its directory and environment, right before running it
+    #[clap(long, default_value = "false")]
+    show_commands: bool,
+    /// Run the regression commands with sh -x, tracing their steps into their captured output
+    #[clap(long, default_value = "false")]
+    shell_trace: bool,
+    /// Trust the results of the regressions whose examples checkout changed while their command ran, instead of flagging them unreliable
+    #[clap(long, default_value = "false")]
+    ignore_source_changes: bool,
+    /// Run the regression commands with a minimal environment: PATH, HOME, TMPDIR, and the env and env_passthrough of each regression
+    #[clap(long, default_value = "false")]
+    clean_env: bool,
+    /// Glob pattern of the names of the environment variables whose values are redacted, besides *_TOKEN, *_SECRET and *PASSWORD* (repeatable)
+    #[clap(long = "secret-pattern", value_name = "GLOB")]
+    secret_patterns: Vec<String>,
+    /// File of patterns of the permitted commands, one per line: the regressions whose regcommand or difftool matches none are blocked instead of executed
+    #[clap(long, value_name = "FILE")]
+    command_allowlist: Option<String>,
 }
 
 /// Available subcommands for regression test operations.
 #[derive(Subcommand)]
 enum Commands {
     /// List the available regressions
-    List { name: Option<String> },
+    List {
+        /// Names of the regressions, those matching any of them are selected, all if none is given
+        names: Vec<String>,
+        /// Show the status and the age of the most recent result of each regression in the history
+        #[clap(long)]
+        status: bool,
+        /// Only list the regressions whose most recent result has one of these statuses, such as failed or "never run"
+        #[clap(long, value_name = "STATUS", value_delimiter = ',')]
+        filter_status: Vec<String>,
+    },
     /// Describe one or more regressions
-    Describe { name: Option<String> },
+    Describe {
+        /// Names of the regressions, those matching any of them are selected, all if none is given
+        names: Vec<String>,
+        /// Note the included file each value comes from
+        #[clap(short, long)]
+        verbose: bool,
+        /// Leave the examples unchecked, and unfetched, when only the data repository is available
+        #[clap(long, default_value = "false")]
+        no_verify: bool,
+        /// Describe every regression, whatever its tags
+        #[clap(long, default_value = "false", conflicts_with = "names")]
+        all: bool,
+        /// Output format, yaml printing the configurations without checking the checkouts
+        #[clap(long, value_enum, default_value = "text")]
+        format: effective::Format,
+        /// With --format yaml, print the effective configurations, every default included, sorted for diffing
+        #[clap(long, default_value = "false")]
+        canonical: bool,
+    },
     /// Run one or more regressions
-    Run { name: Option<String> },
+    Run {
+        /// Names of the regressions, those matching any of them are selected, all if none is given
+        names: Vec<String>,
+        /// Capture the generated output as the expected one when it does not exist yet
+        #[clap(short, long, default_value = "false")]
+        bootstrap: bool,
+        /// Only run the regressions that are due according to their frequency and the history
+        #[clap(long, default_value = "false")]
+        due: bool,
+        /// Only run the regressions that failed, errored or were not run for lack of time in their last recorded run
+        #[clap(long, default_value = "false")]
+        failed: bool,
+        /// Only run the regressions whose example changed since this git reference of the examples
+        #[clap(long, value_name = "REF")]
+        since: Option<String>,
+        /// Only run the regressions whose data changed since this git reference of the data repository
+        #[clap(long, value_name = "REF")]
+        since_data: Option<String>,
+        /// Print the differences of the failing regressions after their status line
+        #[clap(long, default_value = "false")]
+        diff_on_fail: bool,
+        /// Count the results of each group of regressions in the recap and the summary
+        #[clap(long, value_enum, value_name = "CRITERION")]
+        group_by: Option<results::GroupBy>,
+        /// Run the smokecommand of the regressions instead of their regcommand, skipping those without one
+        #[clap(long, default_value = "false")]
+        smoke: bool,
+        /// Run the command of each regression N more times after a warm-up, reporting the spread of their durations, the output of the last one being compared
+        #[clap(long, value_name = "N")]
+        bench: Option<u32>,
+        /// Run up to N regressions at the same time, never two of the same example directory, their results shown in order
+        #[clap(short, long, value_name = "N", default_value = "1")]
+        jobs: usize,
+        #[command(flatten)]
+        reports: ReportOptions,
+    },
     /// Reset one or more regressions
-    Reset { name: Option<String> },
-    /// Diff the results of one or more regressions
-    Diff { name: Option<String> },
+    Reset {
+        /// Names of the regressions, those matching any of them are selected, all if none is given
+        names: Vec<String>,
+        /// Show the differences and ask for confirmation before resetting each regression
+        #[clap(short, long, default_value = "false", conflicts_with = "preview")]
+        interactive: bool,
+        /// Show what would change without writing anything, exit with 1 if any baseline would change
+        #[clap(short, long, default_value = "false", conflicts_with = "commit")]
+        preview: bool,
+        /// Commit the reset expected outputs in the data repository
+        #[clap(short, long, default_value = "false")]
+        commit: bool,
+        /// Overwrite the hand-written expected outputs too, those of the regex_lines and contains regressions, and write those above --baseline-max-size
+        #[clap(short, long, default_value = "false")]
+        force: bool,
+        /// Overwrite the protected expected outputs too
+        #[clap(long, default_value = "false")]
+        override_protected: bool,
+        /// Candidate expected output to overwrite, for the regressions with several of them
+        #[clap(long)]
+        reset_candidate: Option<String>,
+        /// Store the expected outputs gzip-compressed, renaming them to .gz in config.yaml
+        #[clap(long, default_value = "false", conflicts_with_all = ["preview", "smoke"])]
+        compress: bool,
+        /// Reset the expected outputs of the smokecommand of the regressions, see run --smoke
+        #[clap(long, default_value = "false")]
+        smoke: bool,
+    },
+    /// Run one or more regressions and reset only the ones whose output changed
+    Update {
+        name: Option<String>,
+        /// Commit the updated expected outputs in the data repository
+        #[clap(short, long, default_value = "false")]
+        commit: bool,
+        /// Overwrite the hand-written expected outputs too, those of the regex_lines and contains regressions, and write those above --baseline-max-size
+        #[clap(short, long, default_value = "false")]
+        force: bool,
+        /// Overwrite the protected expected outputs too
+        #[clap(long, default_value = "false")]
+        override_protected: bool,
+        /// Candidate expected output to overwrite, for the regressions with several of them
+        #[clap(long)]
+        reset_candidate: Option<String>,
+        /// Update the expected outputs of the smokecommand of the regressions, see run --smoke
+        #[clap(long, default_value = "false")]
+        smoke: bool,
+    },
+    /// Diff the results of one or more regressions, exit with 1 if any differs and 2 on errors
+    Diff {
+        /// Names of the regressions, those matching any of them are selected, all if none is given
+        names: Vec<String>,
+        /// Only print the status of each regression, without the differences
+        #[clap(short, long, default_value = "false")]
+        quiet: bool,
+        /// Command showing the differences, run through the shell with the generated and expected files appended
+        #[clap(long)]
+        difftool: Option<String>,
+        /// Diff the outputs of the smokecommand of the regressions, see run --smoke
+        #[clap(long, default_value = "false")]
+        smoke: bool,
+    },
+    /// Check the configurations of the regressions without running them, exit with 1 if any is invalid
+    Validate { name: Option<String> },
+    /// Check the data repository for problems such as colliding regression names, exit with 1 if any is found
+    Lint {
+        name: Option<String>,
+        /// Warn about the expected outputs whose provenance is older than this, e.g. 180d
+        #[clap(long, value_name = "DURATION", default_value = "365d")]
+        max_baseline_age: String,
+    },
+    /// List the tags of the regressions, the explicit ones and those derived from their paths
+    Tags { name: Option<String> },
+    /// Show the results of the most recent recorded run
+    Status {
+        /// Write a badge of the results to this SVG file
+        #[clap(long)]
+        badge: Option<String>,
+    },
+    /// Show the git history of the expected output and of the configuration of a regression
+    Blame {
+        regression: String,
+        /// Number of commits of the expected output shown
+        #[clap(long, default_value = "5")]
+        count: usize,
+        /// Also show the changes of the last commit of the expected output
+        #[clap(long)]
+        patch: bool,
+    },
+    /// Show the command logs of a regression from the most recent run that executed it
+    Logs {
+        regression: String,
+        /// Show the logs of this run instead of the most recent one
+        #[clap(long)]
+        run: Option<String>,
+    },
+    /// Open an interactive shell where the command of a regression runs, with its environment, to debug it
+    Shell { regression: String },
+    /// Append a timestamped note to the notes.md of a regression in the data directory, shown by describe and on failures
+    Note {
+        regression: String,
+        /// Text of the note
+        #[clap(required_unless_present = "list", conflicts_with = "list")]
+        text: Option<String>,
+        /// Print all the notes of the regression instead, oldest first
+        #[clap(long, default_value = "false")]
+        list: bool,
+    },
+    /// Show pass rates and durations of the regressions over the recorded runs
+    Stats {
+        name: Option<String>,
+        /// Number of most recent runs considered
+        #[clap(long, default_value = "20")]
+        last: usize,
+        /// Output format
+        #[clap(long, value_enum, default_value = "text")]
+        format: report::Format,
+        /// Read the runs from the local history or from the results recorded in the data repository
+        #[clap(long, value_enum, default_value = "history")]
+        source: history::Source,
+    },
+    /// Compare two recorded runs, exit with 1 if any regression newly fails
+    CompareRuns {
+        /// Identifier of the first (older) run
+        run_a: String,
+        /// Identifier of the second (newer) run
+        run_b: String,
+        /// Highlight duration changes larger than this percentage
+        #[clap(long, default_value = "20")]
+        duration_threshold: f64,
+        /// Output format
+        #[clap(long, value_enum, default_value = "text")]
+        format: report::Format,
+        /// Read the runs from the local history or from the results recorded in the data repository
+        #[clap(long, value_enum, default_value = "history")]
+        source: history::Source,
+    },
+    /// Run the regressions and record the results in the results directory of the data repository
+    Record {
+        name: Option<String>,
+        /// Commit the results file, and the removal of the pruned ones, in the data repository
+        #[clap(short, long, default_value = "false")]
+        commit: bool,
+        /// Number of most recent results files kept in the data repository
+        #[clap(long, default_value = "30")]
+        keep: usize,
+    },
+    /// Run the regressions again whenever their example, configuration or expected outputs change, until interrupted
+    Watch {
+        /// The regressions to watch
+        name: Option<String>,
+        /// Glob of the files whose changes are ignored, matched against the path relative to the watched directory and against the file name (repeatable)
+        #[clap(long = "ignore", value_name = "GLOB")]
+        ignore: Vec<String>,
+        /// Milliseconds the files must be left unchanged before the regressions are run again
+        #[clap(long, default_value = "500")]
+        debounce: u64,
+    },
+    /// Stay resident and run the regressions that are due according to their frequency, until terminated
+    Schedule {
+        name: Option<String>,
+        /// Time between the starts of two cycles, e.g. 30m, 1h or 1d
+        #[clap(long, default_value = "1h")]
+        interval: String,
+        /// POST a JSON summary of each cycle that ran regressions to this webhook URL
+        #[clap(long)]
+        notify_webhook: Option<String>,
+        /// When the webhook notification is sent
+        #[clap(long, value_enum, default_value = "failure")]
+        notify_on: notify::NotifyOn,
+    },
+    /// Serve an HTTP API listing the regressions and running them on request, until interrupted
+    Serve {
+        /// Address and port to listen on
+        #[clap(long, default_value = "127.0.0.1:8642")]
+        listen: String,
+    },
+    /// Rewrite the configurations in the canonical form, or convert the data repository in bulk, such as compressing the large expected outputs
+    Migrate {
+        name: Option<String>,
+        /// Show the changes to the configurations without writing them, exit with 1 if any would change
+        #[clap(long, default_value = "false", conflicts_with = "compress_baselines")]
+        dry_run: bool,
+        /// Compress the expected outputs, renaming them to .gz in config.yaml
+        #[clap(long, default_value = "false")]
+        compress_baselines: bool,
+        /// Size from which the expected outputs are compressed, in bytes
+        #[clap(long, default_value = "1048576")]
+        min_size: u64,
+    },
+    /// Print the JSON Schema of config.yaml, for the editors
+    Schema,
+    /// Check that this binary works, running it on a bundled fixture without network access
+    SelfTest {
+        /// Keep the directory of the fixture, to inspect it
+        #[clap(long)]
+        keep: bool,
+    },
+    /// Create a regression as a copy of another one, to bootstrap its baseline with run --bootstrap
+    Copy {
+        /// Regression to copy
+        source: String,
+        /// Name of the new regression
+        name: String,
+        /// Directory of the example of the new regression in the examples repository
+        #[clap(long)]
+        regbase: Option<String>,
+        /// Command generating the output of the new regression
+        #[clap(long)]
+        regcommand: Option<String>,
+        /// Copy the expected outputs too, when the new regression generates the same output
+        #[clap(long, default_value = "false", conflicts_with = "no_baseline")]
+        with_baseline: bool,
+        /// Leave the new regression without expected outputs, the default
+        #[clap(long, default_value = "false")]
+        no_baseline: bool,
+    },
+    /// Create a regression for an example, proposing its configuration from its Makefile and outputs
+    Import {
+        /// Directory of the example in the examples repository
+        example: String,
+        /// Name of the new regression, the example directory with / replaced by _ by default
+        #[clap(long)]
+        name: Option<String>,
+        /// Output file, relative to the example directory, instead of the detected ones
+        #[clap(long)]
+        sourcedata: Option<String>,
+        /// Command generating the output, instead of the detected make target
+        #[clap(long)]
+        regcommand: Option<String>,
+        /// Write the configuration without asking, failing if several commands or outputs are found
+        #[clap(short, long, default_value = "false")]
+        yes: bool,
+        /// Run the new regression once to capture its expected output
+        #[clap(short, long, default_value = "false")]
+        bootstrap: bool,
+    },
+    /// Delete a regression from the data directory with its history, logs and quarantine entry, or without a name those with the --tag tags
+    Remove {
+        name: Option<String>,
+        /// Remove without asking for confirmation
+        #[clap(short, long, default_value = "false")]
+        yes: bool,
+    },
+    /// Package the summary, logs, differences and artifacts of a run in a gzipped tarball
+    Export {
+        /// Export this run instead of the most recent one
+        #[clap(long)]
+        run: Option<String>,
+        /// Path of the archive, bmregression-<run-id>.tar.gz by default
+        #[clap(long)]
+        out: Option<String>,
+        /// Leave the collected artifacts out of the archive
+        #[clap(long)]
+        no_artifacts: bool,
+    },
+    /// Take the generated outputs of an archive written by export as the expected outputs, without running anything
+    ImportBaselines {
+        /// Archive written by export, or a directory laid out like one
+        archive: String,
+        /// Names of the regressions, those matching any of them are selected, all if none is given
+        names: Vec<String>,
+        /// Commit the imported expected outputs in the data repository
+        #[clap(short, long, default_value = "false")]
+        commit: bool,
+        /// Overwrite the hand-written expected outputs too, those of the regex_lines and contains regressions, and write those above --baseline-max-size
+        #[clap(short, long, default_value = "false")]
+        force: bool,
+        /// Overwrite the protected expected outputs too
+        #[clap(long, default_value = "false")]
+        override_protected: bool,
+        /// Candidate expected output to overwrite, for the regressions with several of them
+        #[clap(long)]
+        reset_candidate: Option<String>,
+    },
+    /// Write the plan of a run, the regressions it would execute with their commands, comparisons and paths, without executing them
+    Plan {
+        name: Option<String>,
+        /// File the plan is written to
+        #[clap(long, default_value = "plan.json")]
+        out: String,
+    },
+    /// Execute the regressions of a plan, refusing if the checkouts moved from the planned commits or the regressions changed
+    Apply {
+        /// Plan written by the plan command
+        plan: String,
+        /// Print the differences of the failing regressions after their status line
+        #[clap(long, default_value = "false")]
+        diff_on_fail: bool,
+        #[command(flatten)]
+        reports: ReportOptions,
+    },
+    /// Print the version, with --verbose also the build, the OS and the detected tools
+    Version {
+        /// Print the details to paste in bug reports
+        #[clap(short, long)]
+        verbose: bool,
+    },
+}
+
+impl Commands {
+    /// Returns the names of the regressions the command selects, None if it takes a single one
+    /// or none.
+    fn names_mut(&mut self) -> Option<&mut Vec<String>> {
+        match self {
+            Commands::List { names, .. }
+            | Commands::Describe { names, .. }
+            | Commands::Run { names, .. }
+            | Commands::Reset { names, .. }
+            | Commands::Diff { names, .. }
+            | Commands::ImportBaselines { names, .. } => Some(names),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the regressions the command selects, None if it selects none by name.
+    fn name_mut(&mut self) -> Option<&mut Option<String>> {
+        match self {
+            Commands::Update { name, .. }
+            | Commands::Validate { name }
+            | Commands::Lint { name, .. }
+            | Commands::Tags { name }
+            | Commands::Stats { name, .. }
+            | Commands::Record { name, .. }
+            | Commands::Watch { name, .. }
+            | Commands::Schedule { name, .. }
+            | Commands::Migrate { name, .. }
+            | Commands::Remove { name, .. }
+            | Commands::Plan { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Makes `--reg-name` the name of the command when it is given none.
+///
+/// # Returns
+///
+/// A warning if the command selects no regression by name, so `--reg-name` is ignored, or an
+/// error if the command is given another name.
+fn resolve_reg_name(args: &mut Cli) -> Result<Option<String>, String> {
+    let Some(reg_name) = args.reg_name.clone() else {
+        return Ok(None);
+    };
+    if let Some(names) = args.command.as_mut().and_then(Commands::names_mut) {
+        return match names.as_slice() {
+            [] => {
+                names.push(reg_name);
+                Ok(None)
+            }
+            [name] if *name == reg_name => Ok(None),
+            [name] => Err(format!(
+                "the command is given the name {} and --reg-name {}, give only one of them",
+                name, reg_name
+            )),
+            _ => Err(format!(
+                "the command is given the names {} and --reg-name {}, give only one of them",
+                names.join(", "),
+                reg_name
+            )),
+        };
+    }
+    let Some(name) = args.command.as_mut().and_then(Commands::name_mut) else {
+        return Ok(Some(format!(
+            "Warning: the command selects no regression by name, --reg-name {} is ignored",
+            reg_name
+        )));
+    };
+    match name {
+        Some(name) if *name != reg_name => Err(format!(
+            "the command is given the name {} and --reg-name {}, give only one of them",
+            name, reg_name
+        )),
+        Some(_) => Ok(None),
+        None => {
+            *name = Some(reg_name);
+            Ok(None)
+        }
+    }
+}
+
+/// Reports and notifications produced at the end of a run.
+#[derive(clap::Args)]
+struct ReportOptions {
+    /// Write a self-contained HTML report of the run to this file
+    #[clap(long)]
+    html_report: Option<String>,
+    /// Write a structured report of the run to this file, in the --format
+    #[clap(long, value_name = "PATH")]
+    report: Option<String>,
+    /// Format of the --report file
+    #[clap(
+        long = "format",
+        value_enum,
+        default_value = "json",
+        requires = "report"
+    )]
+    report_format: report::ReportFormat,
+    /// Emit GitHub Actions error annotations and step summary, automatic when GITHUB_STEP_SUMMARY is set
+    #[clap(long, default_value = "false")]
+    github: bool,
+    /// POST a JSON summary of the run to this webhook URL (Slack and Mattermost compatible)
+    #[clap(long)]
+    notify_webhook: Option<String>,
+    /// When the webhook notification is sent
+    #[clap(long, value_enum, default_value = "failure")]
+    notify_on: notify::NotifyOn,
+    /// Write the results in the Prometheus textfile collector format to this file
+    #[clap(long)]
+    metrics_file: Option<String>,
+    /// Push the results to this Prometheus Pushgateway
+    #[clap(long)]
+    pushgateway: Option<String>,
+    /// Write a badge of the results to this SVG file
+    #[clap(long)]
+    badge: Option<String>,
+    /// Show a desktop notification with the counts when the run finishes, or ring the terminal bell, never in CI
+    #[clap(long, default_value = "false")]
+    notify_desktop: bool,
+    /// Leave the host name out of the environment recorded in the reports and the history
+    #[clap(long, default_value = "false")]
+    redact_host: bool,
+}
+
+/// Wall-clock budget of a run, from `--max-total-time`.
+#[derive(Debug, Clone, Copy)]
+struct Deadline {
+    /// When the budget is spent
+    at: std::time::Instant,
+    budget: std::time::Duration,
+    /// Terminate the running command at the deadline, instead of letting it finish
+    hard: bool,
+}
+
+impl Deadline {
+    /// Returns true once the budget is spent.
+    fn reached(&self) -> bool {
+        std::time::Instant::now() >= self.at
+    }
+
+    /// Returns the time left before the deadline, zero once it is reached.
+    fn remaining(&self) -> std::time::Duration {
+        self.at.saturating_duration_since(std::time::Instant::now())
+    }
+}
+
+/// Settings and state shared by the regressions handled in a single invocation.
+struct Context {
+    /// Path to the examples directory
+    source: String,
+    /// Paths of the additional examples directories, by name
+    named_sources: Vec<(String, String)>,
+    /// Path to the regression data directory
+    target: String,
+    /// Tags to filter by (regressions must match at least one tag), none with `tag_expr`
+    tags: Vec<String>,
+    /// Expression over the tags selecting the regressions instead of `tags`
+    tag_expr: Option<tagexpr::TagExpr>,
+    /// Pattern the regbase of the selected regressions matches, any regbase if None
+    regbase: Option<String>,
+    /// Select the regressions whatever their tags, ignoring `tags` and `tag_expr`
+    any_tag: bool,
+    /// Enable debug output
+    debug: bool,
+    /// Print the debug output without truncating it
+    debug_full: bool,
+    /// Relay the output of the regression commands live
+    stream: bool,
+    /// Interval of the heartbeat lines printed while a command runs
+    heartbeat: Option<std::time::Duration>,
+    /// Number of regressions whose execution ended with an error
+    errors: AtomicUsize,
+    /// Number of regressions skipped because their configuration is broken
+    config_errors: AtomicUsize,
+    /// Results of the regressions executed so far
+    results: Mutex<Vec<results::RegressionResult>>,
+    /// Identifier of this run, naming its logs and artifacts directories
+    run_id: String,
+    /// Directory where the command logs of this run are written
+    log_dir: std::path::PathBuf,
+    /// When the artifacts of the regressions are collected
+    collect_artifacts: artifacts::Collect,
+    /// Directory where the artifacts of this run are collected
+    artifacts_dir: std::path::PathBuf,
+    /// Maximum number of diff lines printed for a regression, unlimited if None
+    max_printed_diff_lines: Option<usize>,
+    /// Directory where the full diffs are written as patch files
+    patch_dir: Option<std::path::PathBuf>,
+    /// Diff tool given on the command line, overriding the regression and the user ones
+    difftool: Option<String>,
+    /// Settings of the user configuration file
+    user_config: userconfig::UserConfig,
+    /// Known-flaky regressions whose failures do not fail the invocation
+    quarantine: quarantine::Quarantine,
+    /// Only print the status lines, without the differences
+    quiet: bool,
+    /// Note where the configuration values come from when describing
+    verbose: bool,
+    /// Check the example and the expected outputs when describing
+    verify: bool,
+    /// Allow configuration paths pointing outside their directories
+    allow_external_paths: bool,
+    /// How the status lines of the regressions are printed
+    status_style: style::StatusStyle,
+    /// Baseline whose expected outputs are compared and reset
+    baseline: String,
+    /// Match the requested tags against the components of the regression paths too
+    implicit_tags: bool,
+    /// Let reset and update overwrite the hand-written expected outputs and write large ones
+    force: bool,
+    /// Let reset and update overwrite the protected expected outputs
+    override_protected: bool,
+    /// Run the smokecommand of the regressions, against its own outputs, instead of their regcommand
+    smoke: bool,
+    /// Timed iterations of the command of each regression run after a warm-up, with `run --bench`
+    bench: Option<u32>,
+    /// Number of regressions run at the same time, with `run --jobs`
+    jobs: usize,
+    /// Timeout in seconds of the commands of the regressions without one
+    timeout: Option<u64>,
+    /// Candidate expected output reset and update overwrite when a regression has several
+    reset_candidate: Option<String>,
+    /// Let reset store the expected outputs compressed
+    compress: bool,
+    /// Size above which writing an expected output not stored with git LFS is warned about
+    baseline_warn_size: u64,
+    /// Size above which writing an expected output not stored with git LFS is refused
+    baseline_max_size: u64,
+    /// Size above which a generated output is not read, for the regressions not setting one
+    max_output_size: u64,
+    /// Budget of the run, after which no regression is started
+    deadline: Option<Deadline>,
+    /// Command run after the on_fail of each regression that fails or errors
+    on_fail: Option<String>,
+    /// Select the regressions by their exact name instead of a part of it
+    exact: bool,
+    /// Print the differences of the failing regressions when running them
+    diff_on_fail: bool,
+    /// Print the regression commands before running them
+    show_commands: bool,
+    /// Trace the steps of the regression commands with sh -x
+    shell_trace: bool,
+    /// Do not check whether the examples checkout changes while a regression command runs
+    ignore_source_changes: bool,
+    /// Run all the regression commands with a minimal environment
+    clean_env: bool,
+    /// Patterns of the commands the regressions may run, any command if None
+    command_allowlist: Option<allowlist::Allowlist>,
+    /// Executes the regression commands
+    runner: std::sync::Arc<dyn runner::CommandRunner>,
+    /// Header of the last run, recorded with its options
+    header: Mutex<Option<header::RunHeader>>,
+    /// Last commits of the expected outputs, shown with the failures
+    last_changes: blame::LastChanges,
+    /// Writes the provenance of the expected outputs written by reset, update and bootstrap
+    provenance: provenance::Recorder,
+}
+
+impl Context {
+    /// Returns the results of the regressions executed so far, the regressions executing
+    /// concurrently adding theirs.
+    fn results(&self) -> MutexGuard<'_, Vec<results::RegressionResult>> {
+        self.results.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Returns the header of the last run.
+    fn header(&self) -> MutexGuard<'_, Option<header::RunHeader>> {
+        self.header.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Returns true if a regression is selected by the requested tags, or by the tag
+    /// expression if one is given, the tag groups expanded, or by any tag with `any_tag`;
+    /// `has_any` tells whether it has any of a list of tags.
+    fn selects_tags(
+        &self,
+        groups: &[config::TagGroup],
+        has_any: &dyn Fn(&[String]) -> bool,
+    ) -> bool {
+        if self.any_tag {
+            return true;
+        }
+        match &self.tag_expr {
+            Some(expr) => {
+                expr.matches(&|tag| has_any(&config::expand_tags(&[tag.to_string()], groups)))
+            }
+            None => has_any(&config::expand_tags(&self.tags, groups)),
+        }
+    }
+
+    /// Returns true if the regbase of a regression matches the one requested, or none is.
+    fn selects_regbase(&self, config: &config::RegressionConfig) -> bool {
+        match &self.regbase {
+            Some(regbase) => self.selects(&config.regbase, regbase),
+            None => true,
+        }
+    }
+
+    /// Describes the regressions selected by the tags and the regbase, e.g. `tagged fpga,
+    /// nightly, with a regbase matching basys3_blink`.
+    fn tagged(&self) -> String {
+        let tagged = match &self.tag_expr {
+            _ if self.any_tag => "whatever their tags".to_string(),
+            Some(expr) => format!("matching the tag expression {}", expr),
+            None => format!("tagged {}", self.tags.join(", ")),
+        };
+        match &self.regbase {
+            Some(regbase) => format!("{}, with a regbase matching {}", tagged, regbase),
+            None => tagged,
+        }
+    }
+
+    /// Returns true if the name of a regression matches the name requested on the command line.
+    fn selects(&self, name: &str, regression_name: &str) -> bool {
+        if self.exact {
+            name == regression_name
+        } else {
+            name.contains(regression_name)
+        }
+    }
+
+    /// Returns true if the name of a regression matches any of the names requested on the
+    /// command line, or none is requested.
+    fn selects_any(&self, name: &str, regression_names: &[String]) -> bool {
+        regression_names.is_empty()
+            || regression_names
+                .iter()
+                .any(|regression_name| self.selects(name, regression_name))
+    }
+
+    /// Returns the path of the examples directory a regression takes its regbase from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the defined repositories if the regression names an unknown one.
+    fn examples_dir(&self, config: &config::RegressionConfig) -> Result<&str, io::Error> {
+        let Some(name) = &config.examples else {
+            return Ok(&self.source);
+        };
+        match self
+            .named_sources
+            .iter()
+            .find(|(defined, _)| defined == name)
+        {
+            Some((_, dir)) => Ok(dir),
+            None if self.named_sources.is_empty() => Err(io::Error::other(format!(
+                "examples repository {} is not defined, none is (use --examples {}=<location>)",
+                name, name
+            ))),
+            None => Err(io::Error::other(format!(
+                "examples repository {} is not defined, the defined ones are {}",
+                name,
+                self.named_sources
+                    .iter()
+                    .map(|(defined, _)| defined.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// Reports the error of a regression, with its output, and counts it.
+    fn report_error(&self, message: String) {
+        say!("{}", message);
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 /// Main entry point for the bmregression tool.
@@ -91,10 +995,10 @@ enum Commands {
 /// # Workflow
 ///
 /// 1. Parses command-line arguments
-/// 2. Creates a temporary working directory
+/// 2. Creates a temporary working directory (or uses the one given with `--workdir`)
 /// 3. Clones or uses existing repositories (bmexamples and bmregressiondata)
 /// 4. Executes the requested command
-/// 5. Cleans up temporary resources
+/// 5. Cleans up temporary resources, unless asked to keep them or a regression errored
 ///
 /// # Errors
 ///
@@ -104,533 +1008,5960 @@ enum Commands {
 /// - Temporary directory creation fails
 /// - Any regression operation fails
 fn main() -> Result<(), io::Error> {
-    let args = Cli::parse();
+    // The time budget includes fetching the repositories
+    let invoked = std::time::Instant::now();
+    let mut args = Cli::parse();
+    if args.debug_full {
+        args.debug = true;
+    }
 
     // Ensure a command is specified
     if args.command.is_none() {
         println!("No command specified");
         ::std::process::exit(1);
     }
-
-    // Create a temporary directory for cloned repositories and intermediate files
-    let tmp_dir = TempDir::new("bmregression")?;
-    if args.debug {
-        println!("Working directory: {}", tmp_dir.path().display());
-    }
-
-    // Setup examples repository (either use provided directory or clone)
-    let mut srcdir = args.examples_dir.clone();
-    if args.examples_dir.is_empty() {
-        let clone_dir = tmp_dir.path().join("examples");
-        let clone_url = args.examples_url;
-        if args.debug {
-            println!(
-                "Cloning examples repository from: {} to {}",
-                clone_url,
-                clone_dir.display()
-            );
-        }
-
-        let git_clone = Command::new("git")
-            .arg("clone")
-            .arg(clone_url)
-            .arg(clone_dir)
-            .output()?;
-        if !git_clone.status.success() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Error cloning examples repository",
-            ));
+    match resolve_reg_name(&mut args) {
+        Ok(Some(warning)) => println!("{}", warning),
+        Ok(None) => {}
+        Err(err) => {
+            println!("Error: {}", err);
+            ::std::process::exit(1);
         }
-        srcdir = tmp_dir
-            .path()
-            .join("examples")
-            .to_str()
-            .unwrap()
-            .to_string();
     }
 
-    // Setup regression data repository (either use provided directory or clone)
-    let mut tgtdir = args.data_dir;
-    if tgtdir.is_empty() {
-        let clone_dir = tmp_dir.path().join("regressiondata");
-        let clone_url = args.data_url;
-        if args.debug {
-            println!(
-                "Cloning regression data repository from: {} to {}",
-                clone_url,
-                clone_dir.display()
-            );
-        }
-
-        let git_clone = Command::new("git")
-            .arg("clone")
-            .arg(clone_url)
-            .arg(clone_dir)
-            .output()?;
-        if !git_clone.status.success() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Error cloning regression data repository",
-            ));
+    // Nothing to clone to print the version
+    if let Some(Commands::Version { verbose }) = args.command {
+        if verbose {
+            toolchain::print_version_report();
+        } else {
+            println!("bmregression {}", env!("CARGO_PKG_VERSION"));
         }
-        tgtdir = tmp_dir
-            .path()
-            .join("regressiondata")
-            .to_str()
-            .unwrap()
-            .to_string();
+        return Ok(());
     }
-
-    // Parse tags into a vector for easier filtering
-    let tags: Vec<String> = args.tag.split(',').map(|s| s.trim().to_string()).collect();
-
-    // Execute the requested command
-    match args.command.unwrap() {
-        Commands::List { name } => {
-            if let Err(_) = list_regressions(
-                &srcdir,
-                &tgtdir,
-                &name.unwrap_or("".to_string()),
-                &tags,
-                args.debug,
-            ) {
-                println!("Error listing regressions");
-            }
-        }
-        Commands::Describe { name } => {
-            if let Err(_) = describe_regressions(
-                &srcdir,
-                &tgtdir,
-                &name.unwrap_or("".to_string()),
-                &tags,
-                args.debug,
-            ) {
-                println!("Error describing regressions");
-            }
+    if let Some(Commands::Schema) = args.command {
+        print!("{}", schema::render());
+        return Ok(());
+    }
+    if args.regbase.is_some() && matches!(&args.command, Some(Commands::Serve { .. })) {
+        println!("Error: --regbase does not apply to serve, whose requests select the regressions");
+        ::std::process::exit(1);
+    }
+    let tag_expr = match &args.tag_expr {
+        Some(_) if matches!(&args.command, Some(Commands::Serve { .. })) => {
+            println!("Error: --tag-expr does not apply to serve, whose requests give their tags");
+            ::std::process::exit(1);
         }
-        Commands::Run { name } => {
-            if let Err(err) = run_regressions(
-                &srcdir,
-                &tgtdir,
-                &name.unwrap_or("".to_string()),
-                &tags,
-                args.debug,
-            ) {
-                println!("Error executing regression: {}", err);
+        Some(text) => match tagexpr::TagExpr::parse(text) {
+            Ok(expr) => Some(expr),
+            Err(err) => {
+                println!("Error: invalid --tag-expr: {}", err);
+                for line in err.pointer(text).lines() {
+                    println!("  {}", line);
+                }
+                ::std::process::exit(1);
             }
+        },
+        None => None,
+    };
+    if let Some(Commands::SelfTest { keep }) = args.command {
+        ::std::process::exit(self_test(keep)?);
+    }
+
+    // From now on the secrets are kept out of what is printed and written
+    if let Err(err) = redact::init(&args.secret_patterns) {
+        println!("Error: {}", err);
+        ::std::process::exit(1);
+    }
+    let fail_threshold = match args
+        .fail_threshold
+        .as_deref()
+        .map(threshold::Threshold::parse)
+    {
+        Some(Err(err)) => {
+            println!("Error: {}", err);
+            ::std::process::exit(1);
         }
-        Commands::Reset { name } => {
-            if let Err(_) = reset_regressions(
-                &srcdir,
-                &tgtdir,
-                &name.unwrap_or("".to_string()),
-                &tags,
-                args.debug,
-            ) {
-                println!("Error resetting regressions");
-            }
+        Some(Ok(threshold)) => Some(threshold),
+        None => None,
+    };
+    let deadline = match args.max_total_time.as_deref().map(schedule::parse_interval) {
+        Some(Err(err)) => {
+            println!("Error: --max-total-time: {}", err);
+            ::std::process::exit(1);
         }
-        Commands::Diff { name } => {
-            if let Err(_) = diff_regressions(
-                &srcdir,
-                &tgtdir,
-                &name.unwrap_or("".to_string()),
-                &tags,
-                args.debug,
-            ) {
-                println!("Error diffing regressions");
-            }
+        // The resident modes have no end to budget
+        Some(Ok(budget))
+            if matches!(
+                args.command,
+                Some(Commands::Run { .. } | Commands::Apply { .. })
+            ) =>
+        {
+            Some(Deadline {
+                at: invoked + budget,
+                budget,
+                hard: args.hard_deadline,
+            })
         }
-    }
-
-    tmp_dir.close()?;
-    Ok(())
-}
+        _ => None,
+    };
 
-/// Lists available regression tests matching the given pattern.
-///
-/// # Arguments
-///
-/// * `_source` - Path to the examples directory (unused in listing)
-/// * `target` - Path to the regression data directory
-/// * `regression_name` - Filter pattern for regression names (empty string matches all)
-/// * `tags` - List of tags to filter by (tests must match at least one tag)
-/// * `debug` - Enable debug output
-///
-/// # Errors
-///
-/// Returns an error if the target directory cannot be read.
-///
-/// # Examples
-///
-/// Lists all regressions:
-/// ```text
-/// Regressions found:
-///     basys3_blink
-///     basys3_counter
-/// ```
-fn list_regressions(
-    _source: &str,
-    target: &str,
-    regression_name: &str,
-    tags: &[String],
-    debug: bool,
-) -> Result<(), io::Error> {
-    if debug {
-        println!("List of regressions matching: \"{}\"", regression_name);
-        println!("Filtering by tags: {:?}", tags);
+    let bench = match &args.command {
+        Some(Commands::Run { bench, .. }) => *bench,
+        _ => None,
+    };
+    if bench == Some(0) {
+        println!("Error: --bench needs at least 1 iteration");
+        ::std::process::exit(1);
+    }
+    let jobs = match &args.command {
+        Some(Commands::Run { jobs, .. }) => *jobs,
+        _ => 1,
+    };
+    // Beyond that the commands could not all be terminated on an interrupt
+    if !(1..=interrupt::MAX_CHILDREN).contains(&jobs) {
+        println!(
+            "Error: --jobs must be between 1 and {}",
+            interrupt::MAX_CHILDREN
+        );
+        ::std::process::exit(1);
+    }
+    if args.timeout == Some(0) {
+        println!("Error: --timeout needs at least 1 second");
+        ::std::process::exit(1);
     }
 
-    println!("Regressions found:");
-    let entries = fs::read_dir(target)?;
-    for entry in entries {
-        let entry = entry?;
-        let filename = entry.file_name();
-        // Skip .git directory
-        if filename.to_str().unwrap() == ".git" {
-            continue;
-        }
-        // Filter regressions by name pattern
-        if filename.to_str().unwrap().contains(regression_name) {
-            // Check if regression matches any of the requested tags
-            if check_regression_tags(target, filename.to_str().unwrap(), tags, debug) {
-                println!("\t{}", filename.to_str().unwrap());
-            }
+    // The interactive reset needs a terminal to ask the questions
+    if let Some(Commands::Reset {
+        interactive: true, ..
+    }) = args.command
+    {
+        if !io::stdin().is_terminal() {
+            println!("The --interactive option requires stdin to be a terminal");
+            ::std::process::exit(1);
         }
     }
 
-    Ok(())
-}
+    // Regressions created in or removed from a temporary clone would be lost
+    if matches!(
+        args.command,
+        Some(
+            Commands::Copy { .. }
+                | Commands::Import { .. }
+                | Commands::ImportBaselines { .. }
+                | Commands::Remove { .. }
+        )
+    ) && args.data_dir.is_empty()
+    {
+        println!(
+            "The copy, import, import-baselines and remove commands change the data directory, give it with --data-dir"
+        );
+        ::std::process::exit(1);
+    }
 
-/// Checks if a regression's tags match any of the requested tags.
-///
-/// # Arguments
-///
-/// * `target` - Path to the regression data directory
-/// * `regression_name` - Name of the regression to check
-/// * `requested_tags` - List of tags to match against
-/// * `debug` - Enable debug output
-///
-/// # Returns
-///
-/// Returns true if the regression has at least one tag that matches the requested tags,
-/// or if the regression has no tags defined and "default" is in the requested tags.
-fn check_regression_tags(
-    target: &str,
-    regression_name: &str,
-    requested_tags: &[String],
-    debug: bool,
-) -> bool {
-    let config_path = format!("{}/{}/config.yaml", target, regression_name);
+    // From now on an interrupt terminates the running command and stops the run cleanly
+    interrupt::install();
 
-    // If config doesn't exist, skip this regression
-    if !std::path::Path::new(&config_path).exists() {
-        return false;
+    // The additional examples repositories, each fetched once whatever the regressions using it
+    let mut named_examples: Vec<(String, String, String)> = Vec::new();
+    for definition in &args.named_examples {
+        let (name, location) = match repository::parse_named(definition) {
+            Ok(named) => named,
+            Err(err) => {
+                println!("Error: {}", err);
+                ::std::process::exit(1);
+            }
+        };
+        if named_examples
+            .iter()
+            .any(|(defined, _, _)| *defined == name)
+        {
+            println!("Error: examples repository {} defined twice", name);
+            ::std::process::exit(1);
+        }
+        let description = format!("{} examples", name);
+        named_examples.push((name, location, description));
     }
 
-    // Read and parse the config file
-    if let Ok(config_content) = fs::read_to_string(&config_path) {
-        if let Ok(parsed_config) = YamlLoader::load_from_str(&config_content) {
-            if let Some(config) = parsed_config.get(0) {
-                // Get tags from config, default to ["default"] if not present
-                let regression_tags = extract_tags_from_config(config);
+    let user_config = match userconfig::load() {
+        Ok(config) => config,
+        Err(err) => {
+            println!("Error reading the user configuration: {}", err);
+            ::std::process::exit(1);
+        }
+    };
 
-                if debug {
-                    println!(
-                        "Regression {} has tags: {:?}",
-                        regression_name, regression_tags
-                    );
-                }
+    let command_allowlist = match args
+        .command_allowlist
+        .as_deref()
+        .map(allowlist::Allowlist::load)
+    {
+        Some(Ok(allowlist)) => Some(allowlist),
+        Some(Err(err)) => {
+            println!("Error reading the command allowlist: {}", err);
+            ::std::process::exit(1);
+        }
+        None => None,
+    };
 
-                // Check if any requested tag matches any regression tag
-                return requested_tags
-                    .iter()
-                    .any(|tag| regression_tags.contains(tag));
+    // Create the working directory for cloned repositories and intermediate files, once its
+    // filesystem is known to have room for them
+    let work_dir = if args.workdir.is_empty() {
+        let parent = match &args.tmp_dir {
+            Some(tmp_dir) => {
+                fs::create_dir_all(tmp_dir)?;
+                std::path::PathBuf::from(tmp_dir)
             }
-        }
+            None => std::env::temp_dir(),
+        };
+        check_free_space(&parent, args.min_free_space);
+        WorkDir::Temp(TempDir::new_in(&parent, "bmregression")?)
+    } else {
+        fs::create_dir_all(&args.workdir)?;
+        check_free_space(std::path::Path::new(&args.workdir), args.min_free_space);
+        println!("Working directory: {}", args.workdir);
+        WorkDir::Fixed(std::path::PathBuf::from(&args.workdir))
+    };
+    if args.debug {
+        println!("Working directory: {}", work_dir.path().display());
     }
 
-    false
-}
+    // Where the repositories come from, for the environment recorded by runs
+    let command_line = redacted_command_line(std::env::args());
+    let examples_location = if args.examples_dir.is_empty() {
+        redact::text(&args.examples_url)
+    } else {
+        args.examples_dir.clone()
+    };
+    let data_location = if args.data_dir.is_empty() {
+        redact::text(&args.data_url)
+    } else {
+        args.data_dir.clone()
+    };
 
-/// Extracts tags from a YAML config, defaulting to ["default"] if not present.
-///
-/// # Arguments
-///
-/// * `config` - The parsed YAML configuration
-///
-/// # Returns
-///
-/// A vector of tag strings
-fn extract_tags_from_config(config: &yaml_rust::Yaml) -> Vec<String> {
-    if let Some(tags_yaml) = config["tags"].as_vec() {
-        tags_yaml
+    // The commands of the clones and of the regressions
+    let processes: std::sync::Arc<dyn runner::CommandRunner> =
+        std::sync::Arc::new(runner::ProcessRunner);
+    // The quiet and JSON outputs must stay free of progress and heartbeat lines
+    let bare_output = matches!(
+        &args.command,
+        Some(Commands::Diff { quiet: true, .. })
+            | Some(Commands::Stats {
+                format: report::Format::Json,
+                ..
+            })
+            | Some(Commands::CompareRuns {
+                format: report::Format::Json,
+                ..
+            })
+    );
+    let clone_options = repository::CloneOptions {
+        timeout: if args.clone_timeout > 0 {
+            Some(std::time::Duration::from_secs(args.clone_timeout))
+        } else {
+            None
+        },
+        retries: args.clone_retries,
+        progress: args.verbose_clone && !bare_output,
+        runner: processes.clone(),
+    };
+
+    // Setup the repositories, fetching in parallel those not given as directories
+    let verify = !matches!(
+        &args.command,
+        Some(Commands::Describe {
+            no_verify: true,
+            ..
+        }) | Some(Commands::Describe {
+            format: effective::Format::Yaml,
+            ..
+        }) | Some(Commands::Note { .. })
+            | Some(Commands::ImportBaselines { .. })
+    );
+    let mut srcdir = args.examples_dir.clone();
+    let mut tgtdir = args.data_dir.clone();
+    let mut fetches = Vec::new();
+    // Describing without checking and taking notes need no examples
+    if srcdir.is_empty() && verify {
+        let clone_dir = work_dir.path().join("examples");
+        srcdir = clone_dir.to_str().unwrap().to_string();
+        fetches.push((args.examples_url.as_str(), clone_dir, "examples"));
+    }
+    if tgtdir.is_empty() {
+        let clone_dir = work_dir.path().join("regressiondata");
+        tgtdir = clone_dir.to_str().unwrap().to_string();
+        fetches.push((args.data_url.as_str(), clone_dir, "regression data"));
+    }
+    let mut named_sources = Vec::new();
+    for (name, location, description) in named_examples.iter().filter(|_| verify) {
+        let clone_dir = work_dir.path().join(format!("examples-{}", name));
+        named_sources.push((name.clone(), clone_dir.to_str().unwrap().to_string()));
+        fetches.push((location.as_str(), clone_dir, description.as_str()));
+    }
+    let errors: Vec<io::Error> = std::thread::scope(|scope| {
+        let handles: Vec<_> = fetches
             .iter()
-            .filter_map(|t| t.as_str().map(|s| s.to_string()))
+            .map(|(location, clone_dir, description)| {
+                scope.spawn(|| {
+                    repository::fetch(location, clone_dir, description, &clone_options, args.debug)
+                })
+            })
+            .collect();
+        // Every failure is reported, not only the first one
+        handles
+            .into_iter()
+            .filter_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(io::Error::other("fetching thread panicked")))
+                    .err()
+            })
             .collect()
-    } else {
-        vec!["default".to_string()]
+    });
+    if !errors.is_empty() {
+        let work_dir = exit_if_interrupted(work_dir);
+        for err in errors {
+            println!("Error: {}", redact::text(&err.to_string()));
+        }
+        // Without the partial clones
+        exit_with_error(work_dir);
     }
-}
 
-/// Describes regression tests by displaying their configuration details.
-///
-/// # Arguments
-///
-/// * `_source` - Path to the examples directory (unused in describing)
-/// * `target` - Path to the regression data directory
-/// * `regression_name` - Filter pattern for regression names (empty string matches all)
-/// * `tags` - List of tags to filter by (tests must match at least one tag)
-/// * `debug` - Enable debug output
-///
-/// # Errors
-///
-/// Returns an error if the target directory cannot be read or if
-/// executing a regression description fails.
-///
-/// # Output Format
-///
-/// For each matching regression, displays:
-/// - Regression name (in green)
-/// - regbase: Base directory in examples repository
-/// - sourcedata: Path to generated output file
-/// - targetdata: Path to expected output file
-/// - regcommand: Command to execute
-/// - tags: List of tags for this regression
-fn describe_regressions(
-    _source: &str,
-    target: &str,
-    regression_name: &str,
-    tags: &[String],
-    debug: bool,
-) -> Result<(), io::Error> {
-    if debug {
-        println!("Describe regressions matching: \"{}\"", regression_name);
-        println!("Filtering by tags: {:?}", tags);
+    // The checkouts, with where they come from, recorded by the plans
+    let mut checkouts = vec![
+        (
+            "examples".to_string(),
+            examples_location.clone(),
+            srcdir.clone(),
+        ),
+        ("data".to_string(), data_location.clone(), tgtdir.clone()),
+    ];
+    for ((name, location, _), (_, dir)) in named_examples.iter().zip(&named_sources) {
+        checkouts.push((
+            format!("examples:{}", name),
+            redact::text(location),
+            dir.clone(),
+        ));
     }
 
-    let entries = fs::read_dir(target)?;
-    for entry in entries {
-        let entry = entry?;
-        let filename = entry.file_name();
-        // Skip .git directory
-        if filename.to_str().unwrap() == ".git" {
-            continue;
+    let quarantine = match quarantine::Quarantine::load(&tgtdir) {
+        Ok(quarantine) => quarantine,
+        Err(err) => {
+            println!("Error reading the quarantine: {}", err);
+            exit_with_error(work_dir);
         }
-        // Filter regressions by name pattern and tag
-        if filename.to_str().unwrap().contains(regression_name) {
-            if check_regression_tags(target, filename.to_str().unwrap(), tags, debug) {
-                if let Err(err) =
-                    execute_regression("", target, "describe", filename.to_str().unwrap(), debug)
-                {
-                    println!(
-                        "Error describing regression {}: {}",
-                        filename.to_str().unwrap(),
-                        err
-                    );
-                }
+    };
+    let (difftool, quiet) = match &args.command {
+        Some(Commands::Diff {
+            difftool, quiet, ..
+        }) => (difftool.clone(), *quiet),
+        _ => (None, false),
+    };
+    let verbose = matches!(
+        &args.command,
+        Some(Commands::Describe { verbose: true, .. })
+    );
+    let any_tag = matches!(&args.command, Some(Commands::Describe { all: true, .. }));
+    let force = matches!(
+        &args.command,
+        Some(
+            Commands::Reset { force: true, .. }
+                | Commands::Update { force: true, .. }
+                | Commands::ImportBaselines { force: true, .. }
+        )
+    );
+    let override_protected = matches!(
+        &args.command,
+        Some(
+            Commands::Reset {
+                override_protected: true,
+                ..
+            } | Commands::Update {
+                override_protected: true,
+                ..
+            } | Commands::ImportBaselines {
+                override_protected: true,
+                ..
+            }
+        )
+    );
+    let compress = matches!(&args.command, Some(Commands::Reset { compress: true, .. }));
+    let smoke = matches!(
+        &args.command,
+        Some(
+            Commands::Run { smoke: true, .. }
+                | Commands::Reset { smoke: true, .. }
+                | Commands::Update { smoke: true, .. }
+                | Commands::Diff { smoke: true, .. }
+        )
+    );
+    let diff_on_fail = matches!(
+        &args.command,
+        Some(
+            Commands::Run {
+                diff_on_fail: true,
+                ..
+            } | Commands::Apply {
+                diff_on_fail: true,
+                ..
+            }
+        )
+    );
+    let group_by = match &args.command {
+        Some(Commands::Run { group_by, .. }) => *group_by,
+        _ => None,
+    };
+    let reset_candidate = match &args.command {
+        Some(
+            Commands::Reset {
+                reset_candidate, ..
+            }
+            | Commands::Update {
+                reset_candidate, ..
             }
+            | Commands::ImportBaselines {
+                reset_candidate, ..
+            },
+        ) => reset_candidate.clone(),
+        _ => None,
+    };
+
+    // Parse tags into a vector for easier filtering, none are requested with an expression
+    let tags: Vec<String> = match &tag_expr {
+        Some(_) => Vec::new(),
+        None => args.tag.split(',').map(|s| s.trim().to_string()).collect(),
+    };
+
+    // Each run writes the command logs in its own directory
+    let started = timestamp::DateTime::now();
+    let start = std::time::Instant::now();
+    let run_id = match args.run_id.clone() {
+        Some(_)
+            if matches!(
+                &args.command,
+                Some(Commands::Watch { .. } | Commands::Schedule { .. })
+            ) =>
+        {
+            println!(
+                "Error: --run-id names a single run, watch and schedule start one run per cycle"
+            );
+            exit_with_error(work_dir);
         }
+        Some(run_id) => {
+            if let Err(err) = history::check_run_id(&tgtdir, &run_id) {
+                println!("Error: {}", err);
+                exit_with_error(work_dir);
+            }
+            run_id
+        }
+        None => history::new_run_id(started),
+    };
+    let log_dir = logs::logs_root(&tgtdir).join(&run_id);
+    let artifacts_dir = if args.artifacts_dir.is_empty() {
+        std::path::Path::new(&tgtdir)
+            .join(logs::STATE_DIR)
+            .join("artifacts")
+    } else {
+        std::path::PathBuf::from(&args.artifacts_dir)
     }
+    .join(&run_id);
 
-    Ok(())
-}
+    let status_style = args
+        .status_style
+        .or(user_config.status_style)
+        .unwrap_or_default();
 
-/// Runs regression tests and compares results against expected outputs.
-///
-/// # Arguments
-///
-/// * `source` - Path to the examples directory
-/// * `target` - Path to the regression data directory
-/// * `regression_name` - Filter pattern for regression names (empty string matches all)
-/// * `tags` - List of tags to filter by (tests must match at least one tag)
-/// * `debug` - Enable debug output
-///
-/// # Errors
-///
-/// Returns an error if the target directory cannot be read or if
-/// executing a regression test fails.
-///
-/// # Output
-///
-/// For each test:
-/// - "Regression `<name>`: passed" (in green) if output matches expected
-/// - "Regression `<name>`: failed" (in red) if output differs
+    let mut ctx = Context {
+        run_id: run_id.clone(),
+        log_dir,
+        source: srcdir,
+        named_sources,
+        target: tgtdir,
+        tags,
+        tag_expr,
+        regbase: args.regbase.clone(),
+        any_tag,
+        debug: args.debug,
+        debug_full: args.debug_full,
+        stream: args.stream,
+        heartbeat: if args.heartbeat > 0 && !bare_output {
+            Some(std::time::Duration::from_secs(args.heartbeat))
+        } else {
+            None
+        },
+        errors: AtomicUsize::new(0),
+        config_errors: AtomicUsize::new(0),
+        results: Mutex::new(Vec::new()),
+        collect_artifacts: args.collect_artifacts,
+        artifacts_dir,
+        max_printed_diff_lines: if args.full_diff {
+            None
+        } else {
+            Some(args.max_printed_diff_lines)
+        },
+        patch_dir: if args.output_dir.is_empty() {
+            None
+        } else {
+            Some(std::path::PathBuf::from(&args.output_dir))
+        },
+        difftool,
+        user_config,
+        quarantine,
+        quiet,
+        verbose,
+        verify,
+        allow_external_paths: args.allow_external_paths,
+        status_style,
+        baseline: args.baseline.clone(),
+        implicit_tags: !args.no_implicit_tags,
+        force,
+        override_protected,
+        smoke,
+        bench,
+        jobs,
+        timeout: args.timeout,
+        reset_candidate,
+        compress,
+        baseline_warn_size: args.baseline_warn_size,
+        baseline_max_size: args.baseline_max_size,
+        max_output_size: args.max_output_size,
+        deadline,
+        on_fail: args.on_fail.clone(),
+        exact: args.exact,
+        diff_on_fail,
+        show_commands: args.show_commands,
+        shell_trace: args.shell_trace,
+        ignore_source_changes: args.ignore_source_changes,
+        clean_env: args.clean_env,
+        command_allowlist,
+        runner: processes,
+        header: Mutex::new(None),
+        last_changes: blame::LastChanges::default(),
+        provenance: provenance::Recorder::default(),
+    };
+
+    // Execute the requested command
+    let reproduce = reproduce_command(&args);
+    let mut exit_code = 0;
+    // Results of a run, with the files it wrote, for the summary written at the end
+    let mut run_summary: Option<(results::RunReport, Vec<(String, String)>)> = None;
+    match args.command.unwrap() {
+        Commands::List {
+            names,
+            status,
+            filter_status,
+        } => {
+            if let Err(err) = list_regressions(&ctx, &names, status, &filter_status) {
+                println!("Error listing regressions: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::Describe {
+            format: effective::Format::Yaml,
+            canonical,
+            names,
+            ..
+        } => {
+            if let Err(err) = print_configurations(&ctx, &names, canonical) {
+                println!("Error describing regressions: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::Describe {
+            canonical: true, ..
+        } => {
+            println!("Error describing regressions: --canonical requires --format yaml");
+            exit_code = 1;
+        }
+        Commands::Describe { names, .. } => match describe_regressions(&ctx, &names) {
+            Ok(results) => {
+                if print_errors(&results) {
+                    exit_code = 1;
+                }
+            }
+            Err(err) => {
+                println!("Error describing regressions: {}", err);
+                exit_code = 1;
+            }
+        },
+        Commands::Run {
+            names,
+            bootstrap,
+            due,
+            failed,
+            since,
+            since_data,
+            reports,
+            ..
+        } => {
+            // Captured before running, so that it describes what the regressions ran against
+            let mut environment = toolchain::snapshot(
+                &ctx.source,
+                &ctx.target,
+                vec![
+                    ("command line".to_string(), command_line.clone()),
+                    ("examples".to_string(), examples_location.clone()),
+                    ("data".to_string(), data_location.clone()),
+                    ("tags".to_string(), ctx.tags.join(",")),
+                    ("name".to_string(), names.join(" ")),
+                    ("bootstrap".to_string(), bootstrap.to_string()),
+                    ("baseline".to_string(), ctx.baseline.clone()),
+                ],
+                reports.redact_host,
+            );
+            let since = changes::Since {
+                examples: since,
+                data: since_data,
+            };
+            if let Err(err) = run_regressions(&ctx, &names, bootstrap, due, failed, &since) {
+                println!("Error selecting the regressions to run: {}", err);
+                exit_code = 1;
+            }
+            if let Some(header) = ctx.header().take() {
+                environment.options.extend(header.options());
+            }
+            print_quarantine_summary(&ctx);
+            // Interrupted runs are partial and would skew the statistics
+            if interrupt::interrupted().is_none() && !ctx.results().is_empty() {
+                let saved = history::save(&ctx.target, &run_id, &ctx.results()).and_then(|()| {
+                    history::save_environment(&ctx.target, &run_id, started, &environment)
+                });
+                if let Err(err) = saved {
+                    println!("Warning: saving the run history failed: {}", err);
+                }
+            }
+            let report = build_run_report(&ctx, &run_id, started, start.elapsed(), environment);
+            if !report.results.is_empty() {
+                let bootstrap = if bootstrap { " --bootstrap" } else { "" };
+                print!(
+                    "{}",
+                    summary::recap(
+                        &report,
+                        &format!("{} run{}", reproduce, bootstrap),
+                        group_by
+                    )
+                );
+            }
+            if !publish_reports(&ctx, &reports, &report) {
+                exit_code = 1;
+            }
+            let written = written_reports(&ctx, &reports);
+            run_summary = Some((report, written));
+        }
+        Commands::Plan { name, out } => {
+            let name = name.unwrap_or("".to_string());
+            if let Err(err) = plan_regressions(&ctx, &name, &plan::repositories(&checkouts), &out) {
+                println!("Error planning the regressions: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::Apply { plan, reports, .. } => {
+            // Captured before running, so that it describes what the regressions ran against
+            let environment = toolchain::snapshot(
+                &ctx.source,
+                &ctx.target,
+                vec![
+                    ("command line".to_string(), command_line.clone()),
+                    ("examples".to_string(), examples_location.clone()),
+                    ("data".to_string(), data_location.clone()),
+                    ("plan".to_string(), plan.clone()),
+                ],
+                reports.redact_host,
+            );
+            if let Err(err) = apply_plan(&mut ctx, &plan, &plan::repositories(&checkouts)) {
+                println!("Error applying the plan: {}", err);
+                exit_code = 1;
+            }
+            print_quarantine_summary(&ctx);
+            // Interrupted runs are partial and would skew the statistics
+            if interrupt::interrupted().is_none() && !ctx.results().is_empty() {
+                let saved = history::save(&ctx.target, &run_id, &ctx.results()).and_then(|()| {
+                    history::save_environment(&ctx.target, &run_id, started, &environment)
+                });
+                if let Err(err) = saved {
+                    println!("Warning: saving the run history failed: {}", err);
+                }
+            }
+            let report = build_run_report(&ctx, &run_id, started, start.elapsed(), environment);
+            if !report.results.is_empty() {
+                print!(
+                    "{}",
+                    summary::recap(&report, &format!("{} run", reproduce), None)
+                );
+            }
+            if !publish_reports(&ctx, &reports, &report) {
+                exit_code = 1;
+            }
+            let written = written_reports(&ctx, &reports);
+            run_summary = Some((report, written));
+        }
+        Commands::Record { name, commit, keep } => {
+            let name = name.unwrap_or("".to_string());
+            // The results file is committed, so the host name is left out
+            let mut environment = toolchain::snapshot(
+                &ctx.source,
+                &ctx.target,
+                vec![
+                    ("command line".to_string(), command_line.clone()),
+                    ("examples".to_string(), examples_location.clone()),
+                    ("data".to_string(), data_location.clone()),
+                    ("tags".to_string(), ctx.tags.join(",")),
+                    ("name".to_string(), name.clone()),
+                ],
+                true,
+            );
+            if let Err(err) = run_regressions(
+                &ctx,
+                &requested_names(&name),
+                false,
+                false,
+                false,
+                &changes::Since::default(),
+            ) {
+                println!("Error selecting the regressions to run: {}", err);
+                exit_code = 1;
+            }
+            if let Some(header) = ctx.header().take() {
+                environment.options.extend(header.options());
+            }
+            print_quarantine_summary(&ctx);
+            // Interrupted runs are partial, they are neither saved nor recorded
+            if interrupt::interrupted().is_none() && !ctx.results().is_empty() {
+                let saved = history::save(&ctx.target, &run_id, &ctx.results()).and_then(|()| {
+                    history::save_environment(&ctx.target, &run_id, started, &environment)
+                });
+                if let Err(err) = saved {
+                    println!("Warning: saving the run history failed: {}", err);
+                }
+                let report = build_run_report(&ctx, &run_id, started, start.elapsed(), environment);
+                if let Err(err) = record_results(&ctx, &report, keep, commit) {
+                    println!("Error recording the results: {}", err);
+                    exit_code = 1;
+                }
+            }
+        }
+        Commands::Reset {
+            names,
+            interactive,
+            preview,
+            commit,
+            ..
+        } => match reset_regressions(&ctx, &names, interactive, preview) {
+            Ok(results) => {
+                let changed = present::changed(&results);
+                let errored = print_errors(&results);
+                if errored || (preview && !changed.is_empty()) {
+                    exit_code = 1;
+                }
+                if commit {
+                    if let Err(err) = commit_baselines(&ctx.target, &changed, "Reset", ctx.debug) {
+                        println!("Error committing the reset regressions: {}", err);
+                        exit_code = 1;
+                    }
+                }
+            }
+            Err(err) => {
+                println!("Error resetting regressions: {}", err);
+                exit_code = 1;
+            }
+        },
+        Commands::Update { name, commit, .. } => {
+            match update_regressions(&ctx, &name.unwrap_or("".to_string())) {
+                Ok(results) => {
+                    if print_errors(&results) {
+                        exit_code = 1;
+                    }
+                    let updated = present::names(&results, |status| *status == Status::Updated);
+                    if commit {
+                        if let Err(err) =
+                            commit_baselines(&ctx.target, &updated, "Update", ctx.debug)
+                        {
+                            println!("Error committing the updated regressions: {}", err);
+                            exit_code = 1;
+                        }
+                    }
+                }
+                Err(err) => {
+                    println!("Error updating regressions: {}", err);
+                    exit_code = 1;
+                }
+            }
+        }
+        Commands::Diff { names, .. } => {
+            // Like diff(1): 1 when differences are found, 2 when something went wrong
+            let diffed = diff_regressions(&ctx, &names);
+            print_quarantine_summary(&ctx);
+            match diffed {
+                Ok(results) => {
+                    // Differences of quarantined regressions do not count
+                    let differences =
+                        present::names(&results, |status| *status == Status::DiffFound)
+                            .iter()
+                            .filter(|name| ctx.quarantine.active(name).is_none())
+                            .count();
+                    if print_errors(&results) {
+                        exit_code = 2;
+                    } else if differences > 0 {
+                        exit_code = 1;
+                    }
+                }
+                Err(err) => {
+                    println!("Error diffing regressions: {}", err);
+                    exit_code = 2;
+                }
+            }
+        }
+        Commands::Validate { name } => {
+            match validate_regressions(&ctx, &name.unwrap_or("".to_string())) {
+                Ok(0) => {}
+                Ok(_) => exit_code = 1,
+                Err(err) => {
+                    println!("Error validating regressions: {}", err);
+                    exit_code = 1;
+                }
+            }
+        }
+        Commands::Lint {
+            name,
+            max_baseline_age,
+        } => match schedule::parse_interval(&max_baseline_age)
+            .map_err(io::Error::other)
+            .and_then(|max_age| lint_regressions(&ctx, &name.unwrap_or("".to_string()), max_age))
+        {
+            Ok(0) => {}
+            Ok(_) => exit_code = 1,
+            Err(err) => {
+                println!("Error linting regressions: {}", err);
+                exit_code = 1;
+            }
+        },
+        Commands::Tags { name } => {
+            if let Err(err) = list_tags(&ctx, &name.unwrap_or("".to_string())) {
+                println!("Error listing tags: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::Stats {
+            name,
+            last,
+            format,
+            source,
+        } => {
+            let name = name.unwrap_or("".to_string());
+            if let Err(err) = show_stats(&ctx, &name, last, format, source) {
+                println!("Error computing statistics: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::CompareRuns {
+            run_a,
+            run_b,
+            duration_threshold,
+            format,
+            source,
+        } => match compare_runs(&ctx, &run_a, &run_b, duration_threshold, format, source) {
+            Ok(newly_failing) => {
+                if newly_failing {
+                    exit_code = 1;
+                }
+            }
+            Err(err) => {
+                println!("Error comparing runs: {}", err);
+                exit_code = 1;
+            }
+        },
+        Commands::Status { badge } => {
+            if let Err(err) = show_status(&ctx, badge.as_deref()) {
+                println!("Error showing the status: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::Blame {
+            regression,
+            count,
+            patch,
+        } => {
+            let shown = config::load(&ctx.target, &regression).and_then(|config| {
+                blame::show(&ctx.target, &config, &ctx.baseline, count, patch, ctx.debug)
+            });
+            if let Err(err) = shown {
+                println!("Error showing the history of {}: {}", regression, err);
+                exit_code = 1;
+            }
+        }
+        Commands::Watch {
+            name,
+            ignore,
+            debounce,
+        } => {
+            let name = name.unwrap_or_default();
+            let watched = watch_regressions(
+                &mut ctx,
+                &name,
+                &ignore,
+                std::time::Duration::from_millis(debounce),
+                &reproduce,
+                args.keep_logs,
+            );
+            if let Err(err) = watched {
+                println!("Error watching regressions: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::Schedule {
+            name,
+            interval,
+            notify_webhook,
+            notify_on,
+        } => {
+            let repositories: Vec<(&str, &std::path::Path, &str)> = fetches
+                .iter()
+                .map(|(location, dir, description)| (*location, dir.as_path(), *description))
+                .collect();
+            let scheduled = schedule(
+                &mut ctx,
+                &name.unwrap_or("".to_string()),
+                &interval,
+                notify_webhook.as_deref().map(|url| (url, notify_on)),
+                &repositories,
+                &clone_options,
+                args.keep_logs,
+            );
+            if let Err(err) = scheduled {
+                println!("Error scheduling regressions: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::Serve { listen } => {
+            if let Err(err) = serve(&mut ctx, &listen, args.keep_logs) {
+                println!("Error serving: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::Migrate {
+            name,
+            dry_run,
+            compress_baselines: compress,
+            min_size,
+        } => {
+            let name = name.unwrap_or("".to_string());
+            if compress {
+                if let Err(err) = compress_baselines(&ctx, &name, min_size) {
+                    println!("Error compressing the expected outputs: {}", err);
+                    exit_code = 1;
+                }
+            } else {
+                match canonicalize_configs(&ctx, &name, dry_run) {
+                    Ok(changed) if dry_run && changed > 0 => exit_code = 1,
+                    Ok(_) => {}
+                    Err(err) => {
+                        println!("Error migrating the configurations: {}", err);
+                        exit_code = 1;
+                    }
+                }
+            }
+        }
+        Commands::Copy {
+            source,
+            name,
+            regbase,
+            regcommand,
+            with_baseline,
+            ..
+        } => {
+            let overrides: Vec<(&str, &str)> = [("regbase", &regbase), ("regcommand", &regcommand)]
+                .into_iter()
+                .filter_map(|(key, value)| Some((key, value.as_deref()?)))
+                .collect();
+            if let Err(err) = copy_regression(&ctx, &source, &name, &overrides, with_baseline) {
+                println!("Error copying regression {}: {}", source, err);
+                exit_code = 1;
+            }
+        }
+        Commands::Import {
+            example,
+            name,
+            sourcedata,
+            regcommand,
+            yes,
+            bootstrap,
+        } => {
+            let name = name.unwrap_or_else(|| example.trim_matches('/').replace(['/', '\\'], "_"));
+            let proposal = ImportProposal {
+                sourcedata,
+                regcommand,
+            };
+            match import_regression(&ctx, &example, &name, proposal, yes, bootstrap) {
+                Ok(Some(Status::Failed { .. } | Status::Skipped { .. })) => exit_code = 1,
+                Ok(_) => {}
+                Err(err) => {
+                    println!("Error importing example {}: {}", example, err);
+                    exit_code = 1;
+                }
+            }
+        }
+        Commands::Remove { name, yes } => {
+            if let Err(err) = remove_regressions(&ctx, name.as_deref(), yes) {
+                println!("Error removing regressions: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::Export {
+            run,
+            out,
+            no_artifacts,
+        } => {
+            if let Err(err) = export_run(&ctx, run, out, no_artifacts) {
+                println!("Error exporting the run: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::ImportBaselines {
+            archive,
+            names,
+            commit,
+            ..
+        } => match import_baselines(&ctx, &archive, &names) {
+            Ok(imported) => {
+                if imported.errors > 0 {
+                    exit_code = 1;
+                }
+                if commit {
+                    let committed =
+                        commit_baselines(&ctx.target, &imported.updated, "Import", ctx.debug);
+                    if let Err(err) = committed {
+                        println!("Error committing the imported regressions: {}", err);
+                        exit_code = 1;
+                    }
+                }
+            }
+            Err(err) => {
+                println!("Error importing the expected outputs: {}", err);
+                exit_code = 1;
+            }
+        },
+        Commands::Shell { regression } => {
+            if let Err(err) = open_shell(&ctx, &regression) {
+                println!(
+                    "Error opening a shell for regression {}: {}",
+                    regression, err
+                );
+                exit_code = 1;
+            }
+        }
+        Commands::Logs { regression, run } => {
+            if let Err(err) = logs::show(&ctx.target, &regression, run.as_deref()) {
+                println!("Error showing logs: {}", err);
+                exit_code = 1;
+            }
+        }
+        Commands::Note {
+            regression,
+            text,
+            list,
+        } => {
+            if let Err(err) = take_note(&ctx, &regression, text.as_deref(), list) {
+                println!("Error with the notes of regression {}: {}", regression, err);
+                exit_code = 1;
+            }
+        }
+        // Handled before setting up the repositories
+        Commands::Version { .. } | Commands::Schema | Commands::SelfTest { .. } => {}
+    }
+
+    // The failures of a completed run are measured against the threshold
+    let measure = match (&run_summary, fail_threshold) {
+        (Some((report, _)), Some(threshold)) if interrupt::interrupted().is_none() => {
+            let measure = threshold::Measure::new(threshold, report, args.threshold_counts_errors);
+            println!("{}", measure.line());
+            Some(measure)
+        }
+        _ => None,
+    };
+
+    // The results of a run give its exit code, telling the failures from the errors
+    if let Some((report, _)) = &run_summary {
+        exit_code = run_exit_code(
+            report,
+            exit_code != 0,
+            ctx.config_errors.load(Ordering::Relaxed),
+            measure.as_ref(),
+        );
+    }
+
+    // In strict mode infrastructure errors fail the invocation, distinctly from test failures
+    if args.strict && interrupt::interrupted().is_none() {
+        print_strict_summary(&ctx);
+        if ctx.errors.load(Ordering::Relaxed) + ctx.config_errors.load(Ordering::Relaxed) > 0 {
+            exit_code = EXIT_INFRASTRUCTURE;
+        }
+    }
+
+    // Written whatever the report options, so that CI wrappers need not parse the output
+    if let Some((report, written)) = run_summary {
+        let completed = interrupt::interrupted().is_none();
+        let code = if completed {
+            exit_code
+        } else {
+            interrupt::exit_code()
+        };
+        let path = summary::path(&ctx.target);
+        if let Err(err) = summary::write(
+            &path,
+            &summary::render(
+                &report,
+                completed,
+                code,
+                measure.as_ref(),
+                &written,
+                group_by,
+            ),
+        ) {
+            println!(
+                "Warning: writing the run summary to {} failed: {}",
+                path.display(),
+                err
+            );
+        }
+    }
+
+    // Only the most recent run logs are kept
+    if ctx.log_dir.exists() {
+        if let Err(err) = logs::prune(&ctx.target, args.keep_logs, ctx.debug) {
+            println!("Error pruning old logs: {}", err);
+        }
+    }
+
+    // Summarize what was done before an interrupt, then clean up and exit, a
+    // graceful SIGTERM of the scheduler exits normally
+    if let Some(signal) = interrupt::interrupted().filter(|_| interrupt::aborted()) {
+        let completed = ctx.results();
+        println!(
+            "\x1b[0;31mInterrupted by {}\x1b[0m, {} regressions completed before the interrupt:",
+            interrupt::signal_name(),
+            completed.len()
+        );
+        for result in completed.iter() {
+            println!("  {}: {}", result.name, result.status);
+        }
+        if ctx.debug {
+            println!("Received signal {}", signal);
+        }
+        if args.keep_temp {
+            if let WorkDir::Temp(tmp_dir) = work_dir {
+                println!(
+                    "\x1b[0;33mWorking directory kept at: {}\x1b[0m",
+                    tmp_dir.into_path().display()
+                );
+            }
+        } else {
+            drop(work_dir);
+        }
+        ::std::process::exit(interrupt::exit_code());
+    }
+
+    // Keep the working directory for post-mortem inspection when asked or when something went wrong
+    match work_dir {
+        WorkDir::Temp(tmp_dir) => {
+            if args.keep_temp || ctx.errors.load(Ordering::Relaxed) > 0 {
+                let kept = tmp_dir.into_path();
+                println!(
+                    "\x1b[0;33mWorking directory kept at: {}\x1b[0m",
+                    kept.display()
+                );
+            } else {
+                tmp_dir.close()?;
+            }
+        }
+        WorkDir::Fixed(path) => {
+            if ctx.errors.load(Ordering::Relaxed) > 0 {
+                println!(
+                    "\x1b[0;33mWorking directory kept at: {}\x1b[0m",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    if exit_code != 0 {
+        ::std::process::exit(exit_code);
+    }
+    Ok(())
+}
+
+/// Prints the regressions that could not be executed, once all of them are done.
+///
+/// # Returns
+///
+/// True if there are any, for the exit code.
+fn print_errors(results: &[RunResult]) -> bool {
+    print!("{}", present::error_section(results));
+    !present::errors(results).is_empty()
+}
+
+/// Exit code of a run in which a regression failed.
+const EXIT_FAILED: i32 = 1;
+
+/// Exit code of a run in which a regression could not be executed, or that could not
+/// select or report its regressions.
+const EXIT_ERROR: i32 = 2;
+
+/// Exit code of the strict mode when the infrastructure failed, as opposed to the regressions.
+const EXIT_INFRASTRUCTURE: i32 = 3;
+
+/// Exit code of a run whose time budget was spent before all its regressions ran.
+const EXIT_INCOMPLETE: i32 = 5;
+
+/// Returns the exit code of a run or an apply from its results.
+///
+/// The first code that applies wins: 2 when the run itself failed, to select or to report
+/// its regressions, when a configuration is broken or when a regression could not be
+/// executed; 5 when the time budget left regressions not run; with a threshold, 4 when the
+/// failures exceed it; without one, 1 when a regression failed. Quarantined failures never
+/// count, and the errors counted by the threshold only count there. The strict mode
+/// replaces the code with 3 on infrastructure errors afterwards.
+fn run_exit_code(
+    report: &results::RunReport,
+    run_failed: bool,
+    config_errors: usize,
+    measure: Option<&threshold::Measure>,
+) -> i32 {
+    let counts_errors = measure.is_some_and(|measure| measure.counts_errors);
+    let errors = report.results.iter().any(|result| {
+        results::outcome(result.status) == "infrastructure"
+            && !(counts_errors && result.status == "error")
+    });
+    if run_failed || config_errors > 0 || errors {
+        EXIT_ERROR
+    } else if report.count(results::NOT_RUN) > 0 {
+        EXIT_INCOMPLETE
+    } else if let Some(measure) = measure {
+        if measure.exceeded() {
+            threshold::EXIT_CODE
+        } else {
+            0
+        }
+    } else if report.count("failed") > 0 {
+        EXIT_FAILED
+    } else {
+        0
+    }
+}
+
+/// Prints the summary of the strict mode, separating infrastructure errors from test failures.
+fn print_strict_summary(ctx: &Context) {
+    let results = ctx.results();
+    if results.is_empty() && ctx.config_errors.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    let count = |status: &str| results.iter().filter(|r| r.status == status).count();
+    let errors: Vec<&results::RegressionResult> =
+        results.iter().filter(|r| r.status == "error").collect();
+    println!(
+        "Summary: {} passed, {} failed, {} infrastructure errors, {} skipped for config errors",
+        count("passed"),
+        count("failed"),
+        errors.len(),
+        ctx.config_errors.load(Ordering::Relaxed)
+    );
+    if !errors.is_empty() {
+        println!("\x1b[0;31mInfrastructure errors:\x1b[0m");
+        for result in errors {
+            println!("  {}: {}", result.name, result.error);
+        }
+    }
+}
+
+/// Prints how many regressions are quarantined and how they went, with the expired entries.
+///
+/// Nothing is printed when the data repository has no quarantine.
+fn print_quarantine_summary(ctx: &Context) {
+    if ctx.quarantine.is_empty() {
+        return;
+    }
+    let results = ctx.results();
+    let quarantined: Vec<&results::RegressionResult> = results
+        .iter()
+        .filter(|r| ctx.quarantine.active(&r.name).is_some())
+        .collect();
+    let failures = quarantined
+        .iter()
+        .filter(|r| r.status == "quarantined failure" || r.status == "differences found")
+        .count();
+    let unexpected: Vec<&str> = quarantined
+        .iter()
+        .filter(|r| r.status == "passed" || r.status == "no differences")
+        .map(|r| r.name.as_str())
+        .collect();
+    let expired = ctx.quarantine.expired();
+    println!(
+        "Quarantine: {} regressions quarantined, {} executed, {} quarantined failures, {} unexpected passes, {} expired entries",
+        ctx.quarantine.entries().len() - expired.len(),
+        quarantined.len(),
+        failures,
+        unexpected.len(),
+        expired.len()
+    );
+    if !unexpected.is_empty() {
+        println!(
+            "\x1b[0;33mPassing while quarantined, consider removing them from {}:\x1b[0m {}",
+            quarantine::FILE,
+            unexpected.join(", ")
+        );
+    }
+    for entry in expired {
+        println!(
+            "\x1b[0;33mWarning: the quarantine of {} expired on {}, its failures count again\x1b[0m",
+            entry.name,
+            entry.expires.as_deref().unwrap_or_default()
+        );
+    }
+}
+
+/// Exits with the interrupt exit code if a signal has been received, or else gives the
+/// working directory back.
+///
+/// The working directory is removed before exiting.
+fn exit_if_interrupted(work_dir: WorkDir) -> WorkDir {
+    if interrupt::interrupted().is_some() {
+        println!(
+            "\x1b[0;31mInterrupted by {}\x1b[0m",
+            interrupt::signal_name()
+        );
+        drop(work_dir);
+        ::std::process::exit(interrupt::exit_code());
+    }
+    work_dir
+}
+
+/// Exits with 1 after an error found before running the regressions.
+///
+/// The working directory is removed before exiting, as exiting drops nothing.
+fn exit_with_error(work_dir: WorkDir) -> ! {
+    drop(work_dir);
+    ::std::process::exit(1);
+}
+
+/// Options whose values may hold credentials, such as the token of a webhook URL.
+const SECRET_OPTIONS: [&str; 2] = ["--notify-webhook", "--pushgateway"];
+
+/// Joins the command line arguments, masking the values of the secret options.
+fn redacted_command_line(args: impl Iterator<Item = String>) -> String {
+    let mut out = Vec::new();
+    let mut secret_next = false;
+    for arg in args {
+        if secret_next {
+            out.push("***".to_string());
+            secret_next = false;
+        } else if SECRET_OPTIONS.contains(&arg.as_str()) {
+            out.push(arg);
+            secret_next = true;
+        } else if let Some(option) = SECRET_OPTIONS
+            .iter()
+            .find(|option| arg.starts_with(&format!("{}=", option)))
+        {
+            out.push(format!("{}=***", option));
+        } else {
+            out.push(arg);
+        }
+    }
+    redact::text(&out.join(" "))
+}
+
+/// Returns the command line running regressions with the effective options of this invocation.
+///
+/// Only the options changing which regressions run and what they run against are kept:
+/// the repositories, as absolute paths when given as directories, the tags and the
+/// baseline. The recap of a run appends the name of each failing regression to it.
+fn reproduce_command(args: &Cli) -> String {
+    let absolute = |path: &str| {
+        fs::canonicalize(path)
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|_| path.to_string())
+    };
+    let mut out = vec![std::env::args()
+        .next()
+        .unwrap_or_else(|| "bmregression".to_string())];
+    if args.data_dir.is_empty() {
+        out.extend(["--data-url".to_string(), args.data_url.clone()]);
+    } else {
+        out.extend(["--data-dir".to_string(), absolute(&args.data_dir)]);
+    }
+    if args.examples_dir.is_empty() {
+        out.extend(["--examples-url".to_string(), args.examples_url.clone()]);
+    } else {
+        out.extend(["--examples-dir".to_string(), absolute(&args.examples_dir)]);
+    }
+    for definition in &args.named_examples {
+        let definition = match repository::parse_named(definition) {
+            Ok((name, location)) if std::path::Path::new(&location).exists() => {
+                format!("{}={}", name, absolute(&location))
+            }
+            _ => definition.clone(),
+        };
+        out.extend(["--examples".to_string(), definition]);
+    }
+    match &args.tag_expr {
+        Some(expr) => out.extend(["--tag-expr".to_string(), expr.clone()]),
+        None => out.extend(["--tag".to_string(), args.tag.clone()]),
+    }
+    if let Some(regbase) = &args.regbase {
+        out.extend(["--regbase".to_string(), regbase.clone()]);
+    }
+    if args.baseline != config::DEFAULT_BASELINE {
+        out.extend(["--baseline".to_string(), args.baseline.clone()]);
+    }
+    if args.no_implicit_tags {
+        out.push("--no-implicit-tags".to_string());
+    }
+    if args.system_tools {
+        out.push("--system-tools".to_string());
+    }
+    if args.allow_external_paths {
+        out.push("--allow-external-paths".to_string());
+    }
+    redact::text(
+        &out.iter()
+            .map(|arg| summary::shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// Writes the reports and sends the notifications requested for a run.
+///
+/// Failures to reach external services are only warnings, while failing to write
+/// a requested file makes the run fail.
+///
+/// # Returns
+///
+/// Returns false if a requested report could not be written.
+fn publish_reports(
+    ctx: &Context,
+    reports: &ReportOptions,
+    run_report: &results::RunReport,
+) -> bool {
+    // Inside GitHub Actions the results are also published on the workflow page
+    let step_summary = std::env::var_os("GITHUB_STEP_SUMMARY").filter(|p| !p.is_empty());
+    let github = reports.github || step_summary.is_some();
+    if reports.html_report.is_none()
+        && reports.report.is_none()
+        && !github
+        && reports.metrics_file.is_none()
+        && reports.pushgateway.is_none()
+        && reports.notify_webhook.is_none()
+        && reports.badge.is_none()
+        && !reports.notify_desktop
+    {
+        return true;
+    }
+
+    let mut ok = true;
+    if let Some(path) = &reports.html_report {
+        match report::write_html(run_report, std::path::Path::new(path)) {
+            Ok(()) => println!("HTML report written to {}", path),
+            Err(err) => {
+                println!("Error writing the HTML report: {}", err);
+                ok = false;
+            }
+        }
+    }
+    if let Some(path) = &reports.report {
+        match report::write_report(
+            run_report,
+            reports.report_format,
+            std::path::Path::new(path),
+        ) {
+            Ok(()) => println!("Report written to {}", path),
+            Err(err) => {
+                println!("Error writing the report: {}", err);
+                ok = false;
+            }
+        }
+    }
+    if github {
+        for annotation in report::github_annotations(run_report) {
+            println!("{}", annotation);
+        }
+        if let Some(path) = step_summary {
+            if let Err(err) = report::append_step_summary(run_report, std::path::Path::new(&path)) {
+                println!("Error writing the GitHub step summary: {}", err);
+            }
+        }
+    }
+    if let Some(path) = &reports.metrics_file {
+        if let Err(err) = metrics::write(run_report, std::path::Path::new(path)) {
+            println!("Error writing the metrics file: {}", err);
+            ok = false;
+        }
+    }
+    if let Some(path) = &reports.badge {
+        let passed = run_report
+            .results
+            .iter()
+            .filter(|r| results::is_pass(r.status))
+            .count();
+        match badge::write(passed, run_report.results.len(), std::path::Path::new(path)) {
+            Ok(()) => println!("Badge written to {}", path),
+            Err(err) => {
+                println!("Error writing the badge: {}", err);
+                ok = false;
+            }
+        }
+    }
+    if let Some(url) = &reports.pushgateway {
+        if let Err(err) = metrics::push(run_report, url, ctx.debug) {
+            println!("Warning: pushing the metrics failed: {}", err);
+        }
+    }
+    if let Some(url) = &reports.notify_webhook {
+        if notify::should_notify(run_report, reports.notify_on) {
+            if let Err(err) = notify::send(url, &notify::payload(run_report), ctx.debug) {
+                println!("Warning: sending the webhook notification failed: {}", err);
+            }
+        }
+    }
+    // There is nobody at the desktop of a CI job
+    if reports.notify_desktop && !notify::in_ci() {
+        let (title, body, urgency) = notify::desktop_message(run_report);
+        if let Err(err) = notify::desktop(&title, &body, urgency) {
+            if ctx.debug {
+                println!("Desktop notification not shown: {}", err);
+            }
+            println!("\x07{}: {}", title, body.replace('\n', "; "));
+        }
+    }
+    ok
+}
+
+/// Returns the files written by a run, as (kind, path) pairs, for its summary.
+fn written_reports(ctx: &Context, reports: &ReportOptions) -> Vec<(String, String)> {
+    let mut written = Vec::new();
+    let requested = [
+        ("html", &reports.html_report),
+        ("report", &reports.report),
+        ("metrics", &reports.metrics_file),
+        ("badge", &reports.badge),
+    ];
+    for (kind, path) in requested {
+        if let Some(path) = path.as_ref().filter(|p| std::path::Path::new(p).exists()) {
+            written.push((kind.to_string(), path.clone()));
+        }
+    }
+    for (kind, dir) in [("logs", &ctx.log_dir), ("artifacts", &ctx.artifacts_dir)] {
+        if dir.exists() {
+            written.push((kind.to_string(), dir.display().to_string()));
+        }
+    }
+    written
+}
+
+/// Collects the results of the run so far, with the environment they were produced in.
+fn build_run_report(
+    ctx: &Context,
+    run_id: &str,
+    started: timestamp::DateTime,
+    duration: std::time::Duration,
+    environment: results::Environment,
+) -> results::RunReport {
+    results::RunReport {
+        run_id: run_id.to_string(),
+        started,
+        duration,
+        environment,
+        results: ctx.results().clone(),
+    }
+}
+
+/// Working directory holding the cloned repositories.
+enum WorkDir {
+    /// A temporary directory, removed at the end unless kept
+    Temp(TempDir),
+    /// A directory chosen with `--workdir`, never removed
+    Fixed(std::path::PathBuf),
+}
+
+impl WorkDir {
+    fn path(&self) -> &std::path::Path {
+        match self {
+            WorkDir::Temp(tmp_dir) => tmp_dir.path(),
+            WorkDir::Fixed(path) => path,
+        }
+    }
+}
+
+/// Exits with 1 if the filesystem of `dir` has less than `min_free` bytes available, naming
+/// the ways to choose another one.
+fn check_free_space(dir: &std::path::Path, min_free: u64) {
+    if min_free == 0 {
+        return;
+    }
+    match diskspace::available(dir) {
+        Ok(available) if available < min_free => {
+            println!(
+                "Error: the filesystem of {} has {} free, less than the {} of --min-free-space; choose a larger one with --tmp-dir, TMPDIR or --workdir",
+                dir.display(),
+                human_size(available),
+                human_size(min_free)
+            );
+            ::std::process::exit(1);
+        }
+        Ok(_) => {}
+        Err(err) => println!(
+            "\x1b[0;33mWarning: cannot check the free space of {}: {}\x1b[0m",
+            dir.display(),
+            err
+        ),
+    }
+}
+
+/// Status listed for the regressions absent from the history.
+const NEVER_RUN: &str = "never run";
+
+/// Statuses the listing can be filtered by, those of the runs in the history.
+const LISTED_STATUSES: [&str; 9] = [
+    "passed",
+    "failed",
+    "error",
+    "captured",
+    "skipped",
+    "quarantined failure",
+    "blocked",
+    results::NOT_RUN,
+    NEVER_RUN,
+];
+
+/// Lists available regression tests matching the given pattern.
+///
+/// # Arguments
+///
+/// * `ctx` - Settings shared by all the regressions
+/// * `regression_names` - Filter patterns for regression names, a regression matching any is selected (none selects all)
+/// * `show_status` - Append the status and the age of the most recent result in the history
+/// * `filter_status` - Only list the regressions whose most recent result has one of these
+///   statuses, showing them
+///
+/// # Errors
+///
+/// Returns an error if the target directory or the history cannot be read, or a
+/// filtered status is unknown.
+///
+/// # Examples
+///
+/// Lists all regressions:
+/// ```text
+/// Regressions found:
+///     basys3_blink
+///     basys3_counter
+/// ```
+fn list_regressions(
+    ctx: &Context,
+    regression_names: &[String],
+    show_status: bool,
+    filter_status: &[String],
+) -> Result<(), io::Error> {
+    if let Some(unknown) = filter_status
+        .iter()
+        .find(|status| !LISTED_STATUSES.contains(&status.as_str()))
+    {
+        return Err(io::Error::other(format!(
+            "unknown status {:?}, the statuses are {}",
+            unknown,
+            LISTED_STATUSES.join(", ")
+        )));
+    }
+    if ctx.debug {
+        println!("List of regressions matching: {:?}", regression_names);
+        println!("Filtering the regressions {}", ctx.tagged());
+    }
+
+    let (selected, broken) = select_with_broken(ctx, regression_names)?;
+    let groups = config::load_tag_groups(&ctx.target)?;
+    // The history is only read when the statuses are asked for
+    let latest = if show_status || !filter_status.is_empty() {
+        Some(history::latest_entries(&ctx.target)?)
+    } else {
+        None
+    };
+    let now = timestamp::DateTime::now();
+    let mut rows = Vec::new();
+    for config in selected {
+        // Stop scheduling new regressions after an interrupt
+        if interrupt::interrupted().is_some() {
+            break;
+        }
+        let status = latest
+            .as_ref()
+            .map(|latest| match latest.get(&config.name) {
+                Some((run_id, entry)) => (
+                    entry.status.clone(),
+                    timestamp::DateTime::from_compact(run_id)
+                        .map(|started| started.age(now))
+                        .unwrap_or_default(),
+                ),
+                None => (NEVER_RUN.to_string(), String::new()),
+            });
+        let listed = filter_status.is_empty()
+            || status
+                .as_ref()
+                .is_some_and(|(label, _)| filter_status.contains(label));
+        if !listed {
+            continue;
+        }
+        let via = config.matching_groups(&ctx.tags, &groups, ctx.implicit_tags);
+        let mut via = if via.is_empty() {
+            String::new()
+        } else {
+            format!(" (via the tag group {})", via.join(", "))
+        };
+        // A pattern can match several examples, tell which one each regression runs
+        if ctx.regbase.is_some() {
+            via.push_str(&format!(" (regbase {})", config.regbase));
+        }
+        let status = status.map(|(label, age)| (style::status(ctx.status_style, &label), age));
+        rows.push((config.name, via, status));
+    }
+
+    println!("Regressions found:");
+    // The columns are aligned on the widest name and status
+    let name_width = rows
+        .iter()
+        .map(|(name, _, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let status_width = rows
+        .iter()
+        .filter_map(|(_, _, status)| status.as_ref())
+        .map(|(rendered, _)| style::width(rendered))
+        .max()
+        .unwrap_or(0);
+    for (name, via, status) in rows {
+        match status {
+            None => println!("\t{}{}", name, via),
+            Some((rendered, age)) => {
+                let line = format!(
+                    "\t{:<name_width$}  {}{}  {}{}",
+                    name,
+                    rendered,
+                    " ".repeat(status_width - style::width(&rendered)),
+                    age,
+                    via
+                );
+                println!("{}", line.trim_end());
+            }
+        }
+    }
+    // Listed whatever their tags and status, so that a broken configuration is noticed
+    for (name, err) in &broken {
+        println!(
+            "\x1b[0;31m\t{}  (invalid config: {})\x1b[0m",
+            name,
+            config_error_summary(err)
+        );
+    }
+    if !broken.is_empty() {
+        println!(
+            "\x1b[0;31m{} invalid configurations\x1b[0m, describe or validate them for the details",
+            broken.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Directories of the data repository that never hold regressions.
+const IGNORED_DIRS: [&str; 5] = [".git", ".github", ".gitlab", logs::STATE_DIR, recorded::DIR];
+
+/// Returns why an entry of the data directory is never a regression, whatever it holds.
+fn skip_reason(name: &str) -> Option<&'static str> {
+    if name == recorded::DIR {
+        return Some("directory of the recorded results");
+    }
+    if IGNORED_DIRS.contains(&name) {
+        return Some("ignored directory");
+    }
+    // Directories such as _common hold the files included by the configurations
+    if name.starts_with('_') {
+        return Some("shared configuration directory");
+    }
+    None
+}
+
+/// Returns the directories of the data directory skipped for their name although they
+/// hold a configuration, with the reason, sorted.
+///
+/// Such a directory is likely a regression renamed by mistake, which must not silently
+/// disappear from the selections.
+fn skipped_configurations(ctx: &Context) -> Result<Vec<(String, &'static str)>, io::Error> {
+    let mut skipped = Vec::new();
+    for entry in fs::read_dir(&ctx.target)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if let Some(reason) = skip_reason(&name) {
+            if std::path::Path::new(&ctx.target)
+                .join(&name)
+                .join("config.yaml")
+                .is_file()
+            {
+                skipped.push((name, reason));
+            }
+        }
+    }
+    skipped.sort();
+    Ok(skipped)
+}
+
+/// Returns true if an entry of the data directory can be a regression.
+///
+/// Files and ignored directories are skipped, symbolic links are followed only
+/// when they resolve to a directory inside the data directory. Skipped entries
+/// are noted in debug mode.
+fn is_regression_entry(ctx: &Context, entry: &fs::DirEntry) -> bool {
+    let filename = entry.file_name();
+    let name = filename.to_string_lossy();
+    if let Some(reason) = skip_reason(&name) {
+        if ctx.debug {
+            println!("Skipping {}: {}", name, reason);
+        }
+        return false;
+    }
+    let file_type = match entry.file_type() {
+        Ok(file_type) => file_type,
+        Err(_) => return false,
+    };
+    if file_type.is_symlink() {
+        let inside = match (
+            fs::canonicalize(entry.path()),
+            fs::canonicalize(&ctx.target),
+        ) {
+            (Ok(resolved), Ok(target)) => resolved.starts_with(&target) && resolved.is_dir(),
+            _ => false,
+        };
+        if !inside && ctx.debug {
+            println!(
+                "Skipping {}: symbolic link not resolving to a directory inside the data directory",
+                name
+            );
+        }
+        return inside;
+    }
+    if !file_type.is_dir() {
+        if ctx.debug {
+            println!("Skipping {}: not a directory", name);
+        }
+        return false;
+    }
+    true
+}
+
+/// Selects the regressions of the data directory matching any of the name patterns, all
+/// of them if there is none, and the tags.
+///
+/// A name pattern that selects no regression is reported with a warning, telling whether
+/// it matches no regression at all or none with the tags. The configuration of each candidate is loaded once and the same instance is
+/// used for the tag filter and for the execution. Regressions without a valid
+/// configuration are skipped, with a warning listing them, and counted in the
+/// context. The regressions are returned by decreasing priority, in directory
+/// order within the same priority.
+///
+/// # Errors
+///
+/// Returns an error if the target directory cannot be read.
+fn select_regressions(
+    ctx: &Context,
+    regression_names: &[String],
+) -> Result<Vec<config::RegressionConfig>, io::Error> {
+    let (selected, broken) = select_with_broken(ctx, regression_names)?;
+    if !broken.is_empty() {
+        println!(
+            "\x1b[0;33mWarning: {} regressions skipped due to config errors:\x1b[0m",
+            broken.len()
+        );
+        for (name, err) in &broken {
+            println!("  {}/config.yaml: {}", name, err);
+        }
+    }
+    Ok(selected)
+}
+
+/// Regressions with a broken configuration, as (name, error) pairs.
+type BrokenConfigs = Vec<(String, String)>;
+
+/// Selects the regressions like [`select_regressions`], returning those matching the name
+/// patterns whose configuration is broken, whatever their tags, with their error, sorted by
+/// name, instead of warning about them. They are counted in the context all the same.
+///
+/// # Errors
+///
+/// Returns an error if the target directory cannot be read.
+fn select_with_broken(
+    ctx: &Context,
+    regression_names: &[String],
+) -> Result<(Vec<config::RegressionConfig>, BrokenConfigs), io::Error> {
+    let names = scan_regressions(ctx)?;
+    // Colliding names map to the same directory on case-insensitive file systems
+    let collisions = name_collisions(&names);
+    if !collisions.is_empty() {
+        return Err(io::Error::other(format!(
+            "regression names collide: {}",
+            describe_collisions(&collisions)
+        )));
+    }
+
+    for (name, reason) in skipped_configurations(ctx)? {
+        println!(
+            "\x1b[0;33mWarning: {} holds a config.yaml but is not a regression ({})\x1b[0m",
+            name, reason
+        );
+    }
+
+    let groups = config::load_tag_groups(&ctx.target)?;
+    let requested = config::expand_tags(&ctx.tags, &groups);
+    if ctx.debug && requested != ctx.tags {
+        println!("Tag groups expanded to: {:?}", requested);
+    }
+
+    let mut selected = Vec::new();
+    let mut broken = Vec::new();
+    let mut shadowed: Vec<(&str, Vec<String>)> = Vec::new();
+    // Filter regressions by name pattern, then parse their configurations in parallel
+    let names: Vec<String> = names
+        .into_iter()
+        .filter(|name| ctx.selects_any(name, regression_names))
+        .collect();
+    let unmatched: Vec<&String> = regression_names
+        .iter()
+        .filter(|regression_name| !names.iter().any(|name| ctx.selects(name, regression_name)))
+        .collect();
+    let loaded = config::load_many(&ctx.target, &names);
+    for (name, config) in names.into_iter().zip(loaded) {
+        let config = match config {
+            Ok(config) => config,
+            Err(err) => {
+                // Whatever its tags, a broken regression must not silently disappear
+                broken.push((name, err.to_string()));
+                continue;
+            }
+        };
+        if ctx.debug {
+            println!("Regression {} has tags: {:?}", config.name, config.tags);
+        }
+        for group in &groups {
+            let requested_group = std::slice::from_ref(&group.name);
+            if ctx.tags.contains(&group.name)
+                && config.matches_tags(requested_group, ctx.implicit_tags)
+            {
+                match shadowed.iter_mut().find(|(name, _)| *name == group.name) {
+                    Some((_, names)) => names.push(config.name.clone()),
+                    None => shadowed.push((&group.name, vec![config.name.clone()])),
+                }
+            }
+        }
+        // Check if regression matches any of the requested tags, or the expression, groups expanded
+        if ctx.selects_tags(&groups, &|tags| {
+            config.matches_tags(tags, ctx.implicit_tags)
+        }) && ctx.selects_regbase(&config)
+        {
+            selected.push(config);
+        }
+    }
+
+    for (group, names) in &shadowed {
+        println!(
+            "\x1b[0;33mWarning: the tag group {} of {} shadows the tag {} of {}, both are selected\x1b[0m",
+            group,
+            config::TAG_GROUPS_FILE,
+            group,
+            names.join(", ")
+        );
+    }
+
+    broken.sort();
+    ctx.config_errors.fetch_add(broken.len(), Ordering::Relaxed);
+    for regression_name in regression_names {
+        if unmatched.contains(&regression_name) {
+            println!(
+                "\x1b[0;33mWarning: no regression matches the name {}\x1b[0m",
+                regression_name
+            );
+        } else if !selected
+            .iter()
+            .any(|config| ctx.selects(&config.name, regression_name))
+            && !broken
+                .iter()
+                .any(|(name, _)| ctx.selects(name, regression_name))
+        {
+            let mut filter = match &ctx.tag_expr {
+                Some(expr) => format!("satisfies the tag expression {}", expr),
+                None => format!("has the tags {}", ctx.tags.join(",")),
+            };
+            if let Some(regbase) = &ctx.regbase {
+                filter.push_str(&format!(" and a regbase matching {}", regbase));
+            }
+            println!(
+                "\x1b[0;33mWarning: no regression matching the name {} {}\x1b[0m",
+                regression_name, filter
+            );
+        }
+    }
+    // Higher priorities first, directory order otherwise
+    selected.sort_by_key(|config| std::cmp::Reverse(config.priority));
+    Ok((selected, broken))
+}
+
+/// Returns the error of a broken configuration without its position, for the listings.
+fn config_error_summary(err: &str) -> &str {
+    err.split(" at line ").next().unwrap_or(err)
+}
+
+/// Returns the name patterns of a command selecting the regressions by a single one, none
+/// selecting them all.
+fn requested_names(regression_name: &str) -> Vec<String> {
+    if regression_name.is_empty() {
+        Vec::new()
+    } else {
+        vec![regression_name.to_string()]
+    }
+}
+
+/// Returns the names of the regression directories in the data directory, in scan order.
+fn scan_regressions(ctx: &Context) -> Result<Vec<String>, io::Error> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&ctx.target)? {
+        let entry = entry?;
+        // Skip anything that cannot be a regression
+        if is_regression_entry(ctx, &entry) {
+            names.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// Groups the regression names that are equal ignoring case, exact duplicates included.
+///
+/// Only groups with more than one name are returned, sorted.
+fn name_collisions(names: &[String]) -> Vec<Vec<String>> {
+    let mut groups: std::collections::BTreeMap<String, Vec<String>> =
+        std::collections::BTreeMap::new();
+    for name in names {
+        groups
+            .entry(name.to_lowercase())
+            .or_default()
+            .push(name.clone());
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort();
+            group
+        })
+        .collect()
+}
+
+/// Formats name collisions as `a, A; b, B`.
+fn describe_collisions(collisions: &[Vec<String>]) -> String {
+    collisions
+        .iter()
+        .map(|group| group.join(", "))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Describes regression tests by displaying their configuration details.
+///
+/// # Arguments
+///
+/// * `ctx` - Settings shared by all the regressions
+/// * `regression_names` - Filter patterns for regression names, a regression matching any is selected (none selects all)
+///
+/// # Returns
+///
+/// The results of the regressions, those that could not be described included.
+///
+/// # Errors
+///
+/// Returns an error if the target directory cannot be read.
+///
+/// # Output Format
+///
+/// For each matching regression, displays:
+/// - Regression name (in green)
+/// - regbase: Base directory in examples repository
+/// - sourcedata: Path to generated output file
+/// - targetdata: Path to expected output file
+/// - regcommand: Command to execute
+/// - tags: List of tags for this regression
+fn describe_regressions(
+    ctx: &Context,
+    regression_names: &[String],
+) -> Result<Vec<RunResult>, io::Error> {
+    if ctx.debug {
+        println!("Describe regressions matching: {:?}", regression_names);
+        println!("Filtering the regressions {}", ctx.tagged());
+    }
+
+    let (selected, broken) = select_with_broken(ctx, regression_names)?;
+    let mut presenter = Presenter::default();
+    for config in selected {
+        // Stop scheduling new regressions after an interrupt
+        if interrupt::interrupted().is_some() {
+            break;
+        }
+        // The error is reported with the output of the regression
+        presenter.show(execute_regression(
+            ctx,
+            "describe",
+            &config,
+            &mut ResetPrompt::Always,
+        ));
+    }
+    // The whole error is printed, with the position of a parse error
+    for (name, err) in broken {
+        let mut result = RunResult::skipped(&name, "describe", "invalid config");
+        result.output = format!(
+            "Regression: \x1b[0;31m{} (invalid config: {})\x1b[0m\n  error: {}\n",
+            name,
+            config_error_summary(&err),
+            err
+        );
+        presenter.show(result);
+    }
+
+    Ok(presenter.results)
+}
+
+/// Prints the configurations of the selected regressions as a YAML document, the effective
+/// ones sorted for diffing with `canonical`, the merged ones otherwise.
+fn print_configurations(
+    ctx: &Context,
+    regression_names: &[String],
+    canonical: bool,
+) -> Result<(), io::Error> {
+    let (selected, broken) = select_with_broken(ctx, regression_names)?;
+    if canonical {
+        print!("{}", effective::canonical(&selected, &broken));
+    } else {
+        print!("{}", effective::merged(&ctx.target, &selected, &broken)?);
+    }
+    Ok(())
+}
+
+/// Runs regression tests and compares results against expected outputs.
+///
+/// # Arguments
+///
+/// * `ctx` - Settings shared by all the regressions
+/// * `regression_names` - Filter patterns for regression names, a regression matching any is selected (none selects all)
+/// * `bootstrap` - Capture missing expected outputs instead of failing
+/// * `only_due` - Skip the regressions that are not due according to their frequency
+/// * `only_failed` - Skip the regressions that neither failed nor errored, nor were left
+///   unrun by the time budget, in their last recorded run
+/// * `since` - Skip the regressions not affected by the changes since these git references
+///
+/// # Returns
+///
+/// The results of the regressions, those that could not be executed included.
+///
+/// # Errors
+///
+/// Returns an error if the target directory cannot be read, or the regressions to run
+/// cannot be selected.
+///
+/// # Output
+///
+/// For each test:
+/// - "Regression `<name>`: passed" (in green) if output matches expected
+/// - "Regression `<name>`: failed" (in red) if output differs
+/// - "Regression `<name>`: captured" (in yellow) if the missing expected output was
+///   written in bootstrap mode
 fn run_regressions(
+    ctx: &Context,
+    regression_names: &[String],
+    bootstrap: bool,
+    only_due: bool,
+    only_failed: bool,
+    since: &changes::Since,
+) -> Result<Vec<RunResult>, io::Error> {
+    if ctx.debug {
+        println!("Run regressions matching: {:?}", regression_names);
+        println!("Filtering the regressions {}", ctx.tagged());
+    }
+
+    let mut selected = select_regressions(ctx, regression_names)?;
+    if only_due {
+        let count = selected.len();
+        selected = due_regressions(ctx, selected, timestamp::DateTime::now())?;
+        if selected.len() < count {
+            println!(
+                "{} regressions skipped as not due according to their frequency",
+                count - selected.len()
+            );
+        }
+    }
+    if only_failed {
+        let count = selected.len();
+        selected = failed_regressions(ctx, selected)?;
+        if selected.len() < count {
+            println!(
+                "{} regressions skipped as they did not fail in their last recorded run",
+                count - selected.len()
+            );
+        }
+    }
+    if since.is_set() {
+        selected = affected_regressions(ctx, selected, since)?;
+        if selected.is_empty() {
+            println!("No regression is affected by the changes, nothing to run");
+            return Ok(Vec::new());
+        }
+    }
+    let header = run_header(ctx, regression_names, &selected);
+    print!("{}", header.render(ctx.debug));
+    *ctx.header() = Some(header);
+    let action = if bootstrap { "bootstrap" } else { "run" };
+    let mut presenter = Presenter::default();
+    if ctx.jobs > 1 {
+        execute_concurrently(ctx, action, &selected, &mut presenter);
+        return Ok(presenter.results);
+    }
+    for (i, config) in selected.iter().enumerate() {
+        // Stop scheduling new regressions after an interrupt
+        if interrupt::interrupted().is_some() {
+            break;
+        }
+        if budget_spent(ctx, selected[i..].iter()) {
+            break;
+        }
+        // The error is reported with the output of the regression
+        presenter.show(execute_regression(
+            ctx,
+            action,
+            config,
+            &mut ResetPrompt::Always,
+        ));
+    }
+
+    Ok(presenter.results)
+}
+
+/// Executes the selected regressions, up to `--jobs` of them at the same time, and shows
+/// their results in the order of the selection, each one as soon as those before it are.
+///
+/// Two regressions of the same example directory never run at the same time, their
+/// commands would build in it together. The results are recorded in the order of the
+/// selection too, whatever the order they complete in.
+fn execute_concurrently(
+    ctx: &Context,
+    action: &str,
+    selected: &[config::RegressionConfig],
+    presenter: &mut Presenter,
+) {
+    let example =
+        |config: &config::RegressionConfig| (config.examples.clone(), config.regbase.clone());
+    // The positions of the regressions not started yet, and the example directories of the
+    // running ones
+    let state = Mutex::new((
+        (0..selected.len()).collect::<std::collections::VecDeque<usize>>(),
+        std::collections::HashSet::new(),
+    ));
+    let finished = std::sync::Condvar::new();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..ctx.jobs.min(selected.len()) {
+            let sender = sender.clone();
+            let (state, finished, example) = (&state, &finished, &example);
+            scope.spawn(move || loop {
+                let next = {
+                    let mut state = state.lock().unwrap_or_else(|err| err.into_inner());
+                    loop {
+                        let (waiting, running) = &mut *state;
+                        // Stop scheduling new regressions after an interrupt
+                        if waiting.is_empty() || interrupt::interrupted().is_some() {
+                            break None;
+                        }
+                        if budget_spent(ctx, waiting.iter().map(|i| &selected[*i])) {
+                            waiting.clear();
+                            break None;
+                        }
+                        let free = waiting
+                            .iter()
+                            .position(|i| !running.contains(&example(&selected[*i])));
+                        if let Some(i) = free.and_then(|position| waiting.remove(position)) {
+                            running.insert(example(&selected[i]));
+                            break Some(i);
+                        }
+                        state = finished.wait(state).unwrap_or_else(|err| err.into_inner());
+                    }
+                };
+                let Some(i) = next else {
+                    finished.notify_all();
+                    break;
+                };
+                let result =
+                    execute_regression(ctx, action, &selected[i], &mut ResetPrompt::Always);
+                state
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .1
+                    .remove(&example(&selected[i]));
+                finished.notify_all();
+                if sender.send((i, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+        // The results completed ahead of those before them wait for them
+        let mut completed = std::collections::BTreeMap::new();
+        let mut next = 0;
+        for (i, result) in receiver {
+            completed.insert(i, result);
+            while let Some(result) = completed.remove(&next) {
+                presenter.show(result);
+                next += 1;
+            }
+        }
+        // Those after a regression not started, after an interrupt
+        for result in completed.into_values() {
+            presenter.show(result);
+        }
+    });
+    let position = |name: &str| selected.iter().position(|config| config.name == name);
+    ctx.results().sort_by_key(|record| position(&record.name));
+}
+
+/// Returns the header of a run of the selected regressions: the filters, the checkouts
+/// with their commits, and the settings in effect.
+fn run_header(
+    ctx: &Context,
+    regression_names: &[String],
+    selected: &[config::RegressionConfig],
+) -> header::RunHeader {
+    let mut checkouts = vec![
+        ("examples".to_string(), ctx.source.clone()),
+        ("regression data".to_string(), ctx.target.clone()),
+    ];
+    for (name, dir) in &ctx.named_sources {
+        checkouts.push((format!("examples {}", name), dir.clone()));
+    }
+    // Each regression runs within its own timeout if it has one, or else that of --timeout
+    let timeouts: Vec<u64> = selected
+        .iter()
+        .filter_map(|config| config.timeout.or(ctx.timeout))
+        .collect();
+    let timeout = match timeouts.iter().max() {
+        Some(longest) => format!(
+            "{} of {} regressions, up to {}s",
+            timeouts.len(),
+            selected.len(),
+            longest
+        ),
+        None => "none".to_string(),
+    };
+    let mut settings = vec![
+        ("jobs".to_string(), ctx.jobs.to_string()),
+        ("timeout".to_string(), timeout),
+    ];
+    if ctx.smoke {
+        settings.push(("mode".to_string(), "smoke".to_string()));
+    }
+    if let Some(iterations) = ctx.bench {
+        settings.push((
+            "bench".to_string(),
+            format!("{} iterations after a warm-up", iterations),
+        ));
+    }
+    if ctx.ignore_source_changes {
+        settings.push(("source changes".to_string(), "ignored".to_string()));
+    }
+    if let Some(deadline) = &ctx.deadline {
+        let hard = if deadline.hard { " (hard)" } else { "" };
+        settings.push((
+            "max total time".to_string(),
+            format!("{}{}", runner::format_elapsed(deadline.budget), hard),
+        ));
+    }
+    header::RunHeader {
+        run_id: ctx.run_id.clone(),
+        names: regression_names.to_vec(),
+        exact: ctx.exact,
+        tags: ctx.tags.clone(),
+        tag_expr: ctx.tag_expr.as_ref().map(|expr| expr.to_string()),
+        regbase: ctx.regbase.clone(),
+        selected: selected.iter().map(|config| config.name.clone()).collect(),
+        checkouts: checkouts
+            .into_iter()
+            .map(|(repository, dir)| {
+                let commit = toolchain::repo_commit(std::path::Path::new(&dir));
+                let dir = fs::canonicalize(&dir)
+                    .map(|dir| dir.display().to_string())
+                    .unwrap_or(dir);
+                (repository, dir, commit)
+            })
+            .collect(),
+        settings,
+    }
+}
+
+/// Resets regression tests by updating expected outputs with current results.
+///
+/// This command is useful when the expected output needs to be updated,
+/// such as after intentional changes to the tool or examples.
+///
+/// # Arguments
+///
+/// * `ctx` - Settings shared by all the regressions
+/// * `regression_names` - Filter patterns for regression names, a regression matching any is selected (none selects all)
+/// * `interactive` - Ask for confirmation before resetting each regression
+/// * `preview` - Only show what would change, without writing anything
+///
+/// # Returns
+///
+/// The results of the regressions, those skipped after a quit at the prompt included.
+/// [`present::changed`] gives those whose expected output has been (or, in preview mode,
+/// would be) changed.
+///
+/// # Errors
+///
+/// Returns an error if the target directory cannot be read or if
+/// resetting a regression fails.
+///
+/// # Output
+///
+/// For each reset test:
+/// - "Regression `<name>`: reset" (in yellow)
+/// - "Regression `<name>`: skipped" (in yellow) if declined at the interactive prompt
+/// - "Regression `<name>`: would change ..." (in yellow) or "unchanged" (in green) in preview mode
+///
+/// In interactive mode a final summary lists the reset and the skipped regressions,
+/// in preview mode it totals the changes.
+fn reset_regressions(
+    ctx: &Context,
+    regression_names: &[String],
+    interactive: bool,
+    preview: bool,
+) -> Result<Vec<RunResult>, io::Error> {
+    if ctx.debug {
+        println!("Reset regressions matching: {:?}", regression_names);
+        println!("Filtering the regressions {}", ctx.tagged());
+    }
+
+    let mut prompt = if interactive {
+        ResetPrompt::Ask
+    } else {
+        ResetPrompt::Always
+    };
+    let action = if preview { "preview" } else { "reset" };
+    let mut presenter = Presenter::default();
+
+    for config in select_regressions(ctx, regression_names)? {
+        // Stop scheduling new regressions after an interrupt
+        if interrupt::interrupted().is_some() {
+            break;
+        }
+        // After a quit at the prompt the remaining regressions are not even executed
+        if prompt == ResetPrompt::Quit {
+            presenter.keep(RunResult::skipped(
+                &config.name,
+                action,
+                "quit at the prompt",
+            ));
+            continue;
+        }
+        presenter.show(execute_regression(ctx, action, &config, &mut prompt));
+    }
+
+    print!("{}", presenter.reset_summary(interactive, preview));
+    Ok(presenter.results)
+}
+
+/// Runs regression tests and resets only those whose output differs from the expected one.
+///
+/// Each regression command is executed once, the generated output is compared with
+/// the expected output and copied over it only when they differ. Regressions whose
+/// command fails are reported as errors and their expected output is left untouched.
+///
+/// # Arguments
+///
+/// * `ctx` - Settings shared by all the regressions
+/// * `regression_name` - Filter pattern for regression names (empty string matches all)
+///
+/// # Returns
+///
+/// The results of the regressions, those whose expected output has been updated being
+/// [`Status::Updated`].
+///
+/// # Errors
+///
+/// Returns an error if the target directory cannot be read.
+///
+/// # Output
+///
+/// For each test:
+/// - "Regression `<name>`: updated" (in yellow) if the expected output has been replaced
+/// - "Regression `<name>`: unchanged" (in green) if the output already matched
+fn update_regressions(ctx: &Context, regression_name: &str) -> Result<Vec<RunResult>, io::Error> {
+    if ctx.debug {
+        println!("Update regressions matching: \"{}\"", regression_name);
+        println!("Filtering the regressions {}", ctx.tagged());
+    }
+
+    let mut presenter = Presenter::default();
+
+    for config in select_regressions(ctx, &requested_names(regression_name))? {
+        // Stop scheduling new regressions after an interrupt
+        if interrupt::interrupted().is_some() {
+            break;
+        }
+        presenter.show(execute_regression(
+            ctx,
+            "update",
+            &config,
+            &mut ResetPrompt::Always,
+        ));
+    }
+
+    Ok(presenter.results)
+}
+
+/// Checks the configurations of the regressions, without executing anything.
+///
+/// All the regressions matching the name are checked, whatever their tags, since a
+/// broken configuration can hide a regression from the tag filter.
+///
+/// # Returns
+///
+/// The number of invalid regressions.
+///
+/// # Errors
+///
+/// Returns an error if the target directory cannot be read.
+fn validate_regressions(ctx: &Context, regression_name: &str) -> Result<usize, io::Error> {
+    if ctx.debug {
+        println!("Validate regressions matching: \"{}\"", regression_name);
+    }
+
+    let mut invalid = 0;
+    let all_names = scan_regressions(ctx)?;
+    let collisions = name_collisions(&all_names);
+    let mut names: Vec<String> = all_names
+        .into_iter()
+        .filter(|name| ctx.selects(name, regression_name))
+        .collect();
+    names.sort();
+
+    if std::path::Path::new(&ctx.target)
+        .join(config::TAGS_FILE)
+        .exists()
+    {
+        match config::load_tag_defaults(&ctx.target) {
+            Ok(_) => println!("Tags file {}: \x1b[0;32mvalid\x1b[0m", config::TAGS_FILE),
+            Err(err) => {
+                invalid += 1;
+                println!("Tags file {}: \x1b[0;31minvalid\x1b[0m", config::TAGS_FILE);
+                println!("  - {}", err);
+            }
+        }
+    }
+
+    if std::path::Path::new(&ctx.target)
+        .join(config::TAG_GROUPS_FILE)
+        .exists()
+    {
+        match config::load_tag_groups(&ctx.target) {
+            Ok(_) => println!(
+                "Tag groups file {}: \x1b[0;32mvalid\x1b[0m",
+                config::TAG_GROUPS_FILE
+            ),
+            Err(err) => {
+                invalid += 1;
+                println!(
+                    "Tag groups file {}: \x1b[0;31minvalid\x1b[0m",
+                    config::TAG_GROUPS_FILE
+                );
+                println!("  - {}", err);
+            }
+        }
+    }
+
+    for name in names {
+        let mut problems = validate_regression(ctx, &name);
+        if let Some(group) = collisions.iter().find(|group| group.contains(&name)) {
+            let others: Vec<&str> = group
+                .iter()
+                .filter(|other| **other != name)
+                .map(|other| other.as_str())
+                .collect();
+            problems.push(format!(
+                "name collides case-insensitively with {}",
+                others.join(", ")
+            ));
+        }
+        if problems.is_empty() {
+            println!("Regression {}: \x1b[0;32mvalid\x1b[0m", name);
+        } else {
+            invalid += 1;
+            println!("Regression {}: \x1b[0;31minvalid\x1b[0m", name);
+            for problem in problems {
+                println!("  - {}", problem);
+            }
+        }
+    }
+
+    Ok(invalid)
+}
+
+/// Compresses the expected outputs of at least `min_size` bytes of the regressions matching
+/// the name, whatever their tags, renaming them to `.gz` in their configurations.
+///
+/// # Returns
+///
+/// The number of expected outputs compressed.
+fn compress_baselines(ctx: &Context, regression_name: &str, min_size: u64) -> io::Result<usize> {
+    let mut names: Vec<String> = scan_regressions(ctx)?
+        .into_iter()
+        .filter(|name| ctx.selects(name, regression_name))
+        .collect();
+    names.sort();
+    let mut compressed = 0;
+    for name in names {
+        let config = match config::load(&ctx.target, &name) {
+            Ok(config) => config,
+            Err(err) => {
+                println!("Regression {}: error: {}", name, err);
+                continue;
+            }
+        };
+        let regression_dir = std::path::Path::new(&ctx.target).join(&config.name);
+        let mut converted = Vec::new();
+        let mut files: Vec<&str> = config
+            .targetdata
+            .iter()
+            .map(|(_, file)| file.as_str())
+            .filter(|file| !gzip::is_compressed(file))
+            .collect();
+        files.dedup();
+        for file in files {
+            let size = match fs::metadata(regression_dir.join(file)) {
+                Ok(metadata) if metadata.len() >= min_size => metadata.len(),
+                _ => continue,
+            };
+            match compress_baseline(ctx, &config, file) {
+                Ok(renamed) => {
+                    let compressed_size = fs::metadata(regression_dir.join(&renamed))?.len();
+                    converted.push(format!(
+                        "{} ({}) to {} ({})",
+                        file,
+                        human_size(size),
+                        renamed,
+                        human_size(compressed_size)
+                    ));
+                }
+                Err(err) => println!("Regression {}: error: {}", config.name, err),
+            }
+        }
+        if converted.is_empty() {
+            println!("Regression {}: unchanged", config.name);
+        } else {
+            compressed += converted.len();
+            println!(
+                "Regression {}: compressed {}",
+                config.name,
+                converted.join(", ")
+            );
+        }
+    }
+    println!("{} expected outputs compressed", compressed);
+    Ok(compressed)
+}
+
+/// Checks the name of a new regression against the names of the existing ones.
+///
+/// # Errors
+///
+/// Returns an error if the name cannot be a regression directory or is taken, whatever the case.
+fn check_new_name(names: &[String], name: &str) -> io::Result<()> {
+    if name.is_empty()
+        || name.contains(['/', '\\'])
+        || name.starts_with(['.', '_'])
+        || IGNORED_DIRS.contains(&name)
+    {
+        return Err(io::Error::other(format!(
+            "{} is not a valid regression name, a directory name not starting with . or _",
+            name
+        )));
+    }
+    if let Some(taken) = names.iter().find(|n| n.eq_ignore_ascii_case(name)) {
+        return Err(io::Error::other(format!(
+            "the regression {} already exists",
+            taken
+        )));
+    }
+    Ok(())
+}
+
+/// Creates a regression as a copy of the directory of another one, setting the `overrides`
+/// keys in its configuration.
+///
+/// Without `with_baseline` the expected outputs are not copied, so that the first
+/// `run --bootstrap` captures them. The regbase of the new regression is checked against the
+/// examples right away.
+///
+/// # Errors
+///
+/// Returns an error if the source does not exist, the name is not a valid regression name or
+/// is taken, or the new configuration is invalid or names a regbase missing from the
+/// examples, in which case nothing is created.
+fn copy_regression(
+    ctx: &Context,
     source: &str,
+    name: &str,
+    overrides: &[(&str, &str)],
+    with_baseline: bool,
+) -> io::Result<()> {
+    let names = scan_regressions(ctx)?;
+    if !names.iter().any(|n| n == source) {
+        return Err(io::Error::other(format!("no regression named {}", source)));
+    }
+    check_new_name(&names, name)?;
+    let source_config = config::load(&ctx.target, source)?;
+    let dir = std::path::Path::new(&ctx.target).join(name);
+    repository::copy_dir(&std::path::Path::new(&ctx.target).join(source), &dir)?;
+
+    let created = (|| {
+        if !overrides.is_empty() {
+            config::set_keys(&ctx.target, name, overrides)?;
+        }
+        let config = config::load(&ctx.target, name)?;
+        let examples = ctx.examples_dir(&config)?;
+        let regbase = paths::regbase_dir(examples, &config.regbase)?;
+        if !regbase.is_dir() {
+            return Err(io::Error::other(format!(
+                "the regbase {} is not a directory of the examples",
+                config.regbase
+            )));
+        }
+        // The notes are about the failures of the source
+        match fs::remove_file(dir.join(notes::FILE)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        if !with_baseline {
+            for (_, file) in &source_config.targetdata {
+                match fs::remove_file(dir.join(file)) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                    _ => {}
+                }
+                provenance::remove(&dir.join(file))?;
+            }
+        }
+        Ok(config)
+    })();
+    let config = match created {
+        Ok(config) => config,
+        Err(err) => {
+            fs::remove_dir_all(&dir)?;
+            return Err(err);
+        }
+    };
+    println!(
+        "Regression {}: created from {}, regbase {}, command {}",
+        config.name, source, config.regbase, config.regcommand
+    );
+    if !with_baseline {
+        println!(
+            "Capture its expected output with: bmregression --data-dir {} --tag {} run --exact {} --bootstrap",
+            summary::shell_quote(&ctx.target),
+            summary::shell_quote(&config.tags.join(",")),
+            summary::shell_quote(name)
+        );
+    }
+    Ok(())
+}
+
+/// Parts of the configuration of an imported regression given on the command line.
+struct ImportProposal {
+    sourcedata: Option<String>,
+    regcommand: Option<String>,
+}
+
+/// Asks to choose among several candidates, unless there is only one.
+///
+/// # Returns
+///
+/// The chosen candidate, None if the question is not answered.
+///
+/// # Errors
+///
+/// Returns an error listing the candidates if there are several and `yes` forbids asking.
+fn choose(
+    what: &str,
+    option: &str,
+    candidates: Vec<String>,
+    yes: bool,
+) -> io::Result<Option<String>> {
+    if candidates.len() == 1 {
+        return Ok(candidates.into_iter().next());
+    }
+    if yes {
+        return Err(io::Error::other(format!(
+            "several {} found, {}, choose one with {}",
+            what,
+            candidates.join(", "),
+            option
+        )));
+    }
+    println!("Several {} found:", what);
+    for (index, candidate) in candidates.iter().enumerate() {
+        println!("  {}) {}", index + 1, candidate);
+    }
+    let stdin = io::stdin();
+    loop {
+        print!("Choose [1-{}]: ", candidates.len());
+        io::stdout().flush()?;
+        let Some(answer) = interrupt::read_answer(&mut stdin.lock())? else {
+            println!();
+            return Ok(None);
+        };
+        match answer.trim().parse::<usize>() {
+            Ok(choice) if (1..=candidates.len()).contains(&choice) => {
+                return Ok(candidates.into_iter().nth(choice - 1))
+            }
+            _ => println!("Please answer a number between 1 and {}", candidates.len()),
+        }
+    }
+}
+
+/// Creates a regression for an example of the primary examples repository, with the
+/// configuration proposed from its Makefile and outputs, see [`import`].
+///
+/// The candidates are chosen among when there are several, and the configuration is written
+/// after a confirmation, unless `yes`. With `bootstrap` the new regression is run once to
+/// capture its expected output.
+///
+/// # Returns
+///
+/// The status of the bootstrap run, None if the regression was not run.
+///
+/// # Errors
+///
+/// Returns an error if the example does not exist, the name is invalid or taken, nothing can
+/// be proposed, or the configuration cannot be written, in which case nothing is created.
+fn import_regression(
+    ctx: &Context,
+    example: &str,
+    name: &str,
+    proposal: ImportProposal,
+    yes: bool,
+    bootstrap: bool,
+) -> io::Result<Option<Status>> {
+    check_new_name(&scan_regressions(ctx)?, name)?;
+    let example_dir = paths::regbase_dir(&ctx.source, example)?;
+    if !example_dir.is_dir() {
+        return Err(io::Error::other(format!(
+            "{} is not a directory of the examples",
+            example
+        )));
+    }
+    let commands = match proposal.regcommand {
+        Some(regcommand) => vec![regcommand],
+        None => import::commands(&example_dir)?,
+    };
+    let outputs = match proposal.sourcedata {
+        Some(sourcedata) => vec![sourcedata],
+        None => import::outputs(&example_dir)?,
+    };
+    let Some(regcommand) = choose("make targets", "--regcommand", commands, yes)? else {
+        println!("Nothing imported");
+        return Ok(None);
+    };
+    let Some(sourcedata) = choose("outputs", "--sourcedata", outputs, yes)? else {
+        println!("Nothing imported");
+        return Ok(None);
+    };
+    let targetdata = std::path::Path::new(&sourcedata)
+        .file_name()
+        .map(|file| file.to_string_lossy().to_string())
+        .unwrap_or_else(|| sourcedata.clone());
+    let content = format!(
+        "regbase: {}\nsourcedata: {}\ntargetdata: {}\nregcommand: {}\ntags: [{}]\n",
+        config::yaml_scalar(example.trim_matches('/')),
+        config::yaml_scalar(&sourcedata),
+        config::yaml_scalar(&targetdata),
+        config::yaml_scalar(&regcommand),
+        config::DEFAULT_TAG
+    );
+    println!("Configuration of the regression {}:", name);
+    for line in content.lines() {
+        println!("  {}", line);
+    }
+    if !yes && !confirm(&format!("Create the regression {}?", name))? {
+        println!("Nothing imported");
+        return Ok(None);
+    }
+
+    let dir = std::path::Path::new(&ctx.target).join(name);
+    fs::create_dir_all(&dir)?;
+    let config =
+        fs::write(dir.join("config.yaml"), &content).and_then(|_| config::load(&ctx.target, name));
+    let config = match config {
+        Ok(config) => config,
+        Err(err) => {
+            fs::remove_dir_all(&dir)?;
+            return Err(err);
+        }
+    };
+    println!("Regression {}: created for the example {}", name, example);
+    if !bootstrap {
+        return Ok(None);
+    }
+    // The error is reported with the output of the regression
+    let result = execute_regression(ctx, "bootstrap", &config, &mut ResetPrompt::Always);
+    present::print(&result);
+    Ok(Some(result.status))
+}
+
+/// Asks a yes or no question, no being the default and the answer at the end of the input.
+fn confirm(question: &str) -> io::Result<bool> {
+    print!("{} [y/N]: ", question);
+    io::stdout().flush()?;
+    let Some(answer) = interrupt::read_answer(&mut io::stdin().lock())? else {
+        println!();
+        return Ok(false);
+    };
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Checks the capabilities of this binary on the bundled fixture, see [`selftest`].
+///
+/// # Returns
+///
+/// The exit code, 1 if a capability does not work.
+///
+/// # Errors
+///
+/// Returns an error if the fixture cannot be written.
+fn self_test(keep: bool) -> io::Result<i32> {
+    let exe = std::env::current_exe()?;
+    let dir = TempDir::new("bmregression-self-test")?;
+    println!("Self-test of {} in {}", exe.display(), dir.path().display());
+    let checks = selftest::run(&exe, dir.path())?;
+    let style = style::StatusStyle::default();
+    for check in &checks {
+        match &check.result {
+            Some(Ok(())) => println!("{}: {}", check.capability, style::status(style, "passed")),
+            Some(Err(problem)) => {
+                println!("{}: {}", check.capability, style::status(style, "failed"));
+                for line in problem.lines() {
+                    println!("  {}", line);
+                }
+            }
+            None => println!(
+                "{}: {} (an earlier capability failed)",
+                check.capability,
+                style::status(style, "skipped")
+            ),
+        }
+    }
+    if keep {
+        println!("Fixture kept in {}", dir.into_path().display());
+    }
+    let working = checks
+        .iter()
+        .filter(|check| matches!(check.result, Some(Ok(()))))
+        .count();
+    println!("{} of {} capabilities working", working, checks.len());
+    Ok(if working < checks.len() { 1 } else { 0 })
+}
+
+/// Writes the plan of the regressions a run would execute to `out`, see [`plan`].
+///
+/// # Errors
+///
+/// Returns an error if the regressions cannot be selected, one has no expected output
+/// for the baseline, or the plan cannot be written.
+fn plan_regressions(
+    ctx: &Context,
+    regression_name: &str,
+    repositories: &[plan::Repository],
+    out: &str,
+) -> io::Result<()> {
+    let selected = select_regressions(ctx, &requested_names(regression_name))?;
+    let mut entries = Vec::new();
+    for config in &selected {
+        let targetdata = config
+            .targetdata_for(&ctx.baseline)
+            .map_err(|err| io::Error::other(format!("regression {}: {}", config.name, err)))?;
+        entries.push(plan::regression_json(config, &targetdata));
+    }
+    fs::write(
+        out,
+        plan::render(
+            regression_name,
+            &ctx.tags,
+            &ctx.baseline,
+            repositories,
+            &entries,
+        ),
+    )?;
+    for repository in repositories {
+        match &repository.commit {
+            Some(commit) => println!("{} at {}", repository.name, commit),
+            None => println!(
+                "\x1b[0;33mWarning: {} is not a git checkout, apply cannot check that it is unchanged\x1b[0m",
+                repository.name
+            ),
+        }
+    }
+    for config in &selected {
+        println!("\t{}", config.name);
+    }
+    println!("Planned {} regressions in {}", selected.len(), out);
+    Ok(())
+}
+
+/// Executes the regressions of a plan, in its order and with its baseline, see [`plan`].
+///
+/// Nothing is executed unless every checkout is at its planned commit and every planned
+/// regression still has its planned command, comparison and paths.
+///
+/// # Errors
+///
+/// Returns an error if the plan cannot be read, or describing the checkouts and the
+/// regressions that no longer match it.
+fn apply_plan(ctx: &mut Context, path: &str, repositories: &[plan::Repository]) -> io::Result<()> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| io::Error::other(format!("reading {} failed: {}", path, err)))?;
+    let plan = plan::parse(&text)
+        .map_err(|err| io::Error::other(format!("{} is not a valid plan: {}", path, err)))?;
+    let moved = plan::moved(&plan.repositories, repositories);
+    if !moved.is_empty() {
+        return Err(io::Error::other(format!(
+            "the checkouts moved since the plan:\n  {}",
+            moved.join("\n  ")
+        )));
+    }
+    for repository in plan
+        .repositories
+        .iter()
+        .filter(|repository| repository.commit.is_none())
+    {
+        println!(
+            "\x1b[0;33mWarning: {} was not a git checkout when planned, it is not checked\x1b[0m",
+            repository.name
+        );
+    }
+
+    ctx.baseline = plan.baseline;
+    let mut configs = Vec::new();
+    let mut changed = Vec::new();
+    for (name, planned) in &plan.regressions {
+        let config = match config::load(&ctx.target, name) {
+            Ok(config) => config,
+            Err(err) => {
+                changed.push(format!("{}: {}", name, err));
+                continue;
+            }
+        };
+        let current = config
+            .targetdata_for(&ctx.baseline)
+            .ok()
+            .and_then(|targetdata| json::parse(&plan::regression_json(&config, &targetdata)).ok());
+        if current.as_ref() != Some(planned) {
+            changed.push(format!("{}: changed since the plan", name));
+        }
+        configs.push(config);
+    }
+    if !changed.is_empty() {
+        return Err(io::Error::other(format!(
+            "the regressions no longer match the plan:\n  {}",
+            changed.join("\n  ")
+        )));
+    }
+
+    println!("Applying the plan {}: {} regressions", path, configs.len());
+    for (i, config) in configs.iter().enumerate() {
+        // Stop scheduling new regressions after an interrupt
+        if interrupt::interrupted().is_some() {
+            break;
+        }
+        if budget_spent(ctx, configs[i..].iter()) {
+            break;
+        }
+        // The error is reported with the output of the regression
+        present::print(&execute_regression(
+            ctx,
+            "run",
+            config,
+            &mut ResetPrompt::Always,
+        ));
+    }
+    Ok(())
+}
+
+/// Exports a run, the most recent one by default, in a gzipped tarball, see [`export`].
+///
+/// # Errors
+///
+/// Returns an error if there is no such run or the archive cannot be written.
+fn export_run(
+    ctx: &Context,
+    run: Option<String>,
+    out: Option<String>,
+    no_artifacts: bool,
+) -> io::Result<()> {
+    let run_id = match run {
+        Some(run_id) => run_id,
+        None => export::latest_run(&ctx.target)?,
+    };
+    let out = out.unwrap_or_else(|| format!("bmregression-{}.tar.gz", run_id));
+    // The artifacts of the current run are in a directory named after it
+    let artifacts_root = ctx.artifacts_dir.parent().filter(|_| !no_artifacts);
+    let exported = export::write(
+        &ctx.target,
+        &run_id,
+        artifacts_root,
+        std::path::Path::new(&out),
+    )?;
+    println!(
+        "Exported run {} to {}: {} regressions, {} files",
+        run_id, out, exported.regressions, exported.files
+    );
+    Ok(())
+}
+
+/// Expected outputs imported from an archive.
+struct Imported {
+    /// Regressions whose expected output has been written
+    updated: Vec<String>,
+    /// Regressions that could not be imported, broken configurations included
+    errors: usize,
+}
+
+/// Takes the generated outputs of an archive written by export as the expected outputs of
+/// the selected regressions, without running anything.
+///
+/// The expected outputs are checked as reset checks them: the protected and the
+/// hand-written ones are skipped unless overridden, and their provenance records the
+/// archived run. The selected regressions without an output in the archive, such as those
+/// that passed, are listed, and so are the outputs of the archive matching no selected
+/// regression.
+///
+/// # Errors
+///
+/// Returns an error if the archive cannot be opened or the regressions cannot be read.
+fn import_baselines(ctx: &Context, archive: &str, names: &[String]) -> io::Result<Imported> {
+    let archive = export::open(std::path::Path::new(archive))?;
+    let run_id = archive.run_id();
+    if let Some(run_id) = &run_id {
+        ctx.provenance
+            .preset(provenance::from_run(run_id, &archive.environment()));
+    }
+    let outputs = archive.outputs()?;
+    let (configs, broken) = select_with_broken(ctx, names)?;
+    let mut imported = Imported {
+        updated: Vec::new(),
+        errors: broken.len(),
+    };
+    for (name, err) in &broken {
+        let status = Status::Errored {
+            kind: io::ErrorKind::InvalidData,
+            detail: err.clone(),
+        };
+        let note = format!(" (invalid config: {})", config_error_summary(err));
+        print_status(ctx, name, &status, &note);
+    }
+    let mut missing = Vec::new();
+    for config in &configs {
+        let Some((_, output)) = outputs.iter().find(|(name, _)| *name == config.name) else {
+            missing.push(config.name.as_str());
+            continue;
+        };
+        match import_baseline(ctx, config, output) {
+            Ok(Status::Updated) => imported.updated.push(config.name.clone()),
+            Ok(_) => {}
+            Err(err) => {
+                let status = Status::Errored {
+                    kind: err.kind(),
+                    detail: err.to_string(),
+                };
+                print_status(ctx, &config.name, &status, &format!(": {}", err));
+                imported.errors += 1;
+            }
+        }
+    }
+
+    let run = run_id
+        .map(|run_id| format!(" of run {}", run_id))
+        .unwrap_or_default();
+    if !missing.is_empty() {
+        let statuses = archive.statuses();
+        println!(
+            "{} selected regressions have no output in the archive{}, their expected output is kept:",
+            missing.len(),
+            run
+        );
+        for name in missing {
+            let status = statuses
+                .iter()
+                .find(|(archived, _)| archived == name)
+                .map(|(_, status)| match status {
+                    Some(status) => format!(" ({} in the run)", status),
+                    None => " (no result in the run)".to_string(),
+                })
+                .unwrap_or_else(|| " (not in the run)".to_string());
+            println!("  - {}{}", name, status);
+        }
+    }
+    let unmatched: Vec<(&str, &str)> = outputs
+        .iter()
+        .filter(|(name, _)| !configs.iter().any(|config| config.name == *name))
+        .map(|(name, _)| {
+            let known = broken.iter().any(|(broken, _)| broken == name)
+                || std::path::Path::new(&ctx.target)
+                    .join(name)
+                    .join("config.yaml")
+                    .is_file();
+            let reason = if known {
+                "not selected"
+            } else {
+                "not a regression"
+            };
+            (name.as_str(), reason)
+        })
+        .collect();
+    if !unmatched.is_empty() {
+        println!(
+            "\x1b[0;33m{} outputs of the archive are not imported:\x1b[0m",
+            unmatched.len()
+        );
+        for (name, reason) in unmatched {
+            println!("\x1b[0;33m  - {} ({})\x1b[0m", name, reason);
+        }
+    }
+    Ok(imported)
+}
+
+/// Writes the archived output `output` as the expected output of a regression, unless it
+/// is identical, protected or written by hand.
+///
+/// # Errors
+///
+/// Returns an error if the expected output cannot be chosen among the candidates, read
+/// or written.
+fn import_baseline(
+    ctx: &Context,
+    config: &config::RegressionConfig,
+    output: &std::path::Path,
+) -> io::Result<Status> {
+    // The expected output is written as reset writes it
+    let config = config.for_action("reset");
+    let regression_dir = format!("{}/{}", ctx.target, config.name);
+    let output = output.to_string_lossy().to_string();
+    let result_data = gzip::read_to_string(&output)?;
+    let candidates = config.targetdata_for(&ctx.baseline)?;
+    let targetdata = choose_candidate(
+        ctx,
+        config,
+        "reset",
+        &regression_dir,
+        &candidates,
+        &result_data,
+    )?;
+    let targetdatafull = format!("{}/{}", regression_dir, targetdata);
+    let baseline_missing = !std::path::Path::new(&targetdatafull).exists();
+    if !baseline_missing && gzip::read_to_string(&targetdatafull)? == result_data {
+        print_status(ctx, &config.name, &Status::Unchanged, "");
+        return Ok(Status::Unchanged);
+    }
+    if config.protected && !baseline_missing && !ctx.override_protected {
+        let status = Status::Skipped {
+            reason: "the targetdata is protected".to_string(),
+        };
+        let reason = config
+            .protected_reason
+            .as_ref()
+            .map(|reason| format!(": {}", reason))
+            .unwrap_or_default();
+        print_status(
+            ctx,
+            &config.name,
+            &status,
+            &format!(
+                " (the targetdata is protected{}, use --override-protected to overwrite it)",
+                reason
+            ),
+        );
+        return Ok(status);
+    }
+    if config.compare.is_hand_written() && !baseline_missing && !ctx.force {
+        let status = Status::Skipped {
+            reason: "the targetdata is written by hand".to_string(),
+        };
+        print_status(
+            ctx,
+            &config.name,
+            &status,
+            " (the targetdata is written by hand for its compare mode, use --force to overwrite it)",
+        );
+        return Ok(status);
+    }
+    let stored = store_baseline(ctx, config, &output, targetdata, &targetdatafull)?;
+    let new_baseline = if baseline_missing {
+        " (new baseline)"
+    } else {
+        ""
+    };
+    print_status(
+        ctx,
+        &config.name,
+        &Status::Updated,
+        &format!("{}{}", new_baseline, stored),
+    );
+    Ok(Status::Updated)
+}
+
+/// Deletes regressions from the data directory, with their entries in the history, the
+/// command logs and the quarantine file.
+///
+/// With a name the regression with exactly that name is removed, otherwise those selected by
+/// the tags, listed first and confirmed twice. Unless `yes`, the removal is confirmed, after
+/// a warning naming the other regressions including files of the removed ones. The recorded
+/// results of the `results` directory are kept, they are an audit trail.
+///
+/// # Errors
+///
+/// Returns an error if the named regression does not exist, or if anything cannot be
+/// read or deleted.
+fn remove_regressions(ctx: &Context, regression_name: Option<&str>, yes: bool) -> io::Result<()> {
+    let names = scan_regressions(ctx)?;
+    let removed: Vec<String> = match regression_name {
+        Some(name) if names.iter().any(|n| n == name) => vec![name.to_string()],
+        Some(name) => return Err(io::Error::other(format!("no regression named {}", name))),
+        None => select_regressions(ctx, &[])?
+            .into_iter()
+            .map(|config| config.name)
+            .collect(),
+    };
+    if removed.is_empty() {
+        println!("No regressions {}", ctx.tagged());
+        return Ok(());
+    }
+
+    // The configurations including files of the removed regressions would break
+    for config in names
+        .iter()
+        .filter(|name| !removed.contains(name))
+        .filter_map(|name| config::load(&ctx.target, name).ok())
+    {
+        for (key, source) in &config.sources {
+            if let Some(dependency) = removed
+                .iter()
+                .find(|name| std::path::Path::new(source).starts_with(name))
+            {
+                println!(
+                    "\x1b[0;33mWarning: {} depends on {}, its {} comes from {}\x1b[0m",
+                    config.name, dependency, key, source
+                );
+            }
+        }
+    }
+
+    if !yes {
+        let confirmed = match regression_name {
+            Some(name) => confirm(&format!(
+                "Remove regression {} with its history, logs and quarantine entry?",
+                name
+            ))?,
+            None => {
+                println!("Regressions {} ({}):", ctx.tagged(), removed.len());
+                for name in &removed {
+                    println!("  {}", name);
+                }
+                confirm(&format!(
+                    "Remove these {} regressions with their history, logs and quarantine entries?",
+                    removed.len()
+                ))? && confirm(&format!(
+                    "The {} regression directories will be deleted, are you sure?",
+                    removed.len()
+                ))?
+            }
+        };
+        if !confirmed {
+            println!("Nothing removed");
+            return Ok(());
+        }
+    }
+
+    for name in &removed {
+        fs::remove_dir_all(std::path::Path::new(&ctx.target).join(name))?;
+        let entries = history::remove(&ctx.target, name)?;
+        let logs = logs::remove(&ctx.target, name)?;
+        let quarantined = quarantine::Quarantine::remove(&ctx.target, name)?;
+        println!(
+            "Regression {}: removed, with {} history entries, the logs of {} runs{}",
+            name,
+            entries,
+            logs,
+            if quarantined {
+                " and its quarantine entry"
+            } else {
+                ""
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Rewrites the configurations of the regressions matching the name, whatever their tags, in
+/// the canonical form, see [`config::canonical_config`].
+///
+/// # Returns
+///
+/// The number of configurations changed, or that would change with `dry_run`.
+///
+/// # Errors
+///
+/// Returns an error if the data directory cannot be scanned.
+///
+/// # Output
+///
+/// For each regression "Regression `<name>`: changed" or "unchanged", with `dry_run`
+/// "would change" followed by the diff, or the error leaving it as is.
+fn canonicalize_configs(ctx: &Context, regression_name: &str, dry_run: bool) -> io::Result<usize> {
+    let mut names: Vec<String> = scan_regressions(ctx)?
+        .into_iter()
+        .filter(|name| ctx.selects(name, regression_name))
+        .collect();
+    names.sort();
+    let mut changed = 0;
+    for name in names {
+        let (content, canonical) = match config::canonical_config(&ctx.target, &name) {
+            Ok(contents) => contents,
+            Err(err) => {
+                println!("Regression {}: error: {}", name, err);
+                continue;
+            }
+        };
+        if content == canonical {
+            println!("Regression {}: unchanged", name);
+            continue;
+        }
+        if dry_run {
+            println!("Regression {}: would change", name);
+            let label = format!("{}/config.yaml", name);
+            print!("{}", diff::unified(&content, &canonical, &label, &label));
+        } else {
+            let path = std::path::Path::new(&ctx.target)
+                .join(&name)
+                .join("config.yaml");
+            if let Err(err) = atomic::write(&path, &canonical) {
+                println!("Regression {}: error: {}", name, err);
+                continue;
+            }
+            println!("Regression {}: changed", name);
+        }
+        changed += 1;
+    }
+    if dry_run {
+        println!("{} configurations would change", changed);
+    } else {
+        println!("{} configurations changed", changed);
+    }
+    Ok(changed)
+}
+
+/// Compresses an expected output, renaming it to `.gz` in the configuration of its regression.
+///
+/// # Returns
+///
+/// The name of the compressed expected output.
+///
+/// # Errors
+///
+/// Returns an error if the compressed file already exists or the configuration cannot be
+/// updated, in which case the uncompressed file is kept.
+fn compress_baseline(
+    ctx: &Context,
+    config: &config::RegressionConfig,
+    targetdata: &str,
+) -> io::Result<String> {
+    let regression_dir = std::path::Path::new(&ctx.target).join(&config.name);
+    let compressed = format!("{}{}", targetdata, gzip::EXTENSION);
+    let compressedfull = regression_dir.join(&compressed);
+    if compressedfull.exists() {
+        return Err(io::Error::other(format!(
+            "{} already exists, remove it or name it in targetdata",
+            compressed
+        )));
+    }
+    gzip::copy(regression_dir.join(targetdata), &compressedfull)?;
+    if let Err(err) = config::rename_targetdata(&ctx.target, &config.name, targetdata, &compressed)
+    {
+        fs::remove_file(&compressedfull)?;
+        return Err(err);
+    }
+    fs::remove_file(regression_dir.join(targetdata))?;
+    // The provenance follows the expected output
+    let sidecar = provenance::sidecar(&regression_dir.join(targetdata));
+    if sidecar.exists() {
+        fs::rename(&sidecar, provenance::sidecar(&compressedfull))?;
+    }
+    Ok(compressed)
+}
+
+/// Formats a size in bytes for people.
+fn human_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1048576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        1048576..1073741824 => format!("{:.1} MiB", bytes as f64 / 1048576.0),
+        _ => format!("{:.1} GiB", bytes as f64 / 1073741824.0),
+    }
+}
+
+/// Checks the data repository for problems best caught when reviewing it.
+///
+/// # Returns
+///
+/// The number of problems found, the warnings about the provenance of the expected outputs
+/// older than `max_age` or unknown do not count.
+fn lint_regressions(
+    ctx: &Context,
+    regression_name: &str,
+    max_age: std::time::Duration,
+) -> Result<usize, io::Error> {
+    if ctx.debug {
+        println!("Lint regressions matching: \"{}\"", regression_name);
+    }
+
+    let names = scan_regressions(ctx)?;
+    let mut problems = Vec::new();
+    for group in name_collisions(&names) {
+        if group.iter().any(|name| ctx.selects(name, regression_name)) {
+            problems.push(format!("regression names collide: {}", group.join(", ")));
+        }
+    }
+    for entry in ctx.quarantine.entries() {
+        if !ctx.selects(&entry.name, regression_name) {
+            continue;
+        }
+        if !names.contains(&entry.name) {
+            problems.push(format!(
+                "{} quarantines {}, which is not a regression",
+                quarantine::FILE,
+                entry.name
+            ));
+        } else if ctx.quarantine.is_expired(entry) {
+            problems.push(format!(
+                "the quarantine of {} expired on {}",
+                entry.name,
+                entry.expires.as_deref().unwrap_or_default()
+            ));
+        }
+    }
+    // Broken configurations are left to validate
+    let names: Vec<String> = names
+        .into_iter()
+        .filter(|name| ctx.selects(name, regression_name))
+        .collect();
+    let configs = config::load_many(&ctx.target, &names)
+        .into_iter()
+        .filter_map(Result::ok);
+    let mut protected = Vec::new();
+    let mut warnings = Vec::new();
+    let now = timestamp::DateTime::now();
+    for config in configs {
+        if config.protected {
+            match &config.protected_reason {
+                Some(reason) => protected.push(format!("{} ({})", config.name, reason)),
+                None => protected.push(config.name.clone()),
+            }
+        }
+        let source = ctx.examples_dir(&config);
+        match source.and_then(|source| paths::regbase_dir(source, &config.regbase)) {
+            Ok(dir) if !dir.is_dir() => problems.push(format!(
+                "the regbase {} of {} is not a directory of the examples",
+                config.regbase, config.name
+            )),
+            Ok(_) => {}
+            Err(err) => problems.push(format!("{}: {}", config.name, err)),
+        }
+        // Large files slow down the clones of the data repository for everyone
+        for (_, targetdata) in &config.targetdata {
+            let path = std::path::Path::new(&ctx.target)
+                .join(&config.name)
+                .join(targetdata);
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            match provenance::load(&path) {
+                Ok(Some(provenance)) => {
+                    if let Some(generated) = provenance.generated {
+                        let age = now.unix_seconds() - generated.unix_seconds();
+                        if age > max_age.as_secs() as i64 {
+                            warnings.push(format!(
+                                "the expected output {}/{} was generated on {}, {}",
+                                config.name,
+                                targetdata,
+                                generated.date(),
+                                generated.age(now)
+                            ));
+                        }
+                    }
+                }
+                Ok(None) => warnings.push(format!(
+                    "the expected output {}/{} has no provenance, reset it to record it",
+                    config.name, targetdata
+                )),
+                Err(err) => problems.push(format!("{}: {}", config.name, err)),
+            }
+            if metadata.len() > ctx.baseline_warn_size
+                && !lfs::is_tracked(std::path::Path::new(&ctx.target), &path)
+            {
+                problems.push(format!(
+                    "the expected output {}/{} is {}, above {}, compress it or route it to git LFS",
+                    config.name,
+                    targetdata,
+                    human_size(metadata.len()),
+                    human_size(ctx.baseline_warn_size)
+                ));
+            }
+        }
+    }
+
+    // Protected expected outputs are not problems, they are listed to review the protections
+    if !protected.is_empty() {
+        println!("Protected regressions ({}):", protected.len());
+        for regression in &protected {
+            println!("  - {}", regression);
+        }
+    }
+    if !warnings.is_empty() {
+        println!(
+            "\x1b[0;33m{} warnings about the provenance of the expected outputs:\x1b[0m",
+            warnings.len()
+        );
+        for warning in &warnings {
+            println!("  - {}", warning);
+        }
+    }
+    if problems.is_empty() {
+        println!("\x1b[0;32mNo problems found\x1b[0m");
+    } else {
+        println!("\x1b[0;31m{} problems found:\x1b[0m", problems.len());
+        for problem in &problems {
+            println!("  - {}", problem);
+        }
+    }
+    Ok(problems.len())
+}
+
+/// Lists the tags of the regressions, with the regressions carrying each one.
+///
+/// The tags of the configurations are listed apart from those derived from the
+/// paths of the regressions, which `--tag` matches unless `--no-implicit-tags`
+/// is given, and followed by the tag groups. Regressions with a broken
+/// configuration are left to validate.
+fn list_tags(ctx: &Context, regression_name: &str) -> Result<(), io::Error> {
+    let mut explicit: std::collections::BTreeMap<String, Vec<String>> =
+        std::collections::BTreeMap::new();
+    let mut implicit: std::collections::BTreeMap<String, Vec<String>> =
+        std::collections::BTreeMap::new();
+    let names: Vec<String> = scan_regressions(ctx)?
+        .into_iter()
+        .filter(|name| ctx.selects(name, regression_name))
+        .collect();
+    let configs = config::load_many(&ctx.target, &names)
+        .into_iter()
+        .filter_map(Result::ok);
+    for config in configs {
+        for tag in &config.tags {
+            explicit
+                .entry(tag.clone())
+                .or_default()
+                .push(config.name.clone());
+        }
+        for tag in config.implicit_tags() {
+            implicit.entry(tag).or_default().push(config.name.clone());
+        }
+    }
+
+    println!("Explicit tags:");
+    for (tag, names) in &explicit {
+        println!("  {}: {}", tag, names.join(", "));
+    }
+    if ctx.implicit_tags {
+        println!("Implicit tags (from the paths):");
+    } else {
+        println!("Implicit tags (from the paths, disabled by --no-implicit-tags):");
+    }
+    for (tag, names) in &implicit {
+        println!("  {}: {}", tag, names.join(", "));
+    }
+    let groups = config::load_tag_groups(&ctx.target)?;
+    if !groups.is_empty() {
+        println!("Tag groups (from {}):", config::TAG_GROUPS_FILE);
+    }
+    for group in &groups {
+        let shadows = explicit.contains_key(&group.name)
+            || (ctx.implicit_tags && implicit.contains_key(&group.name));
+        println!(
+            "  {}: {}{}",
+            group.name,
+            group.members.join(", "),
+            if shadows {
+                " \x1b[0;33m(shadows the tag of the same name)\x1b[0m"
+            } else {
+                ""
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Returns the problems found in the configuration of a regression.
+fn validate_regression(ctx: &Context, regression_name: &str) -> Vec<String> {
+    let config = match config::load(&ctx.target, regression_name) {
+        Ok(config) => config,
+        Err(err) => return vec![err.to_string()],
+    };
+
+    let mut problems = Vec::new();
+    let source = match ctx.examples_dir(&config) {
+        Ok(source) => source,
+        Err(err) => return vec![err.to_string()],
+    };
+    let examplesource = match paths::regbase_dir(source, &config.regbase) {
+        Ok(dir) => dir.to_string_lossy().to_string(),
+        Err(err) => return vec![err.to_string()],
+    };
+    if !std::path::Path::new(&examplesource).is_dir() {
+        problems.push(
+            setup::Missing::example(
+                &config.regbase,
+                std::path::Path::new(&examplesource),
+                std::path::Path::new(source),
+            )
+            .to_string(),
+        );
+    } else if !ctx.allow_external_paths {
+        let sourcedata = std::iter::once(&config.sourcedata)
+            .chain(config.files.iter().skip(1).map(|file| &file.sourcedata));
+        for sourcedata in sourcedata {
+            if let Err(err) = paths::check_within(&examplesource, sourcedata, "sourcedata") {
+                problems.push(err.to_string());
+            }
+        }
+        if let Some(workdir) = &config.workdir {
+            if let Err(err) = paths::check_within(&examplesource, workdir, "workdir") {
+                problems.push(err.to_string());
+            }
+        }
+    }
+    if !ctx.allow_external_paths {
+        let regression_dir = format!("{}/{}", ctx.target, regression_name);
+        let targetdata = config.targetdata.iter().chain(
+            config
+                .files
+                .iter()
+                .skip(1)
+                .flat_map(|file| &file.targetdata),
+        );
+        for (_, targetdata) in targetdata {
+            if let Err(err) = paths::check_within(&regression_dir, targetdata, "targetdata") {
+                problems.push(err.to_string());
+            }
+        }
+    }
+    if let Err(err) = config.targetdata_for(&ctx.baseline) {
+        problems.push(err.to_string());
+    }
+    for file in config.files.iter().skip(1) {
+        if let Err(err) = config.for_file(file).targetdata_for(&ctx.baseline) {
+            problems.push(format!("{}: {}", file.sourcedata, err));
+        }
+    }
+    // Invalid patterns would only be found when running the regression
+    if config.compare == config::Compare::RegexLines {
+        for (_, targetdata) in &config.targetdata {
+            let path = std::path::Path::new(&ctx.target)
+                .join(regression_name)
+                .join(targetdata);
+            let Ok(patterns) = gzip::read_to_string(&path) else {
+                continue;
+            };
+            if let Err(err) = pattern::match_lines(&patterns, "") {
+                problems.push(format!("{}: {}", targetdata, err));
+            }
+        }
+    }
+    if std::path::Path::new(&examplesource).is_dir() {
+        if let Err(err) = command_dir(&examplesource, &config) {
+            problems.push(err.to_string());
+        }
+    }
+    problems
+}
+
+/// Returns the directory the command of a regression runs in, its workdir if it has one.
+///
+/// # Errors
+///
+/// Returns an error with the resolved path if the workdir is not a directory.
+fn command_dir(
+    examplesource: &str,
+    config: &config::RegressionConfig,
+) -> Result<std::path::PathBuf, io::Error> {
+    let Some(workdir) = &config.workdir else {
+        return Ok(std::path::PathBuf::from(examplesource));
+    };
+    let dir = std::path::Path::new(examplesource).join(workdir);
+    if !dir.is_dir() {
+        return Err(io::Error::other(format!(
+            "workdir {} not found",
+            paths::resolve(&dir)?.display()
+        )));
+    }
+    Ok(dir)
+}
+
+/// Prints the statistics of the regressions over the most recent recorded runs.
+///
+/// The name and tag filters apply to the regressions as recorded in the history,
+/// or in the recorded results, so that regressions removed since then are still
+/// accounted for.
+///
+/// # Errors
+///
+/// Returns an error if the runs cannot be read.
+fn show_stats(
+    ctx: &Context,
+    regression_name: &str,
+    last: usize,
+    format: report::Format,
+    source: history::Source,
+) -> Result<(), io::Error> {
+    let runs = source.load_all(&ctx.target)?;
+    let runs = &runs[runs.len().saturating_sub(last)..];
+    if runs.is_empty() && format == report::Format::Text {
+        println!("No recorded runs");
+        return Ok(());
+    }
+    let groups = config::load_tag_groups(&ctx.target)?;
+    let (regressions, trend) = stats::aggregate(runs, |entry| {
+        ctx.selects(&entry.name, regression_name)
+            && ctx.selects_tags(&groups, &|tags| entry.tags.iter().any(|t| tags.contains(t)))
+    });
+    match format {
+        report::Format::Text => stats::print_text(&regressions, &trend),
+        report::Format::Json => println!("{}", stats::render_json(&regressions, &trend)),
+    }
+    Ok(())
+}
+
+/// How often the resident modes check for an interrupt while waiting.
+const RESIDENT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Serves the HTTP API, executing the requested runs one at a time until interrupted.
+///
+/// Each run gets its own identifier, logs and artifacts directory, and its
+/// results are saved in the history like those of the run command.
+///
+/// # Errors
+///
+/// Returns an error if the address cannot be bound or the data directory cannot be read.
+fn serve(ctx: &mut Context, listen: &str, keep_logs: usize) -> Result<(), io::Error> {
+    let server = serve::Server::start(listen, &ctx.target, ctx.tags.clone())?;
+    server.set_regressions(all_regressions(ctx)?);
+    println!("Serving on http://{}", listen);
+    if std::env::var_os(serve::TOKEN_VARIABLE).is_none() {
+        println!(
+            "\x1b[0;33mWarning: {} is not set, the API accepts requests from anyone who can reach it\x1b[0m",
+            serve::TOKEN_VARIABLE
+        );
+    }
+
+    while interrupt::interrupted().is_none() {
+        let Some(job) = server.next_job(RESIDENT_POLL_INTERVAL) else {
+            continue;
+        };
+        println!(
+            "Run {}: regressions matching \"{}\" with tags {}",
+            job.id,
+            job.name,
+            job.tags.join(",")
+        );
+        server.set_regressions(all_regressions(ctx)?);
+        ctx.tags = job.tags;
+        ctx.log_dir = ctx.log_dir.with_file_name(&job.id);
+        ctx.artifacts_dir = ctx.artifacts_dir.with_file_name(&job.id);
+        ctx.errors.store(0, Ordering::Relaxed);
+        ctx.config_errors.store(0, Ordering::Relaxed);
+        ctx.results().clear();
+        server.update(&job.id, "running", Vec::new());
+
+        if let Err(err) = run_regressions(
+            ctx,
+            &requested_names(&job.name),
+            false,
+            false,
+            false,
+            &changes::Since::default(),
+        ) {
+            println!("Error selecting the regressions to run: {}", err);
+        }
+        // Taken out, so that only the results of an interrupted run are summarized at the exit
+        let results = std::mem::take(&mut *ctx.results());
+        if interrupt::interrupted().is_some() {
+            server.update(&job.id, "interrupted", results.clone());
+            *ctx.results() = results;
+            break;
+        }
+        if !results.is_empty() {
+            if let Err(err) = history::save(&ctx.target, &job.id, &results) {
+                println!("Warning: saving the run history failed: {}", err);
+            }
+        }
+        server.update(&job.id, "completed", results);
+        if let Err(err) = logs::prune(&ctx.target, keep_logs, ctx.debug) {
+            println!("Error pruning old logs: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the paths whose changes make a regression run again: its example, its directory
+/// in the data repository and the files its configuration takes keys from.
+fn watched_paths(
+    ctx: &Context,
+    config: &config::RegressionConfig,
+) -> Result<Vec<std::path::PathBuf>, io::Error> {
+    let target = std::path::Path::new(&ctx.target);
+    let mut paths = vec![
+        paths::regbase_dir(ctx.examples_dir(config)?, &config.regbase)?,
+        target.join(&config.name),
+        target.join(config::TAGS_FILE),
+    ];
+    for (_, source) in &config.sources {
+        if !source.starts_with(config::TAGS_FILE) {
+            paths.push(target.join(source));
+        }
+    }
+    Ok(paths)
+}
+
+/// Runs the selected regressions, then again whenever the files they depend on change,
+/// until interrupted.
+///
+/// The configurations are loaded again before each run, so that a change to a
+/// `config.yaml` applies to the run it triggers. What the regressions write while
+/// they run, such as build artifacts in the example, is part of the snapshot taken
+/// after the run and does not trigger another one. A watched path stays watched when
+/// its regression is no longer selected, such as after a broken edit of its
+/// configuration, so that fixing it triggers a run.
+///
+/// # Errors
+///
+/// Returns an error if an ignore pattern is invalid or the data directory cannot be read.
+fn watch_regressions(
+    ctx: &mut Context,
+    regression_name: &str,
+    ignore: &[String],
+    debounce: std::time::Duration,
+    reproduce: &str,
+    keep_logs: usize,
+) -> Result<(), io::Error> {
+    let ignore = ignore
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern).map_err(|err| {
+                io::Error::other(format!("invalid ignore pattern {}: {}", pattern, err))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut paths: Vec<std::path::PathBuf> = Vec::new();
+    let mut trigger = "starting".to_string();
+
+    while interrupt::interrupted().is_none() {
+        let started = timestamp::DateTime::now();
+        let run_id = history::new_run_id(started);
+        let selected = select_regressions(ctx, &requested_names(regression_name))?;
+        for config in &selected {
+            match watched_paths(ctx, config) {
+                Ok(watched) => {
+                    for path in watched {
+                        if !paths.contains(&path) {
+                            paths.push(path);
+                        }
+                    }
+                }
+                Err(err) => println!("Warning: cannot watch {}: {}", config.name, err),
+            }
+        }
+        if paths.is_empty() {
+            return Err(io::Error::other(format!(
+                "no regression matches \"{}\", nothing to watch",
+                regression_name
+            )));
+        }
+
+        println!(
+            "\x1b[1m=== {} {} UTC, {}: running {} regressions ===\x1b[0m",
+            started.date(),
+            started.time(),
+            trigger,
+            selected.len()
+        );
+        let run_start = std::time::Instant::now();
+        ctx.run_id = run_id.clone();
+        ctx.log_dir = ctx.log_dir.with_file_name(&run_id);
+        ctx.artifacts_dir = ctx.artifacts_dir.with_file_name(&run_id);
+        ctx.errors.store(0, Ordering::Relaxed);
+        ctx.config_errors.store(0, Ordering::Relaxed);
+        for config in &selected {
+            if interrupt::interrupted().is_some() {
+                break;
+            }
+            present::print(&execute_regression(
+                ctx,
+                "run",
+                config,
+                &mut ResetPrompt::Always,
+            ));
+        }
+        if interrupt::interrupted().is_some() {
+            break;
+        }
+        // Taken out, so that only the results of an interrupted run are summarized at the exit
+        let report = results::RunReport {
+            run_id,
+            started,
+            duration: run_start.elapsed(),
+            environment: results::Environment::default(),
+            results: std::mem::take(&mut *ctx.results()),
+        };
+        if !report.results.is_empty() {
+            print!(
+                "{}",
+                summary::recap(&report, &format!("{} run", reproduce), None)
+            );
+        }
+        if let Err(err) = logs::prune(&ctx.target, keep_logs, ctx.debug) {
+            println!("Error pruning old logs: {}", err);
+        }
+
+        println!("Watching {} paths for changes, Ctrl-C to stop", paths.len());
+        let before = watch::snapshot(&paths, &ignore);
+        let Some(changed) = watch::wait(
+            &paths,
+            &ignore,
+            &before,
+            RESIDENT_POLL_INTERVAL.min(debounce.max(std::time::Duration::from_millis(100))),
+            debounce,
+            || interrupt::interrupted().is_some(),
+        ) else {
+            break;
+        };
+        let mut named: Vec<String> = changed
+            .iter()
+            .take(3)
+            .map(|path| path.display().to_string())
+            .collect();
+        if changed.len() > 3 {
+            named.push(format!("{} more", changed.len() - 3));
+        }
+        trigger = format!("changed {}", named.join(", "));
+    }
+    println!("Watch stopped by {}", interrupt::signal_name());
+    Ok(())
+}
+
+/// Runs the regressions that are due at every cycle, until interrupted.
+///
+/// Each cycle refreshes the fetched repositories, runs the regressions whose
+/// frequency makes them due according to the history, saves their results and
+/// environment in the history, notifies the webhook and appends a line to the
+/// log of the scheduler. A SIGTERM lets the running regression finish before exiting.
+///
+/// # Errors
+///
+/// Returns an error if the interval is invalid or the data directory cannot be read.
+fn schedule(
+    ctx: &mut Context,
+    regression_name: &str,
+    interval: &str,
+    webhook: Option<(&str, notify::NotifyOn)>,
+    repositories: &[(&str, &std::path::Path, &str)],
+    clone_options: &repository::CloneOptions,
+    keep_logs: usize,
+) -> Result<(), io::Error> {
+    let interval = schedule::parse_interval(interval).map_err(io::Error::other)?;
+    interrupt::set_graceful();
+    println!(
+        "Scheduling the regressions matching \"{}\" every {}, log in {}",
+        regression_name,
+        runner::format_elapsed(interval),
+        schedule::log_path(&ctx.target).display()
+    );
+
+    while interrupt::interrupted().is_none() {
+        let cycle_start = std::time::Instant::now();
+        let started = timestamp::DateTime::now();
+        let run_id = history::new_run_id(started);
+        for (location, dir, description) in repositories {
+            let refreshed =
+                repository::refresh(location, dir, description, clone_options, ctx.debug);
+            if let Err(err) = refreshed {
+                println!("Warning: {}, using the current checkout", err);
+            }
+        }
+
+        // Taken at every cycle, as the refresh may have moved the commits
+        let environment = toolchain::snapshot(&ctx.source, &ctx.target, Vec::new(), false);
+        let selected = select_regressions(ctx, &requested_names(regression_name))?;
+        // A cron expression may fire before the next cycle
+        let next_fire = selected
+            .iter()
+            .filter_map(|config| config.frequency.next_fire(started))
+            .min();
+        let due = due_regressions(ctx, selected, started)?;
+        println!("Cycle {}: {} regressions due", run_id, due.len());
+        ctx.run_id = run_id.clone();
+        ctx.log_dir = ctx.log_dir.with_file_name(&run_id);
+        ctx.artifacts_dir = ctx.artifacts_dir.with_file_name(&run_id);
+        ctx.errors.store(0, Ordering::Relaxed);
+        ctx.config_errors.store(0, Ordering::Relaxed);
+        for config in &due {
+            // Stop scheduling new regressions after an interrupt
+            if interrupt::interrupted().is_some() {
+                break;
+            }
+            present::print(&execute_regression(
+                ctx,
+                "run",
+                config,
+                &mut ResetPrompt::Always,
+            ));
+        }
+
+        let report = results::RunReport {
+            run_id: run_id.clone(),
+            started,
+            duration: cycle_start.elapsed(),
+            environment,
+            results: std::mem::take(&mut *ctx.results()),
+        };
+        if !report.results.is_empty() {
+            let saved = history::save(&ctx.target, &run_id, &report.results).and_then(|()| {
+                history::save_environment(&ctx.target, &run_id, report.started, &report.environment)
+            });
+            if let Err(err) = saved {
+                println!("Warning: saving the run history failed: {}", err);
+            }
+            if let Some((url, notify_on)) = webhook {
+                if notify::should_notify(&report, notify_on) {
+                    if let Err(err) = notify::send(url, &notify::payload(&report), ctx.debug) {
+                        println!("Warning: sending the webhook notification failed: {}", err);
+                    }
+                }
+            }
+        }
+        let due: Vec<String> = due
+            .iter()
+            .map(|config| report::json_string(&config.name))
+            .collect();
+        let line = format!(
+            "{{\"run_id\": {}, \"due\": [{}], \"passed\": {}, \"failed\": {}, \"errors\": {}, \"duration_seconds\": {}, \"interrupted\": {}}}",
+            report::json_string(&run_id),
+            due.join(", "),
+            report.count("passed"),
+            report.count("failed"),
+            report.count("error"),
+            report.duration.as_secs(),
+            interrupt::interrupted().is_some()
+        );
+        if let Err(err) = schedule::append_log(&ctx.target, &line) {
+            println!("Warning: writing the schedule log failed: {}", err);
+        }
+        if let Err(err) = logs::prune(&ctx.target, keep_logs, ctx.debug) {
+            println!("Error pruning old logs: {}", err);
+        }
+
+        // The cycles start at regular intervals, whatever they last, or when a cron expression fires
+        while cycle_start.elapsed() < interval
+            && next_fire.is_none_or(|fire| timestamp::DateTime::now() < fire)
+            && interrupt::interrupted().is_none()
+        {
+            std::thread::sleep(RESIDENT_POLL_INTERVAL);
+        }
+    }
+    println!("Scheduler stopped by {}", interrupt::signal_name());
+    Ok(())
+}
+
+/// Keeps the regressions affected by the changes since the git references, printing why each
+/// one is selected.
+///
+/// The changed paths of each examples checkout are listed once, see [`changes`].
+///
+/// # Errors
+///
+/// Returns an error if a checkout is not a git repository or a reference is unknown.
+fn affected_regressions(
+    ctx: &Context,
+    selected: Vec<config::RegressionConfig>,
+    since: &changes::Since,
+) -> Result<Vec<config::RegressionConfig>, io::Error> {
+    let mut changed_examples: Vec<(String, Vec<String>)> = Vec::new();
+    let changed_data = match &since.data {
+        Some(reference) => {
+            let changed = changes::changed_paths(std::path::Path::new(&ctx.target), reference)?;
+            println!(
+                "{} paths changed in the data repository since {}",
+                changed.len(),
+                reference
+            );
+            Some(changed)
+        }
+        None => None,
+    };
+
+    let mut affected = Vec::new();
+    for config in selected {
+        let mut reasons = Vec::new();
+        if let Some(reference) = &since.examples {
+            let dir = ctx.examples_dir(&config)?.to_string();
+            if !changed_examples.iter().any(|(listed, _)| listed == &dir) {
+                let changed = changes::changed_paths(std::path::Path::new(&dir), reference)?;
+                println!(
+                    "{} paths changed in the examples {} since {}",
+                    changed.len(),
+                    dir,
+                    reference
+                );
+                changed_examples.push((dir.clone(), changed));
+            }
+            let (_, changed) = changed_examples
+                .iter()
+                .find(|(listed, _)| listed == &dir)
+                .expect("listed above");
+            reasons.extend(changes::example_reason(&config, changed));
+        }
+        if let Some(changed) = &changed_data {
+            reasons.extend(changes::data_reason(&config, changed));
+        }
+        if reasons.is_empty() {
+            if ctx.debug {
+                println!("Regression {} not affected by the changes", config.name);
+            }
+            continue;
+        }
+        println!(
+            "Regression {} selected, {}",
+            config.name,
+            reasons.join("; ")
+        );
+        affected.push(config);
+    }
+    Ok(affected)
+}
+
+/// Keeps the regressions that are due at `now`, according to their frequency and the history.
+///
+/// # Errors
+///
+/// Returns an error if the history cannot be read.
+fn due_regressions(
+    ctx: &Context,
+    selected: Vec<config::RegressionConfig>,
+    now: timestamp::DateTime,
+) -> Result<Vec<config::RegressionConfig>, io::Error> {
+    let last_runs = schedule::last_runs(&ctx.target)?;
+    Ok(selected
+        .into_iter()
+        .filter(|config| {
+            let last = last_runs.get(&config.name).copied();
+            let due = config.frequency.is_due(last, now);
+            if !due && ctx.debug {
+                println!(
+                    "Regression {} not due, frequency {}, last run {}",
+                    config.name,
+                    config.frequency,
+                    last.map(|last| last.compact()).unwrap_or_default()
+                );
+            }
+            due
+        })
+        .collect())
+}
+
+/// Keeps the regressions that failed, errored or were not run for lack of time in their last
+/// recorded run, those never run are left out.
+///
+/// # Errors
+///
+/// Returns an error if the history cannot be read.
+fn failed_regressions(
+    ctx: &Context,
+    selected: Vec<config::RegressionConfig>,
+) -> Result<Vec<config::RegressionConfig>, io::Error> {
+    let latest = history::latest_entries(&ctx.target)?;
+    Ok(selected
+        .into_iter()
+        .filter(|config| {
+            latest.get(&config.name).is_some_and(|(_, entry)| {
+                results::is_failure(&entry.status) || entry.status == results::NOT_RUN
+            })
+        })
+        .collect())
+}
+
+/// Records the regressions left to run as not run once the time budget is spent.
+///
+/// # Returns
+///
+/// True if the budget is spent, and no other regression must be started.
+fn budget_spent<'a>(
+    ctx: &Context,
+    left: impl ExactSizeIterator<Item = &'a config::RegressionConfig>,
+) -> bool {
+    let Some(deadline) = ctx.deadline.filter(Deadline::reached) else {
+        return false;
+    };
+    let error = format!(
+        "not run, the --max-total-time budget of {} was spent",
+        runner::format_elapsed(deadline.budget)
+    );
+    println!(
+        "\x1b[0;33mTime budget of {} spent, {} regressions not run\x1b[0m",
+        runner::format_elapsed(deadline.budget),
+        left.len()
+    );
+    ctx.results()
+        .extend(left.map(|config| results::RegressionResult {
+            name: config.name.clone(),
+            tags: config.tags.clone(),
+            command: redact::text(&config.regcommand),
+            status: results::NOT_RUN,
+            error: error.clone(),
+            ..Default::default()
+        }));
+    true
+}
+
+/// Returns the names and tags of all the regressions with a valid configuration, whatever their tags.
+fn all_regressions(ctx: &Context) -> Result<Vec<(String, Vec<String>)>, io::Error> {
+    Ok(config::load_many(&ctx.target, &scan_regressions(ctx)?)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|config| (config.name, config.tags))
+        .collect())
+}
+
+/// Prints the results of the most recent recorded run, optionally writing a badge of them.
+///
+/// # Errors
+///
+/// Returns an error if the history cannot be read or the badge cannot be written.
+fn show_status(ctx: &Context, badge_path: Option<&str>) -> Result<(), io::Error> {
+    let runs = history::load_all(&ctx.target)?;
+    let Some(run) = runs.last() else {
+        println!("No recorded runs");
+        return Ok(());
+    };
+    let total = run.entries.len();
+    let passed = run
+        .entries
+        .iter()
+        .filter(|e| results::is_pass(&e.status))
+        .count();
+    println!("Run {}: {}/{} passing", run.id, passed, total);
+    for entry in run.entries.iter().filter(|e| !results::is_pass(&e.status)) {
+        println!("  {}: \x1b[0;31m{}\x1b[0m", entry.name, entry.status);
+    }
+    if let Some(path) = badge_path {
+        badge::write(passed, total, std::path::Path::new(path))?;
+        println!("Badge written to {}", path);
+    }
+    Ok(())
+}
+
+/// Compares two recorded runs and prints the status transitions of the regressions.
+///
+/// # Returns
+///
+/// Returns true if any regression newly fails in the second run.
+///
+/// # Errors
+///
+/// Returns an error if any of the runs is not in the history or the recorded results.
+fn compare_runs(
+    ctx: &Context,
+    run_a: &str,
+    run_b: &str,
+    duration_threshold: f64,
+    format: report::Format,
+    source: history::Source,
+) -> Result<bool, io::Error> {
+    let first = source.load(&ctx.target, run_a)?;
+    let second = source.load(&ctx.target, run_b)?;
+    let groups = config::load_tag_groups(&ctx.target)?;
+    // The tags of a regression are taken from whichever run executed it
+    let changes = compare::compare(&first, &second, |name| {
+        first
+            .entry(name)
+            .or_else(|| second.entry(name))
+            .is_some_and(|entry| {
+                ctx.selects_tags(&groups, &|tags| entry.tags.iter().any(|t| tags.contains(t)))
+            })
+    });
+    match format {
+        report::Format::Text => compare::print_text(&changes, duration_threshold),
+        report::Format::Json => println!("{}", compare::render_json(&changes, duration_threshold)),
+    }
+    Ok(changes
+        .iter()
+        .any(|c| c.transition == compare::Transition::NewlyFailing))
+}
+
+/// Writes the results file of a run in the data repository and prunes the old ones.
+///
+/// With `commit`, the new file and the removal of the pruned ones are committed
+/// together, anything else staged in the data repository is left alone.
+///
+/// # Errors
+///
+/// Returns an error if the files cannot be written or removed, or git fails.
+fn record_results(
+    ctx: &Context,
+    report: &results::RunReport,
+    keep: usize,
+    commit: bool,
+) -> Result<(), io::Error> {
+    let path = recorded::write(&ctx.target, report)?;
+    println!("Results recorded in {}", path.display());
+    let pruned = recorded::prune(&ctx.target, keep)?;
+    if ctx.debug {
+        for path in &pruned {
+            println!("Pruned results file {}", path.display());
+        }
+    }
+    if !commit {
+        return Ok(());
+    }
+
+    let git_add = Command::new("git")
+        .current_dir(&ctx.target)
+        .args(["add", "--all", "--", recorded::DIR])
+        .output()?;
+    if ctx.debug {
+        println!("git add: {:?}", git_add);
+    }
+    if !git_add.status.success() {
+        return Err(io::Error::other(format!(
+            "git add failed: {}",
+            String::from_utf8_lossy(&git_add.stderr).trim()
+        )));
+    }
+
+    let message = format!(
+        "Record regression results of run {}: {} passed, {} failed, {} errors",
+        report.run_id,
+        report.count("passed"),
+        report.count("failed"),
+        report.count("error")
+    );
+    let git_commit = Command::new("git")
+        .current_dir(&ctx.target)
+        .args(["commit", "-m", &message, "--", recorded::DIR])
+        .output()?;
+    if ctx.debug {
+        println!("git commit: {:?}", git_commit);
+    }
+    if !git_commit.status.success() {
+        return Err(io::Error::other(format!(
+            "git commit failed: {}",
+            String::from_utf8_lossy(&git_commit.stderr).trim()
+        )));
+    }
+
+    println!("Committed: {}", message);
+    Ok(())
+}
+
+/// Commits the expected outputs of the given regressions in the data repository.
+///
+/// Only the directories of the given regressions are committed, anything else
+/// staged in the data repository is left alone. Nothing is done if the list is empty.
+///
+/// # Errors
+///
+/// Returns an error if the data directory is not a git repository or if
+/// any git command fails.
+fn commit_baselines(
     target: &str,
+    regressions: &[String],
+    verb: &str,
+    debug: bool,
+) -> Result<(), io::Error> {
+    if regressions.is_empty() {
+        if debug {
+            println!("No regressions to commit");
+        }
+        return Ok(());
+    }
+
+    let git_add = Command::new("git")
+        .current_dir(target)
+        .arg("add")
+        .arg("--")
+        .args(regressions)
+        .output()?;
+    if debug {
+        println!("git add: {:?}", git_add);
+    }
+    if !git_add.status.success() {
+        return Err(io::Error::other(format!(
+            "git add failed: {}",
+            String::from_utf8_lossy(&git_add.stderr).trim()
+        )));
+    }
+
+    let message = format!("{} regression baselines: {}", verb, regressions.join(", "));
+    let git_commit = Command::new("git")
+        .current_dir(target)
+        .arg("commit")
+        .arg("-m")
+        .arg(&message)
+        .arg("--")
+        .args(regressions)
+        .output()?;
+    if debug {
+        println!("git commit: {:?}", git_commit);
+    }
+    if !git_commit.status.success() {
+        return Err(io::Error::other(format!(
+            "git commit failed: {}",
+            String::from_utf8_lossy(&git_commit.stderr).trim()
+        )));
+    }
+
+    println!("Committed: {}", message);
+    Ok(())
+}
+
+/// State of the interactive reset prompt, carried across regressions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ResetPrompt {
+    /// Reset without asking (non-interactive mode or after answering 'all')
+    Always,
+    /// Ask before each reset
+    Ask,
+    /// The user quit the prompt, nothing else is reset
+    Quit,
+}
+
+/// Prints the status line of a passing regression under `label`, noting it if it is
+/// quarantined.
+fn print_passed(ctx: &Context, regression_name: &str, label: &str, criterion: String) -> Status {
+    // Highlighted, so that fixed regressions get out of the quarantine
+    let note = if ctx.quarantine.active(regression_name).is_some() {
+        format!(
+            "{} {}",
+            criterion,
+            style::note(
+                ctx.status_style,
+                "(quarantined, consider removing it from the quarantine)"
+            )
+        )
+    } else {
+        criterion
+    };
+    print_status(ctx, label, &Status::Passed, &note);
+    Status::Passed
+}
+
+/// Returns the timeout of the command of a regression, shortened to the time left before a
+/// hard deadline.
+fn command_timeout(
+    ctx: &Context,
+    config: &config::RegressionConfig,
+) -> Option<std::time::Duration> {
+    let timeout = config
+        .timeout
+        .or(ctx.timeout)
+        .map(std::time::Duration::from_secs);
+    match ctx.deadline.filter(|deadline| deadline.hard) {
+        Some(deadline) => Some(timeout.map_or(deadline.remaining(), |timeout| {
+            timeout.min(deadline.remaining())
+        })),
+        None => timeout,
+    }
+}
+
+/// Returns the expected output identical, byte for byte, to the generated one, for the
+/// regressions compared exactly as they are, `None` if there is none or the outputs must
+/// be read to be compared.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read.
+fn identical_candidate<'a>(
+    config: &config::RegressionConfig,
+    regression_dir: &str,
+    candidates: &[&'a str],
+    result: &str,
+) -> io::Result<Option<&'a str>> {
+    let transformed = config.compare_between.is_some()
+        || !config.mask.is_empty()
+        || !config.ignore.is_empty()
+        || config.normalize_numbers.is_some();
+    if config.compare != config::Compare::Exact || transformed || gzip::is_compressed(result) {
+        return Ok(None);
+    }
+    for candidate in candidates {
+        let expected = std::path::Path::new(regression_dir).join(candidate);
+        if gzip::is_compressed(candidate) || !expected.exists() {
+            continue;
+        }
+        if filecmp::identical(&expected, std::path::Path::new(result))? {
+            return Ok(Some(candidate));
+        }
+    }
+    Ok(None)
+}
+
+/// Prints the status line of a regression, in the style chosen by the user.
+fn print_status(ctx: &Context, regression_name: &str, status: &Status, rest: &str) {
+    say!(
+        "Regression {}: {}{}",
+        regression_name,
+        style::status(ctx.status_style, status.label()),
+        rest
+    );
+}
+
+/// Maximum number of diff lines shown by the interactive reset prompt.
+const PROMPT_DIFF_LINES: usize = 20;
+
+/// Asks whether the expected output of a regression should be reset.
+///
+/// Shows a short side-by-side diff between the current expected file and the
+/// newly generated output, then prompts for y(es), n(o), a(ll) or q(uit).
+/// Answering 'all' or 'quit' updates the prompt state for the following regressions.
+///
+/// # Returns
+///
+/// Returns true if the reset should be performed.
+fn confirm_reset(
+    prompt: &mut ResetPrompt,
+    regression_name: &str,
+    result: &str,
+    targetdatafull: &str,
+) -> Result<bool, io::Error> {
+    match *prompt {
+        ResetPrompt::Always => return Ok(true),
+        ResetPrompt::Quit => return Ok(false),
+        ResetPrompt::Ask => {}
+    }
+
+    if !std::path::Path::new(targetdatafull).exists() {
+        say!(
+            "Regression {}: no expected output yet, {} will be created",
+            regression_name,
+            targetdatafull
+        );
+        return ask_reset(prompt, regression_name);
+    }
+
+    let diff = show_diff(targetdatafull, result)?;
+    let diff_text = diff.text;
+    say!(
+        "Regression {}: expected (left) vs generated (right){}",
+        regression_name,
+        diff.note
+    );
+    if diff_text.is_empty() {
+        say!("  no differences");
+    } else {
+        let lines: Vec<&str> = diff_text.lines().collect();
+        for line in lines.iter().take(PROMPT_DIFF_LINES) {
+            say!("  {}", line);
+        }
+        if lines.len() > PROMPT_DIFF_LINES {
+            say!("  ... {} more lines", lines.len() - PROMPT_DIFF_LINES);
+        }
+    }
+
+    ask_reset(prompt, regression_name)
+}
+
+/// Reads the answer to the interactive reset question, updating the prompt state.
+fn ask_reset(prompt: &mut ResetPrompt, regression_name: &str) -> Result<bool, io::Error> {
+    // The status and the diff collected so far come before the question
+    output::flush();
+    let stdin = io::stdin();
+    loop {
+        print!("Reset {}? [y]es/[n]o/[a]ll/[q]uit: ", regression_name);
+        io::stdout().flush()?;
+        // End of input and interrupts are treated as a quit
+        let Some(answer) = interrupt::read_answer(&mut stdin.lock())? else {
+            *prompt = ResetPrompt::Quit;
+            return Ok(false);
+        };
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            "a" | "all" => {
+                *prompt = ResetPrompt::Always;
+                return Ok(true);
+            }
+            "q" | "quit" => {
+                *prompt = ResetPrompt::Quit;
+                return Ok(false);
+            }
+            _ => println!("Please answer y, n, a or q"),
+        }
+    }
+}
+
+/// Compares a generated output with the expected one.
+///
+/// This is the single comparison path shared by run, diff and the reset preview,
+/// the returned statistics count the lines added and removed going from the
+/// expected to the generated output.
+fn compare_outputs(result_data: &str, target_data: &str) -> diff::DiffStat {
+    diff::diff_stat(target_data, result_data)
+}
+
+/// Returns the compared sections of the expected and the generated output, None if the whole
+/// outputs are compared.
+///
+/// # Errors
+///
+/// Returns an error naming the marker missing and the output missing it.
+fn compared_sections(
+    config: &config::RegressionConfig,
+    target_data: &str,
+    result_data: &str,
+) -> Result<Option<(String, String)>, String> {
+    let Some(section) = &config.compare_between else {
+        return Ok(None);
+    };
+    let expected = section
+        .extract(target_data)
+        .map_err(|missing| format!("{} in the expected output", missing))?;
+    let output = section
+        .extract(result_data)
+        .map_err(|missing| format!("{} in the output", missing))?;
+    Ok(Some((expected, output)))
+}
+
+/// Returns the expected and the generated output as they are compared: their sections, or
+/// the whole outputs if a marker is missing, normalized, and the missing marker.
+fn compared_outputs(
+    config: &config::RegressionConfig,
+    target_data: &str,
+    result_data: &str,
+) -> (String, String, Option<String>) {
+    let (expected, output, missing_marker) =
+        match compared_sections(config, target_data, result_data) {
+            Ok(Some((expected, output))) => (expected, output, None),
+            Ok(None) => (target_data.to_string(), result_data.to_string(), None),
+            Err(missing) => (
+                target_data.to_string(),
+                result_data.to_string(),
+                Some(missing),
+            ),
+        };
+    (
+        config.normalize(&expected),
+        config.normalize(&output),
+        missing_marker,
+    )
+}
+
+/// Describes how the compared outputs differ from the files, empty if they are the files.
+fn normalizations(config: &config::RegressionConfig, sections_found: bool) -> Vec<String> {
+    let mut normalizations = Vec::new();
+    if config.compare_between.is_some() && sections_found {
+        normalizations.push("between the markers".to_string());
+    }
+    match config.mask.len() {
+        0 => {}
+        1 => normalizations.push("1 mask applied".to_string()),
+        masks => normalizations.push(format!("{} masks applied", masks)),
+    }
+    match config.ignore.len() {
+        0 => {}
+        1 => normalizations.push("1 ignore pattern applied".to_string()),
+        patterns => normalizations.push(format!("{} ignore patterns applied", patterns)),
+    }
+    if let Some(precision) = config.normalize_numbers {
+        normalizations.push(format!("numbers with {} decimals", precision));
+    }
+    normalizations
+}
+
+/// Chooses the candidate expected output a generated output is compared with, or overwrites.
+///
+/// The first candidate identical to the output is chosen, or else the one with the fewest
+/// differing lines. Reset and update only overwrite a candidate that is identical, missing
+/// or named with `--reset-candidate`, since the closest one is not necessarily the one to
+/// replace.
+///
+/// # Errors
+///
+/// Returns an error if a candidate cannot be read, or if reset or update cannot tell which
+/// candidate to overwrite.
+fn choose_candidate<'a>(
+    ctx: &Context,
+    config: &config::RegressionConfig,
+    action: &str,
+    regression_dir: &str,
+    candidates: &[&'a str],
+    result_data: &str,
+) -> io::Result<&'a str> {
+    let writes = matches!(action, "reset" | "update" | "preview");
+    if let [candidate] = candidates {
+        return Ok(candidate);
+    }
+    if let (true, Some(chosen)) = (writes, &ctx.reset_candidate) {
+        if let Some(candidate) = candidates.iter().find(|candidate| **candidate == chosen) {
+            return Ok(candidate);
+        }
+    }
+    let mut closest: Option<(&'a str, usize)> = None;
+    for candidate in candidates {
+        let path = std::path::Path::new(regression_dir).join(candidate);
+        if !path.exists() {
+            continue;
+        }
+        let target_data = gzip::read_to_string(&path)?;
+        let (expected, output, _) = compared_outputs(config, &target_data, result_data);
+        let comparison = compare_outputs(&output, &expected);
+        if comparison.is_identical() {
+            return Ok(candidate);
+        }
+        let differences = comparison.added + comparison.removed;
+        if closest.is_none_or(|(_, fewest)| differences < fewest) {
+            closest = Some((candidate, differences));
+        }
+    }
+    match closest {
+        Some(_) if writes => Err(io::Error::other(format!(
+            "the output matches none of the candidates {}, choose the one to overwrite with --reset-candidate",
+            candidates.join(", ")
+        ))),
+        Some((candidate, _)) => Ok(candidate),
+        // None exists yet, the first one is the one to create
+        None => Ok(candidates[0]),
+    }
+}
+
+/// Returns the percentage of identical lines between the expected and the generated output.
+fn similarity_of(comparison: &diff::DiffStat, target_data: &str, result_data: &str) -> f64 {
+    comparison.similarity(
+        diff::split_lines(target_data).len(),
+        diff::split_lines(result_data).len(),
+    )
+}
+
+/// Describes a measured similarity against the required one.
+fn similarity_note(similarity: f64, min: f64) -> String {
+    format!("similarity {:.2}%, at least {}% required", similarity, min)
+}
+
+/// Maximum number of lines not matching their patterns printed by run.
+const MISMATCH_LINES: usize = 5;
+
+/// Describes how an output fails to match the lines of a pattern baseline.
+fn mismatch_note(matched: &pattern::LineMatch) -> String {
+    let mismatches = matched.mismatches.len();
+    if matched.pattern_lines == matched.output_lines {
+        return format!("{} lines not matching their patterns", mismatches);
+    }
+    format!(
+        "{} output lines for {} patterns, {} lines not matching",
+        matched.output_lines, matched.pattern_lines, mismatches
+    )
+}
+
+/// Describes the first expected line missing from an output, and where the search stopped.
+fn missing_note(target_data: &str, (line, stopped): (usize, usize)) -> String {
+    let expected = target_data.lines().nth(line - 1).unwrap_or_default();
+    if stopped == 0 {
+        return format!("expected line {} {:?} not found", line, expected);
+    }
+    format!(
+        "expected line {} {:?} not found after output line {}",
+        line, expected, stopped
+    )
+}
+
+/// Prints the first lines of an output that do not match their patterns.
+fn print_mismatches(matched: &pattern::LineMatch) {
+    for mismatch in matched.mismatches.iter().take(MISMATCH_LINES) {
+        say!(
+            "  line {}: {:?} does not match the pattern {:?}",
+            mismatch.line,
+            mismatch.actual,
+            mismatch.pattern
+        );
+    }
+    if matched.mismatches.len() > MISMATCH_LINES {
+        say!(
+            "  ... and {} more lines",
+            matched.mismatches.len() - MISMATCH_LINES
+        );
+    }
+}
+
+/// Writes a new expected output, creating the parent directories when missing, compressed
+/// if its name ends in `.gz`.
+fn write_baseline(result: &str, targetdatafull: &str) -> Result<(), io::Error> {
+    if let Some(parent) = std::path::Path::new(targetdatafull).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    gzip::copy(result, targetdatafull)
+}
+
+/// Writes a new expected output, then compresses it if `--compress` or `store_compressed`
+/// asks to store it compressed.
+///
+/// # Returns
+///
+/// The note naming the compressed expected output, empty if it is not compressed.
+fn store_baseline(
+    ctx: &Context,
+    config: &config::RegressionConfig,
+    result: &str,
+    targetdata: &str,
+    targetdatafull: &str,
+) -> io::Result<String> {
+    let compress = (ctx.compress || config.store_compressed) && !gzip::is_compressed(targetdata);
+    let stored = if compress {
+        format!("{}{}", targetdatafull, gzip::EXTENSION)
+    } else {
+        targetdatafull.to_string()
+    };
+    check_baseline_size(ctx, &config.name, result, &stored)?;
+    write_baseline(result, targetdatafull)?;
+    if !compress {
+        record_provenance(ctx, &config.name, &stored);
+        return Ok(String::new());
+    }
+    let compressed = compress_baseline(ctx, config, targetdata)?;
+    record_provenance(ctx, &config.name, &stored);
+    Ok(format!(" (compressed to {})", compressed))
+}
+
+/// Writes the provenance sidecar of an expected output just written at `stored`.
+///
+/// The expected output is in place all the same, a sidecar that cannot be written only
+/// prints a warning.
+fn record_provenance(ctx: &Context, regression_name: &str, stored: &str) {
+    let written = ctx.provenance.write(
+        &ctx.source,
+        &ctx.target,
+        &ctx.run_id,
+        std::path::Path::new(stored),
+    );
+    if let Err(err) = written {
+        say!(
+            "\x1b[0;33mWarning: the provenance of the expected output {} of {} cannot be written: {}\x1b[0m",
+            stored,
+            regression_name,
+            err
+        );
+    }
+}
+
+/// Checks the size an expected output would have once stored at `stored`, unless the data
+/// repository routes it to git LFS: above `--baseline-warn-size` a warning is printed.
+///
+/// # Errors
+///
+/// Returns an error if the size is above `--baseline-max-size` without `--force`.
+fn check_baseline_size(
+    ctx: &Context,
     regression_name: &str,
-    tags: &[String],
-    debug: bool,
-) -> Result<(), io::Error> {
-    if debug {
-        println!("Run regressions matching: \"{}\"", regression_name);
-        println!("Filtering by tags: {:?}", tags);
+    result: &str,
+    stored: &str,
+) -> io::Result<()> {
+    if lfs::is_tracked(
+        std::path::Path::new(&ctx.target),
+        std::path::Path::new(stored),
+    ) {
+        return Ok(());
+    }
+    let size = if gzip::is_compressed(stored) {
+        gzip::compressed_size(result)?
+    } else {
+        fs::metadata(result)?.len()
+    };
+    if size > ctx.baseline_max_size && !ctx.force {
+        return Err(io::Error::other(format!(
+            "the expected output {} would be {}, above the limit of {}, route it to git LFS or use --force to write it anyway",
+            stored,
+            human_size(size),
+            human_size(ctx.baseline_max_size)
+        )));
+    }
+    if size > ctx.baseline_warn_size {
+        say!(
+            "\x1b[0;33mWarning: the expected output {} of {} is {}, above {}: store it compressed with reset --compress, compare only a section with compare_between, or route it to git LFS in .gitattributes\x1b[0m",
+            stored,
+            regression_name,
+            human_size(size),
+            human_size(ctx.baseline_warn_size)
+        );
     }
+    Ok(())
+}
 
-    let entries = fs::read_dir(target)?;
-    for entry in entries {
-        let entry = entry?;
-        let filename = entry.file_name();
-        // Skip .git directory
-        if filename.to_str().unwrap() == ".git" {
+/// External diff tools tried in order, with their arguments, before the built-in diff.
+const DIFF_TOOLS: &[(&str, &[&str])] =
+    &[("sdiff", &["--suppress-common-lines"]), ("diff", &["-u"])];
+
+/// Differences between two files, as shown to the user.
+struct ShownDiff {
+    /// Note naming the tool that produced the text, empty for the preferred `sdiff`
+    note: String,
+    /// Differing lines, empty if the files are identical
+    text: String,
+}
+
+/// Shows the differences between two files with the first available diff tool.
+///
+/// `sdiff` is preferred, then `diff -u`; on systems having neither the built-in
+/// unified diff is used instead.
+///
+/// # Errors
+///
+/// Returns an error if the available tool cannot be executed or the files cannot be read.
+fn show_diff(left: &str, right: &str) -> Result<ShownDiff, io::Error> {
+    // The diff tools would compare the compressed bytes
+    if gzip::is_compressed(left) || gzip::is_compressed(right) {
+        let old = gzip::read_to_string(left)?;
+        let new = gzip::read_to_string(right)?;
+        return Ok(ShownDiff {
+            note: " (decompressed, using the built-in diff)".to_string(),
+            text: diff::unified(&old, &new, left, right),
+        });
+    }
+    for (tool, args) in DIFF_TOOLS {
+        if toolchain::find_on_path(tool).is_none() {
             continue;
         }
-        // Filter regressions by name pattern and tag
-        if filename.to_str().unwrap().contains(regression_name) {
-            if check_regression_tags(target, filename.to_str().unwrap(), tags, debug) {
-                if let Err(err) =
-                    execute_regression(source, target, "run", filename.to_str().unwrap(), debug)
-                {
-                    println!(
-                        "Error executing regression {}: {}",
-                        filename.to_str().unwrap(),
-                        err
-                    );
-                }
-            }
+        let output = Command::new(tool)
+            .args(*args)
+            .arg(left)
+            .arg(right)
+            .output()?;
+        return Ok(ShownDiff {
+            note: if *tool == "sdiff" {
+                String::new()
+            } else {
+                format!(" (using {} {}, sdiff not found)", tool, args.join(" "))
+            },
+            text: String::from_utf8_lossy(&output.stdout).to_string(),
+        });
+    }
+    let old = fs::read_to_string(left)?;
+    let new = fs::read_to_string(right)?;
+    Ok(ShownDiff {
+        note: " (using the built-in diff, install sdiff or diff for other formats)".to_string(),
+        text: diff::unified(&old, &new, left, right),
+    })
+}
+
+/// Shows the differences between two files with a user chosen diff tool.
+///
+/// The tool is run through the shell with the two paths appended. Like `diff` and
+/// `sdiff`, diff tools exit with a non-zero status when the files differ, so the
+/// status is not an error; only a tool that cannot be found is.
+///
+/// # Errors
+///
+/// Returns an error naming the tool if it cannot be found or executed.
+fn run_difftool(difftool: &str, left: &str, right: &str) -> Result<ShownDiff, io::Error> {
+    let program = difftool.split_whitespace().next().unwrap_or("");
+    let found = if program.contains('/') {
+        std::path::Path::new(program).is_file()
+    } else {
+        toolchain::find_on_path(program).is_some()
+    };
+    if !found {
+        return Err(io::Error::other(format!("diff tool {} not found", program)));
+    }
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", difftool))
+        .arg("sh")
+        .arg(left)
+        .arg(right)
+        .output()?;
+    // The shell exits with 127 when the command cannot be found
+    if output.status.code() == Some(127) {
+        return Err(io::Error::other(format!(
+            "diff tool {} could not be executed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(ShownDiff {
+        note: format!(" (using {})", difftool),
+        text: String::from_utf8_lossy(&output.stdout).to_string(),
+    })
+}
+
+/// Writes the full unified diff of a regression to the patch directory, if one was given.
+///
+/// # Returns
+///
+/// The path of the written patch file.
+fn write_patch(ctx: &Context, regression_name: &str, unified: &str) -> Option<std::path::PathBuf> {
+    let dir = ctx.patch_dir.as_ref()?;
+    let path = dir.join(format!("{}.patch", regression_name));
+    let written = fs::create_dir_all(dir).and_then(|_| fs::write(&path, unified));
+    match written {
+        Ok(()) => Some(path),
+        Err(err) => {
+            say!(
+                "Warning: writing the patch of regression {} failed: {}",
+                regression_name,
+                err
+            );
+            None
         }
     }
+}
 
-    Ok(())
+/// Prints a diff, truncated to the maximum number of lines of the context.
+///
+/// The truncation note points to the patch file when one has been written.
+fn print_capped_diff(ctx: &Context, text: &str, patch: Option<&std::path::Path>) {
+    let lines: Vec<&str> = text.lines().collect();
+    let shown = ctx
+        .max_printed_diff_lines
+        .unwrap_or(lines.len())
+        .min(lines.len());
+    for line in &lines[..shown] {
+        say!("{}", line);
+    }
+    if shown < lines.len() {
+        let hint = match patch {
+            Some(path) => format!("rerun with --full-diff or see {}", path.display()),
+            None => "rerun with --full-diff or use --output-dir to save it".to_string(),
+        };
+        say!(
+            "\x1b[0;33m... {} more lines, {}\x1b[0m",
+            group_thousands(lines.len() - shown),
+            hint
+        );
+    }
 }
 
-/// Resets regression tests by updating expected outputs with current results.
+/// Formats a number with comma separated thousands, e.g. `38,412`.
+fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Shows differences between current and expected regression outputs.
 ///
-/// This command is useful when the expected output needs to be updated,
-/// such as after intentional changes to the tool or examples.
+/// Uses `sdiff` to display side-by-side comparison of files, falling back to
+/// `diff -u` and to the built-in diff when it is not available.
 ///
 /// # Arguments
 ///
-/// * `source` - Path to the examples directory
-/// * `target` - Path to the regression data directory
-/// * `regression_name` - Filter pattern for regression names (empty string matches all)
-/// * `tags` - List of tags to filter by (tests must match at least one tag)
-/// * `debug` - Enable debug output
+/// * `ctx` - Settings shared by all the regressions
+/// * `regression_names` - Filter patterns for regression names, a regression matching any is selected (none selects all)
+///
+/// # Returns
+///
+/// The results of the regressions, those that could not be diffed included.
 ///
 /// # Errors
 ///
-/// Returns an error if the target directory cannot be read or if
-/// resetting a regression fails.
+/// Returns an error if the target directory cannot be read.
 ///
 /// # Output
 ///
-/// For each reset test:
-/// - "Regression `<name>`: reset" (in yellow)
-fn reset_regressions(
-    source: &str,
-    target: &str,
-    regression_name: &str,
-    tags: &[String],
-    debug: bool,
-) -> Result<(), io::Error> {
-    if debug {
-        println!("Reset regressions matching: \"{}\"", regression_name);
-        println!("Filtering by tags: {:?}", tags);
+/// For each test:
+/// - "Regression `<name>`: no differences" (in green) if outputs match
+/// - "Regression `<name>`: differences found" (in red) followed by diff output,
+///   unless in quiet mode
+fn diff_regressions(
+    ctx: &Context,
+    regression_names: &[String],
+) -> Result<Vec<RunResult>, io::Error> {
+    if ctx.debug {
+        println!("Diff regressions matching: {:?}", regression_names);
+        println!("Filtering the regressions {}", ctx.tagged());
     }
 
-    let entries = fs::read_dir(target)?;
-    for entry in entries {
-        let entry = entry?;
-        let filename = entry.file_name();
-        // Skip .git directory
-        if filename.to_str().unwrap() == ".git" {
+    let mut presenter = Presenter::default();
+
+    for config in select_regressions(ctx, regression_names)? {
+        // Stop scheduling new regressions after an interrupt
+        if interrupt::interrupted().is_some() {
+            break;
+        }
+        presenter.show(execute_regression(
+            ctx,
+            "diff",
+            &config,
+            &mut ResetPrompt::Always,
+        ));
+    }
+
+    Ok(presenter.results)
+}
+
+/// Executes a single regression test action, recording its outcome.
+///
+/// See [`perform_regression`] for the details; the result of each regression is
+/// recorded in the context, for the reports and for the summary printed after an interrupt.
+///
+/// The output of the regression, including its error, is collected while it is executed
+/// and returned with its status, to be printed in one piece by the caller, see [`present`].
+/// The error is also counted in the context, the callers only have to look at the status.
+fn execute_regression(
+    ctx: &Context,
+    action: &str,
+    config: &config::RegressionConfig,
+    prompt: &mut ResetPrompt,
+) -> RunResult {
+    // The keys the action overrides apply from here on, retries and timeout included
+    let config = config.for_action(action);
+    let mut record = results::RegressionResult {
+        name: config.name.clone(),
+        tags: config.tags.clone(),
+        command: config.regcommand.clone(),
+        ..Default::default()
+    };
+    let start = std::time::Instant::now();
+    redact::register(
+        config
+            .env
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    );
+    output::capture();
+    let mut performed = perform_regression(ctx, action, config, prompt, &mut record);
+    // Only runs are retried, the other actions modify or show the baselines
+    if action == "run" {
+        for attempt in 1..=config.retries {
+            if !matches!(performed, Ok(Status::Failed { .. }) | Err(_))
+                || interrupt::interrupted().is_some()
+            {
+                break;
+            }
+            say!(
+                "Retrying regression {} ({} of {})",
+                config.name,
+                attempt,
+                config.retries
+            );
+            record = results::RegressionResult {
+                name: config.name.clone(),
+                tags: config.tags.clone(),
+                command: config.regcommand.clone(),
+                ..Default::default()
+            };
+            performed = perform_regression(ctx, action, config, prompt, &mut record);
+        }
+    }
+    if let Some(spread) = results::Spread::of(&record.bench) {
+        say!("  bench: {}", spread);
+    }
+    if let Some(changes) = &record.unreliable {
+        say!(
+            "  \x1b[0;33munreliable\x1b[0m (source tree changed during execution): {}",
+            changes
+        );
+    }
+    let status = performed.unwrap_or_else(|err| Status::Errored {
+        kind: err.kind(),
+        detail: err.to_string(),
+    });
+    let mut result = RunResult {
+        name: config.name.clone(),
+        action: action.to_string(),
+        status,
+        duration: start.elapsed(),
+        output: String::new(),
+        shown: 0,
+    };
+    if let Some(line) = result.error_line() {
+        ctx.report_error(line);
+    }
+    (result.output, result.shown) = output::finish();
+    // After a graceful SIGTERM the regression has completed and is recorded
+    if !interrupt::aborted() {
+        record.duration = result.duration;
+        record.output = result.output.clone();
+        record.status = result.status.label();
+        if let Status::Errored { detail, .. } = &result.status {
+            record.error = detail.clone();
+        }
+        // The reports and the history are written from the result
+        for text in [
+            &mut record.command,
+            &mut record.error,
+            &mut record.diff,
+            &mut record.output_tail,
+        ] {
+            *text = redact::text(text);
+        }
+        // The warnings and the hooks follow the output of the regression, not in its record
+        output::capture();
+        // Kept with the logs, for the export of the run
+        let mut diff_file = None;
+        if !record.diff.is_empty() {
+            match logs::write_diff(&ctx.log_dir, &config.name, &record.diff) {
+                Ok(()) => diff_file = Some(logs::log_file(&ctx.log_dir, &config.name, "diff")),
+                Err(err) => say!(
+                    "Warning: writing the differences of regression {} failed: {}",
+                    config.name,
+                    err
+                ),
+            }
+        }
+        if matches!(action, "run" | "bootstrap") && results::is_failure(record.status) {
+            run_failure_hooks(ctx, config, &record, diff_file.as_deref());
+        }
+        result.output.push_str(&output::finish().0);
+        ctx.results().push(record);
+    }
+    result
+}
+
+/// Time after which a failure hook is terminated.
+const HOOK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(600);
+
+/// Runs the failure hooks of a regression that failed or errored, its `on_fail` then
+/// `--on-fail`, in its example directory.
+///
+/// The hooks find the regression in the variables of their environment: `BMREG_NAME`,
+/// `BMREG_STATUS`, `BMREG_DIFF_FILE` and `BMREG_LOG_FILE` (the stdout of the command, its
+/// stderr is next to it), empty when there are no differences or logs. A hook failing is
+/// a warning, the status of the regression stands.
+fn run_failure_hooks(
+    ctx: &Context,
+    config: &config::RegressionConfig,
+    record: &results::RegressionResult,
+    diff_file: Option<&std::path::Path>,
+) {
+    // The on_fail of an untrusted data repository is a command like the others
+    let hooks: Vec<(&str, &str)> = config
+        .on_fail
+        .as_deref()
+        .map(|hook| ("on_fail", hook))
+        .into_iter()
+        .chain(ctx.on_fail.as_deref().map(|hook| ("--on-fail", hook)))
+        .collect();
+    if hooks.is_empty() {
+        return;
+    }
+    let dir = match ctx
+        .examples_dir(config)
+        .and_then(|source| paths::regbase_dir(source, &config.regbase))
+    {
+        Ok(dir) if dir.is_dir() => dir,
+        Ok(dir) => {
+            say!(
+                "Warning: the failure hooks of regression {} not run, its example directory {} does not exist",
+                config.name,
+                dir.display()
+            );
+            return;
+        }
+        Err(err) => {
+            say!(
+                "Warning: the failure hooks of regression {} not run: {}",
+                config.name,
+                err
+            );
+            return;
+        }
+    };
+    // Absolute, as the hooks run in the example directory
+    let absolute = |path: &std::path::Path| fs::canonicalize(path).unwrap_or(path.to_path_buf());
+    let log_file = if record.log.is_empty() {
+        std::path::PathBuf::new()
+    } else {
+        absolute(std::path::Path::new(&format!("{}.out", record.log)))
+    };
+    let diff_file = diff_file.map(absolute).unwrap_or_default();
+    for (which, hook) in hooks {
+        let allowed = which != "on_fail"
+            || ctx
+                .command_allowlist
+                .as_ref()
+                .is_none_or(|allowlist| allowlist.allows(hook, true));
+        if !allowed {
+            say!(
+                "Warning: the on_fail hook of regression {} not run, it is not allowlisted: {}",
+                config.name,
+                hook
+            );
             continue;
         }
-        // Filter regressions by name pattern and tag
-        if filename.to_str().unwrap().contains(regression_name) {
-            if check_regression_tags(target, filename.to_str().unwrap(), tags, debug) {
-                if let Err(err) =
-                    execute_regression(source, target, "reset", filename.to_str().unwrap(), debug)
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(hook)
+            .current_dir(&dir)
+            .envs(
+                trusted_environment(ctx, config)
+                    .env
+                    .iter()
+                    .map(|(name, value)| (name, value)),
+            )
+            .env("BMREG_NAME", &config.name)
+            .env("BMREG_STATUS", record.status)
+            .env("BMREG_DIFF_FILE", &diff_file)
+            .env("BMREG_LOG_FILE", &log_file);
+        let completed = ctx.runner.run(
+            command,
+            &runner::RunOptions {
+                stream_prefix: None,
+                heartbeat: None,
+                timeout: Some(HOOK_TIMEOUT),
+            },
+        );
+        let failure = match completed {
+            Err(err) => err.to_string(),
+            Ok(completed) if completed.timed_out => {
+                format!("timed out after {}", runner::format_elapsed(HOOK_TIMEOUT))
+            }
+            Ok(completed) if !completed.output.status.success() => format!(
+                "{}{}",
+                completed.output.status,
+                match results::tail_lines(&String::from_utf8_lossy(&completed.output.stderr), 5)
+                    .trim_end()
                 {
-                    println!(
-                        "Error executing regression {}: {}",
-                        filename.to_str().unwrap(),
-                        err
-                    );
+                    "" => String::new(),
+                    stderr => format!(":\n{}", stderr),
                 }
+            ),
+            Ok(_) => {
+                say!(
+                    "Ran the {} hook of regression {}: {}",
+                    which,
+                    config.name,
+                    hook
+                );
+                continue;
+            }
+        };
+        say!(
+            "Warning: the {} hook of regression {} failed: {}",
+            which,
+            config.name,
+            redact::text(&failure)
+        );
+    }
+}
+
+/// Collects the artifacts of a regression, printing a note about the patterns matching no file.
+///
+/// # Returns
+///
+/// The text to append to the status line or error of the regression, mentioning
+/// where the artifacts have been saved, empty if nothing has been collected.
+fn save_artifacts(
+    ctx: &Context,
+    regression_name: &str,
+    examplesource: &str,
+    patterns: &[String],
+) -> String {
+    if patterns.is_empty() {
+        return String::new();
+    }
+    let dest = ctx.artifacts_dir.join(regression_name);
+    match artifacts::collect(patterns, std::path::Path::new(examplesource), &dest) {
+        Ok(collected) => {
+            for pattern in &collected.unmatched {
+                say!(
+                    "Note: artifact {} of regression {} not found",
+                    pattern,
+                    regression_name
+                );
             }
+            if collected.copied == 0 {
+                String::new()
+            } else {
+                format!(
+                    " ({} artifacts saved to {})",
+                    collected.copied,
+                    collected.dir.display()
+                )
+            }
+        }
+        Err(err) => {
+            say!(
+                "Warning: collecting the artifacts of regression {} failed: {}",
+                regression_name,
+                err
+            );
+            String::new()
         }
     }
+}
 
-    Ok(())
+/// What the command of a regression needs, checked before it runs.
+struct Prepared<'a> {
+    /// Candidate expected outputs of the selected baseline
+    candidates: Vec<&'a str>,
+    /// Directory of the example
+    examplesource: String,
+    /// Directory the command runs in: the example directory, or its workdir
+    command_dir: std::path::PathBuf,
 }
 
-/// Shows differences between current and expected regression outputs.
+/// Resolves the directories of a regression and checks them, as before running its command.
 ///
-/// Uses `sdiff` to display side-by-side comparison of files.
+/// # Errors
 ///
-/// # Arguments
+/// Returns an error if the baseline has no expected output, the example directory does not
+/// exist, or a path leads outside its directory.
+fn prepare_regression<'a>(
+    ctx: &Context,
+    config: &'a config::RegressionConfig,
+) -> Result<Prepared<'a>, io::Error> {
+    // A missing variant is an error of this regression, found before running its command
+    let candidates = config.targetdata_for(&ctx.baseline)?;
+    for file in config.files.iter().skip(1) {
+        config.for_file(file).targetdata_for(&ctx.baseline)?;
+    }
+    if let Some(candidate) = &ctx.reset_candidate {
+        if candidates.len() > 1 && !candidates.contains(&candidate.as_str()) {
+            return Err(io::Error::other(format!(
+                "{} is not a candidate targetdata, the candidates are {}",
+                candidate,
+                candidates.join(", ")
+            )));
+        }
+    }
+
+    // Verify example source directory exists
+    let examples = ctx.examples_dir(config)?;
+    let examplesource = paths::regbase_dir(examples, &config.regbase)?
+        .to_string_lossy()
+        .to_string();
+
+    if ctx.debug {
+        output::trace(&config.name, &format!("examplesource: {}", examplesource));
+    }
+
+    if !std::path::Path::new(&examplesource).exists() {
+        return Err(setup::Missing::example(
+            &config.regbase,
+            std::path::Path::new(&examplesource),
+            std::path::Path::new(examples),
+        )
+        .into());
+    }
+
+    // Refuse configurations reading or writing outside their directories
+    if !ctx.allow_external_paths {
+        paths::check_within(&examplesource, &config.sourcedata, "sourcedata")?;
+        for targetdata in &candidates {
+            paths::check_within(
+                &format!("{}/{}", ctx.target, config.name),
+                targetdata,
+                "targetdata",
+            )?;
+        }
+        for file in config.files.iter().skip(1) {
+            paths::check_within(&examplesource, &file.sourcedata, "sourcedata")?;
+            for (_, targetdata) in &file.targetdata {
+                paths::check_within(
+                    &format!("{}/{}", ctx.target, config.name),
+                    targetdata,
+                    "targetdata",
+                )?;
+            }
+        }
+        if let Some(workdir) = &config.workdir {
+            paths::check_within(&examplesource, workdir, "workdir")?;
+        }
+    }
+    let command_dir = command_dir(&examplesource, config)?;
+    Ok(Prepared {
+        candidates,
+        examplesource,
+        command_dir,
+    })
+}
+
+/// Returns the configuration of a regression without its `env`, `clean_env` and
+/// `env_passthrough` under `--command-allowlist`: through `PATH`, `LD_PRELOAD` or
+/// `MAKEFLAGS`, the environment of an untrusted configuration runs any command.
+fn trusted_environment<'a>(
+    ctx: &Context,
+    config: &'a config::RegressionConfig,
+) -> std::borrow::Cow<'a, config::RegressionConfig> {
+    if ctx.command_allowlist.is_none() {
+        return std::borrow::Cow::Borrowed(config);
+    }
+    std::borrow::Cow::Owned(config::RegressionConfig {
+        env: Vec::new(),
+        clean_env: false,
+        env_passthrough: Vec::new(),
+        ..config.clone()
+    })
+}
+
+/// Sets the environment of the command of a regression: its `env`, over a clean environment
+/// with `--clean-env` or `clean_env`, or else over the environment of bmregression.
+fn set_environment(ctx: &Context, config: &config::RegressionConfig, command: &mut Command) {
+    let config = &*trusted_environment(ctx, config);
+    if ctx.clean_env || config.clean_env {
+        let env = clean_environment(config);
+        if ctx.debug {
+            output::trace(&config.name, "environment:");
+            for (name, value) in &env {
+                output::trace(
+                    &config.name,
+                    &format!(
+                        "  {}={}",
+                        name,
+                        redact::value(name, &value.to_string_lossy())
+                    ),
+                );
+            }
+        }
+        command.env_clear().envs(env);
+    } else {
+        command.envs(config.env.iter().map(|(name, value)| (name, value)));
+    }
+}
+
+/// Opens an interactive shell in the directory where the command of a regression runs, with
+/// the environment of the command, and waits for it to exit.
 ///
-/// * `source` - Path to the examples directory
-/// * `target` - Path to the regression data directory
-/// * `regression_name` - Filter pattern for regression names (empty string matches all)
-/// * `tags` - List of tags to filter by (tests must match at least one tag)
-/// * `debug` - Enable debug output
+/// The shell is `$SHELL`, or `sh`. The command is printed, and given to the shell in
+/// `BMREG_COMMAND` to be rerun, while `BMREG_NAME` and the prompt name the regression.
+fn open_shell(ctx: &Context, regression_name: &str) -> Result<(), io::Error> {
+    let config = config::load(&ctx.target, regression_name)?;
+    let Prepared { command_dir, .. } = prepare_regression(ctx, &config)?;
+    let shell = std::env::var_os("SHELL")
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| "sh".into());
+    let mut command = Command::new(&shell);
+    command.current_dir(&command_dir);
+    set_environment(ctx, &config, &mut command);
+    command
+        .env("PS1", format!("(bmregression {}) $ ", config.name))
+        .env("BMREG_NAME", &config.name)
+        .env("BMREG_COMMAND", &config.regcommand);
+
+    println!(
+        "Shell for regression \x1b[0;32m{}\x1b[0m in {}",
+        config.name,
+        command_dir.display()
+    );
+    println!("  regcommand: {}", config.regcommand);
+    println!("  rerun it with sh -c \"$BMREG_COMMAND\", exit the shell to return");
+    let status = interrupt::run_interactive(&mut command).map_err(|err| {
+        io::Error::other(format!(
+            "starting {} failed: {}",
+            shell.to_string_lossy(),
+            err
+        ))
+    })?;
+    if ctx.debug {
+        println!("Shell exited: {}", runner::describe_status(&status));
+    }
+    Ok(())
+}
+
+/// Appends a note to the notes of a regression, or with `list` prints them all.
 ///
 /// # Errors
 ///
-/// Returns an error if the target directory cannot be read or if
-/// diffing a regression fails.
-///
-/// # Output
-///
-/// For each test:
-/// - "Regression `<name>`: no differences" (in green) if outputs match
-/// - "Regression `<name>`: differences found" (in red) followed by diff output
-fn diff_regressions(
-    source: &str,
-    target: &str,
+/// Returns an error if there is no such regression or its notes file cannot be read or
+/// written.
+fn take_note(
+    ctx: &Context,
     regression_name: &str,
-    tags: &[String],
-    debug: bool,
-) -> Result<(), io::Error> {
-    if debug {
-        println!("Diff regressions matching: \"{}\"", regression_name);
-        println!("Filtering by tags: {:?}", tags);
+    text: Option<&str>,
+    list: bool,
+) -> io::Result<()> {
+    if !scan_regressions(ctx)?
+        .iter()
+        .any(|name| name == regression_name)
+    {
+        return Err(io::Error::other(format!(
+            "no regression named {}",
+            regression_name
+        )));
+    }
+    let dir = std::path::Path::new(&ctx.target).join(regression_name);
+    if list {
+        let notes = notes::load(&dir)?;
+        if notes.is_empty() {
+            println!("No notes for regression {}", regression_name);
+        }
+        for note in notes {
+            println!("\x1b[0;32m{}\x1b[0m", note.written);
+            for line in note.text.lines() {
+                println!("  {}", line);
+            }
+        }
+        return Ok(());
     }
+    let note = notes::append(&dir, text.unwrap_or_default(), timestamp::DateTime::now())?;
+    println!(
+        "Note added to {} ({}), commit it in the data repository to share it",
+        dir.join(notes::FILE).display(),
+        note.written
+    );
+    Ok(())
+}
 
-    let entries = fs::read_dir(target)?;
-    for entry in entries {
-        let entry = entry?;
-        let filename = entry.file_name();
-        // Skip .git directory
-        if filename.to_str().unwrap() == ".git" {
+/// Returns the line showing the most recent note of a regression, if it has notes.
+fn latest_note_line(ctx: &Context, regression_name: &str) -> Option<String> {
+    let note = notes::latest(&std::path::Path::new(&ctx.target).join(regression_name))?;
+    let more = if note.text.lines().count() > 1 {
+        " …"
+    } else {
+        ""
+    };
+    Some(format!(
+        "note ({}): {}{}",
+        note.written,
+        note.text.lines().next().unwrap_or_default(),
+        more
+    ))
+}
+
+/// Prints the provenance of the expected outputs of the selected baseline when describing.
+fn say_provenance(ctx: &Context, config: &config::RegressionConfig) {
+    let Ok(candidates) = config.targetdata_for(&ctx.baseline) else {
+        return;
+    };
+    let dir = std::path::Path::new(&ctx.target).join(&config.name);
+    for file in &candidates {
+        let path = dir.join(file);
+        if !path.exists() {
             continue;
         }
-        // Filter regressions by name pattern and tag
-        if filename.to_str().unwrap().contains(regression_name) {
-            if check_regression_tags(target, filename.to_str().unwrap(), tags, debug) {
-                if let Err(err) =
-                    execute_regression(source, target, "diff", filename.to_str().unwrap(), debug)
-                {
-                    println!(
-                        "Error executing regression {}: {}",
-                        filename.to_str().unwrap(),
-                        err
-                    );
+        let named = if candidates.len() > 1 {
+            format!(" of {}", file)
+        } else {
+            String::new()
+        };
+        match provenance::load(&path) {
+            Ok(Some(provenance)) => say!("  provenance{}: {}", named, provenance.describe()),
+            Ok(None) => say!(
+                "  provenance{}: unknown, no {} sidecar",
+                named,
+                provenance::SUFFIX
+            ),
+            Err(err) => say!("  provenance{}: {}", named, err),
+        }
+    }
+}
+
+/// Lines printed from the start and from the end of an output in debug mode.
+const DEBUG_EXCERPT_LINES: usize = 10;
+
+/// Prints in debug mode the output of a command, only its first and last lines unless
+/// `--debug-full` is given, marking what was left out.
+fn trace_excerpt(ctx: &Context, regression_name: &str, label: &str, content: &[u8], log: &str) {
+    if content.is_empty() {
+        return;
+    }
+    let content = String::from_utf8_lossy(content);
+    let lines: Vec<&str> = content.lines().collect();
+    output::trace(regression_name, &format!("{}:", label));
+    if ctx.debug_full || lines.len() <= 2 * DEBUG_EXCERPT_LINES {
+        for line in &lines {
+            output::trace(regression_name, &format!("  {}", line));
+        }
+        return;
+    }
+    let see = if log.is_empty() {
+        String::new()
+    } else {
+        format!(" or see {}", log)
+    };
+    for line in &lines[..DEBUG_EXCERPT_LINES] {
+        output::trace(regression_name, &format!("  {}", line));
+    }
+    output::trace(
+        regression_name,
+        &format!(
+            "  … {} lines truncated (use --debug-full{})",
+            lines.len() - 2 * DEBUG_EXCERPT_LINES,
+            see
+        ),
+    );
+    for line in &lines[lines.len() - DEBUG_EXCERPT_LINES..] {
+        output::trace(regression_name, &format!("  {}", line));
+    }
+}
+
+/// Variables of the environment of bmregression kept in a clean environment.
+const CLEAN_ENV_VARIABLES: [&str; 3] = ["PATH", "HOME", "TMPDIR"];
+
+/// Returns the environment of a regression command run with a clean environment.
+///
+/// It holds the [`CLEAN_ENV_VARIABLES`] and the `env_passthrough` ones, when set in the
+/// environment of bmregression, then the `env` of the regression, which overrides them.
+fn clean_environment(config: &config::RegressionConfig) -> Vec<(String, std::ffi::OsString)> {
+    let mut env: Vec<(String, std::ffi::OsString)> = Vec::new();
+    let kept = CLEAN_ENV_VARIABLES
+        .iter()
+        .copied()
+        .chain(config.env_passthrough.iter().map(String::as_str));
+    for name in kept {
+        if let Some(value) = std::env::var_os(name) {
+            if !env.iter().any(|(set, _)| set == name) {
+                env.push((name.to_string(), value));
+            }
+        }
+    }
+    for (name, value) in &config.env {
+        env.retain(|(set, _)| set != name);
+        env.push((name.clone(), value.into()));
+    }
+    env
+}
+
+/// Returns the line printed by `--show-commands` before running the command of a regression.
+fn shown_command(config: &config::RegressionConfig, command_dir: &std::path::Path) -> String {
+    let mut line = format!("$ {} (in {}", config.regcommand, command_dir.display());
+    if !config.env.is_empty() {
+        let env: Vec<String> = config
+            .env
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "{}={}",
+                    name,
+                    summary::shell_quote(redact::value(name, value))
+                )
+            })
+            .collect();
+        line.push_str(&format!(", with {}", env.join(" ")));
+    }
+    if config.argv.is_some() {
+        line.push_str(", without a shell");
+    }
+    line.push(')');
+    line
+}
+
+/// Number of command output lines kept in the result of a regression.
+const OUTPUT_TAIL_LINES: usize = 30;
+
+/// Number of stderr lines quoted in the error of a failing regression command.
+const STDERR_TAIL_LINES: usize = 30;
+
+/// Prints the facts that describe checks against the checkouts: whether the example
+/// directory exists, the resolved path of the generated output, and the expected outputs
+/// of the selected baseline with their size and modification time.
+///
+/// Without verification, only the data repository being available, they read
+/// "not checked".
+fn say_checks(ctx: &Context, config: &config::RegressionConfig) {
+    if !ctx.verify {
+        say!("  example directory: not checked");
+        say!("  sourcedata path: not checked");
+        say!("  expected output: not checked");
+        return;
+    }
+    let examples = ctx.examples_dir(config);
+    match examples.and_then(|source| Ok((source, paths::regbase_dir(source, &config.regbase)?))) {
+        Ok((_, dir)) if dir.is_dir() => {
+            say!("  example directory: {} (exists)", dir.display());
+            match paths::resolve(&dir.join(&config.sourcedata)) {
+                Ok(path) => say!("  sourcedata path: {}", path.display()),
+                Err(err) => say!("  sourcedata path: {}", err),
+            }
+        }
+        Ok((source, dir)) => {
+            say!(
+                "  example directory: {} (missing: {})",
+                dir.display(),
+                setup::example_hint(&dir, std::path::Path::new(source))
+            );
+            say!(
+                "  sourcedata path: {}",
+                dir.join(&config.sourcedata).display()
+            );
+        }
+        Err(err) => {
+            say!("  example directory: {}", err);
+            say!("  sourcedata path: {}", err);
+        }
+    }
+    let candidates = match config.targetdata_for(&ctx.baseline) {
+        Ok(candidates) => candidates,
+        Err(err) => {
+            say!("  expected output: {}", err);
+            return;
+        }
+    };
+    let regression_dir = std::path::Path::new(&ctx.target).join(&config.name);
+    for targetdata in candidates {
+        let path = regression_dir.join(targetdata);
+        let path = paths::resolve(&path).unwrap_or(path);
+        match fs::metadata(&path) {
+            Ok(metadata) => {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|age| timestamp::DateTime::from_unix(age.as_secs() as i64));
+                match modified {
+                    Some(modified) => say!(
+                        "  expected output: {}, {}, modified {} {} UTC",
+                        path.display(),
+                        human_size(metadata.len()),
+                        modified.date(),
+                        modified.time()
+                    ),
+                    None => say!(
+                        "  expected output: {}, {}",
+                        path.display(),
+                        human_size(metadata.len())
+                    ),
                 }
             }
+            Err(_) => say!("  expected output: {} (does not exist)", path.display()),
         }
     }
-
-    Ok(())
 }
 
-/// Executes a single regression test action.
+/// Performs a single regression test action.
 ///
 /// This is the core function that handles all regression operations.
-/// It reads the configuration file, executes the regression command,
+/// It executes the regression command of the already loaded configuration
 /// and performs the requested action (describe, run, reset, or diff).
 ///
 /// # Arguments
 ///
-/// * `source` - Path to the examples directory
-/// * `target` - Path to the regression data directory
-/// * `action` - The action to perform: "describe", "run", "reset", or "diff"
-/// * `regression_name` - Name of the specific regression to execute
-/// * `debug` - Enable debug output
+/// * `ctx` - Settings shared by all the regressions
+/// * `action` - The action to perform: "describe", "run", "bootstrap", "reset", "preview",
+///   "update", or "diff"
+/// * `config` - Configuration of the regression to execute, see [`config::load`]
+/// * `prompt` - State of the interactive reset prompt (only used by reset)
+/// * `record` - Result of the regression, filled with the details used by the reports
 ///
 /// # Configuration File Format
 ///
@@ -646,188 +6977,1701 @@ fn diff_regressions(
 /// # Errors
 ///
 /// Returns an error if:
-/// - The regression directory doesn't exist
-/// - The config.yaml file is missing or invalid
 /// - The example base directory doesn't exist
 /// - The regression command fails
 /// - The generated output file is missing
-/// - The expected output file is missing (for run/diff/reset)
+/// - The expected output file is missing (for run/diff, reset and update create it)
 /// - File operations fail
-fn execute_regression(
-    source: &str,
-    target: &str,
+fn perform_regression(
+    ctx: &Context,
     action: &str,
-    regression_name: &str,
-    debug: bool,
-) -> Result<(), io::Error> {
+    config: &config::RegressionConfig,
+    prompt: &mut ResetPrompt,
+    record: &mut results::RegressionResult,
+) -> Result<Status, io::Error> {
+    let target = ctx.target.as_str();
+    let debug = ctx.debug;
+    let regression_name = config.name.as_str();
+
     if debug {
-        println!("Execute regression: \"{}\"", regression_name);
+        output::trace(
+            regression_name,
+            &format!("Execute regression: \"{}\"", regression_name),
+        );
+        output::trace(regression_name, "Regression configuration:");
+        if ctx.debug_full {
+            output::trace(regression_name, &format!("{:?}", config));
+        } else {
+            match config::merged_yaml(target, regression_name) {
+                Ok(yaml) => {
+                    for line in yaml.lines() {
+                        output::trace(regression_name, &format!("  {}", line));
+                    }
+                }
+                Err(err) => output::trace(regression_name, &format!("  {}", err)),
+            }
+        }
     }
 
-    // Verify regression directory exists
-    let regression_dir = format!("{}/{}", target, regression_name);
-    if !std::path::Path::new(&regression_dir).exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "getting regression directory failed",
-        ));
-    }
+    // The smoke mode runs the quick command of the regression, against its own outputs
+    let smoke_config;
+    let config = if !ctx.smoke || action == "describe" {
+        config
+    } else if let Some(smoke) = config.smoke() {
+        smoke_config = smoke;
+        &smoke_config
+    } else {
+        let status = Status::Skipped {
+            reason: "no smokecommand".to_string(),
+        };
+        print_status(
+            ctx,
+            regression_name,
+            &status,
+            " (no smokecommand, it only runs without --smoke)",
+        );
+        return Ok(status);
+    };
 
-    // Load configuration file
-    let config_path = regression_dir + "/config.yaml";
+    let regbase = config.regbase.as_str();
+    let sourcedata = config.sourcedata.as_str();
+    let regcommand = config.regcommand.as_str();
+    let tags = &config.tags;
 
-    if !std::path::Path::new(&config_path).exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "getting regression configuration file failed",
-        ));
+    // For describe action, just print configuration and return
+    if action == "describe" {
+        // Note the values given by a tag, and in verbose mode those inherited from an included file
+        let origin = |key: &str| match config.source_of(key) {
+            Some(source) if ctx.verbose || source.starts_with(config::TAGS_FILE) => {
+                format!(" (from {})", source)
+            }
+            _ => String::new(),
+        };
+        say!("Regression: \x1b[0;32m{}\x1b[0m", regression_name);
+        if let Some(examples) = &config.examples {
+            say!("  examples: {}{}", examples, origin("examples"));
+        }
+        say!("  regbase: {}{}", regbase, origin("regbase"));
+        if !config.files.is_empty() {
+            say!("  files:{}", origin("files"));
+            for file in &config.files {
+                let targetdata: Vec<String> = match file.targetdata.as_slice() {
+                    [(baseline, targetdata)] if baseline == config::DEFAULT_BASELINE => {
+                        vec![targetdata.clone()]
+                    }
+                    variants => variants
+                        .iter()
+                        .map(|(baseline, targetdata)| format!("{}: {}", baseline, targetdata))
+                        .collect(),
+                };
+                say!("    {} -> {}", file.sourcedata, targetdata.join(", "));
+                for ignore in &file.ignore {
+                    say!("      ignore: {}", ignore.pattern);
+                }
+            }
+        }
+        match config.targetdata.as_slice() {
+            _ if !config.files.is_empty() => {}
+            [(baseline, file)] if baseline == config::DEFAULT_BASELINE => {
+                say!("  sourcedata: {}{}", sourcedata, origin("sourcedata"));
+                say!("  targetdata: {}{}", file, origin("targetdata"))
+            }
+            variants => {
+                say!("  sourcedata: {}{}", sourcedata, origin("sourcedata"));
+                say!("  targetdata:{}", origin("targetdata"));
+                for (baseline, file) in variants {
+                    let selected = if *baseline == ctx.baseline {
+                        " (selected)"
+                    } else {
+                        ""
+                    };
+                    say!("    {}: {}{}", baseline, file, selected);
+                }
+            }
+        }
+        let shell = if config.argv.is_some() {
+            " (without a shell)"
+        } else {
+            ""
+        };
+        say!(
+            "  regcommand: {}{}{}",
+            regcommand,
+            shell,
+            origin("regcommand")
+        );
+        if let Some(smoke) = config.smoke() {
+            let shell = if smoke.argv.is_some() {
+                " (without a shell)"
+            } else {
+                ""
+            };
+            say!(
+                "  smokecommand: {}{}{}",
+                smoke.regcommand,
+                shell,
+                origin("smokecommand")
+            );
+            if config.smoke_sourcedata.is_some() {
+                say!(
+                    "  smoke_sourcedata: {}{}",
+                    smoke.sourcedata,
+                    origin("smoke_sourcedata")
+                );
+            }
+            if !config.smoke_targetdata.is_empty() {
+                let files: Vec<&str> = smoke
+                    .targetdata
+                    .iter()
+                    .map(|(_, file)| file.as_str())
+                    .collect();
+                say!(
+                    "  smoke_targetdata: {}{}",
+                    files.join(", "),
+                    origin("smoke_targetdata")
+                );
+            }
+        }
+        say!("  tags: {:?}{}", tags, origin("tags"));
+        if !config.artifacts.is_empty() {
+            say!("  artifacts: {:?}{}", config.artifacts, origin("artifacts"));
+        }
+        if let Some(on_fail) = &config.on_fail {
+            say!("  on_fail: {}{}", on_fail, origin("on_fail"));
+        }
+        if let Some(difftool) = &config.difftool {
+            say!("  difftool: {}{}", difftool, origin("difftool"));
+        }
+        if let Some(workdir) = &config.workdir {
+            say!("  workdir: {}{}", workdir, origin("workdir"));
+        }
+        match (config.timeout, ctx.timeout) {
+            (Some(timeout), _) => say!("  timeout: {}s{}", timeout, origin("timeout")),
+            (None, Some(timeout)) => say!("  timeout: {}s (from --timeout)", timeout),
+            (None, None) => {}
+        }
+        if config.retries > 0 {
+            say!("  retries: {}{}", config.retries, origin("retries"));
+        }
+        match config.compare {
+            config::Compare::Exact => {}
+            config::Compare::Similarity(min) => {
+                say!("  compare: similarity, at least {}% of the lines", min)
+            }
+            config::Compare::RegexLines => say!("  compare: regex_lines"),
+            config::Compare::Contains => say!("  compare: contains"),
+        }
+        if !config.mask.is_empty() {
+            say!("  mask:{}", origin("mask"));
+            for mask in &config.mask {
+                say!("    {} -> {}", mask.pattern, mask.replace);
+            }
+        }
+        if !config.ignore.is_empty() && config.files.is_empty() {
+            say!("  ignore:{}", origin("ignore"));
+            for ignore in &config.ignore {
+                say!("    {}", ignore.pattern);
+            }
+        }
+        if config.store_compressed {
+            say!("  store_compressed: true{}", origin("store_compressed"));
+        }
+        if config.protected {
+            let reason = config
+                .protected_reason
+                .as_ref()
+                .map(|reason| format!(" ({})", reason))
+                .unwrap_or_default();
+            say!("  protected: true{}{}", reason, origin("protected"));
+        }
+        if let Some(precision) = config.normalize_numbers {
+            say!(
+                "  normalize_numbers: {} decimals{}",
+                precision,
+                origin("normalize_numbers")
+            );
+        }
+        if let Some(section) = &config.compare_between {
+            say!(
+                "  compare_between: {:?} and {:?}{}",
+                section.start,
+                section.end,
+                origin("compare_between")
+            );
+        }
+        if config.max_diff_lines > 0 {
+            say!(
+                "  max_diff_lines: {}{}",
+                config.max_diff_lines,
+                origin("max_diff_lines")
+            );
+        }
+        if let Some(size) = config.max_output_size {
+            say!(
+                "  max_output_size: {}{}",
+                human_size(size),
+                origin("max_output_size")
+            );
+        }
+        if config.priority != 0 {
+            say!("  priority: {}{}", config.priority, origin("priority"));
+        }
+        if config.frequency != schedule::Frequency::Always {
+            say!("  frequency: {}", config.frequency);
+        }
+        if !config.limits.is_empty() {
+            let mut limits = Vec::new();
+            if let Some(mb) = config.limits.memory_mb {
+                limits.push(format!("memory_mb: {}", mb));
+            }
+            if let Some(seconds) = config.limits.cpu_seconds {
+                limits.push(format!("cpu_seconds: {}", seconds));
+            }
+            if let Some(nice) = config.limits.nice {
+                limits.push(format!("nice: {}", nice));
+            }
+            say!("  limits: {}{}", limits.join(", "), origin("limits"));
+        }
+        if !config.env.is_empty() {
+            say!("  env:{}", origin("env"));
+            for (name, value) in &config.env {
+                say!("    {}={}", name, redact::value(name, value));
+            }
+        }
+        if config.clean_env {
+            say!("  clean_env: true{}", origin("clean_env"));
+        }
+        if !config.env_passthrough.is_empty() {
+            say!(
+                "  env_passthrough: {}{}",
+                config.env_passthrough.join(", "),
+                origin("env_passthrough")
+            );
+        }
+        if !config.actions.is_empty() {
+            say!("  actions:");
+            for overrides in &config.actions {
+                let keys: Vec<String> = overrides
+                    .keys
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect();
+                say!("    {}: {}", overrides.action, keys.join(", "));
+            }
+        }
+        if let Some(note) = latest_note_line(ctx, regression_name) {
+            say!("  {}", note);
+        }
+        say_provenance(ctx, config);
+        say_checks(ctx, config);
+        return Ok(Status::Described);
     }
 
-    // Parse YAML configuration
-    let config_content = fs::read_to_string(&config_path)?;
-    let parsed_config = YamlLoader::load_from_str(&config_content);
+    let Prepared {
+        candidates,
+        examplesource,
+        command_dir,
+    } = prepare_regression(ctx, config)?;
 
-    if debug {
-        println!("Regression configuration:");
-        println!("{:?}", parsed_config);
+    // Commands of untrusted data repositories only run when allowlisted, the difftool
+    // of the configuration too since diff runs it
+    if let Some(allowlist) = &ctx.command_allowlist {
+        let difftool = config.difftool.as_deref().filter(|_| action == "diff");
+        let blocked = std::iter::once((regcommand, config.argv.is_none()))
+            .chain(difftool.map(|difftool| (difftool, true)))
+            .find(|(command, shell)| !allowlist.allows(command, *shell))
+            .map(|(command, _)| command);
+        if let Some(command) = blocked {
+            if debug {
+                output::trace(
+                    regression_name,
+                    &format!("command not allowlisted: {}", command),
+                );
+            }
+            print_status(
+                ctx,
+                regression_name,
+                &Status::Blocked,
+                " (command not allowlisted)",
+            );
+            return Ok(Status::Blocked);
+        }
+        if !config.env.is_empty() || config.clean_env || !config.env_passthrough.is_empty() {
+            say!(
+                "Warning: the env, clean_env and env_passthrough of regression {} ignored, with --command-allowlist",
+                regression_name
+            );
+        }
     }
 
-    let config = &parsed_config.unwrap();
+    // Execute the regression command in the example directory, or its workdir, in
+    // its own process group so that an interrupt can terminate all its children
+    if ctx.show_commands {
+        output::trace(
+            regression_name,
+            &shown_command(&trusted_environment(ctx, config), &command_dir),
+        );
+    }
+    let build_command = || {
+        let mut command = match &config.argv {
+            Some(argv) => {
+                let mut command = Command::new(&argv[0]);
+                command.args(&argv[1..]);
+                command
+            }
+            None => {
+                let mut command = Command::new("sh");
+                // The trace goes to stderr, captured and logged with the rest of the output
+                if ctx.shell_trace {
+                    command.arg("-x");
+                }
+                command.arg("-c").arg(regcommand);
+                command
+            }
+        };
+        command.current_dir(&command_dir);
+        set_environment(ctx, config, &mut command);
+        limits::apply(&mut command, &config.limits);
+        command
+    };
+    let options = runner::RunOptions {
+        stream_prefix: if ctx.stream {
+            Some(regression_name)
+        } else {
+            None
+        },
+        heartbeat: ctx.heartbeat.map(|interval| (regression_name, interval)),
+        timeout: command_timeout(ctx, config),
+    };
+    // A benchmarked command runs once to warm the caches up, then the timed iterations, and
+    // only the output of the last one is logged and compared
+    let iterations = match ctx.bench {
+        Some(timed) if action == "run" => timed + 1,
+        _ => 1,
+    };
+    // Sources edited while the command builds them make its result meaningless
+    let checkout = std::path::Path::new(ctx.examples_dir(config)?);
+    let watched = if ctx.ignore_source_changes {
+        None
+    } else {
+        let ignore: Vec<glob::Pattern> = std::iter::once(sourcedata)
+            .chain(config.files.iter().map(|file| file.sourcedata.as_str()))
+            .map(glob::Pattern::escape)
+            .chain(config.artifacts.iter().cloned())
+            .filter_map(|pattern| glob::Pattern::new(&pattern).ok())
+            .collect();
+        let example = std::path::Path::new(&examplesource);
+        Some((checkout::Snapshot::take(checkout, example, &ignore), ignore))
+    };
+    let mut iteration = 0;
+    let completed = loop {
+        let started = std::time::Instant::now();
+        let completed =
+            ctx.runner
+                .run(build_command(), &options)
+                .map_err(|err| match &config.argv {
+                    Some(argv) if err.kind() == io::ErrorKind::NotFound => {
+                        io::Error::other(format!("command {} not found", argv[0]))
+                    }
+                    _ => err,
+                })?;
+        if iteration > 0 {
+            record.bench.push(started.elapsed());
+        }
+        iteration += 1;
+        if iteration == iterations
+            || completed.timed_out
+            || !completed.output.status.success()
+            || interrupt::interrupted().is_some()
+        {
+            break completed;
+        }
+    };
+    if let Some((before, ignore)) = watched {
+        let after =
+            checkout::Snapshot::take(checkout, std::path::Path::new(&examplesource), &ignore);
+        record.unreliable = before.changes(&after);
+    }
+    let regcommand = completed.output;
 
-    // Extract configuration values
-    let regbase = config[0]["regbase"].as_str().unwrap();
-    let sourcedata = config[0]["sourcedata"].as_str().unwrap();
-    let targetdata = config[0]["targetdata"].as_str().unwrap();
-    let regcommand = config[0]["regcommand"].as_str().unwrap();
+    if interrupt::aborted() {
+        return Err(io::Error::other(format!(
+            "interrupted by {}",
+            interrupt::signal_name()
+        )));
+    }
 
-    // Extract tags using helper function
-    let tags = extract_tags_from_config(&config[0]);
+    let output = format!(
+        "{}{}",
+        String::from_utf8_lossy(&regcommand.stdout),
+        String::from_utf8_lossy(&regcommand.stderr)
+    );
+    record.output_tail = results::tail_lines(&output, OUTPUT_TAIL_LINES);
 
+    // The command output is always logged, to be inspected with the logs subcommand
+    let log_base = match logs::write(
+        &ctx.log_dir,
+        regression_name,
+        &regcommand.stdout,
+        &regcommand.stderr,
+    ) {
+        Ok(base) => {
+            record.log = base.display().to_string();
+            record.log.clone()
+        }
+        Err(err) => {
+            say!(
+                "Warning: writing the logs of regression {} failed: {}",
+                regression_name,
+                err
+            );
+            String::new()
+        }
+    };
     if debug {
-        println!("regbase: {}", regbase);
-        println!("sourcedata: {}", sourcedata);
-        println!("targetdata: {}", targetdata);
-        println!("regcommand: {}", regcommand);
-        println!("tags: {:?}", tags);
+        output::trace(
+            regression_name,
+            &format!(
+                "regcommand: {}, {} bytes of stdout, {} bytes of stderr",
+                runner::describe_status(&regcommand.status),
+                regcommand.stdout.len(),
+                regcommand.stderr.len()
+            ),
+        );
+        for (label, content, extension) in [
+            ("stdout", &regcommand.stdout, "out"),
+            ("stderr", &regcommand.stderr, "err"),
+        ] {
+            let log = if log_base.is_empty() {
+                String::new()
+            } else {
+                format!("{}.{}", log_base, extension)
+            };
+            trace_excerpt(ctx, regression_name, label, content, &log);
+        }
     }
 
-    // For describe action, just print configuration and return
-    if action == "describe" {
-        println!("Regression: \x1b[0;32m{}\x1b[0m", regression_name);
-        println!("  regbase: {}", regbase);
-        println!("  sourcedata: {}", sourcedata);
-        println!("  targetdata: {}", targetdata);
-        println!("  regcommand: {}", regcommand);
-        println!("  tags: {:?}", tags);
-        return Ok(());
+    // Artifacts are collected at most once, right after the command when always
+    // collected, otherwise at the first sign of failure
+    let mut artifacts_note: Option<String> = None;
+    let collect_artifacts = |note: &mut Option<String>| -> String {
+        note.get_or_insert_with(|| {
+            save_artifacts(ctx, regression_name, &examplesource, &config.artifacts)
+        })
+        .clone()
+    };
+    if ctx.collect_artifacts == artifacts::Collect::Always {
+        collect_artifacts(&mut artifacts_note);
     }
 
-    // Verify example source directory exists
-    let examplesource = format!("{}/{}", source, regbase);
+    if completed.timed_out {
+        let note = collect_artifacts(&mut artifacts_note);
+        if let Some(deadline) = ctx.deadline.filter(Deadline::reached) {
+            return Err(io::Error::other(format!(
+                "regression command terminated at the --max-total-time deadline of {}, see {}.{{out,err}}{}",
+                runner::format_elapsed(deadline.budget),
+                log_base,
+                note
+            )));
+        }
+        return Err(io::Error::other(format!(
+            "regression command timed out after {}s, see {}.{{out,err}}{}",
+            config.timeout.or(ctx.timeout).unwrap_or_default(),
+            log_base,
+            note
+        )));
+    }
 
-    if debug {
-        println!("examplesource: {}", examplesource);
+    if !regcommand.status.success() {
+        let note = collect_artifacts(&mut artifacts_note);
+        let status = runner::describe_status(&regcommand.status);
+        let mut failed =
+            match limits::exceeded(&config.limits, &regcommand.status, &regcommand.stderr) {
+                Some(limit) => format!(
+                    "executing regression command failed: {} ({}, in {})",
+                    limit,
+                    status,
+                    command_dir.display()
+                ),
+                None => format!(
+                    "executing regression command failed ({}) in {}",
+                    status,
+                    command_dir.display()
+                ),
+            };
+        if !log_base.is_empty() {
+            failed.push_str(&format!(", see {}.{{out,err}}", log_base));
+        }
+        failed.push_str(&note);
+        // The full output is in the logs, the end of the stderr usually tells what went wrong
+        let stderr = results::tail_lines(
+            String::from_utf8_lossy(&regcommand.stderr).trim_end(),
+            STDERR_TAIL_LINES,
+        );
+        if !stderr.is_empty() {
+            failed.push_str(", its stderr ends with:");
+            for line in stderr.lines() {
+                failed.push_str(&format!("\n    {}", line));
+            }
+        }
+        return Err(io::Error::other(failed));
     }
 
-    if !std::path::Path::new(&examplesource).exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "getting regression base directory failed",
-        ));
+    // The output files are checked one by one, as if each were the only one
+    if config.files.is_empty() {
+        let output = CheckedOutput {
+            label: regression_name.to_string(),
+            patch_name: regression_name.to_string(),
+            keep_generated: true,
+            candidates,
+            examplesource: &examplesource,
+            command_dir: &command_dir,
+        };
+        return check_output(
+            ctx,
+            action,
+            config,
+            prompt,
+            record,
+            output,
+            &mut artifacts_note,
+        );
+    }
+    let mut checked = Vec::new();
+    let mut diffs = String::new();
+    let mut output_size = 0;
+    for file in &config.files {
+        let file_config = config.for_file(file);
+        let output = CheckedOutput {
+            label: format!("{} ({})", regression_name, file.sourcedata),
+            patch_name: format!("{}.{}", regression_name, file.sourcedata.replace('/', "_")),
+            keep_generated: false,
+            candidates: file_config.targetdata_for(&ctx.baseline)?,
+            examplesource: &examplesource,
+            command_dir: &command_dir,
+        };
+        let status = check_output(
+            ctx,
+            action,
+            &file_config,
+            prompt,
+            record,
+            output,
+            &mut artifacts_note,
+        )?;
+        diffs.push_str(&std::mem::take(&mut record.diff));
+        output_size += record.output_size.take().unwrap_or_default();
+        checked.push((file.sourcedata.as_str(), status));
+        if *prompt == ResetPrompt::Quit {
+            break;
+        }
     }
+    record.diff = diffs;
+    record.output_size = Some(output_size);
+    let (status, rest) = combined_status(&checked, config.files.len());
+    print_status(ctx, regression_name, &status, &rest);
+    Ok(status)
+}
 
-    // Execute the regression command in the example directory
-    let regcommand = Command::new("sh")
-        .current_dir(&examplesource)
-        .arg("-c")
-        .arg(regcommand)
-        .output()?;
+/// An output file of a regression, checked once its command has run.
+struct CheckedOutput<'a> {
+    /// Name of the regression in the status lines, with the output file when it has several
+    label: String,
+    /// Name of the patch file of the differences, without its extension
+    patch_name: String,
+    /// Keep a failing output for import-baselines, which only takes the single ones
+    keep_generated: bool,
+    /// Candidate expected outputs of the selected baseline
+    candidates: Vec<&'a str>,
+    /// Directory of the example
+    examplesource: &'a str,
+    /// Directory the command ran in
+    command_dir: &'a std::path::Path,
+}
 
-    if debug {
-        println!("regcommand: {:?}", regcommand);
+/// Returns the rank of the status of an output file, the highest one tells the status of
+/// the regression.
+fn status_rank(status: &Status) -> u8 {
+    match status {
+        Status::Failed { .. } => 6,
+        Status::QuarantinedFailure { .. } => 5,
+        Status::DiffFound => 4,
+        Status::WouldChange { .. } => 3,
+        Status::Reset | Status::Updated | Status::Captured => 2,
+        Status::Skipped { .. } => 1,
+        _ => 0,
     }
+}
 
-    if !regcommand.status.success() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "executing regression command failed",
+/// Combines the statuses of the output files of a regression, `total` of them, into its
+/// status and the note of its status line, naming the files of that status when the others
+/// are not.
+fn combined_status(checked: &[(&str, Status)], total: usize) -> (Status, String) {
+    let rank = checked
+        .iter()
+        .map(|(_, status)| status_rank(status))
+        .max()
+        .unwrap_or_default();
+    let worst: Vec<&(&str, Status)> = checked
+        .iter()
+        .filter(|(_, status)| status_rank(status) == rank)
+        .collect();
+    let status = match &worst[0].1 {
+        Status::Failed { .. } | Status::QuarantinedFailure { .. } => {
+            let diff_summary = worst
+                .iter()
+                .map(|(file, status)| match status {
+                    Status::Failed { diff_summary }
+                    | Status::QuarantinedFailure { diff_summary } => {
+                        format!("{}: {}", file, diff_summary)
+                    }
+                    _ => String::new(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            match &worst[0].1 {
+                Status::Failed { .. } => Status::Failed { diff_summary },
+                _ => Status::QuarantinedFailure { diff_summary },
+            }
+        }
+        Status::WouldChange { .. } => {
+            let mut stat = diff::DiffStat::default();
+            let mut files = 0;
+            for (_, status) in &worst {
+                if let Status::WouldChange {
+                    stat: changed,
+                    files: changed_files,
+                } = status
+                {
+                    stat.added += changed.added;
+                    stat.removed += changed.removed;
+                    files += changed_files;
+                }
+            }
+            Status::WouldChange { stat, files }
+        }
+        status => status.clone(),
+    };
+    let mut rest = match &status {
+        Status::WouldChange { stat, .. } => format!(" +{} -{} lines", stat.added, stat.removed),
+        _ => String::new(),
+    };
+    if worst.len() == total {
+        rest.push_str(&format!(" ({} files)", total));
+    } else {
+        let files: Vec<&str> = worst.iter().map(|(file, _)| *file).collect();
+        rest.push_str(&format!(
+            " ({} of {} files: {})",
+            worst.len(),
+            total,
+            files.join(", ")
         ));
     }
+    (status, rest)
+}
+
+/// Checks an output file of a regression whose command has run, as the action requires.
+///
+/// # Errors
+///
+/// Returns an error if the generated output or the expected one is missing, or if reading
+/// or writing them fails.
+fn check_output(
+    ctx: &Context,
+    action: &str,
+    config: &config::RegressionConfig,
+    prompt: &mut ResetPrompt,
+    record: &mut results::RegressionResult,
+    output: CheckedOutput,
+    artifacts_note: &mut Option<String>,
+) -> Result<Status, io::Error> {
+    let target = ctx.target.as_str();
+    let debug = ctx.debug;
+    let regression_name = config.name.as_str();
+    let sourcedata = config.sourcedata.as_str();
+    let CheckedOutput {
+        label,
+        patch_name,
+        keep_generated,
+        candidates,
+        examplesource,
+        command_dir,
+    } = output;
+    let collect_artifacts = |note: &mut Option<String>| -> String {
+        note.get_or_insert_with(|| {
+            save_artifacts(ctx, regression_name, examplesource, &config.artifacts)
+        })
+        .clone()
+    };
 
     // Verify the generated output file exists
     let result = format!("{}/{}", examplesource, sourcedata);
 
     if debug {
-        println!("result: {}", result);
+        output::trace(regression_name, &format!("result: {}", result));
     }
 
     if !std::path::Path::new(&result).exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "getting regression result failed",
-        ));
+        return Err(setup::Missing::Output {
+            sourcedata: sourcedata.to_string(),
+            path: result.into(),
+            command_dir: command_dir.to_path_buf(),
+            note: collect_artifacts(artifacts_note),
+        }
+        .into());
     }
 
-    // Load the generated output
-    let result_data = fs::read_to_string(&result)?;
+    // An enormous output is neither read nor diffed, it would exhaust the memory
+    let size = fs::metadata(&result)?.len();
+    record.output_size = Some(size);
+    let max_size = config.max_output_size.unwrap_or(ctx.max_output_size);
+    if size > max_size {
+        let exceeded = format!(
+            "generated output exceeds size limit ({} > {})",
+            human_size(size),
+            human_size(max_size)
+        );
+        let note = collect_artifacts(artifacts_note);
+        if !matches!(action, "run" | "bootstrap") {
+            return Err(io::Error::other(format!("{}{}", exceeded, note)));
+        }
+        let status = match ctx.quarantine.active(regression_name) {
+            Some(_) => Status::QuarantinedFailure {
+                diff_summary: exceeded.clone(),
+            },
+            None => Status::Failed {
+                diff_summary: exceeded.clone(),
+            },
+        };
+        print_status(ctx, &label, &status, &format!(" ({}){}", exceeded, note));
+        return Ok(status);
+    }
 
     let regression_dir = format!("{}/{}", target, regression_name);
 
+    // Most outputs pass: identical files are found without reading them as text
+    if action == "run" {
+        if let Some(candidate) = identical_candidate(config, &regression_dir, &candidates, &result)?
+        {
+            if debug {
+                output::trace(
+                    regression_name,
+                    &format!("identical to {}, not read", candidate),
+                );
+            }
+            let note = if candidates.len() > 1 {
+                format!(" (candidate {})", candidate)
+            } else {
+                String::new()
+            };
+            return Ok(print_passed(ctx, regression_name, &label, note));
+        }
+    }
+
+    // Load the generated output
+    let result_data = gzip::read_to_string(&result)?;
+
+    // Any of the candidates is an acceptable output, the closest one is compared
+    let targetdata = choose_candidate(
+        ctx,
+        config,
+        action,
+        &regression_dir,
+        &candidates,
+        &result_data,
+    )?;
+    let candidate_note = if candidates.len() > 1 {
+        format!(" (candidate {})", targetdata)
+    } else {
+        String::new()
+    };
+
     // Verify the expected output file exists
     let targetdatafull = format!("{}/{}", regression_dir, targetdata);
 
     if debug {
-        println!("targetdatafull: {}", targetdatafull);
+        output::trace(
+            regression_name,
+            &format!("targetdatafull: {}", targetdatafull),
+        );
     }
 
-    if !std::path::Path::new(&targetdatafull).exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "getting regression target data directory failed",
-        ));
+    // A missing expected output can be created by the actions that write it
+    let baseline_missing = !std::path::Path::new(&targetdatafull).exists();
+    if baseline_missing && !matches!(action, "bootstrap" | "reset" | "preview" | "update") {
+        return Err(setup::Missing::Baseline {
+            regression: regression_name.to_string(),
+            targetdata: targetdata.to_string(),
+            path: targetdatafull.into(),
+        }
+        .into());
     }
 
     // Load the expected output
-    let target_data = fs::read_to_string(&targetdatafull)?;
+    let target_data = if baseline_missing {
+        String::new()
+    } else {
+        gzip::read_to_string(&targetdatafull)?
+    };
+    let new_baseline = if baseline_missing {
+        " (new baseline)"
+    } else {
+        ""
+    };
+
+    // Only the marked sections are compared, normalized, and a missing marker fails the regression
+    let (target_data, result_data, missing_marker) = if baseline_missing {
+        (target_data, result_data, None)
+    } else {
+        compared_outputs(config, &target_data, &result_data)
+    };
+
+    // Compare generated output with expected output
+    let comparison = compare_outputs(&result_data, &target_data);
+    // The lines of a pattern baseline are matched rather than compared
+    let match_patterns = || {
+        pattern::match_lines(&target_data, &result_data)
+            .map_err(|err| io::Error::other(format!("{}: {}", targetdata, err)))
+    };
 
     // Perform the requested action
-    if action == "run" {
-        // Compare generated output with expected output
-        if result_data == target_data {
-            println!("Regression {}: \x1b[0;32mpassed\x1b[0m", regression_name);
+    if action == "bootstrap" && baseline_missing {
+        write_baseline(&result, &targetdatafull)?;
+        record_provenance(ctx, regression_name, &targetdatafull);
+        print_status(
+            ctx,
+            &label,
+            &Status::Captured,
+            &format!(
+                " (new baseline written to {}, review it before committing)",
+                targetdatafull
+            ),
+        );
+        Ok(Status::Captured)
+    } else if action == "run" || action == "bootstrap" {
+        let quarantined = ctx.quarantine.active(regression_name);
+        let differences = comparison.added + comparison.removed;
+        let mut line_match = None;
+        let mut containment = None;
+        let (passes, mut criterion) = match (config.compare, &missing_marker) {
+            (_, Some(missing)) => (false, format!(" ({})", missing)),
+            (config::Compare::Exact, None) if comparison.is_identical() => (true, String::new()),
+            // The tolerance stays visible, in case the output drifts further
+            (config::Compare::Exact, None) if differences <= config.max_diff_lines => {
+                (true, format!(" ({} tolerated differences)", differences))
+            }
+            (config::Compare::Exact, None) => (false, String::new()),
+            (config::Compare::Similarity(min), None) => {
+                let similarity = similarity_of(&comparison, &target_data, &result_data);
+                record.similarity = Some(similarity);
+                (
+                    similarity >= min,
+                    format!(" ({})", similarity_note(similarity, min)),
+                )
+            }
+            (config::Compare::RegexLines, None) => {
+                let matched = match_patterns()?;
+                let passes = matched.passes();
+                let criterion = if passes {
+                    String::new()
+                } else {
+                    format!(" ({})", mismatch_note(&matched))
+                };
+                line_match = Some(matched);
+                (passes, criterion)
+            }
+            (config::Compare::Contains, None) => {
+                let contained = diff::containment(&target_data, &result_data);
+                let criterion = contained
+                    .first_missing()
+                    .map(|missing| format!(" ({})", missing_note(&target_data, missing)))
+                    .unwrap_or_default();
+                let passes = contained.passes();
+                containment = Some(contained);
+                (passes, criterion)
+            }
+        };
+        criterion.push_str(&candidate_note);
+        if passes {
+            Ok(print_passed(ctx, regression_name, &label, criterion))
         } else {
-            println!("Regression {}: \x1b[0;31mfailed\x1b[0m", regression_name);
+            let note = format!("{}{}", criterion, collect_artifacts(artifacts_note));
+            record.diff = match (&line_match, &containment) {
+                (Some(matched), _) => matched.render(),
+                (_, Some(contained)) => contained.annotate(&target_data),
+                _ => diff::unified(&target_data, &result_data, &targetdatafull, &result),
+            };
+            // Exported with the run, the output can become the expected one with import-baselines
+            let kept = if keep_generated {
+                logs::write_generated(&ctx.log_dir, regression_name, std::path::Path::new(&result))
+            } else {
+                Ok(())
+            };
+            if let Err(err) = kept {
+                say!(
+                    "Warning: keeping the generated output of regression {} failed: {}",
+                    regression_name,
+                    err
+                );
+            }
+            let patch = write_patch(ctx, &patch_name, &record.diff);
+            let diff_summary = format!("+{} -{} lines", comparison.added, comparison.removed);
+            let status = match quarantined {
+                Some(entry) => {
+                    let reason = entry
+                        .reason
+                        .as_ref()
+                        .map(|reason| format!(" ({})", reason))
+                        .unwrap_or_default();
+                    let status = Status::QuarantinedFailure { diff_summary };
+                    print_status(ctx, &label, &status, &format!("{}{}", reason, note));
+                    status
+                }
+                None => {
+                    let status = Status::Failed { diff_summary };
+                    print_status(ctx, &label, &status, &note);
+                    status
+                }
+            };
+            // A recent reset of the baseline often explains the failure
+            let committed = format!("{}/{}", regression_name, targetdata);
+            if let Some(change) = ctx.last_changes.of(target, &committed) {
+                say!("  {}", change.describe(timestamp::DateTime::now()));
+            }
+            if let Some(note) = latest_note_line(ctx, regression_name) {
+                say!("  {}", note);
+            }
+            if let Ok(Some(provenance)) = provenance::load(std::path::Path::new(&targetdatafull)) {
+                say!("  {}", provenance.describe());
+            }
+            if ctx.diff_on_fail {
+                print_capped_diff(ctx, &record.diff, patch.as_deref());
+            } else if let Some(matched) = &line_match {
+                print_mismatches(matched);
+            }
+            Ok(status)
         }
+    } else if matches!(action, "reset" | "update" | "preview")
+        && config.protected
+        && !baseline_missing
+        && !ctx.override_protected
+        && (action == "reset" || !comparison.is_identical())
+    {
+        // Update and preview still report the protected expected outputs that are up to date
+        let status = Status::Skipped {
+            reason: "the targetdata is protected".to_string(),
+        };
+        let reason = config
+            .protected_reason
+            .as_ref()
+            .map(|reason| format!(": {}", reason))
+            .unwrap_or_default();
+        print_status(
+            ctx,
+            &label,
+            &status,
+            &format!(
+                " (the targetdata is protected{}, use --override-protected to overwrite it)",
+                reason
+            ),
+        );
+        Ok(status)
+    } else if matches!(action, "reset" | "update" | "preview")
+        && config.compare.is_hand_written()
+        && !baseline_missing
+        && !ctx.force
+    {
+        // The expected lines are written by hand, the generated output would replace them
+        let status = Status::Skipped {
+            reason: "the targetdata is written by hand".to_string(),
+        };
+        print_status(
+            ctx,
+            &label,
+            &status,
+            " (the targetdata is written by hand for its compare mode, use --force to overwrite it)",
+        );
+        Ok(status)
     } else if action == "reset" {
-        // Update expected output with current generated output
-        fs::copy(result, targetdatafull)?;
+        if !confirm_reset(prompt, &label, &result, &targetdatafull)? {
+            let reason = if *prompt == ResetPrompt::Quit {
+                "quit at the prompt"
+            } else {
+                "declined at the prompt"
+            };
+            let status = Status::Skipped {
+                reason: reason.to_string(),
+            };
+            print_status(ctx, &label, &status, "");
+            return Ok(status);
+        }
 
-        println!("Regression {}: \x1b[0;33mreset\x1b[0m", regression_name);
-    } else if action == "diff" {
-        // Show differences using sdiff
-        let diff = Command::new("sdiff")
-            .arg("--suppress-common-lines")
-            .arg(result)
-            .arg(targetdatafull)
-            .output()?;
+        // Update expected output with current generated output
+        let stored = store_baseline(ctx, config, &result, targetdata, &targetdatafull)?;
 
-        if debug {
-            println!("diff: {:?}", diff);
+        print_status(
+            ctx,
+            &label,
+            &Status::Reset,
+            &format!("{}{}", new_baseline, stored),
+        );
+        Ok(Status::Reset)
+    } else if action == "update" {
+        if comparison.is_identical() && !baseline_missing {
+            print_status(ctx, &label, &Status::Unchanged, "");
+            return Ok(Status::Unchanged);
         }
 
-        if diff.status.success() {
-            println!(
-                "Regression {}: \x1b[0;32mno differences\x1b[0m",
-                regression_name
+        // Update expected output with current generated output
+        let stored = store_baseline(ctx, config, &result, targetdata, &targetdatafull)?;
+
+        print_status(
+            ctx,
+            &label,
+            &Status::Updated,
+            &format!("{}{}", new_baseline, stored),
+        );
+        Ok(Status::Updated)
+    } else if action == "preview" {
+        if comparison.is_identical() && !baseline_missing {
+            print_status(ctx, &label, &Status::Unchanged, "");
+            Ok(Status::Unchanged)
+        } else {
+            print_status(
+                ctx,
+                &label,
+                &Status::WouldChange {
+                    stat: comparison,
+                    files: 1,
+                },
+                &format!(
+                    " 1 file, +{} -{} lines{}",
+                    comparison.added, comparison.removed, new_baseline
+                ),
             );
+            Ok(Status::WouldChange {
+                stat: comparison,
+                files: 1,
+            })
+        }
+    } else if action == "diff" {
+        // Differences of quarantined regressions do not count in the exit code
+        let mut notes = if ctx.quarantine.active(regression_name).is_some() {
+            format!(" {}", style::note(ctx.status_style, "(quarantined)"))
         } else {
-            println!(
-                "Regression {}: \x1b[0;31mdifferences found\x1b[0m",
-                regression_name
+            String::new()
+        };
+        notes.push_str(&candidate_note);
+        // The differences are shown in full, even those run tolerates
+        match (config.compare, &missing_marker) {
+            // The whole outputs are shown, to find where the marker went
+            (_, Some(missing)) => notes.push_str(&format!(" ({})", missing)),
+            (config::Compare::Exact, None) => {
+                if !comparison.is_identical()
+                    && comparison.added + comparison.removed <= config.max_diff_lines
+                {
+                    notes.push_str(&format!(
+                        " (within the {} tolerated differences)",
+                        config.max_diff_lines
+                    ));
+                }
+            }
+            (config::Compare::Similarity(min), None) => {
+                let similarity = similarity_of(&comparison, &target_data, &result_data);
+                notes.push_str(&format!(" ({})", similarity_note(similarity, min)));
+            }
+            // The lines not matching their patterns are the differences
+            (config::Compare::RegexLines, None) => {
+                let matched = match_patterns()?;
+                if matched.passes() {
+                    notes.push_str(" (all the lines match their patterns)");
+                    print_status(ctx, &label, &Status::NoDiff, &notes);
+                    return Ok(Status::NoDiff);
+                }
+                notes.push_str(&format!(" ({})", mismatch_note(&matched)));
+                print_status(ctx, &label, &Status::DiffFound, &notes);
+                let patch = write_patch(ctx, &patch_name, &matched.render());
+                if !ctx.quiet {
+                    print_capped_diff(ctx, &matched.render(), patch.as_deref());
+                }
+                return Ok(Status::DiffFound);
+            }
+            // The expected lines are shown with where they were found
+            (config::Compare::Contains, None) => {
+                let contained = diff::containment(&target_data, &result_data);
+                let Some(missing) = contained.first_missing() else {
+                    notes.push_str(" (all the expected lines found in order)");
+                    print_status(ctx, &label, &Status::NoDiff, &notes);
+                    return Ok(Status::NoDiff);
+                };
+                notes.push_str(&format!(" ({})", missing_note(&target_data, missing)));
+                print_status(ctx, &label, &Status::DiffFound, &notes);
+                let annotated = contained.annotate(&target_data);
+                let patch = write_patch(ctx, &patch_name, &annotated);
+                if !ctx.quiet {
+                    print_capped_diff(ctx, &annotated, patch.as_deref());
+                }
+                return Ok(Status::DiffFound);
+            }
+        }
+        if comparison.is_identical() && missing_marker.is_none() {
+            print_status(ctx, &label, &Status::NoDiff, &notes);
+            return Ok(Status::NoDiff);
+        }
+
+        // In quiet mode only the status matters, the patch file is still written
+        if ctx.quiet {
+            print_status(ctx, &label, &Status::DiffFound, &notes);
+            let unified = diff::unified(&target_data, &result_data, &targetdatafull, &result);
+            write_patch(ctx, &patch_name, &unified);
+            return Ok(Status::DiffFound);
+        }
+
+        // The diff tools compare the files, the normalized outputs are diffed here
+        let mut normalizations = normalizations(config, missing_marker.is_none());
+        if gzip::is_compressed(&result) || gzip::is_compressed(&targetdatafull) {
+            normalizations.push("decompressed".to_string());
+        }
+        if !normalizations.is_empty() {
+            print_status(
+                ctx,
+                &label,
+                &Status::DiffFound,
+                &format!("{} ({})", notes, normalizations.join(", ")),
             );
-            println!("{}", String::from_utf8_lossy(&diff.stdout));
+            let unified = diff::unified(&target_data, &result_data, &targetdatafull, &result);
+            let patch = write_patch(ctx, &patch_name, &unified);
+            print_capped_diff(ctx, &unified, patch.as_deref());
+            return Ok(Status::DiffFound);
+        }
+
+        // Show differences using the chosen diff tool, or else the first available one
+        let difftool = ctx
+            .difftool
+            .clone()
+            .or_else(|| config.difftool.clone())
+            .or_else(|| ctx.user_config.difftool.clone());
+        let diff = match &difftool {
+            Some(difftool) => run_difftool(difftool, &result, &targetdatafull)?,
+            None => show_diff(&result, &targetdatafull)?,
+        };
+
+        if debug {
+            output::trace(regression_name, &format!("diff: {}", diff.text));
         }
+
+        print_status(
+            ctx,
+            &label,
+            &Status::DiffFound,
+            &format!("{}{}", notes, diff.note),
+        );
+        let unified = diff::unified(&target_data, &result_data, &targetdatafull, &result);
+        let patch = write_patch(ctx, &patch_name, &unified);
+        print_capped_diff(ctx, &diff.text, patch.as_deref());
+        Ok(Status::DiffFound)
+    } else {
+        Err(io::Error::other(format!("unknown action {}", action)))
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runner::scripted::{Scripted, ScriptedRunner};
+    use std::sync::Arc;
+
+    /// Creates a data directory with the regression `blink`, whose command writes `out.txt`,
+    /// expected to be `on`, and the examples directory with its regbase.
+    fn fixture(extra_keys: &str) -> TempDir {
+        let dir = TempDir::new("bmregression-main").unwrap();
+        let data = dir.path().join("data/blink");
+        fs::create_dir_all(&data).unwrap();
+        fs::create_dir_all(dir.path().join("examples/blink")).unwrap();
+        fs::write(
+            data.join("config.yaml"),
+            format!(
+                "regbase: blink\nsourcedata: out.txt\ntargetdata: out.txt\nregcommand: make hdl\n{}",
+                extra_keys
+            ),
+        )
+        .unwrap();
+        fs::write(data.join("out.txt"), "on\n").unwrap();
+        dir
+    }
+
+    /// Returns a context on the fixture, with the default options, executing with `runner`.
+    fn context(dir: &std::path::Path, runner: Arc<ScriptedRunner>) -> Context {
+        let target = dir.join("data");
+        Context {
+            source: dir.join("examples").display().to_string(),
+            named_sources: Vec::new(),
+            target: target.display().to_string(),
+            tags: vec!["default".to_string()],
+            tag_expr: None,
+            regbase: None,
+            any_tag: false,
+            debug: false,
+            debug_full: false,
+            stream: false,
+            heartbeat: None,
+            errors: AtomicUsize::new(0),
+            config_errors: AtomicUsize::new(0),
+            results: Mutex::new(Vec::new()),
+            run_id: "test-run".to_string(),
+            log_dir: logs::logs_root(target.to_str().unwrap()).join("test-run"),
+            collect_artifacts: artifacts::Collect::Failure,
+            artifacts_dir: dir.join("artifacts/test-run"),
+            max_printed_diff_lines: None,
+            patch_dir: None,
+            difftool: None,
+            user_config: userconfig::UserConfig::default(),
+            quarantine: quarantine::Quarantine::default(),
+            quiet: false,
+            verbose: false,
+            verify: true,
+            allow_external_paths: false,
+            status_style: style::StatusStyle::Ascii,
+            baseline: "default".to_string(),
+            implicit_tags: true,
+            force: false,
+            override_protected: false,
+            smoke: false,
+            bench: None,
+            jobs: 1,
+            timeout: None,
+            reset_candidate: None,
+            compress: false,
+            baseline_warn_size: u64::MAX,
+            baseline_max_size: u64::MAX,
+            max_output_size: u64::MAX,
+            deadline: None,
+            on_fail: None,
+            exact: true,
+            diff_on_fail: false,
+            show_commands: false,
+            shell_trace: false,
+            ignore_source_changes: false,
+            clean_env: false,
+            command_allowlist: None,
+            runner,
+            header: Mutex::new(None),
+            last_changes: blame::LastChanges::default(),
+            provenance: provenance::Recorder::default(),
+        }
+    }
+
+    /// Runs the regression of the fixture once with each scripted result.
+    fn run_blink(extra_keys: &str, scripted: Vec<Scripted>) -> Vec<Status> {
+        let dir = fixture(extra_keys);
+        let count = scripted.len();
+        let runner = Arc::new(ScriptedRunner::new(scripted));
+        let ctx = context(dir.path(), runner.clone());
+        let config = config::load(&ctx.target, "blink").unwrap();
+        let statuses: Vec<Status> = (0..count)
+            .map(|_| execute_regression(&ctx, "run", &config, &mut ResetPrompt::Always).status)
+            .collect();
+        assert!(runner
+            .commands()
+            .iter()
+            .all(|command| command.ends_with("make hdl")));
+        statuses
+    }
+
+    /// Returns the error of a regression that could not be executed.
+    fn error_of(status: &Status) -> &str {
+        match status {
+            Status::Errored { detail, .. } => detail,
+            status => panic!("{:?} is not an error", status),
+        }
+    }
+
+    #[test]
+    fn groups_the_names_equal_ignoring_case() {
+        let names: Vec<String> = ["b", "Blink", "counter", "blink", "B", "BLINK", "blink"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let collisions = name_collisions(&names);
+        assert_eq!(
+            collisions,
+            vec![
+                vec!["B".to_string(), "b".to_string()],
+                vec![
+                    "BLINK".to_string(),
+                    "Blink".to_string(),
+                    "blink".to_string(),
+                    "blink".to_string()
+                ],
+            ]
+        );
+        assert_eq!(
+            describe_collisions(&collisions),
+            "B, b; BLINK, Blink, blink, blink"
+        );
+        assert!(name_collisions(&names[2..4]).is_empty());
+    }
+
+    #[test]
+    fn compares_the_output_of_the_command() {
+        let outcomes = run_blink(
+            "",
+            vec![
+                Scripted::writing("out.txt", "on\n"),
+                Scripted::writing("out.txt", "off\n"),
+            ],
+        );
+        assert_eq!(outcomes[0], Status::Passed);
+        assert_eq!(
+            outcomes[1],
+            Status::Failed {
+                diff_summary: "+1 -1 lines".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn refuses_to_overwrite_a_protected_baseline() {
+        let dir = fixture("protected: true\nprotected_reason: signed off by the hardware team\n");
+        let runner = Arc::new(ScriptedRunner::new(vec![
+            Scripted::writing("out.txt", "off\n"),
+            Scripted::writing("out.txt", "on\n"),
+            Scripted::writing("out.txt", "off\n"),
+            Scripted::writing("out.txt", "off\n"),
+        ]));
+        let mut ctx = context(dir.path(), runner);
+        let config = config::load(&ctx.target, "blink").unwrap();
+        let expected = dir.path().join("data/blink/out.txt");
+
+        let refused = execute_regression(&ctx, "reset", &config, &mut ResetPrompt::Always);
+        assert_eq!(
+            refused.status,
+            Status::Skipped {
+                reason: "the targetdata is protected".to_string()
+            }
+        );
+        assert!(
+            refused.output.contains(
+                "(the targetdata is protected: signed off by the hardware team, use --override-protected to overwrite it)"
+            ),
+            "{}",
+            refused.output
+        );
+        // An update leaving the expected output as is reports it up to date
+        let unchanged = execute_regression(&ctx, "update", &config, &mut ResetPrompt::Always);
+        assert_eq!(unchanged.status, Status::Unchanged);
+        let refused = execute_regression(&ctx, "update", &config, &mut ResetPrompt::Always);
+        assert!(matches!(refused.status, Status::Skipped { .. }));
+        assert_eq!(fs::read_to_string(&expected).unwrap(), "on\n");
+
+        ctx.override_protected = true;
+        let reset = execute_regression(&ctx, "reset", &config, &mut ResetPrompt::Always);
+        assert_eq!(reset.status, Status::Reset);
+        assert_eq!(fs::read_to_string(&expected).unwrap(), "off\n");
+    }
+
+    #[test]
+    fn reports_a_failing_command() {
+        let outcomes = run_blink(
+            "",
+            vec![Scripted {
+                code: 2,
+                stderr: "make: *** [hdl] Error 2\n".to_string(),
+                ..Default::default()
+            }],
+        );
+        let err = error_of(&outcomes[0]);
+        assert!(
+            err.starts_with("executing regression command failed (exit code 2) in "),
+            "{}",
+            err
+        );
+        assert!(
+            err.ends_with(".{out,err}, its stderr ends with:\n    make: *** [hdl] Error 2"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn describes_the_checks_against_the_checkouts() {
+        let dir = fixture("");
+        let mut ctx = context(dir.path(), Arc::new(ScriptedRunner::new(Vec::new())));
+        let config = config::load(&ctx.target, "blink").unwrap();
+        let described = execute_regression(&ctx, "describe", &config, &mut ResetPrompt::Always);
+        assert_eq!(described.status, Status::Described);
+        let examples = dir.path().join("examples").canonicalize().unwrap();
+        let expected = dir
+            .path()
+            .join("data/blink/out.txt")
+            .canonicalize()
+            .unwrap();
+        assert!(
+            described.output.contains(&format!(
+                "  example directory: {} (exists)\n  sourcedata path: {}\n  expected output: {}, 3 B, modified ",
+                dir.path().join("examples/blink").display(),
+                examples.join("blink/out.txt").display(),
+                expected.display()
+            )),
+            "{}",
+            described.output
+        );
+
+        fs::remove_file(&expected).unwrap();
+        fs::remove_dir_all(examples.join("blink")).unwrap();
+        let described = execute_regression(&ctx, "describe", &config, &mut ResetPrompt::Always);
+        assert!(
+            described
+                .output
+                .contains(" (missing: is the examples checkout complete?"),
+            "{}",
+            described.output
+        );
+        assert!(
+            described.output.contains(&format!(
+                "  expected output: {} (does not exist)\n",
+                expected.display()
+            )),
+            "{}",
+            described.output
+        );
+
+        ctx.verify = false;
+        let described = execute_regression(&ctx, "describe", &config, &mut ResetPrompt::Always);
+        assert!(described.output.ends_with(
+            "  example directory: not checked\n  sourcedata path: not checked\n  expected output: not checked\n"
+        ));
+    }
+
+    #[test]
+    fn names_the_last_change_of_the_failing_baseline() {
+        let dir = fixture("");
+        let data = dir.path().join("data");
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .current_dir(&data)
+                .args(["-c", "user.name=Ada", "-c", "user.email=ada@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}: {}", args, status);
+        };
+        let runner = Arc::new(ScriptedRunner::new(vec![
+            Scripted::writing("out.txt", "off\n"),
+            Scripted::writing("out.txt", "off\n"),
+        ]));
+        let ctx = context(dir.path(), runner.clone());
+        let config = config::load(&ctx.target, "blink").unwrap();
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "reset after v0.9.1"]);
+
+        let failed = execute_regression(&ctx, "run", &config, &mut ResetPrompt::Always);
+        assert!(
+            failed
+                .output
+                .contains("\n  baseline last changed just now by Ada ("),
+            "{}",
+            failed.output
+        );
+        assert!(failed.output.contains("): 'reset after v0.9.1'\n"));
+
+        // Outside a git checkout the line is left out
+        fs::remove_dir_all(data.join(".git")).unwrap();
+        let ctx = context(dir.path(), runner);
+        let failed = execute_regression(&ctx, "run", &config, &mut ResetPrompt::Always);
+        assert!(matches!(failed.status, Status::Failed { .. }));
+        assert!(!failed.output.contains("baseline last changed"));
+    }
+
+    /// Performs an action on the regression of a fixture, returning its setup failure.
+    fn setup_failure(dir: &TempDir, action: &str, scripted: Vec<Scripted>) -> setup::Missing {
+        let ctx = context(dir.path(), Arc::new(ScriptedRunner::new(scripted)));
+        let config = config::load(&ctx.target, "blink").unwrap();
+        let mut record = results::RegressionResult::default();
+        let err = perform_regression(&ctx, action, &config, &mut ResetPrompt::Always, &mut record)
+            .unwrap_err();
+        setup::of(&err)
+            .unwrap_or_else(|| panic!("{} is not a setup failure", err))
+            .clone()
+    }
+
+    #[test]
+    fn names_the_missing_paths() {
+        let outcomes = run_blink("", vec![Scripted::exit(0)]);
+        let err = error_of(&outcomes[0]);
+        assert!(
+            err.starts_with("the command succeeded but did not write "),
+            "{}",
+            err
+        );
+
+        let dir = fixture("");
+        let failure = setup_failure(&dir, "run", vec![Scripted::exit(0)]);
+        assert!(matches!(
+            failure,
+            setup::Missing::Output { ref sourcedata, .. } if sourcedata == "out.txt"
+        ));
+        fs::remove_file(dir.path().join("data/blink/out.txt")).unwrap();
+        let written = || vec![Scripted::writing("out.txt", "on\n")];
+        assert!(matches!(
+            setup_failure(&dir, "run", written()),
+            setup::Missing::Baseline { ref targetdata, .. } if targetdata == "out.txt"
+        ));
+        fs::remove_dir_all(dir.path().join("examples/blink")).unwrap();
+        assert!(matches!(
+            setup_failure(&dir, "run", written()),
+            setup::Missing::Example { ref regbase, .. } if regbase == "blink"
+        ));
+    }
+
+    #[test]
+    fn fails_an_oversized_output_without_reading_it() {
+        let dir = fixture("max_output_size: 4\n");
+        let runner = Arc::new(ScriptedRunner::new(vec![Scripted::writing(
+            "out.txt",
+            "on and on\n",
+        )]));
+        let ctx = context(dir.path(), runner);
+        let config = config::load(&ctx.target, "blink").unwrap();
+        let result = execute_regression(&ctx, "run", &config, &mut ResetPrompt::Always);
+        assert_eq!(
+            result.status,
+            Status::Failed {
+                diff_summary: "generated output exceeds size limit (10 B > 4 B)".to_string()
+            }
+        );
+        assert_eq!(result.output, ctx.results()[0].output);
+        let results = ctx.results();
+        assert_eq!(results[0].output_size, Some(10));
+        assert!(results[0].diff.is_empty());
+        assert!(
+            results[0]
+                .output
+                .contains("(generated output exceeds size limit (10 B > 4 B))"),
+            "{}",
+            results[0].output
+        );
+    }
+
+    #[test]
+    fn reports_a_timeout() {
+        let outcomes = run_blink(
+            "timeout: 5\n",
+            vec![Scripted {
+                timed_out: true,
+                ..Default::default()
+            }],
+        );
+        let err = error_of(&outcomes[0]);
+        assert!(
+            err.starts_with("regression command timed out after 5s"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn records_the_regressions_left_when_the_budget_is_spent() {
+        let dir = fixture("");
+        let runner = Arc::new(ScriptedRunner::new(vec![Scripted::writing(
+            "out.txt", "on\n",
+        )]));
+        let mut ctx = context(dir.path(), runner.clone());
+        ctx.deadline = Some(Deadline {
+            at: std::time::Instant::now(),
+            budget: std::time::Duration::from_secs(60),
+            hard: false,
+        });
+        let since = changes::Since::default();
+        run_regressions(&ctx, &requested_names("blink"), false, false, false, &since).unwrap();
+        assert!(runner.commands().is_empty());
+        let results = std::mem::take(&mut *ctx.results());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, results::NOT_RUN);
+        assert!(results[0].error.contains("budget of 1m"));
+
+        // The next run of the failed regressions takes it up
+        history::save(&ctx.target, "20240102T030405Z-1", &results).unwrap();
+        ctx.deadline = None;
+        run_regressions(&ctx, &requested_names("blink"), false, false, true, &since).unwrap();
+        assert_eq!(runner.commands().len(), 1);
+        assert_eq!(ctx.results()[0].status, "passed");
+    }
+
+    #[test]
+    fn runs_the_failure_hooks_without_changing_the_status() {
+        let dir = fixture("on_fail: ./collect_debug.sh\n");
+        let runner = Arc::new(ScriptedRunner::new(vec![
+            Scripted::writing("out.txt", "off\n"),
+            Scripted {
+                code: 2,
+                stderr: "vivado.log not found\n".to_string(),
+                ..Default::default()
+            },
+            Scripted::exit(0),
+        ]));
+        let mut ctx = context(dir.path(), runner.clone());
+        ctx.on_fail = Some("tar czf debug.tgz *.log".to_string());
+        let config = config::load(&ctx.target, "blink").unwrap();
+        let result = execute_regression(&ctx, "run", &config, &mut ResetPrompt::Always);
+        assert_eq!(result.status.label(), "failed");
+        assert_eq!(ctx.results()[0].status, "failed");
+        assert_eq!(
+            runner.commands()[1..],
+            ["sh -c ./collect_debug.sh", "sh -c tar czf debug.tgz *.log"]
+        );
+
+        // A passing regression runs no hook
+        let runner = Arc::new(ScriptedRunner::new(vec![Scripted::writing(
+            "out.txt", "on\n",
+        )]));
+        let mut ctx = context(dir.path(), runner.clone());
+        ctx.on_fail = Some("tar czf debug.tgz *.log".to_string());
+        let result = execute_regression(&ctx, "run", &config, &mut ResetPrompt::Always);
+        assert_eq!(result.status, Status::Passed);
+        assert_eq!(runner.commands().len(), 1);
+    }
+
+    #[test]
+    fn reports_the_failed_clones() {
+        let dir = TempDir::new("bmregression-clone").unwrap();
+        let fetch = |scripted: Scripted| {
+            let runner = Arc::new(ScriptedRunner::new(vec![scripted]));
+            let options = repository::CloneOptions {
+                timeout: Some(std::time::Duration::from_secs(1)),
+                retries: 0,
+                progress: false,
+                runner: runner.clone(),
+            };
+            let dest = dir.path().join("examples");
+            let result = repository::fetch(
+                "https://example.invalid/bmexamples.git",
+                &dest,
+                "examples",
+                &options,
+                false,
+            );
+            assert_eq!(
+                runner.commands(),
+                vec![format!(
+                    "git clone https://example.invalid/bmexamples.git {}",
+                    dest.display()
+                )]
+            );
+            result.unwrap_err().to_string()
+        };
+        assert!(fetch(Scripted {
+            timed_out: true,
+            ..Default::default()
+        })
+        .ends_with("git clone timed out after 1s"));
+        assert!(fetch(Scripted {
+            code: 128,
+            stderr: "Cloning into 'examples'...\nfatal: repository not found\n".to_string(),
+            ..Default::default()
+        })
+        .ends_with("git clone failed:\n  fatal: repository not found"));
+    }
 }
@@ -0,0 +1,160 @@
+//! Metrics of a run in the Prometheus text exposition format.
+//!
+//! The file is meant for the node_exporter textfile collector, which may read
+//! it at any time, so it is written to a temporary file and renamed into place.
+
+use std::io;
+use std::path::Path;
+
+use crate::atomic;
+use crate::notify;
+use crate::results::{self, RunReport};
+
+/// Escapes a label value of the exposition format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Renders the metrics of a run.
+pub fn render(report: &RunReport) -> String {
+    let mut out = String::new();
+    out.push_str(
+        "# HELP bmregression_result Result of the regression, 1 if passed and 0 otherwise.\n",
+    );
+    out.push_str("# TYPE bmregression_result gauge\n");
+    for result in &report.results {
+        let passed = results::is_pass(result.status);
+        out.push_str(&format!(
+            "bmregression_result{{regression=\"{}\",tags=\"{}\"}} {}\n",
+            escape_label(&result.name),
+            escape_label(&result.tags.join(",")),
+            if passed { 1 } else { 0 }
+        ));
+    }
+    out.push_str("# HELP bmregression_duration_seconds Time spent on the regression.\n");
+    out.push_str("# TYPE bmregression_duration_seconds gauge\n");
+    for result in &report.results {
+        out.push_str(&format!(
+            "bmregression_duration_seconds{{regression=\"{}\",tags=\"{}\"}} {:.3}\n",
+            escape_label(&result.name),
+            escape_label(&result.tags.join(",")),
+            result.duration.as_secs_f64()
+        ));
+    }
+    out.push_str("# HELP bmregression_regressions Number of regressions of the run by status.\n");
+    out.push_str("# TYPE bmregression_regressions gauge\n");
+    for status in ["passed", "failed", "error"] {
+        out.push_str(&format!(
+            "bmregression_regressions{{status=\"{}\"}} {}\n",
+            status,
+            report.count(status)
+        ));
+    }
+    out.push_str("# HELP bmregression_run_duration_seconds Duration of the whole run.\n");
+    out.push_str("# TYPE bmregression_run_duration_seconds gauge\n");
+    out.push_str(&format!(
+        "bmregression_run_duration_seconds {:.3}\n",
+        report.duration.as_secs_f64()
+    ));
+    // A label of its own, rather than one on every series, to correlate the metrics with the run
+    out.push_str("# HELP bmregression_run_info Identifier of the run, always 1.\n");
+    out.push_str("# TYPE bmregression_run_info gauge\n");
+    out.push_str(&format!(
+        "bmregression_run_info{{run_id=\"{}\"}} 1\n",
+        escape_label(&report.run_id)
+    ));
+    out
+}
+
+/// Writes the metrics of a run to a file, replacing it atomically.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write(report: &RunReport, path: &Path) -> io::Result<()> {
+    atomic::write(path, render(report))
+}
+
+/// Pushes the metrics of a run to a Prometheus Pushgateway, under the `bmregression` job.
+///
+/// # Errors
+///
+/// Returns an error if the push fails.
+pub fn push(report: &RunReport, url: &str, debug: bool) -> io::Result<()> {
+    let url = format!("{}/metrics/job/bmregression", url.trim_end_matches('/'));
+    notify::post(&url, "text/plain; version=0.0.4", &render(report), debug)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::RegressionResult;
+    use crate::timestamp::DateTime;
+    use std::time::Duration;
+
+    fn report() -> RunReport {
+        let result = |name: &str, tags: &[&str], status, millis| RegressionResult {
+            name: name.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            status,
+            duration: Duration::from_millis(millis),
+            ..Default::default()
+        };
+        RunReport {
+            run_id: "20240401T020000Z-3fa9c1".to_string(),
+            started: DateTime::from_compact("20240401T020000Z").unwrap(),
+            duration: Duration::from_millis(62_500),
+            environment: Default::default(),
+            results: vec![
+                result("blink", &["default", "fpga"], "passed", 1500),
+                result("counter", &["default"], "failed", 250),
+                result("uart \"v2\"", &[], "captured", 5),
+            ],
+        }
+    }
+
+    #[test]
+    fn renders_the_metrics_in_the_text_format() {
+        assert_eq!(
+            render(&report()),
+            "# HELP bmregression_result Result of the regression, 1 if passed and 0 otherwise.\n\
+             # TYPE bmregression_result gauge\n\
+             bmregression_result{regression=\"blink\",tags=\"default,fpga\"} 1\n\
+             bmregression_result{regression=\"counter\",tags=\"default\"} 0\n\
+             bmregression_result{regression=\"uart \\\"v2\\\"\",tags=\"\"} 1\n\
+             # HELP bmregression_duration_seconds Time spent on the regression.\n\
+             # TYPE bmregression_duration_seconds gauge\n\
+             bmregression_duration_seconds{regression=\"blink\",tags=\"default,fpga\"} 1.500\n\
+             bmregression_duration_seconds{regression=\"counter\",tags=\"default\"} 0.250\n\
+             bmregression_duration_seconds{regression=\"uart \\\"v2\\\"\",tags=\"\"} 0.005\n\
+             # HELP bmregression_regressions Number of regressions of the run by status.\n\
+             # TYPE bmregression_regressions gauge\n\
+             bmregression_regressions{status=\"passed\"} 1\n\
+             bmregression_regressions{status=\"failed\"} 1\n\
+             bmregression_regressions{status=\"error\"} 0\n\
+             # HELP bmregression_run_duration_seconds Duration of the whole run.\n\
+             # TYPE bmregression_run_duration_seconds gauge\n\
+             bmregression_run_duration_seconds 62.500\n\
+             # HELP bmregression_run_info Identifier of the run, always 1.\n\
+             # TYPE bmregression_run_info gauge\n\
+             bmregression_run_info{run_id=\"20240401T020000Z-3fa9c1\"} 1\n"
+        );
+    }
+
+    #[test]
+    fn escapes_the_label_values() {
+        assert_eq!(escape_label("a\\b\"c\nd"), "a\\\\b\\\"c\\nd");
+    }
+
+    #[test]
+    fn replaces_the_metrics_file() {
+        let dir = tempdir::TempDir::new("metrics").unwrap();
+        let path = dir.path().join("bmregression.prom");
+        std::fs::write(&path, "stale\n").unwrap();
+        write(&report(), &path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), render(&report()));
+    }
+}
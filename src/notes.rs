@@ -0,0 +1,109 @@
+//! Triage notes of the regressions.
+//!
+//! The `notes.md` file in the directory of a regression keeps what is known
+//! about its failures, one timestamped section per note, oldest first:
+//!
+//! ```markdown
+//! ## 2024-04-01 15:30:00 UTC
+//!
+//! waiting for bondmachine#456, fails since v0.9.2
+//! ```
+//!
+//! The notes live in the data repository, so they travel with the expected
+//! outputs and are reviewed with them.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::atomic;
+use crate::timestamp::DateTime;
+
+/// Name of the notes file, in the directory of a regression.
+pub const FILE: &str = "notes.md";
+
+/// Prefix of the heading starting a note.
+const HEADING: &str = "## ";
+
+/// A note about a regression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Note {
+    /// When the note was written, as in its heading
+    pub written: String,
+    /// Text of the note
+    pub text: String,
+}
+
+/// Loads the notes of a regression, oldest first, none if it has no notes file.
+///
+/// Anything before the first heading, such as a title, is ignored.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read.
+pub fn load(dir: &Path) -> io::Result<Vec<Note>> {
+    let content = match fs::read_to_string(dir.join(FILE)) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut notes: Vec<Note> = Vec::new();
+    for line in content.lines() {
+        if let Some(written) = line.strip_prefix(HEADING) {
+            notes.push(Note {
+                written: written.trim().to_string(),
+                text: String::new(),
+            });
+        } else if let Some(note) = notes.last_mut() {
+            note.text.push_str(line);
+            note.text.push('\n');
+        }
+    }
+    for note in &mut notes {
+        note.text = note.text.trim().to_string();
+    }
+    Ok(notes)
+}
+
+/// Returns the most recent note of a regression, None if it has none or they cannot be read.
+pub fn latest(dir: &Path) -> Option<Note> {
+    load(dir).ok()?.pop()
+}
+
+/// Appends a note written at `now` to the notes file of a regression, creating it if needed.
+///
+/// # Errors
+///
+/// Returns an error if the text is empty, if one of its lines would be read as the heading
+/// of another note, or if the file cannot be written.
+pub fn append(dir: &Path, text: &str, now: DateTime) -> io::Result<Note> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(io::Error::other("the note is empty"));
+    }
+    if text.lines().any(|line| line.starts_with(HEADING)) {
+        return Err(io::Error::other(format!(
+            "a line of the note cannot start with {:?}, it would start another note",
+            HEADING
+        )));
+    }
+    let path = dir.join(FILE);
+    let mut content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    if !content.is_empty() {
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push('\n');
+    }
+    let note = Note {
+        written: format!("{} {} UTC", now.date(), now.time()),
+        text: text.to_string(),
+    };
+    content.push_str(&format!("{}{}\n\n{}\n", HEADING, note.written, note.text));
+    atomic::write(&path, content)?;
+    Ok(note)
+}
@@ -0,0 +1,314 @@
+//! Notifications sent to a webhook or to the desktop when a run completes.
+//!
+//! The payload is compatible with Slack and Mattermost incoming webhooks, which
+//! only look at the `text` field, while the other fields carry the summary in a
+//! form that other receivers can process. It is sent with `curl`, so that no
+//! HTTP client is needed in the tool itself.
+//!
+//! Desktop notifications go through `notify-send` on Linux and `osascript` on
+//! macOS, the tools talking to the notification daemon of the session. Without
+//! them, or without a daemon, the terminal bell rings and the summary is printed.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crate::report::json_string;
+use crate::results::RunReport;
+use crate::runner::format_elapsed;
+
+/// When the webhook notification is sent.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum NotifyOn {
+    /// Only when at least one regression failed or errored
+    Failure,
+    /// After every run
+    Always,
+}
+
+/// Maximum time allowed for the webhook request, in seconds.
+const WEBHOOK_TIMEOUT: &str = "30";
+
+/// Returns the names of the regressions that failed or errored.
+fn failing(report: &RunReport) -> Vec<&str> {
+    report
+        .results
+        .iter()
+        .filter(|r| r.status == "failed" || r.status == "error")
+        .map(|r| r.name.as_str())
+        .collect()
+}
+
+/// Returns true if the notification must be sent for this run.
+pub fn should_notify(report: &RunReport, on: NotifyOn) -> bool {
+    on == NotifyOn::Always || !failing(report).is_empty()
+}
+
+/// Builds the JSON payload describing a run.
+pub fn payload(report: &RunReport) -> String {
+    let failing = failing(report);
+    let passed = report.count("passed");
+    let failed = report.count("failed");
+    let errors = report.count("error");
+    let duration = format_elapsed(report.duration);
+
+    let mut text = format!(
+        "bmregression run {}: {} passed, {} failed{}, {} errors in {}",
+        report.run_id,
+        passed,
+        failed,
+        report.quarantined_note(),
+        errors,
+        duration
+    );
+    if !failing.is_empty() {
+        text.push_str(&format!("\nFailing: {}", failing.join(", ")));
+    }
+
+    let environment = &report.environment;
+    let failing: Vec<String> = failing.iter().map(|name| json_string(name)).collect();
+    let host: Vec<(String, String)> = environment
+        .host()
+        .into_iter()
+        .map(|(field, value)| (field.to_string(), value))
+        .collect();
+
+    format!(
+        "{{\"text\": {}, \"run_id\": {}, \"summary\": {{\"total\": {}, \"passed\": {}, \"failed\": {}, \"errors\": {}}}, \"failing\": [{}], \"duration_seconds\": {}, \"commits\": {}, \"environment\": {{\"host\": {}, \"toolchain\": {}, \"options\": {}}}}}",
+        json_string(&text),
+        json_string(&report.run_id),
+        report.results.len(),
+        passed,
+        failed,
+        errors,
+        failing.join(", "),
+        report.duration.as_secs(),
+        json_object(&environment.commits),
+        json_object(&host),
+        json_object(&environment.toolchain),
+        json_object(&environment.options)
+    )
+}
+
+/// Renders (key, value) pairs as a JSON object of strings.
+fn json_object(pairs: &[(String, String)]) -> String {
+    let fields: Vec<String> = pairs
+        .iter()
+        .map(|(key, value)| format!("{}: {}", json_string(key), json_string(value)))
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+/// Variables set by the CI services, where there is no desktop to notify.
+const CI_VARIABLES: [&str; 7] = [
+    "CI",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "JENKINS_URL",
+    "BUILDKITE",
+    "TF_BUILD",
+    "TEAMCITY_VERSION",
+];
+
+/// Returns true when running in a CI job.
+pub fn in_ci() -> bool {
+    CI_VARIABLES
+        .iter()
+        .any(|variable| std::env::var_os(variable).is_some_and(|value| !value.is_empty()))
+}
+
+/// Returns the title, the body and the urgency of the desktop notification of a run, the
+/// urgency being `critical` when regressions failed or errored, `normal` when some
+/// failures are quarantined and `low` otherwise.
+pub fn desktop_message(report: &RunReport) -> (String, String, &'static str) {
+    let failing = failing(report);
+    let urgency = if !failing.is_empty() {
+        "critical"
+    } else if report.count("quarantined failure") > 0 {
+        "normal"
+    } else {
+        "low"
+    };
+    let title = if failing.is_empty() {
+        format!(
+            "bmregression: {} regressions passed",
+            report.count("passed")
+        )
+    } else {
+        format!("bmregression: {} regressions failing", failing.len())
+    };
+    let mut body = format!(
+        "{} passed, {} failed{}, {} errors in {}",
+        report.count("passed"),
+        report.count("failed"),
+        report.quarantined_note(),
+        report.count("error"),
+        format_elapsed(report.duration)
+    );
+    if !failing.is_empty() {
+        body.push_str(&format!("\nFailing: {}", failing.join(", ")));
+    }
+    (title, body, urgency)
+}
+
+/// Quotes a text as an AppleScript string.
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Shows a notification on the desktop of the session.
+///
+/// # Errors
+///
+/// Returns an error if there is no desktop session, no tool to notify it, or the
+/// notification daemon cannot be reached.
+pub fn desktop(title: &str, body: &str, urgency: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            applescript_string(body),
+            applescript_string(title)
+        ));
+        command
+    } else {
+        let session = ["DBUS_SESSION_BUS_ADDRESS", "DISPLAY", "WAYLAND_DISPLAY"]
+            .iter()
+            .any(|variable| std::env::var_os(variable).is_some());
+        if !session {
+            return Err(io::Error::other("no desktop session"));
+        }
+        let mut command = Command::new("notify-send");
+        command
+            .arg("--app-name=bmregression")
+            .arg(format!("--urgency={}", urgency))
+            .arg(title)
+            .arg(body);
+        command
+    };
+    let output = command.stdin(Stdio::null()).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed: {}",
+            command.get_program().to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Posts a JSON payload to a webhook.
+///
+/// # Errors
+///
+/// Returns an error if `curl` cannot be executed or the request fails.
+pub fn send(url: &str, payload: &str, debug: bool) -> io::Result<()> {
+    post(url, "application/json", payload, debug)
+}
+
+/// Posts a body to a URL with `curl`.
+///
+/// # Errors
+///
+/// Returns an error if `curl` cannot be executed or the request fails.
+pub fn post(url: &str, content_type: &str, body: &str, debug: bool) -> io::Result<()> {
+    if debug {
+        println!("Posting to {}: {}", url, body);
+    }
+    let mut curl = Command::new("curl")
+        .arg("--silent")
+        .arg("--show-error")
+        .arg("--fail")
+        .arg("--max-time")
+        .arg(WEBHOOK_TIMEOUT)
+        .arg("-X")
+        .arg("POST")
+        .arg("-H")
+        .arg(format!("Content-Type: {}", content_type))
+        .arg("--data-binary")
+        .arg("@-")
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = curl.stdin.take() {
+        stdin.write_all(body.as_bytes())?;
+    }
+    let output = curl.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "curl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{Environment, RegressionResult};
+    use crate::timestamp::DateTime;
+    use std::time::Duration;
+
+    fn report(statuses: &[(&str, &'static str)]) -> RunReport {
+        RunReport {
+            run_id: "20240401T020000Z-3fa9c1".to_string(),
+            started: DateTime::from_compact("20240401T020000Z").unwrap(),
+            duration: Duration::from_secs(125),
+            environment: Environment {
+                hostname: Some("builder".to_string()),
+                os_release: "Linux 6.1".to_string(),
+                cpus: 8,
+                commits: vec![("examples".to_string(), "3fa9c1".to_string())],
+                toolchain: vec![("git".to_string(), "git version 2.43.0".to_string())],
+                options: Vec::new(),
+            },
+            results: statuses
+                .iter()
+                .map(|(name, status)| RegressionResult {
+                    name: name.to_string(),
+                    status,
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn notifies_the_failures_or_every_run() {
+        let passing = report(&[("blink", "passed"), ("counter", "quarantined failure")]);
+        assert!(!should_notify(&passing, NotifyOn::Failure));
+        assert!(should_notify(&passing, NotifyOn::Always));
+        for status in ["failed", "error"] {
+            let failing = report(&[("blink", "passed"), ("counter", status)]);
+            assert!(should_notify(&failing, NotifyOn::Failure), "{}", status);
+        }
+    }
+
+    #[test]
+    fn describes_the_run_in_the_payload() {
+        let failing = report(&[
+            ("blink", "passed"),
+            ("counter", "failed"),
+            ("uart", "error"),
+        ]);
+        assert_eq!(
+            payload(&failing),
+            "{\"text\": \"bmregression run 20240401T020000Z-3fa9c1: 1 passed, 1 failed, 1 errors in 2m\\n\
+             Failing: counter, uart\", \"run_id\": \"20240401T020000Z-3fa9c1\", \
+             \"summary\": {\"total\": 3, \"passed\": 1, \"failed\": 1, \"errors\": 1}, \
+             \"failing\": [\"counter\", \"uart\"], \"duration_seconds\": 125, \
+             \"commits\": {\"examples\": \"3fa9c1\"}, \"environment\": {\"host\": \
+             {\"hostname\": \"builder\", \"os\": \"Linux 6.1\", \"cpus\": \"8\"}, \
+             \"toolchain\": {\"git\": \"git version 2.43.0\"}, \"options\": {}}}"
+        );
+        let passing = payload(&report(&[("blink", "passed")]));
+        assert!(
+            passing.starts_with("{\"text\": \"bmregression run 20240401T020000Z-3fa9c1: 1 passed, 0 failed, 0 errors in 2m\", "),
+            "{}",
+            passing
+        );
+        assert!(passing.contains("\"failing\": [], "), "{}", passing);
+    }
+}
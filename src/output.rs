@@ -0,0 +1,93 @@
+//! Output of the regressions, printed in one piece when each one completes.
+//!
+//! While a regression is executed, the lines it prints (its status, its
+//! differences, its warnings and its error) are collected with [`say!`] instead
+//! of being written right away, then printed at once by the caller and kept in
+//! its result. The output of a regression is thus never interleaved with that of
+//! another one, and can be reordered or filtered. Outside of a regression
+//! [`say!`] prints directly.
+//!
+//! The debug lines and the progress of the commands are printed immediately,
+//! prefixed with the name of the regression, see [`trace`].
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+
+use crate::redact;
+
+/// Lines of the regression being executed, and how much of them has already been printed
+#[derive(Default)]
+struct Buffer {
+    text: String,
+    printed: usize,
+}
+
+impl Buffer {
+    /// Prints the lines not printed yet.
+    fn print_pending(&mut self) {
+        print!("{}", &self.text[self.printed..]);
+        let _ = io::stdout().flush();
+        self.printed = self.text.len();
+    }
+}
+
+thread_local! {
+    /// Lines collected for the regression being executed by this thread, None if none is
+    static BUFFER: RefCell<Option<Buffer>> = const { RefCell::new(None) };
+}
+
+/// Prints a line, or collects it if a regression is being executed.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        $crate::output::line(&format!($($arg)*))
+    };
+}
+
+/// Prints a line, or collects it if a regression is being executed. See [`say!`].
+pub fn line(text: &str) {
+    let text = redact::text(text);
+    BUFFER.with(|buffer| match buffer.borrow_mut().as_mut() {
+        Some(buffer) => {
+            buffer.text.push_str(&text);
+            buffer.text.push('\n');
+        }
+        None => println!("{}", text),
+    });
+}
+
+/// Starts collecting the lines of a regression.
+pub fn capture() {
+    BUFFER.with(|buffer| *buffer.borrow_mut() = Some(Buffer::default()));
+}
+
+/// Prints the lines collected so far, before asking a question about the regression.
+///
+/// The lines are still collected, and the following ones too.
+pub fn flush() {
+    BUFFER.with(|buffer| {
+        if let Some(buffer) = buffer.borrow_mut().as_mut() {
+            buffer.print_pending();
+        }
+    });
+}
+
+/// Stops collecting the lines of a regression.
+///
+/// # Returns
+///
+/// All the lines collected since [`capture`], to be kept with the result of the regression,
+/// and the length of those already printed by [`flush`]. The others are printed with the
+/// result, see [`crate::present`].
+pub fn finish() -> (String, usize) {
+    BUFFER.with(|buffer| match buffer.borrow_mut().take() {
+        Some(buffer) => (buffer.text, buffer.printed),
+        None => (String::new(), 0),
+    })
+}
+
+/// Prints a debug line immediately, each of its lines prefixed with the regression name.
+pub fn trace(regression_name: &str, text: &str) {
+    for line in redact::text(text).lines() {
+        println!("[{}] {}", regression_name, line);
+    }
+}
@@ -0,0 +1,347 @@
+//! Checks on the paths taken from the regression configurations.
+//!
+//! The data repository accepts external contributions, so the relative paths of
+//! a configuration must not be able to point outside the directories they are
+//! meant for, neither with `..` components nor through symbolic links.
+
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// The number of symbolic links followed in a path, as the usual `MAXSYMLINKS`.
+const MAX_LINKS: usize = 40;
+
+/// Resolves a path that may not exist yet.
+///
+/// The components are walked in order, from the current directory for a relative
+/// path. Symbolic links are followed, dangling ones too since writing to them creates
+/// their target, and `..` goes back from what was resolved so far, as the system does.
+/// The missing components are taken as the directories they will become, so that a
+/// `..` after one of them still goes through the links before it.
+pub fn resolve(path: &Path) -> io::Result<PathBuf> {
+    let start = if path.is_absolute() {
+        PathBuf::new()
+    } else {
+        std::env::current_dir()?.canonicalize()?
+    };
+    follow(start, path, &mut 0)
+}
+
+/// Resolves `path` from the resolved directory `resolved`, counting the links followed.
+fn follow(mut resolved: PathBuf, path: &Path, links: &mut usize) -> io::Result<PathBuf> {
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => resolved.push(component),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(name) => {
+                let next = resolved.join(name);
+                match next.symlink_metadata() {
+                    Ok(metadata) if metadata.file_type().is_symlink() => {
+                        *links += 1;
+                        if *links > MAX_LINKS {
+                            return Err(io::Error::other(format!(
+                                "too many levels of symbolic links in {}",
+                                path.display()
+                            )));
+                        }
+                        let target = next.read_link()?;
+                        resolved = follow(resolved, &target, links)?;
+                    }
+                    _ => resolved = next,
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+/// Resolves the example directory of a `regbase` in the examples checkout.
+///
+/// The regbase may have several components, separated by `/` or `\\`, which are
+/// joined to the checkout; empty and `.` components are ignored. Unlike the other
+/// paths, a regbase can never point outside the checkout.
+///
+/// # Errors
+///
+/// Returns an error if the regbase is empty or absolute, or resolves outside the checkout.
+pub fn regbase_dir(source: &str, regbase: &str) -> io::Result<PathBuf> {
+    // Including Windows drive letters, such as C:\\examples
+    let bytes = regbase.as_bytes();
+    let absolute = regbase.starts_with(['/', '\\'])
+        || (bytes.len() > 1 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':');
+    if absolute {
+        return Err(io::Error::other(format!(
+            "regbase {} is absolute, expected a path inside the examples",
+            regbase
+        )));
+    }
+    let mut dir = PathBuf::from(source);
+    let mut components = 0;
+    for component in regbase.split(['/', '\\']) {
+        if !component.is_empty() && component != "." {
+            dir.push(component);
+            components += 1;
+        }
+    }
+    if components == 0 {
+        return Err(io::Error::other("regbase is empty"));
+    }
+    let base_resolved = resolve(Path::new(source))?;
+    let resolved = resolve(&dir)?;
+    if resolved == base_resolved || !resolved.starts_with(&base_resolved) {
+        return Err(io::Error::other(format!(
+            "regbase {} resolves to {}, outside the examples {}",
+            regbase,
+            resolved.display(),
+            base_resolved.display()
+        )));
+    }
+    Ok(resolved)
+}
+
+/// Checks that `base/relative` resolves inside `base`.
+///
+/// # Errors
+///
+/// Returns an error naming the configuration key if the path escapes `base`.
+pub fn check_within(base: &str, relative: &str, key: &str) -> io::Result<()> {
+    let base_resolved = resolve(Path::new(base))?;
+    let resolved = resolve(&Path::new(base).join(relative))?;
+    if resolved.starts_with(&base_resolved) {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} {} resolves to {}, outside {} (use --allow-external-paths to allow it)",
+            key,
+            relative,
+            resolved.display(),
+            base_resolved.display()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempdir::TempDir;
+
+    /// Creates the examples checkout `examples` with the example `blink`, and a directory
+    /// `outside` next to it.
+    fn checkout() -> (TempDir, String) {
+        let dir = TempDir::new("paths").unwrap();
+        fs::create_dir_all(dir.path().join("examples/blink/src")).unwrap();
+        fs::create_dir_all(dir.path().join("outside")).unwrap();
+        let examples = dir.path().join("examples").to_str().unwrap().to_string();
+        (dir, examples)
+    }
+
+    #[test]
+    fn resolves_the_missing_components_lexically() {
+        let (dir, examples) = checkout();
+        let root = dir.path().canonicalize().unwrap();
+        let examples = Path::new(&examples);
+        assert_eq!(
+            resolve(&examples.join("blink/src")).unwrap(),
+            root.join("examples/blink/src")
+        );
+        assert_eq!(
+            resolve(&examples.join("blink/new/./a/../b")).unwrap(),
+            root.join("examples/blink/new/b")
+        );
+        assert_eq!(
+            resolve(&examples.join("new/..")).unwrap(),
+            root.join("examples")
+        );
+        // Out of the existing ancestor, then of the checkout
+        assert_eq!(
+            resolve(&examples.join("new/../../outside/x")).unwrap(),
+            root.join("outside/x")
+        );
+        assert_eq!(
+            resolve(&examples.join("blink/../../new")).unwrap(),
+            root.join("new")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolves_through_the_symbolic_links() {
+        let (dir, examples) = checkout();
+        let root = dir.path().canonicalize().unwrap();
+        let examples = Path::new(&examples);
+        std::os::unix::fs::symlink(dir.path().join("outside"), examples.join("link")).unwrap();
+        assert_eq!(
+            resolve(&examples.join("link/new/out.txt")).unwrap(),
+            root.join("outside/new/out.txt")
+        );
+        // Lexically, link/.. would be the checkout
+        assert_eq!(resolve(&examples.join("link/..")).unwrap(), root);
+        // Through the link once new is created, whatever its ..
+        assert_eq!(
+            resolve(&examples.join("new/../link/x")).unwrap(),
+            root.join("outside/x")
+        );
+        assert_eq!(
+            resolve(&examples.join("new/a/../../link/../examples")).unwrap(),
+            root.join("examples")
+        );
+    }
+
+    #[test]
+    fn joins_the_regbase_components_to_the_checkout() {
+        let (_dir, examples) = checkout();
+        let blink = Path::new(&examples).canonicalize().unwrap().join("blink");
+        for regbase in ["blink", "./blink", "blink/", "blink//.", "new/../blink"] {
+            assert_eq!(
+                regbase_dir(&examples, regbase).unwrap(),
+                blink,
+                "{}",
+                regbase
+            );
+        }
+        assert_eq!(
+            regbase_dir(&examples, "blink/src").unwrap(),
+            blink.join("src")
+        );
+        assert_eq!(
+            regbase_dir(&examples, "blink\\src").unwrap(),
+            blink.join("src")
+        );
+        // Not created yet, as before a fetch
+        assert_eq!(
+            regbase_dir(&examples, "new").unwrap(),
+            blink.with_file_name("new")
+        );
+    }
+
+    #[test]
+    fn rejects_the_regbases_outside_the_checkout() {
+        let (_dir, examples) = checkout();
+        for regbase in [
+            "",
+            ".",
+            "./",
+            "/",
+            "/tmp",
+            "\\tmp",
+            "C:\\examples",
+            "c:/examples",
+            "..",
+            "../outside",
+            "blink/..",
+            "blink/../..",
+            "blink/src/../../../outside",
+            "new/../../outside",
+            // src does not exist at the top of the checkout, its .. is taken lexically
+            "src/../../blink",
+            "..\\outside",
+            "blink\\..\\..\\outside",
+        ] {
+            assert!(regbase_dir(&examples, regbase).is_err(), "{}", regbase);
+        }
+        let err = regbase_dir(&examples, "../outside").unwrap_err();
+        assert!(err.to_string().contains("outside the examples"), "{}", err);
+        let err = regbase_dir(&examples, "/tmp").unwrap_err();
+        assert!(err.to_string().contains("is absolute"), "{}", err);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_the_regbases_linked_outside_the_checkout() {
+        let (dir, examples) = checkout();
+        let examples_path = Path::new(&examples);
+        std::os::unix::fs::symlink(dir.path().join("outside"), examples_path.join("link")).unwrap();
+        std::os::unix::fs::symlink(examples_path.join("blink"), examples_path.join("alias"))
+            .unwrap();
+        assert!(regbase_dir(&examples, "link").is_err());
+        assert!(regbase_dir(&examples, "link/new").is_err());
+        // A link within the checkout is followed
+        assert_eq!(
+            regbase_dir(&examples, "alias/src").unwrap(),
+            examples_path.canonicalize().unwrap().join("blink/src")
+        );
+    }
+
+    #[test]
+    fn checks_the_paths_within_their_base() {
+        let (dir, examples) = checkout();
+        let blink = dir.path().join("examples/blink");
+        let blink = blink.to_str().unwrap();
+        for relative in [
+            "out.txt",
+            "src/main.c",
+            "./out.txt",
+            "src/../out.txt",
+            "new/dir/out.txt",
+            "new/../out.txt",
+            ".",
+        ] {
+            assert!(
+                check_within(blink, relative, "sourcedata").is_ok(),
+                "{}",
+                relative
+            );
+        }
+        for relative in [
+            "..",
+            "../out.txt",
+            "src/../../out.txt",
+            "new/../../out.txt",
+            "new/dir/../../../outside",
+            "/tmp/out.txt",
+        ] {
+            assert!(
+                check_within(blink, relative, "sourcedata").is_err(),
+                "{}",
+                relative
+            );
+        }
+        // The base itself may contain ..
+        let dotted = format!("{}/blink/src/..", examples);
+        assert!(check_within(&dotted, "out.txt", "workdir").is_ok());
+        assert!(check_within(&dotted, "../out.txt", "workdir").is_err());
+
+        let err = check_within(blink, "../../outside", "targetdata").unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.starts_with("targetdata ../../outside resolves to "),
+            "{}",
+            message
+        );
+        assert!(message.contains("--allow-external-paths"), "{}", message);
+    }
+
+    #[test]
+    fn takes_the_windows_separators_as_the_platform_does() {
+        let (dir, _examples) = checkout();
+        let blink = dir.path().join("examples/blink");
+        let blink = blink.to_str().unwrap();
+        // A single file name on Unix, parent components on Windows
+        let escapes = check_within(blink, "..\\..\\outside", "sourcedata").is_err();
+        assert_eq!(escapes, cfg!(windows));
+        assert!(check_within(blink, "src\\main.c", "sourcedata").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_the_paths_linked_outside_their_base() {
+        let (dir, _examples) = checkout();
+        let blink = dir.path().join("examples/blink");
+        std::os::unix::fs::symlink(dir.path().join("outside"), blink.join("link")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("outside/out.txt"), blink.join("out.txt"))
+            .unwrap();
+        std::os::unix::fs::symlink(blink.join("src"), blink.join("alias")).unwrap();
+        let blink = blink.to_str().unwrap();
+        assert!(check_within(blink, "link", "sourcedata").is_err());
+        assert!(check_within(blink, "link/new/out.txt", "sourcedata").is_err());
+        assert!(check_within(blink, "new/../link/out.txt", "sourcedata").is_err());
+        // Dangling, writing to it would create its target
+        assert!(check_within(blink, "out.txt", "targetdata").is_err());
+        std::os::unix::fs::symlink("loop", Path::new(blink).join("loop")).unwrap();
+        assert!(check_within(blink, "loop", "sourcedata").is_err());
+        assert!(check_within(blink, "alias/main.c", "sourcedata").is_ok());
+    }
+}
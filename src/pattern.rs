@@ -0,0 +1,246 @@
+//! Regular expressions of the pattern baselines, the masks and the ignored lines.
+//!
+//! The `regex_lines` comparison matches each line of the generated output
+//! against a hand-written pattern, and the masks of a regression replace the
+//! matches of their patterns in each line of both outputs, and its ignore
+//! patterns leave out the lines they are found in. The patterns come from the
+//! data repository, which may not be trusted, and run on every line of the
+//! outputs: they are matched with the `regex` crate, in a time linear in the
+//! length of the line whatever the pattern.
+//!
+//! The documented syntax is the common subset: literals, `.`, classes such as
+//! `[a-z_]` and `[^0-9]`, the escapes `\d`, `\w`, `\s` (and their negations
+//! `\D`, `\W`, `\S`), `\t` and escaped metacharacters, the anchors `^` and `$`,
+//! groups `(...)` and `(?:...)` with alternatives `|`, and the greedy
+//! quantifiers `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}`, lazy when followed by
+//! `?`. A literal `{` is escaped.
+
+use regex::Regex;
+
+/// A compiled pattern.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    /// The pattern, found anywhere in a text
+    found: Regex,
+    /// The pattern, matching a whole text
+    whole: Regex,
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Pattern) -> bool {
+        self.found.as_str() == other.found.as_str()
+    }
+}
+
+/// Returns the error of a pattern on one line, without the excerpt of the pattern the
+/// `regex` crate draws above it.
+fn describe_error(err: regex::Error) -> String {
+    match err {
+        regex::Error::Syntax(syntax) => syntax
+            .lines()
+            .rev()
+            .find_map(|line| line.trim().strip_prefix("error: "))
+            .unwrap_or(syntax.trim())
+            .to_string(),
+        regex::Error::CompiledTooBig(_) => "pattern too large".to_string(),
+        err => err.to_string(),
+    }
+}
+
+impl Pattern {
+    /// Compiles a pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first syntax error.
+    pub fn new(pattern: &str) -> Result<Pattern, String> {
+        let found = Regex::new(pattern).map_err(describe_error)?;
+        let whole = Regex::new(&format!("^(?:{})$", pattern)).map_err(describe_error)?;
+        Ok(Pattern { found, whole })
+    }
+
+    /// Returns true if the pattern matches the whole text.
+    pub fn matches(&self, text: &str) -> bool {
+        self.whole.is_match(text)
+    }
+
+    /// Returns true if the pattern matches anywhere in the text.
+    pub fn is_found_in(&self, text: &str) -> bool {
+        self.found.is_match(text)
+    }
+
+    /// Replaces the matches of the pattern in the text, from left to right, without
+    /// overlapping. Empty matches are not replaced.
+    pub fn replace_all(&self, text: &str, replacement: &str) -> String {
+        let mut replaced = String::new();
+        let mut pos = 0;
+        for found in self.found.find_iter(text).filter(|found| !found.is_empty()) {
+            replaced.push_str(&text[pos..found.start()]);
+            replaced.push_str(replacement);
+            pos = found.end();
+        }
+        replaced.push_str(&text[pos..]);
+        replaced
+    }
+}
+
+/// A line of the generated output that does not match its pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// Number of the line, from 1
+    pub line: usize,
+    pub pattern: String,
+    pub actual: String,
+}
+
+/// Result of matching the lines of an output against the lines of patterns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineMatch {
+    pub pattern_lines: usize,
+    pub output_lines: usize,
+    /// Lines not matching their pattern, among those present in both
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl LineMatch {
+    /// Returns true if the output has as many lines as the patterns, each matching its pattern.
+    pub fn passes(&self) -> bool {
+        self.pattern_lines == self.output_lines && self.mismatches.is_empty()
+    }
+
+    /// Renders the mismatches as a diff, the pattern removed and the actual line added.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for mismatch in &self.mismatches {
+            out.push_str(&format!(
+                "@@ line {} @@\n-{}\n+{}\n",
+                mismatch.line, mismatch.pattern, mismatch.actual
+            ));
+        }
+        if self.pattern_lines != self.output_lines {
+            out.push_str(&format!(
+                "@@ {} pattern lines, {} output lines @@\n",
+                self.pattern_lines, self.output_lines
+            ));
+        }
+        out
+    }
+}
+
+/// Matches each line of an output against the pattern on the same line of `patterns`.
+///
+/// # Errors
+///
+/// Returns an error naming the line of the first invalid pattern.
+pub fn match_lines(patterns: &str, output: &str) -> Result<LineMatch, String> {
+    let patterns: Vec<&str> = patterns.lines().collect();
+    let lines: Vec<&str> = output.lines().collect();
+    let mut mismatches = Vec::new();
+    for (index, pattern) in patterns.iter().enumerate() {
+        let compiled = Pattern::new(pattern)
+            .map_err(|err| format!("line {}: invalid pattern {:?}: {}", index + 1, pattern, err))?;
+        match lines.get(index) {
+            Some(line) if !compiled.matches(line) => mismatches.push(Mismatch {
+                line: index + 1,
+                pattern: pattern.to_string(),
+                actual: line.to_string(),
+            }),
+            _ => {}
+        }
+    }
+    Ok(LineMatch {
+        pattern_lines: patterns.len(),
+        output_lines: lines.len(),
+        mismatches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_supported_syntax() {
+        let cases = [
+            (r"out\.sv \(12 files\)", "out.sv (12 files)", true),
+            ("a.c", "abc", true),
+            ("[a-z_]+", "snake_case", true),
+            ("[^0-9]+", "abc1", false),
+            (r"\d{4}-\d{2}", "2024-03", true),
+            (r"\w+\s\W", "word !", true),
+            (r"\S+\D", "x1", false),
+            ("a\\tb", "a\tb", true),
+            ("(ab|cd)+", "abcdab", true),
+            ("(?:x|y){2,}", "x", false),
+            ("a{2}", "aa", true),
+            ("a{1,2}", "aaa", false),
+            (r"\{a\}", "{a}", true),
+            ("^abc$", "abc", true),
+        ];
+        for (pattern, text, matches) in cases {
+            let compiled = Pattern::new(pattern).unwrap();
+            assert_eq!(compiled.matches(text), matches, "{} on {}", pattern, text);
+        }
+        // Matching the whole text, whatever the alternatives
+        assert!(!Pattern::new("a|ab").unwrap().matches("abc"));
+        assert!(Pattern::new("a|ab").unwrap().matches("ab"));
+    }
+
+    #[test]
+    fn finds_and_replaces_the_matches() {
+        let pattern = Pattern::new("0x[0-9a-f]+").unwrap();
+        assert!(pattern.is_found_in("at 0xdead now"));
+        assert!(!pattern.is_found_in("at 0 now"));
+        assert_eq!(pattern.replace_all("0x1 and 0xff", "ADDR"), "ADDR and ADDR");
+        // Lazy quantifiers stop at the first possible end
+        let lazy = Pattern::new("<.+?>").unwrap();
+        assert_eq!(lazy.replace_all("<a><b>", "T"), "TT");
+        // Empty matches are not replaced
+        assert_eq!(Pattern::new("x*").unwrap().replace_all("axb", "-"), "a-b");
+    }
+
+    #[test]
+    fn reports_the_invalid_patterns() {
+        for pattern in ["(x", "x)", "[a-", "*a", r"\q", "a{2,1}"] {
+            let err = Pattern::new(pattern).unwrap_err();
+            assert!(
+                !err.is_empty() && !err.contains('\n'),
+                "{}: {}",
+                pattern,
+                err
+            );
+        }
+        assert_eq!(
+            match_lines("ok\n(x\n", "ok\n").unwrap_err(),
+            "line 2: invalid pattern \"(x\": unclosed group"
+        );
+    }
+
+    #[test]
+    fn matches_nested_quantifiers_in_linear_time() {
+        // A backtracking matcher takes exponential time on these
+        let pattern = Pattern::new("(a+)+b").unwrap();
+        let text = "a".repeat(10_000);
+        let started = std::time::Instant::now();
+        assert!(!pattern.matches(&text));
+        assert!(!pattern.is_found_in(&text));
+        assert_eq!(pattern.replace_all(&text, "x"), text);
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn matches_the_lines_against_their_patterns() {
+        let matched = match_lines("Elapsed: \\d+s\nok\n", "Elapsed: 12s\nko\nmore\n").unwrap();
+        assert!(!matched.passes());
+        assert_eq!(
+            matched.mismatches,
+            vec![Mismatch {
+                line: 2,
+                pattern: "ok".to_string(),
+                actual: "ko".to_string(),
+            }]
+        );
+        assert_eq!(matched.output_lines, 3);
+        assert!(match_lines("a+\n", "aaa\n").unwrap().passes());
+    }
+}
@@ -0,0 +1,299 @@
+//! Plans of runs, written by `plan` and executed by `apply`.
+//!
+//! A plan is a JSON document listing the regressions a run would execute, in
+//! order, with what they run and compare: the command, the comparison mode, the
+//! example directory and the generated and expected files of the baseline. It
+//! records the commit of each checkout too, so that `apply` can refuse to run
+//! against repositories that moved since the plan was reviewed:
+//!
+//! ```json
+//! {
+//!   "format": 1,
+//!   "created": "20240102T030405Z",
+//!   "bmregression": "0.1.0",
+//!   "selection": {"name": "", "tags": ["default"], "baseline": "default"},
+//!   "repositories": [
+//!     {"name": "examples", "location": "https://...", "commit": "3f2a..."}
+//!   ],
+//!   "regressions": [
+//!     {"name": "basys3/blink", "examples": null, "regbase": "blink", ...}
+//!   ]
+//! }
+//! ```
+
+use std::path::Path;
+
+use crate::config::{Compare, RegressionConfig};
+use crate::json::{self, Value};
+use crate::report::json_string;
+use crate::timestamp;
+use crate::toolchain;
+
+/// Version of the plan layout, bumped on incompatible changes.
+pub const FORMAT: u32 = 1;
+
+/// A checkout a plan runs against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repository {
+    /// `examples`, `data`, or `examples:<name>` for the additional examples repositories
+    pub name: String,
+    /// Directory or URL the checkout comes from
+    pub location: String,
+    /// HEAD commit of the checkout, `None` if it is not a git checkout
+    pub commit: Option<String>,
+}
+
+/// A plan read back from its file.
+#[derive(Debug)]
+pub struct Plan {
+    /// Baseline whose expected outputs are compared
+    pub baseline: String,
+    pub repositories: Vec<Repository>,
+    /// Names of the regressions in execution order, with their planned entry
+    pub regressions: Vec<(String, Value)>,
+}
+
+/// Returns the repositories of the checkouts, given as their name, location and directory.
+pub fn repositories(checkouts: &[(String, String, String)]) -> Vec<Repository> {
+    checkouts
+        .iter()
+        .map(|(name, location, dir)| Repository {
+            name: name.clone(),
+            location: location.clone(),
+            commit: toolchain::repo_commit(Path::new(dir)),
+        })
+        .collect()
+}
+
+/// Returns a JSON value, or `null` if there is none.
+fn optional(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "null".to_string())
+}
+
+/// Returns a JSON array of strings.
+fn string_array<'a>(items: impl IntoIterator<Item = &'a str>) -> String {
+    let items: Vec<String> = items.into_iter().map(json_string).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Renders the entry of a regression, with the expected outputs of `targetdata`.
+pub fn regression_json(config: &RegressionConfig, targetdata: &[&str]) -> String {
+    let (compare, similarity) = match config.compare {
+        Compare::Exact => ("exact", None),
+        Compare::Similarity(min) => ("similarity", Some(min.to_string())),
+        Compare::RegexLines => ("regex_lines", None),
+        Compare::Contains => ("contains", None),
+    };
+    format!(
+        "{{\"name\": {}, \"examples\": {}, \"regbase\": {}, \"workdir\": {}, \"command\": {}, \"argv\": {}, \"compare\": {}, \"min_similarity\": {}, \"sourcedata\": {}, \"targetdata\": {}, \"timeout\": {}}}",
+        json_string(&config.name),
+        optional(config.examples.as_deref().map(json_string)),
+        json_string(&config.regbase),
+        optional(config.workdir.as_deref().map(json_string)),
+        json_string(&config.regcommand),
+        optional(
+            config
+                .argv
+                .as_ref()
+                .map(|argv| string_array(argv.iter().map(String::as_str)))
+        ),
+        json_string(compare),
+        optional(similarity),
+        json_string(&config.sourcedata),
+        string_array(targetdata.iter().copied()),
+        optional(config.timeout.map(|timeout| timeout.to_string())),
+    )
+}
+
+/// Renders a plan, `regressions` being the entries from [`regression_json`].
+pub fn render(
+    name: &str,
+    tags: &[String],
+    baseline: &str,
+    repositories: &[Repository],
+    regressions: &[String],
+) -> String {
+    let repositories: Vec<String> = repositories
+        .iter()
+        .map(|repository| {
+            format!(
+                "    {{\"name\": {}, \"location\": {}, \"commit\": {}}}",
+                json_string(&repository.name),
+                json_string(&repository.location),
+                optional(repository.commit.as_deref().map(json_string))
+            )
+        })
+        .collect();
+    let regressions: Vec<String> = regressions
+        .iter()
+        .map(|regression| format!("    {}", regression))
+        .collect();
+    format!(
+        "{{\n  \"format\": {},\n  \"created\": {},\n  \"bmregression\": {},\n  \"selection\": {{\"name\": {}, \"tags\": {}, \"baseline\": {}}},\n  \"repositories\": [\n{}\n  ],\n  \"regressions\": [\n{}\n  ]\n}}\n",
+        FORMAT,
+        json_string(&timestamp::DateTime::now().compact()),
+        json_string(env!("CARGO_PKG_VERSION")),
+        json_string(name),
+        string_array(tags.iter().map(String::as_str)),
+        json_string(baseline),
+        repositories.join(",\n"),
+        regressions.join(",\n")
+    )
+}
+
+/// Returns a string member of an object.
+fn string_member<'a>(value: &'a Value, key: &str, what: &str) -> Result<&'a str, String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("{} has no {} string", what, key))
+}
+
+/// Parses a plan.
+///
+/// # Errors
+///
+/// Returns an error if the document is not JSON, has another format, or lacks a member.
+pub fn parse(text: &str) -> Result<Plan, String> {
+    let document = json::parse(text)?;
+    match document.get("format").and_then(Value::as_f64) {
+        Some(format) if format == FORMAT as f64 => {}
+        Some(format) => {
+            return Err(format!(
+                "the plan has format {}, this version reads format {}",
+                format, FORMAT
+            ))
+        }
+        None => return Err("the document is not a plan, it has no format".to_string()),
+    }
+    let selection = document
+        .get("selection")
+        .ok_or("the plan has no selection")?;
+    let baseline = string_member(selection, "baseline", "the selection")?.to_string();
+    let mut repositories = Vec::new();
+    for repository in document
+        .get("repositories")
+        .and_then(Value::as_array)
+        .ok_or("the plan has no repositories")?
+    {
+        repositories.push(Repository {
+            name: string_member(repository, "name", "a repository")?.to_string(),
+            location: string_member(repository, "location", "a repository")?.to_string(),
+            commit: repository
+                .get("commit")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        });
+    }
+    let mut regressions = Vec::new();
+    for regression in document
+        .get("regressions")
+        .and_then(Value::as_array)
+        .ok_or("the plan has no regressions")?
+    {
+        let name = string_member(regression, "name", "a regression")?.to_string();
+        regressions.push((name, regression.clone()));
+    }
+    Ok(Plan {
+        baseline,
+        repositories,
+        regressions,
+    })
+}
+
+/// Describes the planned repositories whose checkout is not at the planned commit.
+///
+/// A repository planned without a commit, not a git checkout, cannot be checked and is
+/// not reported.
+pub fn moved(planned: &[Repository], current: &[Repository]) -> Vec<String> {
+    let mut moved = Vec::new();
+    for repository in planned {
+        let Some(commit) = &repository.commit else {
+            continue;
+        };
+        match current
+            .iter()
+            .find(|current| current.name == repository.name)
+        {
+            None => moved.push(format!("{}: not given", repository.name)),
+            Some(Repository { commit: None, .. }) => moved.push(format!(
+                "{}: planned at {}, not a git checkout now",
+                repository.name, commit
+            )),
+            Some(Repository {
+                commit: Some(current),
+                ..
+            }) if current != commit => moved.push(format!(
+                "{}: planned at {}, checked out at {}",
+                repository.name, commit, current
+            )),
+            Some(_) => {}
+        }
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use std::fs;
+    use tempdir::TempDir;
+
+    #[test]
+    fn reads_back_the_plan_and_finds_the_moved_repositories() {
+        let data = TempDir::new("plan").unwrap();
+        fs::create_dir(data.path().join("blink")).unwrap();
+        fs::write(
+            data.path().join("blink/config.yaml"),
+            "regbase: blink\nsourcedata: out.sv\ntargetdata: out.sv\nregcommand: make hdl\ncompare: similarity\nmin_similarity: 90\n",
+        )
+        .unwrap();
+        let config = config::load(data.path().to_str().unwrap(), "blink").unwrap();
+        let entry = regression_json(&config, &["out.sv"]);
+        let repositories = vec![
+            Repository {
+                name: "examples".to_string(),
+                location: "https://example.com/examples.git".to_string(),
+                commit: Some("aaa".to_string()),
+            },
+            Repository {
+                name: "data".to_string(),
+                location: "data".to_string(),
+                commit: None,
+            },
+        ];
+        let text = render(
+            "",
+            &["default".to_string()],
+            "default",
+            &repositories,
+            std::slice::from_ref(&entry),
+        );
+
+        let plan = parse(&text).unwrap();
+        assert_eq!(plan.baseline, "default");
+        assert_eq!(plan.repositories, repositories);
+        assert_eq!(plan.regressions.len(), 1);
+        assert_eq!(plan.regressions[0].0, "blink");
+        assert_eq!(plan.regressions[0].1, json::parse(&entry).unwrap());
+        assert_eq!(
+            plan.regressions[0].1.get("compare").and_then(Value::as_str),
+            Some("similarity")
+        );
+        assert!(parse("{\"format\": 2}").is_err());
+
+        assert!(moved(&repositories, &repositories).is_empty());
+        let mut current = repositories.clone();
+        current[0].commit = Some("bbb".to_string());
+        current[1].commit = Some("ccc".to_string());
+        assert_eq!(
+            moved(&repositories, &current),
+            vec!["examples: planned at aaa, checked out at bbb"]
+        );
+        assert_eq!(
+            moved(&repositories, &current[1..]),
+            vec!["examples: not given"]
+        );
+    }
+}
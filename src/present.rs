@@ -0,0 +1,362 @@
+//! Results of the executed regressions and their presentation on the console.
+//!
+//! Executing a regression yields a [`RunResult`]: its status, its duration and
+//! the lines it printed, collected while it was executed (see
+//! [`crate::output`]). The subcommands hand each result to a [`Presenter`] as
+//! soon as the regression completes: it prints the lines not printed yet, the
+//! ones shown before an interactive prompt being printed already, and keeps
+//! the result for the summary of the subcommand, rendered once all the
+//! regressions are done.
+//!
+//! The results kept for the reports and the history are the
+//! [`crate::results::RegressionResult`]s of the context.
+
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::diff::DiffStat;
+
+/// Status of a regression once its action is done.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    /// The configuration has been printed
+    Described,
+    /// The generated output matches the expected one
+    Passed,
+    /// The generated output differs from the expected one
+    Failed { diff_summary: String },
+    /// The generated output differs from the expected one, but the regression is quarantined
+    QuarantinedFailure { diff_summary: String },
+    /// The expected output did not exist and has been captured from the generated one
+    Captured,
+    /// The regression could not be executed
+    Errored { kind: io::ErrorKind, detail: String },
+    /// The expected output has been left as is
+    Skipped { reason: String },
+    /// The command is not in the allowlist, it has not been executed
+    Blocked,
+    /// The expected output has been replaced with the generated one
+    Reset,
+    /// The preview found that the reset would change the expected outputs, the lines of
+    /// `files` of them
+    WouldChange { stat: DiffStat, files: usize },
+    /// The expected output was already up to date (preview and update)
+    Unchanged,
+    /// The expected output differed and has been replaced with the generated one
+    Updated,
+    /// The diff found no differences
+    NoDiff,
+    /// The diff found differences
+    DiffFound,
+}
+
+impl Status {
+    /// Returns the short description of the status used in summaries.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Status::Described => "described",
+            Status::Passed => "passed",
+            Status::Failed { .. } => "failed",
+            Status::QuarantinedFailure { .. } => "quarantined failure",
+            Status::Captured => "captured",
+            Status::Errored { .. } => "error",
+            Status::Skipped { .. } => "skipped",
+            Status::Blocked => "blocked",
+            Status::Reset => "reset",
+            Status::WouldChange { .. } => "would change",
+            Status::Unchanged => "unchanged",
+            Status::Updated => "updated",
+            Status::NoDiff => "no differences",
+            Status::DiffFound => "differences found",
+        }
+    }
+}
+
+/// Result of an action on a regression.
+#[derive(Debug, Clone)]
+pub struct RunResult {
+    /// Name of the regression
+    pub name: String,
+    /// Action executed: describe, run, bootstrap, reset, preview, update or diff
+    pub action: String,
+    pub status: Status,
+    /// Time spent on the regression, retries included
+    pub duration: Duration,
+    /// Lines printed for the regression: its status, differences, warnings and error
+    pub output: String,
+    /// Length of the output already printed, before an interactive prompt
+    pub shown: usize,
+}
+
+impl RunResult {
+    /// Returns the result of a regression left out without being executed.
+    pub fn skipped(name: &str, action: &str, reason: &str) -> RunResult {
+        RunResult {
+            name: name.to_string(),
+            action: action.to_string(),
+            status: Status::Skipped {
+                reason: reason.to_string(),
+            },
+            duration: Duration::ZERO,
+            output: String::new(),
+            shown: 0,
+        }
+    }
+
+    /// Returns the output of the regression not printed yet.
+    pub fn pending(&self) -> &str {
+        &self.output[self.shown.min(self.output.len())..]
+    }
+
+    /// Renders the line reporting the error of the regression, None if it was executed.
+    pub fn error_line(&self) -> Option<String> {
+        let Status::Errored { detail, .. } = &self.status else {
+            return None;
+        };
+        let doing = if self.action == "describe" {
+            "describing"
+        } else {
+            "executing"
+        };
+        Some(format!(
+            "Error {} regression {}: {}",
+            doing, self.name, detail
+        ))
+    }
+}
+
+/// Prints the output of a regression not printed yet.
+pub fn print(result: &RunResult) {
+    print!("{}", result.pending());
+    let _ = io::stdout().flush();
+}
+
+/// Prints the results of the regressions of a subcommand as they complete, and keeps them.
+#[derive(Debug, Default)]
+pub struct Presenter {
+    pub results: Vec<RunResult>,
+}
+
+impl Presenter {
+    /// Prints the output of a completed regression not printed yet, and keeps its result.
+    pub fn show(&mut self, result: RunResult) {
+        print(&result);
+        self.results.push(result);
+    }
+
+    /// Keeps the result of a regression left out, nothing is printed for it.
+    pub fn keep(&mut self, result: RunResult) {
+        self.results.push(result);
+    }
+
+    /// Renders the summary of `reset`: the reset and the skipped regressions in interactive
+    /// mode, the total of the changes in preview mode.
+    pub fn reset_summary(&self, interactive: bool, preview: bool) -> String {
+        let reset = changed(&self.results);
+        let unchanged = names(&self.results, |status| *status == Status::Unchanged).len();
+        // Declined, quit, or the reset failed
+        let skipped = names(&self.results, |status| {
+            !matches!(
+                status,
+                Status::Reset | Status::WouldChange { .. } | Status::Unchanged
+            )
+        });
+        let mut total = DiffStat::default();
+        let mut changed_files = 0;
+        for result in &self.results {
+            if let Status::WouldChange { stat, files } = result.status {
+                total.added += stat.added;
+                total.removed += stat.removed;
+                changed_files += files;
+            }
+        }
+
+        let mut summary = String::new();
+        if interactive {
+            summary.push_str("Reset summary:\n");
+            summary.push_str(&format!(
+                "  reset ({}): {}\n",
+                reset.len(),
+                reset.join(", ")
+            ));
+            summary.push_str(&format!(
+                "  skipped ({}): {}\n",
+                skipped.len(),
+                skipped.join(", ")
+            ));
+        }
+        if preview {
+            summary.push_str(&format!(
+                "Preview: {} regressions would change ({} files, +{} -{} lines), {} unchanged\n",
+                reset.len(),
+                changed_files,
+                total.added,
+                total.removed,
+                unchanged
+            ));
+        }
+        summary
+    }
+}
+
+/// Returns the names of the regressions whose status matches.
+pub fn names(results: &[RunResult], matches: impl Fn(&Status) -> bool) -> Vec<String> {
+    results
+        .iter()
+        .filter(|result| matches(&result.status))
+        .map(|result| result.name.clone())
+        .collect()
+}
+
+/// Returns the names of the regressions reset, or that a preview would change.
+pub fn changed(results: &[RunResult]) -> Vec<String> {
+    names(results, |status| {
+        matches!(status, Status::Reset | Status::WouldChange { .. })
+    })
+}
+
+/// Returns the results of the regressions that could not be executed.
+pub fn errors(results: &[RunResult]) -> Vec<&RunResult> {
+    results
+        .iter()
+        .filter(|result| matches!(result.status, Status::Errored { .. }))
+        .collect()
+}
+
+/// Renders the section listing the regressions that could not be executed, printed once
+/// all the regressions are done, empty if there are none.
+pub fn error_section(results: &[RunResult]) -> String {
+    let errors = errors(results);
+    if errors.is_empty() {
+        return String::new();
+    }
+    let mut section = format!("\x1b[0;31mErrors ({}):\x1b[0m\n", errors.len());
+    for result in errors {
+        if let Status::Errored { detail, .. } = &result.status {
+            section.push_str(&format!("  {}: {}\n", result.name, detail));
+        }
+    }
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str, status: Status, output: &str, shown: usize) -> RunResult {
+        RunResult {
+            name: name.to_string(),
+            action: "reset".to_string(),
+            status,
+            duration: Duration::ZERO,
+            output: output.to_string(),
+            shown,
+        }
+    }
+
+    #[test]
+    fn prints_only_the_output_not_shown_at_the_prompt() {
+        let prompted = result(
+            "blink",
+            Status::Reset,
+            "--- expected\n+++ generated\nRegression blink: reset\n",
+            27,
+        );
+        assert_eq!(prompted.pending(), "Regression blink: reset\n");
+        assert_eq!(prompted.error_line(), None);
+        let mut errored = result(
+            "counter",
+            Status::Errored {
+                kind: io::ErrorKind::TimedOut,
+                detail: "regression command timed out after 60s".to_string(),
+            },
+            "Error executing regression counter: regression command timed out after 60s\n",
+            0,
+        );
+        assert_eq!(errored.pending(), errored.output);
+        assert_eq!(errored.status.label(), "error");
+        assert_eq!(
+            errored.error_line().as_deref(),
+            Some(errored.output.trim_end())
+        );
+        errored.action = "describe".to_string();
+        assert_eq!(
+            errored.error_line().unwrap(),
+            "Error describing regression counter: regression command timed out after 60s"
+        );
+    }
+
+    #[test]
+    fn lists_the_errors_once_the_regressions_are_done() {
+        let results = vec![
+            result("blink", Status::Passed, "", 0),
+            result(
+                "counter",
+                Status::Errored {
+                    kind: io::ErrorKind::TimedOut,
+                    detail: "regression command timed out after 60s".to_string(),
+                },
+                "",
+                0,
+            ),
+        ];
+        assert_eq!(errors(&results).len(), 1);
+        assert_eq!(
+            error_section(&results),
+            "\x1b[0;31mErrors (1):\x1b[0m\n  counter: regression command timed out after 60s\n"
+        );
+        assert_eq!(error_section(&results[..1]), "");
+    }
+
+    #[test]
+    fn summarizes_the_reset_regressions() {
+        let mut presenter = Presenter::default();
+        presenter.keep(result("blink", Status::Reset, "", 0));
+        presenter.keep(result(
+            "counter",
+            Status::Skipped {
+                reason: "declined at the prompt".to_string(),
+            },
+            "",
+            0,
+        ));
+        presenter.keep(RunResult::skipped("uart", "reset", "quit at the prompt"));
+        assert_eq!(changed(&presenter.results), vec!["blink"]);
+        assert_eq!(
+            presenter.reset_summary(true, false),
+            "Reset summary:\n  reset (1): blink\n  skipped (2): counter, uart\n"
+        );
+        assert_eq!(presenter.reset_summary(false, false), "");
+
+        let mut presenter = Presenter::default();
+        presenter.keep(result(
+            "blink",
+            Status::WouldChange {
+                stat: DiffStat {
+                    added: 3,
+                    removed: 1,
+                },
+                files: 2,
+            },
+            "",
+            0,
+        ));
+        presenter.keep(result(
+            "counter",
+            Status::WouldChange {
+                stat: DiffStat {
+                    added: 2,
+                    removed: 2,
+                },
+                files: 1,
+            },
+            "",
+            0,
+        ));
+        presenter.keep(result("uart", Status::Unchanged, "", 0));
+        assert_eq!(
+            presenter.reset_summary(false, true),
+            "Preview: 2 regressions would change (3 files, +5 -3 lines), 1 unchanged\n"
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! Provenance of the expected outputs.
+//!
+//! An expected output alone does not tell which toolchain produced it. When
+//! reset, update or a bootstrap writes one, a `<targetdata>.meta.yaml` sidecar
+//! is written next to it, recording when, with which tools, against which
+//! commits and by whom:
+//!
+//! ```yaml
+//! generated: "2024-04-01T15:30:00Z"
+//! by: "alice"
+//! run_id: "20240401T153000Z-3fa9c1"
+//! toolchain:
+//!   "bondmachine": "v0.9.0"
+//! commits:
+//!   "examples": "0123456789abcdef0123456789abcdef01234567"
+//! ```
+//!
+//! The sidecars are not expected outputs: nothing compares or diffs them.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use yaml_rust::{Yaml, YamlLoader};
+
+use crate::atomic;
+use crate::report;
+use crate::timestamp::DateTime;
+use crate::toolchain;
+
+/// Suffix of the sidecar of an expected output.
+pub const SUFFIX: &str = ".meta.yaml";
+
+/// The tool whose version describes a baseline.
+const MAIN_TOOL: &str = "bondmachine";
+
+/// Returns the path of the sidecar of an expected output.
+pub fn sidecar(baseline: &Path) -> PathBuf {
+    let mut path = baseline.as_os_str().to_owned();
+    path.push(SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Returns true if a file name is the one of a sidecar.
+pub fn is_sidecar(name: &str) -> bool {
+    name.ends_with(SUFFIX)
+}
+
+/// Where an expected output comes from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Provenance {
+    /// When it was written
+    pub generated: Option<DateTime>,
+    /// Who wrote it, the user or the CI job
+    pub by: String,
+    /// Identifier of the run that wrote it
+    pub run_id: String,
+    /// Versions of the tools, as (tool, version) pairs
+    pub toolchain: Vec<(String, String)>,
+    /// Checked out commits, as (repository, commit) pairs
+    pub commits: Vec<(String, String)>,
+}
+
+impl Provenance {
+    /// Renders the sidecar.
+    fn render(&self, baseline: &str) -> String {
+        let mut out = format!("# Provenance of {}, written by bmregression\n", baseline);
+        if let Some(generated) = &self.generated {
+            out.push_str(&format!(
+                "generated: {}\n",
+                report::json_string(&iso(generated))
+            ));
+        }
+        out.push_str(&format!("by: {}\n", report::json_string(&self.by)));
+        out.push_str(&format!("run_id: {}\n", report::json_string(&self.run_id)));
+        for (key, pairs) in [("toolchain", &self.toolchain), ("commits", &self.commits)] {
+            out.push_str(&format!("{}:\n", key));
+            for (name, value) in pairs {
+                out.push_str(&format!(
+                    "  {}: {}\n",
+                    report::json_string(name),
+                    report::json_string(value)
+                ));
+            }
+        }
+        out
+    }
+
+    /// Describes the provenance in a line, e.g. `baseline generated with bondmachine v0.9.0
+    /// on 2024-04-01 by alice`.
+    pub fn describe(&self) -> String {
+        let tool = match self.toolchain.iter().find(|(tool, _)| tool == MAIN_TOOL) {
+            Some((_, version)) if version != "not found" => {
+                format!(" with {} {}", MAIN_TOOL, version)
+            }
+            Some(_) => format!(" without {}", MAIN_TOOL),
+            None => String::new(),
+        };
+        let date = self
+            .generated
+            .map(|generated| format!(" on {}", generated.date()))
+            .unwrap_or_default();
+        let by = if self.by.is_empty() {
+            String::new()
+        } else {
+            format!(" by {}", self.by)
+        };
+        format!("baseline generated{}{}{}", tool, date, by)
+    }
+}
+
+/// Renders a date and time in ISO 8601 form, e.g. `2024-04-01T15:30:00Z`.
+fn iso(time: &DateTime) -> String {
+    format!("{}T{}Z", time.date(), time.time())
+}
+
+/// Parses the ISO 8601 form written by [`iso`].
+fn parse_iso(text: &str) -> Option<DateTime> {
+    DateTime::from_compact(&text.replace(['-', ':'], ""))
+}
+
+/// Reads a mapping of the sidecar as pairs.
+fn pairs(value: &Yaml) -> Vec<(String, String)> {
+    let Yaml::Hash(hash) = value else {
+        return Vec::new();
+    };
+    hash.iter()
+        .filter_map(|(key, value)| Some((key.as_str()?.to_string(), value.as_str()?.to_string())))
+        .collect()
+}
+
+/// Loads the provenance of an expected output, None if it has no sidecar.
+///
+/// # Errors
+///
+/// Returns an error if the sidecar cannot be read or parsed.
+pub fn load(baseline: &Path) -> io::Result<Option<Provenance>> {
+    let path = sidecar(baseline);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let docs = YamlLoader::load_from_str(&content)
+        .map_err(|err| io::Error::other(format!("parsing {} failed: {}", path.display(), err)))?;
+    let Some(doc) = docs.into_iter().next() else {
+        return Ok(Some(Provenance::default()));
+    };
+    Ok(Some(Provenance {
+        generated: doc["generated"].as_str().and_then(parse_iso),
+        by: doc["by"].as_str().unwrap_or_default().to_string(),
+        run_id: doc["run_id"].as_str().unwrap_or_default().to_string(),
+        toolchain: pairs(&doc["toolchain"]),
+        commits: pairs(&doc["commits"]),
+    }))
+}
+
+/// Removes the sidecar of an expected output, if it has one.
+///
+/// # Errors
+///
+/// Returns an error if the sidecar exists but cannot be removed.
+pub fn remove(baseline: &Path) -> io::Result<()> {
+    match fs::remove_file(sidecar(baseline)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Returns who is writing the expected outputs: the CI job, or else the user.
+fn identity() -> String {
+    let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+    if let Some(run) = var("GITHUB_RUN_ID") {
+        let actor = var("GITHUB_ACTOR").map(|actor| format!(" ({})", actor));
+        return format!("GitHub Actions run {}{}", run, actor.unwrap_or_default());
+    }
+    if let Some(job) = var("CI_JOB_ID") {
+        let user = var("GITLAB_USER_LOGIN").map(|user| format!(" ({})", user));
+        return format!("GitLab CI job {}{}", job, user.unwrap_or_default());
+    }
+    if var("CI").is_some() {
+        return "CI".to_string();
+    }
+    var("USER")
+        .or_else(|| var("LOGNAME"))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Returns the provenance of the expected outputs imported from the archive of a run, from
+/// the environment the run recorded.
+pub fn from_run(run_id: &str, environment: &[(String, String)]) -> Provenance {
+    let prefixed = |prefix: &str| {
+        environment
+            .iter()
+            .filter_map(|(key, value)| Some((key.strip_prefix(prefix)?.to_string(), value.clone())))
+            .collect()
+    };
+    Provenance {
+        generated: DateTime::from_compact(run_id),
+        by: identity(),
+        run_id: run_id.to_string(),
+        toolchain: prefixed("tool."),
+        commits: prefixed("commit."),
+    }
+}
+
+/// Writes the provenance of the expected outputs of a run, probing the tools, the commits
+/// and the identity once, on the first write.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    common: OnceLock<Provenance>,
+}
+
+impl Recorder {
+    /// Records `provenance` instead of probing it, the time and the run included when set,
+    /// for the expected outputs generated elsewhere.
+    pub fn preset(&self, provenance: Provenance) {
+        let _ = self.common.set(provenance);
+    }
+
+    /// Writes the sidecar of the expected output at `baseline`, just written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sidecar cannot be written.
+    pub fn write(
+        &self,
+        source: &str,
+        target: &str,
+        run_id: &str,
+        baseline: &Path,
+    ) -> io::Result<()> {
+        let common = self.common.get_or_init(|| Provenance {
+            generated: None,
+            by: identity(),
+            run_id: String::new(),
+            toolchain: toolchain::versions(),
+            commits: vec![
+                ("examples".to_string(), toolchain::repo_commit_label(source)),
+                (
+                    "regression data".to_string(),
+                    toolchain::repo_commit_label(target),
+                ),
+            ],
+        });
+        let provenance = Provenance {
+            generated: common.generated.or_else(|| Some(DateTime::now())),
+            run_id: if common.run_id.is_empty() {
+                run_id.to_string()
+            } else {
+                common.run_id.clone()
+            },
+            ..common.clone()
+        };
+        let name = baseline
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        atomic::write(sidecar(baseline), provenance.render(&name))
+    }
+}
@@ -0,0 +1,334 @@
+//! Quarantine of known-flaky regressions.
+//!
+//! The `quarantine.yaml` file at the root of the data repository lists the
+//! regressions whose failures must not fail the invocation, either by name or
+//! with an expiry date and a reason:
+//!
+//! ```yaml
+//! - basys3_blink
+//! - name: zedboard_counter
+//!   expires: 2024-06-30
+//!   reason: timing dependent output
+//! ```
+//!
+//! Quarantined regressions run normally. An entry stops applying after its
+//! expiry date, so that quarantines cannot be forgotten.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use yaml_rust::{Yaml, YamlLoader};
+
+use crate::atomic;
+use crate::timestamp::DateTime;
+
+/// Name of the quarantine file, at the root of the data repository.
+pub const FILE: &str = "quarantine.yaml";
+
+/// A quarantined regression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Name of the regression
+    pub name: String,
+    /// Last day the entry applies, `YYYY-MM-DD`
+    pub expires: Option<String>,
+    /// Why the regression is quarantined
+    pub reason: Option<String>,
+}
+
+/// The quarantine of a data repository, as of today.
+#[derive(Debug, Clone, Default)]
+pub struct Quarantine {
+    entries: Vec<Entry>,
+    /// Today, `YYYY-MM-DD`
+    today: String,
+}
+
+/// Returns true if a text is a date in the `YYYY-MM-DD` form.
+fn is_date(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, b)| {
+            if i == 4 || i == 7 {
+                *b == b'-'
+            } else {
+                b.is_ascii_digit()
+            }
+        })
+}
+
+/// Parses an entry of the quarantine file.
+fn parse_entry(item: &Yaml) -> io::Result<Entry> {
+    if let Some(name) = item.as_str() {
+        return Ok(Entry {
+            name: name.to_string(),
+            expires: None,
+            reason: None,
+        });
+    }
+    let name = item["name"].as_str().ok_or_else(|| {
+        io::Error::other(format!(
+            "{}: every entry must be a regression name or have a name key",
+            FILE
+        ))
+    })?;
+    let expires = item["expires"].as_str().map(|s| s.to_string());
+    if let Some(expires) = &expires {
+        if !is_date(expires) {
+            return Err(io::Error::other(format!(
+                "{}: expiry date {} of {} is not in the YYYY-MM-DD form",
+                FILE, expires, name
+            )));
+        }
+    }
+    Ok(Entry {
+        name: name.to_string(),
+        expires,
+        reason: item["reason"].as_str().map(|s| s.to_string()),
+    })
+}
+
+impl Quarantine {
+    /// Loads the quarantine of a data directory, empty if it has no quarantine file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a list of valid entries.
+    pub fn load(target: &str) -> io::Result<Quarantine> {
+        let path = Path::new(target).join(FILE);
+        let mut quarantine = Quarantine {
+            entries: Vec::new(),
+            today: DateTime::now().date(),
+        };
+        if !path.exists() {
+            return Ok(quarantine);
+        }
+        let docs = YamlLoader::load_from_str(&fs::read_to_string(&path)?)
+            .map_err(|err| io::Error::other(format!("parsing {} failed: {}", FILE, err)))?;
+        let items = match docs.first() {
+            None | Some(Yaml::Null) => return Ok(quarantine),
+            Some(Yaml::Array(items)) => items,
+            Some(_) => {
+                return Err(io::Error::other(format!(
+                    "{} must be a list of regressions",
+                    FILE
+                )))
+            }
+        };
+        for item in items {
+            quarantine.entries.push(parse_entry(item)?);
+        }
+        Ok(quarantine)
+    }
+
+    /// Returns true if the file lists no regression.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns all the entries, expired ones included.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns true if an entry no longer applies.
+    pub fn is_expired(&self, entry: &Entry) -> bool {
+        entry
+            .expires
+            .as_ref()
+            .is_some_and(|expires| *expires < self.today)
+    }
+
+    /// Returns the entry quarantining a regression, if it is listed and not expired.
+    pub fn active(&self, name: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name && !self.is_expired(entry))
+    }
+
+    /// Removes the entries of a regression from the quarantine file of a data directory, for
+    /// a removed regression.
+    ///
+    /// The text of the file is edited, so that the comments of the other entries are kept:
+    /// an entry goes with the comments above it.
+    ///
+    /// # Returns
+    ///
+    /// True if the regression was quarantined.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or written, or if it would not list the
+    /// same other entries once edited, in which case it is left unchanged.
+    pub fn remove(target: &str, name: &str) -> io::Result<bool> {
+        let path = Path::new(target).join(FILE);
+        let before = Quarantine::load(target)?;
+        if !before.entries.iter().any(|entry| entry.name == name) {
+            return Ok(false);
+        }
+        let content = fs::read_to_string(&path)?;
+        let mut kept = String::new();
+        let mut item = String::new();
+        let mut pending = String::new();
+        let flush = |item: &mut String, kept: &mut String| {
+            let docs = YamlLoader::load_from_str(item).unwrap_or_default();
+            let listed = match docs.first() {
+                Some(Yaml::Array(items)) => items
+                    .iter()
+                    .any(|entry| parse_entry(entry).is_ok_and(|entry| entry.name == name)),
+                _ => false,
+            };
+            if !listed {
+                kept.push_str(item);
+            }
+            item.clear();
+        };
+        for line in content.split_inclusive('\n') {
+            let trimmed = line.trim();
+            if line.starts_with('-') {
+                flush(&mut item, &mut kept);
+                item.push_str(&pending);
+                item.push_str(line);
+                pending.clear();
+            } else if trimmed.is_empty() || trimmed.starts_with('#') {
+                pending.push_str(line);
+            } else {
+                item.push_str(&pending);
+                item.push_str(line);
+                pending.clear();
+            }
+        }
+        flush(&mut item, &mut kept);
+        kept.push_str(&pending);
+
+        let expected: Vec<Entry> = before
+            .entries
+            .into_iter()
+            .filter(|entry| entry.name != name)
+            .collect();
+        let docs = YamlLoader::load_from_str(&kept).unwrap_or_default();
+        let after = match docs.first() {
+            None | Some(Yaml::Null) => Some(Vec::new()),
+            Some(Yaml::Array(items)) => items.iter().map(|item| parse_entry(item).ok()).collect(),
+            Some(_) => None,
+        };
+        if after.as_ref() != Some(&expected) {
+            return Err(io::Error::other(format!(
+                "could not remove {} from {}, edit it by hand",
+                name, FILE
+            )));
+        }
+        atomic::write(&path, kept)?;
+        Ok(true)
+    }
+
+    /// Returns the entries past their expiry date.
+    pub fn expired(&self) -> Vec<&Entry> {
+        self.entries
+            .iter()
+            .filter(|entry| self.is_expired(entry))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    /// Loads the quarantine file with the given content, as of 2024-04-01.
+    fn load(content: &str) -> io::Result<Quarantine> {
+        let dir = TempDir::new("quarantine").unwrap();
+        fs::write(dir.path().join(FILE), content).unwrap();
+        let mut quarantine = Quarantine::load(dir.path().to_str().unwrap())?;
+        quarantine.today = "2024-04-01".to_string();
+        Ok(quarantine)
+    }
+
+    #[test]
+    fn lists_the_regressions_by_name_or_with_details() {
+        let quarantine = load(
+            "# known flaky\n- basys3_blink\n- name: zedboard_counter\n  expires: 2024-06-30\n  reason: timing dependent output\n",
+        )
+        .unwrap();
+        assert_eq!(
+            quarantine.entries(),
+            [
+                Entry {
+                    name: "basys3_blink".to_string(),
+                    expires: None,
+                    reason: None
+                },
+                Entry {
+                    name: "zedboard_counter".to_string(),
+                    expires: Some("2024-06-30".to_string()),
+                    reason: Some("timing dependent output".to_string())
+                }
+            ]
+        );
+        assert!(quarantine.active("basys3_blink").is_some());
+        assert_eq!(
+            quarantine
+                .active("zedboard_counter")
+                .unwrap()
+                .reason
+                .as_deref(),
+            Some("timing dependent output")
+        );
+        assert!(quarantine.active("uart").is_none());
+        assert!(quarantine.expired().is_empty());
+    }
+
+    #[test]
+    fn stops_applying_after_the_expiry_date() {
+        let quarantine =
+            load("- name: blink\n  expires: 2024-04-01\n- name: counter\n  expires: 2024-03-31\n")
+                .unwrap();
+        // Still on its last day
+        assert!(quarantine.active("blink").is_some());
+        assert!(quarantine.active("counter").is_none());
+        let expired: Vec<&str> = quarantine
+            .expired()
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(expired, ["counter"]);
+    }
+
+    #[test]
+    fn is_empty_without_a_file() {
+        let dir = TempDir::new("quarantine").unwrap();
+        assert!(Quarantine::load(dir.path().to_str().unwrap())
+            .unwrap()
+            .is_empty());
+        assert!(load("").unwrap().is_empty());
+        assert!(load("# nothing quarantined\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_the_malformed_files() {
+        for (content, error) in [
+            (
+                "blink: yes\n",
+                "quarantine.yaml must be a list of regressions",
+            ),
+            (
+                "- reason: flaky\n",
+                "quarantine.yaml: every entry must be a regression name or have a name key",
+            ),
+            (
+                "- name: blink\n  expires: 30/06/2024\n",
+                "quarantine.yaml: expiry date 30/06/2024 of blink is not in the YYYY-MM-DD form",
+            ),
+        ] {
+            assert_eq!(load(content).unwrap_err().to_string(), error);
+        }
+        let err = load("- [blink\n").unwrap_err().to_string();
+        assert!(
+            err.starts_with("parsing quarantine.yaml failed: "),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Results of the runs recorded in the data repository.
+//!
+//! Unlike the local history, which stays on the machine that ran the
+//! regressions, the `record` command writes the results of a run to
+//! `<data-dir>/results/<run-id>.json`, meant to be committed next to the
+//! baselines. Each file holds the counts, the environment of the run (without
+//! the host name) and one entry per regression, and only the most recent files
+//! are kept.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::atomic;
+use crate::history::{Entry, RecordedRun};
+use crate::json::{self, Value};
+use crate::report::json_string;
+use crate::results::RunReport;
+use crate::timestamp::DateTime;
+
+/// Directory of the results files, relative to the data directory.
+pub const DIR: &str = "results";
+
+/// Extension of the results files.
+const EXTENSION: &str = "json";
+
+/// Returns the directory holding the results files.
+pub fn results_root(target: &str) -> PathBuf {
+    Path::new(target).join(DIR)
+}
+
+/// Renders (key, value) pairs as a JSON object.
+fn json_object(pairs: &[(String, String)]) -> String {
+    let fields: Vec<String> = pairs
+        .iter()
+        .map(|(key, value)| format!("{}: {}", json_string(key), json_string(value)))
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+/// Renders the results file of a run.
+pub fn render(report: &RunReport) -> String {
+    let environment = &report.environment;
+    let host: Vec<(String, String)> = environment
+        .host()
+        .into_iter()
+        .map(|(field, value)| (field.to_string(), value))
+        .collect();
+    let results: Vec<String> = report
+        .results
+        .iter()
+        .map(|result| {
+            let tags: Vec<String> = result.tags.iter().map(|tag| json_string(tag)).collect();
+            let bench: Vec<String> = result
+                .bench
+                .iter()
+                .map(|d| d.as_millis().to_string())
+                .collect();
+            format!(
+                "    {{\"name\": {}, \"status\": {}, \"duration_ms\": {}, \"similarity\": {}, \"output_size\": {}, \"bench_ms\": [{}], \"unreliable\": {}, \"tags\": [{}]}}",
+                json_string(&result.name),
+                json_string(result.status),
+                result.duration.as_millis(),
+                result.similarity_json(),
+                result.output_size_json(),
+                bench.join(", "),
+                result.unreliable_json(),
+                tags.join(", ")
+            )
+        })
+        .collect();
+    format!(
+        "{{\n  \"run_id\": {},\n  \"started\": {},\n  \"duration_seconds\": {},\n  \"counts\": {{\"total\": {}, \"passed\": {}, \"failed\": {}, \"errors\": {}}},\n  \"commits\": {},\n  \"toolchain\": {},\n  \"host\": {},\n  \"results\": [\n{}\n  ]\n}}\n",
+        json_string(&report.run_id),
+        json_string(&report.started.compact()),
+        report.duration.as_secs(),
+        report.results.len(),
+        report.count("passed"),
+        report.count("failed"),
+        report.count("error"),
+        json_object(&environment.commits),
+        json_object(&environment.toolchain),
+        json_object(&host),
+        results.join(",\n")
+    )
+}
+
+/// Writes the results file of a run.
+///
+/// # Returns
+///
+/// The path of the file written.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write(target: &str, report: &RunReport) -> io::Result<PathBuf> {
+    let root = results_root(target);
+    fs::create_dir_all(&root)?;
+    let path = root.join(format!("{}.{}", report.run_id, EXTENSION));
+    atomic::write(&path, render(report))?;
+    Ok(path)
+}
+
+/// Returns the identifiers of the recorded runs, oldest first by the time they started.
+fn ids(target: &str) -> io::Result<Vec<String>> {
+    let root = results_root(target);
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+    let mut ids = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some(EXTENSION) {
+            if let Some(id) = path.file_stem().and_then(|s| s.to_str()) {
+                ids.push(id.to_string());
+            }
+        }
+    }
+    ids.sort_by_cached_key(|id| (started(target, id), id.clone()));
+    Ok(ids)
+}
+
+/// Returns the time a recorded run started, or else as its identifier starts with.
+fn started(target: &str, id: &str) -> Option<DateTime> {
+    let path = results_root(target).join(format!("{}.{}", id, EXTENSION));
+    let document = fs::read_to_string(path)
+        .ok()
+        .and_then(|content| json::parse(&content).ok());
+    document
+        .as_ref()
+        .and_then(|document| document.get("started"))
+        .and_then(Value::as_str)
+        .and_then(DateTime::from_compact)
+        .or_else(|| DateTime::from_compact(id))
+}
+
+/// Removes the oldest results files, keeping the `keep` most recent ones.
+///
+/// # Returns
+///
+/// The paths of the removed files.
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be read or a file cannot be removed.
+pub fn prune(target: &str, keep: usize) -> io::Result<Vec<PathBuf>> {
+    let ids = ids(target)?;
+    let mut removed = Vec::new();
+    for id in &ids[..ids.len().saturating_sub(keep)] {
+        let path = results_root(target).join(format!("{}.{}", id, EXTENSION));
+        fs::remove_file(&path)?;
+        removed.push(path);
+    }
+    Ok(removed)
+}
+
+/// Parses a results file into the entries of its regressions.
+fn parse(id: &str, content: &str) -> Result<RecordedRun, String> {
+    let document = json::parse(content)?;
+    let results = document
+        .get("results")
+        .and_then(Value::as_array)
+        .ok_or("no results array")?;
+    let mut entries = Vec::new();
+    for result in results {
+        let field = |key: &str| {
+            result
+                .get(key)
+                .and_then(Value::as_str)
+                .map(|value| value.to_string())
+                .ok_or(format!("a result has no {}", key))
+        };
+        entries.push(Entry {
+            name: field("name")?,
+            status: field("status")?,
+            duration: Duration::from_millis(
+                result
+                    .get("duration_ms")
+                    .and_then(Value::as_f64)
+                    .unwrap_or_default() as u64,
+            ),
+            similarity: result.get("similarity").and_then(Value::as_f64),
+            bench: result
+                .get("bench_ms")
+                .and_then(Value::as_array)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(Value::as_f64)
+                .map(|millis| Duration::from_millis(millis as u64))
+                .collect(),
+            tags: result
+                .get("tags")
+                .and_then(Value::as_array)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|tag| tag.as_str().map(|tag| tag.to_string()))
+                .collect(),
+        });
+    }
+    Ok(RecordedRun {
+        id: id.to_string(),
+        entries,
+    })
+}
+
+/// Loads the recorded run with the given identifier.
+///
+/// # Errors
+///
+/// Returns an error if the run is not recorded or its file is invalid.
+pub fn load(target: &str, run_id: &str) -> io::Result<RecordedRun> {
+    let path = results_root(target).join(format!("{}.{}", run_id, EXTENSION));
+    if !path.exists() {
+        return Err(io::Error::other(format!(
+            "run {} not found in the recorded results",
+            run_id
+        )));
+    }
+    parse(run_id, &fs::read_to_string(&path)?)
+        .map_err(|err| io::Error::other(format!("{}: {}", path.display(), err)))
+}
+
+/// Loads all the recorded runs, oldest first.
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be read or a file is invalid.
+pub fn load_all(target: &str) -> io::Result<Vec<RecordedRun>> {
+    ids(target)?.iter().map(|id| load(target, id)).collect()
+}
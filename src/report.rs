@@ -0,0 +1,675 @@
+//! Reports of a run written to files.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::atomic;
+use crate::results::{self, RegressionResult, RunReport};
+use crate::runner::format_elapsed;
+
+/// Output format of the commands that can also produce machine readable output.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    /// Human readable text
+    Text,
+    /// JSON
+    Json,
+}
+
+/// Format of the report written with `--report`.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// JSON, one entry per regression with its command, diff and error
+    Json,
+    /// JUnit XML, one test case per regression, for the test report uploaders
+    Junit,
+}
+
+/// Inline style sheet of the HTML report, which must not depend on external resources.
+const HTML_STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1.5em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+th { background: #eee; }
+.passed, .captured, .unchanged, .updated, .reset { color: #1a7f37; font-weight: bold; }
+.failed, .error { color: #cf222e; font-weight: bold; }
+.skipped { color: #9a6700; font-weight: bold; }
+details { margin: 0.5em 0; border: 1px solid #ccc; padding: 0.5em; }
+summary { cursor: pointer; font-weight: bold; }
+pre { background: #f6f8fa; padding: 0.5em; overflow-x: auto; }
+.add { color: #1a7f37; }
+.del { color: #cf222e; }
+.hunk { color: #8250df; }
+";
+
+/// Escapes a text for inclusion in HTML.
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Encodes a text as a JSON string literal.
+pub fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Renders a unified diff, coloring the added, removed and hunk header lines.
+fn render_diff(diff: &str) -> String {
+    let mut out = String::new();
+    for line in diff.lines() {
+        let class = if line.starts_with("+++") || line.starts_with("---") {
+            ""
+        } else if line.starts_with('+') {
+            "add"
+        } else if line.starts_with('-') {
+            "del"
+        } else if line.starts_with("@@") {
+            "hunk"
+        } else {
+            ""
+        };
+        if class.is_empty() {
+            out.push_str(&escape_html(line));
+        } else {
+            out.push_str(&format!(
+                "<span class=\"{}\">{}</span>",
+                class,
+                escape_html(line)
+            ));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders the details of a regression that did not pass.
+fn render_details(result: &RegressionResult) -> String {
+    let mut out = format!(
+        "<details id=\"{name}\">\n<summary>{name}: <span class=\"{status}\">{status}</span></summary>\n",
+        name = escape_html(&result.name),
+        status = escape_html(result.status)
+    );
+    out.push_str(&format!(
+        "<p>Command: <code>{}</code></p>\n",
+        escape_html(&result.command)
+    ));
+    if !result.error.is_empty() {
+        out.push_str(&format!("<p>Error: {}</p>\n", escape_html(&result.error)));
+    }
+    if !result.diff.is_empty() {
+        out.push_str(&format!(
+            "<h4>Diff (expected to generated)</h4>\n<pre>{}</pre>\n",
+            render_diff(&result.diff)
+        ));
+    }
+    if !result.output_tail.is_empty() {
+        out.push_str(&format!(
+            "<h4>Command output (last lines)</h4>\n<pre>{}</pre>\n",
+            escape_html(&result.output_tail)
+        ));
+    }
+    out.push_str("</details>\n");
+    out
+}
+
+/// Renders a run as a self-contained HTML page.
+pub fn render_html(report: &RunReport) -> String {
+    let started = &report.started;
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!(
+        "<title>bmregression run {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape_html(&report.run_id),
+        HTML_STYLE
+    ));
+    out.push_str(&format!(
+        "<h1>bmregression run {}</h1>\n",
+        escape_html(&report.run_id)
+    ));
+    out.push_str(&format!(
+        "<p>Started {:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC, took {}. {} passed, {} failed{}, {} errors, {} total.</p>\n",
+        started.year,
+        started.month,
+        started.day,
+        started.hour,
+        started.minute,
+        started.second,
+        format_elapsed(report.duration),
+        report.count("passed"),
+        report.count("failed"),
+        report.quarantined_note(),
+        report.count("error"),
+        report.results.len()
+    ));
+
+    let environment = &report.environment;
+    out.push_str("<h2>Environment</h2>\n<table>\n<tr><th>Host</th><th></th></tr>\n");
+    for (field, value) in environment.host() {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            field,
+            escape_html(&value)
+        ));
+    }
+    out.push_str("</table>\n<table>\n<tr><th>Tool</th><th>Version</th></tr>\n");
+    for (tool, version) in &environment.toolchain {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            escape_html(tool),
+            escape_html(version)
+        ));
+    }
+    out.push_str("</table>\n<table>\n<tr><th>Repository</th><th>Commit</th></tr>\n");
+    for (repository, commit) in &environment.commits {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td><code>{}</code></td></tr>\n",
+            escape_html(repository),
+            escape_html(commit)
+        ));
+    }
+    out.push_str("</table>\n<table>\n<tr><th>Option</th><th>Value</th></tr>\n");
+    for (option, value) in &environment.options {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td><code>{}</code></td></tr>\n",
+            escape_html(option),
+            escape_html(value)
+        ));
+    }
+    out.push_str("</table>\n");
+
+    out.push_str("<h2>Regressions</h2>\n<table>\n<tr><th>Name</th><th>Tags</th><th>Status</th><th>Duration</th></tr>\n");
+    for result in &report.results {
+        let name = if result.status == "passed" {
+            escape_html(&result.name)
+        } else {
+            format!(
+                "<a href=\"#{name}\">{name}</a>",
+                name = escape_html(&result.name)
+            )
+        };
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td>{:.1}s</td></tr>\n",
+            name,
+            escape_html(&result.tags.join(", ")),
+            escape_html(result.status),
+            escape_html(&result.status_with_similarity()),
+            result.duration.as_secs_f64()
+        ));
+    }
+    out.push_str("</table>\n");
+
+    let failures: Vec<&RegressionResult> = report
+        .results
+        .iter()
+        .filter(|r| r.status != "passed")
+        .collect();
+    if !failures.is_empty() {
+        out.push_str("<h2>Details</h2>\n");
+        for result in failures {
+            out.push_str(&render_details(result));
+        }
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Writes the HTML report of a run.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write_html(report: &RunReport, path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    atomic::write(path, render_html(report))
+}
+
+/// Encodes an optional text as a JSON string literal, `null` when empty.
+fn json_optional(text: &str) -> String {
+    if text.is_empty() {
+        "null".to_string()
+    } else {
+        json_string(text)
+    }
+}
+
+/// Renders the results of a run as a JSON document, each regression with its outcome
+/// telling the failures from the infrastructure errors.
+pub fn render_json(report: &RunReport) -> String {
+    let entries: Vec<String> = report
+        .results
+        .iter()
+        .map(|result| {
+            let tags: Vec<String> = result.tags.iter().map(|tag| json_string(tag)).collect();
+            let log = if result.log.is_empty() {
+                String::new()
+            } else {
+                format!("{}.out", result.log)
+            };
+            format!(
+                "    {{\"name\": {}, \"tags\": [{}], \"status\": {}, \"outcome\": {}, \"duration_seconds\": {:.3}, \"command\": {}, \"similarity\": {}, \"output_size\": {}, \"unreliable\": {}, \"log\": {}, \"error\": {}, \"diff\": {}}}",
+                json_string(&result.name),
+                tags.join(", "),
+                json_string(result.status),
+                json_string(results::outcome(result.status)),
+                result.duration.as_secs_f64(),
+                json_string(&result.command),
+                result.similarity_json(),
+                result.output_size_json(),
+                result.unreliable_json(),
+                json_optional(&log),
+                json_optional(&result.error),
+                json_optional(&result.diff)
+            )
+        })
+        .collect();
+    let outcomes = [
+        "passed",
+        "failure",
+        "infrastructure",
+        "quarantined",
+        "skipped",
+    ];
+    let counts: Vec<String> = outcomes
+        .iter()
+        .map(|outcome| {
+            let count = report
+                .results
+                .iter()
+                .filter(|result| results::outcome(result.status) == *outcome)
+                .count();
+            format!("{}: {}", json_string(outcome), count)
+        })
+        .collect();
+    format!(
+        "{{\n  \"run_id\": {},\n  \"started\": {},\n  \"duration_seconds\": {:.3},\n  \"total\": {},\n  \"outcomes\": {{{}}},\n  \"results\": [\n{}\n  ]\n}}\n",
+        json_string(&report.run_id),
+        json_string(&report.started.compact()),
+        report.duration.as_secs_f64(),
+        report.results.len(),
+        counts.join(", "),
+        entries.join(",\n")
+    )
+}
+
+/// Escapes a text for inclusion in XML, replacing the control characters XML 1.0 cannot hold.
+fn escape_xml(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .map(|c| match c {
+            '\t' | '\n' | '\r' => c,
+            c if (c as u32) < 0x20 => '\u{fffd}',
+            c => c,
+        })
+        .collect();
+    escape_html(&text)
+}
+
+/// Renders the results of a run as JUnit XML, one test case per regression: the failures
+/// carry their diff, the regressions that could not be executed are errors, and the
+/// quarantined failures and the skipped regressions are skipped.
+pub fn render_junit(report: &RunReport) -> String {
+    let count = |outcome: &str| {
+        report
+            .results
+            .iter()
+            .filter(|result| results::outcome(result.status) == outcome)
+            .count()
+    };
+    let (failures, errors) = (count("failure"), count("infrastructure"));
+    let skipped = count("quarantined") + count("skipped");
+    let totals = format!(
+        "tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\"",
+        report.results.len(),
+        failures,
+        errors,
+        skipped,
+        report.duration.as_secs_f64()
+    );
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!("<testsuites name=\"bmregression\" {}>\n", totals));
+    out.push_str(&format!(
+        "  <testsuite name=\"bmregression\" id=\"{}\" timestamp=\"{}T{}\" {}>\n",
+        escape_xml(&report.run_id),
+        report.started.date(),
+        report.started.time(),
+        totals
+    ));
+    for result in &report.results {
+        out.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"bmregression\" time=\"{:.3}\">\n",
+            escape_xml(&result.name),
+            result.duration.as_secs_f64()
+        ));
+        match results::outcome(result.status) {
+            "failure" => {
+                let message = match first_change(&result.diff) {
+                    Some(change) => format!("generated output differs: {}", change),
+                    None => "generated output differs".to_string(),
+                };
+                out.push_str(&format!(
+                    "      <failure message=\"{}\" type=\"failed\">{}</failure>\n",
+                    escape_xml(&message),
+                    escape_xml(&result.diff)
+                ));
+            }
+            "infrastructure" => {
+                let message = result.error.lines().next().unwrap_or(result.status);
+                out.push_str(&format!(
+                    "      <error message=\"{}\" type=\"{}\">{}</error>\n",
+                    escape_xml(message),
+                    escape_xml(result.status),
+                    escape_xml(&result.error)
+                ));
+            }
+            "quarantined" | "skipped" => out.push_str(&format!(
+                "      <skipped message=\"{}\"/>\n",
+                escape_xml(result.status)
+            )),
+            _ => {}
+        }
+        if !result.output_tail.is_empty() {
+            out.push_str(&format!(
+                "      <system-out>{}</system-out>\n",
+                escape_xml(&result.output_tail)
+            ));
+        }
+        out.push_str("    </testcase>\n");
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+/// Writes the report of a run given with `--report`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write_report(report: &RunReport, format: ReportFormat, path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    let rendered = match format {
+        ReportFormat::Json => render_json(report),
+        ReportFormat::Junit => render_junit(report),
+    };
+    atomic::write(path, rendered)
+}
+
+/// Renders the results of a run as a Markdown table.
+pub fn render_markdown(report: &RunReport) -> String {
+    let mut out = format!(
+        "### bmregression run {}\n\n{} passed, {} failed{}, {} errors, {} total in {}.\n\n",
+        report.run_id,
+        report.count("passed"),
+        report.count("failed"),
+        report.quarantined_note(),
+        report.count("error"),
+        report.results.len(),
+        format_elapsed(report.duration)
+    );
+    out.push_str("| Regression | Tags | Status | Duration |\n");
+    out.push_str("|---|---|---|---|\n");
+    for result in &report.results {
+        let status = match result.status {
+            "failed" | "error" => format!("**{}**", result.status_with_similarity()),
+            _ => result.status_with_similarity(),
+        };
+        out.push_str(&format!(
+            "| {} | {} | {} | {:.1}s |\n",
+            escape_markdown(&result.name),
+            escape_markdown(&result.tags.join(", ")),
+            status,
+            result.duration.as_secs_f64()
+        ));
+    }
+    out
+}
+
+/// Escapes the characters that would break a Markdown table cell.
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Appends the Markdown summary of a run to the GitHub Actions step summary file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn append_step_summary(report: &RunReport, path: &Path) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(render_markdown(report).as_bytes())
+}
+
+/// Returns the GitHub Actions workflow commands annotating the failed regressions.
+pub fn github_annotations(report: &RunReport) -> Vec<String> {
+    report
+        .results
+        .iter()
+        .filter_map(|result| match result.status {
+            "failed" => Some(format!(
+                "::error title=Regression failed::{}: {}",
+                escape_workflow_data(&result.name),
+                escape_workflow_data(first_change(&result.diff).unwrap_or("output differs"))
+            )),
+            "error" => Some(format!(
+                "::error title=Regression error::{}: {}",
+                escape_workflow_data(&result.name),
+                escape_workflow_data(&result.error)
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the first added or removed line of a unified diff.
+fn first_change(diff: &str) -> Option<&str> {
+    diff.lines().find(|line| {
+        (line.starts_with('+') || line.starts_with('-'))
+            && !line.starts_with("+++")
+            && !line.starts_with("---")
+    })
+}
+
+/// Escapes the message of a workflow command, which must fit on a single line.
+fn escape_workflow_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::Environment;
+    use crate::timestamp::DateTime;
+    use std::time::Duration;
+
+    fn result(name: &str, status: &'static str) -> RegressionResult {
+        RegressionResult {
+            name: name.to_string(),
+            tags: vec!["default".to_string()],
+            command: format!("make {} > out.txt", name),
+            status,
+            duration: Duration::from_millis(1250),
+            ..Default::default()
+        }
+    }
+
+    fn report(results: Vec<RegressionResult>) -> RunReport {
+        RunReport {
+            run_id: "20240401T020000Z-3fa9c1".to_string(),
+            started: DateTime::from_compact("20240401T020000Z").unwrap(),
+            duration: Duration::from_secs(95),
+            environment: Environment {
+                hostname: Some("builder".to_string()),
+                os_release: "Linux 6.1".to_string(),
+                cpus: 8,
+                commits: vec![("examples".to_string(), "3fa9c1".to_string())],
+                toolchain: vec![("yosys".to_string(), "0.38".to_string())],
+                options: vec![("jobs".to_string(), "4".to_string())],
+            },
+            results,
+        }
+    }
+
+    #[test]
+    fn escapes_the_texts_of_the_reports() {
+        assert_eq!(
+            escape_html("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+        assert_eq!(
+            json_string("say \"hi\"\\\n\tbye\r\u{1}"),
+            "\"say \\\"hi\\\"\\\\\\n\\tbye\\r\\u0001\""
+        );
+    }
+
+    #[test]
+    fn colors_the_lines_of_the_diffs() {
+        assert_eq!(
+            render_diff("--- expected\n+++ generated\n@@ -1 +1 @@\n-1\n+<2>\n same\n"),
+            "--- expected\n+++ generated\n<span class=\"hunk\">@@ -1 +1 @@</span>\n\
+             <span class=\"del\">-1</span>\n<span class=\"add\">+&lt;2&gt;</span>\n same\n"
+        );
+    }
+
+    #[test]
+    fn renders_the_run_as_a_page() {
+        let mut counter = result("counter", "failed");
+        counter.diff = "@@ -1 +1 @@\n-1\n+2\n".to_string();
+        counter.output_tail = "counting <done>\n".to_string();
+        let mut uart = result("uart", "error");
+        uart.error = "command exited with 2".to_string();
+        let html = render_html(&report(vec![result("blink", "passed"), counter, uart]));
+
+        assert!(html.starts_with("<!DOCTYPE html>\n"), "{}", html);
+        assert!(html.ends_with("</body>\n</html>\n"), "{}", html);
+        assert!(html.contains("<title>bmregression run 20240401T020000Z-3fa9c1</title>"));
+        assert!(html.contains(
+            "<p>Started 2024-04-01 02:00:00 UTC, took 1m. 1 passed, 1 failed, 1 errors, 3 total.</p>"
+        ));
+        for row in [
+            "<tr><td>hostname</td><td>builder</td></tr>",
+            "<tr><td>cpus</td><td>8</td></tr>",
+            "<tr><td>yosys</td><td>0.38</td></tr>",
+            "<tr><td>examples</td><td><code>3fa9c1</code></td></tr>",
+            "<tr><td>jobs</td><td><code>4</code></td></tr>",
+            "<tr><td>blink</td><td>default</td><td class=\"passed\">passed</td><td>1.2s</td></tr>",
+            "<tr><td><a href=\"#counter\">counter</a></td><td>default</td><td class=\"failed\">failed</td><td>1.2s</td></tr>",
+        ] {
+            assert!(html.contains(row), "{}\n{}", row, html);
+        }
+
+        // Only the regressions that did not pass are detailed
+        assert!(!html.contains("<details id=\"blink\">"), "{}", html);
+        assert!(html.contains(
+            "<details id=\"counter\">\n<summary>counter: <span class=\"failed\">failed</span></summary>\n\
+             <p>Command: <code>make counter &gt; out.txt</code></p>\n\
+             <h4>Diff (expected to generated)</h4>\n<pre><span class=\"hunk\">@@ -1 +1 @@</span>\n\
+             <span class=\"del\">-1</span>\n<span class=\"add\">+2</span>\n</pre>\n\
+             <h4>Command output (last lines)</h4>\n<pre>counting &lt;done&gt;\n</pre>\n</details>\n"
+        ), "{}", html);
+        assert!(
+            html.contains("<p>Error: command exited with 2</p>\n"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn leaves_out_the_details_when_everything_passed() {
+        let mut blink = result("blink", "passed");
+        blink.similarity = Some(99.5);
+        let html = render_html(&report(vec![blink]));
+        assert!(!html.contains("<h2>Details</h2>"), "{}", html);
+        assert!(
+            html.contains("<td class=\"passed\">passed (99.50%)</td>"),
+            "{}",
+            html
+        );
+        // Not even the self-contained page loads anything
+        assert!(!html.contains("<link"), "{}", html);
+        assert!(!html.contains("<script"), "{}", html);
+    }
+
+    #[test]
+    fn writes_the_page_creating_its_directory() {
+        let dir = tempdir::TempDir::new("report").unwrap();
+        let path = dir.path().join("reports/nightly/index.html");
+        write_html(&report(Vec::new()), &path).unwrap();
+        assert!(fs::read_to_string(path).unwrap().contains("0 total."));
+    }
+
+    #[test]
+    fn renders_the_step_summary_as_a_table() {
+        let mut counter = result("counter", "failed");
+        counter.tags = vec!["default".to_string(), "a|b".to_string()];
+        let mut blink = result("blink", "passed");
+        blink.similarity = Some(99.5);
+        let markdown = render_markdown(&report(vec![blink, counter]));
+        assert_eq!(
+            markdown,
+            "### bmregression run 20240401T020000Z-3fa9c1\n\n\
+             1 passed, 1 failed, 0 errors, 2 total in 1m.\n\n\
+             | Regression | Tags | Status | Duration |\n|---|---|---|---|\n\
+             | blink | default | passed (99.50%) | 1.2s |\n\
+             | counter | default, a\\|b | **failed** | 1.2s |\n"
+        );
+
+        let dir = tempdir::TempDir::new("report").unwrap();
+        let path = dir.path().join("step_summary.md");
+        fs::write(&path, "previous step\n").unwrap();
+        append_step_summary(&report(Vec::new()), &path).unwrap();
+        let appended = fs::read_to_string(&path).unwrap();
+        assert!(appended.starts_with("previous step\n### bmregression run "));
+    }
+
+    #[test]
+    fn annotates_the_failures_and_the_errors() {
+        let mut counter = result("counter", "failed");
+        counter.diff = "--- expected\n+++ generated\n@@ -1 +1 @@\n-100%\n+2\n".to_string();
+        let mut uart = result("uart", "error");
+        uart.error = "command failed\nwith exit code 2".to_string();
+        let spi = result("spi", "failed");
+        assert_eq!(
+            github_annotations(&report(vec![result("blink", "passed"), counter, uart, spi])),
+            [
+                "::error title=Regression failed::counter: -100%25",
+                "::error title=Regression error::uart: command failed%0Awith exit code 2",
+                "::error title=Regression failed::spi: output differs",
+            ]
+        );
+    }
+}
@@ -0,0 +1,711 @@
+//! Fetching of the examples and regression data repositories.
+//!
+//! The repository options accept three forms: remote git URLs, which are
+//! cloned; paths (or `file://` URLs) of local git repositories, which are
+//! cloned with `--local` so that the objects are hardlinked; and paths of plain
+//! directories, which are copied recursively.
+//!
+//! Clones are run with a timeout, as a hanging network would otherwise stall
+//! the whole run, and failed clones are retried with an increasing delay. On
+//! request the progress of git is relayed, prefixed with the repository, so
+//! that long clones do not look like a hang.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::os::unix::fs as unix_fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::interrupt;
+use crate::redact;
+use crate::runner::{self, CommandRunner};
+
+/// Delay before the first retry of a failed clone, doubled at every retry.
+const RETRY_DELAY: Duration = if cfg!(test) {
+    Duration::from_millis(100)
+} else {
+    Duration::from_secs(5)
+};
+
+/// How the git clones are run.
+#[derive(Clone)]
+pub struct CloneOptions {
+    /// Terminate a clone after this time, unlimited if None
+    pub timeout: Option<Duration>,
+    /// Number of times a failed clone is retried
+    pub retries: u32,
+    /// Relay the progress output of git
+    pub progress: bool,
+    /// Executes the git commands
+    pub runner: Arc<dyn CommandRunner>,
+}
+
+/// Where a repository is fetched from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Origin {
+    /// A git URL, cloned
+    Remote(String),
+    /// A local git repository, bare or not, cloned with hardlinks
+    LocalGit(PathBuf),
+    /// A plain directory, copied
+    Directory(PathBuf),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Origin::Remote(url) => write!(f, "remote git repository {}", redact::text(url)),
+            Origin::LocalGit(path) => write!(f, "local git repository {}", path.display()),
+            Origin::Directory(path) => write!(f, "plain directory {}", path.display()),
+        }
+    }
+}
+
+/// Parses the definition of a named repository, `name=location`.
+///
+/// # Errors
+///
+/// Returns an error if the definition has no `=`, or an empty or unusual name.
+pub fn parse_named(definition: &str) -> Result<(String, String), String> {
+    let Some((name, location)) = definition.split_once('=') else {
+        return Err(format!(
+            "invalid repository definition {:?}, expected name=location",
+            definition
+        ));
+    };
+    let valid_name = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if !valid_name {
+        return Err(format!(
+            "invalid repository name {:?}, expected letters, digits, '-', '_' and '.'",
+            name
+        ));
+    }
+    if location.is_empty() {
+        return Err(format!("repository {} has an empty location", name));
+    }
+    Ok((name.to_string(), location.to_string()))
+}
+
+/// Returns true if a location is a URL, or an scp-like `host:path`, rather than a path.
+fn is_url(location: &str) -> bool {
+    if location.contains("://") {
+        return true;
+    }
+    // As for git, a colon before the first slash makes a remote location
+    match (location.find(':'), location.find('/')) {
+        (Some(colon), Some(slash)) => colon < slash,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+/// Returns true if a directory is a git repository, either a work tree or a bare one.
+fn is_git_repository(dir: &Path) -> bool {
+    dir.join(".git").exists() || (dir.join("HEAD").is_file() && dir.join("objects").is_dir())
+}
+
+/// Determines where a repository option points to.
+///
+/// # Errors
+///
+/// Returns an error naming the path if a local location does not exist or is not a directory.
+pub fn classify(location: &str) -> io::Result<Origin> {
+    let path = match location.strip_prefix("file://") {
+        Some(path) => PathBuf::from(path),
+        None if is_url(location) => return Ok(Origin::Remote(location.to_string())),
+        None => PathBuf::from(location),
+    };
+    if !path.exists() {
+        return Err(io::Error::other(format!(
+            "{}: no such file or directory",
+            path.display()
+        )));
+    }
+    if !path.is_dir() {
+        return Err(io::Error::other(format!(
+            "{}: not a directory",
+            path.display()
+        )));
+    }
+    if is_git_repository(&path) {
+        Ok(Origin::LocalGit(path))
+    } else {
+        Ok(Origin::Directory(path))
+    }
+}
+
+/// Fetches a repository into the working directory.
+///
+/// An already existing checkout, left by a previous run with the same `--workdir`,
+/// is reused as it is.
+///
+/// # Errors
+///
+/// Returns an error naming the location and the reason if the repository
+/// cannot be cloned or copied, with the output of git for failed clones.
+pub fn fetch(
+    location: &str,
+    dest: &Path,
+    description: &str,
+    options: &CloneOptions,
+    debug: bool,
+) -> io::Result<()> {
+    if dest.exists() {
+        println!(
+            "Reusing existing {} repository in {}",
+            description,
+            dest.display()
+        );
+        return Ok(());
+    }
+
+    let origin = classify(location).map_err(|err| {
+        io::Error::other(format!(
+            "fetching {} repository failed: {}",
+            description, err
+        ))
+    })?;
+    if debug {
+        println!(
+            "Fetching {} repository from the {} to {}",
+            description,
+            origin,
+            dest.display()
+        );
+    }
+
+    let result = match &origin {
+        Origin::Remote(url) => {
+            clone_with_retries(url.as_ref(), dest, false, description, options, debug)
+        }
+        Origin::LocalGit(path) => {
+            clone_with_retries(path.as_os_str(), dest, true, description, options, debug)
+        }
+        Origin::Directory(path) => copy_dir(path, dest),
+    };
+    result.map_err(|err| {
+        io::Error::other(format!(
+            "fetching {} repository from the {} failed: {}",
+            description, origin, err
+        ))
+    })
+}
+
+/// Brings a fetched repository up to date with its location.
+///
+/// Clones are updated with a fast-forward `git pull`, under the timeout of the
+/// clones, and copies of plain directories are copied again.
+///
+/// # Errors
+///
+/// Returns an error naming the repository if it cannot be updated.
+pub fn refresh(
+    location: &str,
+    dest: &Path,
+    description: &str,
+    options: &CloneOptions,
+    debug: bool,
+) -> io::Result<()> {
+    let origin = classify(location)?;
+    if debug {
+        println!("Refreshing {} repository from the {}", description, origin);
+    }
+    let result = match &origin {
+        Origin::Remote(_) | Origin::LocalGit(_) => git_pull(dest, options),
+        Origin::Directory(path) => fs::remove_dir_all(dest).and_then(|()| copy_dir(path, dest)),
+    };
+    result.map_err(|err| {
+        io::Error::other(format!(
+            "refreshing {} repository from the {} failed: {}",
+            description, origin, err
+        ))
+    })
+}
+
+/// Updates a clone with a fast-forward pull.
+fn git_pull(dest: &Path, options: &CloneOptions) -> io::Result<()> {
+    let timeout = options.timeout;
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(dest)
+        .args(["pull", "--ff-only", "--quiet"]);
+    let completed = options.runner.run(
+        command,
+        &runner::RunOptions {
+            stream_prefix: None,
+            heartbeat: None,
+            timeout,
+        },
+    )?;
+    if completed.timed_out {
+        return Err(io::Error::other(format!(
+            "git pull timed out after {}",
+            runner::format_elapsed(timeout.unwrap_or_default())
+        )));
+    }
+    if !completed.output.status.success() {
+        let stderr = String::from_utf8_lossy(&completed.output.stderr);
+        return Err(io::Error::other(format!(
+            "git pull failed: {}",
+            stderr.trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Clones a git repository, retrying with an increasing delay when the clone fails.
+///
+/// The partial checkout of a failed attempt is removed, before the next one and after
+/// the last one, so that it is never taken for a checkout. No further attempt is made
+/// after an interrupt.
+fn clone_with_retries(
+    source: &std::ffi::OsStr,
+    dest: &Path,
+    local: bool,
+    description: &str,
+    options: &CloneOptions,
+    debug: bool,
+) -> io::Result<()> {
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        if debug {
+            println!(
+                "Cloning {} repository from {} (attempt {} of {})",
+                description,
+                redact::text(&source.to_string_lossy()),
+                attempt,
+                options.retries + 1
+            );
+        }
+        let progress = if options.progress {
+            Some(description)
+        } else {
+            None
+        };
+        let err = match git_clone(source, dest, local, options, progress) {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        if attempt > options.retries || interrupt::interrupted().is_some() {
+            // The clone error tells more than a failed removal
+            let _ = remove_partial(dest);
+            return Err(err);
+        }
+        if debug {
+            println!(
+                "Clone attempt {} of the {} repository failed, retrying in {}: {}",
+                attempt,
+                description,
+                runner::format_elapsed(delay),
+                err
+            );
+        }
+        remove_partial(dest)?;
+        sleep_unless_interrupted(delay);
+        if interrupt::interrupted().is_some() {
+            return Err(err);
+        }
+        delay *= 2;
+    }
+}
+
+/// Removes the partial checkout of a failed clone or copy, if any.
+fn remove_partial(dest: &Path) -> io::Result<()> {
+    if dest.exists() {
+        fs::remove_dir_all(dest)?;
+    }
+    Ok(())
+}
+
+/// Sleeps for the given time, returning early on an interrupt.
+fn sleep_unless_interrupted(duration: Duration) {
+    let step = Duration::from_millis(100);
+    let mut slept = Duration::ZERO;
+    while slept < duration && interrupt::interrupted().is_none() {
+        thread::sleep(step);
+        slept += step;
+    }
+}
+
+/// Clones a git repository, with `--local` to hardlink the objects of a local one.
+///
+/// The clone runs in its own process group, like the regression commands, so
+/// that a timeout or an interrupt terminates git with its helpers. With a
+/// progress prefix git reports its progress, relayed with that prefix.
+fn git_clone(
+    source: &std::ffi::OsStr,
+    dest: &Path,
+    local: bool,
+    options: &CloneOptions,
+    progress: Option<&str>,
+) -> io::Result<()> {
+    let timeout = options.timeout;
+    let mut command = Command::new("git");
+    command.arg("clone");
+    if local {
+        command.arg("--local");
+    }
+    // git only reports the progress to a terminal unless asked to
+    if progress.is_some() {
+        command.arg("--progress");
+    }
+    command.arg(source).arg(dest);
+    let completed = options.runner.run(
+        command,
+        &runner::RunOptions {
+            stream_prefix: progress,
+            heartbeat: None,
+            timeout,
+        },
+    )?;
+    if completed.timed_out {
+        return Err(io::Error::other(format!(
+            "git clone timed out after {}",
+            runner::format_elapsed(timeout.unwrap_or_default())
+        )));
+    }
+    if completed.output.status.success() {
+        return Ok(());
+    }
+    // All of git's stderr but the progress, the reason is not always on the last line
+    let stderr = String::from_utf8_lossy(&completed.output.stderr);
+    let lines: Vec<&str> = stderr
+        .split(['\n', '\r'])
+        .map(|line| line.trim_end())
+        .filter(|line| !line.is_empty() && !line.starts_with("Cloning into"))
+        .filter(|line| !is_progress(line))
+        .collect();
+    if lines.is_empty() {
+        return Err(io::Error::other("git clone failed"));
+    }
+    Err(io::Error::other(format!(
+        "git clone failed:\n  {}",
+        lines.join("\n  ")
+    )))
+}
+
+/// Returns true if a line of git's stderr reports the progress of a transfer.
+fn is_progress(line: &str) -> bool {
+    const PROGRESS: [&str; 5] = [
+        "remote: ",
+        "Receiving objects:",
+        "Resolving deltas:",
+        "Updating files:",
+        "Checking out files:",
+    ];
+    PROGRESS.iter().any(|prefix| line.starts_with(prefix)) && line.contains('%')
+}
+
+/// Copies a directory recursively, recreating the symbolic links it contains.
+///
+/// A destination created by the copy is removed again if the copy fails.
+pub fn copy_dir(source: &Path, dest: &Path) -> io::Result<()> {
+    let created = !dest.exists();
+    let copied = copy_tree(source, dest);
+    if copied.is_err() && created {
+        let _ = remove_partial(dest);
+    }
+    copied
+}
+
+/// Copies the files of a directory into another, created if needed.
+fn copy_tree(source: &Path, dest: &Path) -> io::Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let from = entry.path();
+        let to = dest.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_tree(&from, &to)?;
+            continue;
+        }
+        let copied = if file_type.is_symlink() {
+            fs::read_link(&from).and_then(|link| unix_fs::symlink(link, &to))
+        } else {
+            fs::copy(&from, &to).map(|_| ())
+        };
+        copied.map_err(|err| io::Error::other(format!("copying {}: {}", from.display(), err)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runner::scripted::{Scripted, ScriptedRunner};
+    use tempdir::TempDir;
+
+    /// Returns the options cloning with git itself, without timeout nor retries.
+    fn options() -> CloneOptions {
+        CloneOptions {
+            timeout: None,
+            retries: 0,
+            progress: false,
+            runner: Arc::new(runner::ProcessRunner),
+        }
+    }
+
+    /// Runs git in `dir`, panicking if it fails.
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .current_dir(dir)
+            .args([
+                "-c",
+                "user.name=alice",
+                "-c",
+                "user.email=alice@example.com",
+            ])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    #[test]
+    fn retries_the_failed_clones() {
+        let dir = TempDir::new("repository").unwrap();
+        let dest = dir.path().join("examples");
+        let failed = Scripted {
+            code: 128,
+            stderr: "fatal: unable to access: Could not resolve host\n".to_string(),
+            ..Default::default()
+        };
+        let clone = |results: Vec<Scripted>, retries: u32| {
+            let runner = Arc::new(ScriptedRunner::new(results));
+            let options = CloneOptions {
+                retries,
+                runner: runner.clone(),
+                ..options()
+            };
+            let result = fetch(
+                "https://example.invalid/bmexamples.git",
+                &dest,
+                "examples",
+                &options,
+                false,
+            );
+            (result, runner.commands().len())
+        };
+
+        let (result, attempts) = clone(vec![failed.clone(), Scripted::default()], 2);
+        assert!(result.is_ok());
+        assert_eq!(attempts, 2);
+
+        let (result, attempts) = clone(vec![failed.clone(), failed.clone(), failed], 2);
+        assert_eq!(attempts, 3);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "fetching examples repository from the remote git repository https://example.invalid/bmexamples.git failed: git clone failed:\n  fatal: unable to access: Could not resolve host"
+        );
+    }
+
+    #[test]
+    fn asks_git_for_its_progress() {
+        let dir = TempDir::new("repository").unwrap();
+        let dest = dir.path().join("examples");
+        let clone = |progress: bool, result: Scripted| {
+            let runner = Arc::new(ScriptedRunner::new(vec![result]));
+            let options = CloneOptions {
+                progress,
+                runner: runner.clone(),
+                ..options()
+            };
+            let result = fetch(
+                "https://example.invalid/bmexamples.git",
+                &dest,
+                "examples",
+                &options,
+                false,
+            );
+            (result, runner.commands())
+        };
+        let command = |progress: &str| {
+            format!(
+                "git clone {}https://example.invalid/bmexamples.git {}",
+                progress,
+                dest.display()
+            )
+        };
+
+        let (result, commands) = clone(true, Scripted::default());
+        assert!(result.is_ok());
+        assert_eq!(commands, vec![command("--progress ")]);
+        let (_, commands) = clone(false, Scripted::default());
+        assert_eq!(commands, vec![command("")]);
+
+        // The progress is left out of the reason of a failure
+        let (result, _) = clone(
+            true,
+            Scripted {
+                code: 128,
+                stderr: "Cloning into 'examples'...\nremote: Counting objects: 100% (5/5)\rReceiving objects:  40% (2/5)\rerror: RPC failed\nfatal: early EOF\n"
+                    .to_string(),
+                ..Default::default()
+            },
+        );
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .ends_with("git clone failed:\n  error: RPC failed\n  fatal: early EOF"));
+    }
+
+    #[test]
+    fn recognizes_the_progress_lines() {
+        assert!(is_progress(
+            "Receiving objects:  40% (2/5), 1.2 MiB | 3 MiB/s"
+        ));
+        assert!(is_progress(
+            "remote: Compressing objects: 100% (3/3), done."
+        ));
+        assert!(is_progress("Updating files: 100% (12/12), done."));
+        assert!(!is_progress("remote: Repository not found."));
+        assert!(!is_progress("fatal: unable to access '100%': timeout"));
+    }
+
+    #[test]
+    fn tells_the_urls_from_the_paths() {
+        for url in [
+            "https://github.com/BondMachineHQ/bmexamples.git",
+            "ssh://git@github.com/bmexamples",
+            "git@github.com:BondMachineHQ/bmexamples.git",
+            "mirror:bmexamples",
+        ] {
+            assert!(is_url(url), "{}", url);
+        }
+        for path in [
+            "/srv/mirrors/bmexamples.git",
+            "mirrors/a:b",
+            "bmexamples",
+            "./a:b",
+        ] {
+            assert!(!is_url(path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn classifies_the_locations() {
+        let dir = TempDir::new("repository").unwrap();
+        let plain = dir.path().join("plain");
+        let work_tree = dir.path().join("work");
+        let bare = dir.path().join("bare.git");
+        fs::create_dir_all(&plain).unwrap();
+        fs::create_dir_all(&work_tree).unwrap();
+        git(&work_tree, &["init", "-q"]);
+        git(dir.path(), &["init", "-q", "--bare", "bare.git"]);
+        fs::write(dir.path().join("file"), "").unwrap();
+
+        let url = "https://github.com/BondMachineHQ/bmexamples.git";
+        assert_eq!(classify(url).unwrap(), Origin::Remote(url.to_string()));
+        assert_eq!(
+            classify(plain.to_str().unwrap()).unwrap(),
+            Origin::Directory(plain.clone())
+        );
+        assert_eq!(
+            classify(&format!("file://{}", plain.display())).unwrap(),
+            Origin::Directory(plain)
+        );
+        assert_eq!(
+            classify(work_tree.to_str().unwrap()).unwrap(),
+            Origin::LocalGit(work_tree)
+        );
+        assert_eq!(
+            classify(&format!("file://{}", bare.display())).unwrap(),
+            Origin::LocalGit(bare.clone())
+        );
+        assert_eq!(
+            Origin::LocalGit(bare.clone()).to_string(),
+            format!("local git repository {}", bare.display())
+        );
+
+        let missing = dir.path().join("missing");
+        let err = classify(&format!("file://{}", missing.display())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("{}: no such file or directory", missing.display())
+        );
+        let file = dir.path().join("file");
+        let err = classify(file.to_str().unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("{}: not a directory", file.display())
+        );
+    }
+
+    #[test]
+    fn fetches_the_local_repositories() {
+        let dir = TempDir::new("repository").unwrap();
+        let plain = dir.path().join("plain");
+        fs::create_dir_all(plain.join("blink")).unwrap();
+        fs::write(plain.join("blink/out.txt"), "blink\n").unwrap();
+        unix_fs::symlink("blink/out.txt", plain.join("link")).unwrap();
+        let work_tree = dir.path().join("work");
+        fs::create_dir_all(&work_tree).unwrap();
+        fs::write(work_tree.join("counter.v"), "module counter;\n").unwrap();
+        git(&work_tree, &["init", "-q"]);
+        git(&work_tree, &["add", "counter.v"]);
+        git(&work_tree, &["commit", "-q", "-m", "Add the counter"]);
+        fs::write(work_tree.join("untracked"), "").unwrap();
+
+        let copied = dir.path().join("checkouts/plain");
+        fetch(
+            &format!("file://{}", plain.display()),
+            &copied,
+            "examples",
+            &options(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(copied.join("blink/out.txt")).unwrap(),
+            "blink\n"
+        );
+        assert_eq!(
+            fs::read_link(copied.join("link")).unwrap(),
+            Path::new("blink/out.txt")
+        );
+
+        // Only what is committed is cloned
+        let cloned = dir.path().join("checkouts/work");
+        fetch(
+            work_tree.to_str().unwrap(),
+            &cloned,
+            "data",
+            &options(),
+            false,
+        )
+        .unwrap();
+        assert!(cloned.join(".git").is_dir());
+        assert!(cloned.join("counter.v").is_file());
+        assert!(!cloned.join("untracked").exists());
+
+        let missing = dir.path().join("missing");
+        let err = fetch(
+            missing.to_str().unwrap(),
+            &dir.path().join("checkouts/missing"),
+            "data",
+            &options(),
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "fetching data repository failed: {}: no such file or directory",
+                missing.display()
+            )
+        );
+    }
+}
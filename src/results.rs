@@ -0,0 +1,289 @@
+//! Structured results of the executed regressions.
+//!
+//! Every report format is rendered from these structures, so that the
+//! different outputs of a run cannot disagree with each other.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use crate::timestamp::DateTime;
+
+/// Result of a single regression.
+#[derive(Debug, Clone, Default)]
+pub struct RegressionResult {
+    /// Name of the regression
+    pub name: String,
+    /// Tags of the regression
+    pub tags: Vec<String>,
+    /// Command executed to generate the output
+    pub command: String,
+    /// Short status: passed, failed, error, captured, ...
+    pub status: &'static str,
+    /// Time spent on the regression, command execution included
+    pub duration: Duration,
+    /// Unified diff from the expected to the generated output, for failures
+    pub diff: String,
+    /// Last lines of the command output
+    pub output_tail: String,
+    /// Path of the command logs without the `.out` and `.err` extensions, empty if not written
+    pub log: String,
+    /// Error message, for regressions that could not be executed
+    pub error: String,
+    /// Percentage of identical lines, for the regressions compared by similarity
+    pub similarity: Option<f64>,
+    /// Size in bytes of the generated output file as stored, if it was found
+    pub output_size: Option<u64>,
+    /// Durations of the command in the timed iterations of `run --bench`, empty otherwise
+    pub bench: Vec<Duration>,
+    /// What changed in the examples checkout while the command ran, making the result
+    /// unreliable, None if nothing did or it was not checked
+    pub unreliable: Option<String>,
+    /// Lines printed for the regression: its status, differences, warnings and error
+    pub output: String,
+}
+
+impl RegressionResult {
+    /// Returns the measured similarity as a JSON number, `null` for the regressions compared exactly.
+    pub fn similarity_json(&self) -> String {
+        match self.similarity {
+            Some(similarity) => format!("{:.2}", similarity),
+            None => "null".to_string(),
+        }
+    }
+
+    /// Returns the size of the generated output as a JSON number, `null` if it was not found.
+    pub fn output_size_json(&self) -> String {
+        match self.output_size {
+            Some(size) => size.to_string(),
+            None => "null".to_string(),
+        }
+    }
+
+    /// Returns the spread of the benchmarked durations as a JSON object, `null` if the
+    /// regression was not benchmarked.
+    pub fn bench_json(&self) -> String {
+        match Spread::of(&self.bench) {
+            Some(spread) => spread.json(),
+            None => "null".to_string(),
+        }
+    }
+
+    /// Returns what made the result unreliable as a JSON string, `null` if it is reliable.
+    pub fn unreliable_json(&self) -> String {
+        match &self.unreliable {
+            Some(changes) => crate::report::json_string(changes),
+            None => "null".to_string(),
+        }
+    }
+
+    /// Returns the status followed by the measured similarity, if any.
+    pub fn status_with_similarity(&self) -> String {
+        match self.similarity {
+            Some(similarity) => format!("{} ({:.2}%)", self.status, similarity),
+            None => self.status.to_string(),
+        }
+    }
+}
+
+/// Spread of the durations of the iterations of a benchmarked command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spread {
+    pub iterations: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+}
+
+impl Spread {
+    /// Returns the spread of some durations, None if there are none. The median of an even
+    /// number of durations is the mean of the two middle ones.
+    pub fn of(durations: &[Duration]) -> Option<Spread> {
+        let mut sorted = durations.to_vec();
+        sorted.sort();
+        let middle = sorted.len() / 2;
+        let median = if sorted.len().is_multiple_of(2) {
+            (*sorted.get(middle.checked_sub(1)?)? + sorted[middle]) / 2
+        } else {
+            sorted[middle]
+        };
+        Some(Spread {
+            iterations: sorted.len(),
+            min: sorted[0],
+            median,
+            max: *sorted.last()?,
+        })
+    }
+
+    /// Renders the spread as a JSON object, the durations in seconds.
+    pub fn json(&self) -> String {
+        format!(
+            "{{\"iterations\": {}, \"min_seconds\": {:.3}, \"median_seconds\": {:.3}, \"max_seconds\": {:.3}}}",
+            self.iterations,
+            self.min.as_secs_f64(),
+            self.median.as_secs_f64(),
+            self.max.as_secs_f64()
+        )
+    }
+}
+
+impl fmt::Display for Spread {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} iterations, min {:.2}s, median {:.2}s, max {:.2}s",
+            self.iterations,
+            self.min.as_secs_f64(),
+            self.median.as_secs_f64(),
+            self.max.as_secs_f64()
+        )
+    }
+}
+
+/// Snapshot of the environment a run was started in.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    /// Name of the host, `None` when redacted
+    pub hostname: Option<String>,
+    /// Operating system release and kernel version
+    pub os_release: String,
+    /// Number of CPUs available to the run
+    pub cpus: usize,
+    /// Checked out commits, as (repository, commit) pairs
+    pub commits: Vec<(String, String)>,
+    /// Detected tool versions, as (tool, version) pairs
+    pub toolchain: Vec<(String, String)>,
+    /// Effective command line options, as (option, value) pairs
+    pub options: Vec<(String, String)>,
+}
+
+impl Environment {
+    /// Returns the description of the host, as (field, value) pairs.
+    pub fn host(&self) -> Vec<(&'static str, String)> {
+        let mut host = Vec::new();
+        if let Some(hostname) = &self.hostname {
+            host.push(("hostname", hostname.clone()));
+        }
+        host.push(("os", self.os_release.clone()));
+        host.push(("cpus", self.cpus.to_string()));
+        host
+    }
+}
+
+/// Results of a whole run, with the context they were produced in.
+pub struct RunReport {
+    /// Identifier of the run
+    pub run_id: String,
+    /// When the run started
+    pub started: DateTime,
+    /// Duration of the whole run
+    pub duration: Duration,
+    /// Environment the run was started in
+    pub environment: Environment,
+    /// Results of the executed regressions, in execution order
+    pub results: Vec<RegressionResult>,
+}
+
+impl RunReport {
+    /// Returns the number of regressions with the given status.
+    pub fn count(&self, status: &str) -> usize {
+        self.results.iter().filter(|r| r.status == status).count()
+    }
+
+    /// Returns the counts of the results of each group, in the order of the group names.
+    pub fn groups(&self, by: GroupBy) -> Vec<(String, GroupCounts)> {
+        let mut groups: BTreeMap<String, GroupCounts> = BTreeMap::new();
+        for result in &self.results {
+            for key in by.keys(result) {
+                let counts = groups.entry(key).or_default();
+                counts.total += 1;
+                match result.status {
+                    "passed" => counts.passed += 1,
+                    "failed" => counts.failed += 1,
+                    "error" => counts.errors += 1,
+                    _ => {}
+                }
+            }
+        }
+        groups.into_iter().collect()
+    }
+
+    /// Returns the count of quarantined failures to add to the summaries, empty if none.
+    pub fn quarantined_note(&self) -> String {
+        match self.count("quarantined failure") {
+            0 => String::new(),
+            count => format!(", {} quarantined failures", count),
+        }
+    }
+}
+
+/// What the results are grouped by in the summaries of a run.
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum GroupBy {
+    /// The tags of the regressions, a regression with several tags counting in each
+    Tag,
+}
+
+impl GroupBy {
+    /// Returns the name of the criterion, as given on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            GroupBy::Tag => "tag",
+        }
+    }
+
+    /// Returns the groups a result belongs to, each once.
+    pub fn keys(self, result: &RegressionResult) -> Vec<String> {
+        let mut keys = match self {
+            GroupBy::Tag => result.tags.clone(),
+        };
+        keys.sort();
+        keys.dedup();
+        if keys.is_empty() {
+            keys.push("(none)".to_string());
+        }
+        keys
+    }
+}
+
+/// Counts of the results of a group of regressions.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GroupCounts {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub errors: usize,
+}
+
+/// Returns true if a status counts as a success in reports and statistics.
+pub fn is_pass(status: &str) -> bool {
+    matches!(status, "passed" | "captured")
+}
+
+/// Status of the regressions not run because the time budget of the run was spent.
+pub const NOT_RUN: &str = "not run";
+
+/// Returns true if a status counts as a failure in reports and statistics.
+pub fn is_failure(status: &str) -> bool {
+    matches!(status, "failed" | "error")
+}
+
+/// Returns what a status means for a CI job: `passed`, `failure` when the generated
+/// output is wrong, `infrastructure` when the regression could not be executed,
+/// `quarantined` or `skipped`.
+pub fn outcome(status: &str) -> &'static str {
+    match status {
+        "passed" | "captured" => "passed",
+        "failed" => "failure",
+        "error" | "blocked" => "infrastructure",
+        "quarantined failure" => "quarantined",
+        _ => "skipped",
+    }
+}
+
+/// Returns the last `count` lines of a text.
+pub fn tail_lines(text: &str, count: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines.len().saturating_sub(count);
+    lines[start..].join("\n")
+}
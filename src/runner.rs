@@ -0,0 +1,479 @@
+//! Execution of regression commands.
+//!
+//! Commands are spawned in their own session, and so in their own process group
+//! (see [`crate::interrupt`]), with their stdout and stderr read by dedicated
+//! threads, so that the output can be relayed live while still being captured
+//! for the logs. On timeout or interrupt the whole group is terminated, the
+//! children of the command such as make and vivado included: they are sent
+//! SIGTERM, then SIGKILL if any of them is still running after a grace period,
+//! even once the command itself has exited.
+//!
+//! The regression commands and the git clones go through a [`CommandRunner`],
+//! the [`ProcessRunner`] spawning them, so that the tests can replace the
+//! processes with scripted results.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::interrupt;
+use crate::redact;
+
+/// Options controlling how a command is executed.
+pub struct RunOptions<'a> {
+    /// Relay the command output live, each line prefixed with this string
+    pub stream_prefix: Option<&'a str>,
+    /// Print a heartbeat line for this name at every interval while the command runs
+    pub heartbeat: Option<(&'a str, Duration)>,
+    /// Terminate the command, with its whole process group, after this time
+    pub timeout: Option<Duration>,
+}
+
+/// A command that ran to completion or was terminated on timeout.
+pub struct Completed {
+    /// Exit status and captured output
+    pub output: Output,
+    /// The command was terminated because it exceeded its timeout
+    pub timed_out: bool,
+}
+
+/// Executes the commands, spawning processes or, in the tests, replaying scripted results.
+pub trait CommandRunner: Send + Sync {
+    /// Executes a command and waits for it, capturing its stdout and stderr.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be spawned or waited for.
+    fn run(&self, command: Command, options: &RunOptions) -> io::Result<Completed>;
+}
+
+/// Executes the commands as processes, see [`run`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessRunner;
+
+impl CommandRunner for ProcessRunner {
+    fn run(&self, command: Command, options: &RunOptions) -> io::Result<Completed> {
+        run(command, options)
+    }
+}
+
+/// How often a running command is polled for termination.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time a terminated command and its children are given to exit after SIGTERM, before SIGKILL.
+const KILL_GRACE: Duration = if cfg!(test) {
+    Duration::from_millis(500)
+} else {
+    Duration::from_secs(5)
+};
+
+/// Sends a signal to every process of a group, returning false if none is left.
+fn signal_group(group: i32, signal: libc::c_int) -> bool {
+    // A negative pid targets the whole process group of the command
+    unsafe { libc::kill(-group, signal) == 0 }
+}
+
+/// Waits for the processes left in the group of a terminated command, killing them once
+/// the grace period from `terminated_at` is over.
+///
+/// The processes that ignore SIGTERM can outlive the command and keep holding the files
+/// of the example. Exited processes not yet reaped by their new parent still count as
+/// members of the group, so the wait ends with the SIGKILL, not when the group is empty.
+fn kill_group(group: i32, terminated_at: Instant) {
+    while signal_group(group, 0) {
+        if terminated_at.elapsed() >= KILL_GRACE {
+            signal_group(group, libc::SIGKILL);
+            return;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Spawns a command and waits for it, capturing its stdout and stderr.
+///
+/// # Errors
+///
+/// Returns an error if the command cannot be spawned or waited for.
+pub fn run(mut command: Command, options: &RunOptions) -> io::Result<Completed> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // SAFETY: setsid is async-signal-safe, the only call between fork and exec
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut child = command.spawn()?;
+    interrupt::set_child_group(child.id());
+
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let prefix = options.stream_prefix.map(|p| p.to_string());
+    let stdout_reader = spawn_reader(stdout, prefix.clone(), false);
+    let stderr_reader = spawn_reader(stderr, prefix, true);
+
+    // Poll the command instead of blocking on it, so that heartbeats can be printed
+    let start = Instant::now();
+    let mut next_heartbeat = options.heartbeat.map(|(_, interval)| interval);
+    let group = child.id() as i32;
+    let mut terminated_at: Option<Instant> = None;
+    let mut timed_out = false;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) => {}
+            Err(err) => break Err(err),
+        }
+        match terminated_at {
+            None if options
+                .timeout
+                .is_some_and(|timeout| start.elapsed() >= timeout) =>
+            {
+                signal_group(group, libc::SIGTERM);
+                terminated_at = Some(Instant::now());
+                timed_out = true;
+            }
+            // The signal handler has already sent SIGTERM to the group
+            None if interrupt::aborted() => terminated_at = Some(Instant::now()),
+            Some(at) if at.elapsed() >= KILL_GRACE => {
+                signal_group(group, libc::SIGKILL);
+            }
+            _ => {}
+        }
+        if let (Some((name, interval)), Some(next)) = (options.heartbeat, next_heartbeat) {
+            let elapsed = start.elapsed();
+            if elapsed >= next {
+                println!(
+                    "\u{2026} still running {} (elapsed {})",
+                    name,
+                    format_elapsed(elapsed)
+                );
+                next_heartbeat = Some(next + interval);
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    if let Some(at) = terminated_at {
+        kill_group(group, at);
+    }
+    interrupt::clear_child_group(child.id());
+    let status = status?;
+
+    let stdout = stdout_reader.join().unwrap_or_default();
+    let stderr = stderr_reader.join().unwrap_or_default();
+    Ok(Completed {
+        output: Output {
+            status,
+            stdout,
+            stderr,
+        },
+        timed_out,
+    })
+}
+
+/// Starts a thread collecting everything from a pipe, optionally relaying it line by line.
+///
+/// Carriage returns end a relayed line too, and are relayed as they are, so that
+/// progress output such as git's keeps updating a single line.
+fn spawn_reader<R: Read + Send + 'static>(
+    pipe: R,
+    prefix: Option<String>,
+    to_stderr: bool,
+) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut captured = Vec::new();
+        let mut reader = BufReader::new(pipe);
+        let mut line = Vec::new();
+        let mut after_cr = false;
+        let write_out = |text: &str| {
+            if to_stderr {
+                let _ = write!(io::stderr(), "{}", text);
+            } else {
+                let _ = write!(io::stdout(), "{}", text);
+            }
+        };
+        let relay = |line: &[u8], end: &str| {
+            if let Some(prefix) = &prefix {
+                write_out(&format!(
+                    "[{}] {}{}",
+                    prefix,
+                    redact::text(&String::from_utf8_lossy(line)),
+                    end
+                ));
+            }
+        };
+        loop {
+            let consumed = match reader.fill_buf() {
+                Ok([]) | Err(_) => break,
+                Ok(buf) => {
+                    captured.extend_from_slice(buf);
+                    for &byte in buf {
+                        match byte {
+                            // The newline of a CRLF completes the line relayed at the CR
+                            b'\n' if after_cr => {
+                                if prefix.is_some() {
+                                    write_out("\n");
+                                }
+                            }
+                            b'\n' => relay(&line, "\n"),
+                            b'\r' => relay(&line, "\r"),
+                            _ => line.push(byte),
+                        }
+                        if matches!(byte, b'\n' | b'\r') {
+                            line.clear();
+                        }
+                        after_cr = byte == b'\r';
+                    }
+                    buf.len()
+                }
+            };
+            reader.consume(consumed);
+        }
+        if !line.is_empty() {
+            relay(&line, "\n");
+        }
+        captured
+    })
+}
+
+/// Formats a duration for humans, e.g. `45s`, `14m`, `1h02m`.
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+/// Returns the name of a signal, e.g. `SIGSEGV`, or its number if it is not a common one.
+pub fn signal_name(signal: i32) -> String {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        _ => return format!("signal {}", signal),
+    };
+    name.to_string()
+}
+
+/// Describes how a command ended, e.g. `exit code 2` or `killed by SIGSEGV`.
+///
+/// A command run through the shell reports a child killed by a signal with the exit code
+/// 128 plus the signal, which is named too.
+pub fn describe_status(status: &ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) if code > 128 && code < 128 + 32 => {
+            format!(
+                "exit code {}, {} in the shell",
+                code,
+                signal_name(code - 128)
+            )
+        }
+        (Some(code), _) => format!("exit code {}", code),
+        (None, Some(signal)) => format!("killed by {}", signal_name(signal)),
+        (None, None) => status.to_string(),
+    }
+}
+
+/// A runner replaying scripted results, for the tests.
+#[cfg(test)]
+pub mod scripted {
+    use std::collections::VecDeque;
+    use std::fs;
+    use std::io;
+    use std::os::unix::process::ExitStatusExt;
+    use std::path::PathBuf;
+    use std::process::{Command, ExitStatus, Output};
+    use std::sync::Mutex;
+
+    use super::{CommandRunner, Completed, RunOptions};
+
+    /// The result of a scripted command.
+    #[derive(Debug, Clone, Default)]
+    pub struct Scripted {
+        /// Exit code, ignored if the command times out
+        pub code: i32,
+        pub stdout: String,
+        pub stderr: String,
+        pub timed_out: bool,
+        /// Files written by the command, relative to its directory, with their content
+        pub writes: Vec<(String, String)>,
+        /// The command cannot be spawned, with this kind of error
+        pub spawn_error: Option<io::ErrorKind>,
+    }
+
+    impl Scripted {
+        /// A command exiting with a code.
+        pub fn exit(code: i32) -> Scripted {
+            Scripted {
+                code,
+                ..Default::default()
+            }
+        }
+
+        /// A command succeeding after writing a file.
+        pub fn writing(path: &str, content: &str) -> Scripted {
+            Scripted {
+                writes: vec![(path.to_string(), content.to_string())],
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Replays scripted results in order and records the commands, as their command lines.
+    #[derive(Debug, Default)]
+    pub struct ScriptedRunner {
+        results: Mutex<VecDeque<Scripted>>,
+        commands: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedRunner {
+        pub fn new(results: Vec<Scripted>) -> ScriptedRunner {
+            ScriptedRunner {
+                results: Mutex::new(results.into()),
+                commands: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Returns the commands run so far.
+        pub fn commands(&self) -> Vec<String> {
+            self.commands.lock().unwrap().clone()
+        }
+    }
+
+    impl CommandRunner for ScriptedRunner {
+        fn run(&self, command: Command, options: &RunOptions) -> io::Result<Completed> {
+            let mut line = vec![command.get_program().to_string_lossy().to_string()];
+            line.extend(
+                command
+                    .get_args()
+                    .map(|arg| arg.to_string_lossy().to_string()),
+            );
+            self.commands.lock().unwrap().push(line.join(" "));
+            let scripted = self
+                .results
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_else(|| panic!("no scripted result for {}", line.join(" ")));
+            if let Some(kind) = scripted.spawn_error {
+                return Err(io::Error::from(kind));
+            }
+            let dir = command
+                .get_current_dir()
+                .map(PathBuf::from)
+                .unwrap_or_default();
+            for (path, content) in &scripted.writes {
+                fs::write(dir.join(path), content)?;
+            }
+            // A timeout only happens when one is set
+            let timed_out = scripted.timed_out && options.timeout.is_some();
+            Ok(Completed {
+                output: Output {
+                    status: if timed_out {
+                        ExitStatus::from_raw(libc::SIGTERM)
+                    } else {
+                        ExitStatus::from_raw(scripted.code << 8)
+                    },
+                    stdout: scripted.stdout.into_bytes(),
+                    stderr: scripted.stderr.into_bytes(),
+                },
+                timed_out,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempdir::TempDir;
+
+    /// Returns true if a process is running, neither gone nor waiting to be reaped.
+    fn running(pid: &str) -> bool {
+        match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => !stat
+                .rsplit_once(") ")
+                .is_some_and(|(_, fields)| fields.starts_with('Z')),
+            Err(_) => false,
+        }
+    }
+
+    #[test]
+    fn kills_the_children_of_a_timed_out_command() {
+        let dir = TempDir::new("runner").unwrap();
+        let pid_file = dir.path().join("child.pid");
+        // The child ignores SIGTERM and outlives the shell, which exits on it
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg("(trap '' TERM; exec sleep 30) >/dev/null 2>&1 & echo $! > \"$0\"; wait")
+            .arg(&pid_file);
+        let options = RunOptions {
+            stream_prefix: None,
+            heartbeat: None,
+            timeout: Some(Duration::from_millis(300)),
+        };
+        let completed = run(command, &options).unwrap();
+        assert!(completed.timed_out);
+
+        let pid = fs::read_to_string(&pid_file).unwrap().trim().to_string();
+        let start = Instant::now();
+        while running(&pid) && start.elapsed() < Duration::from_secs(2) {
+            thread::sleep(POLL_INTERVAL);
+        }
+        assert!(!running(&pid), "the child {} is still running", pid);
+    }
+
+    #[test]
+    fn formats_the_elapsed_time() {
+        assert_eq!(format_elapsed(Duration::from_millis(999)), "0s");
+        assert_eq!(format_elapsed(Duration::from_secs(59)), "59s");
+        assert_eq!(format_elapsed(Duration::from_secs(60)), "1m");
+        assert_eq!(format_elapsed(Duration::from_secs(14 * 60 + 59)), "14m");
+        assert_eq!(format_elapsed(Duration::from_secs(3600)), "1h00m");
+        assert_eq!(format_elapsed(Duration::from_secs(3720)), "1h02m");
+        assert_eq!(format_elapsed(Duration::from_secs(26 * 3600)), "26h00m");
+    }
+
+    #[test]
+    fn describes_the_exit_code_or_the_signal() {
+        assert_eq!(
+            describe_status(&ExitStatus::from_raw(2 << 8)),
+            "exit code 2"
+        );
+        assert_eq!(
+            describe_status(&ExitStatus::from_raw(139 << 8)),
+            "exit code 139, SIGSEGV in the shell"
+        );
+        assert_eq!(
+            describe_status(&ExitStatus::from_raw(libc::SIGKILL)),
+            "killed by SIGKILL"
+        );
+        assert_eq!(
+            describe_status(&ExitStatus::from_raw(40)),
+            "killed by signal 40"
+        );
+    }
+}
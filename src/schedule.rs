@@ -0,0 +1,325 @@
+//! Frequencies of the regressions and the log of the scheduler.
+//!
+//! A regression with a `frequency` is due when it has not been run for that
+//! long, according to the history, or with a cron expression when a time it
+//! matches has passed since its last run. The scheduler checks which
+//! regressions are due at every cycle and appends a line describing the cycle
+//! to `<data-dir>/.bmregression/schedule.log`, as a JSON object.
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::history;
+use crate::logs::STATE_DIR;
+use crate::timestamp::DateTime;
+
+/// Keywords accepted by the `frequency` key, with their period.
+pub const KEYWORDS: [(&str, Option<u64>); 4] = [
+    ("always", None),
+    ("hourly", Some(3600)),
+    ("daily", Some(86400)),
+    ("weekly", Some(7 * 86400)),
+];
+
+/// Margin allowed on a period, so that a daily regression is still due when the
+/// cycle that runs it starts a few seconds earlier than the day before.
+const DUE_SLACK: i64 = 60;
+
+/// Fields of a cron expression, with their range of values.
+const CRON_FIELDS: [(&str, u32, u32); 5] = [
+    ("minute", 0, 59),
+    ("hour", 0, 23),
+    ("day of month", 1, 31),
+    ("month", 1, 12),
+    ("day of week", 0, 7),
+];
+
+/// Names accepted for the months, from 1.
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Names accepted for the days of the week, from 0 for Sunday.
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far the next time matching a cron expression is searched, the calendar
+/// repeats itself every 28 years.
+const CRON_SEARCH_DAYS: i64 = 28 * 366;
+
+/// How often a regression has to be run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frequency {
+    /// Every time it is selected
+    Always,
+    /// When it has not been run for this long
+    Every(Duration),
+    /// When a time matching the expression has passed since the last run
+    Cron(Cron),
+}
+
+impl Frequency {
+    /// Parses the value of the `frequency` key, a keyword or a cron expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the keywords if the value is neither a keyword
+    /// nor an expression with five fields, or the problem of an invalid expression.
+    pub fn parse(text: &str) -> Result<Frequency, String> {
+        match KEYWORDS.iter().find(|(keyword, _)| *keyword == text) {
+            Some((_, None)) => Ok(Frequency::Always),
+            Some((_, Some(secs))) => Ok(Frequency::Every(Duration::from_secs(*secs))),
+            None if text.split_whitespace().count() == CRON_FIELDS.len() => {
+                Cron::parse(text).map(Frequency::Cron)
+            }
+            None => Err(format!(
+                "unknown frequency {:?}, expected one of {} or a cron expression",
+                text,
+                KEYWORDS.map(|(keyword, _)| keyword).join(", ")
+            )),
+        }
+    }
+
+    /// Returns true if a regression last run at `last` (never if None) is due at `now`.
+    pub fn is_due(&self, last: Option<DateTime>, now: DateTime) -> bool {
+        match (self, last) {
+            (Frequency::Always, _) | (_, None) => true,
+            (Frequency::Every(period), Some(last)) => {
+                now.unix_seconds() - last.unix_seconds() + DUE_SLACK >= period.as_secs() as i64
+            }
+            (Frequency::Cron(cron), Some(last)) => {
+                cron.next_fire(last).is_some_and(|fire| fire <= now)
+            }
+        }
+    }
+
+    /// Returns the next time after `now` at which a cron expression fires, None for the other frequencies.
+    pub fn next_fire(&self, now: DateTime) -> Option<DateTime> {
+        match self {
+            Frequency::Cron(cron) => cron.next_fire(now),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Frequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let secs = match self {
+            Frequency::Always => None,
+            Frequency::Every(period) => Some(period.as_secs()),
+            Frequency::Cron(cron) => return write!(f, "{} (cron, UTC)", cron.text),
+        };
+        match KEYWORDS.iter().find(|(_, period)| *period == secs) {
+            Some((keyword, _)) => write!(f, "{}", keyword),
+            None => write!(f, "every {}s", secs.unwrap_or_default()),
+        }
+    }
+}
+
+/// A standard cron expression with five fields, evaluated in UTC.
+///
+/// Each field is `*`, a value, a range `a-b` or a list of them separated by
+/// commas, each optionally followed by a step `/n`. Months and days of the week
+/// may be given by their three letter English names, and both 0 and 7 stand for
+/// Sunday. As in cron, when both the day of the month and the day of the week
+/// are restricted a day matching either of them matches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+    text: String,
+    /// Allowed values of each field, as bit sets
+    fields: [u64; 5],
+    /// Whether the day of the month and the day of the week are both restricted
+    either_day: bool,
+}
+
+impl Cron {
+    /// Parses a cron expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the invalid field, or if the expression never matches.
+    pub fn parse(text: &str) -> Result<Cron, String> {
+        let invalid = |reason: String| format!("invalid cron expression {:?}: {}", text, reason);
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        if parts.len() != CRON_FIELDS.len() {
+            return Err(invalid(format!(
+                "expected {} fields, found {}",
+                CRON_FIELDS.len(),
+                parts.len()
+            )));
+        }
+        let mut fields = [0; 5];
+        for (index, (part, (name, min, max))) in parts.iter().zip(CRON_FIELDS).enumerate() {
+            fields[index] = parse_cron_field(part, min, max)
+                .map_err(|reason| invalid(format!("{} field {:?} {}", name, part, reason)))?;
+        }
+        // Sunday is both 0 and 7
+        if fields[4] & (1 << 7) != 0 {
+            fields[4] = (fields[4] | 1) & !(1 << 7);
+        }
+        let cron = Cron {
+            text: parts.join(" "),
+            fields,
+            either_day: !parts[2].starts_with('*') && !parts[4].starts_with('*'),
+        };
+        if cron.next_fire(DateTime::from_unix(0)).is_none() {
+            return Err(invalid("it never matches".to_string()));
+        }
+        Ok(cron)
+    }
+
+    /// Returns true if the expression matches the day of a date.
+    fn matches_day(&self, date: &DateTime) -> bool {
+        let [_, _, days, months, weekdays] = self.fields;
+        let day = days & (1 << date.day) != 0;
+        let weekday = weekdays & (1 << date.weekday()) != 0;
+        let day = if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        };
+        day && months & (1 << date.month) != 0
+    }
+
+    /// Returns the first minute strictly after `after` matching the expression.
+    pub fn next_fire(&self, after: DateTime) -> Option<DateTime> {
+        let [minutes, hours, ..] = self.fields;
+        let mut secs = after.unix_seconds().div_euclid(60) * 60 + 60;
+        let limit = secs + CRON_SEARCH_DAYS * 86400;
+        while secs < limit {
+            let time = DateTime::from_unix(secs);
+            if !self.matches_day(&time) {
+                secs = (secs.div_euclid(86400) + 1) * 86400;
+            } else if hours & (1 << time.hour) == 0 {
+                secs = (secs.div_euclid(3600) + 1) * 3600;
+            } else if minutes & (1 << time.minute) == 0 {
+                secs += 60;
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+}
+
+/// Parses a field of a cron expression into the bit set of its values.
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        let names: &[&str] = match (min, max) {
+            (1, 12) => &MONTH_NAMES,
+            (0, 7) => &WEEKDAY_NAMES,
+            _ => &[],
+        };
+        let lower = text.to_ascii_lowercase();
+        let number = match names.iter().position(|name| *name == lower) {
+            Some(position) => position as u32 + if max == 12 { 1 } else { 0 },
+            None => text
+                .parse::<u32>()
+                .map_err(|_| format!("has an invalid value {:?}", text))?,
+        };
+        if number < min || number > max {
+            return Err(format!("has a value {} outside {}-{}", number, min, max));
+        }
+        Ok(number)
+    };
+
+    let mut set = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("has an invalid step {:?}", step)),
+            },
+            None => (item, 1),
+        };
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((first, last)) => (value(first)?, value(last)?),
+            // As in cron, a single value with a step runs until the end of the range
+            None if item.contains('/') => (value(range)?, max),
+            None => {
+                let single = value(range)?;
+                (single, single)
+            }
+        };
+        if first > last {
+            return Err(format!("has a reversed range {:?}", range));
+        }
+        for number in (first..=last).step_by(step as usize) {
+            set |= 1 << number;
+        }
+    }
+    Ok(set)
+}
+
+/// Returns when each regression was last run, according to the history.
+///
+/// # Errors
+///
+/// Returns an error if the history cannot be read.
+pub fn last_runs(target: &str) -> io::Result<HashMap<String, DateTime>> {
+    let mut last = HashMap::new();
+    // Oldest first, so that the most recent run wins
+    for run in history::load_all(target)? {
+        let Some(started) = DateTime::from_compact(&run.id) else {
+            continue;
+        };
+        for entry in run.entries {
+            last.insert(entry.name, started);
+        }
+    }
+    Ok(last)
+}
+
+/// Parses an interval such as `90s`, `30m`, `1h` or `2d`, or a sum of them such as `1h45m`.
+pub fn parse_interval(text: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid interval {:?}, expected numbers followed by s, m, h or d, e.g. 30m or 1h45m",
+            text
+        )
+    };
+    let mut secs = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        let multiplier = match c {
+            '0'..='9' => {
+                number.push(c);
+                continue;
+            }
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(invalid()),
+        };
+        let n: u64 = number.parse().map_err(|_| invalid())?;
+        secs += n * multiplier;
+        number.clear();
+    }
+    if !number.is_empty() || secs == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// Returns the path of the log of the scheduler.
+pub fn log_path(target: &str) -> PathBuf {
+    Path::new(target).join(STATE_DIR).join("schedule.log")
+}
+
+/// Appends the line describing a cycle to the log of the scheduler.
+///
+/// # Errors
+///
+/// Returns an error if the log cannot be written.
+pub fn append_log(target: &str, line: &str) -> io::Result<()> {
+    let path = log_path(target);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)
+}
@@ -0,0 +1,185 @@
+//! JSON Schema of the regression configurations.
+//!
+//! `bmregression schema` prints a JSON Schema (draft-07) of `config.yaml`,
+//! rendered from [`config::KEYS`], the table the configurations are parsed
+//! with, so that editors flag the mistakes before the regressions reach CI.
+//! With the YAML extension of VS Code, a configuration refers to it with a
+//! first line such as:
+//!
+//! ```text
+//! # yaml-language-server: $schema=../config.schema.json
+//! ```
+//!
+//! The keys not in the table, typos included, are rejected. The required keys
+//! are only required when the configuration does not include another file,
+//! which may provide them, `sourcedata` and `targetdata` only when it has no
+//! `files`, which replaces them.
+
+use crate::config::{KeyType, ACTIONS, ACTION_KEYS, INCLUDE_KEY, KEYS, LIMIT_KEYS, OUTPUT_KEYS};
+use crate::report::json_string;
+use crate::schedule;
+
+/// Returns a list of JSON strings.
+fn string_array(items: &[&str]) -> String {
+    let items: Vec<String> = items.iter().map(|item| json_string(item)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Returns the schema of the values of a type.
+fn value_schema(value: KeyType) -> String {
+    let strings = r#"{"type": "array", "items": {"type": "string"}}"#;
+    let scalar = r#"{"type": ["string", "number", "boolean"]}"#;
+    match value {
+        KeyType::String => r#"{"type": "string"}"#.to_string(),
+        KeyType::StringList => strings.to_string(),
+        KeyType::Command => format!(
+            r#"{{"anyOf": [{{"type": "string"}}, {{"type": "array", "items": {}, "minItems": 1}}]}}"#,
+            scalar
+        ),
+        KeyType::Targetdata => {
+            let files = r#"{"anyOf": [{"type": "string"}, {"type": "array", "items": {"type": "string"}, "minItems": 1}]}"#;
+            format!(
+                r#"{{"anyOf": [{files}, {{"type": "object", "additionalProperties": {files}, "minProperties": 1}}]}}"#,
+                files = files
+            )
+        }
+        KeyType::Patterns => strings.to_string(),
+        KeyType::Files => format!(
+            r#"{{"type": "array", "items": {{"type": "object", "properties": {{"sourcedata": {{"type": "string"}}, "targetdata": {}, "ignore": {}}}, "required": ["sourcedata", "targetdata"], "additionalProperties": false}}, "minItems": 1}}"#,
+            value_schema(KeyType::Targetdata),
+            strings
+        ),
+        KeyType::Boolean => r#"{"type": "boolean"}"#.to_string(),
+        KeyType::Integer(i64::MIN) => r#"{"type": "integer"}"#.to_string(),
+        KeyType::Integer(min) => format!(r#"{{"type": "integer", "minimum": {}}}"#, min),
+        KeyType::Number(min, max) => format!(
+            r#"{{"type": "number", "minimum": {}, "maximum": {}}}"#,
+            min, max
+        ),
+        KeyType::Word(words) => format!(r#"{{"enum": {}}}"#, string_array(words)),
+        KeyType::Frequency => {
+            let keywords = schedule::KEYWORDS.map(|(keyword, _)| keyword);
+            format!(
+                r#"{{"type": "string", "anyOf": [{{"enum": {}}}, {{"pattern": "^\\s*\\S+(\\s+\\S+){{4}}\\s*$"}}]}}"#,
+                string_array(&keywords)
+            )
+        }
+        KeyType::Section => r#"{"type": "object", "properties": {"start": {"type": "string", "minLength": 1}, "end": {"type": "string", "minLength": 1}}, "required": ["start", "end"]}"#.to_string(),
+        KeyType::Masks => r#"{"type": "array", "items": {"type": "object", "properties": {"pattern": {"type": "string"}, "replace": {"type": "string"}}, "required": ["pattern", "replace"]}}"#.to_string(),
+        KeyType::Limits => {
+            let properties: Vec<String> = LIMIT_KEYS
+                .iter()
+                .map(|key| {
+                    let limit = match *key {
+                        "nice" => r#"{"type": "integer", "minimum": 0, "maximum": 19}"#,
+                        _ => r#"{"type": "integer", "minimum": 1}"#,
+                    };
+                    format!("{}: {}", json_string(key), limit)
+                })
+                .collect();
+            format!(
+                r#"{{"type": "object", "properties": {{{}}}, "additionalProperties": false}}"#,
+                properties.join(", ")
+            )
+        }
+        KeyType::Env => format!(
+            r#"{{"type": "object", "additionalProperties": {}}}"#,
+            scalar
+        ),
+        KeyType::Actions => {
+            let keys: Vec<String> = KEYS
+                .iter()
+                .filter(|key| ACTION_KEYS.contains(&key.name))
+                .map(|key| format!("{}: {}", json_string(key.name), value_schema(key.value)))
+                .collect();
+            let overrides = format!(
+                r#"{{"type": "object", "properties": {{{}}}, "additionalProperties": false}}"#,
+                keys.join(", ")
+            );
+            let actions: Vec<String> = ACTIONS
+                .iter()
+                .map(|action| format!("{}: {}", json_string(action), overrides))
+                .collect();
+            format!(
+                r#"{{"type": "object", "properties": {{{}}}, "additionalProperties": false}}"#,
+                actions.join(", ")
+            )
+        }
+    }
+}
+
+/// Renders the JSON Schema of `config.yaml`.
+pub fn render() -> String {
+    let properties: Vec<String> = KEYS
+        .iter()
+        .map(|key| {
+            // The description is added to the schema of the value
+            let value = value_schema(key.value);
+            format!(
+                "    {}: {{\"description\": {}, {}",
+                json_string(key.name),
+                json_string(key.description),
+                &value[1..]
+            )
+        })
+        .collect();
+    let required: Vec<&str> = KEYS
+        .iter()
+        .filter(|key| key.required && !OUTPUT_KEYS.contains(&key.name))
+        .map(|key| key.name)
+        .collect();
+    format!(
+        r#"{{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "title": "bmregression config.yaml",
+  "description": "Configuration of a regression of bmregression {}",
+  "type": "object",
+  "properties": {{
+{}
+  }},
+  "additionalProperties": false,
+  "if": {{"not": {{"required": [{}]}}}},
+  "then": {{"required": {}, "anyOf": [{{"required": {}}}, {{"required": ["files"]}}]}}
+}}
+"#,
+        env!("CARGO_PKG_VERSION"),
+        properties.join(",\n"),
+        json_string(INCLUDE_KEY),
+        string_array(&required),
+        string_array(&OUTPUT_KEYS)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::{self, Value};
+
+    #[test]
+    fn describes_every_key() {
+        let schema = json::parse(&render()).unwrap();
+        let Some(Value::Object(properties)) = schema.get("properties") else {
+            panic!("no properties in the schema");
+        };
+        let names: Vec<&str> = properties.iter().map(|(name, _)| name.as_str()).collect();
+        let keys: Vec<&str> = KEYS.iter().map(|key| key.name).collect();
+        assert_eq!(names, keys);
+        assert_eq!(
+            schema.get("additionalProperties"),
+            Some(&Value::Bool(false))
+        );
+        let compare = properties
+            .iter()
+            .find(|(name, _)| name == "compare")
+            .unwrap();
+        assert_eq!(
+            compare.1.get("enum"),
+            Some(&Value::Array(
+                crate::config::COMPARE_MODES
+                    .iter()
+                    .map(|mode| Value::String(mode.to_string()))
+                    .collect()
+            ))
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Self-test of the binary against a bundled fixture.
+//!
+//! `bmregression self-test` writes a tiny examples repository and data
+//! repository in a temporary directory, then runs the binary itself on them
+//! through the commands a user would type, without network access: `alpha`,
+//! tagged `quick`, whose expected output matches what its command prints, and
+//! `beta`, whose expected output is stale. Each capability is checked in turn,
+//! the later ones relying on the earlier:
+//!
+//! - config parsing: `validate` accepts both configurations
+//! - tag filtering: `--tag quick list` selects `alpha` alone
+//! - comparison: `run` passes `alpha` and fails `beta`, exiting with 1
+//! - diff rendering: `run --diff-on-fail` prints the unified diff of `beta`
+//! - reset copy: `reset` copies the output of `beta` to its baseline, and it passes
+//!
+//! The commands are isolated from the user configuration and from the
+//! variables naming the summary files, so that they behave the same everywhere.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::style;
+use crate::summary;
+use crate::userconfig;
+
+/// Files of the fixture, relative to its root.
+const FIXTURE: [(&str, &str); 6] = [
+    ("examples/hello/README", "Example printing hello\n"),
+    ("examples/world/README", "Example printing hello world\n"),
+    (
+        "data/alpha/config.yaml",
+        "regbase: hello\nsourcedata: out.txt\ntargetdata: out.txt\nregcommand: printf 'hello\\n' > out.txt\ntags: [default, quick]\n",
+    ),
+    ("data/alpha/out.txt", "hello\n"),
+    (
+        "data/beta/config.yaml",
+        "regbase: world\nsourcedata: out.txt\ntargetdata: out.txt\nregcommand: printf 'hello\\nworld\\n' > out.txt\n",
+    ),
+    ("data/beta/out.txt", "hello\nmoon\n"),
+];
+
+/// Output of the command of `beta`, its baseline after the reset.
+const BETA_OUTPUT: &str = "hello\nworld\n";
+
+/// Variables of the environment that would make the commands write outside the fixture.
+const ISOLATED_VARIABLES: [&str; 2] = [summary::PATH_VARIABLE, "GITHUB_STEP_SUMMARY"];
+
+/// The result of the check of a capability.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub capability: &'static str,
+    /// `None` if not checked, after a failing capability
+    pub result: Option<Result<(), String>>,
+}
+
+/// The binary under test, run on the fixture.
+struct Harness {
+    exe: PathBuf,
+    root: PathBuf,
+}
+
+impl Harness {
+    /// Runs the binary with the fixture repositories and the given arguments.
+    ///
+    /// # Returns
+    ///
+    /// The exit code and the stdout followed by the stderr, without their colors.
+    fn run(&self, args: &[&str]) -> Result<(i32, String), String> {
+        let mut command = Command::new(&self.exe);
+        command
+            .arg("--data-dir")
+            .arg(self.root.join("data"))
+            .arg("--examples-dir")
+            .arg(self.root.join("examples"))
+            .args(["--status-style", "ascii"])
+            .args(args)
+            .env(
+                userconfig::PATH_VARIABLE,
+                self.root.join("no-user-config.yaml"),
+            );
+        for variable in ISOLATED_VARIABLES {
+            command.env_remove(variable);
+        }
+        let output = command
+            .output()
+            .map_err(|err| format!("running {} failed: {}", self.exe.display(), err))?;
+        let text = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        Ok((output.status.code().unwrap_or(-1), style::plain(&text)))
+    }
+
+    /// Runs the binary, failing unless it exits with `code` and prints all the expected lines.
+    fn expect(&self, args: &[&str], code: i32, lines: &[&str]) -> Result<String, String> {
+        let (actual, text) = self.run(args)?;
+        let command = format!("bmregression {}", args.join(" "));
+        if actual != code {
+            return Err(format!(
+                "{} exited with {} instead of {}:\n{}",
+                command,
+                actual,
+                code,
+                text.trim_end()
+            ));
+        }
+        if let Some(missing) = lines
+            .iter()
+            .find(|line| !text.lines().any(|printed| printed == **line))
+        {
+            return Err(format!(
+                "{} did not print {:?}:\n{}",
+                command,
+                missing,
+                text.trim_end()
+            ));
+        }
+        Ok(text)
+    }
+}
+
+/// Checks that both configurations are valid.
+fn config_parsing(harness: &Harness) -> Result<(), String> {
+    harness.expect(
+        &["validate"],
+        0,
+        &["Regression alpha: valid", "Regression beta: valid"],
+    )?;
+    Ok(())
+}
+
+/// Checks that a tag selects only the regressions carrying it.
+fn tag_filtering(harness: &Harness) -> Result<(), String> {
+    let text = harness.expect(&["--tag", "quick", "list"], 0, &["\talpha"])?;
+    if text.lines().any(|line| line == "\tbeta") {
+        return Err("--tag quick list selected beta, which is not tagged quick".to_string());
+    }
+    Ok(())
+}
+
+/// Checks that the matching output passes and the stale one fails.
+fn comparison(harness: &Harness) -> Result<(), String> {
+    harness.expect(
+        &["run"],
+        1,
+        &["Regression alpha: [PASS]", "Regression beta: [FAIL]"],
+    )?;
+    Ok(())
+}
+
+/// Checks that the differences are found and printed as a unified diff.
+fn diff_rendering(harness: &Harness) -> Result<(), String> {
+    harness.expect(
+        &["diff", "--quiet", "beta"],
+        1,
+        &["Regression beta: [DIFF]"],
+    )?;
+    harness.expect(
+        &["run", "--exact", "beta", "--diff-on-fail"],
+        1,
+        &["@@ -1,2 +1,2 @@", " hello", "-moon", "+world"],
+    )?;
+    Ok(())
+}
+
+/// Checks that a reset copies the output to the baseline, which then passes.
+fn reset_copy(harness: &Harness) -> Result<(), String> {
+    harness.expect(&["reset", "beta"], 0, &["Regression beta: [RESET]"])?;
+    let baseline = harness.root.join("data/beta/out.txt");
+    let content = fs::read_to_string(&baseline)
+        .map_err(|err| format!("reading {} failed: {}", baseline.display(), err))?;
+    if content != BETA_OUTPUT {
+        return Err(format!(
+            "the baseline of beta is {:?} after the reset instead of {:?}",
+            content, BETA_OUTPUT
+        ));
+    }
+    harness.expect(
+        &["run"],
+        0,
+        &["Regression alpha: [PASS]", "Regression beta: [PASS]"],
+    )?;
+    Ok(())
+}
+
+/// Check of a capability, with what went wrong.
+type Capability = fn(&Harness) -> Result<(), String>;
+
+/// The capabilities checked, in order.
+const CAPABILITIES: [(&str, Capability); 5] = [
+    ("config parsing", config_parsing),
+    ("tag filtering", tag_filtering),
+    ("comparison", comparison),
+    ("diff rendering", diff_rendering),
+    ("reset copy", reset_copy),
+];
+
+/// Writes the files of the fixture under a directory.
+fn materialize(root: &Path) -> io::Result<()> {
+    for (path, content) in FIXTURE {
+        let path = root.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+/// Checks the capabilities of a binary on the fixture, written under `root`.
+///
+/// The capabilities after a failing one are not checked, as they build on its state.
+///
+/// # Errors
+///
+/// Returns an error if the fixture cannot be written.
+pub fn run(exe: &Path, root: &Path) -> io::Result<Vec<Check>> {
+    materialize(root)?;
+    let harness = Harness {
+        exe: exe.to_path_buf(),
+        root: root.to_path_buf(),
+    };
+    let mut failed = false;
+    Ok(CAPABILITIES
+        .iter()
+        .map(|(capability, check)| {
+            let result = (!failed).then(|| check(&harness));
+            failed |= matches!(result, Some(Err(_)));
+            Check { capability, result }
+        })
+        .collect())
+}
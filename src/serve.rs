@@ -0,0 +1,645 @@
+//! HTTP API of the serve mode.
+//!
+//! A listener thread hands each connection to a thread of its own, so that a slow
+//! client does not hold the others up, while the runs are executed one at a
+//! time by the caller, which takes them from the queue with [`Server::next_job`]
+//! and reports their progress back. Only what the API needs of HTTP/1.1 is
+//! implemented: one request per connection, headers of a bounded size, bodies
+//! sized by `Content-Length`.
+//!
+//! When `$BMREGRESSION_TOKEN` is set, every request must carry it as a bearer
+//! token in the `Authorization` header, compared in constant time.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::json::{self, Value};
+use crate::logs;
+use crate::report::json_string;
+use crate::results::RegressionResult;
+use crate::timestamp::DateTime;
+
+/// Environment variable holding the bearer token required by the API.
+pub const TOKEN_VARIABLE: &str = "BMREGRESSION_TOKEN";
+
+/// Maximum size of a request body.
+const MAX_BODY: usize = 64 * 1024;
+
+/// Maximum size of the request line and the headers together.
+const MAX_HEAD: usize = 16 * 1024;
+
+/// Maximum number of headers of a request.
+const MAX_HEADERS: usize = 64;
+
+/// Maximum number of connections answered at the same time, the others are refused.
+const MAX_CONNECTIONS: usize = 32;
+
+/// Time and size allowed to the rest of a rejected request, read and discarded.
+const LINGER_TIMEOUT: Duration = Duration::from_millis(500);
+const LINGER_LIMIT: u64 = (MAX_BODY + MAX_HEAD) as u64 * 4;
+
+/// Time allowed to a client to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A run requested through the API.
+pub struct Job {
+    /// Identifier of the run, also naming its logs and history
+    pub id: String,
+    /// Name pattern of the regressions to run
+    pub name: String,
+    /// Tags of the regressions to run
+    pub tags: Vec<String>,
+}
+
+/// A run known to the server.
+struct Run {
+    id: String,
+    name: String,
+    tags: Vec<String>,
+    /// queued, running, completed or interrupted
+    status: &'static str,
+    results: Vec<RegressionResult>,
+}
+
+/// State shared by the listener and the worker.
+struct State {
+    /// Regressions of the data directory, as (name, tags) pairs
+    regressions: Vec<(String, Vec<String>)>,
+    /// Requested runs, oldest first
+    runs: Vec<Run>,
+    /// Number of runs requested so far, to tell apart the runs started in the same second
+    requested: u64,
+}
+
+/// Handle of a running server, from which the worker takes the runs to execute.
+pub struct Server {
+    state: Arc<Mutex<State>>,
+    jobs: Receiver<Job>,
+}
+
+impl Server {
+    /// Starts listening on an address, answering the requests in a thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the address cannot be bound.
+    pub fn start(listen: &str, target: &str, default_tags: Vec<String>) -> io::Result<Server> {
+        let listener = TcpListener::bind(listen)
+            .map_err(|err| io::Error::other(format!("listening on {} failed: {}", listen, err)))?;
+        let state = Arc::new(Mutex::new(State {
+            regressions: Vec::new(),
+            runs: Vec::new(),
+            requested: 0,
+        }));
+        let (sender, jobs) = mpsc::channel();
+        let handler = Arc::new(Handler {
+            state: state.clone(),
+            jobs: sender,
+            target: target.to_string(),
+            default_tags,
+            token: std::env::var(TOKEN_VARIABLE).ok().filter(|t| !t.is_empty()),
+        });
+        let connections = Arc::new(AtomicUsize::new(0));
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // Dropping the stream closes the connection
+                if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
+                let handler = handler.clone();
+                let connections = connections.clone();
+                thread::spawn(move || {
+                    if let Err(err) = handler.handle(stream) {
+                        println!("Warning: answering a request failed: {}", err);
+                    }
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        Ok(Server { state, jobs })
+    }
+
+    /// Replaces the list of the regressions served by `GET /regressions`.
+    pub fn set_regressions(&self, regressions: Vec<(String, Vec<String>)>) {
+        self.state.lock().unwrap().regressions = regressions;
+    }
+
+    /// Waits up to `timeout` for the next requested run.
+    ///
+    /// # Returns
+    ///
+    /// `None` if no run has been requested in the meantime.
+    pub fn next_job(&self, timeout: Duration) -> Option<Job> {
+        match self.jobs.recv_timeout(timeout) {
+            Ok(job) => Some(job),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
+    /// Records the status of a run and the results obtained so far.
+    pub fn update(&self, id: &str, status: &'static str, results: Vec<RegressionResult>) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(run) = state.runs.iter_mut().find(|run| run.id == id) {
+            run.status = status;
+            run.results = results;
+        }
+    }
+}
+
+/// A parsed HTTP request.
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+/// Answers the requests, in the thread of their connection.
+struct Handler {
+    state: Arc<Mutex<State>>,
+    jobs: Sender<Job>,
+    target: String,
+    default_tags: Vec<String>,
+    token: Option<String>,
+}
+
+impl Handler {
+    /// Reads a request from a connection and writes the response.
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let (code, body) = match read_request(&stream) {
+            Ok(request) => self.respond(&request),
+            Err(err) => (400, error_body(&err.to_string())),
+        };
+        let reason = match code {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            code,
+            reason,
+            body.len(),
+            body
+        )?;
+        stream.flush()?;
+        // The rest of a rejected request, left unread, would reset the connection before
+        // the client reads the response
+        if code == 400 {
+            stream.shutdown(Shutdown::Write)?;
+            stream.set_read_timeout(Some(LINGER_TIMEOUT))?;
+            let _ = io::copy(&mut (&stream).take(LINGER_LIMIT), &mut io::sink());
+        }
+        Ok(())
+    }
+
+    /// Returns the status code and the JSON body answering a request.
+    fn respond(&self, request: &Request) -> (u16, String) {
+        if let Some(token) = &self.token {
+            let expected = format!("Bearer {}", token);
+            let given = request
+                .headers
+                .get("authorization")
+                .map_or("", String::as_str);
+            if !constant_time_eq(given.as_bytes(), expected.as_bytes()) {
+                return (401, error_body("missing or wrong bearer token"));
+            }
+        }
+        let segments: Vec<&str> = request
+            .path
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["regressions"]) => (200, self.regressions()),
+            ("POST", ["run"]) => self.request_run(&request.body),
+            ("GET", ["runs", id]) => self.run(id),
+            ("GET", ["runs", id, "logs", regression]) => self.logs(id, regression),
+            (_, ["regressions"]) | (_, ["run"]) | (_, ["runs", ..]) => {
+                (405, error_body("method not allowed"))
+            }
+            _ => (404, error_body("not found")),
+        }
+    }
+
+    /// Lists the regressions with their tags.
+    fn regressions(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let items: Vec<String> = state
+            .regressions
+            .iter()
+            .map(|(name, tags)| {
+                format!(
+                    "{{\"name\": {}, \"tags\": {}}}",
+                    json_string(name),
+                    json_strings(tags)
+                )
+            })
+            .collect();
+        format!("{{\"regressions\": [{}]}}", items.join(", "))
+    }
+
+    /// Queues a run of the regressions selected by the filters of the body.
+    fn request_run(&self, body: &str) -> (u16, String) {
+        let filters = match parse_filters(body) {
+            Ok(filters) => filters,
+            Err(err) => return (400, error_body(&err)),
+        };
+        let mut state = self.state.lock().unwrap();
+        state.requested += 1;
+        let id = format!(
+            "{}-{}-{}",
+            DateTime::now().compact(),
+            std::process::id(),
+            state.requested
+        );
+        let job = Job {
+            id: id.clone(),
+            name: filters.name.unwrap_or_default(),
+            tags: filters.tags.unwrap_or_else(|| self.default_tags.clone()),
+        };
+        state.runs.push(Run {
+            id: id.clone(),
+            name: job.name.clone(),
+            tags: job.tags.clone(),
+            status: "queued",
+            results: Vec::new(),
+        });
+        if self.jobs.send(job).is_err() {
+            return (500, error_body("the worker is not running"));
+        }
+        (202, format!("{{\"run_id\": {}}}", json_string(&id)))
+    }
+
+    /// Describes a run, with the results of the regressions executed so far.
+    fn run(&self, id: &str) -> (u16, String) {
+        let state = self.state.lock().unwrap();
+        let Some(run) = state.runs.iter().find(|run| run.id == id) else {
+            return (404, error_body("no such run"));
+        };
+        let results: Vec<String> = run
+            .results
+            .iter()
+            .map(|r| {
+                format!(
+                    "{{\"name\": {}, \"status\": {}, \"duration_seconds\": {:.3}, \"similarity\": {}, \"output_size\": {}, \"error\": {}, \"output\": {}}}",
+                    json_string(&r.name),
+                    json_string(r.status),
+                    r.duration.as_secs_f64(),
+                    r.similarity_json(),
+                    r.output_size_json(),
+                    json_string(&r.error),
+                    json_string(&r.output)
+                )
+            })
+            .collect();
+        (
+            200,
+            format!(
+                "{{\"run_id\": {}, \"status\": {}, \"name\": {}, \"tags\": {}, \"results\": [{}]}}",
+                json_string(&run.id),
+                json_string(run.status),
+                json_string(&run.name),
+                json_strings(&run.tags),
+                results.join(", ")
+            ),
+        )
+    }
+
+    /// Returns the captured output of a regression of a run.
+    fn logs(&self, id: &str, regression: &str) -> (u16, String) {
+        if !self
+            .state
+            .lock()
+            .unwrap()
+            .runs
+            .iter()
+            .any(|run| run.id == id)
+        {
+            return (404, error_body("no such run"));
+        }
+        // The name must not lead outside the logs of the run
+        if regression.contains("..") || regression.contains('\\') {
+            return (400, error_body("invalid regression name"));
+        }
+        let dir = logs::logs_root(&self.target).join(id);
+        let read = |extension: &str| fs::read(logs::log_file(&dir, regression, extension));
+        match (read("out"), read("err")) {
+            (Ok(stdout), Ok(stderr)) => (
+                200,
+                format!(
+                    "{{\"stdout\": {}, \"stderr\": {}}}",
+                    json_string(&String::from_utf8_lossy(&stdout)),
+                    json_string(&String::from_utf8_lossy(&stderr))
+                ),
+            ),
+            _ => (404, error_body("no logs for this regression in this run")),
+        }
+    }
+}
+
+/// Returns true if two byte strings are equal, in a time that does not depend on where
+/// they differ, so that a token cannot be guessed one byte at a time.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Reads a line of the head of a request, within the size left of [`MAX_HEAD`].
+fn read_head_line(
+    reader: &mut impl BufRead,
+    line: &mut String,
+    left: &mut usize,
+) -> io::Result<usize> {
+    line.clear();
+    let read = reader.take(*left as u64).read_line(line)?;
+    *left -= read;
+    if *left == 0 && !line.ends_with('\n') {
+        return Err(io::Error::other("request header too large"));
+    }
+    Ok(read)
+}
+
+/// Reads the request line, the headers and the body of a request.
+fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    let mut left = MAX_HEAD;
+    read_head_line(&mut reader, &mut line, &mut left)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(io::Error::other("malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut headers = HashMap::new();
+    for count in 0.. {
+        if read_head_line(&mut reader, &mut line, &mut left)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            return Err(io::Error::other("too many request headers"));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length: usize = match headers.get("content-length") {
+        Some(length) => length
+            .parse()
+            .map_err(|_| io::Error::other("invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Err(io::Error::other("request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+/// Renders an error message as a JSON body.
+fn error_body(message: &str) -> String {
+    format!("{{\"error\": {}}}", json_string(message))
+}
+
+/// Renders strings as a JSON array.
+fn json_strings(items: &[String]) -> String {
+    let items: Vec<String> = items.iter().map(|item| json_string(item)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Filters of a run request.
+#[derive(Debug, Default, PartialEq)]
+struct Filters {
+    name: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+/// Parses the body of a run request, `{"name": "...", "tags": [...]}`.
+///
+/// Both keys are optional, and the tags may also be given as a comma separated
+/// string like `--tag`. An empty body runs with the defaults.
+fn parse_filters(body: &str) -> Result<Filters, String> {
+    let mut filters = Filters::default();
+    if body.trim().is_empty() {
+        return Ok(filters);
+    }
+    let document = json::parse(body)?;
+    let Value::Object(members) = document else {
+        return Err("expected a JSON object".to_string());
+    };
+    for (key, value) in members {
+        match (key.as_str(), value) {
+            ("name", Value::String(name)) => filters.name = Some(name),
+            ("tags", Value::String(tags)) => {
+                filters.tags = Some(tags.split(',').map(|t| t.trim().to_string()).collect());
+            }
+            ("tags", Value::Array(items)) => {
+                let tags: Option<Vec<String>> = items
+                    .iter()
+                    .map(|item| item.as_str().map(|tag| tag.to_string()))
+                    .collect();
+                filters.tags = Some(tags.ok_or("invalid value of tags")?);
+            }
+            ("name", _) | ("tags", _) => return Err(format!("invalid value of {}", key)),
+            _ => return Err(format!("unknown key {}, expected name or tags", key)),
+        }
+    }
+    Ok(filters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    /// Returns a handler of the logs in `target`, with its queue of jobs.
+    fn handler(target: &str, token: Option<&str>) -> (Handler, Receiver<Job>) {
+        let (jobs, queue) = mpsc::channel();
+        let state = State {
+            regressions: vec![("blink".to_string(), vec!["quick".to_string()])],
+            runs: Vec::new(),
+            requested: 0,
+        };
+        let handler = Handler {
+            state: Arc::new(Mutex::new(state)),
+            jobs,
+            target: target.to_string(),
+            default_tags: vec!["default".to_string()],
+            token: token.map(str::to_string),
+        };
+        (handler, queue)
+    }
+
+    /// Sends a raw request to the handler over a connection, returning the raw response.
+    fn send(handler: &Handler, request: &[u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(request).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        handler.handle(stream).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn get(handler: &Handler, path: &str) -> String {
+        send(handler, format!("GET {} HTTP/1.1\r\n\r\n", path).as_bytes())
+    }
+
+    #[test]
+    fn routes_the_requests() {
+        let (handler, queue) = handler(".", None);
+        let response = get(&handler, "/regressions");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.ends_with(
+            "\r\n\r\n{\"regressions\": [{\"name\": \"blink\", \"tags\": [\"quick\"]}]}"
+        ));
+
+        let body = "{\"name\": \"blink\"}";
+        let response = send(
+            &handler,
+            format!(
+                "POST /run HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .as_bytes(),
+        );
+        assert!(
+            response.starts_with("HTTP/1.1 202 Accepted\r\n"),
+            "{}",
+            response
+        );
+        let job = queue.try_recv().unwrap();
+        assert_eq!(
+            (job.name.as_str(), job.tags),
+            ("blink", vec!["default".to_string()])
+        );
+        assert!(response.ends_with(&format!("{{\"run_id\": \"{}\"}}", job.id)));
+
+        let response = get(&handler, &format!("/runs/{}?verbose", job.id));
+        assert!(response.contains("\"status\": \"queued\""), "{}", response);
+        assert!(get(&handler, "/runs/unknown").starts_with("HTTP/1.1 404 "));
+        assert!(get(&handler, "/elsewhere").starts_with("HTTP/1.1 404 "));
+        assert!(send(&handler, b"DELETE /run HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 405 "));
+        assert!(send(&handler, b"\r\n\r\n").starts_with("HTTP/1.1 400 "));
+    }
+
+    #[test]
+    fn requires_the_token() {
+        let (handler, _queue) = handler(".", Some("secret"));
+        let request = |authorization: &str| {
+            send(
+                &handler,
+                format!("GET /regressions HTTP/1.1\r\n{}\r\n", authorization).as_bytes(),
+            )
+        };
+        for authorization in [
+            "",
+            "Authorization: Bearer secreT\r\n",
+            "Authorization: secret\r\n",
+        ] {
+            let response = request(authorization);
+            assert!(
+                response.starts_with("HTTP/1.1 401 Unauthorized\r\n"),
+                "{}",
+                response
+            );
+        }
+        let response = request("authorization:  Bearer secret \r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+
+    #[test]
+    fn serves_the_logs_of_the_run_only() {
+        let dir = TempDir::new("serve").unwrap();
+        let target = dir.path().to_str().unwrap();
+        let (handler, queue) = handler(target, None);
+        send(&handler, b"POST /run HTTP/1.1\r\n\r\n");
+        let id = queue.try_recv().unwrap().id;
+        let logs = logs::logs_root(target).join(&id);
+        fs::create_dir_all(&logs).unwrap();
+        fs::write(logs.join("blink.out"), "built\n").unwrap();
+        fs::write(logs.join("blink.err"), "").unwrap();
+        fs::write(dir.path().join("secret.out"), "").unwrap();
+        fs::write(dir.path().join("secret.err"), "").unwrap();
+
+        let response = get(&handler, &format!("/runs/{}/logs/blink", id));
+        assert!(
+            response.ends_with("{\"stdout\": \"built\\n\", \"stderr\": \"\"}"),
+            "{}",
+            response
+        );
+        for regression in ["..", "..%2F..%2F..%2Fsecret", "..\\..\\secret"] {
+            let response = get(&handler, &format!("/runs/{}/logs/{}", id, regression));
+            assert!(
+                response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+                "{}: {}",
+                regression,
+                response
+            );
+        }
+        assert!(get(&handler, &format!("/runs/{}/logs/other", id)).starts_with("HTTP/1.1 404 "));
+        assert!(get(&handler, "/runs/unknown/logs/blink").starts_with("HTTP/1.1 404 "));
+    }
+
+    #[test]
+    fn limits_the_size_of_the_headers() {
+        let (handler, _queue) = handler(".", None);
+        let long = format!(
+            "GET /regressions HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+            "x".repeat(MAX_HEAD)
+        );
+        let response = send(&handler, long.as_bytes());
+        assert!(
+            response.contains("request header too large"),
+            "{}",
+            response
+        );
+
+        let many = format!(
+            "GET /regressions HTTP/1.1\r\n{}\r\n",
+            "X-Header: x\r\n".repeat(MAX_HEADERS + 1)
+        );
+        let response = send(&handler, many.as_bytes());
+        assert!(
+            response.contains("too many request headers"),
+            "{}",
+            response
+        );
+
+        let body = format!(
+            "POST /run HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        let response = send(&handler, body.as_bytes());
+        assert!(response.contains("request body too large"), "{}", response);
+    }
+}
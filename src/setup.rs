@@ -0,0 +1,187 @@
+//! Setup failures of a regression: its example, its generated output or its
+//! expected output missing.
+//!
+//! They are the most common errors of a new or moved regression, and look alike
+//! from afar, so each one names the path that was expected and the likely fix.
+//! They travel as [`io::Error`]s like the other errors of a regression, the
+//! tests telling them apart with [`of`].
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A path a regression needs that does not exist, a setup failure.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Missing {
+    /// The example directory named by the regbase does not exist
+    Example {
+        regbase: String,
+        dir: PathBuf,
+        /// What probably went wrong, found by looking at the examples checkout
+        hint: String,
+    },
+    /// The command succeeded without writing the sourcedata
+    Output {
+        sourcedata: String,
+        path: PathBuf,
+        /// Directory the command ran in
+        command_dir: PathBuf,
+        /// Artifacts collected after the failure, as a note to append
+        note: String,
+    },
+    /// The expected output does not exist, and the action does not create it
+    Baseline {
+        regression: String,
+        targetdata: String,
+        path: PathBuf,
+    },
+}
+
+impl Missing {
+    /// Returns the error of a missing example directory, with a hint found by looking at
+    /// the examples checkout `examples`.
+    pub fn example(regbase: &str, dir: &Path, examples: &Path) -> Missing {
+        Missing::Example {
+            regbase: regbase.to_string(),
+            dir: dir.to_path_buf(),
+            hint: example_hint(dir, examples),
+        }
+    }
+}
+
+/// Tells why an example directory is probably missing.
+pub fn example_hint(dir: &Path, examples: &Path) -> String {
+    // The closest existing directory holds the one with a different case, if any
+    let mut existing = dir;
+    while let Some(parent) = existing.parent() {
+        existing = parent;
+        if existing.is_dir() {
+            break;
+        }
+    }
+    let missing = dir
+        .strip_prefix(existing)
+        .ok()
+        .and_then(|rest| rest.components().next())
+        .map(|component| component.as_os_str().to_string_lossy().to_lowercase());
+    if let (Some(missing), Ok(entries)) = (missing, fs::read_dir(existing)) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.to_lowercase() == missing {
+                return format!(
+                    "{} exists, the regbase is case-sensitive",
+                    existing.join(name).display()
+                );
+            }
+        }
+    }
+    if examples.join(".git/info/sparse-checkout").is_file() {
+        return "the examples are a sparse checkout, it may be too narrow to include the example"
+            .to_string();
+    }
+    if !examples.join(".git").exists() {
+        return "is the examples checkout complete? Otherwise check the regbase in config.yaml"
+            .to_string();
+    }
+    "check the regbase in config.yaml, or update the examples checkout if the example is new"
+        .to_string()
+}
+
+impl fmt::Display for Missing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Missing::Example { regbase, dir, hint } => write!(
+                f,
+                "example directory {} (regbase {}) does not exist: {}",
+                dir.display(),
+                regbase,
+                hint
+            ),
+            Missing::Output {
+                sourcedata,
+                path,
+                command_dir,
+                note,
+            } => write!(
+                f,
+                "the command succeeded but did not write {} (sourcedata {}): check the sourcedata in config.yaml against the files the command writes in {}{}",
+                path.display(),
+                sourcedata,
+                command_dir.display(),
+                note
+            ),
+            Missing::Baseline {
+                regression,
+                targetdata,
+                path,
+            } => write!(
+                f,
+                "expected output {} (targetdata {}) does not exist: capture it with bmregression reset {} or run --bootstrap, or check the targetdata in config.yaml",
+                path.display(),
+                targetdata,
+                regression
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Missing {}
+
+impl From<Missing> for io::Error {
+    fn from(err: Missing) -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, err)
+    }
+}
+
+/// Returns the setup failure an error carries, None if it is another error.
+#[cfg(test)]
+pub fn of(err: &io::Error) -> Option<&Missing> {
+    err.get_ref()
+        .and_then(|inner| inner.downcast_ref::<Missing>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn hints_why_the_example_is_missing() {
+        let dir = TempDir::new("setup").unwrap();
+        let examples = dir.path().join("examples");
+        fs::create_dir_all(examples.join("basys3/Blink")).unwrap();
+
+        let missing = Missing::example("basys3/blink", &examples.join("basys3/blink"), &examples);
+        assert!(
+            missing.to_string().ends_with(&format!(
+                "(regbase basys3/blink) does not exist: {} exists, the regbase is case-sensitive",
+                examples.join("basys3/Blink").display()
+            )),
+            "{}",
+            missing
+        );
+        let missing = Missing::example(
+            "zedboard/blink",
+            &examples.join("zedboard/blink"),
+            &examples,
+        );
+        assert!(missing
+            .to_string()
+            .contains("is the examples checkout complete?"));
+        fs::create_dir_all(examples.join(".git/info")).unwrap();
+        fs::write(examples.join(".git/info/sparse-checkout"), "/basys3/\n").unwrap();
+        let missing = Missing::example(
+            "zedboard/blink",
+            &examples.join("zedboard/blink"),
+            &examples,
+        );
+        assert!(missing.to_string().contains("sparse checkout"));
+
+        let err = io::Error::from(missing.clone());
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(of(&err), Some(&missing));
+        assert_eq!(err.to_string(), missing.to_string());
+        assert_eq!(of(&io::Error::other("timed out")), None);
+    }
+}
@@ -0,0 +1,378 @@
+//! Statistics aggregated from the history of the past runs.
+
+use std::time::Duration;
+
+use crate::history::{Entry, RecordedRun};
+use crate::report::json_string;
+use crate::results::{is_failure, is_pass};
+use crate::timestamp::DateTime;
+
+/// Aggregated results of a regression over the considered runs.
+#[derive(Debug, Clone)]
+pub struct RegressionStats {
+    pub name: String,
+    /// Number of considered runs that executed the regression
+    pub runs: usize,
+    /// Number of those runs where it passed
+    pub passed: usize,
+    /// Number of times the status changed between consecutive executions
+    pub flips: usize,
+    /// Average duration over the considered runs
+    pub average_duration: Duration,
+    /// Identifier of the most recent considered run where it failed
+    pub last_failed: Option<String>,
+    /// Similarities measured by the considered runs, oldest first, for the regressions compared by similarity
+    pub similarities: Vec<f64>,
+}
+
+impl RegressionStats {
+    /// Fraction of the runs where the regression passed.
+    pub fn pass_rate(&self) -> f64 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.passed as f64 / self.runs as f64
+        }
+    }
+}
+
+/// Pass percentage of a single run of the suite.
+#[derive(Debug, Clone)]
+pub struct RunTrend {
+    pub id: String,
+    pub passed: usize,
+    pub total: usize,
+}
+
+/// Aggregates the runs (oldest first) for the regressions accepted by the filter.
+///
+/// The most broken and flaky regressions come first: they are sorted by
+/// increasing pass rate, then by decreasing number of status flips.
+pub fn aggregate<F: Fn(&Entry) -> bool>(
+    runs: &[RecordedRun],
+    filter: F,
+) -> (Vec<RegressionStats>, Vec<RunTrend>) {
+    let mut stats: Vec<RegressionStats> = Vec::new();
+    let mut last_status: Vec<(String, bool)> = Vec::new();
+    let mut total_durations: Vec<Duration> = Vec::new();
+    let mut trend = Vec::new();
+
+    for run in runs {
+        let mut run_trend = RunTrend {
+            id: run.id.clone(),
+            passed: 0,
+            total: 0,
+        };
+        for entry in run.entries.iter().filter(|e| filter(e)) {
+            let passed = is_pass(&entry.status);
+            run_trend.total += 1;
+            if passed {
+                run_trend.passed += 1;
+            }
+
+            let index = match stats.iter().position(|s| s.name == entry.name) {
+                Some(index) => index,
+                None => {
+                    stats.push(RegressionStats {
+                        name: entry.name.clone(),
+                        runs: 0,
+                        passed: 0,
+                        flips: 0,
+                        average_duration: Duration::ZERO,
+                        last_failed: None,
+                        similarities: Vec::new(),
+                    });
+                    last_status.push((entry.name.clone(), passed));
+                    total_durations.push(Duration::ZERO);
+                    stats.len() - 1
+                }
+            };
+            let regression = &mut stats[index];
+            regression.runs += 1;
+            if passed {
+                regression.passed += 1;
+            }
+            if is_failure(&entry.status) {
+                regression.last_failed = Some(run.id.clone());
+            }
+            if last_status[index].1 != passed {
+                regression.flips += 1;
+                last_status[index].1 = passed;
+            }
+            total_durations[index] += entry.duration;
+            regression.similarities.extend(entry.similarity);
+        }
+        trend.push(run_trend);
+    }
+
+    for (regression, total) in stats.iter_mut().zip(total_durations) {
+        regression.average_duration = total / regression.runs.max(1) as u32;
+    }
+    stats.sort_by(|a, b| {
+        a.pass_rate()
+            .total_cmp(&b.pass_rate())
+            .then(b.flips.cmp(&a.flips))
+            .then(a.name.cmp(&b.name))
+    });
+    (stats, trend)
+}
+
+/// Returns the date of a run from its identifier.
+fn run_date(id: &str) -> String {
+    DateTime::from_compact(id)
+        .map(|date| date.date())
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// Width of the bars of the trend lines.
+const TREND_WIDTH: usize = 40;
+
+/// Prints the statistics as text.
+pub fn print_text(stats: &[RegressionStats], trend: &[RunTrend]) {
+    println!(
+        "{:<40} {:>9} {:>6} {:>6} {:>12}  last failed",
+        "regression", "pass rate", "runs", "flips", "avg duration"
+    );
+    for regression in stats {
+        let color = if regression.passed == regression.runs {
+            "\x1b[0;32m"
+        } else if regression.passed == 0 {
+            "\x1b[0;31m"
+        } else {
+            "\x1b[0;33m"
+        };
+        println!(
+            "{:<40} {}{:>8.1}%\x1b[0m {:>6} {:>6} {:>11.1}s  {}",
+            regression.name,
+            color,
+            regression.pass_rate() * 100.0,
+            regression.runs,
+            regression.flips,
+            regression.average_duration.as_secs_f64(),
+            regression
+                .last_failed
+                .as_deref()
+                .map(run_date)
+                .unwrap_or_else(|| "never".to_string())
+        );
+    }
+
+    let compared: Vec<&RegressionStats> = stats
+        .iter()
+        .filter(|regression| !regression.similarities.is_empty())
+        .collect();
+    if !compared.is_empty() {
+        println!();
+        println!("Similarity trend:");
+    }
+    for regression in compared {
+        let similarities: Vec<String> = regression
+            .similarities
+            .iter()
+            .map(|similarity| format!("{:.2}%", similarity))
+            .collect();
+        println!("  {:<40} {}", regression.name, similarities.join(" "));
+    }
+
+    println!();
+    println!("Suite trend:");
+    for run in trend {
+        let percentage = if run.total == 0 {
+            0.0
+        } else {
+            run.passed as f64 * 100.0 / run.total as f64
+        };
+        let bar = (percentage / 100.0 * TREND_WIDTH as f64).round() as usize;
+        println!(
+            "  {} {:>6.1}% ({}/{}) {}",
+            run.id,
+            percentage,
+            run.passed,
+            run.total,
+            "#".repeat(bar)
+        );
+    }
+}
+
+/// Renders the statistics as JSON.
+pub fn render_json(stats: &[RegressionStats], trend: &[RunTrend]) -> String {
+    let regressions: Vec<String> = stats
+        .iter()
+        .map(|r| {
+            format!(
+                "{{\"name\": {}, \"runs\": {}, \"passed\": {}, \"pass_rate\": {:.4}, \"flips\": {}, \"average_duration_seconds\": {:.3}, \"last_failed\": {}, \"similarities\": [{}]}}",
+                json_string(&r.name),
+                r.runs,
+                r.passed,
+                r.pass_rate(),
+                r.flips,
+                r.average_duration.as_secs_f64(),
+                r.last_failed
+                    .as_deref()
+                    .map(json_string)
+                    .unwrap_or_else(|| "null".to_string()),
+                r.similarities
+                    .iter()
+                    .map(|similarity| format!("{:.2}", similarity))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )
+        })
+        .collect();
+    let runs: Vec<String> = trend
+        .iter()
+        .map(|run| {
+            format!(
+                "{{\"run_id\": {}, \"passed\": {}, \"total\": {}}}",
+                json_string(&run.id),
+                run.passed,
+                run.total
+            )
+        })
+        .collect();
+    format!(
+        "{{\"regressions\": [{}], \"trend\": [{}]}}",
+        regressions.join(", "),
+        runs.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, status: &str, millis: u64) -> Entry {
+        Entry {
+            name: name.to_string(),
+            status: status.to_string(),
+            duration: Duration::from_millis(millis),
+            tags: vec!["default".to_string()],
+            similarity: None,
+            bench: Vec::new(),
+        }
+    }
+
+    fn run(id: &str, entries: Vec<Entry>) -> RecordedRun {
+        RecordedRun {
+            id: id.to_string(),
+            entries,
+        }
+    }
+
+    /// Three runs: blink always passes, counter flips and uart is only in the last two.
+    fn runs() -> Vec<RecordedRun> {
+        vec![
+            run(
+                "20240401T020000Z-000000",
+                vec![
+                    entry("blink", "passed", 1000),
+                    entry("counter", "passed", 100),
+                ],
+            ),
+            run(
+                "20240402T020000Z-000000",
+                vec![
+                    entry("blink", "passed", 2000),
+                    entry("counter", "failed", 200),
+                    entry("uart", "error", 10),
+                ],
+            ),
+            run(
+                "20240403T020000Z-000000",
+                vec![
+                    entry("blink", "captured", 3000),
+                    entry("counter", "passed", 300),
+                    entry("uart", "skipped", 20),
+                ],
+            ),
+        ]
+    }
+
+    #[test]
+    fn aggregates_the_runs_of_each_regression() {
+        let (stats, _trend) = aggregate(&runs(), |_| true);
+        // The most broken first
+        let names: Vec<&str> = stats.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["uart", "counter", "blink"]);
+
+        let uart = &stats[0];
+        assert_eq!((uart.runs, uart.passed, uart.flips), (2, 0, 0));
+        assert_eq!(uart.pass_rate(), 0.0);
+        assert_eq!(uart.last_failed.as_deref(), Some("20240402T020000Z-000000"));
+
+        let counter = &stats[1];
+        assert_eq!((counter.runs, counter.passed, counter.flips), (3, 2, 2));
+        assert_eq!(counter.average_duration, Duration::from_millis(200));
+        assert_eq!(
+            counter.last_failed.as_deref(),
+            Some("20240402T020000Z-000000")
+        );
+
+        let blink = &stats[2];
+        assert_eq!((blink.runs, blink.passed, blink.flips), (3, 3, 0));
+        assert_eq!(blink.pass_rate(), 1.0);
+        assert_eq!(blink.average_duration, Duration::from_secs(2));
+        assert_eq!(blink.last_failed, None);
+    }
+
+    #[test]
+    fn orders_the_equally_broken_regressions_by_flips() {
+        let runs = vec![
+            run(
+                "a",
+                vec![entry("steady", "failed", 0), entry("flaky", "passed", 0)],
+            ),
+            run(
+                "b",
+                vec![entry("steady", "failed", 0), entry("flaky", "failed", 0)],
+            ),
+            run(
+                "c",
+                vec![entry("steady", "passed", 0), entry("flaky", "passed", 0)],
+            ),
+            run(
+                "d",
+                vec![entry("steady", "passed", 0), entry("flaky", "failed", 0)],
+            ),
+        ];
+        let (stats, _trend) = aggregate(&runs, |_| true);
+        let flips: Vec<(&str, usize)> = stats.iter().map(|s| (s.name.as_str(), s.flips)).collect();
+        assert_eq!(flips, [("flaky", 3), ("steady", 1)]);
+    }
+
+    #[test]
+    fn follows_the_suite_and_the_similarities_over_the_runs() {
+        let mut runs = runs();
+        runs[0].entries[0].similarity = Some(99.5);
+        runs[2].entries[0].similarity = Some(100.0);
+        let (stats, trend) = aggregate(&runs, |e| e.name != "uart");
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[1].similarities, [99.5, 100.0]);
+        assert!(stats[0].similarities.is_empty());
+        let trend: Vec<(usize, usize)> = trend.iter().map(|t| (t.passed, t.total)).collect();
+        assert_eq!(trend, [(2, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn renders_the_statistics_as_json() {
+        let mut runs = runs();
+        runs.truncate(2);
+        runs[0].entries[0].similarity = Some(99.5);
+        let (stats, trend) = aggregate(&runs, |e| e.name == "blink" || e.name == "uart");
+        assert_eq!(
+            render_json(&stats, &trend),
+            "{\"regressions\": [\
+             {\"name\": \"uart\", \"runs\": 1, \"passed\": 0, \"pass_rate\": 0.0000, \"flips\": 0, \"average_duration_seconds\": 0.010, \"last_failed\": \"20240402T020000Z-000000\", \"similarities\": []}, \
+             {\"name\": \"blink\", \"runs\": 2, \"passed\": 2, \"pass_rate\": 1.0000, \"flips\": 0, \"average_duration_seconds\": 1.500, \"last_failed\": null, \"similarities\": [99.50]}], \
+             \"trend\": [\
+             {\"run_id\": \"20240401T020000Z-000000\", \"passed\": 1, \"total\": 1}, \
+             {\"run_id\": \"20240402T020000Z-000000\", \"passed\": 1, \"total\": 2}]}"
+        );
+    }
+
+    #[test]
+    fn dates_the_runs_by_their_identifier() {
+        assert_eq!(run_date("20240401T020000Z-3fa9c1"), "2024-04-01");
+        assert_eq!(run_date("nightly-42"), "nightly-42");
+    }
+}
@@ -0,0 +1,166 @@
+//! Rendering of the status of the regressions.
+//!
+//! Every status line printed for a regression goes through [`status`], so that
+//! the chosen style applies uniformly: colored words, symbols that do not rely
+//! on the color alone, or plain ASCII tags that are easy to match in scripts.
+
+/// How the status of a regression is printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum StatusStyle {
+    /// Colored words, e.g. passed in green
+    #[default]
+    Words,
+    /// A symbol before the colored word, e.g. ✔ passed
+    Symbols,
+    /// Uncolored ASCII tags, e.g. [PASS]
+    Ascii,
+}
+
+impl StatusStyle {
+    /// Parses the name of a style, as written in the user configuration.
+    pub fn parse(name: &str) -> Option<StatusStyle> {
+        match name {
+            "words" => Some(StatusStyle::Words),
+            "symbols" => Some(StatusStyle::Symbols),
+            "ascii" => Some(StatusStyle::Ascii),
+            _ => None,
+        }
+    }
+}
+
+const GREEN: &str = "\x1b[0;32m";
+const RED: &str = "\x1b[0;31m";
+const YELLOW: &str = "\x1b[0;33m";
+const RESET: &str = "\x1b[0m";
+
+/// Returns the color, the symbol and the ASCII tag of a status.
+fn appearance(label: &str) -> (&'static str, &'static str, &'static str) {
+    match label {
+        "passed" => (GREEN, "✔", "[PASS]"),
+        "no differences" => (GREEN, "✔", "[SAME]"),
+        "unchanged" => (GREEN, "✔", "[UNCHANGED]"),
+        "failed" => (RED, "✘", "[FAIL]"),
+        "differences found" => (RED, "✘", "[DIFF]"),
+        "quarantined failure" => (YELLOW, "✘", "[QUARANTINED-FAIL]"),
+        "reset" => (YELLOW, "↺", "[RESET]"),
+        "updated" => (YELLOW, "↺", "[UPDATED]"),
+        "would change" => (YELLOW, "↺", "[WOULD-CHANGE]"),
+        "captured" => (YELLOW, "+", "[CAPTURED]"),
+        "skipped" => (YELLOW, "-", "[SKIP]"),
+        "blocked" => (RED, "⊘", "[BLOCKED]"),
+        "error" => (RED, "!", "[ERROR]"),
+        "never run" => (YELLOW, "·", "[NEVER-RUN]"),
+        "not run" => (YELLOW, "⏹", "[NOT-RUN]"),
+        _ => (RED, "?", "[UNKNOWN]"),
+    }
+}
+
+/// Renders a status, given by its label (see `Outcome::label`), in a style.
+pub fn status(style: StatusStyle, label: &str) -> String {
+    let (color, symbol, tag) = appearance(label);
+    match style {
+        StatusStyle::Words => format!("{}{}{}", color, label, RESET),
+        StatusStyle::Symbols => format!("{}{} {}{}", color, symbol, label, RESET),
+        StatusStyle::Ascii => tag.to_string(),
+    }
+}
+
+/// Returns a rendered text without its color codes.
+pub fn plain(rendered: &str) -> String {
+    let mut plain = String::new();
+    let mut escape = false;
+    for c in rendered.chars() {
+        match (escape, c) {
+            (false, '\x1b') => escape = true,
+            (false, _) => plain.push(c),
+            (true, 'm') => escape = false,
+            (true, _) => {}
+        }
+    }
+    plain
+}
+
+/// Returns the width of a rendered status on the terminal, without its color codes.
+pub fn width(rendered: &str) -> usize {
+    plain(rendered).chars().count()
+}
+
+/// Renders a note following a status, highlighted unless in the ASCII style.
+pub fn note(style: StatusStyle, text: &str) -> String {
+    match style {
+        StatusStyle::Ascii => text.to_string(),
+        _ => format!("{}{}{}", YELLOW, text, RESET),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_the_status_in_each_style() {
+        assert_eq!(
+            status(StatusStyle::Words, "passed"),
+            "\x1b[0;32mpassed\x1b[0m"
+        );
+        assert_eq!(
+            status(StatusStyle::Symbols, "failed"),
+            "\x1b[0;31m✘ failed\x1b[0m"
+        );
+        assert_eq!(status(StatusStyle::Ascii, "passed"), "[PASS]");
+        assert_eq!(
+            status(StatusStyle::Ascii, "quarantined failure"),
+            "[QUARANTINED-FAIL]"
+        );
+        assert_eq!(status(StatusStyle::Ascii, "mystery"), "[UNKNOWN]");
+    }
+
+    #[test]
+    fn tells_the_statuses_apart_without_the_colors() {
+        let labels = [
+            "passed",
+            "no differences",
+            "unchanged",
+            "failed",
+            "differences found",
+            "quarantined failure",
+            "reset",
+            "updated",
+            "would change",
+            "captured",
+            "skipped",
+            "blocked",
+            "error",
+            "never run",
+            "not run",
+        ];
+        let mut tags: Vec<&str> = labels.iter().map(|label| appearance(label).2).collect();
+        tags.sort();
+        tags.dedup();
+        assert_eq!(tags.len(), labels.len());
+        assert!(!tags.contains(&"[UNKNOWN]"));
+        for label in labels {
+            let tag = status(StatusStyle::Ascii, label);
+            assert!(tag.is_ascii() && !tag.contains('\x1b'), "{}", tag);
+        }
+    }
+
+    #[test]
+    fn measures_the_rendered_text_without_its_colors() {
+        let rendered = status(StatusStyle::Symbols, "passed");
+        assert_eq!(plain(&rendered), "✔ passed");
+        assert_eq!(width(&rendered), 8);
+        assert_eq!(width("[PASS]"), 6);
+        assert_eq!(note(StatusStyle::Words, "flaky"), "\x1b[0;33mflaky\x1b[0m");
+        assert_eq!(note(StatusStyle::Ascii, "flaky"), "flaky");
+    }
+
+    #[test]
+    fn parses_the_configured_styles() {
+        assert_eq!(StatusStyle::parse("words"), Some(StatusStyle::Words));
+        assert_eq!(StatusStyle::parse("symbols"), Some(StatusStyle::Symbols));
+        assert_eq!(StatusStyle::parse("ascii"), Some(StatusStyle::Ascii));
+        assert_eq!(StatusStyle::parse("ASCII"), None);
+        assert_eq!(StatusStyle::default(), StatusStyle::Words);
+    }
+}
@@ -0,0 +1,450 @@
+//! Machine-readable summary of the last run, for CI wrappers.
+//!
+//! Whatever report options are given, every run writes a small JSON summary to
+//! `$BMREGRESSION_SUMMARY`, or to `<data-dir>/.bmregression/last-summary.json`
+//! when the variable is not set. It is written to a temporary file and renamed
+//! into place, so that a reader never sees a partial summary, and it is written
+//! with `"completed": false` when the run is interrupted.
+//!
+//! The recap printed at the end of a run comes from the same results: the
+//! failures first, each with the command reproducing it alone, then the counts.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::atomic;
+use crate::logs::STATE_DIR;
+use crate::report::json_string;
+use crate::results::{self, GroupBy, RunReport};
+use crate::threshold::{self, Measure};
+
+/// Environment variable overriding the path of the summary.
+pub const PATH_VARIABLE: &str = "BMREGRESSION_SUMMARY";
+
+/// Returns the path of the summary of the runs on a data directory.
+pub fn path(target: &str) -> PathBuf {
+    match std::env::var_os(PATH_VARIABLE).filter(|p| !p.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(target).join(STATE_DIR).join("last-summary.json"),
+    }
+}
+
+/// Returns what an exit code of the `run` command means.
+pub fn exit_meaning(code: i32) -> &'static str {
+    match code {
+        0 => "the run completed and no regression failed",
+        1 => "regressions failed",
+        2 => "error: regressions could not be executed, a configuration is broken, or the regressions could not be selected or reported",
+        3 => "infrastructure errors (--strict)",
+        5 => "incomplete: the --max-total-time budget was spent before all the regressions ran",
+        threshold::EXIT_CODE => "more regressions failed than --fail-threshold tolerates",
+        code if code > 128 => "interrupted",
+        _ => "error",
+    }
+}
+
+/// Renders the summary of a run.
+///
+/// # Arguments
+///
+/// * `report` - Results of the run, possibly partial
+/// * `completed` - False if the run was interrupted
+/// * `exit_code` - Exit code of the invocation
+/// * `threshold` - Failures measured against `--fail-threshold`, if given
+/// * `reports` - Files written by the run, as (kind, path) pairs
+/// * `group_by` - Criterion of the counts of each group under `groups`, left out if None
+pub fn render(
+    report: &RunReport,
+    completed: bool,
+    exit_code: i32,
+    threshold: Option<&Measure>,
+    reports: &[(String, String)],
+    group_by: Option<GroupBy>,
+) -> String {
+    let failing: Vec<String> = report
+        .results
+        .iter()
+        .filter(|r| r.status == "failed" || r.status == "error")
+        .map(|r| json_string(&r.name))
+        .collect();
+    let not_run: Vec<String> = report
+        .results
+        .iter()
+        .filter(|r| r.status == results::NOT_RUN)
+        .map(|r| json_string(&r.name))
+        .collect();
+    let similarities: Vec<String> = report
+        .results
+        .iter()
+        .filter(|r| r.similarity.is_some())
+        .map(|r| format!("{}: {}", json_string(&r.name), r.similarity_json()))
+        .collect();
+    let bench: Vec<String> = report
+        .results
+        .iter()
+        .filter(|r| !r.bench.is_empty())
+        .map(|r| format!("{}: {}", json_string(&r.name), r.bench_json()))
+        .collect();
+    let unreliable: Vec<String> = report
+        .results
+        .iter()
+        .filter(|r| r.unreliable.is_some())
+        .map(|r| format!("{}: {}", json_string(&r.name), r.unreliable_json()))
+        .collect();
+    let reports: Vec<String> = reports
+        .iter()
+        .map(|(kind, path)| format!("{}: {}", json_string(kind), json_string(path)))
+        .collect();
+    let groups = match group_by {
+        Some(by) => {
+            let groups: Vec<String> = report
+                .groups(by)
+                .iter()
+                .map(|(key, counts)| {
+                    format!(
+                        "{}: {{\"total\": {}, \"passed\": {}, \"failed\": {}, \"errors\": {}}}",
+                        json_string(key),
+                        counts.total,
+                        counts.passed,
+                        counts.failed,
+                        counts.errors
+                    )
+                })
+                .collect();
+            format!(
+                ",\n  \"group_by\": {},\n  \"groups\": {{{}}}",
+                json_string(by.name()),
+                groups.join(", ")
+            )
+        }
+        None => String::new(),
+    };
+    format!(
+        "{{\n  \"run_id\": {},\n  \"started\": {},\n  \"completed\": {},\n  \"exit_code\": {},\n  \"exit_meaning\": {},\n  \"duration_seconds\": {},\n  \"counts\": {{\"total\": {}, \"passed\": {}, \"captured\": {}, \"failed\": {}, \"errors\": {}, \"quarantined_failures\": {}, \"not_run\": {}}},\n  \"fail_threshold\": {},\n  \"failing\": [{}],\n  \"not_run\": [{}],\n  \"similarities\": {{{}}},\n  \"bench\": {{{}}},\n  \"unreliable\": {{{}}},\n  \"reports\": {{{}}}{}\n}}\n",
+        json_string(&report.run_id),
+        json_string(&report.started.compact()),
+        completed,
+        exit_code,
+        json_string(exit_meaning(exit_code)),
+        report.duration.as_secs(),
+        report.results.len(),
+        report.count("passed"),
+        report.count("captured"),
+        report.count("failed"),
+        report.count("error"),
+        report.count("quarantined failure"),
+        not_run.len(),
+        threshold.map_or("null".to_string(), Measure::json),
+        failing.join(", "),
+        not_run.join(", "),
+        similarities.join(", "),
+        bench.join(", "),
+        unreliable.join(", "),
+        reports.join(", "),
+        groups
+    )
+}
+
+/// Quotes an argument for the shell, when it holds characters the shell would interpret.
+pub fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Renders the recap printed at the end of a run, the failures first.
+///
+/// Each failing regression comes with the command running it alone and showing its
+/// differences, and the regressions that could not be executed with the logs of their
+/// command. Nothing but the counts and the identifier of the run is rendered when no
+/// regression failed.
+///
+/// # Arguments
+///
+/// * `report` - Results of the run, possibly partial
+/// * `reproduce` - Command line running the regressions with the options of this run, to
+///   which the name of a regression is appended
+/// * `group_by` - Criterion of the counts printed for each group before the totals, if any
+pub fn recap(report: &RunReport, reproduce: &str, group_by: Option<GroupBy>) -> String {
+    let mut out = String::new();
+    let failing: Vec<_> = report
+        .results
+        .iter()
+        .filter(|r| results::is_failure(r.status))
+        .collect();
+    if !failing.is_empty() {
+        out.push_str(&format!("\x1b[0;31mFailures ({}):\x1b[0m\n", failing.len()));
+    }
+    for result in failing {
+        if result.status == "error" {
+            out.push_str(&format!("  {}: error: {}\n", result.name, result.error));
+        } else {
+            out.push_str(&format!("  {}: failed\n", result.name));
+        }
+        if result.status == "error" && !result.log.is_empty() {
+            out.push_str(&format!("    logs: {}.{{out,err}}\n", result.log));
+        }
+        out.push_str(&format!(
+            "    {} --exact {} --diff-on-fail\n",
+            reproduce,
+            shell_quote(&result.name)
+        ));
+    }
+    // Listed, so that the regressions the deadline left out are not mistaken for passing ones
+    let not_run: Vec<&str> = report
+        .results
+        .iter()
+        .filter(|r| r.status == results::NOT_RUN)
+        .map(|r| r.name.as_str())
+        .collect();
+    if !not_run.is_empty() {
+        out.push_str(&format!(
+            "\x1b[0;33mNot run, the time budget was spent ({}):\x1b[0m\n",
+            not_run.len()
+        ));
+        for name in &not_run {
+            out.push_str(&format!("  {}\n", name));
+        }
+        out.push_str(&format!("    {} --failed\n", reproduce));
+    }
+    if let Some(by) = group_by {
+        out.push_str(&render_groups(report, by));
+    }
+    out.push_str(&format!(
+        "Recap: {} regressions, {} passed, {} failed, {} errors",
+        report.results.len(),
+        report.count("passed"),
+        report.count("failed"),
+        report.count("error")
+    ));
+    if report.count("captured") > 0 {
+        out.push_str(&format!(", {} captured", report.count("captured")));
+    }
+    if !not_run.is_empty() {
+        out.push_str(&format!(", {} not run", not_run.len()));
+    }
+    if report.count("blocked") > 0 {
+        out.push_str(&format!(
+            ", {} blocked (command not allowlisted)",
+            report.count("blocked")
+        ));
+    }
+    out.push_str(&report.quarantined_note());
+    out.push('\n');
+    out.push_str(&format!("  run id: {}\n", report.run_id));
+    out
+}
+
+/// Renders the counts of each group, a line per group, noting once that a regression in
+/// several groups counts in each of them.
+fn render_groups(report: &RunReport, by: GroupBy) -> String {
+    let groups = report.groups(by);
+    let width = groups.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    let mut out = format!("By {}:\n", by.name());
+    for (key, counts) in &groups {
+        out.push_str(&format!(
+            "  {:width$}  {} regressions, {} passed, {} failed, {} errors\n",
+            key,
+            counts.total,
+            counts.passed,
+            counts.failed,
+            counts.errors,
+            width = width
+        ));
+    }
+    if report
+        .results
+        .iter()
+        .any(|result| by.keys(result).len() > 1)
+    {
+        out.push_str(&format!(
+            "  (a regression with several {}s counts in each of them)\n",
+            by.name()
+        ));
+    }
+    out
+}
+
+/// Writes the summary of a run, replacing the previous one atomically.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write(path: &Path, summary: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    atomic::write(path, summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::RegressionResult;
+    use crate::threshold::Threshold;
+    use crate::timestamp::DateTime;
+    use std::time::Duration;
+
+    fn report(results: &[(&str, &'static str, &[&str])]) -> RunReport {
+        RunReport {
+            run_id: "20240401T153000Z-4242".to_string(),
+            started: DateTime::from_compact("20240401T153000Z").unwrap(),
+            duration: Duration::from_millis(754_900),
+            environment: Default::default(),
+            results: results
+                .iter()
+                .map(|(name, status, tags)| RegressionResult {
+                    name: name.to_string(),
+                    tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                    status,
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn renders_the_summary_of_a_run() {
+        let mut report = report(&[
+            ("blink", "passed", &["default"]),
+            ("counter", "failed", &["default"]),
+            ("uart", "error", &["default"]),
+            ("spi", results::NOT_RUN, &["default"]),
+        ]);
+        report.results[0].similarity = Some(99.5);
+        report.results[0].unreliable = Some("src/main.c changed".to_string());
+        let reports = [("html".to_string(), "report.html".to_string())];
+        assert_eq!(
+            render(&report, true, 2, None, &reports, None),
+            "{\n  \"run_id\": \"20240401T153000Z-4242\",\n  \"started\": \"20240401T153000Z\",\n  \
+             \"completed\": true,\n  \"exit_code\": 2,\n  \"exit_meaning\": \"error: regressions could not be executed, \
+             a configuration is broken, or the regressions could not be selected or reported\",\n  \
+             \"duration_seconds\": 754,\n  \"counts\": {\"total\": 4, \"passed\": 1, \"captured\": 0, \"failed\": 1, \
+             \"errors\": 1, \"quarantined_failures\": 0, \"not_run\": 1},\n  \"fail_threshold\": null,\n  \
+             \"failing\": [\"counter\", \"uart\"],\n  \"not_run\": [\"spi\"],\n  \"similarities\": {\"blink\": 99.50},\n  \
+             \"bench\": {},\n  \"unreliable\": {\"blink\": \"src/main.c changed\"},\n  \
+             \"reports\": {\"html\": \"report.html\"}\n}\n"
+        );
+    }
+
+    #[test]
+    fn renders_the_threshold_and_the_groups() {
+        let report = report(&[
+            ("blink", "passed", &["fpga", "default"]),
+            ("counter", "failed", &["default"]),
+            ("uart", "captured", &[]),
+        ]);
+        let measure = Measure::new(Threshold::Count(0), &report, false);
+        let summary = render(&report, false, 130, Some(&measure), &[], Some(GroupBy::Tag));
+        assert!(summary.contains("\"completed\": false,"), "{}", summary);
+        assert!(
+            summary.contains("\"exit_code\": 130,\n  \"exit_meaning\": \"interrupted\","),
+            "{}",
+            summary
+        );
+        assert!(
+            summary.contains("\"fail_threshold\": {\"threshold\": \"0\", \"failed\": 1, "),
+            "{}",
+            summary
+        );
+        assert!(
+            summary.ends_with(
+                "\"reports\": {},\n  \"group_by\": \"tag\",\n  \"groups\": {\
+                 \"(none)\": {\"total\": 1, \"passed\": 0, \"failed\": 0, \"errors\": 0}, \
+                 \"default\": {\"total\": 2, \"passed\": 1, \"failed\": 1, \"errors\": 0}, \
+                 \"fpga\": {\"total\": 1, \"passed\": 1, \"failed\": 0, \"errors\": 0}}\n}\n"
+            ),
+            "{}",
+            summary
+        );
+    }
+
+    #[test]
+    fn tells_the_meaning_of_each_exit_code() {
+        assert_eq!(
+            exit_meaning(0),
+            "the run completed and no regression failed"
+        );
+        assert_eq!(exit_meaning(1), "regressions failed");
+        assert!(exit_meaning(2).starts_with("error: "));
+        assert_eq!(exit_meaning(3), "infrastructure errors (--strict)");
+        assert_eq!(
+            exit_meaning(4),
+            "more regressions failed than --fail-threshold tolerates"
+        );
+        assert!(exit_meaning(5).starts_with("incomplete: "));
+        assert_eq!(exit_meaning(143), "interrupted");
+        assert_eq!(exit_meaning(7), "error");
+    }
+
+    #[test]
+    fn quotes_the_arguments_for_the_shell() {
+        assert_eq!(shell_quote("basys3_counter"), "basys3_counter");
+        assert_eq!(shell_quote("fpga/led-2.0"), "fpga/led-2.0");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("two words"), "'two words'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+    }
+
+    #[test]
+    fn recaps_the_failures_first() {
+        let mut report = report(&[
+            ("blink", "passed", &["default"]),
+            ("my counter", "failed", &["default"]),
+            ("uart", "error", &["default"]),
+            ("spi", results::NOT_RUN, &["default"]),
+            ("i2c", "blocked", &["default"]),
+        ]);
+        report.results[2].error = "command exited with 2".to_string();
+        report.results[2].log = "logs/uart".to_string();
+        assert_eq!(
+            recap(&report, "bmregression run", None),
+            "\x1b[0;31mFailures (2):\x1b[0m\n  my counter: failed\n    \
+             bmregression run --exact 'my counter' --diff-on-fail\n  \
+             uart: error: command exited with 2\n    logs: logs/uart.{out,err}\n    \
+             bmregression run --exact uart --diff-on-fail\n\
+             \x1b[0;33mNot run, the time budget was spent (1):\x1b[0m\n  spi\n    \
+             bmregression run --failed\n\
+             Recap: 5 regressions, 1 passed, 1 failed, 1 errors, 1 not run, \
+             1 blocked (command not allowlisted)\n  run id: 20240401T153000Z-4242\n"
+        );
+    }
+
+    #[test]
+    fn recaps_only_the_counts_when_everything_passed() {
+        let report = report(&[
+            ("blink", "passed", &["default", "fpga"]),
+            ("counter", "captured", &["default"]),
+        ]);
+        assert_eq!(
+            recap(&report, "bmregression run", None),
+            "Recap: 2 regressions, 1 passed, 0 failed, 0 errors, 1 captured\n  \
+             run id: 20240401T153000Z-4242\n"
+        );
+        assert_eq!(
+            recap(&report, "bmregression run", Some(GroupBy::Tag)),
+            "By tag:\n  default  2 regressions, 1 passed, 0 failed, 0 errors\n  \
+             fpga     1 regressions, 1 passed, 0 failed, 0 errors\n  \
+             (a regression with several tags counts in each of them)\n\
+             Recap: 2 regressions, 1 passed, 0 failed, 0 errors, 1 captured\n  \
+             run id: 20240401T153000Z-4242\n"
+        );
+    }
+
+    #[test]
+    fn writes_the_summary_creating_its_directory() {
+        let dir = tempdir::TempDir::new("summary").unwrap();
+        let path = dir.path().join(STATE_DIR).join("last-summary.json");
+        write(&path, "{}\n").unwrap();
+        write(&path, "{\"completed\": true}\n").unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "{\"completed\": true}\n");
+    }
+}
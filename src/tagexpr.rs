@@ -0,0 +1,277 @@
+//! Boolean expressions over the tags of the regressions.
+//!
+//! `--tag` selects the regressions having any of a list of tags, which cannot
+//! say "fpga and quick and not vivado". `--tag-expr` takes such an expression
+//! instead, with `and`, `or`, `not` and parentheses, `not` binding tightest and
+//! `or` loosest:
+//!
+//! ```text
+//! fpga and quick and not vivado
+//! (basys3 or zedboard) and not slow
+//! ```
+//!
+//! The operators are matched ignoring case, anything else between spaces and
+//! parentheses is a tag name.
+
+use std::fmt;
+
+/// A node of a parsed expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Tag(String),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+}
+
+/// A parsed tag expression, with its text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagExpr {
+    text: String,
+    root: Node,
+}
+
+/// Error of an invalid expression, with the position it was found at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Offset in characters in the expression
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position + 1)
+    }
+}
+
+impl ParseError {
+    /// Returns the expression with a caret under the position of the error, on two lines.
+    pub fn pointer(&self, text: &str) -> String {
+        format!("{}\n{}^", text, " ".repeat(self.position))
+    }
+}
+
+/// A token of an expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Tag(String),
+}
+
+/// Splits an expression into its tokens.
+fn tokenize(text: &str) -> Vec<(usize, Token)> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut start = 0;
+    let flush = |word: &mut String, start: usize, tokens: &mut Vec<(usize, Token)>| {
+        if word.is_empty() {
+            return;
+        }
+        let token = match word.to_ascii_lowercase().as_str() {
+            "and" => Token::And,
+            "or" => Token::Or,
+            "not" => Token::Not,
+            _ => Token::Tag(word.clone()),
+        };
+        tokens.push((start, token));
+        word.clear();
+    };
+    for (position, c) in text.chars().enumerate() {
+        if c.is_whitespace() || c == '(' || c == ')' {
+            flush(&mut word, start, &mut tokens);
+            match c {
+                '(' => tokens.push((position, Token::Open)),
+                ')' => tokens.push((position, Token::Close)),
+                _ => {}
+            }
+        } else {
+            if word.is_empty() {
+                start = position;
+            }
+            word.push(c);
+        }
+    }
+    flush(&mut word, start, &mut tokens);
+    tokens
+}
+
+/// Recursive descent parser of the tokens.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    /// Offset of the end of the expression, where a missing token is reported
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.next)
+            .map(|(position, _)| *position)
+            .unwrap_or(self.end)
+    }
+
+    /// Returns the error of finding the next token, or the end, instead of `expected`.
+    fn error(&self, expected: &str) -> ParseError {
+        let found = match self.peek() {
+            None => "the end".to_string(),
+            Some(Token::Open) => "(".to_string(),
+            Some(Token::Close) => ")".to_string(),
+            Some(Token::And) => "and".to_string(),
+            Some(Token::Or) => "or".to_string(),
+            Some(Token::Not) => "not".to_string(),
+            Some(Token::Tag(tag)) => format!("the tag {}", tag),
+        };
+        ParseError {
+            position: self.position(),
+            message: format!("expected {}, found {}", expected, found),
+        }
+    }
+
+    fn or(&mut self) -> Result<Node, ParseError> {
+        let mut node = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next += 1;
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, ParseError> {
+        let mut node = self.unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next += 1;
+            node = Node::And(Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node, ParseError> {
+        match self.peek().cloned() {
+            Some(Token::Not) => {
+                self.next += 1;
+                Ok(Node::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::Open) => {
+                let open = self.position();
+                self.next += 1;
+                let node = self.or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(self.error(&format!(") closing the ( at position {}", open + 1)));
+                }
+                self.next += 1;
+                Ok(node)
+            }
+            Some(Token::Tag(tag)) => {
+                self.next += 1;
+                Ok(Node::Tag(tag))
+            }
+            _ => Err(self.error("a tag, not or (")),
+        }
+    }
+}
+
+impl TagExpr {
+    /// Parses an expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with the position of the first unexpected token, or of the end of
+    /// the expression if it is incomplete.
+    pub fn parse(text: &str) -> Result<TagExpr, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(text),
+            next: 0,
+            end: text.trim_end().chars().count(),
+        };
+        let root = parser.or()?;
+        if parser.next < parser.tokens.len() {
+            return Err(parser.error("and, or or the end"));
+        }
+        Ok(TagExpr {
+            text: text.trim().to_string(),
+            root,
+        })
+    }
+
+    /// Evaluates the expression, `has` telling whether a regression has a tag.
+    pub fn matches(&self, has: &dyn Fn(&str) -> bool) -> bool {
+        fn eval(node: &Node, has: &dyn Fn(&str) -> bool) -> bool {
+            match node {
+                Node::Tag(tag) => has(tag),
+                Node::Not(node) => !eval(node, has),
+                Node::And(left, right) => eval(left, has) && eval(right, has),
+                Node::Or(left, right) => eval(left, has) || eval(right, has),
+            }
+        }
+        eval(&self.root, has)
+    }
+}
+
+impl fmt::Display for TagExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selects(text: &str, tags: &[&str]) -> bool {
+        TagExpr::parse(text)
+            .unwrap()
+            .matches(&|tag| tags.contains(&tag))
+    }
+
+    #[test]
+    fn evaluates_with_the_usual_precedence() {
+        let expr = "fpga AND quick and not vivado";
+        assert!(selects(expr, &["fpga", "quick"]));
+        assert!(!selects(expr, &["fpga", "quick", "vivado"]));
+        assert!(!selects(expr, &["fpga"]));
+        // not binds tighter than and, which binds tighter than or
+        assert!(selects("basys3 or zedboard and slow", &["basys3"]));
+        assert!(!selects("(basys3 or zedboard) and slow", &["basys3"]));
+        assert!(selects("not not(fpga)", &["fpga"]));
+        assert_eq!(
+            TagExpr::parse(" fpga and  quick ").unwrap().to_string(),
+            "fpga and  quick"
+        );
+    }
+
+    #[test]
+    fn reports_the_position_of_the_errors() {
+        let error = |text: &str| TagExpr::parse(text).unwrap_err();
+        assert_eq!(
+            error("fpga and (quick or").to_string(),
+            "expected a tag, not or (, found the end at position 19"
+        );
+        assert_eq!(
+            error("fpga and (quick").to_string(),
+            "expected ) closing the ( at position 10, found the end at position 16"
+        );
+        assert_eq!(
+            error("fpga quick").to_string(),
+            "expected and, or or the end, found the tag quick at position 6"
+        );
+        assert_eq!(
+            error("fpga and or quick").to_string(),
+            "expected a tag, not or (, found or at position 10"
+        );
+        assert_eq!(
+            error("  ").to_string(),
+            "expected a tag, not or (, found the end at position 1"
+        );
+        assert_eq!(error("fpga )").pointer("fpga )"), "fpga )\n     ^");
+    }
+}
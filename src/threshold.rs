@@ -0,0 +1,189 @@
+//! Failure threshold of a run, for suites where a few failures are expected.
+//!
+//! With `--fail-threshold 10%` a run fails only when more than a tenth of its
+//! regressions failed, with `--fail-threshold 3` only when more than three did.
+//! The regressions that could not be executed are infrastructure errors rather
+//! than verdicts on the toolchain: they are left out of the count and of the
+//! regressions the rate is computed on, unless `--threshold-counts-errors` is
+//! given. Quarantined failures never count.
+
+use crate::results::RunReport;
+
+/// Exit code of a run whose failures exceed the threshold.
+pub const EXIT_CODE: i32 = 4;
+
+/// Number of failures a run tolerates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Threshold {
+    /// Failed regressions tolerated
+    Count(usize),
+    /// Percentage of the regressions tolerated as failures
+    Percent(f64),
+}
+
+impl Threshold {
+    /// Parses a threshold, a count such as `3` or a percentage such as `10%` or `2.5%`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is neither, or the percentage is not between 0 and 100.
+    pub fn parse(text: &str) -> Result<Threshold, String> {
+        let text = text.trim();
+        match text.strip_suffix('%') {
+            Some(percent) => match percent.trim().parse::<f64>() {
+                Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(Threshold::Percent(percent)),
+                _ => Err(format!(
+                    "invalid failure threshold {}: the percentage must be between 0% and 100%",
+                    text
+                )),
+            },
+            None => text.parse().map(Threshold::Count).map_err(|_| {
+                format!(
+                    "invalid failure threshold {}: expected a count of regressions, e.g. 3, or a percentage, e.g. 10%",
+                    text
+                )
+            }),
+        }
+    }
+
+    /// Returns the threshold as given on the command line.
+    pub fn text(&self) -> String {
+        match self {
+            Threshold::Count(count) => count.to_string(),
+            Threshold::Percent(percent) => format!("{}%", percent),
+        }
+    }
+}
+
+/// Failures of a run measured against the threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measure {
+    pub threshold: Threshold,
+    /// Failed regressions, with the errors if they count
+    pub failed: usize,
+    /// Regressions the rate is computed on
+    pub considered: usize,
+    /// True if the infrastructure errors count as failures
+    pub counts_errors: bool,
+}
+
+impl Measure {
+    /// Measures the failures of a run.
+    pub fn new(threshold: Threshold, report: &RunReport, counts_errors: bool) -> Measure {
+        let errors = report.count("error");
+        let (failed, considered) = if counts_errors {
+            (report.count("failed") + errors, report.results.len())
+        } else {
+            (report.count("failed"), report.results.len() - errors)
+        };
+        Measure {
+            threshold,
+            failed,
+            considered,
+            counts_errors,
+        }
+    }
+
+    /// Returns the percentage of failed regressions, 0 when none was considered.
+    pub fn rate(&self) -> f64 {
+        if self.considered == 0 {
+            0.0
+        } else {
+            self.failed as f64 * 100.0 / self.considered as f64
+        }
+    }
+
+    /// Returns true if more regressions failed than the threshold tolerates.
+    pub fn exceeded(&self) -> bool {
+        match self.threshold {
+            Threshold::Count(count) => self.failed > count,
+            Threshold::Percent(percent) => self.rate() > percent,
+        }
+    }
+
+    /// Renders the line stating the threshold and the measured rate, printed after the recap.
+    pub fn line(&self) -> String {
+        let errors = if self.counts_errors {
+            "errors included"
+        } else {
+            "errors excluded"
+        };
+        let verdict = if self.exceeded() {
+            "\x1b[0;31mexceeded\x1b[0m"
+        } else {
+            "\x1b[0;32mwithin\x1b[0m"
+        };
+        format!(
+            "Failure threshold: {} failed of {} ({:.1}%, {}), threshold {}: {}",
+            self.failed,
+            self.considered,
+            self.rate(),
+            errors,
+            self.threshold.text(),
+            verdict
+        )
+    }
+
+    /// Renders the measure as a JSON object for the summary of the run.
+    pub fn json(&self) -> String {
+        format!(
+            "{{\"threshold\": \"{}\", \"failed\": {}, \"considered\": {}, \"rate\": {:.2}, \"errors_counted\": {}, \"exceeded\": {}}}",
+            self.threshold.text(),
+            self.failed,
+            self.considered,
+            self.rate(),
+            self.counts_errors,
+            self.exceeded()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::RegressionResult;
+    use crate::timestamp::DateTime;
+    use std::time::Duration;
+
+    fn report(statuses: &[&'static str]) -> RunReport {
+        RunReport {
+            run_id: "20240102T030405Z".to_string(),
+            started: DateTime::now(),
+            duration: Duration::ZERO,
+            environment: Default::default(),
+            results: statuses
+                .iter()
+                .enumerate()
+                .map(|(i, status)| RegressionResult {
+                    name: format!("r{}", i),
+                    status,
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn fails_only_above_the_threshold() {
+        assert_eq!(Threshold::parse("3"), Ok(Threshold::Count(3)));
+        assert_eq!(Threshold::parse("12.5%"), Ok(Threshold::Percent(12.5)));
+        assert!(Threshold::parse("-1").is_err());
+        assert!(Threshold::parse("101%").is_err());
+        assert!(Threshold::parse("ten").is_err());
+
+        let report = report(&[
+            "passed", "passed", "passed", "passed", "passed", "passed", "failed", "error", "error",
+        ]);
+        let measure = Measure::new(Threshold::Percent(15.0), &report, false);
+        assert_eq!((measure.failed, measure.considered), (1, 7));
+        assert!(!measure.exceeded());
+        assert!(measure.json().contains("\"rate\": 14.29"));
+        assert!(Measure::new(Threshold::Percent(12.5), &report, false).exceeded());
+        let measure = Measure::new(Threshold::Percent(15.0), &report, true);
+        assert_eq!((measure.failed, measure.considered), (3, 9));
+        assert!(measure.exceeded());
+        assert!(measure.json().contains("\"rate\": 33.33"));
+        assert!(!Measure::new(Threshold::Count(3), &report, true).exceeded());
+        assert!(Measure::new(Threshold::Count(0), &report, false).exceeded());
+    }
+}
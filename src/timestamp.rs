@@ -0,0 +1,222 @@
+//! Minimal UTC date and time handling.
+//!
+//! The tool only needs to name runs and to print dates, so a small civil
+//! calendar conversion is used instead of a full date and time library.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A UTC date and time with one second resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl DateTime {
+    /// Returns the current date and time.
+    pub fn now() -> DateTime {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        DateTime::from_unix(secs)
+    }
+
+    /// Converts seconds since the Unix epoch to a date and time.
+    pub fn from_unix(secs: i64) -> DateTime {
+        let days = secs.div_euclid(86400);
+        let rem = secs.rem_euclid(86400);
+        // Civil from days, after Howard Hinnant's algorithm
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        DateTime {
+            year,
+            month,
+            day,
+            hour: (rem / 3600) as u32,
+            minute: (rem % 3600 / 60) as u32,
+            second: (rem % 60) as u32,
+        }
+    }
+
+    /// Converts the date and time to seconds since the Unix epoch.
+    pub fn unix_seconds(&self) -> i64 {
+        // Days from civil, after Howard Hinnant's algorithm
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let month = self.month as i64;
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let doy = (153 * mp + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146097 + doe - 719468;
+        days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+    }
+
+    /// Day of the week, from 0 for Sunday to 6 for Saturday.
+    pub fn weekday(&self) -> u32 {
+        // The epoch was a Thursday
+        (self.unix_seconds().div_euclid(86400) + 4).rem_euclid(7) as u32
+    }
+
+    /// Compact form used in file and directory names, e.g. `20240401T153000Z`.
+    pub fn compact(&self) -> String {
+        format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+
+    /// Parses the compact form, ignoring anything following it (such as the rest of a run identifier).
+    pub fn from_compact(text: &str) -> Option<DateTime> {
+        let text = text.get(..16)?;
+        let bytes = text.as_bytes();
+        if bytes[8] != b'T' || bytes[15] != b'Z' {
+            return None;
+        }
+        let field = |range: std::ops::Range<usize>| text.get(range)?.parse::<u32>().ok();
+        Some(DateTime {
+            year: text.get(0..4)?.parse().ok()?,
+            month: field(4..6)?,
+            day: field(6..8)?,
+            hour: field(9..11)?,
+            minute: field(11..13)?,
+            second: field(13..15)?,
+        })
+    }
+
+    /// Describes how long before `now` this time is, e.g. `3h ago`.
+    pub fn age(&self, now: DateTime) -> String {
+        let secs = now.unix_seconds() - self.unix_seconds();
+        match secs {
+            ..=59 => "just now".to_string(),
+            60..=3599 => format!("{}m ago", secs / 60),
+            3600..=86399 => format!("{}h ago", secs / 3600),
+            _ => format!("{}d ago", secs / 86400),
+        }
+    }
+
+    /// Time of the day, e.g. `15:30:00`.
+    pub fn time(&self) -> String {
+        format!("{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+
+    /// Date in ISO 8601 form, e.g. `2024-04-01`.
+    pub fn date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> DateTime {
+        DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        }
+    }
+
+    #[test]
+    fn converts_from_and_to_the_unix_seconds() {
+        for (secs, time) in [
+            (0, at(1970, 1, 1, 0, 0, 0)),
+            (-1, at(1969, 12, 31, 23, 59, 59)),
+            (951782400, at(2000, 2, 29, 0, 0, 0)),
+            (1709251200, at(2024, 3, 1, 0, 0, 0)),
+            (1711985400, at(2024, 4, 1, 15, 30, 0)),
+            (-2203891200, at(1900, 3, 1, 0, 0, 0)),
+            (4107456000, at(2100, 2, 28, 0, 0, 0)),
+        ] {
+            assert_eq!(DateTime::from_unix(secs), time, "{}", secs);
+            assert_eq!(time.unix_seconds(), secs, "{:?}", time);
+        }
+    }
+
+    #[test]
+    fn crosses_the_month_and_year_ends() {
+        // The leap day of 2024, then the first of March
+        let leap = at(2024, 2, 29, 23, 59, 59);
+        assert_eq!(
+            DateTime::from_unix(leap.unix_seconds() + 1),
+            at(2024, 3, 1, 0, 0, 0)
+        );
+        // 1900 and 2100 are not leap years, 2000 is
+        for (year, last) in [(1900, 28), (2000, 29), (2023, 28), (2100, 28)] {
+            let march = at(year, 3, 1, 0, 0, 0).unix_seconds();
+            assert_eq!(DateTime::from_unix(march - 1).day, last, "{}", year);
+        }
+        assert_eq!(
+            DateTime::from_unix(at(2023, 12, 31, 23, 59, 59).unix_seconds() + 1),
+            at(2024, 1, 1, 0, 0, 0)
+        );
+        assert_eq!(
+            DateTime::from_unix(at(2024, 4, 30, 12, 0, 0).unix_seconds() + 86400),
+            at(2024, 5, 1, 12, 0, 0)
+        );
+    }
+
+    #[test]
+    fn counts_the_weekdays_from_sunday() {
+        assert_eq!(at(1970, 1, 1, 0, 0, 0).weekday(), 4);
+        assert_eq!(at(1969, 12, 28, 23, 0, 0).weekday(), 0);
+        assert_eq!(at(2024, 4, 1, 15, 30, 0).weekday(), 1);
+        assert_eq!(at(2000, 2, 29, 0, 0, 0).weekday(), 2);
+        assert_eq!(at(2024, 3, 2, 0, 0, 0).weekday(), 6);
+    }
+
+    #[test]
+    fn formats_and_parses_the_compact_form() {
+        let time = at(2024, 4, 1, 15, 30, 0);
+        assert_eq!(time.compact(), "20240401T153000Z");
+        assert_eq!(time.date(), "2024-04-01");
+        assert_eq!(time.time(), "15:30:00");
+        assert_eq!(DateTime::from_compact("20240401T153000Z"), Some(time));
+        // The rest of a run identifier is ignored
+        assert_eq!(DateTime::from_compact("20240401T153000Z-alice"), Some(time));
+        for text in [
+            "",
+            "20240401T1530",
+            "20240401 153000Z",
+            "20240401T153000",
+            "2024040xT153000Z",
+        ] {
+            assert_eq!(DateTime::from_compact(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn describes_the_age_in_the_largest_unit() {
+        let now = at(2024, 4, 1, 15, 30, 0);
+        let before = |secs: i64| DateTime::from_unix(now.unix_seconds() - secs);
+        assert_eq!(before(0).age(now), "just now");
+        assert_eq!(before(59).age(now), "just now");
+        assert_eq!(before(60).age(now), "1m ago");
+        assert_eq!(before(3599).age(now), "59m ago");
+        assert_eq!(before(3600).age(now), "1h ago");
+        assert_eq!(before(86399).age(now), "23h ago");
+        assert_eq!(before(3 * 86400 + 5).age(now), "3d ago");
+        // A time in the future, as with a clock skew
+        assert_eq!(before(-120).age(now), "just now");
+    }
+}
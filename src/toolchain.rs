@@ -0,0 +1,236 @@
+//! Detection of the tools, repository versions and host a run depends on.
+
+use std::env;
+use std::ffi::CStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::results::Environment;
+
+/// Tools whose versions are recorded in the reports.
+pub const TOOLS: &[&str] = &["git", "bondmachine", "basm", "procbuilder", "bmnumbers"];
+
+/// Looks for an executable on the PATH.
+pub fn find_on_path(tool: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(tool))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Probes the version of a tool.
+///
+/// Returns the first output line of the version command, the path of the tool
+/// if it does not report a version, or `None` if the tool is not on the PATH.
+pub fn probe_version(tool: &str) -> Option<String> {
+    let path = find_on_path(tool)?;
+    // git follows the GNU convention, the BondMachine tools use Go style flags,
+    // sh has no portable version flag
+    let flag = match tool {
+        "git" => "--version",
+        "sh" => return Some(format!("found at {}", path.display())),
+        _ => "-version",
+    };
+    let version = Command::new(&path)
+        .arg(flag)
+        .stdin(Stdio::null())
+        .output()
+        .ok()
+        .and_then(|output| {
+            let text = if output.stdout.is_empty() {
+                String::from_utf8_lossy(&output.stderr).to_string()
+            } else {
+                String::from_utf8_lossy(&output.stdout).to_string()
+            };
+            let first = text.lines().next().unwrap_or("").trim().to_string();
+            if output.status.success() && !first.is_empty() {
+                Some(first)
+            } else {
+                None
+            }
+        });
+    Some(version.unwrap_or_else(|| format!("found at {}", path.display())))
+}
+
+/// Probes the versions of all the known tools, as (tool, version) pairs.
+pub fn versions() -> Vec<(String, String)> {
+    TOOLS
+        .iter()
+        .map(|tool| {
+            (
+                tool.to_string(),
+                probe_version(tool).unwrap_or_else(|| "not found".to_string()),
+            )
+        })
+        .collect()
+}
+
+/// Prints the versions of the tool and of its environment, for bug reports.
+pub fn print_version_report() {
+    println!("bmregression {}", env!("CARGO_PKG_VERSION"));
+    println!(
+        "build: {}",
+        option_env!("BMREGRESSION_GIT_DESCRIBE")
+            .unwrap_or("unknown (not built from a git checkout)")
+    );
+    println!("os: {} {}", env::consts::OS, env::consts::ARCH);
+    println!("tools:");
+    let probed = ["sh"]
+        .iter()
+        .map(|tool| (tool.to_string(), probe_version(tool)))
+        .chain(
+            TOOLS
+                .iter()
+                .map(|tool| (tool.to_string(), probe_version(tool))),
+        );
+    for (tool, version) in probed {
+        println!(
+            "  {:<12} {}",
+            tool,
+            version.unwrap_or_else(|| "not found".to_string())
+        );
+    }
+}
+
+/// Returns the HEAD commit of a git checkout, if the directory is one.
+pub fn repo_commit(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("rev-parse")
+        .arg("HEAD")
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+/// Returns the checked out commit of a repository, or a placeholder if it is not a git checkout.
+pub fn repo_commit_label(dir: &str) -> String {
+    repo_commit(Path::new(dir)).unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Returns the host name and the kernel release, as reported by uname(2).
+fn uname() -> Option<(String, String)> {
+    // SAFETY: uname only fills the zeroed structure, whose fields are NUL terminated
+    unsafe {
+        let mut name: libc::utsname = std::mem::zeroed();
+        if libc::uname(&mut name) != 0 {
+            return None;
+        }
+        let field =
+            |chars: &[libc::c_char]| CStr::from_ptr(chars.as_ptr()).to_string_lossy().to_string();
+        Some((field(&name.nodename), field(&name.release)))
+    }
+}
+
+/// Returns the name of the distribution, from the `PRETTY_NAME` of os-release(5).
+fn distribution() -> Option<String> {
+    let content = fs::read_to_string("/etc/os-release").ok()?;
+    content.lines().find_map(|line| {
+        line.strip_prefix("PRETTY_NAME=")
+            .map(|value| value.trim_matches('"').to_string())
+    })
+}
+
+/// Captures the environment of a run: host, checked out commits, tool versions
+/// and the given effective options.
+///
+/// With `redact_host` the host name is left out, for reports that get published.
+pub fn snapshot(
+    source: &str,
+    target: &str,
+    options: Vec<(String, String)>,
+    redact_host: bool,
+) -> Environment {
+    let (hostname, kernel) = uname().unzip();
+    let system = distribution().unwrap_or_else(|| env::consts::OS.to_string());
+    Environment {
+        hostname: if redact_host { None } else { hostname },
+        os_release: match kernel {
+            Some(kernel) => format!("{} (kernel {}, {})", system, kernel, env::consts::ARCH),
+            None => format!("{} ({})", system, env::consts::ARCH),
+        },
+        cpus: thread::available_parallelism().map_or(1, |n| n.get()),
+        commits: vec![
+            ("examples".to_string(), repo_commit_label(source)),
+            ("regression data".to_string(), repo_commit_label(target)),
+        ],
+        toolchain: versions(),
+        options,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn labels_the_commits_of_the_checkouts_only() {
+        let dir = TempDir::new("toolchain").unwrap();
+        let path = dir.path().to_str().unwrap();
+        assert_eq!(repo_commit(dir.path()), None);
+        assert_eq!(repo_commit_label(path), "unknown");
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q"]);
+        git(&[
+            "-c",
+            "user.name=alice",
+            "-c",
+            "user.email=alice@example.com",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "initial",
+        ]);
+        let commit = repo_commit_label(path);
+        assert_eq!(commit.len(), 40, "{}", commit);
+        assert!(commit.chars().all(|c| c.is_ascii_hexdigit()), "{}", commit);
+    }
+
+    #[test]
+    fn probes_the_tools_on_the_path() {
+        assert!(find_on_path("sh").is_some());
+        assert!(probe_version("sh").unwrap().starts_with("found at /"));
+        assert!(probe_version("git").unwrap().starts_with("git version "));
+        assert_eq!(probe_version("bmregression-no-such-tool"), None);
+        let tools: Vec<String> = versions().into_iter().map(|(tool, _)| tool).collect();
+        assert_eq!(tools, TOOLS);
+    }
+
+    #[test]
+    fn redacts_the_host_of_the_snapshots() {
+        let dir = TempDir::new("toolchain").unwrap();
+        let path = dir.path().to_str().unwrap();
+        let options = vec![("jobs".to_string(), "4".to_string())];
+        let environment = snapshot(path, path, options.clone(), true);
+        assert_eq!(environment.hostname, None);
+        assert!(environment.cpus >= 1);
+        assert_eq!(
+            environment.commits,
+            [
+                ("examples".to_string(), "unknown".to_string()),
+                ("regression data".to_string(), "unknown".to_string())
+            ]
+        );
+        assert_eq!(environment.options, options);
+        assert_eq!(environment.toolchain.len(), TOOLS.len());
+        assert!(snapshot(path, path, options, false).hostname.is_some());
+    }
+}
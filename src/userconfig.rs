@@ -0,0 +1,67 @@
+//! Settings of the user, shared by all the invocations.
+//!
+//! They are read from the YAML file named by `$BMREGRESSION_CONFIG`, or else from
+//! `$XDG_CONFIG_HOME/bmregression/config.yaml` (`~/.config/bmregression/config.yaml`
+//! when `XDG_CONFIG_HOME` is not set). A missing file means default settings.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use yaml_rust::YamlLoader;
+
+use crate::style::StatusStyle;
+
+/// Settings read from the user configuration file.
+#[derive(Debug, Clone, Default)]
+pub struct UserConfig {
+    /// Command used by the diff subcommand to show the differences
+    pub difftool: Option<String>,
+    /// How the status of the regressions is printed, unless given on the command line
+    pub status_style: Option<StatusStyle>,
+}
+
+/// Environment variable naming the user configuration file.
+pub const PATH_VARIABLE: &str = "BMREGRESSION_CONFIG";
+
+/// Returns the path of the user configuration file.
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(PATH_VARIABLE) {
+        return Some(PathBuf::from(path));
+    }
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("bmregression").join("config.yaml"))
+}
+
+/// Loads the user configuration.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or parsed.
+pub fn load() -> io::Result<UserConfig> {
+    let path = match path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(UserConfig::default()),
+    };
+    let content = fs::read_to_string(&path)?;
+    let docs = YamlLoader::load_from_str(&content)
+        .map_err(|err| io::Error::other(format!("parsing {} failed: {}", path.display(), err)))?;
+    let mut config = UserConfig::default();
+    if let Some(doc) = docs.first() {
+        config.difftool = doc["difftool"].as_str().map(|s| s.to_string());
+        if let Some(name) = doc["status_style"].as_str() {
+            config.status_style = Some(StatusStyle::parse(name).ok_or_else(|| {
+                io::Error::other(format!(
+                    "{}: unknown status_style {}, expected words, symbols or ascii",
+                    path.display(),
+                    name
+                ))
+            })?);
+        }
+    }
+    Ok(config)
+}
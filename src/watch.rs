@@ -0,0 +1,197 @@
+//! Detection of the changed files for `watch`.
+//!
+//! The watched files are polled: each poll lists the files under the watched
+//! paths with their size and modification time, and compares the listing with
+//! the previous one. Polling needs no platform-specific watcher, and an example
+//! directory holds few enough files for a listing every half second. A change
+//! is only reported once the files are stable for the debounce delay, so that
+//! an editor saving several files, or a checkout, trigger a single rerun.
+//!
+//! The `.git` directories are never listed, nor the provenance sidecars that
+//! reset writes next to the expected outputs, nor the paths matching the ignore
+//! patterns, matched against the path relative to the watched path and against
+//! the file name.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::provenance;
+
+/// Files under the watched paths, with their size and modification time.
+pub type Snapshot = BTreeMap<PathBuf, (u64, Option<SystemTime>)>;
+
+/// Returns true if a path relative to a watched path is ignored.
+fn ignored(relative: &Path, ignore: &[glob::Pattern]) -> bool {
+    let name = relative
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    name == ".git"
+        || provenance::is_sidecar(&name)
+        || ignore
+            .iter()
+            .any(|pattern| pattern.matches_path(relative) || pattern.matches(&name))
+}
+
+/// Adds the files under a directory to a snapshot, skipping the ignored ones.
+fn list(root: &Path, dir: &Path, ignore: &[glob::Pattern], snapshot: &mut Snapshot) {
+    // A directory removed meanwhile is listed as empty, its files are then reported removed
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if ignored(path.strip_prefix(root).unwrap_or(&path), ignore) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            list(root, &path, ignore, snapshot);
+        } else {
+            snapshot.insert(path, (metadata.len(), metadata.modified().ok()));
+        }
+    }
+}
+
+/// Lists the files under the watched paths, each a directory or a single file.
+pub fn snapshot(paths: &[PathBuf], ignore: &[glob::Pattern]) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    for path in paths {
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_dir() => list(path, path, ignore, &mut snapshot),
+            Ok(metadata) => {
+                snapshot.insert(path.clone(), (metadata.len(), metadata.modified().ok()));
+            }
+            Err(_) => {}
+        }
+    }
+    snapshot
+}
+
+/// Returns the files added, removed or modified from one snapshot to the next.
+pub fn changed(before: &Snapshot, after: &Snapshot) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = after
+        .iter()
+        .filter(|(path, state)| before.get(*path) != Some(state))
+        .map(|(path, _)| path.clone())
+        .collect();
+    changed.extend(
+        before
+            .keys()
+            .filter(|path| !after.contains_key(*path))
+            .cloned(),
+    );
+    changed.sort();
+    changed
+}
+
+/// Waits for the watched files to change, then to be stable for `debounce`.
+///
+/// # Returns
+///
+/// The changed files, or `None` if `stop` returned true first. It is called at every poll.
+pub fn wait(
+    paths: &[PathBuf],
+    ignore: &[glob::Pattern],
+    before: &Snapshot,
+    poll: Duration,
+    debounce: Duration,
+    stop: impl Fn() -> bool,
+) -> Option<Vec<PathBuf>> {
+    let mut current = loop {
+        if stop() {
+            return None;
+        }
+        std::thread::sleep(poll);
+        let current = snapshot(paths, ignore);
+        if current != *before {
+            break current;
+        }
+    };
+    let mut stable_since = Instant::now();
+    while stable_since.elapsed() < debounce {
+        if stop() {
+            return None;
+        }
+        std::thread::sleep(poll.min(debounce));
+        let next = snapshot(paths, ignore);
+        if next != current {
+            current = next;
+            stable_since = Instant::now();
+        }
+    }
+    // Files changed and changed back meanwhile, such as an editor backup, are no change
+    let changed = changed(before, &current);
+    if changed.is_empty() {
+        return wait(paths, ignore, &current, poll, debounce, stop);
+    }
+    Some(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn reports_the_changed_files_but_the_ignored_ones() {
+        let dir = TempDir::new("watch").unwrap();
+        let root = dir.path().join("blink");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("working_dir")).unwrap();
+        fs::write(root.join("src/blink.basm"), "mov r0, 1\n").unwrap();
+        fs::write(root.join("Makefile"), "hdl:\n").unwrap();
+        fs::write(dir.path().join("common.yaml"), "regcommand: make hdl\n").unwrap();
+        let paths = vec![root.clone(), dir.path().join("common.yaml")];
+        let ignore = vec![
+            glob::Pattern::new("working_dir").unwrap(),
+            glob::Pattern::new("*.swp").unwrap(),
+        ];
+
+        let before = snapshot(&paths, &ignore);
+        assert_eq!(before.len(), 3);
+        fs::write(root.join(".git/index"), "").unwrap();
+        fs::write(root.join("working_dir/out.sv"), "module blink;\n").unwrap();
+        fs::write(root.join("src/.blink.basm.swp"), "").unwrap();
+        assert!(changed(&before, &snapshot(&paths, &ignore)).is_empty());
+
+        fs::write(root.join("src/blink.basm"), "mov r0, 2\n").unwrap();
+        fs::remove_file(root.join("Makefile")).unwrap();
+        fs::write(
+            dir.path().join("common.yaml"),
+            "regcommand: make bitstream\n",
+        )
+        .unwrap();
+        let changed = wait(
+            &paths,
+            &ignore,
+            &before,
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+            || false,
+        )
+        .unwrap();
+        assert_eq!(
+            changed,
+            vec![
+                root.join("Makefile"),
+                root.join("src/blink.basm"),
+                dir.path().join("common.yaml"),
+            ]
+        );
+        assert!(wait(
+            &paths,
+            &ignore,
+            &before,
+            Duration::ZERO,
+            Duration::ZERO,
+            || true
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,56 @@
+//! Overrides keys of the configuration for some actions with the actions key.
+
+mod common;
+
+use common::{bmregression, regression};
+use tempdir::TempDir;
+
+#[test]
+fn applies_the_overrides_of_the_executing_action() {
+    let dir = TempDir::new("actions").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: printf 'a\\nb\\n' > out.txt\nactions:\n  run: {max_diff_lines: 2}\n",
+        "a\nc\n",
+    );
+
+    // run tolerates the differing line, diff still shows it
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;32mpassed"),
+        "{}",
+        stdout
+    );
+    let output = bmregression(dir.path(), &["diff", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;31mdifferences found\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(dir.path(), &["describe", "--no-verify", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("\n  actions:\n    run: max_diff_lines: 2\n"),
+        "{}",
+        stdout
+    );
+
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo\nactions:\n  run: {regbase: other}\n",
+        "\n",
+    );
+    let output = bmregression(dir.path(), &["validate"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("actions.run cannot override \"regbase\" in config.yaml"),
+        "{}",
+        stdout
+    );
+}
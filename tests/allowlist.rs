@@ -0,0 +1,78 @@
+//! Blocks the commands of untrusted data repositories not allowlisted with --command-allowlist,
+//! whatever their shell metacharacters and their environment.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn blocks_the_chained_commands() {
+    let dir = TempDir::new("allowlist").unwrap();
+    fs::write(dir.path().join("allowlist"), "# Any arguments\ntouch .*\n").unwrap();
+    regression(dir.path(), "plain", "regcommand: touch built\n", "ok\n");
+    regression(
+        dir.path(),
+        "chained",
+        "regcommand: 'touch built; touch ../../pwned'\n",
+        "ok\n",
+    );
+    regression(
+        dir.path(),
+        "substituted",
+        "regcommand: 'touch built $(touch ../../pwned)'\n",
+        "ok\n",
+    );
+    regression(dir.path(), "list", "regcommand: [touch, 'a;b']\n", "ok\n");
+
+    let output = bmregression(dir.path(), &["--command-allowlist", "allowlist", "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for name in ["chained", "substituted"] {
+        assert!(
+            stdout.contains(&format!(
+                "Regression {}: \x1b[0;31mblocked\x1b[0m (command not allowlisted)\n",
+                name
+            )),
+            "{}",
+            stdout
+        );
+    }
+    assert!(!dir.path().join("pwned").exists());
+    assert!(dir.path().join("examples/plain/built").exists());
+    // Not run through the shell, the argument is quoted and harmless
+    assert!(dir.path().join("examples/list/a;b").exists(), "{}", stdout);
+}
+
+#[test]
+fn ignores_the_environment_of_the_configuration() {
+    let dir = TempDir::new("allowlist").unwrap();
+    fs::write(dir.path().join("allowlist"), "make hdl\n").unwrap();
+    // A make of the data repository found first in PATH, instead of the real one
+    regression(
+        dir.path(),
+        "hdl",
+        "regcommand: make hdl\nenv:\n  PATH: .:/usr/bin:/bin\nclean_env: true\n",
+        "ok\n",
+    );
+    let make = dir.path().join("examples/hdl/make");
+    fs::write(&make, "#!/bin/sh\ntouch ../../pwned\n").unwrap();
+    let mut permissions = fs::metadata(&make).unwrap().permissions();
+    std::os::unix::fs::PermissionsExt::set_mode(&mut permissions, 0o755);
+    fs::set_permissions(&make, permissions).unwrap();
+
+    let output = bmregression(dir.path(), &["--command-allowlist", "allowlist", "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "Warning: the env, clean_env and env_passthrough of regression hdl ignored, with --command-allowlist\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(!dir.path().join("pwned").exists(), "{}", stdout);
+
+    // Trusted, the configuration sets the environment
+    bmregression(dir.path(), &["run"]);
+    assert!(dir.path().join("pwned").exists());
+}
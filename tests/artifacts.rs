@@ -0,0 +1,63 @@
+//! Collects the artifacts of the failing regressions, or of all of them with --collect-artifacts.
+
+mod common;
+
+use common::{bmregression, regression};
+use tempdir::TempDir;
+
+#[test]
+fn collects_the_artifacts_of_the_failures() {
+    let dir = TempDir::new("artifacts").unwrap();
+    for (name, expected) in [("blink", "blink\n"), ("counter", "1\n")] {
+        regression(
+            dir.path(),
+            name,
+            &format!(
+                "regcommand: mkdir -p build; echo {} > build/synth.log; echo {} > out.txt\nartifacts: [\"build/*.log\", timing.rpt]\n",
+                name, name
+            ),
+            expected,
+        );
+    }
+    let artifacts = dir.path().join("data/.bmregression/artifacts");
+
+    let output = bmregression(dir.path(), &["--run-id", "nightly-1", "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("Note: artifact timing.rpt of regression counter not found\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(" (1 artifacts saved to data/.bmregression/artifacts/nightly-1/counter)"),
+        "{}",
+        stdout
+    );
+    assert!(artifacts
+        .join("nightly-1/counter/build/synth.log")
+        .is_file());
+    assert!(!artifacts.join("nightly-1/blink").exists());
+
+    let output = bmregression(
+        dir.path(),
+        &[
+            "--run-id",
+            "nightly-2",
+            "--collect-artifacts",
+            "always",
+            "--artifacts-dir",
+            "saved",
+            "run",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    for name in ["blink", "counter"] {
+        assert!(dir
+            .path()
+            .join("saved/nightly-2")
+            .join(name)
+            .join("build/synth.log")
+            .is_file());
+    }
+}
@@ -0,0 +1,49 @@
+//! Writes the badge of a run with run --badge, or of the last results with status --badge.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn writes_the_badge_of_the_results() {
+    let dir = TempDir::new("badge").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    regression(
+        dir.path(),
+        "counter",
+        "regcommand: echo 2 > out.txt\n",
+        "1\n",
+    );
+
+    let output = bmregression(dir.path(), &["run", "--badge", "badge.svg"]);
+    assert_eq!(output.status.code(), Some(1));
+    let svg = fs::read_to_string(dir.path().join("badge.svg")).unwrap();
+    assert!(
+        svg.contains("aria-label=\"regressions: 1/2 passing\""),
+        "{}",
+        svg
+    );
+    assert!(svg.contains("fill=\"#e05d44\""), "{}", svg);
+
+    // From the history, without running anything
+    regression(
+        dir.path(),
+        "counter",
+        "regcommand: echo 2 > out.txt\n",
+        "2\n",
+    );
+    let output = bmregression(dir.path(), &["status", "--badge", "status.svg"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert_eq!(
+        fs::read_to_string(dir.path().join("status.svg")).unwrap(),
+        svg
+    );
+}
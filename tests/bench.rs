@@ -0,0 +1,70 @@
+//! Benchmarks the commands of the regressions with run --bench.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn times_the_iterations_after_a_warm_up() {
+    let dir = TempDir::new("bench").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo run >> runs.txt; echo blink > out.txt\n",
+        "blink\n",
+    );
+
+    let output = bmregression(dir.path(), &["run", "--bench", "3"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;32mpassed\x1b[0m\n  bench: 3 iterations, min "),
+        "{}",
+        stdout
+    );
+    let runs = fs::read_to_string(dir.path().join("examples/blink/runs.txt")).unwrap();
+    assert_eq!(runs.lines().count(), 4, "a warm-up and 3 timed iterations");
+
+    let summary =
+        fs::read_to_string(dir.path().join("data/.bmregression/last-summary.json")).unwrap();
+    assert!(
+        summary.contains("\"bench\": {\"blink\": {\"iterations\": 3, \"min_seconds\": "),
+        "{}",
+        summary
+    );
+
+    let history = dir.path().join("data/.bmregression/history");
+    let first = fs::read_dir(&history)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "tsv"))
+        .unwrap();
+    let line = fs::read_to_string(&first).unwrap();
+    let fields: Vec<&str> = line.trim_end().split('\t').collect();
+    assert_eq!(fields[5].split(',').count(), 3, "{}", line);
+
+    let output = bmregression(dir.path(), &["run", "--bench", "2"]);
+    assert!(output.status.success());
+    let mut runs: Vec<String> = fs::read_dir(&history)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "tsv"))
+        .map(|path| path.file_stem().unwrap().to_string_lossy().to_string())
+        .collect();
+    runs.sort();
+    let output = bmregression(dir.path(), &["compare-runs", &runs[0], &runs[1]]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains(", bench medians)"), "{}", stdout);
+
+    let output = bmregression(dir.path(), &["run", "--bench", "0"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(
+        stdout.contains("--bench needs at least 1 iteration"),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,104 @@
+//! Shows the git history of the expected output and of the configuration of a regression.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempdir::TempDir;
+
+/// Commits everything in the data directory as Ada, on the given day.
+fn commit(data: &Path, day: &str, subject: &str) {
+    for args in [&["add", "-A"][..], &["commit", "-q", "-m", subject]] {
+        let status = Command::new("git")
+            .current_dir(data)
+            .args(["-c", "user.name=Ada", "-c", "user.email=ada@example.com"])
+            .args(args)
+            .env("GIT_AUTHOR_DATE", format!("{}T12:00:00", day))
+            .env("GIT_COMMITTER_DATE", format!("{}T12:00:00", day))
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    }
+}
+
+#[test]
+fn shows_the_last_changes_of_the_baseline() {
+    let dir = TempDir::new("blame").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo on > out.txt\n",
+        "off\n",
+    );
+    let data = dir.path().join("data");
+
+    let output = bmregression(dir.path(), &["blame", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("is not a git checkout, its history is not available"),
+        "{}",
+        stdout
+    );
+
+    let status = Command::new("git")
+        .current_dir(&data)
+        .args(["init", "-q"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    commit(&data, "2024-03-01", "Add blink");
+    fs::write(data.join("blink/out.txt"), "on\n").unwrap();
+    commit(&data, "2024-04-02", "reset after v0.9.1");
+    fs::write(
+        data.join("blink/config.yaml"),
+        "regbase: blink\nsourcedata: out.txt\ntargetdata: out.txt\nregcommand: echo on >out.txt\n",
+    )
+    .unwrap();
+    commit(&data, "2024-05-03", "Tidy blink");
+
+    let output = bmregression(dir.path(), &["blame", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    let start = lines
+        .iter()
+        .position(|line| *line == "Expected output blink/out.txt:")
+        .unwrap_or_else(|| panic!("{}", stdout));
+    let shown: Vec<String> = lines[start..]
+        .iter()
+        .map(|line| {
+            // Without the hashes
+            match line.strip_prefix("  ") {
+                Some(commit) => format!("  {}", commit.split_once("  ").unwrap().1),
+                None => line.to_string(),
+            }
+        })
+        .collect();
+    assert_eq!(
+        shown,
+        [
+            "Expected output blink/out.txt:",
+            "  2024-04-02  Ada  reset after v0.9.1",
+            "  2024-03-01  Ada  Add blink",
+            "Configuration last changed:",
+            "  2024-05-03  Ada  Tidy blink",
+        ],
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(dir.path(), &["blame", "--count", "1", "--patch", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(!stdout.contains("Add blink"), "{}", stdout);
+    assert!(
+        stdout.contains("Last change of the expected output out.txt:\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("\n-off\n+on\n"), "{}", stdout);
+}
@@ -0,0 +1,102 @@
+//! Creates the missing expected outputs with reset and run --bootstrap.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use std::path::Path;
+use tempdir::TempDir;
+
+/// Creates the regressions, their expected output in a directory that does not exist.
+fn without_baselines(dir: &Path, names: &[&str]) {
+    for name in names {
+        let expected = dir.join("data").join(name).join("expected");
+        fs::create_dir_all(&expected).unwrap();
+        regression(
+            dir,
+            name,
+            &format!(
+                "regcommand: echo {} > out.txt\ntargetdata: expected/out.txt\n",
+                name
+            ),
+            "",
+        );
+        fs::remove_dir_all(expected).unwrap();
+    }
+}
+
+fn expected(dir: &Path, name: &str) -> String {
+    fs::read_to_string(dir.join("data").join(name).join("expected/out.txt")).unwrap()
+}
+
+#[test]
+fn captures_the_missing_baselines_with_bootstrap() {
+    let dir = TempDir::new("bootstrap").unwrap();
+    without_baselines(dir.path(), &["blink", "counter"]);
+
+    let output = bmregression(dir.path(), &["run", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(2), "{}", stdout);
+    assert!(
+        stdout.contains("Error executing regression blink: expected output data/blink/expected/out.txt (targetdata expected/out.txt) does not exist: capture it with bmregression reset blink or run --bootstrap"),
+        "{}",
+        stdout
+    );
+    assert!(!dir.path().join("data/blink/expected").exists());
+
+    let output = bmregression(dir.path(), &["run", "--bootstrap"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;33mcaptured\x1b[0m (new baseline written to data/blink/expected/out.txt, review it before committing)\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Recap: 2 regressions, 0 passed, 0 failed, 0 errors, 2 captured\n"),
+        "{}",
+        stdout
+    );
+    assert_eq!(expected(dir.path(), "blink"), "blink\n");
+    assert_eq!(expected(dir.path(), "counter"), "counter\n");
+
+    // Only the missing baselines are captured, the existing ones are compared
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo changed > out.txt\ntargetdata: expected/out.txt\n",
+        "blink\n",
+    );
+    let output = bmregression(dir.path(), &["run", "--bootstrap"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("Regression counter: \x1b[0;32mpassed\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+    assert_eq!(expected(dir.path(), "blink"), "blink\n");
+}
+
+#[test]
+fn creates_the_missing_baselines_with_reset() {
+    let dir = TempDir::new("bootstrap").unwrap();
+    without_baselines(dir.path(), &["blink"]);
+
+    let output = bmregression(dir.path(), &["reset", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;33mreset\x1b[0m"),
+        "{}",
+        stdout
+    );
+    assert_eq!(expected(dir.path(), "blink"), "blink\n");
+    assert!(dir
+        .path()
+        .join("data/blink/expected/out.txt.meta.yaml")
+        .exists());
+
+    let output = bmregression(dir.path(), &["run"]);
+    assert_eq!(output.status.code(), Some(0));
+}
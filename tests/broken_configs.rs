@@ -0,0 +1,49 @@
+//! Shows the regressions with a broken configuration in list and describe.
+
+mod common;
+
+use common::{bmregression, regression};
+use tempdir::TempDir;
+
+#[test]
+fn lists_and_describes_the_broken_configurations() {
+    let dir = TempDir::new("broken-configs").unwrap();
+    regression(dir.path(), "blink", "regcommand: make\n", "blink\n");
+    regression(
+        dir.path(),
+        "counter",
+        "sourcedata: [out.txt\ntargetdata: out.txt\n",
+        "counter\n",
+    );
+    let summary = "parsing config.yaml failed: while parsing a flow sequence, expected ',' or ']'";
+
+    let output = bmregression(dir.path(), &["list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.ends_with(&format!(
+            "\tblink\n\x1b[0;31m\tcounter  (invalid config: {})\x1b[0m\n\x1b[0;31m1 invalid configurations\x1b[0m, describe or validate them for the details\n",
+            summary
+        )),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("Warning"), "{}", stdout);
+
+    let output = bmregression(dir.path(), &["describe", "--no-verify"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.ends_with(&format!(
+            "Regression: \x1b[0;31mcounter (invalid config: {})\x1b[0m\n  error: {} at line 3 column 11\n",
+            summary, summary
+        )),
+        "{}",
+        stdout
+    );
+
+    // Listed whatever the requested tags, which a broken configuration cannot tell
+    let output = bmregression(dir.path(), &["--tag", "nightly", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\tcounter  (invalid config"), "{}", stdout);
+}
@@ -0,0 +1,50 @@
+//! Fetches the examples and the regression data concurrently, reporting every failure.
+
+mod common;
+
+use common::bare_command;
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn reports_the_failures_of_both_repositories() {
+    let dir = TempDir::new("clone").unwrap();
+    let output = bare_command(dir.path())
+        .args(["--examples-url", "missing-examples"])
+        .args(["--data-url", "missing-data", "list"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "Error: fetching examples repository failed: missing-examples: no such file or directory\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(
+            "Error: fetching regression data repository failed: missing-data: no such file or directory\n"
+        ),
+        "{}",
+        stdout
+    );
+
+    // Both fetched before listing
+    fs::create_dir_all(dir.path().join("mirror/data/blink")).unwrap();
+    fs::write(
+        dir.path().join("mirror/data/blink/config.yaml"),
+        "regbase: blink\nsourcedata: out.txt\ntargetdata: out.txt\nregcommand: echo\n",
+    )
+    .unwrap();
+    fs::create_dir_all(dir.path().join("mirror/examples/blink")).unwrap();
+    let output = bare_command(dir.path())
+        .args(["--examples-url", "mirror/examples"])
+        .args(["--data-url", "mirror/data", "list"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("blink"), "{}", stdout);
+}
@@ -0,0 +1,76 @@
+//! Helpers shared by the integration tests.
+//!
+//! Each test works in a temporary directory holding the data directory `data` and the
+//! examples directory `examples`.
+
+// Each test file uses some of the helpers only
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Returns the command running bmregression in `dir`, on the directories it is given.
+///
+/// The environment is free of the variables that change what a run writes: the summary
+/// files, the identifiers of the CI jobs, and the user, always `alice`.
+pub fn bare_command(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_bmregression"));
+    command
+        .current_dir(dir)
+        .env("USER", "alice")
+        .env_remove("BMREGRESSION_SUMMARY")
+        .env_remove("GITHUB_STEP_SUMMARY")
+        .env_remove("CI")
+        .env_remove("GITHUB_RUN_ID")
+        .env_remove("CI_JOB_ID");
+    command
+}
+
+/// Returns the command running bmregression in `dir` on its data and examples directories,
+/// see [`bare_command`].
+pub fn command(dir: &Path) -> Command {
+    let mut command = bare_command(dir);
+    command.args(["--data-dir", "data", "--examples-dir", "examples"]);
+    command
+}
+
+/// Runs bmregression in `dir` with the arguments, see [`command`].
+pub fn bmregression(dir: &Path, args: &[&str]) -> Output {
+    command(dir).args(args).output().unwrap()
+}
+
+/// Creates a regression in `dir` with the keys of `config`, its expected output being
+/// `expected`.
+///
+/// Unless `config` gives them, the regbase is the name of the regression and both the
+/// sourcedata and the targetdata are `out.txt`; with a `files` key, there is neither and
+/// the expected outputs are left to the caller. The example directory is created too.
+pub fn regression(dir: &Path, name: &str, config: &str, expected: &str) {
+    let given = |key: &str| {
+        config.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.strip_prefix(':')?;
+            Some(value.strip_prefix(' ').unwrap_or(value))
+        })
+    };
+    let files = given("files").is_some();
+    let mut content = String::new();
+    for (key, default) in [
+        ("regbase", name),
+        ("sourcedata", "out.txt"),
+        ("targetdata", "out.txt"),
+    ] {
+        if given(key).is_none() && (key == "regbase" || !files) {
+            content.push_str(&format!("{}: {}\n", key, default));
+        }
+    }
+    content.push_str(config);
+    let regression = dir.join("data").join(name);
+    fs::create_dir_all(&regression).unwrap();
+    fs::create_dir_all(dir.join("examples").join(given("regbase").unwrap_or(name))).unwrap();
+    fs::write(regression.join("config.yaml"), content).unwrap();
+    if !files {
+        let targetdata = given("targetdata").unwrap_or("out.txt");
+        fs::write(regression.join(targetdata), expected).unwrap();
+    }
+}
@@ -0,0 +1,36 @@
+//! Truncates the output of the commands printed in debug mode, unless --debug-full is given.
+
+mod common;
+
+use common::{bmregression, regression};
+use tempdir::TempDir;
+
+#[test]
+fn truncates_the_command_output_in_debug_mode() {
+    let dir = TempDir::new("debug-output").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: seq 1 50; echo blink > out.txt\n",
+        "blink\n",
+    );
+
+    let output = bmregression(dir.path(), &["--debug", "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("[blink]   regbase: blink\n"), "{}", stdout);
+    assert!(
+        stdout.contains("[blink]   10\n[blink]   … 30 lines truncated (use --debug-full or see "),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains(".out)\n[blink]   41\n"), "{}", stdout);
+    assert!(!stdout.contains("[blink]   25\n"), "{}", stdout);
+
+    let output = bmregression(dir.path(), &["--debug-full", "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("[blink]   25\n"), "{}", stdout);
+    assert!(!stdout.contains("truncated"), "{}", stdout);
+    assert!(stdout.contains("RegressionConfig {"), "{}", stdout);
+}
@@ -0,0 +1,116 @@
+//! Prints the effective configurations of every regression as one deterministic document.
+
+mod common;
+
+use common::bmregression;
+use std::fs;
+use std::path::Path;
+use tempdir::TempDir;
+
+/// Writes a data repository whose regressions take values from an include and a tag default.
+fn data_repository(dir: &Path, timeout: u64) {
+    let data = dir.join("data");
+    for (name, config) in [
+        (
+            "counter",
+            "include: ../_common/fpga.yaml\nregbase: counter\nsourcedata: out.txt\ntargetdata: out.txt\nregcommand: [make, hdl]\ntags: [slow, default]\n",
+        ),
+        (
+            "blink",
+            "regbase: blink\nsourcedata: out.txt\ntargetdata: {default: [a.txt, b.txt], zedboard: z.txt}\nregcommand: make\ntags: [nightly]\nmask:\n  - pattern: '\\d+'\n    replace: N\n",
+        ),
+        ("broken", "regbase: [broken\n"),
+    ] {
+        fs::create_dir_all(data.join(name)).unwrap();
+        fs::write(data.join(name).join("config.yaml"), config).unwrap();
+    }
+    fs::create_dir_all(data.join("_common")).unwrap();
+    fs::write(data.join("_common/fpga.yaml"), "retries: 2\n").unwrap();
+    fs::write(
+        data.join("tags.yaml"),
+        format!("slow:\n  timeout: {}\n", timeout),
+    )
+    .unwrap();
+}
+
+#[test]
+fn prints_the_effective_configurations_for_diffing() {
+    let base = TempDir::new("describe-base").unwrap();
+    let head = TempDir::new("describe-head").unwrap();
+    data_repository(base.path(), 3600);
+    data_repository(head.path(), 7200);
+    let args = ["describe", "--all", "--format", "yaml", "--canonical"];
+
+    let output = bmregression(base.path(), &args);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.starts_with("regressions:\n  \"blink\":\n    actions: {}\n    artifacts: []\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("\n    mask:\n      - pattern: \"\\\\d+\"\n        replace: \"N\"\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("\n    targetdata:\n      \"default\": [\"a.txt\", \"b.txt\"]\n      \"zedboard\": [\"z.txt\"]\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("\n  \"broken\":\n    error: \"parsing config.yaml failed: "),
+        "{}",
+        stdout
+    );
+    // The include and the tag default are merged, the tags sorted
+    assert!(
+        stdout.contains("\n  \"counter\":\n    actions: {}\n    artifacts: []\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("\n    regcommand: [\"make\", \"hdl\"]\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("\n    retries: 2\n"), "{}", stdout);
+    assert!(
+        stdout.contains("\n    tags: [\"default\", \"slow\"]\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("\n    timeout: 3600\n"), "{}", stdout);
+    assert!(
+        !stdout.contains(&base.path().display().to_string()),
+        "{}",
+        stdout
+    );
+
+    // The same configurations render the same document, the changed default is the only difference
+    let again = bmregression(base.path(), &args);
+    assert_eq!(String::from_utf8_lossy(&again.stdout), stdout);
+    let output = bmregression(head.path(), &args);
+    let head_stdout = String::from_utf8_lossy(&output.stdout);
+    let changed: Vec<(&str, &str)> = stdout
+        .lines()
+        .zip(head_stdout.lines())
+        .filter(|(base, head)| base != head)
+        .collect();
+    assert_eq!(
+        changed,
+        [("    timeout: 3600", "    timeout: 7200")],
+        "{}",
+        head_stdout
+    );
+
+    let output = bmregression(base.path(), &["describe", "--canonical"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("Error describing regressions: --canonical requires --format yaml"),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,48 @@
+//! Caps the diff printed for a regression, unless --full-diff, the patch files keeping it whole.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn caps_the_printed_diff_lines() {
+    let dir = TempDir::new("diff-cap").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: seq 1 2000 > out.txt\n",
+        "",
+    );
+
+    let output = bmregression(
+        dir.path(),
+        &[
+            "--max-printed-diff-lines",
+            "10",
+            "--output-dir",
+            "patches",
+            "diff",
+            "blink",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("... 1,990 more lines, rerun with --full-diff or see patches/blink.patch"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("2000"), "{}", stdout);
+    let patch = fs::read_to_string(dir.path().join("patches/blink.patch")).unwrap();
+    assert!(patch.contains("2000\n"), "{}", patch);
+
+    let output = bmregression(dir.path(), &["--full-diff", "diff", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("2000"), "{}", stdout);
+    assert!(!stdout.contains("more lines"), "{}", stdout);
+
+    // The key of the same name, the tolerated differences, is a different setting
+    let output = bmregression(dir.path(), &["--max-diff-lines", "10", "diff", "blink"]);
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,59 @@
+//! Exits from diff as diff(1) does, 1 on differences and 2 on errors, with --quiet checking only.
+
+mod common;
+
+use common::{bmregression, regression};
+use tempdir::TempDir;
+
+#[test]
+fn exits_with_the_outcome_of_the_diff() {
+    let dir = TempDir::new("diff-check").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    regression(
+        dir.path(),
+        "counter",
+        "regcommand: printf '1\\n2\\n' > out.txt\n",
+        "1\n3\n",
+    );
+    regression(dir.path(), "uart", "regcommand: exit 3\n", "uart\n");
+
+    let output = bmregression(dir.path(), &["diff", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;32mno differences\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(dir.path(), &["diff", "blink", "counter"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("Regression counter: \x1b[0;31mdifferences found\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("\t3\n"), "{}", stdout);
+
+    // Only the statuses
+    let output = bmregression(dir.path(), &["diff", "--quiet", "counter"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("Regression counter: \x1b[0;31mdifferences found\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("\t3\n"), "{}", stdout);
+
+    // The errors before the differences
+    let output = bmregression(dir.path(), &["diff", "--quiet"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(2), "{}", stdout);
+}
@@ -0,0 +1,61 @@
+//! Falls back to diff -u, then to the built-in diff, on the systems without sdiff.
+
+mod common;
+
+use common::{command, regression};
+use std::fs;
+use std::path::Path;
+use tempdir::TempDir;
+
+/// Runs the diff of the regressions with only the tools of `tools` on the path.
+fn diff_with(dir: &Path, tools: &[&str]) -> String {
+    let bin = dir.join("bin");
+    let _ = fs::remove_dir_all(&bin);
+    fs::create_dir_all(&bin).unwrap();
+    for tool in tools {
+        std::os::unix::fs::symlink(Path::new("/usr/bin").join(tool), bin.join(tool)).unwrap();
+    }
+    let output = command(dir)
+        .env("PATH", &bin)
+        .args(["diff", "blink"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    assert_eq!(output.status.code(), Some(1), "{:?}: {}", tools, stdout);
+    stdout
+}
+
+#[test]
+fn shows_the_differences_with_the_available_tool() {
+    let dir = TempDir::new("diff-fallback").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: printf 'on\\noff\\n' > out.txt\n",
+        "on\nblink\n",
+    );
+
+    let stdout = diff_with(dir.path(), &["sh", "sdiff", "diff"]);
+    assert!(stdout.contains("differences found\x1b[0m\n"), "{}", stdout);
+    assert!(stdout.contains("off"), "{}", stdout);
+    assert!(!stdout.contains("(using"), "{}", stdout);
+
+    let stdout = diff_with(dir.path(), &["sh", "diff"]);
+    assert!(
+        stdout.contains("differences found\x1b[0m (using diff -u, sdiff not found)\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("\n-off\n+blink\n"), "{}", stdout);
+
+    let stdout = diff_with(dir.path(), &["sh"]);
+    assert!(
+        stdout.contains(
+            "differences found\x1b[0m (using the built-in diff, install sdiff or diff for other formats)\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("\n-off\n+blink\n"), "{}", stdout);
+    assert!(!stdout.contains("Error"), "{}", stdout);
+}
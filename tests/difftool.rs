@@ -0,0 +1,63 @@
+//! Shows the differences with the diff tool of the command line, the regression or the user.
+
+mod common;
+
+use common::{command, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn shows_the_differences_with_the_chosen_tool() {
+    let dir = TempDir::new("difftool").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: printf 'on\\noff\\n' > out.txt\n",
+        "on\nblink\n",
+    );
+    fs::write(dir.path().join("user.yaml"), "difftool: diff -u\n").unwrap();
+    let diff = |args: &[&str]| {
+        let output = command(dir.path())
+            .env("BMREGRESSION_CONFIG", dir.path().join("user.yaml"))
+            .args(args)
+            .output()
+            .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).to_string(),
+        )
+    };
+
+    // The generated output then the expected one, the exit status of the tool is no error
+    let (code, stdout) = diff(&["diff", "--difftool", "diff --normal", "blink"]);
+    assert_eq!(code, Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("differences found\x1b[0m (using diff --normal)\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("< off\n---\n> blink\n"), "{}", stdout);
+
+    let (code, stdout) = diff(&["diff", "blink"]);
+    assert_eq!(code, Some(1), "{}", stdout);
+    assert!(stdout.contains("(using diff -u)\n"), "{}", stdout);
+    assert!(stdout.contains("\n-off\n+blink\n"), "{}", stdout);
+
+    // The regression's own before the user's
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: printf 'on\\noff\\n' > out.txt\ndifftool: diff --normal\n",
+        "on\nblink\n",
+    );
+    let (_, stdout) = diff(&["diff", "blink"]);
+    assert!(stdout.contains("(using diff --normal)\n"), "{}", stdout);
+
+    let (code, stdout) = diff(&["diff", "--difftool", "nonexistent-tool --side-by-side"]);
+    assert_eq!(code, Some(2), "{}", stdout);
+    assert!(
+        stdout.contains("diff tool nonexistent-tool not found"),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,86 @@
+//! Checks the several output files of a regression given with the files key, leaving out
+//! the lines matching their ignore patterns.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn checks_every_output_file() {
+    let dir = TempDir::new("files").unwrap();
+    regression(
+        dir.path(),
+        "hdl",
+        "regcommand: sh hdl.sh\nfiles:\n  - {sourcedata: bm.sv, targetdata: bm.sv}\n  - {sourcedata: bm.json, targetdata: bm.json, ignore: ['\"version\":']}\n",
+        "",
+    );
+    let regression = dir.path().join("data/hdl");
+    fs::write(
+        dir.path().join("examples/hdl/hdl.sh"),
+        "echo module > bm.sv\nprintf '{\\n\"version\": \"2.1\"\\n}\\n' > bm.json\n",
+    )
+    .unwrap();
+    fs::write(regression.join("bm.sv"), "module\n").unwrap();
+    fs::write(regression.join("bm.json"), "{\n\"version\": \"1.0\"\n}\n").unwrap();
+
+    // The version line differs, but it is ignored
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("Regression hdl (bm.sv): \x1b[0;32mpassed\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Regression hdl: \x1b[0;32mpassed\x1b[0m (2 files)\n"),
+        "{}",
+        stdout
+    );
+
+    // A single mismatching file fails the regression, and is named
+    fs::write(regression.join("bm.sv"), "endmodule\n").unwrap();
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Regression hdl: \x1b[0;31mfailed\x1b[0m (1 of 2 files: bm.sv)\n"),
+        "{}",
+        stdout
+    );
+    let output = bmregression(dir.path(), &["diff", "hdl"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Regression hdl (bm.json): \x1b[0;32mno differences\x1b[0m"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Regression hdl (bm.sv): \x1b[0;31mdifferences found"),
+        "{}",
+        stdout
+    );
+
+    // Reset copies all of them back
+    let output = bmregression(dir.path(), &["reset", "hdl"]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(regression.join("bm.sv")).unwrap(),
+        "module\n"
+    );
+    assert_eq!(
+        fs::read_to_string(regression.join("bm.json")).unwrap(),
+        "{\n\"version\": \"2.1\"\n}\n"
+    );
+
+    let output = bmregression(dir.path(), &["describe", "--no-verify", "hdl"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "\n  files:\n    bm.sv -> bm.sv\n    bm.json -> bm.json\n      ignore: \"version\":\n"
+        ),
+        "{}",
+        stdout
+    );
+}
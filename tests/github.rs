@@ -0,0 +1,59 @@
+//! Annotates the failures and appends the step summary when run in GitHub Actions.
+
+mod common;
+
+use common::{bmregression, command, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn reports_to_the_github_actions_job() {
+    let dir = TempDir::new("github").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    regression(
+        dir.path(),
+        "counter",
+        "regcommand: echo 2 > out.txt\n",
+        "1\n",
+    );
+
+    let output = command(dir.path())
+        .arg("run")
+        .env("GITHUB_STEP_SUMMARY", "step_summary.md")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("::error title=Regression failed::counter: -1\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        !stdout.contains("::error title=Regression failed::blink"),
+        "{}",
+        stdout
+    );
+    let summary = fs::read_to_string(dir.path().join("step_summary.md")).unwrap();
+    assert!(summary.starts_with("### bmregression run "), "{}", summary);
+    assert!(
+        summary.contains("| blink | default | passed |"),
+        "{}",
+        summary
+    );
+    assert!(
+        summary.contains("| counter | default | **failed** |"),
+        "{}",
+        summary
+    );
+
+    // Not in GitHub Actions
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("::error"), "{}", stdout);
+}
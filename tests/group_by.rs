@@ -0,0 +1,43 @@
+//! Counts the results of each tag in the recap and the summary of a run.
+
+mod common;
+
+use common::{command, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn counts_the_results_of_each_tag() {
+    let dir = TempDir::new("group-by").unwrap();
+    // blink passes, counter fails, both are hdl regressions and only counter an asm one
+    for (name, tags, expected) in [
+        ("blink", "[default, hdl]", "blink"),
+        ("counter", "[default, hdl, asm]", "0"),
+    ] {
+        let config = format!("regcommand: echo {} > out.txt\ntags: {}\n", name, tags);
+        regression(dir.path(), name, &config, &format!("{}\n", expected));
+    }
+    let summary = dir.path().join("summary.json");
+
+    let output = command(dir.path())
+        .env("BMREGRESSION_SUMMARY", &summary)
+        .args(["run", "--group-by", "tag"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "By tag:\n  asm      1 regressions, 0 passed, 1 failed, 0 errors\n  default  2 regressions, 1 passed, 1 failed, 0 errors\n  hdl      2 regressions, 1 passed, 1 failed, 0 errors\n  (a regression with several tags counts in each of them)\nRecap: 2 regressions, 1 passed, 1 failed, 0 errors\n"
+        ),
+        "{}",
+        stdout
+    );
+    let summary = fs::read_to_string(&summary).unwrap();
+    assert!(
+        summary.contains(
+            "\"group_by\": \"tag\",\n  \"groups\": {\"asm\": {\"total\": 1, \"passed\": 0, \"failed\": 1, \"errors\": 0}, \"default\": {\"total\": 2, \"passed\": 1, \"failed\": 1, \"errors\": 0}, \"hdl\": {\"total\": 2, \"passed\": 1, \"failed\": 1, \"errors\": 0}}\n}"
+        ),
+        "{}",
+        summary
+    );
+}
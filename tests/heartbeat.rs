@@ -0,0 +1,33 @@
+//! Prints heartbeat lines while a regression command runs, but not in the quiet outputs.
+
+mod common;
+
+use common::{bmregression, regression};
+use tempdir::TempDir;
+
+#[test]
+fn prints_the_heartbeats_of_the_long_commands() {
+    let dir = TempDir::new("heartbeat").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: sleep 1.5; echo blink > out.txt\n",
+        "blink\n",
+    );
+    let heartbeat = "\u{2026} still running blink (elapsed 1s)\n";
+
+    let output = bmregression(dir.path(), &["--heartbeat", "1", "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert_eq!(stdout.matches(heartbeat).count(), 1, "{}", stdout);
+
+    for args in [
+        &["--heartbeat", "0", "run"][..],
+        &["--heartbeat", "1", "diff", "--quiet"],
+    ] {
+        let output = bmregression(dir.path(), args);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{:?}: {}", args, stdout);
+        assert!(!stdout.contains("still running"), "{:?}: {}", args, stdout);
+    }
+}
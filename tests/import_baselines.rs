@@ -0,0 +1,131 @@
+//! Takes the generated outputs of an exported run as the expected outputs.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use std::process::Command;
+use tempdir::TempDir;
+
+#[test]
+fn imports_the_outputs_of_the_failed_regressions() {
+    let dir = TempDir::new("import_baselines").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo new > out.txt\n",
+        "old\n",
+    );
+    regression(
+        dir.path(),
+        "steady",
+        "regcommand: echo same > out.txt\n",
+        "same\n",
+    );
+
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;31mfailed"),
+        "{}",
+        stdout
+    );
+    let output = bmregression(dir.path(), &["export", "--out", "run.tar.gz"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    let run_id = stdout
+        .strip_prefix("Exported run ")
+        .and_then(|rest| rest.split(' ').next())
+        .unwrap()
+        .to_string();
+
+    let output = bmregression(dir.path(), &["import-baselines", "run.tar.gz"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;33mupdated\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("  - steady (passed in the run)\n"),
+        "{}",
+        stdout
+    );
+    let blink = dir.path().join("data/blink");
+    assert_eq!(fs::read_to_string(blink.join("out.txt")).unwrap(), "new\n");
+    let sidecar = fs::read_to_string(blink.join("out.txt.meta.yaml")).unwrap();
+    assert!(
+        sidecar.contains(&format!("\nrun_id: \"{}\"\n", run_id)),
+        "{}",
+        sidecar
+    );
+    assert!(sidecar.contains("\nby: \"alice\"\n"), "{}", sidecar);
+
+    // The extracted directory is accepted too, and an imported output is unchanged
+    let extracted = dir.path().join("extracted");
+    fs::create_dir_all(&extracted).unwrap();
+    let tar = Command::new("tar")
+        .arg("-xzf")
+        .arg(dir.path().join("run.tar.gz"))
+        .arg("-C")
+        .arg(&extracted)
+        .status()
+        .unwrap();
+    assert!(tar.success());
+    let output = bmregression(dir.path(), &["import-baselines", "extracted", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;32munchanged\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(dir.path(), &["import-baselines", "data"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("is not laid out like an archive of bmregression export"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn skips_the_protected_expected_outputs() {
+    let dir = TempDir::new("import_baselines").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo new > out.txt\n",
+        "old\n",
+    );
+    let outputs = dir.path().join("archive/outputs");
+    fs::create_dir_all(&outputs).unwrap();
+    fs::write(outputs.join("blink"), "new\n").unwrap();
+    fs::write(outputs.join("gone"), "new\n").unwrap();
+    let config = dir.path().join("data/blink/config.yaml");
+    let protected = fs::read_to_string(&config).unwrap() + "protected: true\n";
+    fs::write(&config, protected).unwrap();
+
+    let output = bmregression(dir.path(), &["import-baselines", "archive"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;33mskipped\x1b[0m (the targetdata is protected"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("  - gone (not a regression)"), "{}", stdout);
+    let expected = dir.path().join("data/blink/out.txt");
+    assert_eq!(fs::read_to_string(&expected).unwrap(), "old\n");
+
+    let output = bmregression(
+        dir.path(),
+        &["import-baselines", "--override-protected", "archive"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert_eq!(fs::read_to_string(&expected).unwrap(), "new\n");
+}
@@ -0,0 +1,134 @@
+//! Confirms each reset with --interactive, on a terminal only.
+
+mod common;
+
+use common::{bmregression, command, regression};
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::fd::FromRawFd;
+use std::path::Path;
+use std::process::Output;
+use tempdir::TempDir;
+
+/// Runs a reset in `dir` with the answers typed on the terminal its stdin is.
+fn reset_answering(dir: &Path, args: &[&str], answers: &str) -> Output {
+    let (mut master_fd, mut slave_fd) = (0, 0);
+    // SAFETY: openpty only writes the two descriptors, which are then owned by the files
+    let (mut master, slave) = unsafe {
+        let opened = libc::openpty(
+            &mut master_fd,
+            &mut slave_fd,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+        );
+        assert_eq!(opened, 0, "openpty failed");
+        (File::from_raw_fd(master_fd), File::from_raw_fd(slave_fd))
+    };
+    // Buffered by the terminal until the questions read them, a line each
+    master.write_all(answers.as_bytes()).unwrap();
+    let output = command(dir)
+        .arg("reset")
+        .args(args)
+        .stdin(slave)
+        .output()
+        .unwrap();
+    drop(master);
+    output
+}
+
+/// Creates the regressions, all of them with a stale expected output.
+fn stale(dir: &Path, names: &[&str]) {
+    for name in names {
+        regression(
+            dir,
+            name,
+            &format!("regcommand: echo {} > out.txt\n", name),
+            "stale\n",
+        );
+    }
+}
+
+fn expected(dir: &Path, name: &str) -> String {
+    fs::read_to_string(dir.join("data").join(name).join("out.txt")).unwrap()
+}
+
+#[test]
+fn resets_only_the_confirmed_regressions() {
+    let dir = TempDir::new("interactive-reset").unwrap();
+    stale(dir.path(), &["blink", "counter", "spi", "uart"]);
+
+    let output = reset_answering(dir.path(), &["--interactive"], "n\nmaybe\ny\na\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    // In the order of the data directory, each asked about once but after the wrong answer
+    let mut asked: Vec<&str> = stdout
+        .split("Reset ")
+        .filter_map(|question| question.split_once("? [y]es/[n]o/[a]ll/[q]uit: "))
+        .map(|(name, _)| name)
+        .collect();
+    asked.dedup();
+    assert_eq!(asked.len(), 3, "{}", stdout);
+    assert!(
+        stdout.contains(&format!(
+            "Regression {}: expected (left) vs generated (right)\n  stale",
+            asked[0]
+        )),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Please answer y, n, a or q\n"),
+        "{}",
+        stdout
+    );
+    // Reset without a question after the answer all
+    let last = ["blink", "counter", "spi", "uart"]
+        .into_iter()
+        .find(|name| !asked.contains(name))
+        .unwrap();
+    assert_eq!(expected(dir.path(), asked[0]), "stale\n");
+    for name in [asked[1], asked[2], last] {
+        assert_eq!(expected(dir.path(), name), format!("{}\n", name));
+    }
+    assert!(
+        stdout.ends_with(&format!(
+            "Reset summary:\n  reset (3): {}, {}, {}\n  skipped (1): {}\n",
+            asked[1], asked[2], last, asked[0]
+        )),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn skips_the_remaining_regressions_after_a_quit() {
+    let dir = TempDir::new("interactive-reset").unwrap();
+    stale(dir.path(), &["blink", "counter", "spi"]);
+
+    let output = reset_answering(dir.path(), &["-i"], "y\nq\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    let reset = ["blink", "counter", "spi"]
+        .into_iter()
+        .filter(|name| expected(dir.path(), name) != "stale\n")
+        .count();
+    assert_eq!(reset, 1, "{}", stdout);
+    assert!(stdout.contains("  reset (1): "), "{}", stdout);
+    assert!(stdout.contains("  skipped (2): "), "{}", stdout);
+}
+
+#[test]
+fn refuses_to_ask_without_a_terminal() {
+    let dir = TempDir::new("interactive-reset").unwrap();
+    stale(dir.path(), &["blink"]);
+    let output = bmregression(dir.path(), &["reset", "--interactive"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("The --interactive option requires stdin to be a terminal"),
+        "{}",
+        stdout
+    );
+    assert_eq!(expected(dir.path(), "blink"), "stale\n");
+}
@@ -0,0 +1,56 @@
+//! Stops waiting for the answer to an interactive question on an interrupt.
+
+mod common;
+
+use common::{command, regression};
+use std::io::Read;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tempdir::TempDir;
+
+#[test]
+fn interrupts_the_question() {
+    let dir = TempDir::new("interrupt").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    let config = dir.path().join("data/blink/config.yaml");
+
+    // The question waits on an open stdin that never answers
+    let mut child = command(dir.path())
+        .args(["remove", "blink"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let mut printed = Vec::new();
+    let mut byte = [0];
+    while !String::from_utf8_lossy(&printed).ends_with("[y/N]: ") {
+        assert_eq!(stdout.read(&mut byte).unwrap(), 1, "{:?}", printed);
+        printed.push(byte[0]);
+    }
+    unsafe {
+        libc::kill(child.id() as i32, libc::SIGINT);
+    }
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if started.elapsed() > Duration::from_secs(10) {
+            child.kill().unwrap();
+            panic!("still waiting for the answer after SIGINT");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    stdout.read_to_end(&mut printed).unwrap();
+    let printed = String::from_utf8_lossy(&printed);
+    assert!(printed.contains("Nothing removed"), "{}", printed);
+    assert!(!status.success());
+    assert!(config.exists());
+}
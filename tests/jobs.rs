@@ -0,0 +1,97 @@
+//! Runs several regressions at the same time with run --jobs, and terminates the commands
+//! of the regressions without a timeout after --timeout.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use std::path::Path;
+use tempdir::TempDir;
+
+/// Creates a regression in the example directory `regbase`, whose command writes its
+/// output after `regcommand`.
+fn job(dir: &Path, name: &str, regbase: &str, regcommand: &str) {
+    let config = format!(
+        "regbase: {}\nsourcedata: {}.txt\nregcommand: {}; echo ok > {}.txt\n",
+        regbase, name, regcommand, name
+    );
+    regression(dir, name, &config, "ok\n");
+}
+
+#[test]
+fn runs_the_regressions_concurrently_in_order() {
+    let dir = TempDir::new("jobs").unwrap();
+    // a only completes once b has started, which it cannot do alone
+    job(
+        dir.path(),
+        "a",
+        "a",
+        "while [ ! -f ../b/started ]; do sleep 0.05; done",
+    );
+    job(dir.path(), "b", "b", "touch started");
+    // c and d share their example directory, they never run together
+    let exclusive = "test ! -f busy && touch busy && sleep 0.3 && rm busy";
+    job(dir.path(), "c", "shared", exclusive);
+    job(dir.path(), "d", "shared", exclusive);
+
+    let output = bmregression(dir.path(), &["--timeout", "20", "run", "--jobs", "4"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("  jobs: 4, timeout: 4 of 4 regressions, up to 20s\n"),
+        "{}",
+        stdout
+    );
+    // In the order of the selection, as when they run one at a time
+    let statuses = |stdout: &str| -> Vec<String> {
+        stdout
+            .lines()
+            .filter(|line| line.starts_with("Regression "))
+            .map(str::to_string)
+            .collect()
+    };
+    let concurrent = statuses(&stdout);
+    let mut sorted = concurrent.clone();
+    sorted.sort();
+    assert_eq!(
+        sorted,
+        [
+            "Regression a: \x1b[0;32mpassed\x1b[0m",
+            "Regression b: \x1b[0;32mpassed\x1b[0m",
+            "Regression c: \x1b[0;32mpassed\x1b[0m",
+            "Regression d: \x1b[0;32mpassed\x1b[0m",
+        ],
+        "{}",
+        stdout
+    );
+    fs::write(dir.path().join("examples/b/started"), "").unwrap();
+    let output = bmregression(dir.path(), &["run"]);
+    assert_eq!(
+        concurrent,
+        statuses(&String::from_utf8_lossy(&output.stdout))
+    );
+
+    let output = bmregression(dir.path(), &["run", "--jobs", "0"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn terminates_the_commands_after_the_timeout() {
+    let dir = TempDir::new("jobs").unwrap();
+    job(dir.path(), "hung", "hung", "sleep 30");
+
+    let output = bmregression(dir.path(), &["--timeout", "1", "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("regression command timed out after 1s"),
+        "{}",
+        stdout
+    );
+    let output = bmregression(dir.path(), &["--timeout", "1", "describe", "hung"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("  timeout: 1s (from --timeout)\n"),
+        "{}",
+        stdout
+    );
+}
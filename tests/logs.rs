@@ -0,0 +1,51 @@
+//! Keeps the command logs of the last --keep-logs runs, and shows them with logs.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn keeps_the_logs_of_the_most_recent_runs() {
+    let dir = TempDir::new("logs").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt; echo built; echo warned >&2\n",
+        "blink\n",
+    );
+    for run_id in ["nightly-1", "nightly-2", "nightly-3"] {
+        let output = bmregression(dir.path(), &["--run-id", run_id, "--keep-logs", "2", "run"]);
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+    let logs = dir.path().join("data/.bmregression/logs");
+    let mut kept: Vec<String> = fs::read_dir(&logs)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    kept.sort();
+    assert_eq!(kept, ["nightly-2", "nightly-3"]);
+    assert_eq!(
+        fs::read_to_string(logs.join("nightly-3/blink.out")).unwrap(),
+        "built\n"
+    );
+    assert_eq!(
+        fs::read_to_string(logs.join("nightly-3/blink.err")).unwrap(),
+        "warned\n"
+    );
+
+    let output = bmregression(dir.path(), &["logs", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert!(stdout.starts_with("Run: nightly-3\n"), "{}", stdout);
+    assert!(stdout.contains("built\n"), "{}", stdout);
+    assert!(stdout.contains("warned\n"), "{}", stdout);
+    let output = bmregression(dir.path(), &["logs", "blink", "--run", "nightly-1"]);
+    assert_ne!(output.status.code(), Some(0));
+}
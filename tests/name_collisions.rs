@@ -0,0 +1,53 @@
+//! Refuses the regression names colliding on the case-insensitive file systems.
+
+mod common;
+
+use common::{bmregression, regression};
+use tempdir::TempDir;
+
+#[test]
+fn reports_the_colliding_names() {
+    let dir = TempDir::new("name-collisions").unwrap();
+    for name in ["Basys3_Blink", "basys3_blink", "counter"] {
+        regression(dir.path(), name, "regcommand: echo 1 > out.txt\n", "1\n");
+    }
+
+    let output = bmregression(dir.path(), &["run", "counter"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(2), "{}", stdout);
+    assert!(
+        stdout.contains("regression names collide: Basys3_Blink, basys3_blink"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("Regression counter"), "{}", stdout);
+
+    let output = bmregression(dir.path(), &["validate"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "Regression basys3_blink: \x1b[0;31minvalid\x1b[0m\n  - name collides case-insensitively with Basys3_Blink\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Regression counter: \x1b[0;32mvalid\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(dir.path(), &["lint"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("regression names collide: Basys3_Blink, basys3_blink"),
+        "{}",
+        stdout
+    );
+    // Only for the selected regressions
+    let output = bmregression(dir.path(), &["lint", "counter"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("collide"), "{}", stdout);
+}
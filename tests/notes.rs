@@ -0,0 +1,79 @@
+//! Keeps triage notes next to the regressions, shown by describe and on failures.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn shows_the_latest_note_of_a_failing_regression() {
+    let dir = TempDir::new("notes").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo counter > out.txt\n",
+        "blink\n",
+    );
+    let regression = dir.path().join("data/blink");
+
+    let output = bmregression(dir.path(), &["note", "blink", "fails since v0.9.1"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    let output = bmregression(
+        dir.path(),
+        &[
+            "note",
+            "blink",
+            "waiting for bondmachine#456\nfails since v0.9.2",
+        ],
+    );
+    assert!(output.status.success());
+    let notes = fs::read_to_string(regression.join("notes.md")).unwrap();
+    assert_eq!(notes.matches("\n## ").count() + 1, 2, "{}", notes);
+    assert!(notes.ends_with("\n\nwaiting for bondmachine#456\nfails since v0.9.2\n"));
+
+    let output = bmregression(dir.path(), &["note", "--list", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(" UTC\x1b[0m\n  fails since v0.9.1\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.ends_with(" UTC\x1b[0m\n  waiting for bondmachine#456\n  fails since v0.9.2\n"),
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(dir.path(), &["describe", "--no-verify", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(" UTC): waiting for bondmachine#456 …\n"),
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;31mfailed"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("  note ("), "{}", stdout);
+    assert!(
+        stdout.contains(" UTC): waiting for bondmachine#456 …\n"),
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(dir.path(), &["note", "counter", "flaky"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("Error with the notes of regression counter: no regression named counter"),
+        "{}",
+        stdout
+    );
+}
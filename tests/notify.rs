@@ -0,0 +1,107 @@
+//! Posts the summary of a run to --notify-webhook, a failed notification being a warning.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use tempdir::TempDir;
+
+/// Receives a single request on a local port, returning its URL and the thread
+/// returning the body.
+fn webhook() -> (String, thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!(
+        "http://{}/hooks/bmregression",
+        listener.local_addr().unwrap()
+    );
+    let receiver = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        String::from_utf8(body).unwrap()
+    });
+    (url, receiver)
+}
+
+#[test]
+fn posts_the_summary_of_the_failing_runs() {
+    let dir = TempDir::new("notify").unwrap();
+    regression(
+        dir.path(),
+        "counter",
+        "regcommand: echo 2 > out.txt\n",
+        "1\n",
+    );
+
+    let (url, receiver) = webhook();
+    let output = bmregression(dir.path(), &["run", "--notify-webhook", &url]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(!stdout.contains("Warning"), "{}", stdout);
+    let body = receiver.join().unwrap();
+    assert!(
+        body.starts_with("{\"text\": \"bmregression run "),
+        "{}",
+        body
+    );
+    assert!(body.contains("Failing: counter\""), "{}", body);
+    assert!(
+        body.contains("\"summary\": {\"total\": 1, \"passed\": 0, \"failed\": 1, \"errors\": 0}"),
+        "{}",
+        body
+    );
+}
+
+#[test]
+fn warns_about_the_failed_notifications() {
+    let dir = TempDir::new("notify").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    // Nothing listens there once the listener is dropped
+    let url = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/", listener.local_addr().unwrap())
+    };
+
+    // Passing, nothing is sent by default
+    let output = bmregression(dir.path(), &["run", "--notify-webhook", &url]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert!(!stdout.contains("Warning"), "{}", stdout);
+
+    let output = bmregression(
+        dir.path(),
+        &["run", "--notify-webhook", &url, "--notify-on", "always"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    assert!(
+        stdout.contains("Warning: sending the webhook notification failed: curl failed: "),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,87 @@
+//! Records the provenance of the expected outputs next to them, shown by describe,
+//! on failures and by lint.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn records_the_provenance_of_the_reset_expected_outputs() {
+    let dir = TempDir::new("provenance").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    let regression = dir.path().join("data/blink");
+
+    let output = bmregression(dir.path(), &["lint"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "  - the expected output blink/out.txt has no provenance, reset it to record it\n"
+        ),
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(dir.path(), &["reset", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    let sidecar = fs::read_to_string(regression.join("out.txt.meta.yaml")).unwrap();
+    assert!(sidecar.contains("\nby: \"alice\"\n"), "{}", sidecar);
+    assert!(sidecar.contains("\n  \"bondmachine\": "), "{}", sidecar);
+
+    let output = bmregression(dir.path(), &["describe", "--no-verify", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("  provenance: baseline generated "),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains(" by alice\n"), "{}", stdout);
+
+    // The sidecar is not an expected output, the regression still passes
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;32mpassed"),
+        "{}",
+        stdout
+    );
+
+    fs::write(regression.join("out.txt"), "counter\n").unwrap();
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;31mfailed"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("\n  baseline generated "), "{}", stdout);
+
+    let backdated = sidecar
+        .lines()
+        .map(|line| {
+            if line.starts_with("generated: ") {
+                "generated: \"2020-04-01T15:30:00Z\"".to_string()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::write(regression.join("out.txt.meta.yaml"), backdated).unwrap();
+    let output = bmregression(dir.path(), &["lint", "--max-baseline-age", "180d"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("  - the expected output blink/out.txt was generated on 2020-04-01, "),
+        "{}",
+        stdout
+    );
+}
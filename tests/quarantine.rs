@@ -0,0 +1,51 @@
+//! Keeps the failures of the regressions listed in quarantine.yaml from failing the run.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn tolerates_the_quarantined_failures() {
+    let dir = TempDir::new("quarantine").unwrap();
+    for (name, expected) in [
+        ("blink", "stale\n"),
+        ("counter", "counter\n"),
+        ("uart", "stale\n"),
+    ] {
+        regression(
+            dir.path(),
+            name,
+            &format!("regcommand: echo {} > out.txt\n", name),
+            expected,
+        );
+    }
+    fs::write(
+        dir.path().join("data/quarantine.yaml"),
+        "- blink\n- name: counter\n  reason: flaky\n- name: uart\n  expires: 2020-01-01\n",
+    )
+    .unwrap();
+
+    // The expired entry no longer applies
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    for line in [
+        "Regression blink: \x1b[0;33mquarantined failure\x1b[0m\n",
+        "Regression counter: \x1b[0;32mpassed\x1b[0m \x1b[0;33m(quarantined, consider removing it from the quarantine)\x1b[0m\n",
+        "Regression uart: \x1b[0;31mfailed\x1b[0m\n",
+        "Quarantine: 2 regressions quarantined, 2 executed, 1 quarantined failures, 1 unexpected passes, 1 expired entries\n",
+        "\x1b[0;33mWarning: the quarantine of uart expired on 2020-01-01, its failures count again\x1b[0m\n",
+        "Recap: 3 regressions, 1 passed, 1 failed, 0 errors, 1 quarantined failures\n",
+    ] {
+        assert!(stdout.contains(line), "{}\n{}", line, stdout);
+    }
+
+    // The quarantined failures alone do not fail the run
+    let output = bmregression(dir.path(), &["run", "blink", "counter"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    let output = bmregression(dir.path(), &["diff", "blink"]);
+    assert_eq!(output.status.code(), Some(0));
+}
@@ -0,0 +1,136 @@
+//! Selects the regressions with the name arguments of each subcommand, with --reg-name, and
+//! with both.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::path::Path;
+use tempdir::TempDir;
+
+const SUBCOMMANDS: [&str; 7] = [
+    "list", "describe", "run", "diff", "update", "validate", "reset",
+];
+
+/// Writes three regressions, blink, counter and uart, whose outputs match their expected
+/// ones. Only uart is tagged, nightly.
+fn fixture(dir: &Path) {
+    for name in ["blink", "counter", "uart"] {
+        let tags = if name == "uart" {
+            "tags: [nightly]\n"
+        } else {
+            ""
+        };
+        let config = format!("regcommand: echo {} > out.txt\n{}", name, tags);
+        regression(dir, name, &config, &format!("{}\n", name));
+    }
+}
+
+/// Runs a subcommand and checks that it selected blink only.
+fn assert_selects_blink(dir: &Path, args: &[&str]) {
+    let output = bmregression(dir, args);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{:?} failed:\n{}", args, stdout);
+    assert!(
+        stdout.contains("blink"),
+        "{:?} left blink out:\n{}",
+        args,
+        stdout
+    );
+    assert!(
+        !stdout.contains("counter"),
+        "{:?} selected counter:\n{}",
+        args,
+        stdout
+    );
+}
+
+#[test]
+fn selects_the_regression_by_name_or_reg_name() {
+    let dir = TempDir::new("reg-name").unwrap();
+    fixture(dir.path());
+
+    for subcommand in SUBCOMMANDS {
+        assert_selects_blink(dir.path(), &[subcommand, "blink"]);
+        assert_selects_blink(dir.path(), &["--reg-name", "blink", subcommand]);
+        assert_selects_blink(dir.path(), &["-r", "blink", subcommand, "blink"]);
+
+        let output = bmregression(dir.path(), &["--reg-name", "counter", subcommand, "blink"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(output.status.code(), Some(1), "{}:\n{}", subcommand, stdout);
+        assert!(
+            stdout.contains(
+                "Error: the command is given the name blink and --reg-name counter, give only one of them"
+            ),
+            "{}:\n{}",
+            subcommand,
+            stdout
+        );
+    }
+
+    let output = bmregression(dir.path(), &["--reg-name", "blink", "version"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(
+        stdout.contains(
+            "Warning: the command selects no regression by name, --reg-name blink is ignored"
+        ),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn selects_the_regressions_matching_any_name() {
+    let dir = TempDir::new("reg-names").unwrap();
+    fixture(dir.path());
+
+    for subcommand in ["list", "describe", "run", "diff", "reset"] {
+        let output = bmregression(dir.path(), &[subcommand, "blink", "count", "nope", "uart"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            output.status.success(),
+            "{} failed:\n{}",
+            subcommand,
+            stdout
+        );
+        assert!(
+            stdout.contains("blink") && stdout.contains("counter"),
+            "{}:\n{}",
+            subcommand,
+            stdout
+        );
+        assert!(
+            stdout.contains("Warning: no regression matches the name nope"),
+            "{}:\n{}",
+            subcommand,
+            stdout
+        );
+        assert!(
+            stdout.contains("Warning: no regression matching the name uart has the tags default"),
+            "{}:\n{}",
+            subcommand,
+            stdout
+        );
+    }
+
+    let output = bmregression(dir.path(), &["--tag", "nightly", "list", "blink", "uart"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\tuart\n"), "{}", stdout);
+    assert!(!stdout.contains("\tblink\n"), "{}", stdout);
+    assert!(
+        stdout.contains("Warning: no regression matching the name blink has the tags nightly"),
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(dir.path(), &["-r", "blink", "run", "blink", "counter"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "Error: the command is given the names blink, counter and --reg-name blink, give only one of them"
+        ),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,73 @@
+//! Selects the regressions by the example they run, with --regbase.
+
+mod common;
+
+use common::{bmregression, regression};
+use tempdir::TempDir;
+
+#[test]
+fn selects_the_regressions_running_an_example() {
+    let dir = TempDir::new("regbase").unwrap();
+    for (name, regbase, tags) in [
+        ("blink_build", "basys3_blink", "[default]"),
+        ("blink_sim", "basys3_blink", "[default, sim]"),
+        ("counter_build", "basys3_counter", "[default]"),
+        ("zed_blink", "zedboard_blink", "[default]"),
+    ] {
+        let config = format!(
+            "regbase: {}\nregcommand: echo {} > out.txt\ntags: {}\n",
+            regbase, name, tags
+        );
+        regression(dir.path(), name, &config, &format!("{}\n", name));
+    }
+
+    let output = bmregression(dir.path(), &["--regbase", "basys3_blink", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("\tblink_build (regbase basys3_blink)\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("\tblink_sim (regbase basys3_blink)\n"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("counter_build"), "{}", stdout);
+    assert!(!stdout.contains("zed_blink"), "{}", stdout);
+
+    // A part of the regbase, combined with the name and tag filters
+    let output = bmregression(dir.path(), &["--regbase", "blink", "--tag", "sim", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("\tblink_sim (regbase basys3_blink)\n"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("blink_build"), "{}", stdout);
+    assert!(!stdout.contains("zed_blink"), "{}", stdout);
+
+    let output = bmregression(dir.path(), &["--regbase", "blink", "run", "zed"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Regression zed_blink: "), "{}", stdout);
+    assert!(!stdout.contains("Regression blink_build: "), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "Selected 1 regressions: names zed (substring match), tags default, regbase blink\n"
+        ),
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(
+        dir.path(),
+        &["--regbase", "blink", "--exact", "run", "counter_build"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Warning: no regression matching the name counter_build has the tags default and a regbase matching blink"),
+        "{}",
+        stdout
+    );
+}
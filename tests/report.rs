@@ -0,0 +1,149 @@
+//! Writes the structured report of a run with --report, in JSON or JUnit XML.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn tells_the_failures_from_the_infrastructure_errors() {
+    let dir = TempDir::new("report").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    regression(
+        dir.path(),
+        "counter",
+        "regcommand: echo 2 > out.txt\n",
+        "1\n",
+    );
+    regression(dir.path(), "uart", "regcommand: exit 2\n", "uart\n");
+
+    let output = bmregression(dir.path(), &["run", "--report", "out/report.json"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // The infrastructure error wins over the failure
+    assert_eq!(output.status.code(), Some(2), "{}", stdout);
+    assert!(
+        stdout.contains("Report written to out/report.json"),
+        "{}",
+        stdout
+    );
+    let json = fs::read_to_string(dir.path().join("out/report.json")).unwrap();
+    assert!(
+        json.contains("\"outcomes\": {\"passed\": 1, \"failure\": 1, \"infrastructure\": 1, \"quarantined\": 0, \"skipped\": 0}"),
+        "{}",
+        json
+    );
+    assert!(
+        json.contains("{\"name\": \"counter\", \"tags\": [\"default\"], \"status\": \"failed\", \"outcome\": \"failure\", "),
+        "{}",
+        json
+    );
+    assert!(
+        json.contains("\"command\": \"echo 2 > out.txt\""),
+        "{}",
+        json
+    );
+    assert!(json.contains("+2\\n"), "{}", json);
+    assert!(
+        json.contains("\"status\": \"error\", \"outcome\": \"infrastructure\""),
+        "{}",
+        json
+    );
+
+    let output = bmregression(
+        dir.path(),
+        &["run", "--report", "junit.xml", "--format", "junit"],
+    );
+    assert_eq!(output.status.code(), Some(2));
+    let xml = fs::read_to_string(dir.path().join("junit.xml")).unwrap();
+    assert!(
+        xml.contains("<testsuites name=\"bmregression\" tests=\"3\" failures=\"1\" errors=\"1\" skipped=\"0\" "),
+        "{}",
+        xml
+    );
+    assert!(
+        xml.contains("<failure message=\"generated output differs: -1\" type=\"failed\">"),
+        "{}",
+        xml
+    );
+    assert!(
+        xml.contains("<error message=\"executing regression command failed (exit code 2)"),
+        "{}",
+        xml
+    );
+
+    // --format describes the --report file, it is useless alone
+    let output = bmregression(dir.path(), &["run", "--format", "junit"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn exits_with_the_code_of_the_outcomes() {
+    let dir = TempDir::new("report").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    regression(
+        dir.path(),
+        "counter",
+        "regcommand: echo 2 > out.txt\n",
+        "1\n",
+    );
+    regression(dir.path(), "uart", "regcommand: exit 2\n", "uart\n");
+    let code = |args: &[&str]| {
+        let output = bmregression(dir.path(), args);
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        output.status.code().unwrap_or_else(|| panic!("{}", stdout))
+    };
+
+    assert_eq!(code(&["run", "--exact", "blink"]), 0);
+    // A failure, tolerated or not by the threshold
+    assert_eq!(code(&["run", "blink", "counter"]), 1);
+    assert_eq!(
+        code(&["--fail-threshold", "0", "run", "blink", "counter"]),
+        4
+    );
+    assert_eq!(
+        code(&["--fail-threshold", "1", "run", "blink", "counter"]),
+        0
+    );
+    // An error, whatever the failures and the threshold, unless it counts there
+    assert_eq!(code(&["run", "--exact", "uart"]), 2);
+    assert_eq!(code(&["--fail-threshold", "5", "run"]), 2);
+    assert_eq!(
+        code(&["--fail-threshold", "5", "--threshold-counts-errors", "run"]),
+        0
+    );
+    assert_eq!(code(&["--strict", "run"]), 3);
+    assert_eq!(code(&["--strict", "run", "blink", "counter"]), 1);
+
+    // A missing baseline cannot be compared, nor a broken configuration run
+    fs::remove_file(dir.path().join("data/blink/out.txt")).unwrap();
+    assert_eq!(code(&["run", "--exact", "blink"]), 2);
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    fs::write(dir.path().join("data/uart/config.yaml"), "regbase: [uart\n").unwrap();
+    assert_eq!(code(&["run"]), 2);
+    assert_eq!(code(&["--strict", "run"]), 3);
+
+    // A run out of time is incomplete, whatever the failures of the others
+    regression(
+        dir.path(),
+        "uart",
+        "regcommand: sleep 1.5; echo uart > out.txt\npriority: 10\n",
+        "uart\n",
+    );
+    assert_eq!(code(&["--max-total-time", "1s", "run"]), 5);
+}
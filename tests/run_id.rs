@@ -0,0 +1,80 @@
+//! Threads the identifier of a run through its header, recap, logs, reports and history.
+
+mod common;
+
+use common::{bmregression, command, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn names_everything_of_a_run_after_its_id() {
+    let dir = TempDir::new("run-id").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+
+    let output = command(dir.path())
+        .env("BMREGRESSION_SUMMARY", dir.path().join("summary.json"))
+        .arg("run")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let run_id = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("  run id: "))
+        .unwrap_or_else(|| panic!("no run id printed:\n{}", stdout))
+        .to_string();
+    // The compact timestamp and six hexadecimal digits
+    assert_eq!(run_id.len(), 23, "{}", run_id);
+    assert!(run_id[17..].chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(
+        stdout.matches(&format!("  run id: {}\n", run_id)).count(),
+        2
+    );
+    let state = dir.path().join("data/.bmregression");
+    assert!(state.join("logs").join(&run_id).is_dir());
+    assert!(
+        fs::read_to_string(state.join("history").join(format!("{}.env", run_id)))
+            .unwrap()
+            .starts_with(&format!("run_id\t{}\n", run_id))
+    );
+    let summary = fs::read_to_string(dir.path().join("summary.json")).unwrap();
+    assert!(summary.contains(&format!("\"run_id\": \"{}\"", run_id)));
+
+    let output = bmregression(dir.path(), &["--run-id", "nightly-42", "run"]);
+    assert!(output.status.success());
+    assert!(state.join("history/nightly-42.tsv").exists());
+    let output = bmregression(dir.path(), &["logs", "blink", "--run", "nightly-42"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Run: nightly-42\n"));
+
+    // The runs are ordered by the time they started, not by their identifier
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let latest = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("  run id: "))
+        .unwrap();
+    let output = bmregression(dir.path(), &["logs", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("Run: {}\n", latest)), "{}", stdout);
+
+    for (run_id, error) in [
+        (
+            "nightly-42",
+            "Error: the run id nightly-42 is already in the history, choose another one",
+        ),
+        (
+            "../up",
+            "Error: the run id \"../up\" must be made of letters, digits, '-', '_' and '.', and not start with '.'",
+        ),
+    ] {
+        let output = bmregression(dir.path(), &["--run-id", run_id, "run"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(output.status.code(), Some(1), "{}", stdout);
+        assert!(stdout.contains(error), "{}", stdout);
+    }
+}
@@ -0,0 +1,15 @@
+//! Runs the self-test of the built binary, the capabilities on the bundled fixture.
+
+mod common;
+
+use common::command;
+use tempdir::TempDir;
+
+#[test]
+fn self_test_passes() {
+    let dir = TempDir::new("self-test").unwrap();
+    let output = command(dir.path()).arg("self-test").output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "self-test failed:\n{}", stdout);
+    assert!(stdout.contains("5 of 5 capabilities working"), "{}", stdout);
+}
@@ -0,0 +1,63 @@
+//! Opens a shell prepared like the command of a regression.
+
+mod common;
+
+use common::{command, regression};
+use std::fs;
+use std::io::Write;
+use std::process::Stdio;
+use tempdir::TempDir;
+
+#[test]
+fn opens_a_shell_where_the_command_runs() {
+    let dir = TempDir::new("shell").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "workdir: hdl\nregcommand: echo $BOARD > ../out.txt\nenv:\n  BOARD: basys3\n",
+        "basys3\n",
+    );
+    fs::create_dir_all(dir.path().join("examples/blink/hdl")).unwrap();
+
+    let mut shell = command(dir.path())
+        .env("SHELL", "sh")
+        .args(["shell", "blink"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    shell
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(
+            b"echo \"in $(basename \"$PWD\") for $BMREG_NAME\"\nsh -c \"$BMREG_COMMAND\"\nexit\n",
+        )
+        .unwrap();
+    let output = shell.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("  regcommand: echo $BOARD > ../out.txt\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("in hdl for blink\n"), "{}", stdout);
+    assert_eq!(
+        fs::read_to_string(dir.path().join("examples/blink/out.txt")).unwrap(),
+        "basys3\n"
+    );
+
+    let output = command(dir.path())
+        .args(["shell", "counter"])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("Error opening a shell for regression counter: "),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,100 @@
+//! Skips the entries of the data directory that cannot be regressions, without losing a
+//! regression renamed by mistake.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn warns_about_the_shared_directories_holding_a_configuration() {
+    let dir = TempDir::new("skipped-entries").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    // A regression renamed by mistake, next to a genuine shared directory
+    regression(
+        dir.path(),
+        "_counter",
+        "regcommand: echo counter > out.txt\n",
+        "counter\n",
+    );
+    fs::create_dir_all(dir.path().join("data/_common")).unwrap();
+    fs::write(dir.path().join("data/_common/fpga.yaml"), "retries: 2\n").unwrap();
+
+    let output = bmregression(dir.path(), &["list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "Warning: _counter holds a config.yaml but is not a regression (shared configuration directory)"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("\tblink\n"), "{}", stdout);
+    assert!(!stdout.contains("\t_counter"), "{}", stdout);
+    assert!(!stdout.contains("_common"), "{}", stdout);
+
+    let output = bmregression(dir.path(), &["--debug", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for name in ["_common", "_counter"] {
+        assert!(
+            stdout.contains(&format!(
+                "Skipping {}: shared configuration directory\n",
+                name
+            )),
+            "{}",
+            stdout
+        );
+    }
+}
+
+#[test]
+fn notes_the_ignored_directories() {
+    let dir = TempDir::new("skipped-entries").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    // A regression sharing its name with the recorded results
+    regression(
+        dir.path(),
+        "results",
+        "regcommand: echo results > out.txt\n",
+        "results\n",
+    );
+    fs::create_dir_all(dir.path().join("data/.github/workflows")).unwrap();
+
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "Warning: results holds a config.yaml but is not a regression (directory of the recorded results)"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("Regression results"), "{}", stdout);
+    assert!(!stdout.contains(".github"), "{}", stdout);
+
+    let output = bmregression(dir.path(), &["--debug", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Skipping .github: ignored directory\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Skipping results: directory of the recorded results\n"),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,62 @@
+//! Runs the quick smokecommand of the regressions instead of their full regcommand.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn runs_and_resets_the_smoke_commands() {
+    let dir = TempDir::new("smoke").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo bitstream > out.txt\nsmokecommand: echo check > check.txt\nsmoke_sourcedata: check.txt\nsmoke_targetdata: check.txt\n",
+        "bitstream\n",
+    );
+    regression(
+        dir.path(),
+        "counter",
+        "regcommand: echo bitstream > out.txt\n",
+        "bitstream\n",
+    );
+    let blink = dir.path().join("data/blink");
+
+    let output = bmregression(dir.path(), &["reset", "--smoke", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert_eq!(
+        fs::read_to_string(blink.join("check.txt")).unwrap(),
+        "check\n"
+    );
+    assert_eq!(
+        fs::read_to_string(blink.join("out.txt")).unwrap(),
+        "bitstream\n"
+    );
+
+    let output = bmregression(dir.path(), &["run", "--smoke"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains(", mode: smoke"), "{}", stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;32mpassed"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("(no smokecommand, it only runs without --smoke)"),
+        "{}",
+        stdout
+    );
+    // The full run is left as it was
+    assert!(!dir.path().join("examples/blink/out.txt").exists());
+
+    let output = bmregression(dir.path(), &["describe", "--no-verify", "blink"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("  smokecommand: echo check > check.txt\n  smoke_sourcedata: check.txt\n  smoke_targetdata: check.txt\n"),
+        "{}",
+        stdout
+    );
+}
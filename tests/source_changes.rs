@@ -0,0 +1,82 @@
+//! Flags the results of the regressions whose examples checkout changed while their
+//! command ran.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempdir::TempDir;
+
+/// Creates the regression blink, whose example has a source file, `main.c`.
+fn blink(dir: &Path, regcommand: &str) {
+    let config = format!("regcommand: {}; echo blink > out.txt\n", regcommand);
+    regression(dir, "blink", &config, "blink\n");
+    fs::write(dir.join("examples/blink/main.c"), "int main;\n").unwrap();
+}
+
+#[test]
+fn flags_the_sources_modified_during_the_command() {
+    let dir = TempDir::new("source_changes").unwrap();
+    blink(dir.path(), "sleep 0.1; echo edited >> main.c");
+
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;32mpassed\x1b[0m\n  \x1b[0;33munreliable\x1b[0m (source tree changed during execution): main.c modified\n"),
+        "{}",
+        stdout
+    );
+    let summary =
+        fs::read_to_string(dir.path().join("data/.bmregression/last-summary.json")).unwrap();
+    assert!(
+        summary.contains("\"unreliable\": {\"blink\": \"main.c modified\"}"),
+        "{}",
+        summary
+    );
+
+    let output = bmregression(dir.path(), &["--ignore-source-changes", "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(!stdout.contains("unreliable"), "{}", stdout);
+}
+
+#[test]
+fn flags_a_moved_head_but_not_the_untracked_outputs() {
+    let dir = TempDir::new("source_changes").unwrap();
+    blink(
+        dir.path(),
+        "git -c user.name=a -c user.email=a@b commit -q --allow-empty -m edit",
+    );
+    let examples = dir.path().join("examples");
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .current_dir(&examples)
+            .args(["-c", "user.name=a", "-c", "user.email=a@b"])
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "-q"]);
+    git(&["add", "blink/main.c"]);
+    git(&["commit", "-q", "-m", "initial"]);
+
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("(source tree changed during execution): HEAD moved from "),
+        "{}",
+        stdout
+    );
+
+    // The generated output is untracked, rewriting it is not a change
+    blink(dir.path(), "true");
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(!stdout.contains("unreliable"), "{}", stdout);
+}
@@ -0,0 +1,61 @@
+//! Prints the statuses in the style of --status-style, or of the user configuration.
+
+mod common;
+
+use common::{bmregression, command, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn prints_the_statuses_in_the_chosen_style() {
+    let dir = TempDir::new("status-style").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    regression(
+        dir.path(),
+        "counter",
+        "regcommand: echo 2 > out.txt\n",
+        "1\n",
+    );
+
+    let output = bmregression(dir.path(), &["--status-style", "ascii", "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Regression blink: [PASS]\n"), "{}", stdout);
+    assert!(
+        stdout.contains("Regression counter: [FAIL]\n"),
+        "{}",
+        stdout
+    );
+
+    fs::write(dir.path().join("user.yaml"), "status_style: symbols\n").unwrap();
+    let user = |args: &[&str]| {
+        let output = command(dir.path())
+            .env("BMREGRESSION_CONFIG", dir.path().join("user.yaml"))
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    let stdout = user(&["run"]);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;32m✔ passed\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Regression counter: \x1b[0;31m✘ failed\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+    // The command line wins
+    let stdout = user(&["--status-style", "words", "run", "blink"]);
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;32mpassed\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,34 @@
+//! Relays the output of the regression commands live with --stream, still comparing it.
+
+mod common;
+
+use common::{bmregression, regression};
+use tempdir::TempDir;
+
+#[test]
+fn relays_the_output_prefixed_with_the_regression_name() {
+    let dir = TempDir::new("stream").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo synthesis; echo warning >&2; printf 'blink\\n' > out.txt; printf done\n",
+        "blink\n",
+    );
+
+    let output = bmregression(dir.path(), &["--stream", "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("[blink] synthesis\n"), "{}", stdout);
+    // The last line is relayed even without its newline
+    assert!(stdout.contains("[blink] done\n"), "{}", stdout);
+    assert!(stderr.contains("[blink] warning\n"), "{}", stderr);
+    assert!(stdout.contains("Regression blink: "), "{}", stdout);
+
+    // Captured only by default
+    let output = bmregression(dir.path(), &["run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(!stdout.contains("[blink]"), "{}", stdout);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("warning"));
+}
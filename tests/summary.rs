@@ -0,0 +1,57 @@
+//! Writes the JSON summary of every run, to $BMREGRESSION_SUMMARY or in the data directory.
+
+mod common;
+
+use common::{bmregression, command, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn writes_the_summary_of_every_run() {
+    let dir = TempDir::new("summary").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    regression(
+        dir.path(),
+        "counter",
+        "regcommand: echo 2 > out.txt\n",
+        "1\n",
+    );
+
+    let output = bmregression(dir.path(), &["run"]);
+    assert_eq!(output.status.code(), Some(1));
+    let summary =
+        fs::read_to_string(dir.path().join("data/.bmregression/last-summary.json")).unwrap();
+    assert!(summary.contains("  \"completed\": true,\n  \"exit_code\": 1,\n  \"exit_meaning\": \"regressions failed\",\n"), "{}", summary);
+    assert!(
+        summary.contains("\"counts\": {\"total\": 2, \"passed\": 1, \"captured\": 0, \"failed\": 1, \"errors\": 0, \"quarantined_failures\": 0, \"not_run\": 0}"),
+        "{}",
+        summary
+    );
+    assert!(
+        summary.contains("\"failing\": [\"counter\"]"),
+        "{}",
+        summary
+    );
+    // No partial summary is left behind
+    let names: Vec<String> = fs::read_dir(dir.path().join("data/.bmregression"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|name| name.contains("summary"))
+        .collect();
+    assert_eq!(names, ["last-summary.json"]);
+
+    let output = command(dir.path())
+        .args(["run", "blink"])
+        .env("BMREGRESSION_SUMMARY", "ci/summary.json")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let summary = fs::read_to_string(dir.path().join("ci/summary.json")).unwrap();
+    assert!(summary.contains("\"exit_code\": 0,"), "{}", summary);
+    assert!(summary.contains("\"failing\": []"), "{}", summary);
+}
@@ -0,0 +1,56 @@
+//! Selects the regressions with a boolean expression over their tags.
+
+mod common;
+
+use common::{bmregression, regression};
+use tempdir::TempDir;
+
+#[test]
+fn selects_the_regressions_satisfying_the_expression() {
+    let dir = TempDir::new("tag-expr").unwrap();
+    for (name, tags) in [
+        ("blink", "[fpga, quick]"),
+        ("counter", "[fpga, quick, vivado]"),
+        ("uart", "[fpga]"),
+    ] {
+        let config = format!("regcommand: echo {} > out.txt\ntags: {}\n", name, tags);
+        regression(dir.path(), name, &config, &format!("{}\n", name));
+    }
+
+    let output = bmregression(
+        dir.path(),
+        &[
+            "--tag-expr",
+            "fpga and (quick or NOT fpga) and not vivado",
+            "list",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.ends_with("Regressions found:\n\tblink\n"),
+        "{}",
+        stdout
+    );
+
+    let output = bmregression(dir.path(), &["--tag-expr", "not quick", "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(
+        stdout.starts_with("Selected 1 regressions: any name, tag expression not quick\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Regression uart: "), "{}", stdout);
+
+    let output = bmregression(dir.path(), &["--tag-expr", "fpga and (quick", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert_eq!(
+        stdout,
+        "Error: invalid --tag-expr: expected ) closing the ( at position 10, found the end at position 16\n  fpga and (quick\n                 ^\n"
+    );
+
+    let output = bmregression(dir.path(), &["--tag", "fpga", "--tag-expr", "fpga", "list"]);
+    assert!(!output.status.success());
+}
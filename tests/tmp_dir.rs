@@ -0,0 +1,204 @@
+//! Creates the temporary working directory under --tmp-dir, once its filesystem has room,
+//! and keeps it for inspection after the errors.
+
+mod common;
+
+use common::{bare_command, bmregression, command, regression};
+use std::fs;
+use tempdir::TempDir;
+
+#[test]
+fn creates_the_working_directory_under_tmp_dir() {
+    let dir = TempDir::new("tmp-dir").unwrap();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::create_dir_all(dir.path().join("examples")).unwrap();
+    let scratch = dir.path().join("scratch/tmp");
+
+    let output = bmregression(
+        dir.path(),
+        &[
+            "--tmp-dir",
+            scratch.to_str().unwrap(),
+            "--keep-temp",
+            "list",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    let kept: Vec<_> = fs::read_dir(&scratch).unwrap().collect();
+    assert_eq!(kept.len(), 1, "{}", stdout);
+    let kept = kept[0].as_ref().unwrap().path();
+    assert!(kept
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("bmregression"));
+    assert!(
+        stdout.contains(&format!("Working directory kept at: {}", kept.display())),
+        "{}",
+        stdout
+    );
+    fs::remove_dir(&kept).unwrap();
+
+    // Without --keep-temp it is removed as usual
+    let output = bmregression(
+        dir.path(),
+        &["--tmp-dir", scratch.to_str().unwrap(), "list"],
+    );
+    assert!(output.status.success());
+    assert_eq!(fs::read_dir(&scratch).unwrap().count(), 0);
+
+    let output = bmregression(
+        dir.path(),
+        &[
+            "--tmp-dir",
+            scratch.to_str().unwrap(),
+            "--min-free-space",
+            &u64::MAX.to_string(),
+            "list",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains(&format!(
+            "Error: the filesystem of {} has ",
+            scratch.display()
+        )),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(
+            "of --min-free-space; choose a larger one with --tmp-dir, TMPDIR or --workdir"
+        ),
+        "{}",
+        stdout
+    );
+    assert_eq!(fs::read_dir(&scratch).unwrap().count(), 0);
+}
+
+#[test]
+fn removes_the_working_directory_on_errors() {
+    let dir = TempDir::new("tmp-dir").unwrap();
+    fs::create_dir_all(dir.path().join("data")).unwrap();
+    fs::create_dir_all(dir.path().join("examples")).unwrap();
+    let scratch = dir.path().join("scratch");
+    let tmp_dir = scratch.to_str().unwrap();
+
+    let cases: [&[&str]; 4] = [
+        &["--run-id", "bad/id", "list"],
+        &["--examples", "vendor", "list"],
+        &["--examples", "vendor=a", "--examples", "vendor=b", "list"],
+        &["--command-allowlist", "/nonexistent", "list"],
+    ];
+    for args in cases {
+        let output = bmregression(dir.path(), &[&["--tmp-dir", tmp_dir][..], args].concat());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(output.status.code(), Some(1), "{:?}: {}", args, stdout);
+        assert!(stdout.contains("Error"), "{:?}: {}", args, stdout);
+        assert_eq!(fs::read_dir(&scratch).unwrap().count(), 0, "{:?}", args);
+    }
+
+    fs::write(dir.path().join("data/quarantine.yaml"), "[").unwrap();
+    let output = bmregression(dir.path(), &["--tmp-dir", tmp_dir, "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Error reading the quarantine"),
+        "{}",
+        stdout
+    );
+    assert_eq!(fs::read_dir(&scratch).unwrap().count(), 0);
+    fs::remove_file(dir.path().join("data/quarantine.yaml")).unwrap();
+
+    fs::write(dir.path().join("user.yaml"), "status_style: [").unwrap();
+    let output = command(dir.path())
+        .env("BMREGRESSION_CONFIG", dir.path().join("user.yaml"))
+        .args(["--tmp-dir", tmp_dir, "list"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Error reading the user configuration"),
+        "{}",
+        stdout
+    );
+    assert_eq!(fs::read_dir(&scratch).unwrap().count(), 0);
+
+    // Nor the partial clones of a failed fetch
+    let output = bare_command(dir.path())
+        .args(["--tmp-dir", tmp_dir, "--examples-dir", "examples"])
+        .args(["--data-url", "missing", "list"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(fs::read_dir(&scratch).unwrap().count(), 0);
+}
+
+#[test]
+fn keeps_the_working_directory_of_the_errored_runs() {
+    let dir = TempDir::new("tmp-dir").unwrap();
+    regression(
+        dir.path(),
+        "blink",
+        "regcommand: echo blink > out.txt\n",
+        "blink\n",
+    );
+    let scratch = dir.path().join("scratch");
+    let tmp_dir = scratch.to_str().unwrap();
+
+    // Only the failures, nothing to inspect
+    regression(
+        dir.path(),
+        "counter",
+        "regcommand: echo 2 > out.txt\n",
+        "1\n",
+    );
+    let output = bmregression(dir.path(), &["--tmp-dir", tmp_dir, "run"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(fs::read_dir(&scratch).unwrap().count(), 0);
+
+    regression(dir.path(), "counter", "regcommand: exit 3\n", "1\n");
+    let output = bmregression(dir.path(), &["--tmp-dir", tmp_dir, "run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(2), "{}", stdout);
+    let kept: Vec<_> = fs::read_dir(&scratch).unwrap().collect();
+    assert_eq!(kept.len(), 1, "{}", stdout);
+    let kept = kept[0].as_ref().unwrap().path();
+    assert!(
+        stdout.ends_with(&format!(
+            "\x1b[0;33mWorking directory kept at: {}\x1b[0m\n",
+            kept.display()
+        )),
+        "{}",
+        stdout
+    );
+
+    // A fixed directory is never removed, its path is printed on the errors only
+    let workdir = dir.path().join("work");
+    let output = bmregression(
+        dir.path(),
+        &["--workdir", workdir.to_str().unwrap(), "run", "counter"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(2), "{}", stdout);
+    assert!(
+        stdout.starts_with(&format!("Working directory: {}\n", workdir.display())),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(&format!("Working directory kept at: {}", workdir.display())),
+        "{}",
+        stdout
+    );
+    let output = bmregression(
+        dir.path(),
+        &["--workdir", workdir.to_str().unwrap(), "run", "blink"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(!stdout.contains("kept at"), "{}", stdout);
+    assert!(workdir.is_dir());
+}
@@ -0,0 +1,94 @@
+//! Rebaselines only the regressions whose output changed, running each command once.
+
+mod common;
+
+use common::{bmregression, regression};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempdir::TempDir;
+
+fn expected(dir: &Path, name: &str) -> String {
+    fs::read_to_string(dir.join("data").join(name).join("out.txt")).unwrap()
+}
+
+#[test]
+fn updates_only_the_changed_baselines() {
+    let dir = TempDir::new("update").unwrap();
+    let runs = dir.path().join("runs.log");
+    for (name, output, baseline) in [
+        ("blink", "blink", "blink\n"),
+        ("counter", "counter", "stale\n"),
+    ] {
+        regression(
+            dir.path(),
+            name,
+            &format!(
+                "regcommand: echo {} >> {}; echo {} > out.txt\n",
+                name,
+                runs.display(),
+                output
+            ),
+            baseline,
+        );
+    }
+    regression(
+        dir.path(),
+        "uart",
+        "regcommand: echo uart > out.txt; exit 2\n",
+        "stale\n",
+    );
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .current_dir(dir.path().join("data"))
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}", args);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    git(&["init", "-q"]);
+    git(&["config", "user.name", "alice"]);
+    git(&["config", "user.email", "alice@example.com"]);
+    git(&["add", "-A"]);
+    git(&["commit", "-q", "-m", "initial"]);
+
+    let output = bmregression(dir.path(), &["update", "--commit"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(
+        stdout.contains("Regression counter: \x1b[0;33mupdated\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Regression blink: \x1b[0;32munchanged\x1b[0m\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains(
+            "Errors (1):\x1b[0m\n  uart: executing regression command failed (exit code 2)"
+        ),
+        "{}",
+        stdout
+    );
+    assert_eq!(expected(dir.path(), "blink"), "blink\n");
+    assert_eq!(expected(dir.path(), "counter"), "counter\n");
+    // Not touched, the command failed
+    assert_eq!(expected(dir.path(), "uart"), "stale\n");
+    // From the single execution of each command
+    let mut executed: Vec<String> = fs::read_to_string(&runs)
+        .unwrap()
+        .lines()
+        .map(|line| line.to_string())
+        .collect();
+    executed.sort();
+    assert_eq!(executed, ["blink", "counter"]);
+
+    // The updated baseline only, with its provenance
+    assert_eq!(
+        git(&["show", "--name-only", "--format=%s", "HEAD"]),
+        "Update regression baselines: counter\n\ncounter/out.txt\ncounter/out.txt.meta.yaml\n"
+    );
+}
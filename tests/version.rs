@@ -0,0 +1,71 @@
+//! Prints the version, with --verbose the build, the OS and the versions of the tools.
+
+mod common;
+
+use common::bare_command;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use tempdir::TempDir;
+
+#[test]
+fn reports_the_versions_of_the_tools_on_the_path() {
+    let dir = TempDir::new("version").unwrap();
+    let bin = dir.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    for (tool, script) in [
+        ("basm", "echo 'basm v0.9 (linux/amd64)'; echo more"),
+        // No version reported
+        ("procbuilder", "exit 1"),
+    ] {
+        let path = bin.join(tool);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let output = bare_command(dir.path()).arg("version").output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("bmregression {}\n", env!("CARGO_PKG_VERSION"))
+    );
+
+    let path = format!("{}:/usr/bin:/bin", bin.display());
+    let output = bare_command(dir.path())
+        .args(["version", "--verbose"])
+        .env("PATH", &path)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines[0],
+        format!("bmregression {}", env!("CARGO_PKG_VERSION"))
+    );
+    assert!(lines[1].starts_with("build: "), "{}", stdout);
+    assert_eq!(
+        lines[2],
+        format!("os: {} {}", std::env::consts::OS, std::env::consts::ARCH)
+    );
+    assert_eq!(lines[3], "tools:");
+    assert!(
+        lines[4].starts_with("  sh           found at /"),
+        "{}",
+        stdout
+    );
+    assert!(
+        lines[5].starts_with("  git          git version "),
+        "{}",
+        stdout
+    );
+    assert_eq!(lines[6], "  bondmachine  not found");
+    assert_eq!(lines[7], "  basm         basm v0.9 (linux/amd64)");
+    assert_eq!(
+        lines[8],
+        format!(
+            "  procbuilder  found at {}",
+            bin.join("procbuilder").display()
+        )
+    );
+    assert_eq!(lines[9], "  bmnumbers    not found");
+    assert_eq!(lines.len(), 10, "{}", stdout);
+}